@@ -0,0 +1,295 @@
+//! The `build` command: compile a file to WebAssembly.
+//!
+//! Builds are reproducible: the same source and options always produce the
+//! same bytes, and the output embeds the compiler version, the options, and a
+//! content hash (see [`BuildMetadata`]).
+
+use crate::{
+    crash::{self, Source, Stage},
+    frontends,
+};
+use anyhow::Result;
+use cadenza_eval::{
+    CompileOptions, Compiler, Env,
+    ir::{BuildMetadata, SizeReport, binary_to_wat, generate_wasm},
+};
+use clap::ValueEnum;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// What `build` writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    /// The WASM binary
+    #[default]
+    Wasm,
+    /// The WASM text format
+    Wat,
+    /// The build metadata (compiler version, content hash, and options) as JSON
+    Metadata,
+    /// A table of the bytes of the WASM binary each function, constant and
+    /// section takes
+    SizeReport,
+    /// The size report as JSON
+    SizeReportJson,
+}
+
+/// A successful compilation.
+pub struct Compiled {
+    /// The WASM binary.
+    pub wasm: Vec<u8>,
+    /// The warnings reported while compiling.
+    pub warnings: Vec<String>,
+}
+
+/// Compile the file at `path` with `options` and return the WASM binary,
+/// printing any warnings to stderr.
+///
+/// The file is read in the syntax its extension names (see [`frontends`]).
+pub fn compile(path: &Path, options: CompileOptions) -> Result<Vec<u8>> {
+    let source = Source::new(path.display().to_string(), std::fs::read_to_string(path)?);
+    let compiled = compile_guarded(source.with_frontend(frontends::for_path(path)), options)?;
+    for warning in &compiled.warnings {
+        eprintln!("warning: {warning}");
+    }
+    Ok(compiled.wasm)
+}
+
+/// Compile `source`, called `name` in errors, with `options`.
+///
+/// A compiler panic is caught and reported with a minimized reproduction (see
+/// [`crash`]).
+pub fn compile_str(name: &str, source: &str, options: CompileOptions) -> Result<Compiled> {
+    compile_guarded(Source::new(name, source), options)
+}
+
+/// Compile `source` with `options`, reporting a compiler panic.
+fn compile_guarded(source: Source, options: CompileOptions) -> Result<Compiled> {
+    crash::guard(vec![source], |sources| {
+        compile_source(&sources[0], options.clone())
+    })
+    .unwrap_or_else(|crash| Err(crash::report(&crash)))
+}
+
+/// Compile `source` with `options`.
+fn compile_source(source: &Source, options: CompileOptions) -> Result<Compiled> {
+    let name = source.name.as_str();
+    let _span = tracing::info_span!("module", file = %name).entered();
+    crash::enter(Stage::Parse);
+    let parsed = tracing::debug_span!("parse").in_scope(|| source.frontend.parse(&source.text));
+
+    if !parsed.errors.is_empty() {
+        let errors: Vec<String> = parsed
+            .errors
+            .into_iter()
+            .map(|e| format!("  {:?}", source.frontend.map_error(e)))
+            .collect();
+        return Err(anyhow::anyhow!(
+            "Failed to parse {name}:\n{}",
+            errors.join("\n")
+        ));
+    }
+
+    crash::enter(Stage::Eval);
+    let mut env = Env::with_options(&options);
+    let mut compiler = Compiler::with_options(options);
+    compiler.enable_ir();
+    if let Some(generator) = compiler.ir_generator_mut() {
+        // Only the file name goes in the source map, so the output doesn't
+        // depend on where the file was built
+        let file = Path::new(name)
+            .file_name()
+            .map_or(name.into(), |file| file.to_string_lossy().as_ref().into());
+        generator.set_file(file);
+    }
+    tracing::debug_span!("eval").in_scope(|| {
+        cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+    });
+
+    if compiler.has_errors() {
+        return Err(failure(name, &compiler));
+    }
+
+    crash::enter(Stage::Lowering);
+    let module = compiler
+        .build_ir_module()
+        .ok_or_else(|| anyhow::anyhow!("IR generation is disabled"))?;
+    // Lowering warns too, such as about unbounded recursion, and denied
+    // warnings are errors
+    if compiler.has_errors() {
+        return Err(failure(name, &compiler));
+    }
+    let warnings = compiler
+        .diagnostics()
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    crash::enter(Stage::Codegen);
+    let wasm = generate_wasm(&module, compiler.options()).map_err(|e| anyhow::anyhow!(e))?;
+    Ok(Compiled { wasm, warnings })
+}
+
+/// The error for a compilation of `name` that reported errors.
+fn failure(name: &str, compiler: &Compiler) -> anyhow::Error {
+    let errors: Vec<String> = compiler
+        .diagnostics()
+        .iter()
+        .map(|d| format!("  {d}"))
+        .collect();
+    anyhow::anyhow!("Failed to compile {name}:\n{}", errors.join("\n"))
+}
+
+/// Build the file at `path` and write the requested output.
+///
+/// The binary and text formats go to `output_path`, defaulting to `path` with
+/// a `.wasm` or `.wat` extension. Metadata and size reports go to
+/// `output_path` if given, or to `stdout` otherwise.
+pub fn run_build<W: Write>(
+    path: &Path,
+    output_path: Option<PathBuf>,
+    emit: Emit,
+    options: CompileOptions,
+    mut stdout: W,
+) -> Result<()> {
+    let binary = compile(path, options)?;
+
+    let (bytes, extension) = match emit {
+        Emit::Wasm => (binary, "wasm"),
+        Emit::Wat => (
+            binary_to_wat(&binary)
+                .map_err(|e| anyhow::anyhow!(e))?
+                .into_bytes(),
+            "wat",
+        ),
+        Emit::Metadata => {
+            let metadata = BuildMetadata::read(&binary)
+                .map_err(|e| anyhow::anyhow!(e))?
+                .ok_or_else(|| anyhow::anyhow!("build produced no metadata"))?;
+            return write_report(metadata.to_json(), output_path, stdout);
+        }
+        Emit::SizeReport | Emit::SizeReportJson => {
+            let report = SizeReport::read(&binary).map_err(|e| anyhow::anyhow!(e))?;
+            let text = if emit == Emit::SizeReport {
+                report.to_string()
+            } else {
+                report.to_json()
+            };
+            return write_report(text, output_path, stdout);
+        }
+    };
+
+    let output_path = output_path.unwrap_or_else(|| path.with_extension(extension));
+    std::fs::write(&output_path, bytes)?;
+    writeln!(stdout, "Wrote {}", output_path.display())?;
+    Ok(())
+}
+
+/// Writes `text` to `output_path` if given, or to `stdout` otherwise.
+fn write_report<W: Write>(text: String, output_path: Option<PathBuf>, mut stdout: W) -> Result<()> {
+    match output_path {
+        Some(output_path) => std::fs::write(output_path, text)?,
+        None => writeln!(stdout, "{text}")?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_file(name: &str, source: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("cadenza-build-{}-{name}", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn builds_are_reproducible() {
+        let path = source_file("repro.cdz", "fn square x = x * x\nfn add a b = a + b\n");
+
+        let first = compile(&path, CompileOptions::default()).unwrap();
+        let second = compile(&path, CompileOptions::default()).unwrap();
+        assert_eq!(first, second);
+
+        let metadata = BuildMetadata::read(&first).unwrap().unwrap();
+        let optimized = CompileOptions {
+            opt_level: cadenza_eval::options::OptLevel::O2,
+            ..CompileOptions::default()
+        };
+        let other = BuildMetadata::read(&compile(&path, optimized).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_ne!(metadata.hash, other.hash);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn emits_metadata() {
+        let path = source_file("meta.cdz", "fn double x = x * 2\n");
+        let mut stdout = Vec::new();
+        run_build(
+            &path,
+            None,
+            Emit::Metadata,
+            CompileOptions::default(),
+            &mut stdout,
+        )
+        .unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&stdout).unwrap();
+        assert_eq!(json["compiler"], cadenza_eval::ir::COMPILER_VERSION);
+        assert_eq!(json["hash"].as_str().unwrap().len(), 64);
+        assert_eq!(json["options"]["opt_level"], "0");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn emits_size_reports() {
+        let path = source_file("size.cdz", "fn double x = x * 2\n");
+        let mut table = Vec::new();
+        run_build(
+            &path,
+            None,
+            Emit::SizeReport,
+            CompileOptions::default(),
+            &mut table,
+        )
+        .unwrap();
+        let table = String::from_utf8(table).unwrap();
+        assert!(table.contains("function  double\n"), "{table}");
+
+        let mut json = Vec::new();
+        run_build(
+            &path,
+            None,
+            Emit::SizeReportJson,
+            CompileOptions::default(),
+            &mut json,
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let total = compile(&path, CompileOptions::default()).unwrap().len();
+        assert_eq!(json["total"], total);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn warns_about_unbounded_recursion() {
+        let source = "fn fact n = n * (fact (n - 1))\nfn countdown n = countdown (n - 1)\n";
+        let compiled = compile_str("fact.cdz", source, CompileOptions::default()).unwrap();
+        assert_eq!(compiled.warnings.len(), 1, "{:?}", compiled.warnings);
+        assert!(compiled.warnings[0].contains("unbounded recursion: fact -> fact"));
+
+        let deny = CompileOptions {
+            warnings: cadenza_eval::options::WarningLevel::Deny,
+            ..CompileOptions::default()
+        };
+        assert!(compile_str("fact.cdz", source, deny).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! The `check` command: evaluate files and report diagnostics.
+//!
+//! The files form one [`ModuleGraph`], each named after its file stem, so they
+//! can `import` each other. Besides evaluation errors, `check` warns about
+//! private functions that are never called (see [`cadenza_eval::dead_code`]).
+
+use crate::{
+    crash::{self, Source},
+    frontends,
+};
+use anyhow::Result;
+use cadenza_eval::{
+    CompileOptions, Compiler, Env, dead_code::unused_functions, module_graph::ModuleGraph,
+};
+use std::{io::Write, path::PathBuf};
+
+/// Check the files at `paths` with `options`, writing diagnostics to `stdout`.
+///
+/// Fails if any diagnostic is an error. A compiler panic is caught and
+/// reported with a minimized reproduction (see [`crash`]).
+pub fn run_check<W: Write>(
+    paths: &[PathBuf],
+    options: CompileOptions,
+    mut stdout: W,
+) -> Result<()> {
+    let mut sources = Vec::with_capacity(paths.len());
+    for path in paths {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid module path: {}", path.display()))?;
+        let source = Source::new(name, std::fs::read_to_string(path)?);
+        sources.push(source.with_frontend(frontends::for_path(path)));
+    }
+
+    let compiler = crash::guard(sources, |sources| check(sources, options.clone()))
+        .map_err(|crash| crash::report(&crash))?;
+
+    for diagnostic in compiler.diagnostics() {
+        writeln!(stdout, "{diagnostic}")?;
+    }
+
+    let errors = compiler
+        .diagnostics()
+        .iter()
+        .filter(|diagnostic| diagnostic.is_error())
+        .count();
+    if errors > 0 {
+        return Err(anyhow::anyhow!("check failed with {errors} error(s)"));
+    }
+    Ok(())
+}
+
+/// Evaluates `sources` as one module graph and returns the compiler holding
+/// the diagnostics.
+fn check(sources: &[Source], options: CompileOptions) -> Compiler {
+    let mut graph = ModuleGraph::new();
+    for source in sources {
+        graph.add_with(
+            source.name.as_str(),
+            source.text.as_str(),
+            source.frontend.clone(),
+        );
+    }
+
+    let mut env = Env::with_options(&options);
+    let mut compiler = Compiler::with_options(options);
+    graph.eval(&mut env, &mut compiler);
+    for warning in unused_functions(&graph) {
+        compiler.record_diagnostic(warning);
+    }
+    compiler
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadenza_eval::options::WarningLevel;
+
+    fn source_file(name: &str, source: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cadenza-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn warns_on_unused_functions() {
+        let lib = source_file("lib.cdz", "fn _unused x = x\nfn _square x = x * x\n");
+        let main = source_file("main.cdz", "import lib\nfn area w = _square w\n");
+
+        let mut stdout = Vec::new();
+        run_check(
+            &[lib.clone(), main.clone()],
+            CompileOptions::default(),
+            &mut stdout,
+        )
+        .unwrap();
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.contains("unused function: _unused"), "{output}");
+        assert!(!output.contains("_square"), "{output}");
+
+        let denied = CompileOptions {
+            warnings: WarningLevel::Deny,
+            ..CompileOptions::default()
+        };
+        assert!(run_check(&[lib.clone(), main.clone()], denied, Vec::new()).is_err());
+
+        std::fs::remove_file(lib).unwrap();
+        std::fs::remove_file(main).unwrap();
+    }
+
+    #[test]
+    fn reports_each_independent_error_once() {
+        let path = source_file("poison.cdz", "let x = missing\nx + 1\nx * 2\n1 / 0\n");
+
+        let mut stdout = Vec::new();
+        let error = run_check(
+            std::slice::from_ref(&path),
+            CompileOptions::default(),
+            &mut stdout,
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "check failed with 2 error(s)");
+        let output = String::from_utf8(stdout).unwrap();
+        assert_eq!(output.matches("missing").count(), 1, "{output}");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reads_files_in_the_syntax_of_their_extension() {
+        // As Cadenza, this is a comment
+        let path = source_file("notes.md", "# Notes\n");
+
+        let mut stdout = Vec::new();
+        run_check(
+            std::slice::from_ref(&path),
+            CompileOptions::default(),
+            &mut stdout,
+        )
+        .unwrap_err();
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.contains("unknown synthetic node: h1"), "{output}");
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+//! The `completions` and `man` commands: shell completions and man pages.
+//!
+//! Both are generated from the [`Cli`](crate::Cli) definition, so new
+//! subcommands and flags show up in them without any changes here.
+
+use anyhow::Result;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::{io::Write, path::Path};
+
+/// Write the completion script for `shell` to `output`.
+pub fn run_completions<W: Write>(shell: Shell, mut output: W) -> Result<()> {
+    let mut command = crate::Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut output);
+    Ok(())
+}
+
+/// Write the `cadenza(1)` man page to `output`, or, given `out_dir`, write it
+/// there along with a page per subcommand (`cadenza-build.1`, ...).
+pub fn run_man<W: Write>(out_dir: Option<&Path>, mut output: W) -> Result<()> {
+    let command = crate::Cli::command();
+    match out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(command, dir)?;
+        }
+        None => clap_mangen::Man::new(command).render(&mut output)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The names of the subcommands, other than clap's `help`.
+    fn subcommands() -> Vec<String> {
+        crate::Cli::command()
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_string())
+            .filter(|name| name != "help")
+            .collect()
+    }
+
+    #[test]
+    fn completions_cover_every_subcommand() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let mut output = Vec::new();
+            run_completions(shell, &mut output).unwrap();
+            let script = String::from_utf8(output).unwrap();
+            for name in subcommands() {
+                assert!(script.contains(&name), "{shell} completions lack `{name}`");
+            }
+        }
+    }
+
+    #[test]
+    fn man_pages_cover_every_subcommand() {
+        let mut output = Vec::new();
+        run_man(None, &mut output).unwrap();
+        let page = String::from_utf8(output).unwrap();
+        assert!(page.starts_with(".ie \\n(.g .ds Aq"), "not roff: {page}");
+        for name in subcommands() {
+            assert!(page.contains(&name), "man page lacks `{name}`");
+        }
+
+        let dir = std::env::temp_dir().join(format!("cadenza-man-{}", std::process::id()));
+        run_man(Some(&dir), std::io::sink()).unwrap();
+        assert!(dir.join("cadenza.1").exists());
+        for name in subcommands() {
+            let page = dir.join(format!("cadenza-{name}.1"));
+            assert!(page.exists(), "missing {}", page.display());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
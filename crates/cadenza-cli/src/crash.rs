@@ -0,0 +1,384 @@
+//! Crash reporting.
+//!
+//! A panic in the compiler is a bug, and a raw backtrace is a poor way to
+//! report one. Commands that compile source run the pipeline under [`guard`],
+//! which catches the panic and turns it into a [`Crash`]:
+//!
+//! 1. **Stage**: the pipeline calls [`enter`] as it moves from evaluation to
+//!    lowering to codegen, so the crash records where it happened.
+//! 2. **Minimization**: the pipeline is rerun on smaller inputs, dropping
+//!    top-level items (delta debugging) as long as it still panics with the
+//!    same message in the same stage.
+//! 3. **Report**: [`report`] writes the stage, panic, backtrace, minimized
+//!    reproduction, and full source to a file and prints an apology pointing
+//!    at the issue tracker.
+//!
+//! Panics outside a guard go to the previously installed hook as usual.
+
+use cadenza_syntax::frontend::{Cadenza, Frontend};
+use std::{
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{Arc, Once},
+};
+
+/// A stage of the compiler pipeline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Stage {
+    /// Parsing source text
+    Parse,
+    /// Evaluating top-level items
+    #[default]
+    Eval,
+    /// Lowering to IR
+    Lowering,
+    /// Generating WebAssembly
+    Codegen,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Parse => "parsing",
+            Stage::Eval => "evaluation",
+            Stage::Lowering => "lowering",
+            Stage::Codegen => "codegen",
+        })
+    }
+}
+
+/// A named source file given to a guarded pipeline.
+#[derive(Debug, Clone)]
+pub struct Source {
+    /// The file name shown in the report.
+    pub name: String,
+    /// The source text.
+    pub text: String,
+    /// The syntax the source is written in.
+    pub frontend: Arc<dyn Frontend>,
+}
+
+impl Source {
+    pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            text: text.into(),
+            frontend: Arc::new(Cadenza),
+        }
+    }
+
+    /// Returns the source, written in the syntax of `frontend`.
+    pub fn with_frontend(self, frontend: Arc<dyn Frontend>) -> Self {
+        Self { frontend, ..self }
+    }
+}
+
+/// A caught compiler panic.
+#[derive(Debug)]
+pub struct Crash {
+    /// The stage the pipeline was in when it panicked.
+    pub stage: Stage,
+    /// The panic message.
+    pub message: String,
+    /// Where the panic was raised.
+    pub location: Option<String>,
+    /// The backtrace of the original panic.
+    pub backtrace: String,
+    /// The sources the pipeline was given.
+    pub sources: Vec<Source>,
+    /// The smallest sources found that still reproduce the panic.
+    pub minimized: Vec<Source>,
+}
+
+/// A panic seen by the hook.
+struct Panic {
+    stage: Stage,
+    message: String,
+    location: Option<String>,
+    backtrace: Backtrace,
+}
+
+thread_local! {
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+    static STAGE: Cell<Stage> = const { Cell::new(Stage::Eval) };
+    static PANIC: RefCell<Option<Panic>> = const { RefCell::new(None) };
+}
+
+/// Records that the current pipeline has moved to `stage`.
+pub fn enter(stage: Stage) {
+    STAGE.set(stage);
+}
+
+/// Runs `pipeline` on `sources`, catching any panic as a [`Crash`].
+///
+/// The pipeline may be rerun on reduced sources to minimize the crash, so it
+/// shouldn't have side effects beyond its return value.
+pub fn guard<T>(sources: Vec<Source>, pipeline: impl Fn(&[Source]) -> T) -> Result<T, Box<Crash>> {
+    install_hook();
+    let panic = match run(&sources, &pipeline) {
+        Ok(value) => return Ok(value),
+        Err(panic) => panic,
+    };
+
+    let minimized = minimize(&sources, |candidate| {
+        run(candidate, &pipeline)
+            .err()
+            .is_some_and(|other| other.stage == panic.stage && other.message == panic.message)
+    });
+
+    Err(Box::new(Crash {
+        stage: panic.stage,
+        message: panic.message,
+        location: panic.location,
+        backtrace: panic.backtrace.to_string(),
+        sources,
+        minimized,
+    }))
+}
+
+/// Runs `pipeline` once with the hook capturing instead of printing.
+fn run<T>(sources: &[Source], pipeline: &impl Fn(&[Source]) -> T) -> Result<T, Panic> {
+    STAGE.set(Stage::Eval);
+    GUARDED.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| pipeline(sources)));
+    GUARDED.set(false);
+    result.map_err(|_| {
+        PANIC.take().unwrap_or_else(|| Panic {
+            stage: STAGE.get(),
+            message: "unknown panic".to_string(),
+            location: None,
+            backtrace: Backtrace::disabled(),
+        })
+    })
+}
+
+/// Installs a panic hook that captures panics inside [`guard`].
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !GUARDED.get() {
+                return previous(info);
+            }
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            PANIC.set(Some(Panic {
+                stage: STAGE.get(),
+                message,
+                location: info.location().map(|location| location.to_string()),
+                backtrace: Backtrace::force_capture(),
+            }));
+        }));
+    });
+}
+
+/// Returns the smallest sources for which `reproduces` still holds.
+///
+/// Each source is reduced in turn, keeping the others fixed.
+fn minimize(sources: &[Source], reproduces: impl Fn(&[Source]) -> bool) -> Vec<Source> {
+    let mut current = sources.to_vec();
+    for i in 0..current.len() {
+        let items = items(&current[i]);
+        let kept = ddmin(items, |subset| {
+            let mut candidate = current.clone();
+            candidate[i].text = subset.join("\n");
+            reproduces(&candidate)
+        });
+        current[i].text = kept.join("\n");
+    }
+    current
+}
+
+/// Returns the source text of each top-level item in `source`.
+fn items(source: &Source) -> Vec<String> {
+    let Source { text, frontend, .. } = source;
+    frontend
+        .parse(text)
+        .ast()
+        .items()
+        .map(|item| {
+            let span = item.span();
+            text[span.start..span.end].trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Reduces `items` to a subset, ideally 1-minimal, for which `test` holds.
+///
+/// This is Zeller's ddmin: split the items into chunks, and keep any chunk or
+/// complement that still fails, refining the split when none does.
+fn ddmin(mut items: Vec<String>, test: impl Fn(&[String]) -> bool) -> Vec<String> {
+    let mut n = 2;
+    while items.len() >= 2 {
+        let size = items.len().div_ceil(n);
+        let chunks: Vec<Vec<String>> = items.chunks(size).map(<[String]>::to_vec).collect();
+
+        if let Some(chunk) = chunks.iter().find(|chunk| test(chunk)) {
+            items = chunk.clone();
+            n = 2;
+            continue;
+        }
+
+        let mut complement = (0..chunks.len()).map(|skip| {
+            chunks
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != skip)
+                .flat_map(|(_, chunk)| chunk.iter().cloned())
+                .collect::<Vec<_>>()
+        });
+        if let Some(complement) = complement.find(|complement| test(complement)) {
+            items = complement;
+            n = (n - 1).max(2);
+            continue;
+        }
+
+        if n >= items.len() {
+            break;
+        }
+        n = (2 * n).min(items.len());
+    }
+    items
+}
+
+impl Crash {
+    /// Renders the crash report as Markdown.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "# Cadenza compiler crash\n\n\
+             - Version: {}\n\
+             - Stage: {}\n\
+             - Panic: {}\n",
+            env!("CARGO_PKG_VERSION"),
+            self.stage,
+            self.message,
+        );
+        if let Some(location) = &self.location {
+            report.push_str(&format!("- Location: {location}\n"));
+        }
+
+        report.push_str("\n## Minimized reproduction\n");
+        push_sources(&mut report, &self.minimized);
+        report.push_str("\n## Full source\n");
+        push_sources(&mut report, &self.sources);
+        report.push_str(&format!(
+            "\n## Backtrace\n\n```text\n{}\n```\n",
+            self.backtrace
+        ));
+        report
+    }
+
+    /// Writes the report to a new file in `dir` and returns its path.
+    pub fn write(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let path = dir.join(format!(
+            "cadenza-crash-{}-{timestamp}.md",
+            std::process::id()
+        ));
+        std::fs::write(&path, self.report())?;
+        Ok(path)
+    }
+}
+
+fn push_sources(report: &mut String, sources: &[Source]) {
+    for source in sources {
+        report.push_str(&format!(
+            "\n`{}`:\n\n```{}\n{}\n```\n",
+            source.name,
+            source.frontend.name(),
+            source.text.trim_end()
+        ));
+    }
+}
+
+/// Writes a report for `crash` to the temp directory, prints an apology, and
+/// returns the error the command should fail with.
+pub fn report(crash: &Crash) -> anyhow::Error {
+    eprintln!(
+        "error: the compiler crashed during {}: {}",
+        crash.stage, crash.message
+    );
+    eprintln!("\nThis is a bug in Cadenza, sorry about that!");
+    match crash.write(&std::env::temp_dir()) {
+        Ok(path) => {
+            eprintln!("A crash report with a minimized reproduction was written to:\n");
+            eprintln!("    {}\n", path.display());
+            eprintln!(
+                "Please open an issue at {}/issues and attach the report.",
+                env!("CARGO_PKG_REPOSITORY")
+            );
+        }
+        Err(err) => {
+            eprintln!("The crash report couldn't be written ({err}). Here it is instead:\n");
+            eprintln!("{}", crash.report());
+            eprintln!(
+                "Please open an issue at {}/issues with the report above.",
+                env!("CARGO_PKG_REPOSITORY")
+            );
+        }
+    }
+    anyhow::anyhow!("internal compiler error during {}", crash.stage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates fine, but crashes while lowering anything that mentions
+    /// both `boom` and `fuse`.
+    fn pipeline(sources: &[Source]) -> usize {
+        enter(Stage::Eval);
+        let source = &sources[0].text;
+        enter(Stage::Lowering);
+        if source.contains("boom") && source.contains("fuse") {
+            panic!("lowering exploded");
+        }
+        source.len()
+    }
+
+    #[test]
+    fn passes_results_through() {
+        let sources = vec![Source::new("main.cdz", "let a = 1\n")];
+        assert_eq!(guard(sources, pipeline).unwrap(), 10);
+    }
+
+    #[test]
+    fn minimizes_to_the_failing_items() {
+        let source = "\
+let a = 1
+let fuse = 2
+fn f x = x + a
+let b = boom
+let c = 3
+";
+        let crash = guard(vec![Source::new("main.cdz", source)], pipeline).unwrap_err();
+        assert_eq!(crash.stage, Stage::Lowering);
+        assert_eq!(crash.message, "lowering exploded");
+        assert!(crash.location.unwrap().contains("crash.rs"));
+        assert_eq!(crash.sources[0].text, source);
+        assert_eq!(crash.minimized[0].text, "let fuse = 2\nlet b = boom");
+    }
+
+    #[test]
+    fn report_includes_the_reproduction() {
+        let crash = guard(vec![Source::new("main.cdz", "boom fuse\n1\n")], pipeline).unwrap_err();
+        let report = crash.report();
+        assert!(report.contains("- Stage: lowering"));
+        assert!(report.contains("- Panic: lowering exploded"));
+        assert!(
+            report
+                .contains("## Minimized reproduction\n\n`main.cdz`:\n\n```cadenza\nboom fuse\n```")
+        );
+    }
+}
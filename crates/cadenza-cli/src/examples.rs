@@ -0,0 +1,73 @@
+//! The `examples` command: browse the bundled example programs.
+
+use anyhow::Result;
+use cadenza_eval::examples::{self, Example};
+use std::io::Write;
+
+/// Write the source of the example `id` to `output`, or, without an `id`, a
+/// line per example: its id, title, description, and tags. `tag` limits the
+/// list to examples with that tag.
+pub fn run_examples<W: Write>(id: Option<&str>, tag: Option<&str>, mut output: W) -> Result<()> {
+    if let Some(id) = id {
+        let example = examples::get(id).ok_or_else(|| {
+            let ids: Vec<&str> = examples::all().iter().map(|example| example.id).collect();
+            anyhow::anyhow!("no example `{id}`; expected one of: {}", ids.join(", "))
+        })?;
+        write!(output, "{}", example.source)?;
+        return Ok(());
+    }
+
+    let listed: Vec<&Example> = match tag {
+        Some(tag) => examples::tagged(tag).collect(),
+        None => examples::all().iter().collect(),
+    };
+    if listed.is_empty() {
+        anyhow::bail!("no examples tagged `{}`", tag.unwrap_or_default());
+    }
+    let width = listed
+        .iter()
+        .map(|example| example.id.len())
+        .max()
+        .unwrap_or(0);
+    for example in listed {
+        writeln!(
+            output,
+            "{:width$}  {}: {} [{}]",
+            example.id,
+            example.title,
+            example.description,
+            example.tags.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: Option<&str>, tag: Option<&str>) -> Result<String> {
+        let mut output = Vec::new();
+        run_examples(id, tag, &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn lists_and_shows_examples() {
+        let all = run(None, None).unwrap();
+        assert_eq!(all.lines().count(), examples::all().len());
+        assert!(all.contains(
+            "measures    Units of Measure: Define and use physical units [units, arithmetic]\n"
+        ));
+
+        let units = run(None, Some("units")).unwrap();
+        assert!(units.starts_with("measures  Units of Measure"));
+        assert_eq!(units.lines().count(), 1);
+        assert!(run(None, Some("nope")).is_err());
+
+        let source = run(Some("measures"), None).unwrap();
+        assert_eq!(source, examples::get("measures").unwrap().source);
+        let missing = run(Some("nope"), None).unwrap_err().to_string();
+        assert!(missing.contains("measures"), "{missing}");
+    }
+}
@@ -0,0 +1,37 @@
+//! The `expand` command: show a file after macro and special-form expansion.
+
+use crate::frontends;
+use anyhow::Result;
+use cadenza_eval::{Compiler, Env};
+use std::{io::Write, path::Path};
+
+/// Expand the file at `path` and write the annotated result to `output`.
+///
+/// Each top-level item is followed by a comment listing the macro invocations
+/// (and their source positions) that produced it. The file is read in the
+/// syntax its extension names (see [`frontends`]).
+pub fn run_expand<W: Write>(path: &Path, mut output: W) -> Result<()> {
+    let source = std::fs::read_to_string(path)?;
+    let frontend = frontends::for_path(path);
+    let parsed = frontend.parse(&source);
+
+    if !parsed.errors.is_empty() {
+        let errors: Vec<String> = parsed
+            .errors
+            .into_iter()
+            .map(|e| format!("  {:?}", frontend.map_error(e)))
+            .collect();
+        return Err(anyhow::anyhow!(
+            "Failed to parse {}:\n{}",
+            path.display(),
+            errors.join("\n")
+        ));
+    }
+
+    let env = Env::with_standard_builtins();
+    let compiler = Compiler::new();
+    let expansion = cadenza_eval::expand::expand(&parsed.ast(), &env, &compiler);
+
+    write!(output, "{}", expansion.annotated(&source))?;
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! The syntaxes the CLI reads.
+//!
+//! Commands given a file pick its [`Frontend`] by the file's extension, so
+//! `cadenza run model.sql` runs SQL as `cadenza run model.cdz` runs Cadenza.
+//! Files with an extension no front-end claims are read as Cadenza.
+
+use cadenza_syntax::frontend::{Frontend, Frontends};
+use std::{path::Path, sync::Arc};
+
+/// Returns the registry of every syntax the CLI reads.
+pub fn registry() -> Frontends {
+    let mut frontends = Frontends::new();
+    frontends.register(cadenza_markdown::MarkdownFrontend);
+    frontends.register(cadenza_sql::SqlFrontend);
+    frontends.register(cadenza_gcode::GcodeFrontend);
+    frontends.register(cadenza_csv::CsvFrontend);
+    frontends.register(cadenza_csv::TsvFrontend);
+    frontends.register(cadenza_config::JsonFrontend);
+    frontends.register(cadenza_config::TomlFrontend);
+    frontends
+}
+
+/// Returns the front-end for the file at `path`.
+pub fn for_path(path: &Path) -> Arc<dyn Frontend> {
+    registry().for_path(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_syntax_by_extension() {
+        for (file, name) in [
+            ("main.cdz", "cadenza"),
+            ("README.md", "markdown"),
+            ("report.sql", "sql"),
+            ("part.gcode", "gcode"),
+            ("parts.csv", "csv"),
+            ("weather.tsv", "tsv"),
+            ("package.json", "json"),
+            ("Cargo.toml", "toml"),
+            ("script", "cadenza"),
+        ] {
+            assert_eq!(for_path(Path::new(file)).name(), name, "{file}");
+        }
+    }
+}
@@ -1,27 +1,108 @@
 //! LSP backend implementation using tower-lsp.
 
-use cadenza_lsp::core;
-use std::collections::HashMap;
-use tokio::sync::RwLock;
+use crate::{
+    frontends,
+    stats::{self, Stats},
+};
+use cadenza_eval::{
+    Compiler, Env,
+    symbol_index::{FoundSymbol, SymbolKind as DefinitionKind, SymbolSearch},
+};
+use cadenza_lsp::{
+    Settings, core,
+    document::{self, Document, Versioned},
+    imports, lenses,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tokio::sync::{Mutex, RwLock};
 use tower_lsp::{Client, LanguageServer, jsonrpc::Result, lsp_types::*};
 
 /// The main LSP backend for Cadenza.
 pub struct CadenzaLspBackend {
     client: Client,
-    documents: RwLock<HashMap<Url, String>>,
+    /// The open documents, at the latest version the editor sent.
+    documents: RwLock<HashMap<Url, Document>>,
+    /// Every `.cdz` file in the workspace folders and every open document,
+    /// indexed for symbol search. Open documents hold the editor's buffer;
+    /// other files hold their contents on disk.
+    workspace: Mutex<SymbolSearch>,
+    /// Whether the client lets us register a file watcher.
+    watch_files: AtomicBool,
+    /// The client's settings, from `initializationOptions` and configuration
+    /// changes.
+    settings: RwLock<Settings>,
+    /// Request latencies, reported by `cadenza/stats`.
+    stats: Stats,
 }
 
+/// The most symbols returned by a `workspace/symbol` request.
+const MAX_WORKSPACE_SYMBOLS: usize = 256;
+
 impl CadenzaLspBackend {
-    pub fn new(client: Client) -> Self {
+    pub fn new(client: Client, stats: Stats) -> Self {
         Self {
             client,
             documents: RwLock::new(HashMap::new()),
+            workspace: Mutex::new(SymbolSearch::new()),
+            watch_files: AtomicBool::new(false),
+            settings: RwLock::new(Settings::default()),
+            stats,
+        }
+    }
+
+    /// Adds every `.cdz` file under `dir` to the workspace.
+    ///
+    /// Hidden directories and `target` are skipped.
+    fn index_dir(workspace: &mut SymbolSearch, dir: &Path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "target" {
+                    Self::index_dir(workspace, &path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "cdz")
+                && let (Ok(uri), Ok(text)) =
+                    (Url::from_file_path(&path), std::fs::read_to_string(&path))
+            {
+                workspace.set_file(uri.as_str(), &text);
+            }
         }
     }
 
+    /// Replaces the settings with those in the client's JSON, keeping the
+    /// current settings if it isn't valid.
+    async fn apply_settings(&self, value: serde_json::Value) {
+        match Settings::from_json(value) {
+            Ok(settings) => *self.settings.write().await = settings,
+            Err(err) => tracing::warn!("Ignoring invalid settings: {err}"),
+        }
+    }
+
+    /// Checks the document at `uri`, resolving its imports from the
+    /// workspace, and publishes the diagnostics.
     async fn publish_diagnostics(&self, uri: Url, text: &str) {
+        let name = module_name(&uri).unwrap_or_else(|| "main".to_string());
+        let settings = self.settings.read().await.clone();
+        let workspace = self.workspace.lock().await;
+        let frontend = frontends::for_path(Path::new(uri.path()));
+        let diagnostics =
+            core::check_module_to_diagnostics_with(frontend, &name, text, &settings, |import| {
+                resolve(&workspace, import)
+            });
+        drop(workspace);
+
         // Convert from cadenza_lsp diagnostics to tower_lsp diagnostics
-        let diagnostics = core::parse_to_diagnostics(text)
+        let diagnostics = diagnostics
             .into_iter()
             .map(|d| Diagnostic {
                 range: d.range,
@@ -40,11 +121,305 @@ impl CadenzaLspBackend {
             .publish_diagnostics(uri, diagnostics, None)
             .await;
     }
+
+    /// Returns the version and text of the open document at `uri`.
+    ///
+    /// Slow answers are computed from a snapshot, so edits aren't held up
+    /// waiting for them; the answer is then validated against the document
+    /// (see [`Document::validate`]).
+    async fn snapshot(&self, uri: &Url) -> Option<(i32, String)> {
+        let documents = self.documents.read().await;
+        let document = documents.get(uri)?;
+        Some((document.version(), document.text().to_string()))
+    }
+}
+
+/// Parameters for the `cadenza/expand` request.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Result of the `cadenza/expand` request.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandResult {
+    /// The document after macro and special-form expansion.
+    pub text: String,
+    /// Links between expanded regions and the macro invocations they came from.
+    pub markers: Vec<ExpandMarker>,
+}
+
+/// A single expanded region in an [`ExpandResult`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandMarker {
+    /// Name of the macro or special form.
+    pub name: String,
+    /// Range of the invocation in the original document.
+    pub origin: Range,
+    /// Range of the expanded region in [`ExpandResult::text`].
+    pub expanded: Range,
+}
+
+/// Returns the module name of the file at `uri`: its file stem.
+fn module_name(uri: &Url) -> Option<String> {
+    let path = Path::new(uri.path());
+    Some(path.file_stem()?.to_str()?.to_string())
+}
+
+/// Returns the source of the workspace module named `name`.
+fn resolve(workspace: &SymbolSearch, name: &str) -> Option<String> {
+    let path = workspace.paths().find(|path| {
+        Url::parse(path)
+            .ok()
+            .and_then(|uri| module_name(&uri))
+            .is_some_and(|module| module == name)
+    })?;
+    workspace.text(path).map(str::to_string)
+}
+
+/// Returns the LSP range of byte range `span` in `text`.
+fn range(text: &str, span: &std::ops::Range<usize>) -> Range {
+    Range::new(
+        core::offset_to_position(text, span.start),
+        core::offset_to_position(text, span.end),
+    )
+}
+
+/// Converts validated code lenses to LSP code lenses.
+fn code_lens_items(document: &Document, lenses: Vec<Versioned<String>>) -> Vec<CodeLens> {
+    lenses
+        .into_iter()
+        .filter_map(|lens| document.validate(lens))
+        .map(|lens| CodeLens {
+            range: range(document.text(), &lens.span),
+            command: Some(Command {
+                title: lens.value,
+                command: String::new(),
+                arguments: None,
+            }),
+            data: None,
+        })
+        .collect()
+}
+
+/// Replaces the workspace's copy of the file at `uri` with its contents on
+/// disk, or removes it if it no longer exists.
+fn reload(workspace: &mut SymbolSearch, uri: &Url) {
+    match uri
+        .to_file_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+    {
+        Some(text) => workspace.set_file(uri.as_str(), &text),
+        None => workspace.remove_file(uri.as_str()),
+    }
+}
+
+/// Applies changes made to files outside the editor to `workspace`.
+///
+/// Files open in the editor are skipped: their buffer is what the user sees,
+/// and the editor sends its own change when it reloads one from disk.
+fn apply_file_events(
+    workspace: &mut SymbolSearch,
+    open: &HashMap<Url, Document>,
+    events: &[FileEvent],
+) {
+    for event in events {
+        if !open.contains_key(&event.uri) {
+            reload(workspace, &event.uri);
+        }
+    }
+}
+
+/// Returns the workspace modules, other than the one at `uri`, that define a
+/// top-level `name`.
+fn defining_modules(workspace: &SymbolSearch, name: &str, uri: &Url) -> Vec<String> {
+    let mut modules: Vec<String> = workspace
+        .search(name, MAX_WORKSPACE_SYMBOLS)
+        .into_iter()
+        .filter(|found| found.symbol.name == name && found.path != uri.as_str())
+        .filter_map(|found| module_name(&Url::parse(&found.path).ok()?))
+        .collect();
+    modules.sort();
+    modules.dedup();
+    modules
+}
+
+/// Returns the code actions for the document at `uri`: an import for each
+/// undefined name some workspace module defines, and organizing the imports.
+fn code_actions(
+    workspace: &SymbolSearch,
+    uri: &Url,
+    text: &str,
+    context: &CodeActionContext,
+) -> Vec<CodeActionOrCommand> {
+    let wants = |kind: &CodeActionKind| {
+        context.only.as_ref().is_none_or(|only| {
+            only.iter()
+                .any(|wanted| kind.as_str().starts_with(wanted.as_str()))
+        })
+    };
+    let action = |title: String, kind: CodeActionKind, edits: Vec<TextEdit>| CodeAction {
+        title,
+        kind: Some(kind),
+        edit: Some(WorkspaceEdit::new(HashMap::from([(uri.clone(), edits)]))),
+        ..CodeAction::default()
+    };
+
+    let mut actions = Vec::new();
+    if wants(&CodeActionKind::QUICKFIX) {
+        let undefined = context.diagnostics.iter().filter(|diagnostic| {
+            diagnostic.code == Some(NumberOrString::String("E0001".to_string()))
+        });
+        for diagnostic in undefined {
+            let start = core::position_to_offset(text, diagnostic.range.start);
+            let end = core::position_to_offset(text, diagnostic.range.end);
+            let name = &text[start..end];
+            let modules = defining_modules(workspace, name, uri);
+            if let Some(suggestion) = core::suggestion(diagnostic) {
+                // A typo is likelier than a missing import only when no
+                // module defines the name as written
+                actions.push(CodeAction {
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    is_preferred: Some(modules.is_empty()),
+                    ..action(
+                        format!("Change `{name}` to `{suggestion}`"),
+                        CodeActionKind::QUICKFIX,
+                        vec![TextEdit::new(diagnostic.range, suggestion.to_string())],
+                    )
+                });
+            }
+            let preferred = modules.len() == 1;
+            for module in modules {
+                let Some(edit) = imports::add_import(text, &module) else {
+                    continue;
+                };
+                actions.push(CodeAction {
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    is_preferred: Some(preferred),
+                    ..action(
+                        format!("Import `{module}` for `{name}`"),
+                        CodeActionKind::QUICKFIX,
+                        vec![edit],
+                    )
+                });
+            }
+        }
+    }
+
+    let organize = imports::organize_imports(text);
+    if !organize.is_empty() && wants(&CodeActionKind::SOURCE_ORGANIZE_IMPORTS) {
+        actions.push(action(
+            "Organize imports".to_string(),
+            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+            organize,
+        ));
+    }
+
+    actions
+        .into_iter()
+        .map(CodeActionOrCommand::CodeAction)
+        .collect()
+}
+
+/// Converts a symbol found in the workspace index to an LSP symbol.
+fn symbol_information(found: FoundSymbol, text: &str) -> Option<SymbolInformation> {
+    let kind = match found.symbol.kind {
+        DefinitionKind::Function => SymbolKind::FUNCTION,
+        DefinitionKind::Variable => SymbolKind::VARIABLE,
+        DefinitionKind::Struct => SymbolKind::STRUCT,
+        DefinitionKind::Enum => SymbolKind::ENUM,
+        DefinitionKind::Measure => SymbolKind::CONSTANT,
+    };
+    let range = Range::new(
+        core::offset_to_position(text, found.symbol.span.start),
+        core::offset_to_position(text, found.symbol.span.end),
+    );
+    #[allow(deprecated)]
+    Some(SymbolInformation {
+        name: found.symbol.name,
+        kind,
+        tags: None,
+        deprecated: None,
+        location: Location::new(Url::parse(&found.path).ok()?, range),
+        container_name: None,
+    })
+}
+
+impl CadenzaLspBackend {
+    /// Handles the `cadenza/stats` custom request: request latencies, heap
+    /// usage, and the workspace's query cache hit rate.
+    pub async fn stats(&self) -> Result<stats::Report> {
+        let queries = self.workspace.lock().await.query_stats();
+        Ok(self.stats.report(queries))
+    }
+
+    /// Handles the `cadenza/expand` custom request.
+    pub async fn expand(&self, params: ExpandParams) -> Result<Option<ExpandResult>> {
+        let documents = self.documents.read().await;
+        let Some(text) = documents.get(&params.text_document.uri).map(Document::text) else {
+            return Ok(None);
+        };
+
+        let parsed = cadenza_syntax::parse::parse(text);
+        let env = Env::with_standard_builtins();
+        let compiler = Compiler::new();
+        let expansion = cadenza_eval::expand::expand(&parsed.ast(), &env, &compiler);
+
+        let markers = expansion
+            .markers
+            .iter()
+            .map(|m| ExpandMarker {
+                name: m.name.to_string(),
+                origin: Range::new(
+                    core::offset_to_position(text, m.origin.start),
+                    core::offset_to_position(text, m.origin.end),
+                ),
+                expanded: Range::new(
+                    core::offset_to_position(&expansion.text, m.expanded.start),
+                    core::offset_to_position(&expansion.text, m.expanded.end),
+                ),
+            })
+            .collect();
+
+        Ok(Some(ExpandResult {
+            text: expansion.text,
+            markers,
+        }))
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for CadenzaLspBackend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let watch_files = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files)
+            .and_then(|watched| watched.dynamic_registration)
+            .unwrap_or(false);
+        self.watch_files.store(watch_files, Ordering::Relaxed);
+
+        if let Some(options) = params.initialization_options {
+            self.apply_settings(options).await;
+        }
+
+        #[allow(deprecated)]
+        let roots: Vec<Url> = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|folder| folder.uri).collect(),
+            None => params.root_uri.into_iter().collect(),
+        };
+        let mut workspace = self.workspace.lock().await;
+        for root in roots {
+            if let Ok(dir) = root.to_file_path() {
+                Self::index_dir(&mut workspace, &dir);
+            }
+        }
+
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
                 name: "cadenza-lsp".to_string(),
@@ -55,6 +430,19 @@ impl LanguageServer for CadenzaLspBackend {
                     TextDocumentSyncKind::FULL,
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                        ]),
+                        ..CodeActionOptions::default()
+                    },
+                )),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
                     trigger_characters: Some(vec![".".to_string()]),
@@ -67,6 +455,48 @@ impl LanguageServer for CadenzaLspBackend {
 
     async fn initialized(&self, _: InitializedParams) {
         tracing::info!("Cadenza LSP server initialized");
+
+        if !self.watch_files.load(Ordering::Relaxed) {
+            return;
+        }
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.cdz".to_string()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "cadenza-watched-files".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(err) = self.client.register_capability(vec![registration]).await {
+            tracing::warn!("Failed to register file watcher: {err}");
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let documents = self.documents.read().await.clone();
+        apply_file_events(
+            &mut *self.workspace.lock().await,
+            &documents,
+            &params.changes,
+        );
+
+        // Open documents may import a changed file
+        for (uri, document) in documents {
+            self.publish_diagnostics(uri, document.text()).await;
+        }
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        self.apply_settings(params.settings).await;
+
+        // Lint levels and fuel change the diagnostics of every open document
+        let documents = self.documents.read().await.clone();
+        for (uri, document) in documents {
+            self.publish_diagnostics(uri, document.text()).await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -78,99 +508,312 @@ impl LanguageServer for CadenzaLspBackend {
         let uri = params.text_document.uri;
         let text = params.text_document.text;
 
-        self.documents
-            .write()
-            .await
-            .insert(uri.clone(), text.clone());
+        self.documents.write().await.insert(
+            uri.clone(),
+            Document::new(params.text_document.version, text.clone()),
+        );
+        self.workspace.lock().await.set_file(uri.as_str(), &text);
         self.publish_diagnostics(uri, &text).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
         let uri = params.text_document.uri;
+        let version = params.text_document.version;
 
         if let Some(change) = params.content_changes.into_iter().next() {
             let text = change.text;
-            self.documents
+            let updated = self
+                .documents
                 .write()
                 .await
-                .insert(uri.clone(), text.clone());
+                .entry(uri.clone())
+                .or_default()
+                .update(version, text.clone());
+            if !updated {
+                // A newer version already arrived
+                return;
+            }
+            self.workspace.lock().await.set_file(uri.as_str(), &text);
             self.publish_diagnostics(uri, &text).await;
         }
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        self.documents
-            .write()
-            .await
-            .remove(&params.text_document.uri);
+        let uri = params.text_document.uri;
+        self.documents.write().await.remove(&uri);
+
+        // The buffer is gone, so the file on disk is the truth again
+        reload(&mut *self.workspace.lock().await, &uri);
+        self.client.publish_diagnostics(uri, Vec::new(), None).await;
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let workspace = self.workspace.lock().await;
+        let found = workspace
+            .search(&params.query, MAX_WORKSPACE_SYMBOLS)
+            .into_iter()
+            .filter_map(|found| {
+                let text = workspace.text(&found.path)?;
+                symbol_information(found, text)
+            })
+            .collect();
+        Ok(Some(found))
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
 
-        let documents = self.documents.read().await;
-        let text = match documents.get(&uri) {
-            Some(text) => text,
-            None => return Ok(None),
+        let Some((version, text)) = self.snapshot(&uri).await else {
+            return Ok(None);
+        };
+        let offset = core::position_to_offset(&text, position);
+        let cached = self
+            .documents
+            .read()
+            .await
+            .get(&uri)
+            .and_then(|document| document.cached_hover(offset));
+        let answer = match cached {
+            Some(answer) => Some(answer),
+            None => {
+                let settings = self.settings.read().await.clone();
+                tokio::task::spawn_blocking(move || {
+                    document::hover(&text, version, offset, &settings)
+                })
+                .await
+                .ok()
+                .flatten()
+            }
         };
 
-        // For now, just show a simple hover with the word at the position
-        let offset = core::position_to_offset(text, position);
+        // The document may have changed while the hover was computed
+        let mut documents = self.documents.write().await;
+        let Some(document) = documents.get_mut(&uri) else {
+            return Ok(None);
+        };
+        let Some(answer) = answer.and_then(|answer| document.validate(answer)) else {
+            return Ok(None);
+        };
+        document.cache_hover(answer.clone());
+
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: answer.value,
+            }),
+            range: Some(range(document.text(), &answer.span)),
+        }))
+    }
 
-        // Find the word boundaries around the offset
-        let start = text[..offset]
-            .rfind(|c: char| !c.is_alphanumeric() && c != '_')
-            .map(|i| i + 1)
-            .unwrap_or(0);
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let uri = params.text_document.uri;
+        let Some((version, text)) = self.snapshot(&uri).await else {
+            return Ok(None);
+        };
+        let lenses = tokio::task::spawn_blocking(move || lenses::code_lenses(&text, version))
+            .await
+            .unwrap_or_default();
 
-        let end = text[offset..]
-            .find(|c: char| !c.is_alphanumeric() && c != '_')
-            .map(|i| offset + i)
-            .unwrap_or(text.len());
+        let documents = self.documents.read().await;
+        let Some(document) = documents.get(&uri) else {
+            return Ok(None);
+        };
+        Ok(Some(code_lens_items(document, lenses)))
+    }
 
-        if start >= end {
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let documents = self.documents.read().await;
+        let Some(text) = documents.get(&uri).map(Document::text) else {
             return Ok(None);
-        }
+        };
 
-        let word = &text[start..end];
+        let workspace = self.workspace.lock().await;
+        Ok(Some(code_actions(&workspace, &uri, text, &params.context)))
+    }
 
-        if word.is_empty() {
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        let documents = self.documents.read().await;
+        let Some(text) = documents.get(&uri).map(Document::text) else {
             return Ok(None);
-        }
+        };
+        let offset = core::position_to_offset(text, position);
+
+        Ok(Some(CompletionResponse::Array(core::completions(
+            text, offset,
+        ))))
+    }
+}
 
-        // Create a simple hover message
-        let contents = HoverContents::Scalar(MarkedString::String(format!(
-            "Symbol: `{}`\n\nType information coming soon!",
-            word
-        )));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(Some(Hover {
-            contents,
-            range: None,
-        }))
+    #[test]
+    fn disk_changes_update_files_not_open_in_the_editor() {
+        let dir = std::env::temp_dir().join(format!("cadenza-lsp-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, text: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, text).unwrap();
+            Url::from_file_path(path).unwrap()
+        };
+        let geometry = file("geometry.cdz", "fn square x = x * x\n");
+        let main = file("main.cdz", "import geometry\n");
+
+        let mut workspace = SymbolSearch::new();
+        CadenzaLspBackend::index_dir(&mut workspace, &dir);
+        workspace.set_file(main.as_str(), "import geometry\nsquare 2\n");
+        let open = HashMap::from([(
+            main.clone(),
+            Document::new(1, "import geometry\nsquare 2\n".to_string()),
+        )]);
+
+        // A `git checkout` rewrites both files, then deletes one
+        file("geometry.cdz", "fn cube x = x * x * x\n");
+        file("main.cdz", "import geometry\ncube 2\n");
+        let changed = |uri: &Url| FileEvent::new(uri.clone(), FileChangeType::CHANGED);
+        apply_file_events(&mut workspace, &open, &[changed(&geometry), changed(&main)]);
+
+        assert_eq!(
+            resolve(&workspace, "geometry").as_deref(),
+            Some("fn cube x = x * x * x\n")
+        );
+        // The editor's buffer wins over the disk
+        assert_eq!(
+            workspace.text(main.as_str()),
+            Some("import geometry\nsquare 2\n")
+        );
+
+        std::fs::remove_file(geometry.to_file_path().unwrap()).unwrap();
+        let deleted = FileEvent::new(geometry.clone(), FileChangeType::DELETED);
+        apply_file_events(&mut workspace, &open, &[deleted]);
+        assert_eq!(resolve(&workspace, "geometry"), None);
+        assert!(workspace.search("cube", 10).is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 
-    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        let _uri = params.text_document_position.text_document.uri;
-        let _position = params.text_document_position.position;
-
-        // For now, return some basic completions
-        let items = vec![
-            CompletionItem {
-                label: "let".to_string(),
-                kind: Some(CompletionItemKind::KEYWORD),
-                detail: Some("Variable binding".to_string()),
-                ..Default::default()
-            },
-            CompletionItem {
-                label: "fn".to_string(),
-                kind: Some(CompletionItemKind::KEYWORD),
-                detail: Some("Function definition".to_string()),
-                ..Default::default()
-            },
-        ];
+    #[test]
+    fn drops_lenses_for_edited_spans() {
+        let lenses = lenses::code_lenses("fn square x = x * x\nfn cube x = x\n", 1);
+
+        // `square` was renamed while the lenses were computed
+        let mut document = Document::new(1, String::new());
+        document.update(2, "fn sq x = x * x\nfn cube x = x\n".to_string());
+        assert!(code_lens_items(&document, lenses.clone()).is_empty());
+
+        // Only the text after `square` changed, so its lens still applies
+        document.update(3, "fn square x = x * x\nfn cubed x = x\n".to_string());
+        let items = code_lens_items(&document, lenses);
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].range,
+            Range::new(Position::new(0, 3), Position::new(0, 9))
+        );
+    }
+
+    #[test]
+    fn imports_undefined_names_from_the_workspace() {
+        let uri = |name: &str| Url::parse(&format!("file:///project/{name}.cdz")).unwrap();
+        let mut workspace = SymbolSearch::new();
+        workspace.set_file(
+            uri("geometry").as_str(),
+            "fn square x = x * x
+",
+        );
+        workspace.set_file(
+            uri("shapes").as_str(),
+            "fn square x = x * x
+",
+        );
+        workspace.set_file(
+            uri("units").as_str(),
+            "let inch = 1
+",
+        );
+        let text = "import units
+let a = square inch
+";
+        workspace.set_file(uri("main").as_str(), text);
+
+        let diagnostics =
+            core::check_module_to_diagnostics("main", text, &Settings::default(), |import| {
+                resolve(&workspace, import)
+            });
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        let context = CodeActionContext {
+            diagnostics,
+            only: None,
+            trigger_kind: None,
+        };
+        let titles: Vec<String> = code_actions(&workspace, &uri("main"), text, &context)
+            .into_iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.title,
+                CodeActionOrCommand::Command(command) => command.title,
+            })
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Import `geometry` for `square`",
+                "Import `shapes` for `square`",
+            ]
+        );
+
+        // Organizing is offered once the imports are out of order
+        let text = "import units
+import geometry
+square inch
+";
+        let context = CodeActionContext {
+            only: Some(vec![CodeActionKind::SOURCE]),
+            ..context
+        };
+        let actions = code_actions(&workspace, &uri("main"), text, &context);
+        assert_eq!(actions.len(), 1);
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("expected a code action");
+        };
+        assert_eq!(action.title, "Organize imports");
+    }
 
-        Ok(Some(CompletionResponse::Array(items)))
+    #[test]
+    fn renames_misspelled_names() {
+        let uri = Url::parse("file:///project/main.cdz").unwrap();
+        let workspace = SymbolSearch::new();
+        let text = "fn square x = x * x\nsqaure 3\n";
+        let context = CodeActionContext {
+            diagnostics: core::check_to_diagnostics(text),
+            only: None,
+            trigger_kind: None,
+        };
+        assert_eq!(
+            context.diagnostics[0].message,
+            "undefined variable: sqaure\nhelp: did you mean `square`?"
+        );
+
+        let actions = code_actions(&workspace, &uri, text, &context);
+        let [CodeActionOrCommand::CodeAction(action)] = actions.as_slice() else {
+            panic!("expected one code action, got {actions:?}");
+        };
+        assert_eq!(action.title, "Change `sqaure` to `square`");
+        assert_eq!(action.is_preferred, Some(true));
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(
+            edits,
+            &[TextEdit::new(
+                Range::new(Position::new(1, 0), Position::new(1, 6)),
+                "square".to_string()
+            )]
+        );
     }
 }
@@ -2,22 +2,56 @@
 //!
 //! This module provides a Language Server Protocol server that can be used
 //! by editors and IDEs to provide intelligent code editing features.
+//!
+//! Each request is handled inside a `request` span (see
+//! [`stats::request_span`]), so its latency is logged, exported, and
+//! reported by the `cadenza/stats` request.
 
 mod backend;
 
+use crate::stats::{self, Stats};
 use anyhow::Result;
-use tower_lsp::{LspService, Server};
+use std::task::{Context, Poll};
+use tower_lsp::{LspService, Server, jsonrpc};
+use tracing::{Instrument, instrument::Instrumented};
 
 /// Start the LSP server on stdin/stdout.
-pub async fn start_server() -> Result<()> {
+pub async fn start_server(stats: Stats) -> Result<()> {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(backend::CadenzaLspBackend::new);
+    let (service, socket) =
+        LspService::build(move |client| backend::CadenzaLspBackend::new(client, stats.clone()))
+            .custom_method("cadenza/expand", backend::CadenzaLspBackend::expand)
+            .custom_method("cadenza/stats", backend::CadenzaLspBackend::stats)
+            .finish();
 
     tracing::info!("Starting Cadenza LSP server");
 
-    Server::new(stdin, stdout, socket).serve(service).await;
+    Server::new(stdin, stdout, socket)
+        .serve(Traced(service))
+        .await;
 
     Ok(())
 }
+
+/// A service that handles each request inside a `request` span.
+struct Traced<S>(S);
+
+impl<S> tower_service::Service<jsonrpc::Request> for Traced<S>
+where
+    S: tower_service::Service<jsonrpc::Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Instrumented<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: jsonrpc::Request) -> Self::Future {
+        let span = stats::request_span(request.method());
+        self.0.call(request).instrument(span)
+    }
+}
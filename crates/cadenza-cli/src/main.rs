@@ -1,13 +1,35 @@
 //! Cadenza CLI - A unified command-line interface for the Cadenza language toolchain.
 //!
 //! This binary provides various commands for working with Cadenza, including:
+//! - `build`: Compile a file to a reproducible WebAssembly module
+//! - `check`: Evaluate files and report errors and unused functions
+//! - `examples`: List the bundled example programs or print one
+//! - `expand`: Show a file after macro and special-form expansion
+//! - `run`: Run a program, calling its `main` function with the arguments
+//! - `test`: Run the tests in files, optionally with a coverage report
 //! - `repl`: Start an interactive REPL with history, syntax highlighting, and auto-completion
 //! - `lsp`: Start a Language Server Protocol server for editor integration
 //! - `mcp`: Start a Model Context Protocol server for LLM integration
+//! - `completions`: Print shell completions
+//! - `man`: Print or write the man pages
 
+mod build;
+mod check;
+mod completions;
+mod crash;
+mod examples;
+mod expand;
+mod frontends;
 mod lsp;
 mod mcp;
+mod pretty;
 mod repl;
+mod run;
+mod stats;
+mod telemetry;
+mod test;
+mod timings;
+mod usage;
 
 #[cfg(test)]
 mod testing;
@@ -15,8 +37,18 @@ mod testing;
 #[cfg(test)]
 mod generated;
 
-use clap::{Parser, Subcommand};
+use anyhow::Context;
+use cadenza_eval::{
+    CompileOptions,
+    messages::{self, Catalog},
+    options::{OptLevel, WarningLevel},
+    system::Capability,
+    trace::{TraceFilter, Tracer},
+};
+use clap::{Args, Parser, Subcommand};
+use stats::Stats;
 use std::path::PathBuf;
+use timings::Timings;
 
 #[derive(Parser)]
 #[command(name = "cadenza")]
@@ -24,45 +56,341 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Log each pipeline stage as it finishes, with how long it took
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// Print how long each file spent in each pipeline stage
+    #[arg(long, global = true)]
+    timings: bool,
+    /// Also write logs to this file, as JSON lines
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// Export trace spans to this OTLP/HTTP endpoint
+    #[arg(long, global = true, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+    /// Show diagnostics with the message catalog in this JSON file
+    #[arg(long, global = true, value_name = "PATH")]
+    messages: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Compile a file to WebAssembly
+    Build {
+        /// The Cadenza file to compile
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// Where to write the output
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// What to emit
+        #[arg(long, value_enum, default_value_t)]
+        emit: build::Emit,
+        #[command(flatten)]
+        compile: CompileArgs,
+    },
+    /// Evaluate files and report errors and unused functions
+    Check {
+        /// The Cadenza files to check, which may import each other by file stem
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+        #[command(flatten)]
+        compile: CompileArgs,
+    },
+    /// Run a program, calling its `main` function with the arguments
+    Run {
+        /// The Cadenza file to run
+        #[arg(value_name = "FILE", required_unless_present = "eval")]
+        file: Option<PathBuf>,
+        /// The arguments passed to `main`
+        #[arg(
+            value_name = "ARGS",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        args: Vec<String>,
+        /// Run this expression as the program instead of a file
+        #[arg(short = 'e', long = "eval", value_name = "EXPR")]
+        eval: Option<String>,
+        /// Grant a capability to the program (`fs`, `process`, `net`, `stdio`); may be repeated
+        #[arg(long = "allow", value_name = "CAPABILITY")]
+        allow: Vec<Capability>,
+        #[command(flatten)]
+        compile: CompileArgs,
+        #[command(flatten)]
+        trace: TraceArgs,
+    },
+    /// Run the tests in files, optionally with a coverage report
+    Test {
+        /// The Cadenza files to test, which may import each other by file stem
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+        /// Write an lcov and HTML coverage report to this directory
+        #[arg(long, value_name = "DIR")]
+        coverage: Option<PathBuf>,
+        #[command(flatten)]
+        compile: CompileArgs,
+    },
+    /// List the bundled example programs, or print one
+    Examples {
+        /// The example to print; omit to list them all
+        #[arg(value_name = "ID")]
+        id: Option<String>,
+        /// Only list examples with this tag
+        #[arg(long, value_name = "TAG", conflicts_with = "id")]
+        tag: Option<String>,
+    },
+    /// Show a file after macro and special-form expansion
+    Expand {
+        /// The Cadenza file to expand
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
     /// Start an interactive REPL (Read-Eval-Print Loop)
     Repl {
         /// Load a Cadenza file into the REPL scope before starting
         #[arg(short, long, value_name = "FILE")]
         load: Option<PathBuf>,
+        /// Grant a capability to scripts (`fs`, `process`, `net`, `stdio`); may be repeated
+        #[arg(long = "allow", value_name = "CAPABILITY")]
+        allow: Vec<Capability>,
+        #[command(flatten)]
+        compile: CompileArgs,
+        #[command(flatten)]
+        trace: TraceArgs,
     },
     /// Start the Language Server Protocol server
     Lsp,
     /// Start the Model Context Protocol server for LLM integration
     Mcp,
+    /// Print shell completions
+    Completions {
+        /// The shell to complete for
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page
+    Man {
+        /// Write the man pages, one per subcommand, to this directory instead
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+/// Command-line flags for [`CompileOptions`].
+#[derive(Args)]
+struct CompileArgs {
+    /// Optimization level (`0`, `1`, `2`)
+    #[arg(long, value_name = "LEVEL", default_value = "0")]
+    opt_level: OptLevel,
+    /// Enable a feature flag; may be repeated
+    #[arg(long = "feature", value_name = "NAME")]
+    features: Vec<String>,
+    /// How to report warnings (`allow`, `warn`, `deny`)
+    #[arg(long, value_name = "LEVEL", default_value = "warn")]
+    warnings: WarningLevel,
+    /// Limit evaluation to this many steps
+    #[arg(long, value_name = "STEPS")]
+    fuel: Option<u64>,
+    /// Limit how deeply function calls may nest
+    #[arg(long, value_name = "DEPTH", default_value_t = CompileOptions::DEFAULT_RECURSION_LIMIT)]
+    recursion_limit: u64,
+    /// Start without the standard prelude
+    #[arg(long)]
+    no_prelude: bool,
+}
+
+impl CompileArgs {
+    fn into_options(self) -> CompileOptions {
+        CompileOptions {
+            opt_level: self.opt_level,
+            features: self.features.into_iter().collect(),
+            warnings: self.warnings,
+            fuel: self.fuel,
+            recursion_limit: self.recursion_limit,
+            prelude: !self.no_prelude,
+            ..CompileOptions::default()
+        }
+    }
+}
+
+/// Command-line flags for tracing evaluation; see [`cadenza_eval::trace`].
+#[derive(Args)]
+struct TraceArgs {
+    /// Log each evaluated expression with its position, value and scope depth
+    #[arg(long)]
+    trace_eval: bool,
+    /// Write the trace to this file instead of standard error
+    #[arg(long, value_name = "PATH", requires = "trace_eval")]
+    trace_file: Option<PathBuf>,
+    /// Only trace expressions whose source contains this text
+    #[arg(long, value_name = "TEXT", requires = "trace_eval")]
+    trace_filter: Option<String>,
+    /// Only trace expressions evaluated at most this many scopes deep
+    #[arg(long, value_name = "DEPTH", requires = "trace_eval")]
+    trace_depth: Option<usize>,
+}
+
+impl TraceArgs {
+    fn filter(&self) -> TraceFilter {
+        TraceFilter {
+            pattern: self.trace_filter.clone(),
+            max_depth: self.trace_depth,
+        }
+    }
+
+    /// Returns the tracer the flags ask for, if any.
+    fn tracer(&self) -> anyhow::Result<Option<Tracer>> {
+        if !self.trace_eval {
+            return Ok(None);
+        }
+        let tracer = match &self.trace_file {
+            Some(path) => {
+                let file = std::fs::File::create(path)
+                    .with_context(|| format!("failed to create {}", path.display()))?;
+                Tracer::new(self.filter(), std::io::LineWriter::new(file))
+            }
+            None => Tracer::stderr(self.filter()),
+        };
+        Ok(Some(tracer))
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
-
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::Repl { load } => {
-            repl::start_repl(load)?;
+    let timings = cli.timings.then(Timings::default);
+    let stats = Stats::default();
+    let options = telemetry::Options {
+        verbose: cli.verbose,
+        log_file: cli.log_file,
+        otlp_endpoint: cli.otlp_endpoint,
+    };
+    let telemetry = telemetry::init(options, timings.clone(), stats.clone())?;
+
+    if let Some(path) = &cli.messages {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let catalog =
+            Catalog::from_json(&json).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+        messages::install(catalog);
+    }
+
+    let result = run(cli.command, stats).await;
+    if let Some(timings) = timings {
+        eprint!("{}", timings.table());
+    }
+    telemetry.shutdown();
+    let status = result?;
+    if status != 0 {
+        std::process::exit(status);
+    }
+    Ok(())
+}
+
+/// Runs `command`, returning the status the process should exit with.
+async fn run(command: Commands, stats: Stats) -> anyhow::Result<i32> {
+    match command {
+        Commands::Build {
+            file,
+            output,
+            emit,
+            compile,
+        } => {
+            build::run_build(
+                &file,
+                output,
+                emit,
+                compile.into_options(),
+                std::io::stdout().lock(),
+            )?;
+        }
+        Commands::Check { files, compile } => {
+            check::run_check(&files, compile.into_options(), std::io::stdout().lock())?;
+        }
+        Commands::Run {
+            file,
+            mut args,
+            eval,
+            allow,
+            compile,
+            trace,
+        } => {
+            let capabilities = allow.into_iter().collect();
+            let tracer = trace.tracer()?;
+            let stdout = std::io::stdout().lock();
+            let Some(expr) = eval else {
+                let file = file.expect("clap requires a file without --eval");
+                return run::run_file(
+                    &file,
+                    &args,
+                    std::env::vars(),
+                    capabilities,
+                    compile.into_options(),
+                    tracer,
+                    stdout,
+                );
+            };
+            // Without a file, the first positional value is an argument too
+            if let Some(first) = file {
+                args.insert(0, first.display().to_string());
+            }
+            return run::run_eval(
+                &expr,
+                &args,
+                std::env::vars(),
+                capabilities,
+                compile.into_options(),
+                tracer,
+                stdout,
+            );
+        }
+        Commands::Test {
+            files,
+            coverage,
+            compile,
+        } => {
+            test::run_test(
+                &files,
+                coverage.as_deref(),
+                compile.into_options(),
+                std::io::stdout().lock(),
+            )?;
+        }
+        Commands::Examples { id, tag } => {
+            examples::run_examples(id.as_deref(), tag.as_deref(), std::io::stdout().lock())?;
+        }
+        Commands::Expand { file } => {
+            expand::run_expand(&file, std::io::stdout().lock())?;
+        }
+        Commands::Repl {
+            load,
+            allow,
+            compile,
+            trace,
+        } => {
+            repl::start_repl(
+                load,
+                allow.into_iter().collect(),
+                compile.into_options(),
+                trace.tracer()?,
+                trace.filter(),
+            )?;
         }
         Commands::Lsp => {
-            lsp::start_server().await?;
+            lsp::start_server(stats).await?;
         }
         Commands::Mcp => {
             mcp::start_server().await?;
         }
+        Commands::Completions { shell } => {
+            completions::run_completions(shell, std::io::stdout().lock())?;
+        }
+        Commands::Man { out_dir } => {
+            completions::run_man(out_dir.as_deref(), std::io::stdout().lock())?;
+        }
     }
 
-    Ok(())
+    Ok(0)
 }
@@ -2,16 +2,42 @@
 //!
 //! Provides an MCP server that allows LLMs to interact with the Cadenza language
 //! environment, enabling code evaluation, AST inspection, and documentation queries.
+//!
+//! It also offers prompt templates for common code-generation tasks, which
+//! embed the bundled [examples](cadenza_eval::examples) so the client writes
+//! idiomatic Cadenza; the `cadenza_examples` tool serves the same corpus.
+//!
+//! The `cadenza_build` tool runs the whole pipeline, as `cadenza build` does,
+//! and returns the WASM module, so agents can produce deployable artifacts.
+//!
+//! Each tool call runs inside a `request` span whose method is the tool's
+//! name (see [`stats::request_span`]), so its latency is logged and exported.
 
+use crate::stats;
 use anyhow::Result;
-use cadenza_eval::{Compiler, Env, Value};
+use base64::Engine as _;
+use cadenza_eval::{
+    CompileOptions, Compiler, Env, Value,
+    examples::Example,
+    ir::{BuildMetadata, binary_to_wat},
+    options::OptLevel,
+};
 use cadenza_syntax::{SyntaxNode, parse::parse};
 use rmcp::{
-    ErrorData as McpError, ServerHandler, ServiceExt, handler::server::router::tool::ToolRouter,
-    model::*, schemars, tool, tool_router,
+    ErrorData as McpError, ServerHandler, ServiceExt,
+    handler::server::{
+        router::{prompt::PromptRouter, tool::ToolRouter},
+        tool::ToolCallContext,
+        wrapper::Parameters,
+    },
+    model::*,
+    prompt, prompt_handler, prompt_router, schemars,
+    service::{RequestContext, RoleServer},
+    tool, tool_router,
 };
 use serde::Deserialize;
 use std::fmt::Write as _;
+use tracing::Instrument;
 
 /// MCP server for Cadenza language environment
 ///
@@ -19,6 +45,7 @@ use std::fmt::Write as _;
 #[derive(Clone)]
 pub struct CadenzaMcpServer {
     tool_router: ToolRouter<CadenzaMcpServer>,
+    prompt_router: PromptRouter<CadenzaMcpServer>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -45,11 +72,38 @@ struct GetDocsRequest {
     symbol: String,
 }
 
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct ExamplesRequest {
+    /// The id of a single example to return, such as `measures`; omit to get every example
+    id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct BuildRequest {
+    /// The Cadenza source to compile
+    source: String,
+    /// The optimization level (`0`, `1`, or `2`); defaults to `0`
+    opt_level: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct UnitSafeFunctionArgs {
+    /// What the function should compute, such as "travel time from a distance and a speed"
+    task: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct ConvertGcodeArgs {
+    /// The GCode program to convert
+    gcode: String,
+}
+
 #[tool_router]
 impl CadenzaMcpServer {
     pub fn new() -> Self {
         Self {
             tool_router: Self::tool_router(),
+            prompt_router: Self::prompt_router(),
         }
     }
 
@@ -268,14 +322,183 @@ impl CadenzaMcpServer {
         let about = include_str!("about_cadenza.md");
         Ok(CallToolResult::success(vec![Content::text(about)]))
     }
+
+    /// Get the bundled example programs
+    #[tool(
+        description = "Get the bundled Cadenza example programs, each with an id, title, description, and source. Pass an id to get one example. Use these as references for idiomatic Cadenza before writing code."
+    )]
+    async fn cadenza_examples(
+        &self,
+        Parameters(req): Parameters<ExamplesRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(id) = req.id else {
+            let all: Vec<String> = cadenza_eval::examples::all()
+                .iter()
+                .map(format_example)
+                .collect();
+            return Ok(CallToolResult::success(vec![Content::text(all.join("\n"))]));
+        };
+        match cadenza_eval::examples::get(&id) {
+            Some(example) => Ok(CallToolResult::success(vec![Content::text(
+                format_example(example),
+            )])),
+            None => {
+                let ids: Vec<&str> = cadenza_eval::examples::all()
+                    .iter()
+                    .map(|example| example.id)
+                    .collect();
+                Ok(CallToolResult::error(vec![Content::text(format!(
+                    "No example with id `{id}`. Available: {}",
+                    ids.join(", ")
+                ))]))
+            }
+        }
+    }
+
+    /// Compile Cadenza source to a WebAssembly module
+    #[tool(
+        description = "Compile Cadenza source to a WebAssembly module. Returns JSON with the module as base64 (`wasm`), its text format (`wat`), the build metadata (`metadata`), and any warnings (`diagnostics`). Builds are reproducible: the same source and options give the same bytes."
+    )]
+    async fn cadenza_build(
+        &self,
+        Parameters(req): Parameters<BuildRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let opt_level = match req.opt_level.as_deref().map(str::parse::<OptLevel>) {
+            None => OptLevel::default(),
+            Some(Ok(level)) => level,
+            Some(Err(e)) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let options = CompileOptions {
+            opt_level,
+            ..CompileOptions::default()
+        };
+
+        let compiled = match crate::build::compile_str("<source>", &req.source, options) {
+            Ok(compiled) => compiled,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e.to_string())])),
+        };
+        let wat = binary_to_wat(&compiled.wasm).map_err(|e| McpError::internal_error(e, None))?;
+        let metadata = BuildMetadata::read(&compiled.wasm)
+            .map_err(|e| McpError::internal_error(e, None))?
+            .map(|metadata| serde_json::from_str::<serde_json::Value>(&metadata.to_json()))
+            .transpose()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let output = serde_json::json!({
+            "wasm": base64::engine::general_purpose::STANDARD.encode(&compiled.wasm),
+            "wat": wat,
+            "metadata": metadata,
+            "diagnostics": compiled.warnings,
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            output.to_string(),
+        )]))
+    }
 }
 
-#[rmcp::tool_handler]
+#[prompt_router]
+impl CadenzaMcpServer {
+    /// Write a function whose inputs and result carry units of measure
+    #[prompt(
+        name = "write_unit_safe_function",
+        description = "Write a Cadenza function whose inputs and result carry units of measure, so dimension mistakes are caught."
+    )]
+    async fn write_unit_safe_function(
+        &self,
+        Parameters(args): Parameters<UnitSafeFunctionArgs>,
+    ) -> Vec<PromptMessage> {
+        let text = format!(
+            "Write a Cadenza function that computes: {task}\n\n\
+             Make it unit safe:\n\
+             - Declare each unit with `measure` (derived units as `measure kilometer = meter 1000`).\n\
+             - Write quantities as literals with a unit suffix, like `5kilometer` or `2.5second`.\n\
+             - Let the units flow through arithmetic instead of converting by hand; \
+             dividing `meter` by `second` gives a speed.\n\
+             - Add an `assert` showing an example call and its expected result.\n\n\
+             Run the code with the `eval` tool and check the result's dimension with \
+             `check_dimensions` before answering.\n\n\
+             For reference, these bundled examples show the syntax:\n\n{}\n{}",
+            format_example(example("functions")),
+            format_example(example("measures")),
+            task = args.task,
+        );
+        vec![PromptMessage::new_text(PromptMessageRole::User, text)]
+    }
+
+    /// Convert a GCode program to Cadenza
+    #[prompt(
+        name = "convert_gcode",
+        description = "Convert a GCode program into equivalent Cadenza, naming repeated values and giving distances and feed rates units."
+    )]
+    async fn convert_gcode(
+        &self,
+        Parameters(args): Parameters<ConvertGcodeArgs>,
+    ) -> Vec<PromptMessage> {
+        let text = format!(
+            "Convert this GCode program to Cadenza:\n\n```gcode\n{gcode}\n```\n\n\
+             Guidelines:\n\
+             - Keep one Cadenza call per GCode command, in the same order, so the result \
+             reads like the original; `G1 X100 Y50 F3000` becomes a call of `G1` with its \
+             parameters.\n\
+             - Give coordinates units (`millimeter`, or `inch` after `G20`) and feed rates \
+             units of distance per minute, declaring them with `measure`.\n\
+             - Name values that repeat, like a safe Z height or a feed rate, with `let`.\n\
+             - Turn repeated sequences of moves into functions.\n\
+             - Keep comments, using `#` instead of `;` or parentheses.\n\n\
+             Check the result parses with the `parse` tool.\n\n\
+             For reference, this bundled example shows units of measure:\n\n{}",
+            format_example(example("measures")),
+            gcode = args.gcode.trim_end(),
+        );
+        vec![PromptMessage::new_text(PromptMessageRole::User, text)]
+    }
+}
+
+/// Returns the bundled example with `id`, which the prompts rely on.
+fn example(id: &str) -> &'static Example {
+    cadenza_eval::examples::get(id)
+        .unwrap_or_else(|| panic!("the `{id}` example is bundled with cadenza-eval"))
+}
+
+/// Formats an example as Markdown: a heading with its title and id, its
+/// description, and its source.
+fn format_example(example: &Example) -> String {
+    format!(
+        "## {} (`{}`)\n\n{}\n\n```cadenza\n{}\n```\n",
+        example.title,
+        example.id,
+        example.description,
+        example.source.trim_end()
+    )
+}
+
+#[prompt_handler]
 impl ServerHandler for CadenzaMcpServer {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let span = stats::request_span(&request.name);
+        let call = ToolCallContext::new(self, request, context);
+        self.tool_router.call(call).instrument(span).await
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_prompts()
+                .build(),
             server_info: Implementation {
                 name: "cadenza-mcp-server".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
@@ -285,7 +508,8 @@ impl ServerHandler for CadenzaMcpServer {
             },
             instructions: Some(
                 "MCP server for the Cadenza programming language. Supports expression evaluation, \
-                 type inference, dimensional analysis, AST inspection, and documentation queries."
+                 type inference, dimensional analysis, AST inspection, and documentation queries. \
+                 Call `cadenza_examples` for reference programs before writing Cadenza."
                     .to_string(),
             ),
         }
@@ -326,3 +550,101 @@ pub async fn start_server() -> Result<()> {
     service.waiting().await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &[Content]) -> &str {
+        &content[0].as_text().expect("text content").text
+    }
+
+    #[tokio::test]
+    async fn serves_examples() {
+        let server = CadenzaMcpServer::new();
+
+        let all = server
+            .cadenza_examples(Parameters(ExamplesRequest { id: None }))
+            .await
+            .unwrap();
+        for example in cadenza_eval::examples::all() {
+            assert!(
+                text(&all.content).contains(&format!("## {} (`{}`)", example.title, example.id))
+            );
+        }
+
+        let one = server
+            .cadenza_examples(Parameters(ExamplesRequest {
+                id: Some("measures".to_string()),
+            }))
+            .await
+            .unwrap();
+        assert!(
+            text(&one.content)
+                .starts_with("## Units of Measure (`measures`)\n\nDefine and use physical units\n")
+        );
+
+        let missing = server
+            .cadenza_examples(Parameters(ExamplesRequest {
+                id: Some("nope".to_string()),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(missing.is_error, Some(true));
+        assert!(text(&missing.content).contains("measures"));
+    }
+
+    #[tokio::test]
+    async fn builds_wasm() {
+        let server = CadenzaMcpServer::new();
+        let build = |source: &str, opt_level: Option<&str>| {
+            server.cadenza_build(Parameters(BuildRequest {
+                source: source.to_string(),
+                opt_level: opt_level.map(str::to_string),
+            }))
+        };
+
+        let built = build("fn square x = x * x\n", Some("2")).await.unwrap();
+        assert_eq!(built.is_error, Some(false));
+        let json: serde_json::Value = serde_json::from_str(text(&built.content)).unwrap();
+        let wasm = base64::engine::general_purpose::STANDARD
+            .decode(json["wasm"].as_str().unwrap())
+            .unwrap();
+        assert!(wasm.starts_with(b"\0asm"));
+        assert!(json["wat"].as_str().unwrap().starts_with("(module"));
+        assert_eq!(json["metadata"]["options"]["opt_level"], "2");
+        assert_eq!(json["diagnostics"], serde_json::json!([]));
+
+        let failed = build("1 + y\n", None).await.unwrap();
+        assert_eq!(failed.is_error, Some(true));
+        assert!(text(&failed.content).contains("Failed to compile <source>"));
+
+        let bad_level = build("1\n", Some("9")).await.unwrap();
+        assert_eq!(bad_level.is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn prompts_embed_examples() {
+        let server = CadenzaMcpServer::new();
+        let prompts: Vec<String> = server
+            .prompt_router
+            .list_all()
+            .into_iter()
+            .map(|prompt| prompt.name)
+            .collect();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts.contains(&"write_unit_safe_function".to_string()));
+        assert!(prompts.contains(&"convert_gcode".to_string()));
+
+        let messages = server
+            .convert_gcode(Parameters(ConvertGcodeArgs {
+                gcode: "G28\nG1 X100 Y50 F3000\n".to_string(),
+            }))
+            .await;
+        let PromptMessageContent::Text { text } = &messages[0].content else {
+            panic!("expected text");
+        };
+        assert!(text.contains("```gcode\nG28\nG1 X100 Y50 F3000\n```"));
+        assert!(text.contains("measure kilometer = meter 1000"));
+    }
+}
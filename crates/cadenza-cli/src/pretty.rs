@@ -0,0 +1,398 @@
+//! Terminal rendering of REPL results.
+//!
+//! Results are laid out with the [rich display protocol](cadenza_eval::rich)
+//! and rendered here:
+//!
+//! - Lists of records print as aligned tables, with numbers right-aligned.
+//! - Long lists, records, tables, and strings are truncated; the
+//!   `:show-more` command prints the last truncated results in full.
+//! - Atoms are colored by kind when writing to a terminal, using the same
+//!   palette as the input highlighting.
+
+use cadenza_eval::{
+    Value,
+    rich::{AtomKind, Rich},
+};
+use std::io::{self, Write};
+
+/// The command that prints the last truncated results in full.
+pub const SHOW_MORE: &str = ":show-more";
+
+/// How much of a value is shown before truncating it.
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    /// The most items shown of a list or record, or rows of a table.
+    items: usize,
+    /// The most characters shown of a string or table cell.
+    text: usize,
+}
+
+const LIMITS: Limits = Limits {
+    items: 20,
+    text: 60,
+};
+
+/// Prints REPL results, remembering truncated ones for `:show-more`.
+pub struct Printer {
+    color: bool,
+    /// The last results, if printing them truncated anything.
+    truncated: Vec<Value>,
+}
+
+impl Printer {
+    /// Creates a printer, coloring its output if `color` is set.
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            truncated: Vec::new(),
+        }
+    }
+
+    /// Prints the results of evaluating one input.
+    pub fn print(&mut self, results: &[Value], output: &mut impl Write) -> io::Result<()> {
+        let mut renderer = Renderer {
+            color: self.color,
+            limits: Some(LIMITS),
+            truncated: false,
+        };
+        write_results(&mut renderer, results, output)?;
+
+        self.truncated.clear();
+        if renderer.truncated {
+            self.truncated = results.to_vec();
+            let hint = format!("… truncated; {SHOW_MORE} prints everything");
+            writeln!(output, "{}", renderer.paint(&hint, AtomKind::Nil))?;
+        }
+        Ok(())
+    }
+
+    /// Prints the last truncated results in full.
+    pub fn show_more(&mut self, output: &mut impl Write) -> io::Result<()> {
+        if self.truncated.is_empty() {
+            return writeln!(output, "Nothing more to show.");
+        }
+        let mut renderer = Renderer {
+            color: self.color,
+            limits: None,
+            truncated: false,
+        };
+        write_results(&mut renderer, &std::mem::take(&mut self.truncated), output)
+    }
+}
+
+fn write_results(
+    renderer: &mut Renderer,
+    results: &[Value],
+    output: &mut impl Write,
+) -> io::Result<()> {
+    for (i, result) in results.iter().enumerate() {
+        let rendered = renderer.block(&Rich::of(result));
+        if results.len() > 1 {
+            // Tables start on their own line, so their columns line up
+            let separator = if rendered.contains('\n') { "\n" } else { " " };
+            writeln!(output, "[{i}]{separator}{rendered}")?;
+        } else {
+            writeln!(output, "{rendered}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders one layout, noting whether anything was truncated.
+struct Renderer {
+    color: bool,
+    /// Where to truncate, or `None` to show everything.
+    limits: Option<Limits>,
+    truncated: bool,
+}
+
+impl Renderer {
+    /// Styles `text` as an atom of `kind`.
+    fn paint(&self, text: &str, kind: AtomKind) -> String {
+        let code = match kind {
+            AtomKind::Number => "33",
+            AtomKind::String => "32",
+            AtomKind::Bool | AtomKind::Symbol => "36",
+            AtomKind::Nil => "90",
+            AtomKind::Function => "35",
+            AtomKind::Type => "34",
+            AtomKind::Other => return text.to_string(),
+        };
+        if self.color {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Returns how many of `len` items to show.
+    fn shown(&mut self, len: usize) -> usize {
+        match self.limits {
+            Some(limits) if len > limits.items => {
+                self.truncated = true;
+                limits.items
+            }
+            _ => len,
+        }
+    }
+
+    /// Renders `rich` as a block: tables over several lines, anything else
+    /// inline.
+    fn block(&mut self, rich: &Rich) -> String {
+        match rich {
+            Rich::Table { columns, rows, .. } => self.table(columns, rows),
+            _ => self.inline(rich),
+        }
+    }
+
+    /// Renders `rich` on a single line.
+    fn inline(&mut self, rich: &Rich) -> String {
+        match rich {
+            Rich::Atom { text, kind } => self.atom(text, *kind),
+            Rich::Seq { name, tuple, items } => {
+                let mut rendered = self.join(items, Self::inline);
+                if !*tuple {
+                    return format!("[{rendered}]");
+                }
+                // Single-element tuples need a trailing comma
+                if items.len() == 1 {
+                    rendered.push(',');
+                }
+                format!("{}({rendered})", prefix(name))
+            }
+            Rich::Record { name, fields } => self.record(name, fields),
+            Rich::Table {
+                name,
+                columns,
+                rows,
+            } => {
+                let rendered = self.join(rows, |renderer, row| {
+                    let fields: Vec<(String, Rich)> =
+                        columns.iter().cloned().zip(row.iter().cloned()).collect();
+                    renderer.record(name, &fields)
+                });
+                format!("[{rendered}]")
+            }
+        }
+    }
+
+    fn atom(&mut self, text: &str, kind: AtomKind) -> String {
+        if kind != AtomKind::String {
+            return self.paint(text, kind);
+        }
+        let mut shown = text;
+        let mut ellipsis = "";
+        if let Some(limits) = self.limits
+            && let Some((end, _)) = text.char_indices().nth(limits.text)
+        {
+            shown = &text[..end];
+            ellipsis = "…";
+            self.truncated = true;
+        }
+        let escaped = shown
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+            .replace('\t', "\\t");
+        self.paint(&format!("\"{escaped}{ellipsis}\""), kind)
+    }
+
+    fn record(&mut self, name: &Option<String>, fields: &[(String, Rich)]) -> String {
+        let rendered = self.join(fields, |renderer, (name, value)| {
+            format!("{name}: {}", renderer.inline(value))
+        });
+        format!("{}{{{rendered}}}", prefix(name))
+    }
+
+    /// Renders and joins `items`, showing at most the item limit and noting
+    /// how many more there are.
+    fn join<T>(&mut self, items: &[T], render: impl Fn(&mut Self, &T) -> String) -> String {
+        let shown = self.shown(items.len());
+        let mut rendered: Vec<String> = items[..shown]
+            .iter()
+            .map(|item| render(self, item))
+            .collect();
+        if shown < items.len() {
+            let more = format!("… {} more", items.len() - shown);
+            rendered.push(self.paint(&more, AtomKind::Nil));
+        }
+        rendered.join(", ")
+    }
+
+    /// Renders a table with a header, a separator, and a row per record.
+    fn table(&mut self, columns: &[String], rows: &[Vec<Rich>]) -> String {
+        let shown = self.shown(rows.len());
+
+        // Each cell as plain text, for widths, and styled
+        let cells: Vec<Vec<Cell>> = rows[..shown]
+            .iter()
+            .map(|row| row.iter().map(|value| self.cell(value)).collect())
+            .collect();
+        let widths: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                cells
+                    .iter()
+                    .map(|row| row[i].width)
+                    .chain([column.chars().count()])
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        let mut lines = Vec::with_capacity(shown + 3);
+        let header: Vec<String> = columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, width)| {
+                let padding = " ".repeat(width - column.chars().count());
+                if self.color {
+                    format!("\x1b[1m{column}\x1b[0m{padding}")
+                } else {
+                    format!("{column}{padding}")
+                }
+            })
+            .collect();
+        lines.push(header.join("  "));
+        lines.push(
+            widths
+                .iter()
+                .map(|width| "-".repeat(*width))
+                .collect::<Vec<_>>()
+                .join("  "),
+        );
+        for row in cells {
+            let line: Vec<String> = row
+                .into_iter()
+                .zip(&widths)
+                .map(|(cell, width)| {
+                    let padding = " ".repeat(width - cell.width);
+                    if cell.right_align {
+                        format!("{padding}{}", cell.text)
+                    } else {
+                        format!("{}{padding}", cell.text)
+                    }
+                })
+                .collect();
+            lines.push(line.join("  "));
+        }
+        if shown < rows.len() {
+            let more = format!("… {} more rows", rows.len() - shown);
+            lines.push(self.paint(&more, AtomKind::Nil));
+        }
+
+        lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Renders a table cell, cutting it to the text limit.
+    fn cell(&mut self, value: &Rich) -> Cell {
+        let mut plain = Renderer {
+            color: false,
+            limits: self.limits,
+            truncated: false,
+        };
+        let text = plain.inline(value);
+        self.truncated |= plain.truncated;
+        let right_align = matches!(
+            value,
+            Rich::Atom {
+                kind: AtomKind::Number,
+                ..
+            }
+        );
+
+        if let Some(limits) = self.limits
+            && let Some((end, _)) = text.char_indices().nth(limits.text)
+        {
+            self.truncated = true;
+            return Cell {
+                text: format!("{}…", &text[..end]),
+                width: limits.text + 1,
+                right_align,
+            };
+        }
+        let width = text.chars().count();
+        let text = if self.color { self.inline(value) } else { text };
+        Cell {
+            text,
+            width,
+            right_align,
+        }
+    }
+}
+
+/// A rendered table cell.
+struct Cell {
+    text: String,
+    /// The width of the text on screen, without escape codes.
+    width: usize,
+    right_align: bool,
+}
+
+/// Returns the type-name prefix of a record or tuple.
+fn prefix(name: &Option<String>) -> String {
+    match name {
+        Some(name) => format!("{name} "),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(printer: &mut Printer, value: Value) -> String {
+        let mut output = Vec::new();
+        printer.print(&[value], &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn truncates_long_strings() {
+        let mut printer = Printer::new(false);
+        let long = "ab\"".repeat(30);
+        let printed = print(&mut printer, Value::String(long.clone().into()));
+        let shown = format!("\"{}…\"", long[..60].replace('"', "\\\""));
+        assert_eq!(
+            printed,
+            format!("{shown}\n… truncated; :show-more prints everything\n")
+        );
+
+        let mut output = Vec::new();
+        printer.show_more(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("\"{}\"\n", long.replace('"', "\\\""))
+        );
+    }
+
+    #[test]
+    fn colors_atoms_by_kind() {
+        let mut printer = Printer::new(true);
+        let value = Value::List(vec![
+            Value::Integer(1),
+            Value::String("a".into()),
+            Value::Nil,
+        ]);
+        assert_eq!(
+            print(&mut printer, value),
+            "[\x1b[33m1\x1b[0m, \x1b[32m\"a\"\x1b[0m, \x1b[90mnil\x1b[0m]\n"
+        );
+
+        // Table cells are padded by their width on screen
+        let row = |n: i64| Value::Record {
+            type_name: None,
+            fields: vec![("n".into(), Value::Integer(n))],
+        };
+        assert_eq!(
+            print(&mut printer, Value::List(vec![row(1), row(10)])),
+            "\x1b[1mn\x1b[0m\n--\n \x1b[33m1\x1b[0m\n\x1b[33m10\x1b[0m\n"
+        );
+    }
+}
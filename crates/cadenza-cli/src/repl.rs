@@ -3,11 +3,26 @@
 //! Provides an interactive environment with:
 //! - Command history (saved to ~/.cadenza_history)
 //! - Syntax highlighting
-//! - Auto-completion for identifiers
+//! - Auto-completion for builtins and the names defined so far
 //! - Option to load files into scope
-
+//! - `:time` and `:memory` commands that evaluate an expression and report
+//!   its wall-clock time or allocations, and its evaluation steps
+//! - Lists of records printed as tables, long values truncated (`:show-more`
+//!   prints them in full), and results colored by type; see [`crate::pretty`]
+//! - `:trace on` and `:trace off` to start and stop logging each evaluated
+//!   expression to standard error; see [`cadenza_eval::trace`]
+
+use crate::{
+    pretty::{Printer, SHOW_MORE},
+    usage::{Meter, Report},
+};
 use anyhow::Result;
-use cadenza_eval::{Compiler, Env, Value};
+use cadenza_eval::{
+    CompileOptions, Compiler, Env,
+    symbol_index::SymbolSearch,
+    system::Capabilities,
+    trace::{TraceFilter, Tracer},
+};
 use cadenza_syntax::{lexer::Lexer, parse::parse, token::Kind};
 use rustyline::{
     Context, Editor, Helper,
@@ -19,16 +34,71 @@ use rustyline::{
 };
 use std::{
     borrow::Cow,
-    io::{BufRead, Write},
+    io::{BufRead, IsTerminal, Write},
     path::PathBuf,
 };
 
+/// A REPL command, entered in place of an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command<'a> {
+    /// `:show-more`: print the last truncated results in full
+    ShowMore,
+    /// `:time expr` or `:memory expr`: evaluate `expr` and report on it
+    Measure(Report, &'a str),
+    /// `:trace on` or `:trace off`: start or stop tracing evaluation
+    Trace(bool),
+}
+
+impl<'a> Command<'a> {
+    /// Parses a command, or returns `None` if `line` isn't one.
+    fn parse(line: &'a str) -> Option<Self> {
+        if line == SHOW_MORE {
+            return Some(Command::ShowMore);
+        }
+        let (name, expr) = line.split_once(char::is_whitespace)?;
+        if name == ":trace" {
+            return match expr.trim() {
+                "on" => Some(Command::Trace(true)),
+                "off" => Some(Command::Trace(false)),
+                _ => None,
+            };
+        }
+        let report = match name {
+            ":time" => Report::Time,
+            ":memory" => Report::Memory,
+            _ => return None,
+        };
+        Some(Command::Measure(report, expr.trim()))
+    }
+}
+
 /// REPL helper that provides completion and syntax highlighting
-struct CadenzaHelper;
+struct CadenzaHelper {
+    /// The definitions from the loaded file and the session so far.
+    symbols: SymbolSearch,
+    /// The lines entered so far that evaluated without errors.
+    session: String,
+}
+
+/// The name of the session's own lines in the symbol index.
+const SESSION_FILE: &str = "<repl>";
+
+/// The most user-defined completions offered at once.
+const MAX_COMPLETIONS: usize = 64;
 
 impl CadenzaHelper {
     fn new() -> Self {
-        Self
+        Self {
+            symbols: SymbolSearch::new(),
+            session: String::new(),
+        }
+    }
+
+    /// Records `line` as evaluated, so its definitions complete.
+    fn define(&mut self, line: &str) {
+        self.session.push_str(line);
+        self.session.push('\n');
+        self.symbols.set_file(SESSION_FILE, &self.session);
     }
 }
 
@@ -61,8 +131,70 @@ impl Completer for CadenzaHelper {
         // Note: Env doesn't expose a way to iterate over bindings,
         // so we'll provide a basic set of built-in names
         let builtins = [
-            "let", "fn", "=", "match", "assert", "typeof", "measure", "+", "-", "*", "/", "==",
-            "!=", "<", "<=", ">", ">=", "|>",
+            "let",
+            "fn",
+            "=",
+            "match",
+            "assert",
+            "typeof",
+            "measure",
+            "+",
+            "-",
+            "*",
+            "/",
+            "==",
+            "!=",
+            "<",
+            "<=",
+            ">",
+            ">=",
+            "|>",
+            "spawn",
+            "import",
+            "await",
+            "channel",
+            "send",
+            "recv",
+            "close",
+            "read_file",
+            "write_file",
+            "exec",
+            "env_var",
+            "http_get",
+            "http_post",
+            "json_decode",
+            "len",
+            "nth",
+            "push",
+            "concat",
+            "to_string",
+            "split",
+            "contains",
+            "to_upper",
+            "parse_int",
+            "hash",
+            "identity",
+            "constant",
+            "compose",
+            "flip",
+            "sqrt",
+            "abs",
+            "floor",
+            "ceil",
+            "round",
+            "min",
+            "max",
+            "clamp",
+            "is_empty",
+            "fold",
+            "map",
+            "filter",
+            "head",
+            "tail",
+            "length",
+            "sum",
+            "reverse",
+            "range",
         ];
 
         for builtin in &builtins {
@@ -74,6 +206,17 @@ impl Completer for CadenzaHelper {
             }
         }
 
+        // Then the names defined in the loaded file and the session
+        for found in self.symbols.search(word, MAX_COMPLETIONS) {
+            let name = found.symbol.name;
+            if name.starts_with(word) && !candidates.iter().any(|pair| pair.replacement == name) {
+                candidates.push(Pair {
+                    display: name.clone(),
+                    replacement: name,
+                });
+            }
+        }
+
         Ok((start, candidates))
     }
 }
@@ -108,11 +251,13 @@ impl Highlighter for CadenzaHelper {
             // Add the token with color based on its kind
             let text = &line[start..end];
             let colored = match token.kind {
-                Kind::Integer | Kind::Float => format!("\x1b[33m{}\x1b[0m", text), // Yellow
+                Kind::Integer | Kind::Float | Kind::Decimal => format!("\x1b[33m{}\x1b[0m", text), // Yellow
                 Kind::StringStart
                 | Kind::StringContent
                 | Kind::StringContentWithEscape
-                | Kind::StringEnd => {
+                | Kind::StringEnd
+                | Kind::InterpolationStart
+                | Kind::InterpolationEnd => {
                     format!("\x1b[32m{}\x1b[0m", text) // Green
                 }
                 Kind::Identifier => {
@@ -211,6 +356,7 @@ pub fn run_repl<R: BufRead, W: Write, E: Write>(
     }
 
     // REPL loop - read lines from input
+    let mut printer = Printer::new(false);
     let mut line = String::new();
     loop {
         line.clear();
@@ -226,9 +372,21 @@ pub fn run_repl<R: BufRead, W: Write, E: Write>(
         if line_trimmed.is_empty() {
             continue;
         }
+        let (source, report) = match Command::parse(line_trimmed) {
+            Some(Command::ShowMore) => {
+                printer.show_more(&mut output)?;
+                continue;
+            }
+            Some(Command::Measure(report, expr)) => (expr, Some(report)),
+            Some(Command::Trace(on)) => {
+                compiler.set_tracer(on.then(|| Tracer::stderr(TraceFilter::default())));
+                continue;
+            }
+            None => (line_trimmed, None),
+        };
 
         // Parse and evaluate
-        let parsed = parse(line_trimmed);
+        let parsed = parse(source);
 
         if !parsed.errors.is_empty() {
             writeln!(error, "Parse errors:")?;
@@ -238,7 +396,9 @@ pub fn run_repl<R: BufRead, W: Write, E: Write>(
             continue;
         }
 
+        let meter = Meter::start(&compiler);
         let results = cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+        let usage = meter.finish(&compiler);
 
         if compiler.has_errors() {
             writeln!(error, "Evaluation errors:")?;
@@ -249,13 +409,9 @@ pub fn run_repl<R: BufRead, W: Write, E: Write>(
             continue;
         }
 
-        // Print results
-        for (i, result) in results.iter().enumerate() {
-            if results.len() > 1 {
-                writeln!(output, "[{}] {}", i, format_value(result))?;
-            } else {
-                writeln!(output, "{}", format_value(result))?;
-            }
+        printer.print(&results, &mut output)?;
+        if let Some(report) = report {
+            writeln!(output, "{}", report.describe(usage))?;
         }
     }
 
@@ -263,14 +419,29 @@ pub fn run_repl<R: BufRead, W: Write, E: Write>(
 }
 
 /// Start the Cadenza REPL with interactive readline support
-pub fn start_repl(load_file: Option<PathBuf>) -> Result<()> {
+///
+/// Scripts may only access the host through the granted `capabilities`. The
+/// standard prelude is loaded unless `prelude` is false.
+///
+/// Evaluation is traced from the start with `tracer`, and `:trace on` traces
+/// to standard error with `trace_filter`.
+pub fn start_repl(
+    load_file: Option<PathBuf>,
+    capabilities: Capabilities,
+    options: CompileOptions,
+    tracer: Option<Tracer>,
+    trace_filter: TraceFilter,
+) -> Result<()> {
     println!("Cadenza REPL v{}", env!("CARGO_PKG_VERSION"));
     println!("Type expressions to evaluate. Press Ctrl+D or Ctrl+C to exit.");
     println!();
 
     // Initialize environment and compiler
-    let mut env = Env::with_standard_builtins();
-    let mut compiler = Compiler::new();
+    let mut env = Env::with_options(&options);
+    let mut compiler = Compiler::with_options(options);
+    compiler.set_capabilities(capabilities);
+    compiler.set_tracer(tracer);
+    let mut helper = CadenzaHelper::new();
 
     // Load file if specified
     if let Some(path) = load_file {
@@ -296,11 +467,13 @@ pub fn start_repl(load_file: Option<PathBuf>) -> Result<()> {
             return Err(anyhow::anyhow!("Failed to evaluate {}", path.display()));
         }
 
+        helper
+            .symbols
+            .set_file(&path.display().to_string(), &source);
         println!("Loaded successfully.\n");
     }
 
     // Create readline editor with helper
-    let helper = CadenzaHelper::new();
     let mut rl = Editor::new()?;
     rl.set_helper(Some(helper));
 
@@ -315,6 +488,7 @@ pub fn start_repl(load_file: Option<PathBuf>) -> Result<()> {
     let _ = rl.load_history(&history_path);
 
     // REPL loop
+    let mut printer = Printer::new(std::io::stdout().is_terminal());
     loop {
         match rl.readline("cadenza> ") {
             Ok(line) => {
@@ -326,8 +500,28 @@ pub fn start_repl(load_file: Option<PathBuf>) -> Result<()> {
                 // Add to history
                 let _ = rl.add_history_entry(line);
 
+                let (source, report) = match Command::parse(line) {
+                    Some(Command::ShowMore) => {
+                        printer.show_more(&mut std::io::stdout())?;
+                        continue;
+                    }
+                    Some(Command::Measure(report, expr)) => (expr, Some(report)),
+                    Some(Command::Trace(false)) => {
+                        compiler.set_tracer(None);
+                        continue;
+                    }
+                    Some(Command::Trace(true)) => {
+                        // Keep tracing to where the flags sent the trace
+                        if compiler.tracer().is_none() {
+                            compiler.set_tracer(Some(Tracer::stderr(trace_filter.clone())));
+                        }
+                        continue;
+                    }
+                    None => (line, None),
+                };
+
                 // Parse and evaluate
-                let parsed = parse(line);
+                let parsed = parse(source);
 
                 if !parsed.errors.is_empty() {
                     eprintln!("Parse errors:");
@@ -337,8 +531,18 @@ pub fn start_repl(load_file: Option<PathBuf>) -> Result<()> {
                     continue;
                 }
 
+                compiler.refuel();
+                let meter = Meter::start(&compiler);
                 let results = cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+                let usage = meter.finish(&compiler);
 
+                // `exit` and `abort` end the session
+                if compiler.exit_status().is_some() {
+                    for diagnostic in compiler.diagnostics() {
+                        eprintln!("  {}", diagnostic);
+                    }
+                    break;
+                }
                 if compiler.has_errors() {
                     eprintln!("Evaluation errors:");
                     for diagnostic in compiler.diagnostics() {
@@ -347,14 +551,13 @@ pub fn start_repl(load_file: Option<PathBuf>) -> Result<()> {
                     compiler.clear_diagnostics();
                     continue;
                 }
+                if let Some(helper) = rl.helper_mut() {
+                    helper.define(source);
+                }
 
-                // Print results
-                for (i, result) in results.iter().enumerate() {
-                    if results.len() > 1 {
-                        println!("[{}] {}", i, format_value(result));
-                    } else {
-                        println!("{}", format_value(result));
-                    }
+                printer.print(&results, &mut std::io::stdout())?;
+                if let Some(report) = report {
+                    println!("{}", report.describe(usage));
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -378,77 +581,36 @@ pub fn start_repl(load_file: Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-/// Format a value for display in the REPL
-fn format_value(value: &Value) -> String {
-    match value {
-        Value::Nil => "nil".to_string(),
-        Value::Integer(n) => n.to_string(),
-        Value::Float(f) => f.to_string(),
-        Value::String(s) => {
-            // Properly escape string contents
-            let escaped = s
-                .replace('\\', "\\\\")
-                .replace('"', "\\\"")
-                .replace('\n', "\\n")
-                .replace('\r', "\\r")
-                .replace('\t', "\\t");
-            format!("\"{}\"", escaped)
-        }
-        Value::Bool(b) => b.to_string(),
-        Value::Symbol(s) => format!(":{}", s),
-        Value::List(items) => {
-            let items_str = items
-                .iter()
-                .map(format_value)
-                .collect::<Vec<_>>()
-                .join(", ");
-            format!("[{}]", items_str)
-        }
-        Value::Tuple {
-            type_name,
-            elements,
-        } => {
-            let (type_name, tn_space) = if let Some(name) = type_name {
-                (&name[..], " ")
-            } else {
-                ("", "")
-            };
-            let elements_str = elements
-                .iter()
-                .map(format_value)
-                .collect::<Vec<_>>()
-                .join(", ");
-            // Single-element tuples need trailing comma
-            let trailing_comma = if elements.len() == 1 { "," } else { "" };
-            format!("{type_name}{tn_space}({}{trailing_comma})", elements_str)
-        }
-        Value::Record { type_name, fields } => {
-            let (type_name, tn_space) = if let Some(name) = type_name {
-                (&name[..], " ")
-            } else {
-                ("", "")
-            };
-            let fields_str = fields
-                .iter()
-                .map(|(k, v)| format!("{}: {}", k, format_value(v)))
-                .collect::<Vec<_>>()
-                .join(", ");
-            format!("{type_name}{tn_space}{{{}}}", fields_str)
-        }
-        Value::StructConstructor { name, field_types } => {
-            let fields_str = field_types
-                .iter()
-                .map(|(k, v)| format!("{}: {}", k, v))
-                .collect::<Vec<_>>()
-                .join(", ");
-            format!("{} {{{}}}", name, fields_str)
-        }
-        Value::UserFunction(f) => format!("<function {}>", f.name),
-        Value::BuiltinFn(f) => format!("<builtin {}>", f.name),
-        Value::BuiltinMacro(_) => "<macro>".to_string(),
-        Value::SpecialForm(_) => "<special-form>".to_string(),
-        Value::Type(t) => format!("<type {}>", t),
-        Value::Quantity { value, unit, .. } => format!("{} {:?}", value, unit),
-        Value::UnitConstructor(unit) => format!("<unit {:?}>", unit),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse(":show-more"), Some(Command::ShowMore));
+        assert_eq!(
+            Command::parse(":time  sum (range 0 10)"),
+            Some(Command::Measure(Report::Time, "sum (range 0 10)"))
+        );
+        assert_eq!(
+            Command::parse(":memory [1, 2]"),
+            Some(Command::Measure(Report::Memory, "[1, 2]"))
+        );
+        assert_eq!(Command::parse(":trace on"), Some(Command::Trace(true)));
+        assert_eq!(Command::parse(":trace  off"), Some(Command::Trace(false)));
+        assert_eq!(Command::parse(":trace maybe"), None);
+        assert_eq!(Command::parse(":time"), None);
+        assert_eq!(Command::parse("time 1"), None);
+    }
+
+    #[test]
+    fn reports_usage_after_the_result() {
+        let output = crate::testing::repl(":time sum (range 0 10)\n:memory [1, 2]\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[3], "45");
+        assert!(lines[4].starts_with("time: "), "{output}");
+        assert!(lines[4].ends_with(" steps"), "{output}");
+        assert_eq!(lines[5], "[1, 2]");
+        assert!(lines[6].starts_with("memory: "), "{output}");
     }
 }
@@ -0,0 +1,385 @@
+//! The `run` command: evaluate a program and call its `main` function.
+//!
+//! The file is evaluated as a one-module [`ModuleGraph`], so its top-level
+//! expressions run first. If it defines `main`, that is called with the
+//! program's arguments as a list of strings and, if it takes a second
+//! parameter, the environment variables as a record:
+//!
+//! ```cadenza
+//! fn main args env = args
+//! ```
+//!
+//! The environment is only visible with the `process` capability; without it,
+//! `main` gets an empty record. A non-nil result of `main` is printed. A file
+//! without `main` prints the value of its last top-level expression instead.
+//!
+//! With the `process` capability, the program can stop early with `exit code`
+//! or `abort message`, and the process exits with its status.
+//!
+//! `--trace-eval` logs each expression the program evaluates to standard
+//! error, or to the `--trace-file`; see [`cadenza_eval::trace`].
+//!
+//! `cadenza run -e EXPR` runs `EXPR` as the program instead of a file, for
+//! one-liners; with the `stdio` capability it can filter standard input:
+//!
+//! ```bash
+//! cadenza run --allow stdio -e 'write (to_upper (read_all ())?) |?'
+//! ```
+
+use crate::{
+    crash::{self, Source},
+    frontends,
+};
+use anyhow::Result;
+use cadenza_eval::{
+    CompileOptions, Compiler, Diagnostic, Env, EvalContext, InternedString, Value, apply_value,
+    module_graph::ModuleGraph,
+    system::{Capabilities, Capability},
+    trace::Tracer,
+};
+use std::{cell::Cell, io::Write, path::Path};
+
+/// What a run left behind.
+struct Run {
+    /// The compiler, holding the evaluation diagnostics.
+    compiler: Compiler,
+    /// The result of `main`, or of the last top-level expression if there is
+    /// no `main`.
+    result: Result<Value, Diagnostic>,
+}
+
+/// Runs the file at `path` with `args`, writing diagnostics and the result to
+/// `stdout`, and returns the exit status: 0, or the status the program
+/// stopped with by calling `exit` or `abort`.
+///
+/// Scripts may only access the host through the granted `capabilities`, and
+/// `vars` are passed to `main` only if `process` is granted. Fails if `main`
+/// fails or any diagnostic is an error. A compiler panic is caught and
+/// reported with a minimized reproduction (see [`crash`]).
+pub fn run_file<W: Write>(
+    path: &Path,
+    args: &[String],
+    vars: impl IntoIterator<Item = (String, String)>,
+    capabilities: Capabilities,
+    options: CompileOptions,
+    tracer: Option<Tracer>,
+    stdout: W,
+) -> Result<i32> {
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow::anyhow!("invalid module path: {}", path.display()))?;
+    let source = Source::new(name, std::fs::read_to_string(path)?);
+    let source = source.with_frontend(frontends::for_path(path));
+    run_program(source, args, vars, capabilities, options, tracer, stdout)
+}
+
+/// Runs the program `expr`, given on the command line, as [`run_file`] runs
+/// a file.
+pub fn run_eval<W: Write>(
+    expr: &str,
+    args: &[String],
+    vars: impl IntoIterator<Item = (String, String)>,
+    capabilities: Capabilities,
+    options: CompileOptions,
+    tracer: Option<Tracer>,
+    stdout: W,
+) -> Result<i32> {
+    let source = Source::new("eval", expr);
+    run_program(source, args, vars, capabilities, options, tracer, stdout)
+}
+
+fn run_program<W: Write>(
+    source: Source,
+    args: &[String],
+    vars: impl IntoIterator<Item = (String, String)>,
+    capabilities: Capabilities,
+    options: CompileOptions,
+    tracer: Option<Tracer>,
+    mut stdout: W,
+) -> Result<i32> {
+    let sources = vec![source];
+
+    let mut vars: Vec<(String, String)> = if capabilities.allows(Capability::Process) {
+        vars.into_iter().collect()
+    } else {
+        Vec::new()
+    };
+    vars.sort();
+
+    // Only the first run is traced, not reruns minimizing a crash
+    let tracer = Cell::new(tracer);
+    let run = crash::guard(sources, |sources| {
+        let options = options.clone();
+        run(
+            &sources[0],
+            args,
+            &vars,
+            capabilities,
+            options,
+            tracer.take(),
+        )
+    })
+    .map_err(|crash| crash::report(&crash))?;
+
+    for diagnostic in run.compiler.diagnostics() {
+        writeln!(stdout, "{diagnostic}")?;
+    }
+    if let Some(status) = run.compiler.exit_status() {
+        return Ok(status);
+    }
+    let errors = run
+        .compiler
+        .diagnostics()
+        .iter()
+        .filter(|diagnostic| diagnostic.is_error())
+        .count();
+    if errors > 0 {
+        return Err(anyhow::anyhow!("run failed with {errors} error(s)"));
+    }
+
+    match run.result {
+        Ok(Value::Nil) => {}
+        Ok(value) => writeln!(stdout, "{value}")?,
+        Err(diagnostic) => return Err(anyhow::anyhow!("main failed: {diagnostic}")),
+    }
+    Ok(0)
+}
+
+/// Evaluates `source`, then calls its `main` function if it defines one.
+fn run(
+    source: &Source,
+    args: &[String],
+    vars: &[(String, String)],
+    capabilities: Capabilities,
+    options: CompileOptions,
+    tracer: Option<Tracer>,
+) -> Run {
+    let mut graph = ModuleGraph::new();
+    graph.add_with(
+        source.name.as_str(),
+        source.text.as_str(),
+        source.frontend.clone(),
+    );
+
+    let mut env = Env::with_options(&options);
+    let mut compiler = Compiler::with_options(options);
+    compiler.set_capabilities(capabilities);
+    compiler.set_tracer(tracer);
+    let results = graph.eval(&mut env, &mut compiler);
+
+    let main = InternedString::new("main");
+    let function = compiler.get_var(main).cloned();
+    let Some(function) = function.filter(|_| compiler.exit_status().is_none()) else {
+        let last = results
+            .into_iter()
+            .flat_map(|(_, values)| values)
+            .next_back()
+            .unwrap_or(Value::Nil);
+        return Run {
+            compiler,
+            result: Ok(last),
+        };
+    };
+
+    let result = main_args(&function, args, vars)
+        .and_then(|main_args| {
+            compiler.refuel();
+            let mut ctx = EvalContext::new(&mut env, &mut compiler);
+            apply_value(function, main_args, &mut ctx)
+        })
+        .map_err(|diagnostic| *diagnostic);
+    // Stopping the program is recorded, not a failure of `main`
+    let result = match result {
+        Err(diagnostic) if diagnostic.stops_program() => {
+            compiler.record_diagnostic(diagnostic);
+            Ok(Value::Nil)
+        }
+        result => result,
+    };
+    Run { compiler, result }
+}
+
+/// Returns the arguments `main` takes: none, the program arguments, or the
+/// program arguments and the environment variables.
+fn main_args(
+    main: &Value,
+    args: &[String],
+    vars: &[(String, String)],
+) -> Result<Vec<Value>, Box<Diagnostic>> {
+    let Value::UserFunction(function) = main else {
+        return Err(Diagnostic::syntax(format!(
+            "main must be a function, but it is {}",
+            main.type_of()
+        )));
+    };
+    let args = Value::List(
+        args.iter()
+            .map(|arg| Value::String(arg.clone().into()))
+            .collect(),
+    );
+    let vars = Value::Record {
+        type_name: None,
+        fields: vars
+            .iter()
+            .map(|(name, value)| {
+                (
+                    InternedString::new(name),
+                    Value::String(value.clone().into()),
+                )
+            })
+            .collect(),
+    };
+    match function.params.len() {
+        0 => Ok(vec![]),
+        1 => Ok(vec![args]),
+        2 => Ok(vec![args, vars]),
+        n => Err(Diagnostic::syntax(format!(
+            "main takes the arguments and the environment, but it has {n} parameters"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadenza_eval::trace::TraceFilter;
+    use std::path::PathBuf;
+
+    fn source_file(name: &str, source: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("cadenza-run-{}-{name}", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    fn run_source(
+        name: &str,
+        source: &str,
+        args: &[&str],
+        capabilities: Capabilities,
+    ) -> (Result<i32>, String) {
+        let path = source_file(name, source);
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let vars = [("GREETING".to_string(), "hello".to_string())];
+        let mut stdout = Vec::new();
+        let result = run_file(
+            &path,
+            &args,
+            vars,
+            capabilities,
+            CompileOptions::default(),
+            None,
+            &mut stdout,
+        );
+        std::fs::remove_file(path).unwrap();
+        (result, String::from_utf8(stdout).unwrap())
+    }
+
+    #[test]
+    fn calls_main_with_the_arguments() {
+        let (result, output) = run_source(
+            "args.cdz",
+            "fn main args = args",
+            &["a", "b"],
+            Capabilities::none(),
+        );
+        result.unwrap();
+        assert_eq!(output, "[a, b]\n");
+    }
+
+    #[test]
+    fn passes_the_environment_with_the_process_capability() {
+        let source = "fn main args env = env";
+        let (result, output) = run_source("env.cdz", source, &[], Capabilities::none());
+        result.unwrap();
+        assert_eq!(output, "{}\n");
+
+        let granted = [Capability::Process].into_iter().collect();
+        let (result, output) = run_source("env.cdz", source, &[], granted);
+        result.unwrap();
+        assert!(output.contains("GREETING = hello"), "{output}");
+    }
+
+    #[test]
+    fn without_main_prints_the_last_value() {
+        let (result, output) = run_source(
+            "script.cdz",
+            "let x = 20\nx + 22",
+            &[],
+            Capabilities::none(),
+        );
+        result.unwrap();
+        assert_eq!(output, "42\n");
+    }
+
+    #[test]
+    fn exit_stops_with_its_status() {
+        let granted = [Capability::Process].into_iter().collect();
+        let source = "fn main args = if (len args) == 0 then exit 2 else args";
+        let (result, output) = run_source("exit.cdz", source, &[], granted);
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(output, "");
+
+        let source = "abort \"no input\"\nfn main args = args";
+        let (result, output) = run_source("abort.cdz", source, &["a"], granted);
+        assert_eq!(result.unwrap(), Compiler::ABORT_STATUS);
+        assert!(output.contains("aborted: no input"), "{output}");
+    }
+
+    #[test]
+    fn eval_runs_an_expression() {
+        let args = ["a".to_string()];
+        let mut stdout = Vec::new();
+        let result = run_eval(
+            "fn main args = len args",
+            &args,
+            [],
+            Capabilities::none(),
+            CompileOptions::default(),
+            None,
+            &mut stdout,
+        );
+        assert_eq!(result.unwrap(), 0);
+        assert_eq!(String::from_utf8(stdout).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn traces_evaluated_expressions() {
+        let path = source_file("trace.log", "");
+        let filter = TraceFilter {
+            pattern: Some("args".to_string()),
+            max_depth: None,
+        };
+        let tracer = Tracer::new(filter, std::fs::File::create(&path).unwrap());
+        let mut stdout = Vec::new();
+        let result = run_eval(
+            "fn main args = len args",
+            &["a".to_string()],
+            [],
+            Capabilities::none(),
+            CompileOptions::default(),
+            Some(tracer),
+            &mut stdout,
+        );
+        assert_eq!(result.unwrap(), 0);
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            trace,
+            "1:1 [depth 1] fn main args = len args => nil\n\
+             1:20 [depth 2] args => [a]\n\
+             1:16 [depth 2] len args => 1\n"
+        );
+    }
+
+    #[test]
+    fn main_failure_is_an_error() {
+        let (result, _) = run_source(
+            "fail.cdz",
+            "fn main args = assert false",
+            &[],
+            Capabilities::none(),
+        );
+        assert!(result.unwrap_err().to_string().starts_with("main failed"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Server statistics, for the language server's `cadenza/stats` request.
+//!
+//! The language and MCP servers handle each request inside a [`tracing`] span
+//! named `request`, whose `method` field names the request (see
+//! [`request_span`]). [`Stats`] is a tracing layer that counts the requests
+//! of each method and how long they took, from when the span was created to
+//! when it closed, so the time spent waiting for a lock counts as latency.
+//!
+//! A [`Report`] combines the request latencies with the process's heap usage
+//! (see [`usage::memory`]) and the cache hit rate of the Salsa queries behind
+//! workspace symbol search.
+
+use crate::usage::{self, Memory};
+use cadenza_eval::db::QueryStats;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{
+    Span, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id},
+};
+use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
+
+/// The name of the span around each server request.
+pub const REQUEST_SPAN: &str = "request";
+
+/// Returns a span for handling a request for `method`.
+pub fn request_span(method: &str) -> Span {
+    tracing::info_span!(REQUEST_SPAN, method)
+}
+
+/// A tracing layer that records the latency of each request method.
+#[derive(Clone, Default)]
+pub struct Stats {
+    methods: Arc<Mutex<BTreeMap<String, Latency>>>,
+}
+
+/// The latencies of the requests for one method.
+#[derive(Debug, Clone, Copy, Default)]
+struct Latency {
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+/// A request in progress, stored in the extensions of its span.
+struct Request {
+    method: String,
+    started: Instant,
+}
+
+/// Finds the `method` field of a span.
+#[derive(Default)]
+struct MethodVisitor(Option<String>);
+
+impl Visit for MethodVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "method" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "method" {
+            self.0 = Some(value.to_string());
+        }
+    }
+}
+
+/// A snapshot of the server's statistics.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Latencies by request method.
+    pub requests: BTreeMap<String, Requests>,
+    /// Heap usage.
+    pub memory: Memory,
+    /// How the Salsa queries were answered.
+    pub queries: Queries,
+}
+
+/// The latencies of the finished requests for one method.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Requests {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Salsa query counts (see [`QueryStats`]).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Queries {
+    pub executed: u64,
+    pub reused: u64,
+    /// The fraction of answers that reused a memoized result.
+    pub hit_rate: Option<f64>,
+}
+
+impl From<QueryStats> for Queries {
+    fn from(stats: QueryStats) -> Self {
+        Self {
+            executed: stats.executed,
+            reused: stats.reused,
+            hit_rate: stats.hit_rate(),
+        }
+    }
+}
+
+impl Stats {
+    /// Returns the latencies recorded so far, with the current heap usage and
+    /// `queries`.
+    pub fn report(&self, queries: QueryStats) -> Report {
+        let methods = self.methods.lock().unwrap();
+        let requests = methods
+            .iter()
+            .map(|(method, latency)| {
+                let requests = Requests {
+                    count: latency.count,
+                    mean_ms: millis(latency.total) / latency.count as f64,
+                    max_ms: millis(latency.max),
+                };
+                (method.clone(), requests)
+            })
+            .collect();
+        Report {
+            requests,
+            memory: usage::memory(),
+            queries: queries.into(),
+        }
+    }
+
+    fn record(&self, method: String, time: Duration) {
+        let mut methods = self.methods.lock().unwrap();
+        let latency = methods.entry(method).or_default();
+        latency.count += 1;
+        latency.total += time;
+        latency.max = latency.max.max(time);
+    }
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+impl<S> Layer<S> for Stats
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != REQUEST_SPAN {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = MethodVisitor::default();
+        attrs.record(&mut visitor);
+        if let Some(method) = visitor.0 {
+            span.extensions_mut().insert(Request {
+                method,
+                started: Instant::now(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id)
+            && let Some(request) = span.extensions_mut().remove::<Request>()
+        {
+            self.record(request.method, request.started.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn records_request_latencies() {
+        let stats = Stats::default();
+        let subscriber = tracing_subscriber::registry().with(stats.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..2 {
+                let _request = request_span("textDocument/hover").entered();
+                let _stage = tracing::info_span!("parse").entered();
+            }
+            request_span("cadenza/stats").in_scope(|| {
+                std::thread::sleep(Duration::from_millis(2));
+            });
+        });
+
+        let report = stats.report(QueryStats {
+            executed: 3,
+            reused: 1,
+        });
+        let methods: Vec<_> = report.requests.keys().map(String::as_str).collect();
+        assert_eq!(methods, ["cadenza/stats", "textDocument/hover"]);
+        assert_eq!(report.requests["textDocument/hover"].count, 2);
+        assert!(report.requests["cadenza/stats"].max_ms >= 2.0);
+        assert_eq!(report.queries.hit_rate, Some(0.25));
+        assert!(report.memory.peak_bytes >= report.memory.live_bytes);
+    }
+}
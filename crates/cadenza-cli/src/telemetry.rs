@@ -0,0 +1,125 @@
+//! Logging and trace export.
+//!
+//! Logs go to stderr so they never mix with command output; `--verbose` logs
+//! the compiler's pipeline spans as they close. Long-running servers can also
+//! be debugged in the field:
+//!
+//! - `--log-file PATH` appends the logs to a file as JSON lines, one object per
+//!   event with the spans it happened in. Each span is logged as it closes,
+//!   with how long it was busy and idle, so the `request` span around each
+//!   server request (see [`stats::request_span`]) records its latency.
+//! - `--otlp-endpoint URL` exports the spans to an OpenTelemetry collector over
+//!   OTLP/HTTP. The URL is used as is, so it names the traces path, such as
+//!   `http://localhost:4318/v1/traces`.
+//!
+//! The [`Timings`] and [`Stats`] layers see every span, whatever the log
+//! level.
+
+use crate::{stats::Stats, timings::Timings};
+use anyhow::{Context as _, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use std::{fs::OpenOptions, path::PathBuf, sync::Mutex};
+use tracing_subscriber::{
+    EnvFilter, Layer, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+/// Where and how much to log.
+pub struct Options {
+    /// Log the compiler's pipeline stages at `debug`.
+    pub verbose: bool,
+    /// Also log to this file, as JSON lines.
+    pub log_file: Option<PathBuf>,
+    /// Export spans to this OTLP/HTTP endpoint.
+    pub otlp_endpoint: Option<String>,
+}
+
+/// The installed exporters, flushed by [`Telemetry::shutdown`].
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+/// Installs the global tracing subscriber for `options`, with the `timings`
+/// and `stats` layers.
+pub fn init(options: Options, timings: Option<Timings>, stats: Stats) -> Result<Telemetry> {
+    let directives = if options.verbose {
+        "info,cadenza=debug,cadenza_eval=debug"
+    } else {
+        "info"
+    };
+    let filter =
+        || EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(directives));
+    let span_events = if options.verbose {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    };
+
+    let log_file = match &options.log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open log file {}", path.display()))?;
+            let layer = tracing_subscriber::fmt::layer()
+                .json()
+                .with_span_list(true)
+                .with_span_events(FmtSpan::CLOSE)
+                .with_writer(Mutex::new(file))
+                .with_filter(filter());
+            Some(layer)
+        }
+        None => None,
+    };
+
+    let provider = match &options.otlp_endpoint {
+        Some(endpoint) => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint)
+                .build()
+                .context("failed to create the OTLP exporter")?;
+            let resource = Resource::builder().with_service_name("cadenza").build();
+            Some(
+                SdkTracerProvider::builder()
+                    .with_batch_exporter(exporter)
+                    .with_resource(resource)
+                    .build(),
+            )
+        }
+        None => None,
+    };
+    let otlp = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("cadenza"))
+            .with_filter(filter())
+    });
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_span_events(span_events)
+                .with_filter(filter()),
+        )
+        .with(log_file)
+        .with(otlp)
+        .with(timings)
+        .with(stats)
+        .init();
+
+    Ok(Telemetry { provider })
+}
+
+impl Telemetry {
+    /// Exports any spans still buffered.
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider
+            && let Err(error) = provider.shutdown()
+        {
+            eprintln!("warning: failed to export traces: {error}");
+        }
+    }
+}
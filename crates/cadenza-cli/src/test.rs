@@ -0,0 +1,251 @@
+//! The `test` command: run the tests in Cadenza files.
+//!
+//! The files form one [`ModuleGraph`], as in `check`, so top-level `assert`s
+//! run as the files are evaluated. Then each function marked `@test` is
+//! called with no arguments, and passes unless it fails:
+//!
+//! ```cadenza
+//! @test
+//! fn adds_numbers = assert 1 + 2 == 3
+//! ```
+//!
+//! With `--coverage DIR`, the evaluator counts which expressions run (see
+//! [`cadenza_eval::coverage`]), and the report is written to `DIR` as an lcov
+//! tracefile (`lcov.info`) and HTML pages (`index.html`, plus one page per
+//! file).
+
+use crate::{
+    crash::{self, Source},
+    frontends,
+};
+use anyhow::Result;
+use cadenza_eval::{
+    CompileOptions, Compiler, Diagnostic, Env, EvalContext, InternedString, apply_value,
+    coverage::{self, FileReport},
+    module_graph::ModuleGraph,
+};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// The outcome of running the tests.
+struct Run {
+    /// The compiler, holding the evaluation diagnostics and the coverage.
+    compiler: Compiler,
+    /// Each test function, with the diagnostic it failed with.
+    tests: Vec<(InternedString, Option<Diagnostic>)>,
+}
+
+/// Runs the tests in the files at `paths` with `options`, writing the results
+/// to `stdout` and, if `coverage` is set, the coverage report to that
+/// directory.
+///
+/// Fails if any test fails or any diagnostic is an error. A compiler panic is
+/// caught and reported with a minimized reproduction (see [`crash`]).
+pub fn run_test<W: Write>(
+    paths: &[PathBuf],
+    coverage: Option<&Path>,
+    options: CompileOptions,
+    mut stdout: W,
+) -> Result<()> {
+    let mut sources = Vec::with_capacity(paths.len());
+    for path in paths {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid module path: {}", path.display()))?;
+        let source = Source::new(name, std::fs::read_to_string(path)?);
+        sources.push(source.with_frontend(frontends::for_path(path)));
+    }
+
+    let with_coverage = coverage.is_some();
+    let run = crash::guard(sources, |sources| {
+        run(sources, options.clone(), with_coverage)
+    })
+    .map_err(|crash| crash::report(&crash))?;
+
+    for diagnostic in run.compiler.diagnostics() {
+        writeln!(stdout, "{diagnostic}")?;
+    }
+
+    writeln!(stdout, "running {} tests", run.tests.len())?;
+    for (name, failure) in &run.tests {
+        let status = if failure.is_some() { "FAILED" } else { "ok" };
+        writeln!(stdout, "test {name} ... {status}")?;
+    }
+    let failures: Vec<_> = run
+        .tests
+        .iter()
+        .filter_map(|(name, failure)| Some((name, failure.as_ref()?)))
+        .collect();
+    if !failures.is_empty() {
+        writeln!(stdout, "\nfailures:")?;
+        for (name, diagnostic) in &failures {
+            writeln!(stdout, "    {name}: {diagnostic}")?;
+        }
+    }
+    let passed = run.tests.len() - failures.len();
+    let status = if failures.is_empty() { "ok" } else { "FAILED" };
+    writeln!(
+        stdout,
+        "\ntest result: {status}. {passed} passed; {} failed",
+        failures.len()
+    )?;
+
+    if let Some(dir) = coverage
+        && let Some(counts) = run.compiler.coverage()
+    {
+        let reports = counts.report();
+        write_coverage(dir, paths, &reports)?;
+        for report in &reports {
+            writeln!(
+                stdout,
+                "coverage: {}: {}/{} lines, {}/{} functions",
+                report.name,
+                report.lines_hit(),
+                report.lines.len(),
+                report.functions_hit(),
+                report.functions.len()
+            )?;
+        }
+    }
+
+    let errors = run
+        .compiler
+        .diagnostics()
+        .iter()
+        .filter(|diagnostic| diagnostic.is_error())
+        .count();
+    if errors > 0 {
+        return Err(anyhow::anyhow!("tests failed with {errors} error(s)"));
+    }
+    if !failures.is_empty() {
+        return Err(anyhow::anyhow!("{} test(s) failed", failures.len()));
+    }
+    Ok(())
+}
+
+/// Evaluates `sources` as one module graph, then calls each `@test` function.
+fn run(sources: &[Source], options: CompileOptions, coverage: bool) -> Run {
+    let mut graph = ModuleGraph::new();
+    for source in sources {
+        graph.add_with(
+            source.name.as_str(),
+            source.text.as_str(),
+            source.frontend.clone(),
+        );
+    }
+
+    let mut env = Env::with_options(&options);
+    let mut compiler = Compiler::with_options(options);
+    if coverage {
+        compiler.enable_coverage();
+    }
+    graph.eval(&mut env, &mut compiler);
+
+    let mut tests = Vec::new();
+    for name in compiler.tests().to_vec() {
+        let Some(function) = compiler.get_var(name).cloned() else {
+            continue;
+        };
+        compiler.refuel();
+        let mut ctx = EvalContext::new(&mut env, &mut compiler);
+        let failure = apply_value(function, Vec::new(), &mut ctx).err();
+        tests.push((name, failure.map(|diagnostic| *diagnostic)));
+    }
+
+    Run { compiler, tests }
+}
+
+/// Writes `reports` to `dir` as `lcov.info`, `index.html`, and a page per
+/// file, naming each file by its path in `paths`.
+fn write_coverage(dir: &Path, paths: &[PathBuf], reports: &[FileReport]) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut lcov = String::new();
+    for report in reports {
+        let path = paths
+            .iter()
+            .find(|path| path.file_stem().and_then(|stem| stem.to_str()) == Some(&report.name))
+            .map_or_else(
+                || report.name.to_string(),
+                |path| path.display().to_string(),
+            );
+        lcov.push_str(&report.lcov(&path));
+        std::fs::write(dir.join(format!("{}.html", report.name)), report.html())?;
+    }
+    std::fs::write(dir.join("lcov.info"), lcov)?;
+    std::fs::write(dir.join("index.html"), coverage::html_index(reports))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cadenza-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn runs_tests_with_coverage() {
+        let dir = temp_dir();
+        let lib = dir.join("shapes.cdz");
+        std::fs::write(
+            &lib,
+            "fn area w h = w * h\n\
+             fn perimeter w h = 2 * (w + h)\n\
+             @test\n\
+             fn area_of_square = assert (area 2 2) == 4\n\
+             @test\n\
+             fn area_of_line = assert (area 0 2) == 1\n",
+        )
+        .unwrap();
+
+        let report = dir.join("coverage");
+        let mut stdout = Vec::new();
+        let error = run_test(
+            std::slice::from_ref(&lib),
+            Some(&report),
+            CompileOptions::default(),
+            &mut stdout,
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "1 test(s) failed");
+
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.contains("running 2 tests\n"), "{output}");
+        assert!(output.contains("test area_of_square ... ok\n"), "{output}");
+        assert!(
+            output.contains("test area_of_line ... FAILED\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("test result: FAILED. 1 passed; 1 failed"),
+            "{output}"
+        );
+
+        let lcov = std::fs::read_to_string(report.join("lcov.info")).unwrap();
+        assert!(lcov.contains(&format!("SF:{}\n", lib.display())), "{lcov}");
+        assert!(lcov.contains("FNDA:0,perimeter\n"), "{lcov}");
+        assert!(lcov.contains("FNH:3\n"), "{lcov}");
+        assert!(report.join("index.html").exists());
+        assert!(report.join("shapes.html").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn prelude_tests_pass() {
+        let prelude =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../cadenza-eval/src/prelude_test.cdz");
+        let mut stdout = Vec::new();
+        let result = run_test(&[prelude], None, CompileOptions::default(), &mut stdout);
+
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(result.is_ok(), "{output}");
+        assert!(output.contains("test result: ok."), "{output}");
+    }
+}
@@ -0,0 +1,258 @@
+//! Per-stage timings for `--timings`.
+//!
+//! The compiler runs each pipeline stage in a [`tracing`] span named after it,
+//! inside a `module` span whose `file` field names the file being compiled
+//! (see the `cadenza_eval` crate docs). [`Timings`] is a tracing layer that
+//! adds up the time spent in each stage, per file, and renders the totals as
+//! a table.
+//!
+//! Stages nest (type inference runs during lowering, which runs during
+//! evaluation), so each stage is charged only its own time: the time spent in
+//! a nested stage is subtracted from the stage around it. The columns of a row
+//! add up to the time spent on that file.
+
+use std::{
+    fmt::{self, Write as _},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{
+    Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id},
+};
+use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
+
+/// The timed stages, in pipeline order.
+pub const STAGES: [&str; 7] = [
+    "parse", "expand", "eval", "infer", "lower", "optimize", "codegen",
+];
+
+/// The time spent in each of the [`STAGES`].
+type StageTimes = [Duration; STAGES.len()];
+
+/// A tracing layer that records how long each file spends in each stage.
+#[derive(Clone, Default)]
+pub struct Timings {
+    /// Time per stage for each file, in the order the files were first seen.
+    files: Arc<Mutex<Vec<(String, StageTimes)>>>,
+}
+
+/// Timing state stored in the extensions of each span.
+struct SpanTiming {
+    /// The index of the stage in [`STAGES`], if the span is a stage.
+    stage: Option<usize>,
+    /// The file the span belongs to.
+    file: Option<String>,
+    /// When the span was last entered.
+    entered: Option<Instant>,
+    /// Time spent inside the span.
+    busy: Duration,
+    /// Time spent inside nested stages.
+    nested: Duration,
+}
+
+/// Finds the `file` field of a span.
+#[derive(Default)]
+struct FileVisitor(Option<String>);
+
+impl Visit for FileVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "file" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "file" {
+            self.0 = Some(value.to_string());
+        }
+    }
+}
+
+impl Timings {
+    /// Returns the recorded times as a table with a row per file, a column
+    /// per stage, and a total row and column.
+    pub fn table(&self) -> String {
+        let files = self.files.lock().unwrap();
+        let mut rows: Vec<(String, Vec<Duration>)> = files
+            .iter()
+            .map(|(file, times)| (file.clone(), times.to_vec()))
+            .collect();
+        let totals = (0..STAGES.len())
+            .map(|stage| files.iter().map(|(_, times)| times[stage]).sum())
+            .collect();
+        rows.push(("total".to_string(), totals));
+
+        let width = rows
+            .iter()
+            .map(|(file, _)| file.len())
+            .max()
+            .unwrap_or_default()
+            .max("file".len());
+
+        let mut table = format!("{:width$}", "file");
+        for stage in STAGES.iter().chain(["total"].iter()) {
+            write!(table, " {stage:>9}").unwrap();
+        }
+        table.push('\n');
+        for (file, times) in rows {
+            write!(table, "{file:width$}").unwrap();
+            let total: Duration = times.iter().sum();
+            for time in times.iter().chain([total].iter()) {
+                write!(table, " {:>9}", format_duration(*time)).unwrap();
+            }
+            table.push('\n');
+        }
+        table
+    }
+
+    fn record(&self, file: &str, stage: usize, time: Duration) {
+        let mut files = self.files.lock().unwrap();
+        let index = match files.iter().position(|(name, _)| name == file) {
+            Some(index) => index,
+            None => {
+                files.push((file.to_string(), Default::default()));
+                files.len() - 1
+            }
+        };
+        files[index].1[stage] += time;
+    }
+}
+
+/// Formats `time` in milliseconds with three significant decimals.
+fn format_duration(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs_f64() * 1000.0)
+}
+
+impl<S> Layer<S> for Timings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = FileVisitor::default();
+        attrs.record(&mut visitor);
+        let file = visitor.0.or_else(|| {
+            span.parent().and_then(|parent| {
+                parent
+                    .extensions()
+                    .get::<SpanTiming>()
+                    .and_then(|timing| timing.file.clone())
+            })
+        });
+        span.extensions_mut().insert(SpanTiming {
+            stage: STAGES
+                .iter()
+                .position(|stage| *stage == attrs.metadata().name()),
+            file,
+            entered: None,
+            busy: Duration::ZERO,
+            nested: Duration::ZERO,
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>()
+        {
+            timing.entered = Some(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>()
+            && let Some(entered) = timing.entered.take()
+        {
+            timing.busy += entered.elapsed();
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+
+        // Time in this span that isn't already charged to a nested stage
+        let unclaimed = timing.busy.saturating_sub(timing.nested);
+        if let (Some(stage), Some(file)) = (timing.stage, &timing.file) {
+            self.record(file, stage, unclaimed);
+        }
+
+        // Charge the nearest enclosing stage with the time this one took, so it
+        // isn't counted twice
+        let claimed = if timing.stage.is_some() {
+            timing.busy
+        } else {
+            timing.nested
+        };
+        if let Some(parent) = span.parent()
+            && let Some(parent_timing) = parent.extensions_mut().get_mut::<SpanTiming>()
+        {
+            parent_timing.nested += claimed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn charges_each_stage_its_own_time() {
+        let timings = Timings::default();
+        let subscriber = tracing_subscriber::registry().with(timings.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            for file in ["main", "lib"] {
+                let _module = tracing::info_span!("module", file).entered();
+                tracing::debug_span!("parse").in_scope(|| {
+                    std::thread::sleep(Duration::from_millis(2));
+                });
+                tracing::debug_span!("eval").in_scope(|| {
+                    std::thread::sleep(Duration::from_millis(2));
+                    tracing::debug_span!("lower").in_scope(|| {
+                        tracing::debug_span!("infer").in_scope(|| {
+                            std::thread::sleep(Duration::from_millis(20));
+                        });
+                    });
+                });
+            }
+        });
+
+        let files = timings.files.lock().unwrap();
+        let names: Vec<&str> = files.iter().map(|(file, _)| file.as_str()).collect();
+        assert_eq!(names, vec!["main", "lib"]);
+
+        let stage = |name| STAGES.iter().position(|stage| *stage == name).unwrap();
+        let times = &files[0].1;
+        assert!(times[stage("parse")] >= Duration::from_millis(2));
+        assert!(times[stage("infer")] >= Duration::from_millis(20));
+        // The inference time is charged to `infer` alone
+        assert!(times[stage("eval")] < Duration::from_millis(20));
+        assert!(times[stage("lower")] < Duration::from_millis(20));
+        assert_eq!(times[stage("codegen")], Duration::ZERO);
+    }
+
+    #[test]
+    fn table_has_a_row_per_file_and_a_total() {
+        let timings = Timings::default();
+        timings.record("main", 0, Duration::from_millis(1));
+        timings.record("main", 6, Duration::from_millis(2));
+
+        let table = timings.table();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("file "));
+        assert!(lines[0].ends_with("codegen     total"));
+        assert!(lines[1].starts_with("main "));
+        assert!(lines[1].ends_with("2.000ms   3.000ms"));
+        assert!(lines[2].starts_with("total"));
+    }
+}
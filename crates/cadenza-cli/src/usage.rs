@@ -0,0 +1,230 @@
+//! Resource usage of an evaluation, for the REPL's `:time` and `:memory`.
+//!
+//! The CLI's global allocator counts every allocation, so a [`Meter`] can
+//! report how many allocations an evaluation made and how many bytes they
+//! asked for, alongside the wall-clock time and the evaluation steps the
+//! compiler took (see [`Compiler::steps`]).
+//!
+//! The allocation counts are process-wide, so they include anything other
+//! threads allocate at the same time. The REPL evaluates on one thread, so in
+//! practice they are the evaluation's own.
+//!
+//! The allocator also tracks the bytes currently live and their high-water
+//! mark, which the language server reports in its stats (see [`memory`]).
+
+use cadenza_eval::Compiler;
+use serde::Serialize;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// The system allocator, counting allocations.
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+static PEAK_BYTES: AtomicU64 = AtomicU64::new(0);
+
+impl Counting {
+    fn record(size: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    /// Adds `size` bytes to the live bytes, raising the peak if needed.
+    fn grow(size: usize) {
+        let live = LIVE_BYTES.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+        PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn shrink(size: usize) {
+        LIVE_BYTES.fetch_sub(size as u64, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::record(layout.size());
+        Self::grow(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::record(layout.size());
+        Self::grow(layout.size());
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::record(new_size);
+        Self::shrink(layout.size());
+        Self::grow(new_size);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Self::shrink(layout.size());
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// The resources used by an evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    /// Wall-clock time.
+    pub elapsed: Duration,
+    /// Evaluation steps.
+    pub steps: u64,
+    /// Allocations, counting each reallocation as one.
+    pub allocations: u64,
+    /// Bytes requested by the allocations.
+    pub bytes: u64,
+}
+
+/// Measures the resources used between [`Meter::start`] and
+/// [`Meter::finish`].
+pub struct Meter {
+    start: Instant,
+    steps: u64,
+    allocations: u64,
+    bytes: u64,
+}
+
+impl Meter {
+    /// Starts measuring an evaluation by `compiler`.
+    pub fn start(compiler: &Compiler) -> Self {
+        Self {
+            steps: compiler.steps(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+            // Last, so reading the counters isn't timed
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the resources used since [`Meter::start`].
+    pub fn finish(self, compiler: &Compiler) -> Usage {
+        let elapsed = self.start.elapsed();
+        Usage {
+            elapsed,
+            steps: compiler.steps() - self.steps,
+            allocations: ALLOCATIONS.load(Ordering::Relaxed) - self.allocations,
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - self.bytes,
+        }
+    }
+}
+
+/// The process's heap usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Memory {
+    /// Bytes currently allocated.
+    pub live_bytes: u64,
+    /// The most bytes allocated at once.
+    pub peak_bytes: u64,
+    /// Allocations made, counting each reallocation as one.
+    pub allocations: u64,
+}
+
+/// Returns the process's current heap usage.
+pub fn memory() -> Memory {
+    Memory {
+        live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// What a REPL command reports about an evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Report {
+    /// Wall-clock time and steps, for `:time`
+    Time,
+    /// Allocations and steps, for `:memory`
+    Memory,
+}
+
+impl Report {
+    /// Describes `usage` for this report.
+    pub fn describe(self, usage: Usage) -> String {
+        let steps = plural(usage.steps, "step");
+        match self {
+            Report::Time => format!("time: {:.3?}, {steps}", usage.elapsed),
+            Report::Memory => format!(
+                "memory: {} ({}), {steps}",
+                plural(usage.allocations, "allocation"),
+                plural(usage.bytes, "byte")
+            ),
+        }
+    }
+}
+
+fn plural(count: u64, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_allocations_and_steps() {
+        let mut env = cadenza_eval::Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let meter = Meter::start(&compiler);
+        let boxed = std::hint::black_box(vec![0u8; 100]);
+        let usage = meter.finish(&compiler);
+        drop(boxed);
+        assert!(usage.allocations >= 1);
+        assert!(usage.bytes >= 100);
+        assert_eq!(usage.steps, 0);
+
+        let run = |compiler: &mut Compiler, env: &mut cadenza_eval::Env, n| {
+            let meter = Meter::start(compiler);
+            let source = format!("sum (range 0 {n})\n");
+            cadenza_eval::eval(&cadenza_syntax::parse::parse(&source).ast(), env, compiler);
+            meter.finish(compiler).steps
+        };
+        let few = run(&mut compiler, &mut env, 2);
+        let many = run(&mut compiler, &mut env, 20);
+        assert!(few > 0);
+        assert!(many > few);
+    }
+
+    #[test]
+    fn tracks_peak_memory() {
+        let before = memory();
+        let boxed = std::hint::black_box(vec![0u8; 1 << 20]);
+        let during = memory();
+        drop(boxed);
+        let after = memory();
+        assert!(during.peak_bytes >= before.live_bytes + (1 << 20));
+        assert!(after.peak_bytes >= during.peak_bytes);
+        assert!(after.peak_bytes >= after.live_bytes);
+    }
+
+    #[test]
+    fn describes_usage() {
+        let usage = Usage {
+            elapsed: Duration::from_micros(1500),
+            steps: 1,
+            allocations: 12,
+            bytes: 480,
+        };
+        assert_eq!(Report::Time.describe(usage), "time: 1.500ms, 1 step");
+        assert_eq!(
+            Report::Memory.describe(usage),
+            "memory: 12 allocations (480 bytes), 1 step"
+        );
+    }
+}
@@ -0,0 +1,486 @@
+//! Building the CST both parsers share.
+//!
+//! JSON and TOML documents lower to the same nodes Cadenza's own parser
+//! builds for record and list literals, so an imported document and the
+//! literal written out by hand evaluate and type-check the same way. Every
+//! byte of the source ends up in a token, so spans point back into the
+//! document.
+
+use cadenza_syntax::{
+    parse::{Parse, ParseError},
+    span::Span,
+    token::Kind,
+};
+use cadenza_tree::GreenNodeBuilder;
+use std::ops::Range;
+
+/// How deeply arrays and tables may nest before the rest of the document is
+/// given up on, which keeps a hostile document from overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+/// The escapes a kind of string allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Escapes {
+    /// No escapes: the text is taken as written.
+    Raw,
+    /// JSON strings, which also allow `\/`.
+    Json,
+    /// TOML basic strings.
+    Toml,
+}
+
+/// What becomes of an escape a Cadenza string can't hold as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Escape {
+    /// The backslash is dropped, along with the line ending and whitespace
+    /// of a TOML line continuation.
+    Dropped,
+    /// The escape is kept and decoded by `__escape__`.
+    Decoded,
+    /// The escape is malformed.
+    Invalid(String),
+}
+
+/// A key of a record field, as written.
+#[derive(Debug, Clone)]
+pub(crate) struct Key {
+    /// The key's text, with its quotes.
+    pub range: Range<usize>,
+    /// The name the key gives, without its quotes.
+    pub name: Range<usize>,
+}
+
+pub(crate) struct Emitter<'src> {
+    pub src: &'src str,
+    pub pos: usize,
+    builder: GreenNodeBuilder,
+    errors: Vec<ParseError>,
+    depth: usize,
+}
+
+impl<'src> Emitter<'src> {
+    pub fn new(src: &'src str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            builder: GreenNodeBuilder::new(),
+            errors: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn finish(self) -> Parse {
+        Parse {
+            green: self.builder.finish(),
+            errors: self.errors,
+        }
+    }
+
+    pub fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    pub fn rest(&self) -> &'src str {
+        &self.src[self.pos..]
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    pub fn start(&mut self, kind: Kind) {
+        self.builder.start_node(kind.into());
+    }
+
+    pub fn finish_node(&mut self) {
+        self.builder.finish_node();
+    }
+
+    /// Emits the next `len` bytes as a token.
+    pub fn token(&mut self, kind: Kind, len: usize) {
+        let end = self.pos + len;
+        self.builder.token(kind.into(), &self.src[self.pos..end]);
+        self.pos = end;
+    }
+
+    /// Emits the text up to `end` as a token, if there is any.
+    pub fn token_to(&mut self, kind: Kind, end: usize) {
+        if end > self.pos {
+            self.token(kind, end - self.pos);
+        }
+    }
+
+    pub fn error(&mut self, message: impl Into<String>, range: Range<usize>) {
+        self.errors.push(ParseError {
+            span: Span::new(range.start, range.end),
+            message: message.into(),
+        });
+    }
+
+    /// Reports `message` for the text up to `end` and emits it in an error
+    /// node, so the parser can carry on after it.
+    pub fn error_to(&mut self, message: impl Into<String>, end: usize) {
+        let start = self.pos;
+        self.start(Kind::Error);
+        self.token_to(Kind::StringContent, end);
+        self.finish_node();
+        self.error(message, start..end.max(start));
+    }
+
+    /// Reports `message` for the next character, and skips it.
+    pub fn error_char(&mut self, message: impl Into<String>) {
+        let len = self.rest().chars().next().map_or(0, char::len_utf8);
+        self.error_to(message, self.pos + len);
+    }
+
+    /// Reports an error at the end of the input, where something was
+    /// expected.
+    pub fn error_here(&mut self, message: impl Into<String>) {
+        self.error(message, self.pos..self.pos);
+    }
+
+    /// Emits spaces and tabs, and line endings if `newlines` is set.
+    pub fn whitespace(&mut self, newlines: bool) {
+        loop {
+            let rest = self.rest().as_bytes();
+            let spaces = rest
+                .iter()
+                .take_while(|byte| matches!(byte, b' ' | b'\t'))
+                .count();
+            if spaces > 0 {
+                self.token(Kind::Space, spaces);
+                continue;
+            }
+            let line_endings = rest
+                .iter()
+                .take_while(|byte| matches!(byte, b'\n' | b'\r'))
+                .count();
+            if newlines && line_endings > 0 {
+                self.token(Kind::Newline, line_endings);
+                continue;
+            }
+            break;
+        }
+    }
+
+    /// Enters an array or table, or returns `false` and gives up on the rest
+    /// of the document if it is nested too deeply.
+    pub fn enter(&mut self) -> bool {
+        if self.depth >= MAX_DEPTH {
+            self.error_to("the document is nested too deeply", self.src.len());
+            return false;
+        }
+        self.depth += 1;
+        true
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Starts a record or list literal, as `{ ... }` or `[ ... ]` starts one
+    /// in Cadenza.
+    pub fn start_literal(&mut self, synthetic: Kind) {
+        self.start(Kind::Apply);
+        self.start(Kind::ApplyReceiver);
+        self.start(synthetic);
+        self.finish_node();
+        self.finish_node();
+    }
+
+    /// Emits the next `len` bytes as a number literal.
+    pub fn number(&mut self, kind: Kind, len: usize) {
+        self.start(Kind::Literal);
+        self.start(kind);
+        self.token(kind, len);
+        self.finish_node();
+        self.finish_node();
+    }
+
+    /// Emits the next `len` bytes, such as `true`, as an identifier.
+    pub fn identifier(&mut self, len: usize) {
+        self.start(Kind::Identifier);
+        self.token(Kind::Identifier, len);
+        self.finish_node();
+    }
+
+    /// Emits the next `len` bytes, such as `null`, as `()`, which is nil.
+    pub fn nil(&mut self, len: usize) {
+        self.start_literal(Kind::SyntheticTuple);
+        self.token(Kind::Identifier, len);
+        self.finish_node();
+    }
+
+    /// Emits the next `len` bytes as a string literal without quotes, such
+    /// as a TOML date.
+    pub fn bare_string(&mut self, len: usize) {
+        self.start(Kind::Literal);
+        self.content(Kind::StringContent, self.pos + len);
+        self.finish_node();
+    }
+
+    /// Emits the key `key` as the name of a field: an identifier, between
+    /// its quotes if it has them.
+    pub fn key(&mut self, key: &Key) {
+        let quote = self.src.as_bytes()[key.range.start];
+        if quote == b'"' && self.src[key.name.clone()].contains('\\') {
+            self.error("escapes aren't supported in keys", key.name.clone());
+        }
+        let (open, close) = if quote == b'\'' {
+            (Kind::SingleQuote, Kind::SingleQuote)
+        } else {
+            (Kind::StringStart, Kind::StringEnd)
+        };
+        self.start(Kind::ApplyArgument);
+        self.token_to(open, key.name.start);
+        self.start(Kind::Identifier);
+        self.token_to(Kind::Identifier, key.name.end);
+        self.finish_node();
+        self.token_to(close, key.range.end);
+        self.finish_node();
+    }
+
+    /// Emits a string: its opening quotes, the text in `content`, and its
+    /// closing quotes up to `end`.
+    ///
+    /// Escapes Cadenza strings share (`\"`, `\\`, `\n`, `\r` and `\t`) are
+    /// kept in the text. Any other escape splits the string into an
+    /// interpolation of the text around it, so the escape can be dropped:
+    /// the backslash of `\/`, or the backslash and line ending of a TOML
+    /// line continuation. Escapes Cadenza spells differently (`\b`, `\f`,
+    /// `\u00e9` and TOML's `\U0001F600`) become a part of their own, kept as
+    /// written under a synthetic `__escape__` that decodes them, the same
+    /// characters as Cadenza's `\x08`, `\x0C` and `\u{...}`. Malformed
+    /// escapes are errors.
+    pub fn string(&mut self, content: Range<usize>, end: usize, escapes: Escapes) {
+        let open = if self.peek() == Some(b'\'') {
+            Kind::SingleQuote
+        } else {
+            Kind::StringStart
+        };
+        let close = if open == Kind::SingleQuote {
+            Kind::SingleQuote
+        } else {
+            Kind::StringEnd
+        };
+        let dropped = self.dropped_escapes(content.clone(), escapes);
+
+        if dropped.is_empty() {
+            self.start(Kind::Literal);
+            self.token_to(open, content.start);
+            self.first_line_ending(content.start);
+            self.content(self.content_kind(escapes), content.end);
+            self.token_to(close, end);
+            self.finish_node();
+            return;
+        }
+
+        self.start(Kind::Apply);
+        self.token_to(open, content.start);
+        self.start(Kind::ApplyReceiver);
+        self.start(Kind::SyntheticInterpolation);
+        self.finish_node();
+        self.finish_node();
+        self.first_line_ending(content.start);
+        for (range, escape) in dropped {
+            self.string_part(escapes, range.start);
+            match escape {
+                Escape::Dropped => {
+                    self.token(Kind::Backslash, 1);
+                    self.whitespace(true);
+                }
+                Escape::Decoded => self.decoded_escape(range.end),
+                Escape::Invalid(message) => self.error_to(message, range.end),
+            }
+        }
+        self.string_part(escapes, content.end);
+        self.token_to(close, end);
+        self.finish_node();
+    }
+
+    /// Emits the escape up to `end` as an argument of an interpolation:
+    /// `__escape__` applied to its text.
+    fn decoded_escape(&mut self, end: usize) {
+        self.start(Kind::ApplyArgument);
+        self.start(Kind::Apply);
+        self.start(Kind::ApplyReceiver);
+        self.start(Kind::SyntheticEscape);
+        self.finish_node();
+        self.finish_node();
+        self.start(Kind::ApplyArgument);
+        self.start(Kind::Literal);
+        self.start(Kind::StringContent);
+        self.token(Kind::StringContent, end - self.pos);
+        self.finish_node();
+        self.finish_node();
+        self.finish_node();
+        self.finish_node();
+        self.finish_node();
+    }
+
+    /// Emits the line ending a multi-line string starts with, which isn't
+    /// part of its text.
+    fn first_line_ending(&mut self, start: usize) {
+        if self.pos != start {
+            return;
+        }
+        let rest = self.rest();
+        let len = if rest.starts_with("\r\n") {
+            2
+        } else {
+            usize::from(rest.starts_with('\n'))
+        };
+        let opened = &self.src.as_bytes()[self.pos.saturating_sub(3)..self.pos];
+        let opened_multiline = matches!(opened, b"\"\"\"" | b"'''");
+        if opened_multiline && len > 0 {
+            self.token(Kind::Newline, len);
+        }
+    }
+
+    fn string_part(&mut self, escapes: Escapes, end: usize) {
+        if end <= self.pos {
+            return;
+        }
+        self.start(Kind::ApplyArgument);
+        self.start(Kind::Literal);
+        self.content(self.content_kind(escapes), end);
+        self.finish_node();
+        self.finish_node();
+    }
+
+    fn content_kind(&self, escapes: Escapes) -> Kind {
+        if escapes == Escapes::Raw {
+            Kind::StringContent
+        } else {
+            Kind::StringContentWithEscape
+        }
+    }
+
+    fn content(&mut self, kind: Kind, end: usize) {
+        // Text without a backslash reads the same either way
+        let kind = if self.src[self.pos..end].contains('\\') {
+            kind
+        } else {
+            Kind::StringContent
+        };
+        self.start(kind);
+        self.token(kind, end - self.pos);
+        self.finish_node();
+    }
+
+    /// Returns the escapes in `content` that can't be kept in a Cadenza
+    /// string, and what becomes of each.
+    fn dropped_escapes(
+        &self,
+        content: Range<usize>,
+        escapes: Escapes,
+    ) -> Vec<(Range<usize>, Escape)> {
+        let mut dropped = Vec::new();
+        if escapes == Escapes::Raw {
+            return dropped;
+        }
+        let bytes = self.src.as_bytes();
+        let mut pos = content.start;
+        while pos < content.end {
+            if bytes[pos] != b'\\' {
+                pos += 1;
+                continue;
+            }
+            let Some(escape) = self.src[pos + 1..content.end].chars().next() else {
+                dropped.push((pos..pos + 1, Escape::Invalid("`\\` escapes nothing".into())));
+                break;
+            };
+            let end = pos + 1 + escape.len_utf8();
+            match escape {
+                '"' | '\\' | 'n' | 'r' | 't' => {}
+                '/' if escapes == Escapes::Json => dropped.push((pos..pos + 1, Escape::Dropped)),
+                ' ' | '\t' | '\r' | '\n' if escapes == Escapes::Toml => {
+                    // A line ending backslash trims the whitespace after it
+                    let trimmed =
+                        self.src[pos + 1..content.end].trim_start_matches([' ', '\t', '\r', '\n']);
+                    let skipped = &self.src[pos + 1..content.end - trimmed.len()];
+                    if skipped.contains('\n') {
+                        dropped.push((pos..pos + 1, Escape::Dropped));
+                        pos = content.end - trimmed.len();
+                        continue;
+                    }
+                    dropped.push((pos..end, Escape::Invalid("unknown escape sequence".into())));
+                }
+                'b' | 'f' => dropped.push((pos..end, Escape::Decoded)),
+                'u' => {
+                    let (escape_end, result) = self.unicode_escape(pos, content.end);
+                    dropped.push((pos..escape_end, result));
+                    pos = escape_end;
+                    continue;
+                }
+                'U' if escapes == Escapes::Toml => {
+                    let (digits, code) = self.hex(end, content.end, 8);
+                    let escape = if digits < 8 {
+                        Escape::Invalid("`\\U` takes eight hex digits".into())
+                    } else if char::from_u32(code).is_none() {
+                        Escape::Invalid(format!(
+                            "`{}` is not a Unicode scalar value",
+                            &self.src[pos..end + digits]
+                        ))
+                    } else {
+                        Escape::Decoded
+                    };
+                    dropped.push((pos..end + digits, escape));
+                    pos = end + digits;
+                    continue;
+                }
+                _ => dropped.push((
+                    pos..end,
+                    Escape::Invalid(format!("`\\{escape}` escapes aren't supported")),
+                )),
+            }
+            pos = end;
+        }
+        dropped
+    }
+
+    /// Reads the `\uXXXX` escape at `pos`, with the low surrogate after it if
+    /// it is a high one, and returns where it ends and what becomes of it.
+    fn unicode_escape(&self, pos: usize, end: usize) -> (usize, Escape) {
+        let (digits, code) = self.hex(pos + 2, end, 4);
+        let escape_end = pos + 2 + digits;
+        if digits < 4 {
+            return (
+                escape_end,
+                Escape::Invalid("`\\u` takes four hex digits".into()),
+            );
+        }
+        let unpaired = || {
+            Escape::Invalid(format!(
+                "`{}` is half of a surrogate pair",
+                &self.src[pos..escape_end]
+            ))
+        };
+        match code {
+            0xD800..0xDC00 => {
+                if !self.src[escape_end..end].starts_with("\\u") {
+                    return (escape_end, unpaired());
+                }
+                let (digits, low) = self.hex(escape_end + 2, end, 4);
+                if digits < 4 || !(0xDC00..0xE000).contains(&low) {
+                    return (escape_end, unpaired());
+                }
+                (escape_end + 6, Escape::Decoded)
+            }
+            0xDC00..0xE000 => (escape_end, unpaired()),
+            _ => (escape_end, Escape::Decoded),
+        }
+    }
+
+    /// Reads up to `len` hex digits from `start`, before `end`, and returns
+    /// how many there are and their value.
+    fn hex(&self, start: usize, end: usize, len: usize) -> (usize, u32) {
+        let digits = self.src[start.min(end)..end]
+            .bytes()
+            .take(len)
+            .take_while(u8::is_ascii_hexdigit)
+            .count();
+        let code = u32::from_str_radix(&self.src[start..start + digits], 16).unwrap_or(0);
+        (digits, code)
+    }
+}
@@ -0,0 +1,380 @@
+//! JSON parser that produces Cadenza-compatible AST.
+//!
+//! A JSON document parses to the literal that would be written in Cadenza
+//! to build the same value:
+//!
+//! - **Objects** become record literals, `{ name = "ada", age = 36 }`, with a
+//!   field per member in the order written. A key that isn't a Cadenza
+//!   identifier, like `"first name"`, still names its field
+//! - **Arrays** become list literals
+//! - **Numbers** become integer literals, or float literals if they have a
+//!   fraction or exponent
+//! - **Strings** become string literals; `\/` is dropped to `/`, and `\b`,
+//!   `\f` and `\u00e9` (or a surrogate pair, `\ud83d\ude00`) decode to the
+//!   characters Cadenza writes `\x08`, `\x0C` and `\u{e9}`
+//! - `true`, `false` and `null` become `true`, `false` and `()`
+//!
+//! # Example
+//!
+//! ```
+//! use cadenza_config::parse_json;
+//!
+//! let json = r#"{ "name": "ada", "langs": ["en", "fr"] }"#;
+//! let root = parse_json(json).ast();
+//! // [[__record__, [:, name, "ada"], [:, langs, [__list__, "en", "fr"]]]]
+//! ```
+
+use crate::emit::{Emitter, Escapes, Key};
+use cadenza_syntax::{frontend::Frontend, parse::Parse, token::Kind};
+
+/// Parse JSON source into a Cadenza-compatible AST.
+pub fn parse_json(src: &str) -> Parse {
+    Parser {
+        e: Emitter::new(src),
+    }
+    .parse()
+}
+
+/// The JSON front-end, for `.json` files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFrontend;
+
+impl Frontend for JsonFrontend {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        parse_json(source)
+    }
+}
+
+struct Parser<'src> {
+    e: Emitter<'src>,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Parse {
+        self.e.start(Kind::Root);
+        self.e.whitespace(true);
+        if self.e.at_end() {
+            self.e.error_here("expected a JSON value");
+        } else {
+            self.value();
+            self.e.whitespace(true);
+            if !self.e.at_end() {
+                self.e
+                    .error_to("unexpected text after the JSON value", self.e.src.len());
+            }
+        }
+        self.e.finish_node();
+        self.e.finish()
+    }
+
+    /// Returns whether the next character can start a value.
+    fn at_value(&self) -> bool {
+        matches!(
+            self.e.peek(),
+            Some(b'{' | b'[' | b'"' | b'-' | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z')
+        )
+    }
+
+    fn value(&mut self) {
+        match self.e.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => {
+                let (content, end) = self.scan_string();
+                self.e.string(content, end, Escapes::Json);
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'a'..=b'z' | b'A'..=b'Z') => self.word(),
+            Some(b',' | b':' | b']' | b'}') | None => self.e.error_here("expected a JSON value"),
+            Some(_) => self.e.error_char("expected a JSON value"),
+        }
+    }
+
+    /// Emits a value as an argument of the literal being built, or reports
+    /// that one is missing.
+    fn argument(&mut self) {
+        if self.at_value() {
+            self.e.start(Kind::ApplyArgument);
+            self.value();
+            self.e.finish_node();
+        } else {
+            self.value();
+        }
+    }
+
+    fn object(&mut self) {
+        if !self.e.enter() {
+            return;
+        }
+        self.e.start_literal(Kind::SyntheticRecord);
+        self.e.token(Kind::LBrace, 1);
+        self.e.whitespace(true);
+        if self.e.peek() == Some(b'}') {
+            self.e.token(Kind::RBrace, 1);
+        } else {
+            loop {
+                if self.e.peek() == Some(b'"') {
+                    self.member();
+                } else {
+                    self.e.error_here("expected a key");
+                }
+                if !self.separator(b'}', Kind::RBrace) {
+                    break;
+                }
+            }
+        }
+        self.e.finish_node();
+        self.e.leave();
+    }
+
+    /// Emits `"key": value` as the field `key: value`.
+    fn member(&mut self) {
+        let start = self.e.pos;
+        let (name, end) = self.scan_string();
+        let colon = self.e.src[end..].trim_start_matches([' ', '\t', '\r', '\n']);
+        if !colon.starts_with(':') {
+            // Keep the key and any value after it out of the record
+            self.e.error("expected `:` after the key", end..end);
+            self.e.start(Kind::Error);
+            self.e.token_to(Kind::StringContent, end);
+            self.e.whitespace(true);
+            if self.at_value() {
+                self.value();
+            }
+            self.e.finish_node();
+            return;
+        }
+
+        self.e.start(Kind::ApplyArgument);
+        self.e.start(Kind::Apply);
+        self.e.key(&Key {
+            range: start..end,
+            name,
+        });
+        self.e.whitespace(true);
+        self.e.start(Kind::ApplyReceiver);
+        self.e.start(Kind::Colon);
+        self.e.token(Kind::Colon, 1);
+        self.e.finish_node();
+        self.e.finish_node();
+        self.e.whitespace(true);
+        self.argument();
+        self.e.finish_node();
+        self.e.finish_node();
+    }
+
+    fn array(&mut self) {
+        if !self.e.enter() {
+            return;
+        }
+        self.e.start_literal(Kind::SyntheticList);
+        self.e.token(Kind::LBracket, 1);
+        self.e.whitespace(true);
+        if self.e.peek() == Some(b']') {
+            self.e.token(Kind::RBracket, 1);
+        } else {
+            loop {
+                self.argument();
+                if !self.separator(b']', Kind::RBracket) {
+                    break;
+                }
+            }
+        }
+        self.e.finish_node();
+        self.e.leave();
+    }
+
+    /// Emits what follows an element of an array or object: a comma, and
+    /// returns `true` if another element follows, or the closing bracket.
+    ///
+    /// Anything else is skipped a character at a time, so the parser always
+    /// moves on.
+    fn separator(&mut self, close: u8, close_kind: Kind) -> bool {
+        self.e.whitespace(true);
+        match self.e.peek() {
+            Some(b',') => {
+                let comma = self.e.pos;
+                self.e.token(Kind::Comma, 1);
+                self.e.whitespace(true);
+                if self.e.peek() == Some(close) {
+                    self.e
+                        .error("trailing commas aren't allowed", comma..comma + 1);
+                    self.e.token(close_kind, 1);
+                    return false;
+                }
+                true
+            }
+            Some(byte) if byte == close => {
+                self.e.token(close_kind, 1);
+                false
+            }
+            None => {
+                let close = char::from(close);
+                self.e.error_here(format!("expected `,` or `{close}`"));
+                false
+            }
+            Some(_) => {
+                let close = char::from(close);
+                self.e.error_char(format!("expected `,` or `{close}`"));
+                self.e.whitespace(true);
+                true
+            }
+        }
+    }
+
+    /// Returns the text of the string at the cursor, between its quotes, and
+    /// where it ends. A string missing its closing quote ends at the end of
+    /// its line.
+    fn scan_string(&mut self) -> (std::ops::Range<usize>, usize) {
+        let bytes = self.e.src.as_bytes();
+        let start = self.e.pos + 1;
+        let mut pos = start;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'"' => return (start..pos, pos + 1),
+                b'\\' if pos + 1 < bytes.len() && bytes[pos + 1] != b'\n' => pos += 2,
+                b'\n' => break,
+                _ => pos += 1,
+            }
+        }
+        let pos = pos.min(bytes.len());
+        self.e.error("unterminated string", self.e.pos..pos);
+        (start..pos, pos)
+    }
+
+    fn number(&mut self) {
+        let len = self
+            .e
+            .rest()
+            .bytes()
+            .take_while(|byte| matches!(byte, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+            .count();
+        let text = &self.e.rest()[..len];
+        match number_kind(text) {
+            Some(kind) => self.e.number(kind, len),
+            None => self.e.error_to("invalid number", self.e.pos + len),
+        }
+    }
+
+    fn word(&mut self) {
+        let len = self
+            .e
+            .rest()
+            .bytes()
+            .take_while(|byte| byte.is_ascii_alphanumeric() || *byte == b'_')
+            .count();
+        match &self.e.rest()[..len] {
+            "true" | "false" => self.e.identifier(len),
+            "null" => self.e.nil(len),
+            _ => self.e.error_to("expected a JSON value", self.e.pos + len),
+        }
+    }
+}
+
+/// Returns the kind of literal the JSON number `text` is, or `None` if it
+/// isn't one: `-`, then `0` or digits not starting with `0`, then an
+/// optional fraction and exponent.
+fn number_kind(text: &str) -> Option<Kind> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        bytes[from.min(bytes.len())..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+
+    let mut pos = usize::from(bytes.first() == Some(&b'-'));
+    let whole = digits(pos);
+    if whole == 0 || (whole > 1 && bytes[pos] == b'0') {
+        return None;
+    }
+    pos += whole;
+
+    let mut kind = Kind::Integer;
+    if bytes.get(pos) == Some(&b'.') {
+        let fraction = digits(pos + 1);
+        if fraction == 0 {
+            return None;
+        }
+        pos += 1 + fraction;
+        kind = Kind::Float;
+    }
+    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(pos + 1), Some(b'+' | b'-')));
+        let exponent = digits(pos + 1 + sign);
+        if exponent == 0 {
+            return None;
+        }
+        pos += 1 + sign + exponent;
+        kind = Kind::Float;
+    }
+    (pos == bytes.len()).then_some(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadenza_eval::{Compiler, Env, Value};
+
+    fn eval(src: &str) -> Value {
+        let parsed = parse_json(src);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let mut results = cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        results.pop().unwrap()
+    }
+
+    #[test]
+    fn evaluates_to_records_and_lists() {
+        let value = eval(
+            r#"{"name": "ada", "first name": "A\"da\/", "n": [1, -2.5e1, true, null], "o": {}}"#,
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{name = ada, first name = A"da/, n = [1, -25, true, nil], o = {}}"#
+        );
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        let value = eval(r#"["caf\u00e9", "\ud83d\ude00", "\b\f", "\u00e9\/\u00e9"]"#);
+        assert_eq!(value.to_string(), "[café, \u{1F600}, \x08\x0C, é/é]");
+    }
+
+    #[test]
+    fn number_kinds() {
+        assert_eq!(number_kind("-0"), Some(Kind::Integer));
+        assert_eq!(number_kind("12.5"), Some(Kind::Float));
+        assert_eq!(number_kind("1E+3"), Some(Kind::Float));
+        assert_eq!(number_kind("01"), None);
+        assert_eq!(number_kind("1."), None);
+        assert_eq!(number_kind("1-2"), None);
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        let parsed = parse_json("[1, \"\\ud800\",]");
+        let errors: Vec<_> = parsed
+            .errors
+            .iter()
+            .map(|e| (e.message.as_str(), e.span.start, e.span.end))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("`\\ud800` is half of a surrogate pair", 5, 11),
+                ("trailing commas aren't allowed", 12, 13),
+            ]
+        );
+    }
+}
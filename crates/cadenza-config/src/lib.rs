@@ -0,0 +1,42 @@
+//! JSON and TOML parsers as alternative Cadenza syntax.
+//!
+//! This crate treats configuration files as alternative lexers/parsers for
+//! Cadenza: a `.json` or `.toml` file parses to the record and list literals
+//! that would be written in Cadenza to build the same value, so documents
+//! can be imported as typed records, and type-checked and evaluated like any
+//! other Cadenza code.
+//!
+//! The TOML front end accepts a subset of TOML: the sections of a table must
+//! be kept together, so a table reopened after another one (`[a] … [b] …
+//! [a.c]`) is rejected. See [`toml`] for details.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cadenza_config::parse_toml;
+//! use cadenza_eval::{eval, Compiler, Env};
+//!
+//! let toml = "name = \"cadenza\"\n\n[server]\nport = 8080\n";
+//! let parse_result = parse_toml(toml);
+//! let root = parse_result.ast();
+//!
+//! let mut compiler = Compiler::new();
+//! let mut env = Env::with_standard_builtins();
+//! // A record: { name = "cadenza", server = { port = 8080 } }
+//! let results = eval(&root, &mut env, &mut compiler);
+//! ```
+
+mod emit;
+pub mod json;
+pub mod toml;
+
+#[cfg(test)]
+pub mod testing;
+
+mod generated;
+
+pub use json::{JsonFrontend, parse_json};
+pub use toml::{TomlFrontend, parse_toml};
+
+#[cfg(test)]
+mod fuzz;
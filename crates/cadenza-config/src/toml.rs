@@ -0,0 +1,745 @@
+//! TOML parser that produces Cadenza-compatible AST.
+//!
+//! A TOML document parses to the record literal that would be written in
+//! Cadenza to build the same value:
+//!
+//! - **Key/value pairs** become fields, `port = 8080`
+//! - **Tables** (`[server]`) and **dotted keys** (`server.port = 8080`)
+//!   become fields holding records
+//! - **Arrays of tables** (`[[server]]`) become a field holding a list of
+//!   records, one per header
+//! - **Arrays** and **inline tables** become list and record literals
+//! - **Numbers** become integer and float literals, including `0x`, `0o` and
+//!   `0b` integers and `inf` and `nan`
+//! - **Strings** become string literals. Literal strings are kept as
+//!   written, and `\b`, `\f`, `\u00e9` and `\U0001F600` decode to the
+//!   characters Cadenza writes `\x08`, `\x0C` and `\u{...}`
+//! - **Dates and times** become strings, as written
+//!
+//! # A TOML subset
+//!
+//! Only a subset of TOML is accepted. A table's fields nest inside the node
+//! for its header, so every section of a table must be kept together: a
+//! document that returns to a table after starting another, like
+//! `[a] … [b] … [a.c]`, is valid TOML but parses to a record with two `a`
+//! fields, which is an error when it's evaluated. Move `[a.c]` up next to
+//! `[a]` to read it.
+//!
+//! # Example
+//!
+//! ```
+//! use cadenza_config::parse_toml;
+//!
+//! let toml = "title = \"demo\"\n\n[server]\nport = 8080\n";
+//! let root = parse_toml(toml).ast();
+//! // [[__record__, [=, title, "demo"], [=, server, [__record__, [=, port, 8080]]]]]
+//! ```
+
+use crate::emit::{Emitter, Escapes, Key};
+use cadenza_syntax::{frontend::Frontend, parse::Parse, token::Kind};
+
+/// Parse TOML source into a Cadenza-compatible AST.
+pub fn parse_toml(src: &str) -> Parse {
+    Parser {
+        e: Emitter::new(src),
+    }
+    .parse()
+}
+
+/// The TOML front-end, for `.toml` files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TomlFrontend;
+
+impl Frontend for TomlFrontend {
+    fn name(&self) -> &str {
+        "toml"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["toml"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        parse_toml(source)
+    }
+}
+
+/// A table being built: a field holding a record, or for an array of
+/// tables, a list of records, whose last record is being built.
+struct Frame {
+    name: String,
+    array: bool,
+    /// Whether a header defined the table, rather than it being implied by
+    /// a longer header or a dotted key.
+    defined: bool,
+}
+
+struct Parser<'src> {
+    e: Emitter<'src>,
+}
+
+impl<'src> Parser<'src> {
+    fn parse(mut self) -> Parse {
+        self.e.start(Kind::Root);
+        self.e.start_literal(Kind::SyntheticRecord);
+
+        let mut frames = Vec::new();
+        // The number of frames the last header opened; dotted keys open the
+        // ones after it
+        let mut base = 0;
+        loop {
+            self.trivia();
+            if self.e.at_end() {
+                break;
+            }
+            if self.e.peek() == Some(b'[') {
+                self.header(&mut frames);
+                base = frames.len();
+            } else if !self.key_value(&mut frames, base) {
+                self.skip_line("expected a key");
+                continue;
+            }
+            self.end_of_line();
+        }
+        self.close_to(&mut frames, 0);
+
+        self.e.finish_node();
+        self.e.finish_node();
+        self.e.finish()
+    }
+
+    /// Emits whitespace, line endings and comments.
+    fn trivia(&mut self) {
+        loop {
+            self.e.whitespace(true);
+            if self.e.peek() != Some(b'#') {
+                break;
+            }
+            self.comment();
+        }
+    }
+
+    fn comment(&mut self) {
+        self.e.token(Kind::CommentStart, 1);
+        let end = self.line_end();
+        let end = if self.e.src[..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        };
+        self.e.token_to(Kind::CommentContent, end);
+    }
+
+    /// Returns where the line the cursor is on ends, before its `\n`.
+    fn line_end(&self) -> usize {
+        self.e
+            .rest()
+            .find('\n')
+            .map_or(self.e.src.len(), |end| self.e.pos + end)
+    }
+
+    fn skip_line(&mut self, message: &str) {
+        let end = self.line_end();
+        self.e.error_to(message, end);
+    }
+
+    /// Checks that nothing but a comment follows a key/value pair or header
+    /// on its line.
+    fn end_of_line(&mut self) {
+        self.e.whitespace(false);
+        if self.e.peek() == Some(b'#') {
+            self.comment();
+        }
+        let rest = self.e.rest();
+        if !(rest.is_empty() || rest.starts_with('\n') || rest.starts_with("\r\n")) {
+            self.skip_line("expected the end of the line");
+        }
+    }
+
+    /// Emits a `[table]` or `[[array]]` header, closing the tables that
+    /// aren't part of its path and opening the ones that are new.
+    fn header(&mut self, frames: &mut Vec<Frame>) {
+        let array = self.e.rest().starts_with("[[");
+        let brackets = if array { 2 } else { 1 };
+        let Some((keys, close)) = self.scan_header(brackets) else {
+            self.skip_line("expected a table header, like `[name]`");
+            return;
+        };
+        let header = self.e.pos..close + brackets;
+        let common = frames
+            .iter()
+            .zip(&keys)
+            .take_while(|(frame, key)| frame.name == self.name(key))
+            .count();
+
+        if common == keys.len() {
+            // The header names a table that is already open
+            self.close_to(frames, common);
+            let frame = frames.last_mut().expect("a header has a key");
+            match (array, frame.array) {
+                (true, true) => {
+                    self.e.finish_node();
+                    self.e.finish_node();
+                    self.path(frames, &keys, common, brackets, false);
+                    self.e.start(Kind::ApplyArgument);
+                    self.e.start_literal(Kind::SyntheticRecord);
+                    return;
+                }
+                (false, false) if !frame.defined => frame.defined = true,
+                (true, false) => self.e.error(
+                    format!("`{}` is a table, not an array of tables", frame.name),
+                    header.clone(),
+                ),
+                _ => self.e.error(
+                    format!("table `{}` is defined more than once", frame.name),
+                    header.clone(),
+                ),
+            }
+            self.path(frames, &keys, common, brackets, false);
+            return;
+        }
+
+        self.close_to(frames, common);
+        self.path(frames, &keys, common, brackets, array);
+    }
+
+    /// Emits the header with `keys` whose first `open` tables are already
+    /// open, opening the rest.
+    fn path(
+        &mut self,
+        frames: &mut Vec<Frame>,
+        keys: &[Key],
+        open: usize,
+        brackets: usize,
+        array: bool,
+    ) {
+        for _ in 0..brackets {
+            self.e.token(Kind::LBracket, 1);
+        }
+        self.e.whitespace(false);
+        for (i, key) in keys.iter().enumerate() {
+            if i < open {
+                self.e.token_to(Kind::Identifier, key.range.end);
+            } else {
+                let last = i + 1 == keys.len();
+                if !self.open(frames, key, array && last, last) {
+                    return;
+                }
+            }
+            self.dot();
+        }
+        for _ in 0..brackets {
+            self.e.token(Kind::RBracket, 1);
+        }
+    }
+
+    /// Emits the whitespace and `.` between two keys of a path.
+    fn dot(&mut self) {
+        self.e.whitespace(false);
+        if self.e.peek() == Some(b'.') {
+            self.e.token(Kind::Dot, 1);
+            self.e.whitespace(false);
+        }
+    }
+
+    /// Opens a field named by `key` holding a record, or for an array of
+    /// tables, a list of records. Returns `false` if the document is nested
+    /// too deeply.
+    fn open(&mut self, frames: &mut Vec<Frame>, key: &Key, array: bool, defined: bool) -> bool {
+        if !self.e.enter() {
+            return false;
+        }
+        self.e.start(Kind::ApplyArgument);
+        self.e.start(Kind::Apply);
+        self.e.key(key);
+        self.e.start(Kind::ApplyReceiver);
+        self.e.start(Kind::SyntheticField);
+        self.e.finish_node();
+        self.e.finish_node();
+        self.e.start(Kind::ApplyArgument);
+        if array {
+            self.e.start_literal(Kind::SyntheticList);
+            self.e.start(Kind::ApplyArgument);
+        }
+        self.e.start_literal(Kind::SyntheticRecord);
+        frames.push(Frame {
+            name: self.name(key).to_string(),
+            array,
+            defined,
+        });
+        true
+    }
+
+    /// Closes the tables after the first `len`.
+    fn close_to(&mut self, frames: &mut Vec<Frame>, len: usize) {
+        while frames.len() > len {
+            let frame = frames.pop().expect("more frames than `len`");
+            let nodes = if frame.array { 6 } else { 4 };
+            for _ in 0..nodes {
+                self.e.finish_node();
+            }
+            self.e.leave();
+        }
+    }
+
+    /// Emits `key = value`, opening a table for each part of a dotted key
+    /// but the last. The tables after the first `base` are kept open, so
+    /// the next key/value pair can add to them.
+    ///
+    /// Returns `false`, having emitted nothing, if there is no key.
+    fn key_value(&mut self, frames: &mut Vec<Frame>, base: usize) -> bool {
+        let Some((keys, _)) = self.scan_path(self.e.pos) else {
+            return false;
+        };
+        let (last, parents) = keys.split_last().expect("a path has a key");
+        let common = frames[base..]
+            .iter()
+            .zip(parents)
+            .take_while(|(frame, key)| frame.name == self.name(key))
+            .count();
+        self.close_to(frames, base + common);
+        for (i, key) in parents.iter().enumerate() {
+            if i < common {
+                self.e.token_to(Kind::Identifier, key.range.end);
+            } else if !self.open(frames, key, false, false) {
+                return true;
+            }
+            self.dot();
+        }
+
+        let equal = skip_spaces(self.e.src, last.range.end);
+        if !self.e.src[equal..].starts_with('=') {
+            // Keep the key out of the record
+            self.e
+                .error("expected `=` after the key", last.range.end..last.range.end);
+            self.e.start(Kind::Error);
+            self.e.token_to(Kind::StringContent, last.range.end);
+            self.e.finish_node();
+            return true;
+        }
+
+        self.e.start(Kind::ApplyArgument);
+        self.e.start(Kind::Apply);
+        self.e.key(last);
+        self.e.whitespace(false);
+        self.e.start(Kind::ApplyReceiver);
+        self.e.start(Kind::Equal);
+        self.e.token(Kind::Equal, 1);
+        self.e.finish_node();
+        self.e.finish_node();
+        self.e.whitespace(false);
+        if self.at_value() {
+            self.e.start(Kind::ApplyArgument);
+            self.value();
+            self.e.finish_node();
+        } else {
+            self.e.error_here("expected a value");
+        }
+        self.e.finish_node();
+        self.e.finish_node();
+        true
+    }
+
+    /// Returns the name `key` gives.
+    fn name(&self, key: &Key) -> &'src str {
+        &self.e.src[key.name.clone()]
+    }
+
+    /// Returns the keys of the header at the cursor and where its closing
+    /// brackets start.
+    fn scan_header(&self, brackets: usize) -> Option<(Vec<Key>, usize)> {
+        let start = skip_spaces(self.e.src, self.e.pos + brackets);
+        let (keys, end) = self.scan_path(start)?;
+        let close = skip_spaces(self.e.src, end);
+        let closing = if brackets == 2 { "]]" } else { "]" };
+        self.e.src[close..]
+            .starts_with(closing)
+            .then_some((keys, close))
+    }
+
+    /// Returns the keys of the dotted key starting at `pos`, and where it
+    /// ends.
+    fn scan_path(&self, pos: usize) -> Option<(Vec<Key>, usize)> {
+        let mut keys = vec![scan_key(self.e.src, pos)?];
+        loop {
+            let end = keys.last().expect("a path has a key").range.end;
+            let dot = skip_spaces(self.e.src, end);
+            if !self.e.src[dot..].starts_with('.') {
+                return Some((keys, end));
+            }
+            keys.push(scan_key(self.e.src, skip_spaces(self.e.src, dot + 1))?);
+        }
+    }
+
+    /// Returns whether the next character can start a value.
+    fn at_value(&self) -> bool {
+        matches!(
+            self.e.peek(),
+            Some(
+                b'"' | b'\'' | b'[' | b'{' | b'+' | b'-' | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z',
+            )
+        )
+    }
+
+    fn value(&mut self) {
+        let rest = self.e.rest();
+        match self.e.peek() {
+            Some(b'"') if rest.starts_with("\"\"\"") => {
+                self.multiline_string("\"\"\"", Escapes::Toml)
+            }
+            Some(b'\'') if rest.starts_with("'''") => self.multiline_string("'''", Escapes::Raw),
+            Some(b'"') => self.string(b'"', Escapes::Toml),
+            Some(b'\'') => self.string(b'\'', Escapes::Raw),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.inline_table(),
+            _ => self.bare_value(),
+        }
+    }
+
+    /// Emits a single-line string, which ends at its line if it is missing
+    /// its closing quote.
+    fn string(&mut self, quote: u8, escapes: Escapes) {
+        let bytes = self.e.src.as_bytes();
+        let start = self.e.pos + 1;
+        let mut pos = start;
+        let end = loop {
+            match bytes.get(pos) {
+                Some(&byte) if byte == quote => break Some(pos),
+                Some(b'\\') if escapes != Escapes::Raw => pos += 2,
+                Some(b'\n') | None => break None,
+                Some(_) => pos += 1,
+            }
+        };
+        match end {
+            Some(end) => self.e.string(start..end, end + 1, escapes),
+            None => {
+                let end = pos.min(bytes.len());
+                let end = if self.e.src[..end].ends_with('\r') {
+                    end - 1
+                } else {
+                    end
+                };
+                self.e.error("unterminated string", self.e.pos..end);
+                self.e.string(start..end, end, escapes);
+            }
+        }
+    }
+
+    /// Emits a string between `delimiter`s, which may span lines. Up to two
+    /// quotes right before the closing delimiter are part of the string.
+    fn multiline_string(&mut self, delimiter: &str, escapes: Escapes) {
+        let src = self.e.src;
+        let start = self.e.pos + delimiter.len();
+        let mut pos = start;
+        let close = loop {
+            let Some(found) = src[pos..].find(delimiter) else {
+                break None;
+            };
+            let at = pos + found;
+            let backslashes = src[start..at]
+                .bytes()
+                .rev()
+                .take_while(|byte| *byte == b'\\')
+                .count();
+            if escapes == Escapes::Raw || backslashes % 2 == 0 {
+                let quote = delimiter.as_bytes()[0];
+                let extra = src.as_bytes()[at + 3..]
+                    .iter()
+                    .take(2)
+                    .take_while(|byte| **byte == quote)
+                    .count();
+                break Some(at + extra);
+            }
+            pos = at + 1;
+        };
+        match close {
+            Some(close) => self.e.string(start..close, close + 3, escapes),
+            None => {
+                self.e.error("unterminated string", self.e.pos..src.len());
+                self.e.string(start..src.len(), src.len(), escapes);
+            }
+        }
+    }
+
+    fn array(&mut self) {
+        if !self.e.enter() {
+            return;
+        }
+        self.e.start_literal(Kind::SyntheticList);
+        self.e.token(Kind::LBracket, 1);
+        loop {
+            self.trivia();
+            match self.e.peek() {
+                Some(b']') => {
+                    self.e.token(Kind::RBracket, 1);
+                    break;
+                }
+                None => {
+                    self.e.error_here("expected `]`");
+                    break;
+                }
+                _ if self.at_value() => {
+                    self.e.start(Kind::ApplyArgument);
+                    self.value();
+                    self.e.finish_node();
+                }
+                _ => {
+                    self.e.error_char("expected a value");
+                    continue;
+                }
+            }
+            self.trivia();
+            match self.e.peek() {
+                Some(b',') => self.e.token(Kind::Comma, 1),
+                Some(b']') => {}
+                None => {
+                    self.e.error_here("expected `]`");
+                    break;
+                }
+                _ => self.e.error_char("expected `,` or `]`"),
+            }
+        }
+        self.e.finish_node();
+        self.e.leave();
+    }
+
+    fn inline_table(&mut self) {
+        if !self.e.enter() {
+            return;
+        }
+        self.e.start_literal(Kind::SyntheticRecord);
+        self.e.token(Kind::LBrace, 1);
+        let mut frames = Vec::new();
+        loop {
+            self.trivia();
+            match self.e.peek() {
+                Some(b'}') => break,
+                None => {
+                    self.e.error_here("expected `}`");
+                    break;
+                }
+                _ => {
+                    if !self.key_value(&mut frames, 0) {
+                        self.e.error_char("expected a key");
+                        continue;
+                    }
+                }
+            }
+            self.trivia();
+            match self.e.peek() {
+                Some(b',') => self.e.token(Kind::Comma, 1),
+                Some(b'}') => {}
+                None => {
+                    self.e.error_here("expected `}`");
+                    break;
+                }
+                _ => self.e.error_char("expected `,` or `}`"),
+            }
+        }
+        self.close_to(&mut frames, 0);
+        if self.e.peek() == Some(b'}') {
+            self.e.token(Kind::RBrace, 1);
+        }
+        self.e.finish_node();
+        self.e.leave();
+    }
+
+    /// Emits a boolean, number, or date and time.
+    fn bare_value(&mut self) {
+        let rest = self.e.rest();
+        let mut len = bare_len(rest);
+        // A date and a time may be separated by a space
+        if is_date(&rest[..len]) && rest[len..].starts_with(' ') && is_time(&rest[len + 1..]) {
+            len += 1 + bare_len(&rest[len + 1..]);
+        }
+        let text = &rest[..len];
+        match text {
+            "" => self.e.error_char("expected a value"),
+            "true" | "false" => self.e.identifier(len),
+            _ => match bare_kind(text) {
+                Some(Kind::StringContent) => self.e.bare_string(len),
+                Some(kind) => self.e.number(kind, len),
+                None => self.e.error_to("expected a value", self.e.pos + len),
+            },
+        }
+    }
+}
+
+/// Returns the key starting at `pos`: a bare key, or a quoted one on a
+/// single line.
+fn scan_key(src: &str, pos: usize) -> Option<Key> {
+    let bytes = src.as_bytes();
+    match bytes.get(pos)? {
+        quote @ (b'"' | b'\'') => {
+            let mut end = pos + 1;
+            loop {
+                match bytes.get(end)? {
+                    byte if byte == quote => break,
+                    b'\n' => return None,
+                    b'\\' if *quote == b'"' => end += 2,
+                    _ => end += 1,
+                }
+            }
+            Some(Key {
+                range: pos..end + 1,
+                name: pos + 1..end,
+            })
+        }
+        _ => {
+            let len = bytes[pos..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-'))
+                .count();
+            (len > 0).then_some(Key {
+                range: pos..pos + len,
+                name: pos..pos + len,
+            })
+        }
+    }
+}
+
+fn skip_spaces(src: &str, pos: usize) -> usize {
+    pos + src.as_bytes()[pos.min(src.len())..]
+        .iter()
+        .take_while(|byte| matches!(byte, b' ' | b'\t'))
+        .count()
+}
+
+/// Returns the length of the bare value `text` starts with.
+fn bare_len(text: &str) -> usize {
+    text.bytes()
+        .take_while(|byte| {
+            byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'+' | b'-' | b'.' | b':')
+        })
+        .count()
+}
+
+/// Returns whether `text` is a date, like `1979-05-27`.
+fn is_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, byte)| match i {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+/// Returns whether `text` starts with a time, like `07:32`.
+fn is_time(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 3 && bytes[..2].iter().all(u8::is_ascii_digit) && bytes[2] == b':'
+}
+
+/// Returns the kind of literal the bare value `text` is: an integer, a
+/// float, or a string for dates and times. Returns `None` if it isn't one.
+fn bare_kind(text: &str) -> Option<Kind> {
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if matches!(unsigned, "inf" | "nan") {
+        return Some(Kind::Float);
+    }
+    if text.contains(':') || (text.len() >= 10 && is_date(&text[..10])) {
+        return Some(Kind::StringContent);
+    }
+    if let Some(digits) = ["0x", "0o", "0b"]
+        .iter()
+        .find_map(|prefix| text.strip_prefix(prefix))
+    {
+        let valid =
+            !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_hexdigit() || b == b'_');
+        return valid.then_some(Kind::Integer);
+    }
+    let valid = unsigned.starts_with(|c: char| c.is_ascii_digit())
+        && unsigned
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'_' | b'.' | b'e' | b'E' | b'+' | b'-'));
+    if !valid {
+        return None;
+    }
+    if unsigned.contains(['.', 'e', 'E']) {
+        Some(Kind::Float)
+    } else {
+        Some(Kind::Integer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadenza_eval::{Compiler, Env, Value};
+
+    fn eval(src: &str) -> Value {
+        let parsed = parse_toml(src);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let mut results = cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        results.pop().unwrap()
+    }
+
+    #[test]
+    fn nests_tables_and_dotted_keys() {
+        let value = eval(
+            "title = 'demo'\nsite.url = \"x\"\nsite.port = 0x50\n\n[a.b]\nc = [1, 2.5]\n[a.d]\ne = { f.g = true }\n",
+        );
+        assert_eq!(
+            value.to_string(),
+            "{title = demo, site = {url = x, port = 80}, a = {b = {c = [1, 2.5]}, d = {e = {f = {g = true}}}}}"
+        );
+    }
+
+    #[test]
+    fn arrays_of_tables() {
+        let value =
+            eval("[[part]]\nname = \"bolt\"\n[part.size]\nmm = 12\n\n[[part]]\nname = \"nut\"\n");
+        assert_eq!(
+            value.to_string(),
+            "{part = [{name = bolt, size = {mm = 12}}, {name = nut}]}"
+        );
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        let value = eval("s = \"caf\\u00e9 \\U0001F600\\b\\f\"\n");
+        assert_eq!(value.to_string(), "{s = café \u{1F600}\x08\x0C}");
+    }
+
+    #[test]
+    fn bare_kinds() {
+        assert_eq!(bare_kind("-17"), Some(Kind::Integer));
+        assert_eq!(bare_kind("1_000"), Some(Kind::Integer));
+        assert_eq!(bare_kind("6.626e-34"), Some(Kind::Float));
+        assert_eq!(bare_kind("-inf"), Some(Kind::Float));
+        assert_eq!(bare_kind("1979-05-27T07:32:00Z"), Some(Kind::StringContent));
+        assert_eq!(bare_kind("07:32:00"), Some(Kind::StringContent));
+        assert_eq!(bare_kind("0xZZ"), None);
+        assert_eq!(bare_kind("yes"), None);
+    }
+
+    #[test]
+    fn reports_duplicate_tables() {
+        let parsed = parse_toml("[a]\nx = 1\n[a]\ny = 2\n");
+        let messages: Vec<_> = parsed.errors.iter().map(|e| &e.message).collect();
+        assert_eq!(messages, ["table `a` is defined more than once"]);
+        assert_eq!(parsed.errors[0].span.start, 10);
+    }
+
+    #[test]
+    fn rejects_reopened_tables() {
+        let parsed = parse_toml("[a]\nx = 1\n[b]\n[a.c]\ny = 2\n");
+        assert!(parsed.errors.is_empty());
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+        // Only a subset of TOML is accepted: `a` is reopened as a second field
+        let messages: Vec<_> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.kind.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            ["syntax error: field 'a' is given more than once"]
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Splitting records into fields and typing their cells.
+//!
+//! The parser and the streaming reader share these, so a file reads the same
+//! whether it's parsed whole or streamed record by record.
+
+use std::ops::Range;
+
+/// A field of a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Field {
+    /// The field's text, without the spaces around it. A quoted field's
+    /// includes its quotes.
+    pub value: Range<usize>,
+    /// Whether the field is quoted.
+    pub quoted: bool,
+    /// Whether a quoted field is missing its closing quote.
+    pub unterminated: bool,
+    /// Text between a quoted field's closing quote and the delimiter.
+    pub junk: Option<Range<usize>>,
+}
+
+/// Splits the record starting at `start` into its fields, and returns them
+/// with where the record ends: at the line ending after it, or the end of
+/// `src`.
+///
+/// Quoted fields may span lines, and a `""` in one is an escaped quote.
+pub(crate) fn split(src: &str, start: usize, delimiter: u8) -> (Vec<Field>, usize) {
+    let bytes = src.as_bytes();
+    let is_space = |byte: u8| byte == b' ' || (byte == b'\t' && delimiter != b'\t');
+    let field_end = |mut pos: usize| {
+        while pos < bytes.len() && !matches!(bytes[pos], b'\n' | b'\r') && bytes[pos] != delimiter {
+            pos += 1;
+        }
+        pos
+    };
+
+    let mut fields = Vec::new();
+    let mut pos = start;
+    loop {
+        while pos < bytes.len() && is_space(bytes[pos]) {
+            pos += 1;
+        }
+
+        let field = if bytes.get(pos) == Some(&b'"') {
+            let open = pos;
+            pos += 1;
+            let mut unterminated = true;
+            while pos < bytes.len() {
+                if bytes[pos] == b'"' {
+                    pos += 1;
+                    if bytes.get(pos) != Some(&b'"') {
+                        unterminated = false;
+                        break;
+                    }
+                }
+                pos += 1;
+            }
+            let value = open..pos.min(bytes.len());
+            while pos < bytes.len() && is_space(bytes[pos]) {
+                pos += 1;
+            }
+            let end = field_end(pos);
+            let junk = (end > pos).then_some(pos..end);
+            pos = end;
+            Field {
+                value,
+                quoted: true,
+                unterminated,
+                junk,
+            }
+        } else {
+            let begin = pos;
+            pos = field_end(pos);
+            let mut end = pos;
+            while end > begin && is_space(bytes[end - 1]) {
+                end -= 1;
+            }
+            Field {
+                value: begin..end,
+                quoted: false,
+                unterminated: false,
+                junk: None,
+            }
+        };
+        fields.push(field);
+
+        if bytes.get(pos) == Some(&delimiter) {
+            pos += 1;
+        } else {
+            return (fields, pos);
+        }
+    }
+}
+
+/// Returns the text of the quoted field `text`, without its quotes and with
+/// its `""`s unescaped.
+pub(crate) fn unquote(text: &str) -> String {
+    let inner = text.strip_prefix('"').unwrap_or(text);
+    let inner = inner.strip_suffix('"').unwrap_or(inner);
+    inner.replace("\"\"", "\"")
+}
+
+/// How the text of an unquoted cell reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shape {
+    /// A blank cell.
+    Empty,
+    /// `true` or `false`.
+    Bool,
+    /// A whole number, of any size.
+    Integer,
+    /// Any other number.
+    Float,
+    /// A number, `number` bytes long, followed by a unit, like `12.5mm`.
+    Quantity { number: usize, float: bool },
+    /// Anything else.
+    Text,
+}
+
+/// Returns how the unquoted cell `text` reads.
+pub(crate) fn shape(text: &str) -> Shape {
+    if text.is_empty() {
+        return Shape::Empty;
+    }
+    if text == "true" || text == "false" {
+        return Shape::Bool;
+    }
+    let Some((number, float)) = number(text) else {
+        return Shape::Text;
+    };
+    let unit = &text[number..];
+    if unit.is_empty() {
+        if float { Shape::Float } else { Shape::Integer }
+    } else if is_unit(unit) {
+        Shape::Quantity { number, float }
+    } else {
+        Shape::Text
+    }
+}
+
+/// Returns the length of the number `text` starts with, and whether it's a
+/// float: an optional `-`, digits, then an optional fraction and exponent.
+fn number(text: &str) -> Option<(usize, bool)> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        bytes[from.min(bytes.len())..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+
+    let mut len = usize::from(bytes.first() == Some(&b'-'));
+    let whole = digits(len);
+    if whole == 0 {
+        return None;
+    }
+    len += whole;
+
+    let mut float = false;
+    if bytes.get(len) == Some(&b'.') && digits(len + 1) > 0 {
+        len += 1 + digits(len + 1);
+        float = true;
+    }
+    if matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        let exponent = digits(len + 1 + sign);
+        if exponent > 0 {
+            len += 1 + sign + exponent;
+            float = true;
+        }
+    }
+    Some((len, float))
+}
+
+/// Returns whether `text` is a unit name: a letter or `_`, then letters,
+/// digits and `_`s.
+fn is_unit(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+/// Returns whether the first record, with `fields`, is a header: every cell
+/// of it is text, which a row of data rarely is.
+pub(crate) fn is_header(src: &str, fields: &[Field]) -> bool {
+    fields.iter().all(|field| {
+        let text = &src[field.value.clone()];
+        if field.quoted {
+            !unquote(text).is_empty()
+        } else {
+            shape(text) == Shape::Text
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes() {
+        assert_eq!(shape(""), Shape::Empty);
+        assert_eq!(shape("true"), Shape::Bool);
+        assert_eq!(shape("-42"), Shape::Integer);
+        assert_eq!(shape("99999999999999999999"), Shape::Integer);
+        assert_eq!(shape("1.5e-3"), Shape::Float);
+        assert_eq!(
+            shape("12.5mm"),
+            Shape::Quantity {
+                number: 4,
+                float: true
+            }
+        );
+        assert_eq!(
+            shape("3kg"),
+            Shape::Quantity {
+                number: 1,
+                float: false
+            }
+        );
+        assert_eq!(shape("1.5"), Shape::Float);
+        assert_eq!(shape("1."), Shape::Text);
+        assert_eq!(shape("12 mm"), Shape::Text);
+        assert_eq!(shape("v1"), Shape::Text);
+    }
+
+    #[test]
+    fn splits_quoted_fields() {
+        let src = "a, \"b,\"\"c\"\"\nd\" ,e\nf";
+        let (fields, end) = split(src, 0, b',');
+        let values: Vec<_> = fields.iter().map(|f| &src[f.value.clone()]).collect();
+        assert_eq!(values, ["a", "\"b,\"\"c\"\"\nd\"", "e"]);
+        assert_eq!(unquote(values[1]), "b,\"c\"\nd");
+        assert_eq!(&src[end..], "\nf");
+    }
+}
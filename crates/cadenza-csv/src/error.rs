@@ -0,0 +1,15 @@
+//! Error types for reading delimited data.
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum Error {
+    #[error("Parse error: {message}")]
+    Parse { message: String },
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,40 @@
+//! CSV and TSV parser as alternative Cadenza syntax.
+//!
+//! This crate treats delimited data as an alternative lexer/parser for
+//! Cadenza: a `.csv` or `.tsv` file parses to a `__table__` application that
+//! evaluates to a list of records, typed by their cells, so data files can be
+//! imported straight into Cadenza pipelines. Files too large to parse whole
+//! can be streamed a record at a time with a [`Reader`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cadenza_csv::parse;
+//! use cadenza_eval::{eval, Compiler, Env};
+//!
+//! let csv = "part,qty,length\nbolt,4,12.5mm\nnut,10,3mm\n";
+//! let parse_result = parse(csv);
+//! let root = parse_result.ast();
+//!
+//! let mut compiler = Compiler::new();
+//! let mut env = Env::with_standard_builtins();
+//! // A list of records: [{ part = "bolt", qty = 4, length = 12.5mm }, ...]
+//! let results = eval(&root, &mut env, &mut compiler);
+//! ```
+
+mod cell;
+pub mod error;
+pub mod reader;
+pub mod syntax;
+
+#[cfg(test)]
+pub mod testing;
+
+mod generated;
+
+pub use error::{Error, Result};
+pub use reader::{Cell, Reader, Record};
+pub use syntax::{CsvFrontend, TsvFrontend, parse, parse_tsv};
+
+#[cfg(test)]
+mod fuzz;
@@ -0,0 +1,274 @@
+//! A streaming reader for files too large to parse whole.
+//!
+//! [`parse`](crate::parse) builds a tree of the whole file, which is the
+//! right thing for a module but not for a multi-gigabyte export. A
+//! [`Reader`] reads one record at a time from any [`BufRead`], typing cells
+//! the same way the parser does, so a pipeline can work through a file in
+//! constant memory.
+//!
+//! # Example
+//!
+//! ```
+//! use cadenza_csv::{Cell, Reader};
+//!
+//! let input = "part,length\nbolt,12.5mm\n";
+//! let mut records = Reader::csv(input.as_bytes());
+//! let record = records.next().unwrap().unwrap();
+//! assert_eq!(record[1], ("length".to_string(), Cell::Quantity {
+//!     value: 12.5,
+//!     unit: "mm".to_string(),
+//! }));
+//! ```
+
+use crate::{
+    Error, Result,
+    cell::{self, Field, Shape},
+};
+use std::io::BufRead;
+
+/// A cell of a record, typed by its text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// A blank cell.
+    Empty,
+    Bool(bool),
+    Integer(i64),
+    /// A whole number too large for an `i64`.
+    BigInt(num_bigint::BigInt),
+    Float(f64),
+    /// A number with a unit suffix, like `12.5mm`.
+    Quantity {
+        value: f64,
+        unit: String,
+    },
+    /// Any other cell, or any quoted one.
+    String(String),
+}
+
+impl Cell {
+    fn new(src: &str, field: &Field) -> Self {
+        let text = &src[field.value.clone()];
+        if field.quoted {
+            return Cell::String(cell::unquote(text));
+        }
+        // The shapes only match text these parse
+        match cell::shape(text) {
+            Shape::Empty => Cell::Empty,
+            Shape::Bool => Cell::Bool(text == "true"),
+            Shape::Integer => match text.parse() {
+                Ok(n) => Cell::Integer(n),
+                Err(_) => Cell::BigInt(text.parse().expect("an integer")),
+            },
+            Shape::Float => Cell::Float(text.parse().expect("a float")),
+            Shape::Quantity { number, .. } => Cell::Quantity {
+                value: text[..number].parse().expect("a number"),
+                unit: text[number..].to_string(),
+            },
+            Shape::Text => Cell::String(text.to_string()),
+        }
+    }
+}
+
+/// A record: its cells, named by their columns, in column order.
+pub type Record = Vec<(String, Cell)>;
+
+/// Reads records from delimited data, one at a time.
+///
+/// The first record is the header if every cell of it is text, as with
+/// [`parse`](crate::parse); otherwise the columns are named `column1`,
+/// `column2`, ... Blank lines are skipped.
+#[derive(Debug)]
+pub struct Reader<R> {
+    input: R,
+    delimiter: u8,
+    columns: Option<Vec<String>>,
+    /// The text of the record being read.
+    buffer: String,
+    /// The number of lines read.
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Returns a reader of comma-separated values.
+    pub fn csv(input: R) -> Self {
+        Self::new(input, b',')
+    }
+
+    /// Returns a reader of tab-separated values.
+    pub fn tsv(input: R) -> Self {
+        Self::new(input, b'\t')
+    }
+
+    fn new(input: R, delimiter: u8) -> Self {
+        Self {
+            input,
+            delimiter,
+            columns: None,
+            buffer: String::new(),
+            line: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the names of the columns, once the first record is read.
+    pub fn columns(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
+
+    /// Reads the next record into the buffer, and returns its fields and the
+    /// line it starts on.
+    fn read_fields(&mut self) -> Result<Option<(Vec<Field>, usize)>> {
+        self.buffer.clear();
+        let mut start = self.line + 1;
+        loop {
+            if self.input.read_line(&mut self.buffer)? == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Err(Error::Parse {
+                    message: format!("line {start}: unterminated quoted field"),
+                });
+            }
+            self.line += 1;
+
+            if self
+                .buffer
+                .trim_start_matches([' ', '\t', '\r', '\n'])
+                .is_empty()
+            {
+                self.buffer.clear();
+                start = self.line + 1;
+                continue;
+            }
+
+            // A quoted field can span lines, so a record isn't over until
+            // its quotes are
+            let (fields, _) = cell::split(&self.buffer, 0, self.delimiter);
+            if fields.last().is_some_and(|field| field.unterminated) {
+                continue;
+            }
+            if fields.iter().any(|field| field.junk.is_some()) {
+                return Err(Error::Parse {
+                    message: format!("line {start}: expected a delimiter after a quoted field"),
+                });
+            }
+            return Ok(Some((fields, start)));
+        }
+    }
+
+    fn read(&mut self) -> Result<Option<Record>> {
+        loop {
+            let Some((fields, line)) = self.read_fields()? else {
+                return Ok(None);
+            };
+
+            let columns = match &self.columns {
+                Some(columns) => columns,
+                None if cell::is_header(&self.buffer, &fields) => {
+                    let names = fields
+                        .iter()
+                        .map(|field| match Cell::new(&self.buffer, field) {
+                            Cell::String(name) => name,
+                            cell => unreachable!("header cells are text, not {cell:?}"),
+                        })
+                        .collect();
+                    self.columns = Some(names);
+                    continue;
+                }
+                None => self
+                    .columns
+                    .insert((1..=fields.len()).map(|n| format!("column{n}")).collect()),
+            };
+
+            if fields.len() != columns.len() {
+                return Err(Error::Parse {
+                    message: format!(
+                        "line {line}: row has {} cells, but the table has {} columns",
+                        fields.len(),
+                        columns.len()
+                    ),
+                });
+            }
+            let record = columns
+                .iter()
+                .zip(&fields)
+                .map(|(name, field)| (name.clone(), Cell::new(&self.buffer, field)))
+                .collect();
+            return Ok(Some(record));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Record>;
+
+    /// Reads the next record. The reader stops after an error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read().transpose();
+        self.done = !matches!(record, Some(Ok(_)));
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_records_one_at_a_time() {
+        let input = "name\tqty\tok\n\n\"a\tb\"\t3\ttrue\nc\t\tfalse\n";
+        let mut records = Reader::tsv(input.as_bytes());
+        assert_eq!(
+            records.next().unwrap().unwrap(),
+            [
+                ("name".to_string(), Cell::String("a\tb".to_string())),
+                ("qty".to_string(), Cell::Integer(3)),
+                ("ok".to_string(), Cell::Bool(true)),
+            ]
+        );
+        assert_eq!(records.columns().unwrap(), ["name", "qty", "ok"]);
+        assert_eq!(records.next().unwrap().unwrap()[1].1, Cell::Empty);
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn names_columns_without_a_header() {
+        let input = "1,\"multi\nline\"\n2\n";
+        let mut records = Reader::csv(input.as_bytes());
+        assert_eq!(
+            records.next().unwrap().unwrap(),
+            [
+                ("column1".to_string(), Cell::Integer(1)),
+                (
+                    "column2".to_string(),
+                    Cell::String("multi\nline".to_string())
+                ),
+            ]
+        );
+        let error = records.next().unwrap().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parse error: line 3: row has 1 cells, but the table has 2 columns"
+        );
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn keeps_large_integers_exact() {
+        let input = "id\n9223372036854775807\n-99999999999999999999\n";
+        let cells: Vec<Cell> = Reader::csv(input.as_bytes())
+            .map(|record| record.unwrap().remove(0).1)
+            .collect();
+        assert_eq!(
+            cells,
+            [
+                Cell::Integer(i64::MAX),
+                Cell::BigInt("-99999999999999999999".parse().unwrap()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! CSV and TSV parser that produces Cadenza-compatible AST.
+//!
+//! This module treats delimited data as an alternative syntax for Cadenza. A
+//! file parses to a single `__table__` application, which evaluates to a list
+//! of records, so a data file can be imported like any other module.
+//!
+//! # Architecture
+//!
+//! - **Header**: The first record is the header if every cell of it is text.
+//!   It becomes a tuple of field names; a file without one gets an empty
+//!   tuple, and its fields are named `column1`, `column2`, ...
+//! - **Rows**: Each other record becomes a tuple of its cells
+//! - **Cells**: Typed by their text: numbers become number literals, numbers
+//!   with a unit suffix (`12.5mm`) become quantities, `true` and `false`
+//!   become booleans, blank cells become `()`, and anything else, or any
+//!   quoted cell, becomes a string
+//!
+//! # Example
+//!
+//! ```
+//! use cadenza_csv::parse;
+//!
+//! let csv = "name,height\nada,1.62meter\ngrace,1.55meter\n";
+//! let root = parse(csv).ast();
+//! // [[__table__, [__tuple__, name, height], [__tuple__, "ada", [meter, 1.62]], ...]]
+//! ```
+
+use crate::cell::{self, Field, Shape};
+use cadenza_syntax::{
+    frontend::Frontend,
+    parse::{Parse, ParseError},
+    span::Span,
+    token::Kind,
+};
+use cadenza_tree::GreenNodeBuilder;
+
+/// Parse CSV source into a Cadenza-compatible AST.
+pub fn parse(src: &str) -> Parse {
+    Parser::new(src, b',').parse()
+}
+
+/// Parse TSV source into a Cadenza-compatible AST.
+pub fn parse_tsv(src: &str) -> Parse {
+    Parser::new(src, b'\t').parse()
+}
+
+/// The CSV front-end, for `.csv` files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvFrontend;
+
+impl Frontend for CsvFrontend {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        parse(source)
+    }
+}
+
+/// The TSV front-end, for `.tsv` files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TsvFrontend;
+
+impl Frontend for TsvFrontend {
+    fn name(&self) -> &str {
+        "tsv"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tsv"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        parse_tsv(source)
+    }
+}
+
+struct Parser<'src> {
+    src: &'src str,
+    delimiter: u8,
+    pos: usize,
+    builder: GreenNodeBuilder,
+    errors: Vec<ParseError>,
+    /// The number of cells in the first record, and whether it's the header
+    /// or the first row, which every other row must have.
+    columns: Option<(usize, &'static str)>,
+}
+
+impl<'src> Parser<'src> {
+    fn new(src: &'src str, delimiter: u8) -> Self {
+        Self {
+            src,
+            delimiter,
+            pos: 0,
+            builder: GreenNodeBuilder::new(),
+            errors: Vec::new(),
+            columns: None,
+        }
+    }
+
+    fn parse(mut self) -> Parse {
+        self.builder.start_node(Kind::Root.into());
+        self.skip_blank_lines();
+
+        self.builder.start_node(Kind::Apply.into());
+        self.builder.start_node(Kind::ApplyReceiver.into());
+        self.builder.start_node(Kind::SyntheticTable.into());
+        self.builder.finish_node();
+        self.builder.finish_node();
+
+        let (fields, end) = cell::split(self.src, self.pos, self.delimiter);
+        if self.pos < self.src.len() && cell::is_header(self.src, &fields) {
+            self.columns = Some((fields.len(), "header"));
+            self.parse_record(fields, end, true);
+        } else {
+            self.builder.start_node(Kind::ApplyArgument.into());
+            self.empty_tuple();
+            self.builder.finish_node();
+        }
+
+        loop {
+            self.skip_blank_lines();
+            if self.pos >= self.src.len() {
+                break;
+            }
+            let (fields, end) = cell::split(self.src, self.pos, self.delimiter);
+            self.parse_record(fields, end, false);
+        }
+
+        self.builder.finish_node();
+        self.builder.finish_node();
+
+        Parse {
+            green: self.builder.finish(),
+            errors: self.errors,
+        }
+    }
+
+    /// Skips lines holding nothing but whitespace, along with the line
+    /// ending before them. A lone `\r` ends a line too, so a record always
+    /// moves past the line ending after it.
+    fn skip_blank_lines(&mut self) {
+        let start = self.pos;
+        let mut pos = self.pos;
+        loop {
+            let rest = &self.src[pos..];
+            let line = rest.find(['\n', '\r']).map_or(rest.len(), |end| end + 1);
+            if !rest[..line]
+                .trim_start_matches([' ', '\t', '\r', '\n'])
+                .is_empty()
+            {
+                break;
+            }
+            if line == 0 {
+                break;
+            }
+            pos += line;
+        }
+        if pos > start {
+            let text = &self.src[start..pos];
+            let kind = if text.contains(['\n', '\r']) {
+                Kind::Newline
+            } else {
+                Kind::Space
+            };
+            self.builder.token(kind.into(), text);
+            self.pos = pos;
+        }
+    }
+
+    /// Parses the record with `fields`, ending at `end`, as a tuple of its
+    /// cells. A header's text cells become identifiers.
+    fn parse_record(&mut self, fields: Vec<Field>, end: usize, header: bool) {
+        let start = self.pos;
+        let cells = fields.len();
+
+        self.builder.start_node(Kind::ApplyArgument.into());
+        self.builder.start_node(Kind::Apply.into());
+        self.builder.start_node(Kind::ApplyReceiver.into());
+        self.builder.start_node(Kind::SyntheticTuple.into());
+        self.builder.finish_node();
+        self.builder.finish_node();
+
+        for field in fields {
+            self.separator(field.value.start);
+            self.builder.start_node(Kind::ApplyArgument.into());
+            self.parse_cell(&field, header);
+            self.builder.finish_node();
+            self.pos = field.value.end;
+
+            if field.unterminated {
+                self.error("unterminated quoted field", field.value.clone());
+            }
+            if let Some(junk) = field.junk {
+                self.separator(junk.start);
+                self.builder.start_node(Kind::Error.into());
+                self.builder
+                    .token(Kind::StringContent.into(), &self.src[junk.clone()]);
+                self.builder.finish_node();
+                self.error("expected a delimiter after a quoted field", junk.clone());
+                self.pos = junk.end;
+            }
+        }
+        self.separator(end);
+
+        self.builder.finish_node();
+        self.builder.finish_node();
+
+        match self.columns {
+            None => self.columns = Some((cells, "first row")),
+            Some((columns, what)) if columns != cells => self.error(
+                format!("row has {cells} cells, but the {what} has {columns}"),
+                start..end,
+            ),
+            Some(_) => {}
+        }
+    }
+
+    /// Emits the delimiters and spaces up to `end`.
+    fn separator(&mut self, end: usize) {
+        while self.pos < end {
+            let start = self.pos;
+            let byte = self.src.as_bytes()[start];
+            let kind = match byte {
+                b',' => Kind::Comma,
+                b'\t' => Kind::Tab,
+                _ => Kind::Space,
+            };
+            self.pos += 1;
+            if kind == Kind::Space {
+                while self.pos < end && self.src.as_bytes()[self.pos] == b' ' {
+                    self.pos += 1;
+                }
+            }
+            self.builder.token(kind.into(), &self.src[start..self.pos]);
+        }
+    }
+
+    fn parse_cell(&mut self, field: &Field, header: bool) {
+        let text = &self.src[field.value.clone()];
+        if field.quoted {
+            return self.parse_quoted(text, !field.unterminated);
+        }
+
+        match cell::shape(text) {
+            Shape::Empty => self.empty_tuple(),
+            Shape::Text if header => self.identifier(text),
+            Shape::Text => self.literal(Kind::StringContent, text),
+            Shape::Bool => self.identifier(text),
+            Shape::Integer => self.literal(Kind::Integer, text),
+            Shape::Float => self.literal(Kind::Float, text),
+            Shape::Quantity { number, float } => {
+                // Written like Cadenza's own `12.5mm`: the unit applied to
+                // the number
+                self.builder.start_node(Kind::Apply.into());
+                self.builder.start_node(Kind::ApplyArgument.into());
+                let kind = if float { Kind::Float } else { Kind::Integer };
+                self.literal(kind, &text[..number]);
+                self.builder.finish_node();
+                self.builder.start_node(Kind::ApplyReceiver.into());
+                self.identifier(&text[number..]);
+                self.builder.finish_node();
+                self.builder.finish_node();
+            }
+        }
+    }
+
+    /// Parses the quoted cell `text` as a string. A `""` in it is an escaped
+    /// quote, which makes the cell an interpolation of the text around its
+    /// escapes: the first quote of each pair ends a part and the second is
+    /// dropped.
+    fn parse_quoted(&mut self, text: &str, terminated: bool) {
+        let inner = &text[1..];
+        let inner = if terminated {
+            &inner[..inner.len() - 1]
+        } else {
+            inner
+        };
+
+        if !inner.contains("\"\"") {
+            self.builder.start_node(Kind::Literal.into());
+            self.builder.token(Kind::StringStart.into(), "\"");
+            self.string_content(inner);
+            if terminated {
+                self.builder.token(Kind::StringEnd.into(), "\"");
+            }
+            self.builder.finish_node();
+            return;
+        }
+
+        self.builder.start_node(Kind::Apply.into());
+        self.builder.token(Kind::StringStart.into(), "\"");
+        self.builder.start_node(Kind::ApplyReceiver.into());
+        self.builder.start_node(Kind::SyntheticInterpolation.into());
+        self.builder.finish_node();
+        self.builder.finish_node();
+
+        let mut rest = inner;
+        while let Some(escape) = rest.find("\"\"") {
+            self.string_part(&rest[..=escape]);
+            self.builder.token(Kind::StringEnd.into(), "\"");
+            rest = &rest[escape + 2..];
+        }
+        if !rest.is_empty() {
+            self.string_part(rest);
+        }
+
+        if terminated {
+            self.builder.token(Kind::StringEnd.into(), "\"");
+        }
+        self.builder.finish_node();
+    }
+
+    fn string_part(&mut self, text: &str) {
+        self.builder.start_node(Kind::ApplyArgument.into());
+        self.builder.start_node(Kind::Literal.into());
+        self.string_content(text);
+        self.builder.finish_node();
+        self.builder.finish_node();
+    }
+
+    fn string_content(&mut self, text: &str) {
+        self.builder.start_node(Kind::StringContent.into());
+        self.builder.token(Kind::StringContent.into(), text);
+        self.builder.finish_node();
+    }
+
+    fn literal(&mut self, kind: Kind, text: &str) {
+        self.builder.start_node(Kind::Literal.into());
+        self.builder.start_node(kind.into());
+        self.builder.token(kind.into(), text);
+        self.builder.finish_node();
+        self.builder.finish_node();
+    }
+
+    fn identifier(&mut self, text: &str) {
+        self.builder.start_node(Kind::Identifier.into());
+        self.builder.token(Kind::Identifier.into(), text);
+        self.builder.finish_node();
+    }
+
+    /// Emits `()`, which takes no source text.
+    fn empty_tuple(&mut self) {
+        self.builder.start_node(Kind::Apply.into());
+        self.builder.start_node(Kind::ApplyReceiver.into());
+        self.builder.start_node(Kind::SyntheticTuple.into());
+        self.builder.finish_node();
+        self.builder.finish_node();
+        self.builder.finish_node();
+    }
+
+    fn error(&mut self, message: impl Into<String>, range: std::ops::Range<usize>) {
+        self.errors.push(ParseError {
+            span: Span::new(range.start, range.end),
+            message: message.into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadenza_eval::{Compiler, Env, Value};
+
+    fn eval(src: &str) -> Value {
+        let parsed = parse(src);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let mut results = cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        results.pop().unwrap()
+    }
+
+    #[test]
+    fn evaluates_to_a_list_of_records() {
+        let value = eval("part,qty,length\nbolt,4,12.5mm\n\"nut, hex\",10,\n");
+        assert_eq!(
+            value.to_string(),
+            "[{part = bolt, qty = 4, length = 12.5mm}, {part = nut, hex, qty = 10, length = nil}]"
+        );
+    }
+
+    #[test]
+    fn large_integers_are_exact() {
+        let value = eval("serial\n18446744073709551617\n-99999999999999999999\n");
+        assert_eq!(
+            value.to_string(),
+            "[{serial = 18446744073709551617}, {serial = -99999999999999999999}]"
+        );
+    }
+
+    #[test]
+    fn reports_ragged_rows() {
+        let parsed = parse("a,b\n1,2\n3\n");
+        let messages: Vec<_> = parsed.errors.iter().map(|e| &e.message).collect();
+        assert_eq!(messages, ["row has 1 cells, but the header has 2"]);
+    }
+
+    #[test]
+    fn ends_records_at_a_lone_carriage_return() {
+        let parsed = parse("a\rb\r\n\rc");
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.syntax().text().to_string(), "a\rb\r\n\rc");
+    }
+}
@@ -0,0 +1,36 @@
+//! Testing utilities for CSV parser.
+
+use cadenza_syntax::parse::Parse;
+
+/// Verify that all bytes in the source are covered by the CST `parse`
+/// builds.
+pub fn verify_cst_coverage(src: &str, parse: fn(&str) -> Parse) {
+    let parse_result = parse(src);
+    let cst = parse_result.syntax();
+
+    // Verify that CST covers all source bytes
+    let mut covered = vec![false; src.len()];
+
+    for token in cst.descendants_with_tokens() {
+        if let cadenza_tree::SyntaxElement::Token(token) = token {
+            let range = token.text_range();
+            for is_covered in covered
+                .iter_mut()
+                .take(range.end().into())
+                .skip(range.start().into())
+            {
+                *is_covered = true;
+            }
+        }
+    }
+
+    for (i, &is_covered) in covered.iter().enumerate() {
+        if !is_covered {
+            panic!(
+                "Byte at position {} is not covered by CST: {:?}",
+                i,
+                &src[i..i + 1]
+            );
+        }
+    }
+}
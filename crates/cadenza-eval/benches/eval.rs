@@ -0,0 +1,83 @@
+//! Evaluation benchmarks over the arithmetic-heavy test-data files.
+//!
+//! Besides timing, each file's allocations per evaluation are counted and
+//! printed once before the timings, since literal evaluation is expected not
+//! to allocate.
+
+use cadenza_eval::{Compiler, Env};
+use cadenza_syntax::{ast::Root, parse::parse};
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts allocations made through the global allocator.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The arithmetic-heavy corpus files, by name.
+fn corpus() -> Vec<(String, Root)> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/test-data");
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("arith-") || name == "example-03-arithmetic.cdz"
+        })
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let root = parse(&std::fs::read_to_string(&path).unwrap()).ast();
+            (name, root)
+        })
+        .collect()
+}
+
+fn eval(root: &Root, env: &mut Env, compiler: &mut Compiler) {
+    black_box(cadenza_eval::eval(root, env, compiler));
+}
+
+fn arithmetic(c: &mut Criterion) {
+    let corpus = corpus();
+    let mut group = c.benchmark_group("arithmetic");
+    for (name, root) in &corpus {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        // Warm up once so one-time setup isn't counted
+        eval(root, &mut env, &mut compiler);
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        eval(root, &mut env, &mut compiler);
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!("{name}: {allocations} allocations per evaluation");
+
+        group.bench_function(name.as_str(), |b| {
+            b.iter(|| eval(root, &mut env, &mut compiler))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, arithmetic);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+//! Workspace symbol search over a synthetic workspace of 100 files with 50
+//! definitions each.
+
+use cadenza_eval::symbol_index::SymbolSearch;
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+
+const FILES: usize = 100;
+const DEFINITIONS: usize = 50;
+
+/// Returns the source of file `file`, with `version` baked into one name so
+/// edits change its definitions.
+fn source(file: usize, version: usize) -> String {
+    let mut source = String::new();
+    for i in 0..DEFINITIONS {
+        source.push_str(&format!("fn parse_item_{file}_{i} x = x + {i}\n"));
+        source.push_str(&format!("let total_{file}_{i} = {i}\n"));
+    }
+    source.push_str(&format!("fn edited_{version} x = x\n"));
+    source
+}
+
+fn workspace() -> SymbolSearch {
+    let mut search = SymbolSearch::new();
+    for file in 0..FILES {
+        search.set_file(&format!("file_{file}.cdz"), &source(file, 0));
+    }
+    search
+}
+
+fn symbols(c: &mut Criterion) {
+    let mut search = workspace();
+    // Build every index before timing queries
+    search.search("", usize::MAX);
+
+    let mut group = c.benchmark_group("symbols");
+    for query in ["p", "parse_item_4", "item_42_1", "zzz"] {
+        group.bench_function(format!("search {query}"), |b| {
+            b.iter(|| black_box(search.search(query, 100)))
+        });
+    }
+
+    let mut version = 0;
+    group.bench_function("edit and search", |b| {
+        b.iter(|| {
+            version += 1;
+            search.set_file("file_0.cdz", &source(0, version));
+            black_box(search.search("edited", 100))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, symbols);
+criterion_main!(benches);
@@ -2,6 +2,13 @@ pub struct Example {
     pub name: String,
     pub id: String,
     pub display_name: String,
+    /// The first line of the header comment
+    pub title: String,
+    /// The rest of the header comment, up to the first line that isn't a
+    /// comment
+    pub description: String,
+    /// The comma-separated topics on the header's `Tags:` line
+    pub tags: Vec<String>,
     pub src: String,
 }
 
@@ -53,10 +60,27 @@ impl Example {
             let id = parts[2..].join("-");
 
             let src = std::fs::read_to_string(&path).unwrap();
+            let mut header = src
+                .lines()
+                .map_while(|line| line.strip_prefix('#'))
+                .map(str::trim);
+            let title = header.next().unwrap_or_default().to_string();
+            let mut description = Vec::new();
+            let mut tags = Vec::new();
+            for line in header.filter(|line| !line.is_empty()) {
+                match line.strip_prefix("Tags:") {
+                    Some(list) => tags.extend(list.split(',').map(|tag| tag.trim().to_string())),
+                    None => description.push(line),
+                }
+            }
+            let description = description.join(" ");
             examples.push(Example {
                 name: file_name.to_string(),
                 id,
                 display_name,
+                title,
+                description,
+                tags,
                 src,
             });
         }
@@ -86,6 +110,9 @@ pub fn generate_typescript() -> String {
     w!("export interface Example {{");
     w!("  id: string;");
     w!("  name: string;");
+    w!("  title: string;");
+    w!("  description: string;");
+    w!("  tags: string[];");
     w!("  source: string;");
     w!("}}");
     w!("");
@@ -95,6 +122,9 @@ pub fn generate_typescript() -> String {
         w!("  {{");
         w!("    id: {:?},", example.id);
         w!("    name: {:?},", example.display_name);
+        w!("    title: {:?},", example.title);
+        w!("    description: {:?},", example.description);
+        w!("    tags: {:?},", example.tags);
         w!("    source: {},", escape_typescript_string(&example.src));
         w!("  }},");
     }
@@ -104,6 +134,43 @@ pub fn generate_typescript() -> String {
     out
 }
 
+/// Generate the Rust table of examples behind `cadenza_eval::examples`
+pub fn generate_rust() -> String {
+    let examples = Example::load("test-data");
+    let mut out = String::new();
+
+    macro_rules! w {
+        ($($tt:tt)*) => {
+            out.push_str(&format!($($tt)*));
+            out.push('\n');
+        };
+    }
+
+    w!("// This file is auto-generated by the build script.");
+    w!("// Do not edit manually - changes will be overwritten.");
+    w!("");
+    w!("use crate::examples::Example;");
+    w!("");
+    w!("pub static EXAMPLES: &[Example] = &[");
+
+    for example in examples.iter() {
+        w!("Example {{");
+        w!("id: {:?},", example.id);
+        w!("title: {:?},", example.title);
+        w!("description: {:?},", example.description);
+        w!("tags: &{:?},", example.tags);
+        w!(
+            "source: include_str!(\"../../test-data/{}.cdz\"),",
+            example.name
+        );
+        w!("}},");
+    }
+
+    w!("];");
+
+    out
+}
+
 fn escape_typescript_string(s: &str) -> String {
     format!(
         "`{}`",
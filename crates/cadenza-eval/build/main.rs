@@ -16,6 +16,11 @@ pub fn main() {
     // Generate TypeScript examples file in cadenza-eval's generated directory
     let typescript_output = examples::generate_typescript();
     write("src/generated/examples.ts", typescript_output).unwrap();
+    write(
+        "src/generated/examples.rs",
+        rustfmt(&examples::generate_rust()),
+    )
+    .unwrap();
 
     println!("cargo:rerun-if-changed=test-data/");
 }
@@ -41,6 +46,7 @@ fn rustfmt(code: &str) -> String {
 }
 
 static GENERATED: &str = r#"
+pub mod examples;
 #[cfg(test)]
 mod test_data;
 "#;
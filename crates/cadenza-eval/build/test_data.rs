@@ -11,7 +11,8 @@ pub fn tests() -> String {
     w!("use crate::testing as t;");
     w!("use insta::{{assert_debug_snapshot as s, assert_snapshot as ss}};");
 
-    // Generate eval, ast, ir, and wat tests for each example
+    // Generate eval, ast, ir, and wat tests for each example, and a rendered
+    // diagnostics test for each error case
     for Example { name, src } in examples.iter() {
         w!("mod {name} {{");
         w!("    use super::*;");
@@ -35,12 +36,88 @@ pub fn tests() -> String {
         let wat_name = format!("{name}_wat");
         w!("        ss!({wat_name:?}, t::wat(SRC), SRC);");
         w!("    }}");
+        if name.starts_with("error_") {
+            w!("    #[test]");
+            w!("    fn diagnostics() {{");
+            let diagnostics_name = format!("{name}_diagnostics");
+            w!("        ss!({diagnostics_name:?}, t::diagnostics(SRC), SRC);");
+            w!("    }}");
+        }
+        w!("}}");
+    }
+
+    // Generate eval, ir, and wat tests for each multi-file example
+    for ModuleExample { name, modules } in ModuleExample::load("test-data").iter() {
+        let src: String = modules
+            .iter()
+            .map(|(module, src)| format!("# {module}.cdz\n{src}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        w!("mod {name} {{");
+        w!("    use super::*;");
+        w!("    const SRC: &str = {src:?};");
+        w!("    const MODULES: &[(&str, &str)] = &{modules:?};");
+        w!("    #[test]");
+        w!("    fn eval() {{");
+        w!("        s!({name:?}, t::eval_modules(MODULES), SRC);");
+        w!("    }}");
+        w!("    #[test]");
+        w!("    fn ir() {{");
+        let ir_name = format!("{name}_ir");
+        w!("        ss!({ir_name:?}, t::ir_modules(MODULES), SRC);");
+        w!("    }}");
+        w!("    #[test]");
+        w!("    fn wat() {{");
+        let wat_name = format!("{name}_wat");
+        w!("        ss!({wat_name:?}, t::wat_modules(MODULES), SRC);");
+        w!("    }}");
         w!("}}");
     }
 
     out
 }
 
+/// A directory of `.cdz` files compiled together as one module graph.
+pub struct ModuleExample {
+    pub name: String,
+    /// Module names (file stems) and sources, sorted by name.
+    pub modules: Vec<(String, String)>,
+}
+
+impl ModuleExample {
+    fn load(subdir: &str) -> Box<[ModuleExample]> {
+        let dir = format!("{}/{}/", env!("CARGO_MANIFEST_DIR"), subdir);
+        let mut examples = Vec::new();
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return examples.into();
+        };
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if !path.is_dir() {
+                continue;
+            }
+            let name = path
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .replace('-', "_");
+            let mut modules = Vec::new();
+            for file in std::fs::read_dir(&path).unwrap() {
+                let file = file.unwrap().path();
+                if file.extension().is_none_or(|ext| ext != "cdz") {
+                    continue;
+                }
+                let module = file.file_stem().unwrap().to_str().unwrap().to_string();
+                modules.push((module, std::fs::read_to_string(file).unwrap()));
+            }
+            modules.sort();
+            examples.push(ModuleExample { name, modules });
+        }
+        examples.into()
+    }
+}
+
 pub struct Example {
     pub name: String,
     pub src: String,
@@ -0,0 +1,132 @@
+// A JavaScript implementation of the Cadenza host interface
+// (cadenza-host.wit), for running generated modules in browsers and Node.
+//
+//   import { createHost } from "./cadenza-host.js";
+//
+//   const host = createHost({ print: (line) => console.log(line) });
+//   const { module, instance } = await WebAssembly.instantiate(bytes, host.imports);
+//   host.bind(instance, module);
+//   try {
+//     instance.exports.main();
+//   } catch (error) {
+//     console.error(host.describeTrap(error));
+//   }
+//
+// `host.describeTrap(error)` maps the frames of a trap back to Cadenza source
+// with the module's source map (the `cadenza.sourcemap` custom section).
+// A fallible function returns a status after its results, nonzero when it
+// failed; `host.describeFailure(status)` says where and why.
+//
+// Strings live here, in a table indexed by handle; `host.text(handle)` reads
+// one, such as a string returned by an exported function.
+//
+// `exit` and `abort` end the program by throwing out of the call into the
+// module: an `Exit` with the status for `exit`, and an `Error` for `abort`.
+
+export const VERSION = "0.1.0";
+
+export class Exit extends Error {
+  constructor(code) {
+    super(`exited with status ${code}`);
+    this.code = code;
+  }
+}
+
+export function createHost({ print = (line) => console.log(line) } = {}) {
+  const texts = [];
+  const decoder = new TextDecoder();
+  let memory = null;
+  let sites = [];
+  let failures = [];
+
+  const text = (handle) => {
+    if (!(handle in texts)) {
+      throw new RangeError(`invalid string handle ${handle}`);
+    }
+    return texts[handle];
+  };
+  const intern = (value) => texts.push(value) - 1;
+  const module = (name) => `cadenza:host/${name}@${VERSION}`;
+  // The site of the instruction at `offset`, or else of the closest mapped
+  // instruction before it
+  const lookup = (offset) => sites.findLast((site) => site.offset <= offset);
+
+  const imports = {
+    [module("math")]: {
+      "sin": Math.sin,
+      "cos": Math.cos,
+      "tan": Math.tan,
+      "asin": Math.asin,
+      "acos": Math.acos,
+      "atan": Math.atan,
+      "atan2": Math.atan2,
+      "exp": Math.exp,
+      "ln": Math.log,
+      "log10": Math.log10,
+      "pow": Math.pow,
+    },
+    [module("strings")]: {
+      "from-utf8": (ptr, len) => {
+        if (memory === null) {
+          throw new Error("call host.bind(instance) before running the module");
+        }
+        return intern(decoder.decode(new Uint8Array(memory.buffer, ptr, len)));
+      },
+      "concat": (a, b) => intern(text(a) + text(b)),
+      "equals": (a, b) => (text(a) === text(b) ? 1 : 0),
+      // Integers are i64, which JavaScript sees as BigInt
+      "length": (s) => BigInt([...text(s)].length),
+      "from-int": (x) => intern(x.toString()),
+      "from-float": (x) => intern(String(x)),
+      "contains": (s, needle) => (text(s).includes(text(needle)) ? 1 : 0),
+      "to-upper": (s) => intern(text(s).toUpperCase()),
+    },
+    [module("io")]: {
+      "print": (s) => print(text(s)),
+    },
+    [module("process")]: {
+      "exit": (code) => {
+        throw new Exit(Number(code));
+      },
+      "abort": (message) => {
+        throw new Error(`aborted: ${text(message)}`);
+      },
+    },
+  };
+
+  return {
+    imports,
+    text,
+    bind(instance, wasmModule = null) {
+      memory = instance.exports.memory ?? null;
+      const [section] = wasmModule
+        ? WebAssembly.Module.customSections(wasmModule, "cadenza.sourcemap")
+        : [];
+      const sourceMap = section ? JSON.parse(decoder.decode(section)) : {};
+      sites = sourceMap.sites ?? [];
+      failures = sourceMap.failures ?? [];
+    },
+    describeTrap(error) {
+      // Engines name the module offset of each wasm frame's instruction as
+      // `wasm-function[N]:0xOFFSET`, innermost first
+      const offsets = [...String(error.stack).matchAll(/wasm-function\[\d+\]:0x([0-9a-f]+)/g)]
+        .map((match) => parseInt(match[1], 16));
+      // A failed check traps at its own site, which has the check's message
+      const check = sites.find((site) => site.offset === offsets[0] && site.message);
+      const frames = offsets
+        .map(lookup)
+        .filter((site) => site !== undefined)
+        .map((site) => `  at ${site.function} (${site.file}:${site.line}:${site.column})`);
+      return [`error: trap: ${check?.message ?? error.message}`, ...frames].join("\n");
+    },
+    describeFailure(status) {
+      // Status N is the module's failure N - 1
+      const failure = failures[status - 1];
+      if (failure === undefined) {
+        return `error: failure: unknown status ${status}`;
+      }
+      const { message, function: name, file, line, column } = failure;
+      return [`error: failure: ${message}`, `  at ${name} (${file}:${line}:${column})`].join("\n");
+    },
+  };
+}
@@ -0,0 +1,202 @@
+//! A wasmtime implementation of the Cadenza host interface
+//! (`cadenza-host.wit`), for running generated modules from Rust.
+//!
+//! This file isn't part of the crate, so that `cadenza-eval` doesn't depend
+//! on wasmtime; copy it into a crate that does:
+//!
+//! ```ignore
+//! let engine = wasmtime::Engine::default();
+//! let module = wasmtime::Module::new(&engine, &wasm)?;
+//! let mut linker = wasmtime::Linker::new(&engine);
+//! cadenza_host::add_to_linker(&mut linker)?;
+//! let mut store = wasmtime::Store::new(&engine, cadenza_host::Host::default());
+//! let instance = linker.instantiate(&mut store, &module)?;
+//! let main = instance.get_typed_func::<(), i64>(&mut store, "main")?;
+//! main.call(&mut store, ())?;
+//! assert_eq!(store.data().output, ["hello"]);
+//! ```
+//!
+//! When a call traps, [`trap_frames`] finds where, for the source map the
+//! module embeds:
+//!
+//! ```ignore
+//! if let Err(error) = main.call(&mut store, ()) {
+//!     let source_map = cadenza_eval::ir::SourceMap::read(&wasm)?.unwrap_or_default();
+//!     if let Some((message, offsets)) = cadenza_host::trap_frames(&error) {
+//!         eprintln!("{}", source_map.trap_diagnostic(&message, &offsets));
+//!     }
+//! }
+//! ```
+//!
+//! Generated modules use the WebAssembly GC, reference types and tail call
+//! proposals, which need to be enabled in the engine's `Config`.
+
+use wasmtime::{Caller, Error, Extern, Linker, Result, Trap, WasmBacktrace, bail};
+
+/// The version of the host interface this implements.
+pub const VERSION: &str = "0.1.0";
+
+/// The host's state: the strings, indexed by handle, the printed lines and
+/// the exit status.
+#[derive(Debug, Default)]
+pub struct Host {
+    texts: Vec<String>,
+    /// Every line printed, in order.
+    pub output: Vec<String>,
+    /// The status the module called `exit` with, which ends the call into
+    /// the module with an error.
+    pub exit_status: Option<i32>,
+}
+
+impl Host {
+    /// Returns the string with `handle`, such as one returned by an export.
+    pub fn text(&self, handle: u32) -> Result<&str> {
+        match self.texts.get(handle as usize) {
+            Some(text) => Ok(text),
+            None => bail!("invalid string handle {handle}"),
+        }
+    }
+
+    fn intern(&mut self, text: String) -> u32 {
+        self.texts.push(text);
+        (self.texts.len() - 1) as u32
+    }
+}
+
+/// Returns the message of the trap `error`, if it is one, and the module
+/// offset of each frame's instruction, innermost first.
+///
+/// Backtraces are on by default; without one, there are no offsets.
+pub fn trap_frames(error: &Error) -> Option<(String, Vec<u32>)> {
+    let trap = error.downcast_ref::<Trap>()?;
+    let offsets = error
+        .downcast_ref::<WasmBacktrace>()
+        .map(|backtrace| {
+            backtrace
+                .frames()
+                .iter()
+                .filter_map(|frame| frame.module_offset())
+                .map(|offset| offset as u32)
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((trap.to_string(), offsets))
+}
+
+fn module(interface: &str) -> String {
+    format!("cadenza:host/{interface}@{VERSION}")
+}
+
+/// Defines every function of the host interface in `linker`.
+pub fn add_to_linker(linker: &mut Linker<Host>) -> Result<()> {
+    let math = module("math");
+    linker.func_wrap(&math, "sin", |x: f64| x.sin())?;
+    linker.func_wrap(&math, "cos", |x: f64| x.cos())?;
+    linker.func_wrap(&math, "tan", |x: f64| x.tan())?;
+    linker.func_wrap(&math, "asin", |x: f64| x.asin())?;
+    linker.func_wrap(&math, "acos", |x: f64| x.acos())?;
+    linker.func_wrap(&math, "atan", |x: f64| x.atan())?;
+    linker.func_wrap(&math, "atan2", |y: f64, x: f64| y.atan2(x))?;
+    linker.func_wrap(&math, "exp", |x: f64| x.exp())?;
+    linker.func_wrap(&math, "ln", |x: f64| x.ln())?;
+    linker.func_wrap(&math, "log10", |x: f64| x.log10())?;
+    linker.func_wrap(&math, "pow", |base: f64, exponent: f64| base.powf(exponent))?;
+
+    let strings = module("strings");
+    linker.func_wrap(
+        &strings,
+        "from-utf8",
+        |mut caller: Caller<'_, Host>, ptr: u32, len: u32| -> Result<u32> {
+            let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                bail!("module doesn't export its memory");
+            };
+            let start = ptr as usize;
+            let Some(bytes) = memory.data(&caller).get(start..start + len as usize) else {
+                bail!("string out of bounds");
+            };
+            let text = std::str::from_utf8(bytes)?.to_string();
+            Ok(caller.data_mut().intern(text))
+        },
+    )?;
+    linker.func_wrap(
+        &strings,
+        "concat",
+        |mut caller: Caller<'_, Host>, a: u32, b: u32| -> Result<u32> {
+            let host = caller.data_mut();
+            let text = format!("{}{}", host.text(a)?, host.text(b)?);
+            Ok(host.intern(text))
+        },
+    )?;
+    linker.func_wrap(
+        &strings,
+        "equals",
+        |caller: Caller<'_, Host>, a: u32, b: u32| -> Result<u32> {
+            let host = caller.data();
+            Ok((host.text(a)? == host.text(b)?) as u32)
+        },
+    )?;
+    linker.func_wrap(
+        &strings,
+        "length",
+        |caller: Caller<'_, Host>, s: u32| -> Result<i64> {
+            Ok(caller.data().text(s)?.chars().count() as i64)
+        },
+    )?;
+    linker.func_wrap(
+        &strings,
+        "from-int",
+        |mut caller: Caller<'_, Host>, x: i64| caller.data_mut().intern(x.to_string()),
+    )?;
+    linker.func_wrap(
+        &strings,
+        "from-float",
+        |mut caller: Caller<'_, Host>, x: f64| caller.data_mut().intern(x.to_string()),
+    )?;
+    linker.func_wrap(
+        &strings,
+        "contains",
+        |caller: Caller<'_, Host>, s: u32, needle: u32| -> Result<u32> {
+            let host = caller.data();
+            Ok(host.text(s)?.contains(host.text(needle)?) as u32)
+        },
+    )?;
+    linker.func_wrap(
+        &strings,
+        "to-upper",
+        |mut caller: Caller<'_, Host>, s: u32| -> Result<u32> {
+            let host = caller.data_mut();
+            let text = host.text(s)?.to_uppercase();
+            Ok(host.intern(text))
+        },
+    )?;
+
+    let io = module("io");
+    linker.func_wrap(
+        &io,
+        "print",
+        |mut caller: Caller<'_, Host>, s: u32| -> Result<()> {
+            let host = caller.data_mut();
+            let line = host.text(s)?.to_string();
+            host.output.push(line);
+            Ok(())
+        },
+    )?;
+
+    let process = module("process");
+    linker.func_wrap(
+        &process,
+        "exit",
+        |mut caller: Caller<'_, Host>, code: i64| -> Result<()> {
+            caller.data_mut().exit_status = Some(code as i32);
+            bail!("exited with status {code}")
+        },
+    )?;
+    linker.func_wrap(
+        &process,
+        "abort",
+        |caller: Caller<'_, Host>, message: u32| -> Result<()> {
+            bail!("aborted: {}", caller.data().text(message)?)
+        },
+    )?;
+    Ok(())
+}
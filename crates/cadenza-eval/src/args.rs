@@ -0,0 +1,150 @@
+//! Command-line flag parsing.
+//!
+//! `argparse flags args` parses a program's arguments against a record of
+//! flags and their defaults, so scripts don't each reimplement argument
+//! handling. The type of each default decides how its flag is parsed:
+//!
+//! - A boolean is a switch: `--verbose` sets it to `true`
+//! - An integer, float or string takes a value: `--count 3` or `--count=3`
+//!
+//! A field `dry_run` is written `--dry-run` (or `--dry_run`). Arguments that
+//! aren't flags, and everything after `--`, are collected in order into the
+//! `args` field of the result:
+//!
+//! ```cadenza
+//! fn main args =
+//!     let opts = (argparse { verbose = false, layers = 1 } args)?
+//!     if opts.verbose then opts.args else opts.layers
+//! ```
+//!
+//! Like the [host functions](crate::system), bad input is returned as
+//! `Err message` rather than raised: an unknown flag, a missing value, or a
+//! value that doesn't parse.
+
+use crate::{
+    diagnostic::{Diagnostic, Result},
+    interner::InternedString,
+    system::{err, expect_string, ok},
+    value::{BuiltinFn, Type, Value},
+};
+
+/// Creates the `argparse` builtin: `argparse flags args` returns `flags` with
+/// the values given in `args`, plus the positional `args`.
+pub fn builtin_argparse() -> BuiltinFn {
+    BuiltinFn {
+        name: "argparse",
+        signature: Type::function(
+            vec![Type::Record(vec![]), Type::list(Type::String)],
+            Type::Unknown,
+        ),
+        func: |args, _ctx| {
+            let [flags, args] = args else {
+                return Err(Diagnostic::arity(2, args.len()));
+            };
+            let Value::Record {
+                type_name: None,
+                fields: flags,
+            } = flags
+            else {
+                return Err(Diagnostic::type_error(
+                    Type::Record(vec![]),
+                    flags.type_of(),
+                ));
+            };
+            let args = match args {
+                Value::List(items) => items
+                    .iter()
+                    .map(expect_string)
+                    .collect::<Result<Vec<_>>>()?,
+                other => {
+                    return Err(Diagnostic::type_error(
+                        Type::list(Type::String),
+                        other.type_of(),
+                    ));
+                }
+            };
+            for (name, default) in flags {
+                if &**name == "args" {
+                    return Err(Diagnostic::syntax(
+                        "the `args` field holds the positional arguments and can't be a flag",
+                    ));
+                }
+                if !matches!(
+                    default,
+                    Value::Bool(_) | Value::Integer(_) | Value::Float(_) | Value::String(_)
+                ) {
+                    return Err(Diagnostic::syntax(format!(
+                        "flag {name} must default to a boolean, number or string, not {}",
+                        default.type_of()
+                    )));
+                }
+            }
+            Ok(match parse(flags.clone(), &args) {
+                Ok(parsed) => ok(parsed),
+                Err(message) => err(message),
+            })
+        },
+    }
+}
+
+/// Returns the native functions that live in the prelude: `argparse`.
+pub fn prelude_builtins() -> Vec<BuiltinFn> {
+    vec![builtin_argparse()]
+}
+
+/// Parses `args` into `flags`, which hold the defaults, returning the parsed
+/// record or a message describing the first bad argument.
+fn parse(
+    mut flags: Vec<(InternedString, Value)>,
+    args: &[&str],
+) -> std::result::Result<Value, String> {
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        if arg == "--" {
+            positional.extend(
+                args.by_ref()
+                    .map(|arg| Value::String(arg.to_string().into())),
+            );
+            break;
+        }
+        let Some(flag) = arg.strip_prefix("--") else {
+            positional.push(Value::String(arg.to_string().into()));
+            continue;
+        };
+        let (flag, inline) = match flag.split_once('=') {
+            Some((flag, value)) => (flag, Some(value)),
+            None => (flag, None),
+        };
+        let name = flag.replace('-', "_");
+        let Some((_, value)) = flags.iter_mut().find(|(field, _)| **field == *name) else {
+            return Err(format!("unknown flag --{flag}"));
+        };
+        if let Value::Bool(switch) = value {
+            if inline.is_some() {
+                return Err(format!("--{flag} doesn't take a value"));
+            }
+            *switch = true;
+            continue;
+        }
+        let Some(text) = inline.or_else(|| args.next().copied()) else {
+            return Err(format!("--{flag} expects a value"));
+        };
+        *value = match value {
+            Value::Integer(_) => text
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("--{flag} expects an integer, got {text:?}"))?,
+            Value::Float(_) => text
+                .parse()
+                .map(Value::Float)
+                .map_err(|_| format!("--{flag} expects a number, got {text:?}"))?,
+            _ => Value::String(text.to_string().into()),
+        };
+    }
+    flags.push(("args".into(), Value::List(positional)));
+    Ok(Value::Record {
+        type_name: None,
+        fields: flags,
+    })
+}
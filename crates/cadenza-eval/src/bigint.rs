@@ -0,0 +1,190 @@
+//! Arbitrary-precision integers.
+//!
+//! An integer is an `i64` ([`Value::Integer`]) until an operation's result
+//! doesn't fit in one. Then it's a [`BigInt`] ([`Value::BigInt`]), and a
+//! result that fits again goes back to an `i64`. Both have the type
+//! `Integer`, so the representation never shows in a program:
+//!
+//! ```cadenza
+//! 9223372036854775807 + 1                  # 9223372036854775808
+//! (9223372036854775807 + 1) - 1            # an i64 again
+//! 340282366920938463463374607431768211456 % 1000   # 456
+//! ```
+//!
+//! Integer literals too large for an `i64` are big integers too. The
+//! arithmetic itself is [`num_bigint`]'s.
+//!
+//! Compiled code has 64-bit integers only: generating IR for a literal that
+//! doesn't fit is an error, and an operation that overflows traps with
+//! "integer overflow" where the evaluator would promote (see
+//! [`crate::ir`]).
+//!
+//! [`Value::Integer`]: crate::Value::Integer
+//! [`Value::BigInt`]: crate::Value::BigInt
+
+use num_traits::{ToPrimitive, Zero};
+use std::{
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// An integer of any size.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigInt(num_bigint::BigInt);
+
+impl BigInt {
+    /// Returns whether the integer is zero.
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Returns whether the integer is below zero.
+    pub fn is_negative(&self) -> bool {
+        self.0.sign() == num_bigint::Sign::Minus
+    }
+
+    /// Returns the integer as an `i64`, if it fits in one.
+    pub fn to_i64(&self) -> Option<i64> {
+        self.0.to_i64()
+    }
+
+    /// Returns the nearest float to the integer.
+    pub fn to_f64(&self) -> f64 {
+        // Always `Some`: integers too large for a float convert to infinity
+        self.0.to_f64().unwrap_or(f64::NAN)
+    }
+
+    /// Parses the digits `s` in `radix`, after an optional `-` or `+`.
+    ///
+    /// Returns `None` if `s` has no digits or a digit out of range.
+    pub fn parse(s: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        // `parse_bytes` would also skip `_` separators
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return None;
+        }
+        let magnitude = num_bigint::BigInt::parse_bytes(digits.as_bytes(), radix)?;
+        Some(Self(if negative { -magnitude } else { magnitude }))
+    }
+
+    /// Divides, rounding toward zero, and returns the quotient and the
+    /// remainder, which has the sign of `self`.
+    ///
+    /// Returns `None` if `rhs` is zero.
+    pub fn div_rem(&self, rhs: &Self) -> Option<(Self, Self)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        Some((Self(&self.0 / &rhs.0), Self(&self.0 % &rhs.0)))
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> Self {
+        Self(n.into())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: &BigInt) -> BigInt {
+        BigInt(&self.0 + &rhs.0)
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: &BigInt) -> BigInt {
+        BigInt(&self.0 - &rhs.0)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: &BigInt) -> BigInt {
+        BigInt(&self.0 * &rhs.0)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt(-&self.0)
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(n: i128) -> BigInt {
+        BigInt::parse(&n.to_string(), 10).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_text_and_i64() {
+        bolero::check!().with_type::<i128>().for_each(|&n| {
+            assert_eq!(big(n).to_string(), n.to_string());
+            assert_eq!(big(n).to_i64(), i64::try_from(n).ok());
+            let sign = if n < 0 { "-" } else { "" };
+            let hex = format!("{sign}{:x}", n.unsigned_abs());
+            assert_eq!(BigInt::parse(&hex, 16), Some(big(n)));
+        });
+        bolero::check!().with_type::<i64>().for_each(|&n| {
+            assert_eq!(BigInt::from(n).to_i64(), Some(n));
+        });
+        for invalid in ["", "-", "12a", "1_000", "+-1"] {
+            assert!(BigInt::parse(invalid, 10).is_none(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn matches_i128_arithmetic() {
+        bolero::check!()
+            .with_type::<(i128, i128)>()
+            .for_each(|&(a, b)| {
+                let (x, y) = (big(a), big(b));
+                if let Some(sum) = a.checked_add(b) {
+                    assert_eq!(&x + &y, big(sum), "{a} + {b}");
+                }
+                if let Some(difference) = a.checked_sub(b) {
+                    assert_eq!(&x - &y, big(difference), "{a} - {b}");
+                }
+                if let Some(product) = a.checked_mul(b) {
+                    assert_eq!(&x * &y, big(product), "{a} * {b}");
+                }
+                if let Some(negated) = a.checked_neg() {
+                    assert_eq!(-&x, big(negated), "-{a}");
+                }
+                assert_eq!(x.cmp(&y), a.cmp(&b), "{a} <=> {b}");
+                assert_eq!(x.is_negative(), a < 0);
+                match (a.checked_div(b), a.checked_rem(b)) {
+                    (Some(quotient), Some(remainder)) => {
+                        let (q, r) = x.div_rem(&y).unwrap();
+                        assert_eq!((q, r), (big(quotient), big(remainder)), "{a} / {b}");
+                    }
+                    _ if b == 0 => assert!(x.div_rem(&y).is_none()),
+                    _ => {}
+                }
+            });
+    }
+}
@@ -5,9 +5,38 @@
 //! API to register definitions, emit IR, etc.
 
 use crate::{
-    diagnostic::Diagnostic, interner::InternedString, ir::IrGenerator, map::Map,
-    trait_registry::TraitRegistry, typeinfer::TypeInferencer, unit::UnitRegistry, value::Value,
+    coverage::Coverage,
+    derive,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, DiagnosticKind, DiagnosticLevel, Result},
+    interner::InternedString,
+    ir::{
+        FunctionId, IrExport, IrExportKind, IrGenerator, IrInstr, IrModule, OptimizationPipeline,
+        host::{self, HostFunction},
+    },
+    map::Map,
+    memo::MemoTable,
+    options::{CompileOptions, WarningLevel},
+    system::Capabilities,
+    task::Executor,
+    trace::Tracer,
+    trait_registry::{TraitImpl, TraitRegistry},
+    typeinfer::TypeInferencer,
+    unit::{Unit, UnitRegistry},
+    value::{Attribute, Value},
 };
+use cadenza_syntax::span::Span;
+use std::collections::HashMap;
+
+/// A definition marked `@export`, to be exported from the compiled module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    /// The name of the function or constant.
+    pub name: InternedString,
+    /// The name the module exports it as.
+    pub export_name: InternedString,
+    /// The span of the definition's name.
+    pub span: Span,
+}
 
 /// The compiler state that accumulates definitions during evaluation.
 ///
@@ -27,6 +56,15 @@ use crate::{
 ///
 /// The compiler includes a trait registry for storing trait definitions and
 /// implementations, enabling the trait system.
+///
+/// Attributes (`@name args...`) are held as pending until the next definition
+/// consumes them, and memoized functions keep their result caches here.
+///
+/// Host access (files, processes) is limited to the granted capabilities,
+/// which are empty by default.
+///
+/// [`CompileOptions`] control warning handling, the evaluation fuel budget,
+/// the recursion limit, and IR optimization; [`Compiler::new`] uses the default options.
 pub struct Compiler {
     /// Variable and function definitions.
     defs: Map<Value>,
@@ -43,6 +81,34 @@ pub struct Compiler {
     ir_generator: Option<IrGenerator>,
     /// Trait registry for trait definitions and implementations.
     trait_registry: TraitRegistry,
+    /// Attributes waiting to be applied to the next definition.
+    pending_attributes: Vec<Attribute>,
+    /// Result caches for `@memoize` functions.
+    memo: MemoTable,
+    /// Executor for tasks and channels.
+    executor: Executor,
+    /// Host access granted to the program.
+    capabilities: Capabilities,
+    /// Options for this compilation.
+    options: CompileOptions,
+    /// Evaluation steps left before running out of fuel, if limited.
+    fuel: Option<u64>,
+    /// Evaluation steps taken so far.
+    steps: u64,
+    /// Function calls currently nested inside one another.
+    depth: u64,
+    /// Modules evaluated so far, which later modules may import.
+    modules: Vec<InternedString>,
+    /// Functions marked `@test`, in definition order.
+    tests: Vec<InternedString>,
+    /// Definitions marked `@export`, in definition order.
+    exports: Vec<Export>,
+    /// Expression hit counts, if coverage is enabled.
+    coverage: Option<Coverage>,
+    /// Where evaluated expressions are logged, if tracing is enabled.
+    tracer: Option<Tracer>,
+    /// The status the program stopped with, once it calls `exit` or `abort`.
+    exit_status: Option<i32>,
 }
 
 impl Default for Compiler {
@@ -52,6 +118,10 @@ impl Default for Compiler {
 }
 
 impl Compiler {
+    /// The exit status of a program that called `abort`, the status of a
+    /// process killed by `SIGABRT`.
+    pub const ABORT_STATUS: i32 = 134;
+
     /// Creates a new empty compiler state.
     pub fn new() -> Self {
         Self {
@@ -61,7 +131,21 @@ impl Compiler {
             units: UnitRegistry::new(),
             type_inferencer: TypeInferencer::new(),
             ir_generator: None,
-            trait_registry: TraitRegistry::new(),
+            trait_registry: derive::trait_registry(),
+            pending_attributes: Vec::new(),
+            memo: MemoTable::new(),
+            executor: Executor::new(),
+            capabilities: Capabilities::none(),
+            options: CompileOptions::default(),
+            fuel: None,
+            steps: 0,
+            depth: 0,
+            modules: Vec::new(),
+            tests: Vec::new(),
+            exports: Vec::new(),
+            coverage: None,
+            tracer: None,
+            exit_status: None,
         }
     }
 
@@ -74,15 +158,43 @@ impl Compiler {
             units: UnitRegistry::new(),
             type_inferencer: TypeInferencer::new(),
             ir_generator: Some(IrGenerator::new()),
-            trait_registry: TraitRegistry::new(),
+            trait_registry: derive::trait_registry(),
+            pending_attributes: Vec::new(),
+            memo: MemoTable::new(),
+            executor: Executor::new(),
+            capabilities: Capabilities::none(),
+            options: CompileOptions::default(),
+            fuel: None,
+            steps: 0,
+            depth: 0,
+            modules: Vec::new(),
+            tests: Vec::new(),
+            exports: Vec::new(),
+            coverage: None,
+            tracer: None,
+            exit_status: None,
         }
     }
 
+    /// Creates a new compiler state using `options`.
+    ///
+    /// IR generation is still enabled separately with [`Compiler::enable_ir`].
+    pub fn with_options(options: CompileOptions) -> Self {
+        let mut compiler = Self::new();
+        compiler.set_options(options);
+        compiler
+    }
+
     /// Defines a variable or function.
     pub fn define_var(&mut self, name: InternedString, value: Value) {
         self.defs.insert(name, value);
     }
 
+    /// Removes a variable or function definition, returning its value.
+    pub fn remove_var(&mut self, name: InternedString) -> Option<Value> {
+        self.defs.shift_remove(&name)
+    }
+
     /// Defines a macro.
     ///
     /// The value must be a `Value::Macro` or `Value::BuiltinMacro`.
@@ -124,10 +236,47 @@ impl Compiler {
     ///
     /// This allows the evaluator to collect multiple diagnostics instead of
     /// bailing on the first error.
+    ///
+    /// Warnings are dropped or promoted to errors according to the warning
+    /// level in the compile options.
+    ///
+    /// Every diagnostic must carry a span (see the [span policy](crate::diagnostic#spans));
+    /// debug builds assert this.
     pub fn record_diagnostic(&mut self, diagnostic: Diagnostic) {
+        // The failure behind a poisoned value was already reported
+        if diagnostic.is_poisoned() {
+            return;
+        }
+        match diagnostic.kind {
+            // Exiting stops the program without anything to report
+            DiagnosticKind::Exit { code } => {
+                self.exit_status = Some(code);
+                return;
+            }
+            DiagnosticKind::Aborted { .. } => self.exit_status = Some(Self::ABORT_STATUS),
+            _ => {}
+        }
+        debug_assert!(
+            diagnostic.span.is_some(),
+            "diagnostic recorded without a span: {diagnostic}"
+        );
+        let diagnostic = match (self.options.warnings, diagnostic.is_warning()) {
+            (WarningLevel::Allow, true) => return,
+            (WarningLevel::Deny, true) => diagnostic.set_level(DiagnosticLevel::Error),
+            _ => diagnostic,
+        };
         self.diagnostics.push(diagnostic);
     }
 
+    /// Returns the status the program stopped with by calling `exit` or
+    /// `abort`, if it did.
+    ///
+    /// Evaluation stops at the top level once the program has stopped, so no
+    /// later expression or module runs.
+    pub fn exit_status(&self) -> Option<i32> {
+        self.exit_status
+    }
+
     /// Returns all accumulated diagnostics.
     pub fn diagnostics(&self) -> &[Diagnostic] {
         &self.diagnostics
@@ -139,6 +288,14 @@ impl Compiler {
         std::mem::take(&mut self.diagnostics)
     }
 
+    /// Sets the source file of the diagnostics recorded since `start` that
+    /// don't have one yet.
+    pub fn tag_diagnostics_from(&mut self, start: usize, file: InternedString) {
+        for diagnostic in self.diagnostics.iter_mut().skip(start) {
+            diagnostic.file.get_or_insert(file);
+        }
+    }
+
     /// Returns the number of accumulated diagnostics.
     pub fn num_diagnostics(&self) -> usize {
         self.diagnostics.len()
@@ -164,6 +321,14 @@ impl Compiler {
         &mut self.units
     }
 
+    /// Registers `unit` for the evaluator and, if enabled, the IR generator.
+    pub fn register_unit(&mut self, unit: Unit) {
+        if let Some(generator) = &mut self.ir_generator {
+            generator.register_unit(unit.clone());
+        }
+        self.units.register(unit);
+    }
+
     /// Returns a reference to the type inferencer.
     ///
     /// This allows lazy type checking - types can be inferred on-demand
@@ -194,11 +359,32 @@ impl Compiler {
         func: &crate::value::UserFunction,
         env: &crate::env::Env,
     ) -> Option<crate::diagnostic::Result<crate::ir::FunctionId>> {
+        let _span = tracing::debug_span!("lower").entered();
         self.ir_generator
             .as_mut()
             .map(|generator| generator.gen_function(func, env))
     }
 
+    /// Declares the top-level function `name` to the IR generator, if
+    /// enabled, so functions generated before its definition can call it.
+    pub fn declare_ir_function(&mut self, name: InternedString) {
+        if let Some(generator) = &mut self.ir_generator {
+            generator.declare_function(name);
+        }
+    }
+
+    /// Generates the module-level constant `name` holding `value`, if IR
+    /// generation is enabled.
+    ///
+    /// Returns whether a constant was generated: values with no constant
+    /// representation, such as functions and lists, are skipped.
+    pub fn generate_ir_global(&mut self, name: InternedString, value: &Value) -> bool {
+        self.ir_generator
+            .as_mut()
+            .and_then(|generator| generator.gen_global(name, value))
+            .is_some()
+    }
+
     /// Returns a reference to the IR generator, if enabled.
     ///
     /// This allows direct access to the IR generator for advanced use cases.
@@ -215,17 +401,109 @@ impl Compiler {
 
     /// Builds and returns the generated IR module, if IR generation is enabled.
     ///
-    /// This consumes the IR generator and returns the final IR module.
+    /// This consumes the IR generator and returns the final IR module,
+    /// optimized according to the optimization level in the compile options.
+    /// The module exports the definitions marked `@export`, and each one that
+    /// didn't compile to a function or constant is reported as an error. A
+    /// program's `main` function is exported as `main` without being marked.
+    /// Functions that can recurse without bound are reported as warnings
+    /// (see [`CallGraph`](crate::ir::CallGraph)), and calls to the host's math
+    /// functions as errors if floats must be deterministic (see
+    /// [`CompileOptions::DETERMINISTIC_FLOATS`]).
     /// After calling this, the compiler will have a fresh IR generator if one was present.
-    pub fn build_ir_module(&mut self) -> Option<crate::ir::IrModule> {
-        self.ir_generator.take().map(|generator| generator.build())
+    pub fn build_ir_module(&mut self) -> Option<IrModule> {
+        let generator = self.ir_generator.take()?;
+        let exports = self.resolve_exports(&generator);
+        let mut module = tracing::debug_span!("lower").in_scope(|| generator.build());
+        module.exports.extend(exports);
+        tracing::debug_span!("optimize").in_scope(|| {
+            OptimizationPipeline::for_level(self.options.opt_level)
+                .run(&mut module, OptimizationPipeline::MAX_ITERATIONS)
+        });
+        for recursion in crate::ir::CallGraph::new(&module).unbounded_recursion() {
+            self.record_diagnostic(recursion.diagnostic());
+        }
+        if self.options.deterministic_floats() {
+            self.report_host_math(&module);
+        }
+        Some(module)
+    }
+
+    /// Returns the module exports for the definitions marked `@export` and
+    /// `main`, recording an error for each one that can't be exported.
+    fn resolve_exports(&mut self, generator: &IrGenerator) -> Vec<IrExport> {
+        let mut exports: Vec<IrExport> = Vec::with_capacity(self.exports.len());
+        for export in std::mem::take(&mut self.exports) {
+            let kind = if let Some(func) = generator.function_id(export.name) {
+                IrExportKind::Function(func)
+            } else if let Some(global) = generator.global_id(export.name) {
+                IrExportKind::Constant(global)
+            } else {
+                let error = Diagnostic::syntax(format!(
+                    "cannot export {}: it didn't compile to a function or constant",
+                    export.name
+                ));
+                self.record_diagnostic(*error.with_span(export.span));
+                continue;
+            };
+            if exports.iter().any(|e| e.name == export.export_name) {
+                let error = Diagnostic::syntax(format!("duplicate export {}", export.export_name));
+                self.record_diagnostic(*error.with_span(export.span));
+                continue;
+            }
+            exports.push(IrExport {
+                name: export.export_name,
+                kind,
+            });
+        }
+        // `main` is the program's entry point, which hosts look for by name
+        let main = InternedString::new("main");
+        if let Some(func) = generator.function_id(main)
+            && !exports.iter().any(|e| e.name == main)
+        {
+            exports.push(IrExport {
+                name: main,
+                kind: IrExportKind::Function(func),
+            });
+        }
+        exports
+    }
+
+    /// Records an error for each call in `module` to a host math function,
+    /// whose results depend on how the host implements it.
+    fn report_host_math(&mut self, module: &IrModule) {
+        let math: HashMap<FunctionId, &HostFunction> = module
+            .imports
+            .iter()
+            .filter(|import| import.function.interface == host::Interface::Math)
+            .map(|import| (import.id, import.function))
+            .collect();
+        let calls = module
+            .functions
+            .iter()
+            .flat_map(|func| &func.blocks)
+            .flat_map(|block| &block.instructions);
+        for instr in calls {
+            if let IrInstr::Call { func, source, .. } = instr
+                && let Some(function) = math.get(func)
+            {
+                let mut diagnostic = *Diagnostic::nondeterministic_float(function.name.into());
+                diagnostic.file = Some(source.file);
+                diagnostic.span = Some(source.span);
+                self.record_diagnostic(diagnostic);
+            }
+        }
     }
 
     /// Enables IR generation for this compiler.
     ///
     /// This initializes a new IR generator. Any previously generated IR is lost.
     pub fn enable_ir(&mut self) {
-        self.ir_generator = Some(IrGenerator::new());
+        let mut generator = IrGenerator::new();
+        for unit in self.units.all_units() {
+            generator.register_unit(unit.clone());
+        }
+        self.ir_generator = Some(generator);
     }
 
     /// Checks if IR generation is enabled.
@@ -246,12 +524,217 @@ impl Compiler {
     pub fn trait_registry_mut(&mut self) -> &mut TraitRegistry {
         &mut self.trait_registry
     }
+
+    /// Registers a trait implementation, recording an orphan warning if its
+    /// module defines neither the trait nor the type.
+    ///
+    /// # Errors
+    /// Returns the coherence errors of [`TraitRegistry::implement_trait`].
+    pub fn implement_trait(&mut self, trait_impl: TraitImpl) -> Result<()> {
+        if let Some(warning) = self.trait_registry.orphan_warning(&trait_impl) {
+            self.record_diagnostic(*warning);
+        }
+        self.trait_registry.implement_trait(trait_impl)
+    }
+
+    /// Queues an attribute to be applied to the next definition.
+    pub fn push_attribute(&mut self, attribute: Attribute) {
+        self.pending_attributes.push(attribute);
+    }
+
+    /// Returns the attributes waiting for the next definition.
+    pub fn pending_attributes(&self) -> &[Attribute] {
+        &self.pending_attributes
+    }
+
+    /// Takes the pending attributes, leaving none queued.
+    pub fn take_attributes(&mut self) -> Vec<Attribute> {
+        std::mem::take(&mut self.pending_attributes)
+    }
+
+    /// Returns a reference to the memoization caches.
+    pub fn memo(&self) -> &MemoTable {
+        &self.memo
+    }
+
+    /// Returns a mutable reference to the memoization caches.
+    pub fn memo_mut(&mut self) -> &mut MemoTable {
+        &mut self.memo
+    }
+
+    /// Returns a reference to the task executor.
+    pub fn executor(&self) -> &Executor {
+        &self.executor
+    }
+
+    /// Returns a mutable reference to the task executor.
+    pub fn executor_mut(&mut self) -> &mut Executor {
+        &mut self.executor
+    }
+
+    /// Returns the capabilities granted to the program.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Replaces the capabilities granted to the program.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Records that `module` has been evaluated and may be imported.
+    pub fn add_module(&mut self, module: InternedString) {
+        if !self.has_module(module) {
+            self.modules.push(module);
+        }
+    }
+
+    /// Returns true if `module` has been evaluated.
+    pub fn has_module(&self, module: InternedString) -> bool {
+        self.modules.contains(&module)
+    }
+
+    /// Returns the evaluated modules in evaluation order.
+    pub fn modules(&self) -> &[InternedString] {
+        &self.modules
+    }
+
+    /// Records that `function` is marked `@test`.
+    pub fn add_test(&mut self, function: InternedString) {
+        if !self.tests.contains(&function) {
+            self.tests.push(function);
+        }
+    }
+
+    /// Returns the functions marked `@test`, in definition order.
+    pub fn tests(&self) -> &[InternedString] {
+        &self.tests
+    }
+
+    /// Records that a definition is marked `@export`.
+    pub fn add_export(&mut self, export: Export) {
+        self.exports.push(export);
+    }
+
+    /// Returns the definitions marked `@export`, in definition order.
+    pub fn exports(&self) -> &[Export] {
+        &self.exports
+    }
+
+    /// Starts counting expression runs for [`Coverage`].
+    ///
+    /// Only files registered with the coverage are counted;
+    /// [`ModuleGraph::eval`](crate::module_graph::ModuleGraph::eval)
+    /// registers each module it evaluates.
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_with(Coverage::new);
+    }
+
+    /// Returns the coverage, if enabled.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Returns a mutable reference to the coverage, if enabled.
+    pub fn coverage_mut(&mut self) -> Option<&mut Coverage> {
+        self.coverage.as_mut()
+    }
+
+    /// Starts logging evaluated expressions to `tracer`, or stops with
+    /// `None`; see [`trace`](crate::trace).
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    /// Returns the tracer, if tracing is enabled.
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    /// Returns a mutable reference to the tracer, if tracing is enabled.
+    pub fn tracer_mut(&mut self) -> Option<&mut Tracer> {
+        self.tracer.as_mut()
+    }
+
+    /// Returns the compile options.
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    /// Replaces the compile options, resetting the fuel budget.
+    pub fn set_options(&mut self, options: CompileOptions) {
+        self.options = options;
+        self.refuel();
+    }
+
+    /// Returns the evaluation steps left, or `None` if fuel is unlimited.
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Restores the fuel budget from the compile options.
+    ///
+    /// Interactive hosts call this before each input so every input gets
+    /// the full budget.
+    pub fn refuel(&mut self) {
+        self.fuel = self.options.fuel;
+    }
+
+    /// Returns the evaluation steps taken so far, whether or not fuel is
+    /// limited.
+    ///
+    /// Hosts compare the count before and after evaluating something to see
+    /// how much work it took.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Consumes one evaluation step.
+    ///
+    /// # Errors
+    ///
+    /// Returns an out-of-fuel error once the budget is used up.
+    pub fn consume_fuel(&mut self) -> Result<()> {
+        match &mut self.fuel {
+            None => {}
+            Some(0) => return Err(Diagnostic::out_of_fuel(self.options.fuel.unwrap_or(0))),
+            Some(fuel) => *fuel -= 1,
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Enters a function call nested inside the calls being evaluated.
+    ///
+    /// # Errors
+    ///
+    /// Returns a recursion limit error if calls already nest as deeply as
+    /// the options allow.
+    pub(crate) fn enter_call(&mut self) -> Result<()> {
+        let limit = self.options.recursion_limit;
+        if self.depth >= limit {
+            return Err(Diagnostic::recursion_limit(limit));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Leaves a call entered with [`Compiler::enter_call`].
+    pub(crate) fn exit_call(&mut self) {
+        self.depth -= 1;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{diagnostic::DiagnosticLevel, value::Type};
+    use crate::{
+        diagnostic::{BoxedDiagnosticExt, DiagnosticLevel},
+        value::Type,
+    };
+    use cadenza_syntax::span::Span;
+
+    const SPAN: Span = Span { start: 0, end: 1 };
 
     #[test]
     fn define_and_get_var() {
@@ -304,7 +787,7 @@ mod tests {
         assert_eq!(compiler.num_diagnostics(), 0);
         assert!(!compiler.has_errors());
 
-        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id));
+        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id).with_span(SPAN));
         assert_eq!(compiler.num_diagnostics(), 1);
         assert!(compiler.has_errors());
 
@@ -312,16 +795,24 @@ mod tests {
         assert_eq!(diagnostics.len(), 1);
         assert!(matches!(
             &diagnostics[0].kind,
-            crate::diagnostic::DiagnosticKind::UndefinedVariable(_)
+            crate::diagnostic::DiagnosticKind::UndefinedVariable { .. }
         ));
     }
 
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "diagnostic recorded without a span")]
+    fn diagnostics_must_have_spans() {
+        let mut compiler = Compiler::new();
+        compiler.record_diagnostic(*Diagnostic::syntax("somewhere"));
+    }
+
     #[test]
     fn take_diagnostics_empties_list() {
         let x_id: InternedString = "x".into();
         let mut compiler = Compiler::new();
 
-        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id));
+        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id).with_span(SPAN));
         assert_eq!(compiler.num_diagnostics(), 1);
 
         let taken = compiler.take_diagnostics();
@@ -334,10 +825,11 @@ mod tests {
         let x_id: InternedString = "x".into();
         let mut compiler = Compiler::new();
 
-        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id));
+        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id).with_span(SPAN));
         // Use union type to express "number" (integer | float)
         let number_type = Type::union(vec![Type::Integer, Type::Float]);
-        compiler.record_diagnostic(*Diagnostic::type_error(number_type, Type::String));
+        compiler
+            .record_diagnostic(*Diagnostic::type_error(number_type, Type::String).with_span(SPAN));
         assert_eq!(compiler.num_diagnostics(), 2);
 
         compiler.clear_diagnostics();
@@ -347,18 +839,151 @@ mod tests {
 
     #[test]
     fn has_errors_distinguishes_levels() {
-        use crate::diagnostic::BoxedDiagnosticExt;
-
         let x_id: InternedString = "x".into();
         let mut compiler = Compiler::new();
 
         // Add a warning - should not count as error
-        let warning = Diagnostic::undefined_variable(x_id).set_level(DiagnosticLevel::Warning);
+        let warning = Diagnostic::undefined_variable(x_id)
+            .with_span(SPAN)
+            .set_level(DiagnosticLevel::Warning);
         compiler.record_diagnostic(*warning);
         assert!(!compiler.has_errors());
 
         // Add an error
-        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id));
+        compiler.record_diagnostic(*Diagnostic::undefined_variable(x_id).with_span(SPAN));
+        assert!(compiler.has_errors());
+    }
+
+    #[test]
+    fn warning_level_filters_and_promotes_warnings() {
+        let warning = || {
+            *Diagnostic::syntax("unused")
+                .with_span(SPAN)
+                .set_level(DiagnosticLevel::Warning)
+        };
+
+        let mut compiler = Compiler::with_options(CompileOptions {
+            warnings: WarningLevel::Allow,
+            ..CompileOptions::default()
+        });
+        compiler.record_diagnostic(warning());
+        assert_eq!(compiler.num_diagnostics(), 0);
+
+        let mut compiler = Compiler::with_options(CompileOptions {
+            warnings: WarningLevel::Deny,
+            ..CompileOptions::default()
+        });
+        compiler.record_diagnostic(warning());
         assert!(compiler.has_errors());
     }
+
+    #[test]
+    fn fuel_limits_evaluation() {
+        let src = "fn spin x = spin x\nspin 1\n";
+        let mut env = crate::Env::with_standard_builtins();
+        let mut compiler = Compiler::with_options(CompileOptions {
+            fuel: Some(50),
+            ..CompileOptions::default()
+        });
+        crate::eval(
+            &cadenza_syntax::parse::parse(src).ast(),
+            &mut env,
+            &mut compiler,
+        );
+
+        assert_eq!(compiler.remaining_fuel(), Some(0));
+        assert_eq!(compiler.steps(), 50);
+        assert!(
+            compiler
+                .diagnostics()
+                .iter()
+                .any(|d| matches!(d.kind(), crate::DiagnosticKind::OutOfFuel { limit: 50 }))
+        );
+    }
+
+    #[test]
+    fn deterministic_floats_forbid_math() {
+        let src = "fn wave x = sin x\nlet y = pow 2.0 0.5\n";
+        let mut env = crate::Env::with_standard_builtins();
+        let mut compiler = Compiler::with_options(CompileOptions {
+            features: [CompileOptions::DETERMINISTIC_FLOATS.to_string()].into(),
+            ..CompileOptions::default()
+        });
+        compiler.enable_ir();
+        crate::eval(
+            &cadenza_syntax::parse::parse(src).ast(),
+            &mut env,
+            &mut compiler,
+        );
+        compiler.build_ir_module().unwrap();
+
+        // Evaluating `pow` fails, and so does compiling the call to `sin`
+        let forbidden: Vec<_> = compiler
+            .diagnostics()
+            .iter()
+            .filter_map(|d| match d.kind() {
+                crate::DiagnosticKind::NondeterministicFloat(name) => Some(&**name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(forbidden, ["pow", "sin"], "{:?}", compiler.diagnostics());
+    }
+
+    #[test]
+    fn build_ir_module_exports_marked_definitions() {
+        let src = "@export\nfn area w h = w * h\n@export \"max-width\"\nlet max_width = 40\nfn helper x = x + 1\n";
+        let mut env = crate::Env::with_standard_builtins();
+        let mut compiler = Compiler::with_ir();
+        crate::eval(
+            &cadenza_syntax::parse::parse(src).ast(),
+            &mut env,
+            &mut compiler,
+        );
+        let module = compiler.build_ir_module().unwrap();
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+
+        let exported: Vec<_> = module
+            .exported()
+            .map(|(name, item)| match item {
+                crate::ir::Exported::Function(func) => (name, func.name, func.params.len()),
+                crate::ir::Exported::Constant(global) => (name, global.name, 0),
+            })
+            .collect();
+        assert_eq!(
+            exported,
+            [
+                ("area".into(), "area".into(), 2),
+                ("max-width".into(), "max_width".into(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn build_ir_module_reports_unexportable_definitions() {
+        let src =
+            "@export\nlet items = [1, 2]\n@export \"one\"\nfn first x = x\n@export\nfn one x = x\n";
+        let mut env = crate::Env::with_standard_builtins();
+        let mut compiler = Compiler::with_ir();
+        crate::eval(
+            &cadenza_syntax::parse::parse(src).ast(),
+            &mut env,
+            &mut compiler,
+        );
+        let module = compiler.build_ir_module().unwrap();
+
+        // The list has no constant to export, and `one` is exported twice
+        let errors: Vec<_> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.kind().to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "syntax error: cannot export items: it didn't compile to a function or constant",
+                "syntax error: duplicate export one"
+            ]
+        );
+        assert_eq!(module.exports.len(), 1);
+    }
 }
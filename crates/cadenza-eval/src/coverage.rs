@@ -0,0 +1,428 @@
+//! Coverage of evaluated source.
+//!
+//! With coverage enabled ([`Compiler::enable_coverage`]), the evaluator
+//! counts how many times each expression of a registered file runs.
+//! [`ModuleGraph::eval`] registers every module it evaluates; expressions
+//! from anywhere else, such as the prelude, aren't counted.
+//!
+//! [`Coverage::report`] turns the counts into line and function coverage per
+//! file, which renders as an lcov tracefile ([`FileReport::lcov`]) or an HTML
+//! page ([`FileReport::html`]):
+//!
+//! - A line is instrumented if a top-level expression, or an expression in
+//!   the body of a top-level function, starts on it. It is hit as many times
+//!   as the most-run expression starting on it.
+//! - A top-level function is hit as many times as its body ran.
+//!
+//! ```
+//! use cadenza_eval::{Compiler, Env, module_graph::ModuleGraph};
+//!
+//! let mut graph = ModuleGraph::new();
+//! graph.add("lib", "fn sign x =\n    match x < 0\n        true => (-1)\n        false => 1\nsign 2\n");
+//!
+//! let mut env = Env::with_standard_builtins();
+//! let mut compiler = Compiler::new();
+//! compiler.enable_coverage();
+//! graph.eval(&mut env, &mut compiler);
+//!
+//! let report = &compiler.coverage().unwrap().report()[0];
+//! // The `true` arm never ran
+//! assert_eq!(report.missed_lines().collect::<Vec<_>>(), [3]);
+//! ```
+//!
+//! [`Compiler::enable_coverage`]: crate::Compiler::enable_coverage
+//! [`ModuleGraph::eval`]: crate::module_graph::ModuleGraph::eval
+
+use crate::{interner::InternedString, module_graph::function_definition};
+use cadenza_syntax::{
+    SyntaxNode,
+    ast::{Expr, Root},
+    span::Span,
+};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Expression hit counts for the registered files.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    files: Vec<File>,
+}
+
+/// A registered file and the hit count of each expression that ran.
+#[derive(Debug)]
+struct File {
+    name: InternedString,
+    source: String,
+    root: Root,
+    /// Keyed by span and depth in the tree, since a block and its only
+    /// expression share a span.
+    hits: BTreeMap<(Span, usize), u64>,
+}
+
+impl Coverage {
+    /// Creates coverage with no registered files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers file `name` with its `source` and parsed `root`, so the
+    /// expressions under `root` are counted.
+    ///
+    /// Registering a name again replaces the file and drops its counts.
+    pub fn add_file(&mut self, name: InternedString, source: &str, root: &Root) {
+        let file = File {
+            name,
+            source: source.to_string(),
+            root: root.clone(),
+            hits: BTreeMap::new(),
+        };
+        match self.files.iter_mut().find(|file| file.name == name) {
+            Some(existing) => *existing = file,
+            None => self.files.push(file),
+        }
+    }
+
+    /// Counts a run of `expr`, if it belongs to a registered file.
+    pub(crate) fn record(&mut self, expr: &Expr) {
+        let mut root = expr.syntax();
+        let mut depth = 0;
+        while let Some(parent) = root.parent() {
+            root = parent;
+            depth += 1;
+        }
+        if let Some(file) = self
+            .files
+            .iter_mut()
+            .find(|file| file.root.syntax() == root)
+        {
+            *file.hits.entry((expr.span(), depth)).or_default() += 1;
+        }
+    }
+
+    /// Returns the line and function coverage of each registered file, in
+    /// registration order.
+    pub fn report(&self) -> Vec<FileReport> {
+        self.files.iter().map(File::report).collect()
+    }
+}
+
+impl File {
+    fn report(&self) -> FileReport {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(self.source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        // 1-based, like lcov
+        let line_of = |offset: usize| line_starts.partition_point(|start| *start <= offset);
+
+        let mut lines: BTreeMap<usize, u64> = BTreeMap::new();
+        let mut functions = Vec::new();
+        for item in self.root.items() {
+            lines.entry(line_of(item.span().start)).or_default();
+            if let Some(def) = function_definition(&item) {
+                instrument(def.body.syntax(), &mut |start| {
+                    lines.entry(line_of(start)).or_default();
+                });
+                functions.push(FunctionHits {
+                    name: def.name,
+                    line: line_of(def.span.start),
+                    hits: self.hits.get(&key(&def.body)).copied().unwrap_or(0),
+                });
+            }
+        }
+        for ((span, _), hits) in &self.hits {
+            let line = lines.entry(line_of(span.start)).or_default();
+            *line = (*line).max(*hits);
+        }
+
+        FileReport {
+            name: self.name,
+            source: self.source.clone(),
+            lines: lines
+                .into_iter()
+                .map(|(line, hits)| LineHits { line, hits })
+                .collect(),
+            functions,
+        }
+    }
+}
+
+/// Returns the key `expr` is counted under.
+fn key(expr: &Expr) -> (Span, usize) {
+    let mut depth = 0;
+    let mut node = expr.syntax();
+    while let Some(parent) = node.parent() {
+        node = parent;
+        depth += 1;
+    }
+    (expr.span(), depth)
+}
+
+/// Calls `visit` with the start offset of `node` and each expression under
+/// it.
+fn instrument(node: &SyntaxNode, visit: &mut impl FnMut(usize)) {
+    if let Some(expr) = Expr::cast_syntax_node(node) {
+        visit(expr.span().start);
+    }
+    for child in node.children() {
+        instrument(&child, visit);
+    }
+}
+
+/// The coverage of one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    /// The module name the file was registered under.
+    pub name: InternedString,
+    /// The file's source text.
+    pub source: String,
+    /// The instrumented lines, in order.
+    pub lines: Vec<LineHits>,
+    /// The top-level functions, in definition order.
+    pub functions: Vec<FunctionHits>,
+}
+
+/// How many times an instrumented line ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineHits {
+    /// The 1-based line number.
+    pub line: usize,
+    pub hits: u64,
+}
+
+/// How many times a top-level function ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionHits {
+    pub name: InternedString,
+    /// The 1-based line of the function name.
+    pub line: usize,
+    pub hits: u64,
+}
+
+impl FileReport {
+    /// Returns how many instrumented lines ran.
+    pub fn lines_hit(&self) -> usize {
+        self.lines.iter().filter(|line| line.hits > 0).count()
+    }
+
+    /// Returns how many top-level functions ran.
+    pub fn functions_hit(&self) -> usize {
+        self.functions
+            .iter()
+            .filter(|function| function.hits > 0)
+            .count()
+    }
+
+    /// Returns the instrumented lines that never ran.
+    pub fn missed_lines(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lines
+            .iter()
+            .filter(|line| line.hits == 0)
+            .map(|line| line.line)
+    }
+
+    /// Returns the lcov tracefile record for this file, naming it `path`.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut out = format!("TN:\nSF:{path}\n");
+        for function in &self.functions {
+            let _ = writeln!(out, "FN:{},{}", function.line, function.name);
+        }
+        for function in &self.functions {
+            let _ = writeln!(out, "FNDA:{},{}", function.hits, function.name);
+        }
+        let _ = writeln!(out, "FNF:{}", self.functions.len());
+        let _ = writeln!(out, "FNH:{}", self.functions_hit());
+        for line in &self.lines {
+            let _ = writeln!(out, "DA:{},{}", line.line, line.hits);
+        }
+        let _ = writeln!(out, "LF:{}", self.lines.len());
+        let _ = writeln!(out, "LH:{}", self.lines_hit());
+        out.push_str("end_of_record\n");
+        out
+    }
+
+    /// Returns a standalone HTML page showing the source with each
+    /// instrumented line marked as run or missed.
+    pub fn html(&self) -> String {
+        let hits: BTreeMap<usize, u64> = self
+            .lines
+            .iter()
+            .map(|line| (line.line, line.hits))
+            .collect();
+
+        let mut rows = String::new();
+        for (i, text) in self.source.lines().enumerate() {
+            let line = i + 1;
+            let (class, count) = match hits.get(&line) {
+                Some(0) => ("miss", "0".to_string()),
+                Some(hits) => ("hit", hits.to_string()),
+                None => ("", String::new()),
+            };
+            let _ = writeln!(
+                rows,
+                "<tr class=\"{class}\"><td class=\"line\">{line}</td>\
+                 <td class=\"count\">{count}</td><td><pre>{}</pre></td></tr>",
+                escape(text)
+            );
+        }
+
+        let title = format!("Coverage: {}", self.name);
+        let summary = summary(
+            self.lines_hit(),
+            self.lines.len(),
+            self.functions_hit(),
+            self.functions.len(),
+        );
+        page(
+            &title,
+            &format!(
+                "<p><a href=\"index.html\">All files</a></p>\n<p>{summary}</p>\n\
+                 <table class=\"source\">\n{rows}</table>"
+            ),
+        )
+    }
+}
+
+/// Returns an HTML index page linking each file's page (`<name>.html`) with
+/// its coverage totals.
+pub fn html_index(reports: &[FileReport]) -> String {
+    let mut rows = String::new();
+    for report in reports {
+        let _ = writeln!(
+            rows,
+            "<tr><td><a href=\"{name}.html\">{name}</a></td><td>{}</td></tr>",
+            summary(
+                report.lines_hit(),
+                report.lines.len(),
+                report.functions_hit(),
+                report.functions.len(),
+            ),
+            name = escape(&report.name),
+        );
+    }
+    page(
+        "Coverage",
+        &format!("<table>\n<tr><th>File</th><th>Coverage</th></tr>\n{rows}</table>"),
+    )
+}
+
+/// Describes line and function totals, e.g. `lines: 3/4 (75.0%), functions:
+/// 1/1 (100.0%)`.
+fn summary(lines_hit: usize, lines: usize, functions_hit: usize, functions: usize) -> String {
+    let percent = |hit: usize, total: usize| {
+        if total == 0 {
+            100.0
+        } else {
+            hit as f64 * 100.0 / total as f64
+        }
+    };
+    format!(
+        "lines: {lines_hit}/{lines} ({:.1}%), functions: {functions_hit}/{functions} ({:.1}%)",
+        percent(lines_hit, lines),
+        percent(functions_hit, functions)
+    )
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; }}\n\
+         table.source {{ border-collapse: collapse; }}\n\
+         pre {{ margin: 0; }}\n\
+         td.line, td.count {{ color: #888; text-align: right; padding: 0 8px; }}\n\
+         tr.hit {{ background: #dfd; }}\n\
+         tr.miss {{ background: #fdd; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n{body}\n</body>\n</html>\n",
+        title = escape(title)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Env, module_graph::ModuleGraph};
+
+    const SOURCE: &str = "\
+fn classify x =
+    match x > 0
+        true => \"positive\"
+        false => \"other\"
+fn unused x = x
+classify 1
+";
+
+    fn report() -> FileReport {
+        let mut graph = ModuleGraph::new();
+        graph.add("lib", SOURCE);
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        compiler.enable_coverage();
+        graph.eval(&mut env, &mut compiler);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        compiler.coverage().unwrap().report().remove(0)
+    }
+
+    #[test]
+    fn reports_lines_and_functions() {
+        let report = report();
+        assert_eq!(report.missed_lines().collect::<Vec<_>>(), [4]);
+        assert_eq!(
+            report.functions,
+            [
+                FunctionHits {
+                    name: "classify".into(),
+                    line: 1,
+                    hits: 1,
+                },
+                FunctionHits {
+                    name: "unused".into(),
+                    line: 5,
+                    hits: 0,
+                },
+            ]
+        );
+
+        let lcov = report.lcov("lib.cdz");
+        assert!(lcov.starts_with("TN:\nSF:lib.cdz\nFN:1,classify\nFN:5,unused\n"));
+        assert!(lcov.contains("FNDA:1,classify\nFNDA:0,unused\nFNF:2\nFNH:1\n"));
+        assert!(lcov.contains("DA:3,1\nDA:4,0\n"));
+        assert!(lcov.ends_with("LF:6\nLH:5\nend_of_record\n"));
+    }
+
+    #[test]
+    fn renders_html() {
+        let report = report();
+        let html = report.html();
+        assert!(html.contains(
+            "<tr class=\"miss\"><td class=\"line\">4</td><td class=\"count\">0</td>\
+             <td><pre>        false =&gt; &quot;other&quot;</pre></td></tr>"
+        ));
+        assert!(html.contains("lines: 5/6 (83.3%), functions: 1/2 (50.0%)"));
+
+        let index = html_index(&[report]);
+        assert!(index.contains("<a href=\"lib.html\">lib</a>"));
+    }
+
+    #[test]
+    fn ignores_unregistered_sources() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        compiler.enable_coverage();
+        crate::eval(
+            &cadenza_syntax::parse::parse("1 + 2\n").ast(),
+            &mut env,
+            &mut compiler,
+        );
+        assert!(compiler.coverage().unwrap().report().is_empty());
+    }
+}
@@ -65,6 +65,11 @@
 //!
 //! See `/docs/SALSA_MIGRATION.md` for the complete migration plan.
 
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
 // =============================================================================
 // Input Types
 // =============================================================================
@@ -314,9 +319,70 @@ pub trait CadenzaDb: salsa::Database {}
 /// // Use db for queries once they're implemented
 /// ```
 #[salsa::db]
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct CadenzaDbImpl {
     storage: salsa::Storage<Self>,
+    /// Counts of query executions and reuses, shared by clones.
+    counters: Arc<QueryCounters>,
+}
+
+impl Default for CadenzaDbImpl {
+    fn default() -> Self {
+        let counters = Arc::new(QueryCounters::default());
+        let events = counters.clone();
+        let storage = salsa::Storage::new(Some(Box::new(move |event: salsa::Event| {
+            match event.kind {
+                salsa::EventKind::WillExecute { .. } => {
+                    events.executed.fetch_add(1, Ordering::Relaxed);
+                }
+                salsa::EventKind::DidValidateMemoizedValue { .. } => {
+                    events.reused.fetch_add(1, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        })));
+        Self { storage, counters }
+    }
+}
+
+impl CadenzaDbImpl {
+    /// Returns how often queries have run, and how often a memoized result
+    /// was reused instead, since the database was created.
+    pub fn query_stats(&self) -> QueryStats {
+        QueryStats {
+            executed: self.counters.executed.load(Ordering::Relaxed),
+            reused: self.counters.reused.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueryCounters {
+    executed: AtomicU64,
+    reused: AtomicU64,
+}
+
+/// How a database's queries were answered (see
+/// [`CadenzaDbImpl::query_stats`]).
+///
+/// A query is `reused` when Salsa revalidates its memoized result after an
+/// input changed, instead of running it again. Results already validated in
+/// the current revision are returned without counting either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// Queries run, because they had no result or an input they read changed.
+    pub executed: u64,
+    /// Memoized results revalidated and reused.
+    pub reused: u64,
+}
+
+impl QueryStats {
+    /// Returns the fraction of answers that reused a memoized result, or
+    /// `None` if no query has been answered.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.executed + self.reused;
+        (total > 0).then(|| self.reused as f64 / total as f64)
+    }
 }
 
 #[salsa::db]
@@ -438,6 +504,27 @@ mod tests {
         assert!(text2.contains("y"), "Expected 'y' in: {}", text2);
     }
 
+    #[test]
+    fn counts_executed_and_reused_queries() {
+        let mut db = CadenzaDbImpl::default();
+        let a = SourceFile::new(&db, "a.cdz".to_string(), "let x = 1".to_string());
+        let b = SourceFile::new(&db, "b.cdz".to_string(), "let y = 2".to_string());
+        parse_file(&db, a);
+        parse_file(&db, b);
+        let first = db.query_stats();
+        assert_eq!(first.reused, 0);
+        assert!(first.executed >= 2);
+        assert_eq!(first.hit_rate(), Some(0.0));
+
+        // Only the edited file is parsed again
+        a.set_text(&mut db).to("let x = 3".to_string());
+        parse_file(&db, a);
+        parse_file(&db, b);
+        let second = db.query_stats();
+        assert_eq!(second.executed, first.executed + 1);
+        assert_eq!(second.reused, 1);
+    }
+
     // Note: CadenzaDbImpl is not Send + Sync because Salsa databases use
     // thread-local storage for performance. In Phase 6, we'll create a
     // thread-safe wrapper for LSP integration that uses parking_lot::Mutex.
@@ -0,0 +1,172 @@
+//! Dead-code detection.
+//!
+//! [`unused_functions`] builds the call graph of a [`ModuleGraph`] and warns
+//! about top-level functions that can never run. A function is live if it is
+//! exported or reachable from a live function or a top-level expression.
+//!
+//! Every function is exported by default; a leading underscore makes it
+//! private. So only private functions are reported:
+//!
+//! ```cadenza
+//! fn _helper x = x + 1       # warning: unused function: _helper
+//! fn _inner x = x * 2        # used by _outer only, which is itself unused
+//! fn _outer x = _inner x     # warning: unused function: _outer
+//! fn area w = w * w          # exported
+//! ```
+//!
+//! The analysis is syntactic, so it doesn't evaluate anything and is cheap
+//! enough to run on every edit. Any identifier that names a function counts as
+//! a reference, even if a local binding shadows it.
+
+use crate::{
+    diagnostic::{BoxedDiagnosticExt, Diagnostic},
+    interner::InternedString,
+    map::Map,
+    module_graph::{FunctionDef, ModuleGraph, function_definition},
+};
+use cadenza_syntax::{ast::Expr, parse::parse, span::Span, token::Kind};
+
+/// Returns true if top-level function `name` is exported from its module.
+pub fn is_exported(name: &str) -> bool {
+    !name.starts_with('_')
+}
+
+/// Returns a warning for each top-level function in `graph` that is neither
+/// exported nor reachable.
+///
+/// All modules share one namespace, as in [`ModuleGraph::eval`]. Warnings are
+/// tagged with the defining module and ordered by module, then position.
+pub fn unused_functions(graph: &ModuleGraph) -> Vec<Diagnostic> {
+    struct Definition {
+        module: InternedString,
+        span: Span,
+        references: Vec<InternedString>,
+    }
+
+    let mut definitions: Vec<(InternedString, Definition)> = Vec::new();
+    let mut live = Vec::new();
+
+    for module in graph.modules() {
+        let root = parse(&module.source).ast();
+        for item in root.items() {
+            match function_definition(&item) {
+                Some(FunctionDef {
+                    name, span, body, ..
+                }) => {
+                    if is_exported(&name) {
+                        live.push(name);
+                    }
+                    definitions.push((
+                        name,
+                        Definition {
+                            module: module.name,
+                            span,
+                            references: references(&body),
+                        },
+                    ));
+                }
+                None => live.extend(references(&item)),
+            }
+        }
+    }
+
+    // Every definition of a name is reached through it
+    let mut by_name: Map<Vec<usize>> = Map::default();
+    for (i, (name, _)) in definitions.iter().enumerate() {
+        by_name.entry(*name).or_default().push(i);
+    }
+
+    let mut reachable = vec![false; definitions.len()];
+    while let Some(name) = live.pop() {
+        for &i in by_name.get(&name).map(Vec::as_slice).unwrap_or_default() {
+            if !reachable[i] {
+                reachable[i] = true;
+                live.extend(definitions[i].1.references.iter().copied());
+            }
+        }
+    }
+
+    definitions
+        .into_iter()
+        .zip(reachable)
+        .filter(|(_, reachable)| !reachable)
+        .map(|((name, definition), _)| {
+            *Diagnostic::unused_function(name)
+                .with_span(definition.span)
+                .with_file(definition.module)
+        })
+        .collect()
+}
+
+/// Returns every identifier mentioned in `expr`.
+fn references(expr: &Expr) -> Vec<InternedString> {
+    expr.syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == Kind::Identifier)
+        .map(|token| token.text().interned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiagnosticKind;
+
+    fn unused(modules: &[(&str, &str)]) -> Vec<String> {
+        let mut graph = ModuleGraph::new();
+        for (name, source) in modules {
+            graph.add(*name, *source);
+        }
+        unused_functions(&graph)
+            .iter()
+            .map(|diagnostic| match diagnostic.kind() {
+                DiagnosticKind::UnusedFunction(name) => name.to_string(),
+                other => panic!("unexpected diagnostic {other}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_unreachable_private_functions() {
+        let src = "\
+fn _helper x = x + 1
+fn _inner x = x * 2
+fn _outer x = _inner x
+fn _used x = x
+fn _spin x = _spin x
+fn area w = _used w
+";
+        assert_eq!(
+            unused(&[("main", src)]),
+            vec!["_helper", "_inner", "_outer", "_spin"]
+        );
+    }
+
+    #[test]
+    fn top_level_expressions_are_roots() {
+        let src = "fn _double x = x * 2\nlet y = _double 4\n";
+        assert!(unused(&[("main", src)]).is_empty());
+    }
+
+    #[test]
+    fn references_cross_modules() {
+        let used = unused(&[
+            ("lib", "fn _shared x = x\n"),
+            ("main", "import lib\n_shared 1\n"),
+        ]);
+        assert!(used.is_empty());
+    }
+
+    #[test]
+    fn warnings_carry_span_and_module() {
+        let mut graph = ModuleGraph::new();
+        graph.add("lib", "fn area w = w\nfn _unused x = x\n");
+        let warnings = unused_functions(&graph);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].is_warning());
+        assert!(warnings[0].is_unnecessary());
+        assert_eq!(warnings[0].file, Some("lib".into()));
+        assert_eq!(warnings[0].span, Some(Span::new(17, 24)));
+    }
+}
@@ -0,0 +1,323 @@
+//! Exact decimal numbers.
+//!
+//! A number literal with a `d` suffix is a [`Decimal`] ([`Value::Decimal`]),
+//! which holds the number exactly rather than as the nearest binary float.
+//! Decimals add, subtract, multiply and divide without rounding, so they
+//! suit measurements and amounts that must come out exact:
+//!
+//! ```cadenza
+//! 0.1d + 0.2d        # 0.3d
+//! 1.5d * 3           # 4.5d
+//! 1d / 3d            # 1/3d
+//! (1d / 3d) * 3      # 1d
+//! ```
+//!
+//! A decimal is a fraction of two integers of any size underneath, so
+//! dividing never loses precision: a quotient without a finite decimal
+//! expansion is shown as a fraction. An integer combines with a decimal as
+//! a decimal, while a float doesn't, as there are no implicit conversions.
+//! `decimal` converts an integer or float (by its shortest decimal form) to
+//! a decimal, `to_float` converts a number to the nearest float, and
+//! `numerator` and `denominator` return the parts of the fraction in lowest
+//! terms.
+//!
+//! Decimals aren't supported in compiled code: generating IR for a decimal
+//! literal is an error.
+//!
+//! [`Value::Decimal`]: crate::Value::Decimal
+
+use crate::{
+    bigint::BigInt,
+    diagnostic::{Diagnostic, Result},
+    value::{BuiltinFn, Type, Value},
+};
+use std::{cmp::Ordering, fmt};
+
+/// An exact rational number.
+///
+/// The fraction is kept in lowest terms with a positive denominator, so
+/// equal numbers have equal representations.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl Decimal {
+    /// Returns the fraction `numerator / denominator`, or `None` if the
+    /// denominator is zero.
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Option<Self> {
+        if denominator.is_zero() {
+            return None;
+        }
+        let (numerator, denominator) = if denominator.is_negative() {
+            (-&numerator, -&denominator)
+        } else {
+            (numerator, denominator)
+        };
+        let divisor = gcd(&numerator, &denominator);
+        let (numerator, _) = numerator.div_rem(&divisor)?;
+        let (denominator, _) = denominator.div_rem(&divisor)?;
+        Some(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// The numerator, in lowest terms.
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    /// The denominator, in lowest terms, which is always positive.
+    pub fn denominator(&self) -> &BigInt {
+        &self.denominator
+    }
+
+    /// Parses the decimal digits `s`, with an optional `-`, `_` separators
+    /// and an optional fractional part, as in `-12.375`.
+    ///
+    /// Returns `None` if `s` isn't a decimal number.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.replace('_', "");
+        let (whole, fraction) = s.split_once('.').unwrap_or((&s, ""));
+        if fraction.starts_with(['-', '+']) {
+            return None;
+        }
+        let numerator = BigInt::parse(&format!("{whole}{fraction}"), 10)?;
+        Self::new(numerator, pow10(fraction.len()))
+    }
+
+    /// Returns the float `x` as a decimal, taking the shortest decimal that
+    /// reads back as `x` (so `0.1` is exactly one tenth), or `None` if it
+    /// isn't finite.
+    pub fn from_f64(x: f64) -> Option<Self> {
+        if !x.is_finite() {
+            return None;
+        }
+        Self::parse(&x.to_string())
+    }
+
+    /// Returns the nearest float to the number.
+    pub fn to_f64(&self) -> f64 {
+        self.numerator.to_f64() / self.denominator.to_f64()
+    }
+}
+
+impl From<BigInt> for Decimal {
+    fn from(n: BigInt) -> Self {
+        Self {
+            numerator: n,
+            denominator: BigInt::from(1),
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(n: i64) -> Self {
+        BigInt::from(n).into()
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // The denominators are positive, so cross-multiplying keeps the order
+        (&self.numerator * &other.denominator).cmp(&(&other.numerator * &self.denominator))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Shows the number in decimal notation when it has a finite expansion, as
+/// in `12.375`, and as a fraction otherwise, as in `1/3`.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(places) = decimal_places(&self.denominator) else {
+            return write!(f, "{}/{}", self.numerator, self.denominator);
+        };
+        if places == 0 {
+            return write!(f, "{}", self.numerator);
+        }
+        let (scale, _) = pow10(places)
+            .div_rem(&self.denominator)
+            .expect("the denominator isn't zero");
+        let scaled = &self.numerator * &scale;
+        let magnitude = if scaled.is_negative() {
+            (-&scaled).to_string()
+        } else {
+            scaled.to_string()
+        };
+        let digits = format!("{magnitude:0>width$}", width = places + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places);
+        let sign = if scaled.is_negative() { "-" } else { "" };
+        write!(f, "{sign}{whole}.{fraction}")
+    }
+}
+
+/// Shows the number as the literal that reads back as it, as in `12.375d`.
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}d")
+    }
+}
+
+/// Returns `10^n`.
+fn pow10(n: usize) -> BigInt {
+    let ten = BigInt::from(10);
+    (0..n).fold(BigInt::from(1), |acc, _| &acc * &ten)
+}
+
+/// Returns the greatest common divisor of `a` and `b`, which is positive
+/// unless both are zero.
+fn gcd(a: &BigInt, b: &BigInt) -> BigInt {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    while let Some((_, remainder)) = a.div_rem(&b) {
+        a = b;
+        b = remainder;
+    }
+    if a.is_negative() { -&a } else { a }
+}
+
+/// Returns the number of digits after the point of `1 / denominator`, or
+/// `None` if it doesn't have a finite decimal expansion.
+fn decimal_places(denominator: &BigInt) -> Option<usize> {
+    let mut rest = denominator.clone();
+    let mut count = |factor: i64| {
+        let factor = BigInt::from(factor);
+        let mut n = 0;
+        loop {
+            match rest.div_rem(&factor) {
+                Some((quotient, remainder)) if remainder.is_zero() => {
+                    rest = quotient;
+                    n += 1;
+                }
+                _ => return n,
+            }
+        }
+    };
+    let places = count(2).max(count(5));
+    (rest == BigInt::from(1)).then_some(places)
+}
+
+/// Returns `value` as a decimal: an integer or decimal exactly, and a float
+/// by its shortest decimal form.
+fn to_decimal(value: &Value) -> Result<Decimal> {
+    match value {
+        Value::Integer(n) => Ok((*n).into()),
+        Value::BigInt(n) => Ok(n.clone().into()),
+        Value::Decimal(d) => Ok(d.clone()),
+        Value::Float(x) => Decimal::from_f64(*x)
+            .ok_or_else(|| Diagnostic::syntax(format!("cannot convert {x} to a decimal"))),
+        other => Err(Diagnostic::type_error(Type::Decimal, other.type_of())),
+    }
+}
+
+/// Returns the builtins bound in the [prelude](crate::prelude): `decimal`,
+/// `to_float`, `numerator` and `denominator`.
+pub fn prelude_builtins() -> Vec<BuiltinFn> {
+    vec![
+        BuiltinFn {
+            name: "decimal",
+            signature: Type::function(vec![Type::Unknown], Type::Decimal),
+            func: |args, _ctx| match args {
+                [value] => Ok(Value::Decimal(to_decimal(value)?)),
+                _ => Err(Diagnostic::arity(1, args.len())),
+            },
+        },
+        BuiltinFn {
+            name: "to_float",
+            signature: Type::function(vec![Type::Unknown], Type::Float),
+            func: |args, _ctx| match args {
+                [Value::Integer(n)] => Ok(Value::Float(*n as f64)),
+                [Value::BigInt(n)] => Ok(Value::Float(n.to_f64())),
+                [Value::Decimal(d)] => Ok(Value::Float(d.to_f64())),
+                [value @ Value::Float(_)] => Ok(value.clone()),
+                [other] => Err(Diagnostic::type_error(Type::Float, other.type_of())),
+                _ => Err(Diagnostic::arity(1, args.len())),
+            },
+        },
+        BuiltinFn {
+            name: "numerator",
+            signature: Type::function(vec![Type::Decimal], Type::Integer),
+            func: |args, _ctx| match args {
+                [value] => Ok(big_integer(to_decimal(value)?.numerator)),
+                _ => Err(Diagnostic::arity(1, args.len())),
+            },
+        },
+        BuiltinFn {
+            name: "denominator",
+            signature: Type::function(vec![Type::Decimal], Type::Integer),
+            func: |args, _ctx| match args {
+                [value] => Ok(big_integer(to_decimal(value)?.denominator)),
+                _ => Err(Diagnostic::arity(1, args.len())),
+            },
+        },
+    ]
+}
+
+/// Returns `n` as an integer value, an `i64` if it fits in one.
+fn big_integer(n: BigInt) -> Value {
+    match n.to_i64() {
+        Some(n) => Value::Integer(n),
+        None => Value::BigInt(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(s: &str) -> Decimal {
+        Decimal::parse(s).unwrap()
+    }
+
+    #[test]
+    fn reduces_to_lowest_terms() {
+        let half = Decimal::new(BigInt::from(-3), BigInt::from(-6)).unwrap();
+        assert_eq!(half, decimal("0.5"));
+        assert_eq!(half.numerator(), &BigInt::from(1));
+        assert_eq!(half.denominator(), &BigInt::from(2));
+        assert_eq!(Decimal::new(BigInt::from(1), BigInt::from(0)), None);
+        assert_eq!(decimal("2.50"), decimal("2.5"));
+        assert_eq!(decimal("1_000.0"), Decimal::from(1000));
+    }
+
+    #[test]
+    fn displays_decimal_expansions() {
+        for (input, shown) in [
+            ("12.375", "12.375"),
+            ("-0.05", "-0.05"),
+            ("3.0", "3"),
+            ("0", "0"),
+            ("-7", "-7"),
+        ] {
+            assert_eq!(decimal(input).to_string(), shown);
+        }
+        let third = Decimal::new(BigInt::from(-1), BigInt::from(3)).unwrap();
+        assert_eq!(third.to_string(), "-1/3");
+        assert_eq!(format!("{third:?}"), "-1/3d");
+    }
+
+    #[test]
+    fn converts_floats_by_their_shortest_form() {
+        assert_eq!(Decimal::from_f64(0.1), Some(decimal("0.1")));
+        assert_eq!(Decimal::from_f64(-2.5), Some(decimal("-2.5")));
+        assert_eq!(
+            Decimal::from_f64(1e20),
+            Some(decimal("100000000000000000000"))
+        );
+        assert_eq!(Decimal::from_f64(f64::NAN), None);
+        assert_eq!(decimal("0.1").to_f64(), 0.1);
+    }
+
+    #[test]
+    fn orders_by_value() {
+        assert!(decimal("0.3") > decimal("0.25"));
+        assert!(decimal("-0.3") < decimal("-0.25"));
+        assert_eq!(decimal("1.10").cmp(&decimal("1.1")), Ordering::Equal);
+    }
+}
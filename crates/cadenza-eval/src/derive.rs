@@ -0,0 +1,348 @@
+//! Derived trait implementations.
+//!
+//! `@derive` before a `struct` generates implementations of the built-in
+//! traits for it and registers them in the [`TraitRegistry`]:
+//!
+//! ```cadenza
+//! @derive(Eq, Show, Json)
+//! struct Point { x = Integer, y = Integer }
+//!
+//! let p = Point { x = 1, y = 2 }
+//! eq p p        # true
+//! show p        # "Point {x = 1, y = 2}"
+//! to_json p     # "{\"x\":1,\"y\":2}"
+//! ```
+//!
+//! - `Eq` provides `eq a b`, true if every field is `eq`
+//! - `Show` provides `show value`, the struct name and each field's `show`
+//! - `Json` provides `to_json value`, an object of each field's `to_json`
+//!
+//! A derived method is generated as Cadenza code, like a macro expansion, and
+//! calls the trait on each field, so a field uses its own type's
+//! implementation.
+//!
+//! The `eq`, `show` and `to_json` builtins dispatch on their first argument.
+//! A struct must implement the trait, usually by deriving it. Other values
+//! have built-in behavior: they compare with `==`, show as they display, and
+//! lists, tuples and records encode their elements as JSON arrays and
+//! objects.
+//!
+//! Enums can't be defined yet, so only structs derive.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::apply_value,
+    interner::InternedString,
+    map::Map,
+    trait_registry::{TraitDef, TraitImpl, TraitRegistry},
+    value::{BuiltinFn, MethodSignature, Type, UserFunction, Value},
+};
+use cadenza_syntax::{parse::parse, span::Span};
+
+/// Returns a trait registry with the derivable traits defined.
+pub fn trait_registry() -> TraitRegistry {
+    let mut registry = TraitRegistry::new();
+    let traits = [
+        ("Eq", "eq", vec![Type::Unknown, Type::Unknown], Type::Bool),
+        ("Show", "show", vec![Type::Unknown], Type::String),
+        ("Json", "to_json", vec![Type::Unknown], Type::String),
+    ];
+    for (name, method, params, ret) in traits {
+        let method = MethodSignature::new(method.into(), params, ret);
+        registry
+            .define_trait(TraitDef::new(name.into(), vec![method]))
+            .expect("derivable traits are defined once");
+    }
+    registry
+}
+
+/// Derives `trait_name` for the struct `name` with `fields`, reporting
+/// errors at `span`.
+pub fn derive(
+    trait_name: InternedString,
+    name: InternedString,
+    fields: &[(InternedString, Type)],
+    span: Span,
+    ctx: &mut EvalContext<'_>,
+) -> Result<()> {
+    let (method, params, body) = match &*trait_name {
+        "Eq" => ("eq", ["a", "b"].as_slice(), eq_body(fields)),
+        "Show" => ("show", ["value"].as_slice(), show_body(name, fields)),
+        "Json" => ("to_json", ["value"].as_slice(), json_body(fields)),
+        other => {
+            return Err(Diagnostic::syntax(format!(
+                "cannot derive {other}: the derivable traits are Eq, Show and Json"
+            ))
+            .with_span(span));
+        }
+    };
+
+    let root = parse(&body).ast();
+    let body = root.items().next().expect("derived body is an expression");
+    let func = UserFunction {
+        name: format!("{}.{method}", &*name).as_str().into(),
+        params: params.iter().map(|param| (*param).into()).collect(),
+        patterns: vec![],
+        body,
+        captured_env: ctx.env.clone(),
+    };
+
+    let mut methods = Map::default();
+    methods.insert(method.into(), Value::UserFunction(func));
+    // Every value of the struct has this implementation, whatever its fields hold
+    let for_type = Type::Struct {
+        name,
+        fields: fields
+            .iter()
+            .map(|(field, _)| (*field, Type::Unknown))
+            .collect(),
+    };
+    ctx.compiler
+        .implement_trait(TraitImpl::new(trait_name, for_type, methods).with_span(span))
+}
+
+/// `a.x` and `b.x` are `eq`, for every field `x`, checked in order until a
+/// field differs.
+fn eq_body(fields: &[(InternedString, Type)]) -> String {
+    let mut fields = fields.iter().rev().map(|(field, _)| &**field);
+    let Some(last) = fields.next() else {
+        return "true".into();
+    };
+    fields.fold(format!("eq a.{last} b.{last}"), |rest, field| {
+        format!("if eq a.{field} b.{field} then {rest} else false")
+    })
+}
+
+/// `"Name {x = ..., y = ...}"`, laid out like a struct displays.
+fn show_body(name: InternedString, fields: &[(InternedString, Type)]) -> String {
+    let fields = fields
+        .iter()
+        .map(|(field, _)| format!("{0} = {{show value.{0}}}", &**field))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("\"{} \\{{{fields}\\}}\"", &*name)
+}
+
+/// `"{"x":...,"y":...}"`.
+fn json_body(fields: &[(InternedString, Type)]) -> String {
+    let fields = fields
+        .iter()
+        .map(|(field, _)| format!("\\\"{0}\\\":{{to_json value.{0}}}", &**field))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("\"\\{{{fields}\\}}\"")
+}
+
+/// Returns the `eq`, `show` and `to_json` builtins.
+pub fn builtins() -> Vec<BuiltinFn> {
+    vec![
+        BuiltinFn {
+            name: "eq",
+            signature: Type::function(vec![Type::Unknown, Type::Unknown], Type::Bool),
+            func: |args, ctx| {
+                let [a, b] = args else {
+                    return Err(Diagnostic::arity(2, args.len()));
+                };
+                eq(a, b, ctx).map(Value::Bool)
+            },
+        },
+        BuiltinFn {
+            name: "show",
+            signature: Type::function(vec![Type::Unknown], Type::String),
+            func: |args, ctx| {
+                let [value] = args else {
+                    return Err(Diagnostic::arity(1, args.len()));
+                };
+                show(value, ctx).map(|s| Value::String(s.into()))
+            },
+        },
+        BuiltinFn {
+            name: "to_json",
+            signature: Type::function(vec![Type::Unknown], Type::String),
+            func: |args, ctx| {
+                let [value] = args else {
+                    return Err(Diagnostic::arity(1, args.len()));
+                };
+                to_json(value, ctx).map(|s| Value::String(s.into()))
+            },
+        },
+    ]
+}
+
+/// Returns whether `a` and `b` are equal under `Eq`.
+pub fn eq(a: &Value, b: &Value, ctx: &mut EvalContext<'_>) -> Result<bool> {
+    match method(a, "Eq", "eq", ctx)? {
+        Some(method) => match apply_value(method, vec![a.clone(), b.clone()], ctx)? {
+            Value::Bool(equal) => Ok(equal),
+            other => Err(Diagnostic::type_error(Type::Bool, other.type_of())),
+        },
+        None => Ok(a == b),
+    }
+}
+
+/// Returns `value` shown under `Show`.
+pub fn show(value: &Value, ctx: &mut EvalContext<'_>) -> Result<String> {
+    match method(value, "Show", "show", ctx)? {
+        Some(method) => expect_string(apply_value(method, vec![value.clone()], ctx)?),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Returns `value` encoded as JSON under `Json`.
+pub fn to_json(value: &Value, ctx: &mut EvalContext<'_>) -> Result<String> {
+    if let Some(method) = method(value, "Json", "to_json", ctx)? {
+        return expect_string(apply_value(method, vec![value.clone()], ctx)?);
+    }
+    let encode = |s: &str| serde_json::to_string(s).expect("strings encode as JSON");
+    let array = |elements: &[Value], ctx: &mut EvalContext<'_>| -> Result<String> {
+        let elements = elements
+            .iter()
+            .map(|element| to_json(element, ctx))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(format!("[{}]", elements.join(",")))
+    };
+    match value {
+        Value::Nil => Ok("null".into()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Integer(n) => Ok(n.to_string()),
+        Value::BigInt(n) => Ok(n.to_string()),
+        Value::Float(n) => Ok(serde_json::to_string(n).expect("floats encode as JSON")),
+        // A decimal without a finite expansion is written as the nearest float
+        Value::Decimal(d) => match d.to_string() {
+            digits if !digits.contains('/') => Ok(digits),
+            _ => Ok(serde_json::to_string(&d.to_f64()).expect("floats encode as JSON")),
+        },
+        Value::String(s) => Ok(encode(s)),
+        Value::List(elements) | Value::Tuple { elements, .. } => array(elements, ctx),
+        Value::Range { start, end } => {
+            let elements: Vec<_> = (*start..*end).map(|n| n.to_string()).collect();
+            Ok(format!("[{}]", elements.join(",")))
+        }
+        Value::Vector(elements) => array(elements, ctx),
+        Value::Matrix {
+            columns, elements, ..
+        } => {
+            let rows = elements
+                .chunks((*columns).max(1))
+                .map(|row| array(row, ctx))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(format!("[{}]", rows.join(",")))
+        }
+        Value::Record { fields, .. } => {
+            let fields = fields
+                .iter()
+                .map(|(name, value)| Ok(format!("{}:{}", encode(name), to_json(value, ctx)?)))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(format!("{{{}}}", fields.join(",")))
+        }
+        other => Err(Diagnostic::missing_impl("Json".into(), other.type_of())),
+    }
+}
+
+/// Returns the `trait_name` implementation of `method_name` for `value` if
+/// it is a struct, or `None` for values with built-in behavior.
+///
+/// # Errors
+/// Returns an error if `value` is a struct that doesn't implement the trait.
+fn method(
+    value: &Value,
+    trait_name: &str,
+    method_name: &str,
+    ctx: &EvalContext<'_>,
+) -> Result<Option<Value>> {
+    let Value::Record {
+        type_name: Some(_), ..
+    } = value
+    else {
+        return Ok(None);
+    };
+    let (trait_name, ty) = (trait_name.into(), value.type_of());
+    let implementation = ctx
+        .compiler
+        .trait_registry()
+        .find_implementation(&ty, trait_name)
+        .ok_or_else(|| Diagnostic::missing_impl(trait_name, ty.clone()))?;
+    Ok(implementation
+        .methods
+        .get(&InternedString::from(method_name))
+        .cloned())
+}
+
+fn expect_string(value: Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.into_owned()),
+        other => Err(Diagnostic::type_error(Type::String, other.type_of())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn derived_methods_dispatch_on_the_struct() {
+        let (results, compiler) = eval_all(
+            "
+@derive(Eq, Show, Json)
+struct Part { name = String, layers = Integer, done = Bool }
+let part = Part { name = \"bracket\", layers = 120, done = true }
+eq part part
+eq part (Part { name = \"bracket\", layers = 3, done = true })
+show part
+to_json { parts = [part] }
+",
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results[3..5], [Value::Bool(true), Value::Bool(false)]);
+        assert_eq!(
+            results[5..],
+            [
+                Value::String("Part {name = bracket, layers = 120, done = true}".into()),
+                Value::String(r#"{"parts":[{"name":"bracket","layers":120,"done":true}]}"#.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn struct_without_implementation_is_an_error() {
+        let (_, compiler) = eval_all(
+            "
+@derive Show
+struct Meters { value = Float }
+to_json (Meters { value = 1.5 })
+",
+        );
+        let codes: Vec<_> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.kind().code())
+            .collect();
+        assert_eq!(codes, ["E0020"]);
+    }
+
+    #[test]
+    fn unknown_traits_are_rejected() {
+        let (_, compiler) = eval_all(
+            "
+@derive(Eq, Hash)
+struct Id { value = Integer }
+",
+        );
+        let messages: Vec<String> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(messages.len(), 1, "{messages:?}");
+        assert!(messages[0].contains("cannot derive Hash"), "{messages:?}");
+    }
+}
@@ -3,12 +3,29 @@
 //! Diagnostics are structured with a `DiagnosticKind` that describes the issue,
 //! wrapped in a `Diagnostic` that carries severity, source location, and stack trace.
 //! Uses miette for standardized diagnostic reporting.
-
-use crate::{interner::InternedString, value::Type};
+//!
+//! # Spans
+//!
+//! Every recorded diagnostic carries a span. Code that raises a diagnostic
+//! should attach the span of the expression at fault when it has one (for
+//! example an argument rather than the whole call). Anything that doesn't is
+//! given the span of the innermost expression being evaluated when it
+//! surfaced (see [`Diagnostic::or_span`]), so a builtin's error points at the
+//! call that raised it. Synthetic nodes such as the `__list__` receiver of
+//! `[1, 2]` have an empty span at their position, so they never claim a span
+//! before their enclosing application does.
+//!
+//! [`Compiler::record_diagnostic`](crate::Compiler::record_diagnostic) checks
+//! this with a debug assertion.
+
+use crate::{
+    interner::InternedString,
+    messages,
+    value::{Type, Value},
+};
 use cadenza_syntax::span::Span;
 use miette::{Diagnostic as MietteDiagnostic, Severity};
 use std::fmt;
-use thiserror::Error;
 
 /// Result type for evaluator operations.
 ///
@@ -18,6 +35,7 @@ pub type Result<T> = std::result::Result<T, Box<Diagnostic>>;
 
 /// The severity level of a diagnostic.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
 pub enum DiagnosticLevel {
     /// An error that prevents compilation or execution.
     #[default]
@@ -28,6 +46,17 @@ pub enum DiagnosticLevel {
     Hint,
 }
 
+impl DiagnosticLevel {
+    /// Returns the lowercase name of the level, as in `"warning"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticLevel::Error => "error",
+            DiagnosticLevel::Warning => "warning",
+            DiagnosticLevel::Hint => "hint",
+        }
+    }
+}
+
 impl From<DiagnosticLevel> for Severity {
     fn from(level: DiagnosticLevel) -> Self {
         match level {
@@ -79,41 +108,287 @@ impl StackFrame {
 }
 
 /// The kind of diagnostic that occurred during evaluation.
-#[derive(Debug, Clone, Error)]
+///
+/// Each kind displays as its code's message in the installed
+/// [catalog](crate::messages).
+#[derive(Debug, Clone)]
 pub enum DiagnosticKind {
     /// An undefined variable was referenced.
-    #[error("undefined variable: {0}")]
-    UndefinedVariable(InternedString),
+    UndefinedVariable {
+        name: InternedString,
+        /// A similarly spelled name that is defined, if there is one.
+        suggestion: Option<InternedString>,
+    },
 
     /// A value was used in an invalid way for its type.
-    #[error("type error: expected {expected}, got {actual}")]
     TypeError { expected: Type, actual: Type },
 
     /// Wrong number of arguments to a function or macro.
-    #[error("arity error: expected {expected} arguments, got {actual}")]
     ArityError { expected: usize, actual: usize },
 
     /// A value is not callable (not a function or macro).
-    #[error("not callable: {0}")]
     NotCallable(Type),
 
     /// Invalid syntax in AST.
-    #[error("syntax error: {0}")]
     SyntaxError(String),
 
     /// A parse error from the parser.
-    #[error("parse error: {0}")]
     ParseError(String),
 
     /// An internal error in the evaluator.
-    #[error("internal error: {0}")]
     InternalError(String),
 
     /// An assertion failed during runtime.
-    #[error("assertion failed: {message}")]
     AssertionFailed { message: String },
+
+    /// Evaluation used up its fuel budget.
+    OutOfFuel { limit: u64 },
+
+    /// Function calls nested deeper than the recursion limit allows.
+    RecursionLimit { limit: u64 },
+
+    /// Two modules in a compilation unit define the same top-level name.
+    DuplicateSymbol {
+        name: InternedString,
+        module: InternedString,
+    },
+
+    /// An import names a module that is not part of the compilation unit.
+    UnresolvedImport(InternedString),
+
+    /// Modules import each other in a cycle.
+    ImportCycle(String),
+
+    /// A private top-level function is never referenced.
+    UnusedFunction(InternedString),
+
+    /// A variable bound to a poisoned value was used.
+    ///
+    /// The failure that poisoned it has already been reported, so this
+    /// diagnostic only unwinds evaluation and is never recorded.
+    Poisoned(InternedString),
+
+    /// Two implementations of a trait could both apply to the same type, and
+    /// neither is more specific than the other.
+    OverlappingImpl {
+        trait_name: InternedString,
+        for_type: Type,
+        existing: Type,
+    },
+
+    /// A trait is implemented in a module that defines neither the trait nor
+    /// the type.
+    OrphanImpl {
+        trait_name: InternedString,
+        for_type: Type,
+    },
+
+    /// A linear value was used after its ownership moved to another binding
+    /// or function.
+    UseAfterMove(InternedString),
+
+    /// Generated code trapped at runtime.
+    Trap(String),
+
+    /// A fallible function in generated code returned a failure to its host.
+    Failure(String),
+
+    /// Functions call each other in a cycle that can grow the stack without
+    /// bound.
+    UnboundedRecursion(String),
+
+    /// A function marked `@total` recurses in a way that isn't evidently
+    /// terminating (see [`crate::totality`]).
+    MayNotTerminate {
+        name: InternedString,
+        reason: String,
+    },
+
+    /// A float operation whose result can differ between hosts was used
+    /// with the `deterministic-floats` feature.
+    NondeterministicFloat(InternedString),
+
+    /// A macro reflected on a name that isn't defined where it expands.
+    NotYetDefined(InternedString),
+
+    /// A `let rec` binding was used while its value was being evaluated,
+    /// before the value existed.
+    Uninitialized(InternedString),
+
+    /// A trait method was applied to a type that doesn't implement the trait.
+    MissingImpl {
+        trait_name: InternedString,
+        for_type: Type,
+    },
+
+    /// The program called `exit`.
+    ///
+    /// This diagnostic only unwinds evaluation to the top level, which stops
+    /// there; the compiler keeps the status instead of recording it.
+    Exit { code: i32 },
+
+    /// The program called `abort`, which stops evaluation.
+    Aborted { message: String },
+
+    /// Every task is waiting on a channel or another task, so none can make
+    /// progress (see [`crate::task`]).
+    Deadlock(String),
+
+    /// A record or struct, described by `record`, has no field `name`.
+    FieldNotFound {
+        name: InternedString,
+        record: String,
+    },
+
+    /// Element `index` of a tuple with `len` elements was projected.
+    IndexOutOfBounds { index: usize, len: usize },
+
+    /// `?` passed on an `Err` or a `None`.
+    ///
+    /// This diagnostic only unwinds evaluation to the enclosing function,
+    /// which returns the value; it is an error when it reaches the top level.
+    Propagated(Box<Value>),
+}
+
+impl DiagnosticKind {
+    /// Returns the stable code for this kind of diagnostic, such as `E0001`.
+    ///
+    /// Errors are numbered `E`, warnings `W`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UndefinedVariable { .. } => "E0001",
+            Self::TypeError { .. } => "E0002",
+            Self::ArityError { .. } => "E0003",
+            Self::NotCallable(_) => "E0004",
+            Self::SyntaxError(_) => "E0005",
+            Self::ParseError(_) => "E0007",
+            Self::InternalError(_) => "E0006",
+            Self::AssertionFailed { .. } => "E0008",
+            Self::OutOfFuel { .. } => "E0009",
+            Self::DuplicateSymbol { .. } => "E0010",
+            Self::UnresolvedImport(_) => "E0011",
+            Self::ImportCycle(_) => "E0012",
+            Self::UnusedFunction(_) => "W0001",
+            Self::Poisoned(_) => "E0013",
+            Self::OverlappingImpl { .. } => "E0014",
+            Self::OrphanImpl { .. } => "W0002",
+            Self::UseAfterMove(_) => "E0015",
+            Self::Trap(_) => "E0016",
+            Self::Failure(_) => "E0017",
+            Self::UnboundedRecursion(_) => "W0003",
+            Self::MayNotTerminate { .. } => "W0004",
+            Self::NondeterministicFloat(_) => "E0018",
+            Self::NotYetDefined(_) => "E0019",
+            Self::MissingImpl { .. } => "E0020",
+            Self::RecursionLimit { .. } => "E0021",
+            Self::Exit { .. } => "E0022",
+            Self::Aborted { .. } => "E0023",
+            Self::Propagated(_) => "E0024",
+            Self::Uninitialized(_) => "E0025",
+            Self::Deadlock(_) => "E0026",
+            Self::FieldNotFound { .. } => "E0027",
+            Self::IndexOutOfBounds { .. } => "E0028",
+        }
+    }
+
+    /// Returns the named parameters interpolated into this kind's message
+    /// template.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::UndefinedVariable { name, suggestion } => {
+                let mut params = vec![("name", name.to_string())];
+                params.extend(suggestion.map(|suggestion| ("suggestion", suggestion.to_string())));
+                params
+            }
+            Self::UnusedFunction(name)
+            | Self::Poisoned(name)
+            | Self::UseAfterMove(name)
+            | Self::NotYetDefined(name)
+            | Self::Uninitialized(name) => vec![("name", name.to_string())],
+            Self::TypeError { expected, actual } => vec![
+                ("expected", expected.to_string()),
+                ("actual", actual.to_string()),
+            ],
+            Self::ArityError { expected, actual } => vec![
+                ("expected", expected.to_string()),
+                ("actual", actual.to_string()),
+            ],
+            Self::NotCallable(ty) => vec![("type", ty.to_string())],
+            Self::SyntaxError(message)
+            | Self::ParseError(message)
+            | Self::InternalError(message)
+            | Self::AssertionFailed { message }
+            | Self::Trap(message)
+            | Self::Failure(message)
+            | Self::Deadlock(message)
+            | Self::Aborted { message } => vec![("message", message.clone())],
+            Self::Exit { code } => vec![("code", code.to_string())],
+            Self::FieldNotFound { name, record } => {
+                vec![("name", name.to_string()), ("record", record.clone())]
+            }
+            Self::IndexOutOfBounds { index, len } => {
+                vec![("index", index.to_string()), ("len", len.to_string())]
+            }
+            Self::Propagated(value) => vec![("value", value.to_string())],
+            Self::OutOfFuel { limit } | Self::RecursionLimit { limit } => {
+                vec![("limit", limit.to_string())]
+            }
+            Self::DuplicateSymbol { name, module } => {
+                vec![("name", name.to_string()), ("module", module.to_string())]
+            }
+            Self::UnresolvedImport(module) => vec![("module", module.to_string())],
+            Self::ImportCycle(cycle) | Self::UnboundedRecursion(cycle) => {
+                vec![("cycle", cycle.clone())]
+            }
+            Self::OverlappingImpl {
+                trait_name,
+                for_type,
+                existing,
+            } => vec![
+                ("trait", trait_name.to_string()),
+                ("type", for_type.to_string()),
+                ("existing", existing.to_string()),
+            ],
+            Self::OrphanImpl {
+                trait_name,
+                for_type,
+            }
+            | Self::MissingImpl {
+                trait_name,
+                for_type,
+            } => vec![
+                ("trait", trait_name.to_string()),
+                ("type", for_type.to_string()),
+            ],
+            Self::NondeterministicFloat(operation) => vec![("operation", operation.to_string())],
+            Self::MayNotTerminate { name, reason } => {
+                vec![("name", name.to_string()), ("reason", reason.clone())]
+            }
+        }
+    }
+
+    /// Returns advice on fixing the problem, such as the name an undefined
+    /// variable was probably meant to be, from the installed
+    /// [catalog](crate::messages).
+    pub fn help(&self) -> Option<String> {
+        match self {
+            Self::UndefinedVariable {
+                suggestion: Some(_),
+                ..
+            } => Some(messages::help(self)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&messages::message(self))
+    }
 }
 
+impl std::error::Error for DiagnosticKind {}
+
 /// A diagnostic message with source location and stack trace.
 ///
 /// This is the primary type for reporting issues during evaluation.
@@ -148,14 +423,7 @@ impl fmt::Display for Diagnostic {
             DiagnosticLevel::Hint => write!(f, "hint: ")?,
         }
 
-        // Write the diagnostic kind with resolved names
-        match &self.kind {
-            DiagnosticKind::UndefinedVariable(id) => {
-                write!(f, "undefined variable: {}", &**id)?;
-            }
-            // For other kinds, use default Display
-            kind => write!(f, "{}", kind)?,
-        }
+        write!(f, "{}", self.kind)?;
 
         // Write location info
         if let Some(file) = &self.file {
@@ -164,6 +432,9 @@ impl fmt::Display for Diagnostic {
         if let Some(span) = self.span {
             write!(f, " at {}..{}", span.start, span.end)?;
         }
+        if let Some(help) = self.kind.help() {
+            write!(f, "\n  help: {help}")?;
+        }
 
         // Write stack trace
         if !self.stack_trace.is_empty() {
@@ -196,17 +467,13 @@ impl MietteDiagnostic for Diagnostic {
     }
 
     fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
-        let code = match &self.kind {
-            DiagnosticKind::UndefinedVariable(_) => "E0001",
-            DiagnosticKind::TypeError { .. } => "E0002",
-            DiagnosticKind::ArityError { .. } => "E0003",
-            DiagnosticKind::NotCallable(_) => "E0004",
-            DiagnosticKind::SyntaxError(_) => "E0005",
-            DiagnosticKind::ParseError(_) => "E0007",
-            DiagnosticKind::InternalError(_) => "E0006",
-            DiagnosticKind::AssertionFailed { .. } => "E0008",
-        };
-        Some(Box::new(code))
+        Some(Box::new(self.kind.code()))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.kind
+            .help()
+            .map(|help| Box::new(help) as Box<dyn fmt::Display>)
     }
 }
 
@@ -259,6 +526,12 @@ impl Diagnostic {
         self
     }
 
+    /// Sets the span for this diagnostic if it doesn't already have one.
+    pub fn or_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
     /// Sets the source file for this diagnostic.
     pub fn with_file(mut self, file: InternedString) -> Self {
         self.file = Some(file);
@@ -293,7 +566,13 @@ impl Diagnostic {
 
     /// Creates an undefined variable error from an interned ID.
     pub fn undefined_variable(id: InternedString) -> Box<Self> {
-        Box::new(Self::new(DiagnosticKind::UndefinedVariable(id), None))
+        Box::new(Self::new(
+            DiagnosticKind::UndefinedVariable {
+                name: id,
+                suggestion: None,
+            },
+            None,
+        ))
     }
 
     /// Creates a type error with an expected type and actual type.
@@ -335,6 +614,214 @@ impl Diagnostic {
         Box::new(Self::new(DiagnosticKind::InternalError(msg.into()), None))
     }
 
+    /// Creates an out-of-fuel error for the given step limit.
+    pub fn out_of_fuel(limit: u64) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::OutOfFuel { limit }, None))
+    }
+
+    /// Creates an error for calls nesting deeper than `limit`.
+    pub fn recursion_limit(limit: u64) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::RecursionLimit { limit }, None))
+    }
+
+    /// Creates an error for a wait that can never end, described by `msg`.
+    pub fn deadlock(msg: impl Into<String>) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::Deadlock(msg.into()), None))
+    }
+
+    /// Creates an error for a field `name` that the record or struct
+    /// described by `record` (such as `struct Point`) doesn't have.
+    pub fn field_not_found(name: InternedString, record: impl Into<String>) -> Box<Self> {
+        Box::new(Self::new(
+            DiagnosticKind::FieldNotFound {
+                name,
+                record: record.into(),
+            },
+            None,
+        ))
+    }
+
+    /// Creates an error for projecting element `index` of a tuple with `len`
+    /// elements.
+    pub fn index_out_of_bounds(index: usize, len: usize) -> Box<Self> {
+        Box::new(Self::new(
+            DiagnosticKind::IndexOutOfBounds { index, len },
+            None,
+        ))
+    }
+
+    /// Creates the diagnostic that stops the program with exit status `code`.
+    pub fn exit(code: i32) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::Exit { code }, None))
+    }
+
+    /// Creates the error that stops the program because it aborted with
+    /// `message`.
+    pub fn aborted(message: impl Into<String>) -> Box<Self> {
+        Box::new(Self::new(
+            DiagnosticKind::Aborted {
+                message: message.into(),
+            },
+            None,
+        ))
+    }
+
+    /// Creates a duplicate-symbol error for `name`, first defined in `module`.
+    pub fn duplicate_symbol(name: InternedString, module: InternedString) -> Box<Self> {
+        Box::new(Self::new(
+            DiagnosticKind::DuplicateSymbol { name, module },
+            None,
+        ))
+    }
+
+    /// Creates an unresolved import error.
+    pub fn unresolved_import(module: InternedString) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::UnresolvedImport(module), None))
+    }
+
+    /// Creates an import cycle error from the modules in the cycle.
+    pub fn import_cycle(modules: &[InternedString]) -> Box<Self> {
+        let path: Vec<&str> = modules.iter().map(|m| &**m).collect();
+        Box::new(Self::new(
+            DiagnosticKind::ImportCycle(path.join(" -> ")),
+            None,
+        ))
+    }
+
+    /// Creates an unused-function warning.
+    pub fn unused_function(name: InternedString) -> Box<Self> {
+        Box::new(Self::with_level(
+            DiagnosticKind::UnusedFunction(name),
+            DiagnosticLevel::Warning,
+        ))
+    }
+
+    /// Creates an unbounded-recursion warning from the functions in the
+    /// cycle, in call order.
+    pub fn unbounded_recursion(cycle: &[InternedString]) -> Box<Self> {
+        let path: Vec<&str> = cycle.iter().map(|f| &**f).collect();
+        Box::new(Self::with_level(
+            DiagnosticKind::UnboundedRecursion(path.join(" -> ")),
+            DiagnosticLevel::Warning,
+        ))
+    }
+
+    /// Creates the warning that `@total` function `name` may not terminate,
+    /// because of `reason`.
+    pub fn may_not_terminate(name: InternedString, reason: impl Into<String>) -> Box<Self> {
+        Box::new(Self::with_level(
+            DiagnosticKind::MayNotTerminate {
+                name,
+                reason: reason.into(),
+            },
+            DiagnosticLevel::Warning,
+        ))
+    }
+
+    /// Creates the error for a use of the float operation `name` with the
+    /// `deterministic-floats` feature.
+    pub fn nondeterministic_float(name: InternedString) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::NondeterministicFloat(name), None))
+    }
+
+    /// Creates the error for a macro reflecting on `name` before it is
+    /// defined.
+    pub fn not_yet_defined(name: InternedString) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::NotYetDefined(name), None))
+    }
+
+    /// Creates an error for using the `let rec` binding `name` before its
+    /// value exists.
+    pub fn uninitialized(name: InternedString) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::Uninitialized(name), None))
+    }
+
+    /// Creates an error for applying a method of `trait_name` to `for_type`,
+    /// which doesn't implement it.
+    pub fn missing_impl(trait_name: InternedString, for_type: Type) -> Box<Self> {
+        Box::new(Self::new(
+            DiagnosticKind::MissingImpl {
+                trait_name,
+                for_type,
+            },
+            None,
+        ))
+    }
+
+    /// Creates an overlapping-implementation error for `trait_name` on
+    /// `for_type`, which conflicts with the implementation for `existing`.
+    pub fn overlapping_impl(
+        trait_name: InternedString,
+        for_type: Type,
+        existing: Type,
+    ) -> Box<Self> {
+        Box::new(Self::new(
+            DiagnosticKind::OverlappingImpl {
+                trait_name,
+                for_type,
+                existing,
+            },
+            None,
+        ))
+    }
+
+    /// Creates an orphan-implementation warning.
+    pub fn orphan_impl(trait_name: InternedString, for_type: Type) -> Box<Self> {
+        Box::new(Self::with_level(
+            DiagnosticKind::OrphanImpl {
+                trait_name,
+                for_type,
+            },
+            DiagnosticLevel::Warning,
+        ))
+    }
+
+    /// Creates a use-after-move error for the variable `name`.
+    pub fn use_after_move(name: InternedString) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::UseAfterMove(name), None))
+    }
+
+    /// Creates the diagnostic raised when a poisoned variable is used.
+    pub fn poisoned(name: InternedString) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::Poisoned(name), None))
+    }
+
+    /// Returns true if this diagnostic only unwinds evaluation past a value
+    /// that is already known to be broken.
+    pub fn is_poisoned(&self) -> bool {
+        matches!(self.kind, DiagnosticKind::Poisoned(_))
+    }
+
+    /// Returns true if this diagnostic stops the program, from `exit` or
+    /// `abort`.
+    pub fn stops_program(&self) -> bool {
+        matches!(
+            self.kind,
+            DiagnosticKind::Exit { .. } | DiagnosticKind::Aborted { .. }
+        )
+    }
+
+    /// Creates the diagnostic that carries `value`, an `Err` or a `None`
+    /// passed on with `?`, to the enclosing function.
+    pub fn propagated(value: Value) -> Box<Self> {
+        Box::new(Self::new(DiagnosticKind::Propagated(Box::new(value)), None))
+    }
+
+    /// Returns the value this diagnostic carries if `?` passed one on, which
+    /// the enclosing function returns, or else the diagnostic.
+    pub fn into_propagated(self: Box<Self>) -> Result<Value> {
+        match self.kind {
+            DiagnosticKind::Propagated(ref value) => Ok((**value).clone()),
+            _ => Err(self),
+        }
+    }
+
+    /// Returns true if this diagnostic marks code that can be removed, which
+    /// editors render grayed out.
+    pub fn is_unnecessary(&self) -> bool {
+        matches!(self.kind, DiagnosticKind::UnusedFunction(_))
+    }
+
     /// Creates an assertion failed error.
     pub fn assertion_failed(msg: impl Into<String>) -> Box<Self> {
         Box::new(Self::new(
@@ -350,6 +837,8 @@ impl Diagnostic {
 pub trait BoxedDiagnosticExt {
     /// Sets the span for this diagnostic.
     fn with_span(self, span: Span) -> Box<Diagnostic>;
+    /// Sets the span for this diagnostic if it doesn't already have one.
+    fn or_span(self, span: Span) -> Box<Diagnostic>;
     /// Sets the source file for this diagnostic.
     fn with_file(self, file: InternedString) -> Box<Diagnostic>;
     /// Sets the severity level for this diagnostic.
@@ -362,6 +851,11 @@ impl BoxedDiagnosticExt for Box<Diagnostic> {
         self
     }
 
+    fn or_span(mut self, span: Span) -> Box<Diagnostic> {
+        self.span.get_or_insert(span);
+        self
+    }
+
     fn with_file(mut self, file: InternedString) -> Box<Diagnostic> {
         self.file = Some(file);
         self
@@ -407,7 +901,10 @@ mod tests {
 
         // Note: Without the interner, the display shows the raw ID
         // The display_with_interned_string method is used to get the resolved name
-        let kind = DiagnosticKind::UndefinedVariable(foo_id);
+        let kind = DiagnosticKind::UndefinedVariable {
+            name: foo_id,
+            suggestion: None,
+        };
         let display = format!("{}", kind);
         assert!(display.starts_with("undefined variable:"));
 
@@ -4,21 +4,29 @@
 //! identifiers to values. Closures capture the environment by reference.
 
 use crate::{
+    derive,
+    diagnostic::Result,
     eval::{
         builtin_add, builtin_div, builtin_eq, builtin_gt, builtin_gte, builtin_lt, builtin_lte,
-        builtin_mul, builtin_ne, builtin_sub,
+        builtin_mul, builtin_ne, builtin_rem, builtin_sub,
     },
+    hash,
     interner::InternedString,
+    json, list,
     map::Map,
-    special_form,
+    math,
+    options::CompileOptions,
+    prelude, result, special_form, string, system, task,
     value::{Type, Value},
 };
-use std::{collections::HashSet, rc::Rc};
+use std::{collections::HashSet, sync::Arc};
 
 /// A single scope in the environment.
 #[derive(Debug, Clone, Default)]
 pub struct Scope {
     bindings: Map<Value>,
+    /// Names declared by `let rec` whose values are still being evaluated.
+    uninitialized: HashSet<InternedString>,
 }
 
 impl Scope {
@@ -29,6 +37,7 @@ impl Scope {
 
     /// Defines a binding in this scope.
     pub fn define(&mut self, name: InternedString, value: Value) {
+        self.uninitialized.remove(&name);
         self.bindings.insert(name, value);
     }
 
@@ -53,17 +62,23 @@ impl Scope {
 /// Variable lookup searches from the top scope to the bottom.
 /// New scopes are pushed for function calls and let bindings.
 ///
-/// Uses Rc for cheap cloning - useful for closures that capture their environment.
+/// Uses Arc for cheap cloning - useful for closures that capture their environment.
+/// Arc rather than Rc keeps scopes `Send` and `Sync`, so the prelude is
+/// evaluated once and shared by every thread.
+///
+/// Below all scopes sits the [prelude](crate::prelude), which is searched last.
 #[derive(Debug, Clone, Default)]
 pub struct Env {
-    scopes: Rc<Vec<Scope>>,
+    scopes: Arc<Vec<Scope>>,
+    prelude: Arc<Scope>,
 }
 
 impl Env {
     /// Creates a new environment with an empty global scope.
     pub fn new() -> Self {
         Self {
-            scopes: Rc::new(vec![Scope::new()]),
+            scopes: Arc::new(vec![Scope::new()]),
+            prelude: Arc::default(),
         }
     }
 
@@ -73,23 +88,60 @@ impl Env {
     /// - `let` - Variable declaration macro
     /// - `=` - Assignment macro
     /// - `fn` - Function definition macro
+    /// - `if` - Conditional macro (`if c then a else b`)
     /// - `assert` - Assertion macro for runtime checks
     /// - `measure` - Unit definition macro for dimensional analysis
     /// - `|>` - Pipeline operator macro
+    /// - `?`, `|?` - Try operators, which pass on an `Err` or a `None`
+    /// - `->` - Anonymous function macro
+    /// - `for` - Loop macro (`for x in xs -> body`)
     /// - `__block__` - Block expression macro (automatically emitted by parser)
+    /// - `do` - Block expression usable anywhere an expression is
     /// - `__list__` - List literal macro (automatically emitted by parser)
     /// - `__record__` - Record literal macro (automatically emitted by parser)
     /// - `__index__` - Array indexing macro (automatically emitted by parser)
+    /// - `__interpolate__` - String interpolation macro (automatically emitted by parser)
     /// - Arithmetic operators: `+`, `-`, `*`, `/`
     /// - Comparison operators: `==`, `!=`, `<`, `<=`, `>`, `>=`
     ///
+    /// The [prelude](crate::prelude) is loaded on top of the built-ins.
+    ///
     /// Use this when you want an environment ready for typical evaluation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prelude fails to evaluate, which it only does if it has
+    /// a bug; see [`Env::try_with_standard_builtins`].
     pub fn with_standard_builtins() -> Self {
+        Self::try_with_standard_builtins().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Creates a new environment with standard built-in forms and functions,
+    /// like [`Env::with_standard_builtins`], returning the error if the
+    /// prelude fails to evaluate.
+    pub fn try_with_standard_builtins() -> Result<Self> {
+        let mut env = Self::without_prelude();
+        env.prelude = prelude::standard()?;
+        Ok(env)
+    }
+
+    /// Creates a new environment with the standard built-ins but without the prelude.
+    pub fn without_prelude() -> Self {
         let mut env = Self::new();
         env.register_standard_builtins();
         env
     }
 
+    /// Creates a new environment with the standard built-ins, loading the
+    /// prelude if `options.prelude` is set.
+    pub fn with_options(options: &CompileOptions) -> Self {
+        if options.prelude {
+            Self::with_standard_builtins()
+        } else {
+            Self::without_prelude()
+        }
+    }
+
     /// Registers all standard built-in forms and functions in the current environment.
     ///
     /// This registers:
@@ -97,16 +149,38 @@ impl Env {
     /// - `=` - Assignment macro
     /// - `fn` - Function definition macro
     /// - `match` - Pattern matching macro for booleans
+    /// - `if` - Conditional macro (`if c then a else b`)
     /// - `assert` - Assertion macro for runtime checks
     /// - `typeof` - Type query macro (returns type as string)
+    /// - `arity`, `fields` - Parameter count and field queries (see [`crate::reflect`])
     /// - `measure` - Unit definition macro for dimensional analysis
     /// - `|>` - Pipeline operator macro
+    /// - `->` - Anonymous function macro
+    /// - `@` - Attribute macro (e.g. `@memoize`)
+    /// - `spawn` - Task creation macro, with `await`, `channel`, `send`, `recv`, `close`
+    /// - `import` - Module dependency declaration (see [`crate::module_graph`])
     /// - `__block__` - Block expression macro (automatically emitted by parser)
+    /// - `do` - Block expression usable anywhere an expression is
     /// - `__list__` - List literal macro (automatically emitted by parser)
     /// - `__record__` - Record literal macro (automatically emitted by parser)
     /// - `__index__` - Array indexing macro (automatically emitted by parser)
+    /// - `__interpolate__` - String interpolation macro (automatically emitted by parser)
     /// - Arithmetic operators: `+`, `-`, `*`, `/`
     /// - Comparison operators: `==`, `!=`, `<`, `<=`, `>`, `>=`
+    /// - Host access: `read_file`, `write_file`, `exec`, `env_var`, `env`, `read_line`, `read_all`,
+    ///   `write`, `write_line`, `http_get`, `http_post` (capability-gated, with the `http`
+    ///   feature), and `json_decode`
+    /// - List primitives: `len`, `nth`, `push` (the prelude adds `map`, `filter`, `fold`,
+    ///   `head`, `tail` and the rest, see [`crate::list`])
+    /// - Math: `sin`, `cos`, `tan`, `asin`, `acos`, `atan`, `atan2`, `exp`, `ln`,
+    ///   `log10`, `pow`, and the exact `sqrt`, `abs`, `floor`, `ceil`, `round` (the prelude
+    ///   adds `min` and `max`, see [`crate::math`])
+    /// - Strings: `concat`, `to_string`, `split`, `contains`, `to_upper`, `parse_int`
+    /// - `__escape__` - Decodes escapes that JSON and TOML strings spell differently
+    ///   (emitted by those front ends)
+    /// - Derivable trait methods: `eq`, `show`, `to_json` (see [`crate::derive`])
+    /// - `hash` - Stable hash of a hashable value
+    /// - `Option` and `Result`, with `Some`, `None`, `Ok` and `Err` (see [`crate::result`])
     ///
     /// This can be called on an existing environment to add the standard built-ins.
     pub fn register_standard_builtins(&mut self) {
@@ -115,16 +189,32 @@ impl Env {
         let assign_id: InternedString = "=".into();
         let fn_id: InternedString = "fn".into();
         let match_id: InternedString = "match".into();
+        let if_id: InternedString = "if".into();
         let assert_id: InternedString = "assert".into();
         let typeof_id: InternedString = "typeof".into();
+        let arity_id: InternedString = "arity".into();
+        let fields_id: InternedString = "fields".into();
         let measure_id: InternedString = "measure".into();
         let pipeline_id: InternedString = "|>".into();
+        let lambda_id: InternedString = "->".into();
+        let for_id: InternedString = "for".into();
         let block_id: InternedString = "__block__".into();
+        let do_id: InternedString = "do".into();
         let list_id: InternedString = "__list__".into();
         let record_id: InternedString = "__record__".into();
         let tuple_id: InternedString = "__tuple__".into();
+        let table_id: InternedString = "__table__".into();
         let index_id: InternedString = "__index__".into();
+        let interpolate_id: InternedString = "__interpolate__".into();
         let struct_id: InternedString = "struct".into();
+        let enum_id: InternedString = "enum".into();
+        let attr_id: InternedString = "@".into();
+        let spawn_id: InternedString = "spawn".into();
+        let import_id: InternedString = "import".into();
+        let try_id: InternedString = "?".into();
+        let pipe_try_id: InternedString = "|?".into();
+        let and_id: InternedString = "&&".into();
+        let or_id: InternedString = "||".into();
 
         self.define(let_id, Value::SpecialForm(special_form::let_form::get()));
         self.define(
@@ -136,6 +226,7 @@ impl Env {
             match_id,
             Value::SpecialForm(special_form::match_form::get()),
         );
+        self.define(if_id, Value::SpecialForm(special_form::if_form::get()));
         self.define(
             assert_id,
             Value::SpecialForm(special_form::assert_form::get()),
@@ -144,6 +235,14 @@ impl Env {
             typeof_id,
             Value::SpecialForm(special_form::typeof_form::get()),
         );
+        self.define(
+            arity_id,
+            Value::SpecialForm(special_form::arity_form::get()),
+        );
+        self.define(
+            fields_id,
+            Value::SpecialForm(special_form::fields_form::get()),
+        );
         self.define(
             measure_id,
             Value::SpecialForm(special_form::measure_form::get()),
@@ -152,10 +251,16 @@ impl Env {
             pipeline_id,
             Value::SpecialForm(special_form::pipeline_form::get()),
         );
+        self.define(
+            lambda_id,
+            Value::SpecialForm(special_form::lambda_form::get()),
+        );
+        self.define(for_id, Value::SpecialForm(special_form::for_form::get()));
         self.define(
             block_id,
             Value::SpecialForm(special_form::block_form::get()),
         );
+        self.define(do_id, Value::SpecialForm(special_form::do_form::get()));
         self.define(list_id, Value::SpecialForm(special_form::list_form::get()));
         self.define(
             record_id,
@@ -165,25 +270,127 @@ impl Env {
             tuple_id,
             Value::SpecialForm(special_form::tuple_form::get()),
         );
+        self.define(
+            table_id,
+            Value::SpecialForm(special_form::table_form::get()),
+        );
         self.define(
             index_id,
             Value::SpecialForm(special_form::index_form::get()),
         );
+        self.define(
+            interpolate_id,
+            Value::SpecialForm(special_form::interpolate_form::get()),
+        );
         self.define(
             struct_id,
             Value::SpecialForm(special_form::struct_form::get()),
         );
+        self.define(enum_id, Value::SpecialForm(special_form::enum_form::get()));
+        self.define(attr_id, Value::SpecialForm(special_form::attr_form::get()));
+        self.define(
+            spawn_id,
+            Value::SpecialForm(special_form::spawn_form::get()),
+        );
+        self.define(
+            import_id,
+            Value::SpecialForm(special_form::import_form::get()),
+        );
+        self.define(try_id, Value::SpecialForm(special_form::try_form::get()));
+        self.define(
+            pipe_try_id,
+            Value::SpecialForm(special_form::try_form::get()),
+        );
+        self.define(and_id, Value::SpecialForm(special_form::and_form::get()));
+        self.define(or_id, Value::SpecialForm(special_form::or_form::get()));
+
+        // Tasks and channels
+        for builtin in [
+            task::builtin_await(),
+            task::builtin_channel(),
+            task::builtin_send(),
+            task::builtin_recv(),
+            task::builtin_close(),
+        ] {
+            self.define(builtin.name.into(), Value::BuiltinFn(builtin));
+        }
+
+        // Host access (capability-gated)
+        for builtin in [
+            system::builtin_read_file(),
+            system::builtin_write_file(),
+            system::builtin_exec(),
+            system::builtin_env_var(),
+            system::builtin_env(),
+            system::builtin_read_line(),
+            system::builtin_read_all(),
+            system::builtin_write(),
+            system::builtin_write_line(),
+            system::builtin_exit(),
+            system::builtin_abort(),
+            json::builtin_json_decode(),
+        ] {
+            self.define(builtin.name.into(), Value::BuiltinFn(builtin));
+        }
+        #[cfg(feature = "http")]
+        for builtin in [
+            crate::http::builtin_http_get(),
+            crate::http::builtin_http_post(),
+        ] {
+            self.define(builtin.name.into(), Value::BuiltinFn(builtin));
+        }
+
+        // List primitives (the prelude builds the rest)
+        for builtin in [
+            list::builtin_len(),
+            list::builtin_nth(),
+            list::builtin_push(),
+            list::builtin_range(),
+            list::builtin_range_inclusive(),
+        ] {
+            self.define(builtin.name.into(), Value::BuiltinFn(builtin));
+        }
+
+        // Math and strings, which compiled modules import from the host
+        for builtin in math::builtins().into_iter().chain([
+            string::builtin_concat(),
+            string::builtin_to_string(),
+            string::builtin_contains(),
+            string::builtin_to_upper(),
+        ]) {
+            self.define(builtin.name.into(), Value::BuiltinFn(builtin));
+        }
+
+        // Strings beyond what the host provides
+        for builtin in [
+            string::builtin_split(),
+            string::builtin_parse_int(),
+            string::builtin_escape(),
+        ] {
+            self.define(builtin.name.into(), Value::BuiltinFn(builtin));
+        }
+
+        // Trait methods, dispatched to derived implementations
+        for builtin in derive::builtins() {
+            self.define(builtin.name.into(), Value::BuiltinFn(builtin));
+        }
+
+        // Hashing
+        let hash = hash::builtin_hash();
+        self.define(hash.name.into(), Value::BuiltinFn(hash));
 
         // Arithmetic operators
         let add_id: InternedString = "+".into();
         let sub_id: InternedString = "-".into();
         let mul_id: InternedString = "*".into();
         let div_id: InternedString = "/".into();
+        let rem_id: InternedString = "%".into();
 
         self.define(add_id, Value::BuiltinFn(builtin_add()));
         self.define(sub_id, Value::BuiltinFn(builtin_sub()));
         self.define(mul_id, Value::BuiltinFn(builtin_mul()));
         self.define(div_id, Value::BuiltinFn(builtin_div()));
+        self.define(rem_id, Value::BuiltinFn(builtin_rem()));
 
         // Comparison operators
         let eq_id: InternedString = "==".into();
@@ -216,6 +423,11 @@ impl Env {
         self.define(string_type_id, Value::Type(Type::String));
         self.define(bool_type_id, Value::Type(Type::Bool));
 
+        // Option and Result, with their variants (see `crate::result`)
+        for (name, value) in result::bindings() {
+            self.define(name, value);
+        }
+
         // Field access operator
         let dot_id: InternedString = ".".into();
         self.define(
@@ -226,7 +438,7 @@ impl Env {
 
     /// Pushes a new empty scope onto the stack.
     pub fn push_scope(&mut self) {
-        Rc::make_mut(&mut self.scopes).push(Scope::new());
+        Arc::make_mut(&mut self.scopes).push(Scope::new());
     }
 
     /// Pops the top scope from the stack.
@@ -236,30 +448,83 @@ impl Env {
     /// Panics if there is only one scope (the global scope).
     pub fn pop_scope(&mut self) {
         assert!(self.scopes.len() > 1, "Cannot pop the global scope");
-        Rc::make_mut(&mut self.scopes).pop();
+        Arc::make_mut(&mut self.scopes).pop();
     }
 
     /// Defines a binding in the current (top) scope.
     pub fn define(&mut self, name: InternedString, value: Value) {
-        if let Some(scope) = Rc::make_mut(&mut self.scopes).last_mut() {
+        if let Some(scope) = Arc::make_mut(&mut self.scopes).last_mut() {
             scope.define(name, value);
         }
     }
 
-    /// Looks up a binding, searching from the top scope to the bottom.
+    /// Looks up a binding, searching from the top scope to the bottom, then the prelude.
     pub fn get(&self, name: InternedString) -> Option<&Value> {
+        self.get_scoped(name).or_else(|| self.prelude.get(name))
+    }
+
+    /// Looks up a binding in the scopes only, ignoring the prelude.
+    ///
+    /// Identifier evaluation uses this so compiler definitions can shadow
+    /// prelude functions.
+    ///
+    /// A name declared uninitialized hides the bindings below it.
+    pub fn get_scoped(&self, name: InternedString) -> Option<&Value> {
         for scope in self.scopes.iter().rev() {
             if let Some(value) = scope.get(name) {
                 return Some(value);
             }
+            if scope.uninitialized.contains(&name) {
+                return None;
+            }
         }
         None
     }
 
+    /// Declares `name` in the current scope without a value, for a `let rec`
+    /// binding whose value is being evaluated. Until [`Env::define`] gives
+    /// it one, looking the name up finds nothing, not even an outer binding,
+    /// and [`Env::is_uninitialized`] is true.
+    pub fn declare_uninitialized(&mut self, name: InternedString) {
+        if let Some(scope) = Arc::make_mut(&mut self.scopes).last_mut() {
+            scope.bindings.shift_remove(&name);
+            scope.uninitialized.insert(name);
+        }
+    }
+
+    /// Returns true if the innermost declaration of `name` is uninitialized.
+    pub fn is_uninitialized(&self, name: InternedString) -> bool {
+        for scope in self.scopes.iter().rev() {
+            if scope.contains(name) {
+                return false;
+            }
+            if scope.uninitialized.contains(&name) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Looks up a binding in the prelude only.
+    pub fn get_prelude(&self, name: InternedString) -> Option<&Value> {
+        self.prelude.get(name)
+    }
+
+    /// Shares `other`'s prelude with this environment.
+    ///
+    /// Function calls use this so prelude functions (whose captured
+    /// environments predate the prelude itself) can call each other.
+    pub fn inherit_prelude(&mut self, other: &Env) {
+        self.prelude = other.prelude.clone();
+    }
+
     /// Looks up a mutable binding, searching from the top scope to the bottom.
     /// Used by the `=` operator to update values.
     pub fn get_mut(&mut self, name: InternedString) -> Option<&mut Value> {
-        for scope in Rc::make_mut(&mut self.scopes).iter_mut().rev() {
+        for scope in Arc::make_mut(&mut self.scopes).iter_mut().rev() {
+            if scope.uninitialized.contains(&name) {
+                return None;
+            }
             if let Some(value) = scope.get_mut(name) {
                 return Some(value);
             }
@@ -267,6 +532,14 @@ impl Env {
         None
     }
 
+    /// Removes the innermost binding of `name`, returning its value.
+    pub fn remove(&mut self, name: InternedString) -> Option<Value> {
+        Arc::make_mut(&mut self.scopes)
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.bindings.shift_remove(&name))
+    }
+
     /// Returns true if any scope contains a binding for the given name.
     pub fn contains(&self, name: InternedString) -> bool {
         self.scopes.iter().any(|scope| scope.contains(name))
@@ -279,12 +552,13 @@ impl Env {
 
     /// Defines a binding in the global (bottom) scope.
     pub fn define_global(&mut self, name: InternedString, value: Value) {
-        if let Some(scope) = Rc::make_mut(&mut self.scopes).first_mut() {
+        if let Some(scope) = Arc::make_mut(&mut self.scopes).first_mut() {
             scope.define(name, value);
         }
     }
 
-    /// Iterates over all bindings in all scopes, from top to bottom.
+    /// Iterates over all bindings in all scopes, from top to bottom, then the prelude.
+    /// Within a scope, bindings come in the order they were first defined.
     ///
     /// If a name is shadowed, only the innermost binding is yielded.
     /// This is useful for building a type environment from the current runtime environment.
@@ -293,7 +567,7 @@ impl Env {
         let mut seen = HashSet::new();
         let mut bindings = Vec::new();
 
-        for scope in self.scopes.iter().rev() {
+        for scope in self.scopes.iter().rev().chain([&*self.prelude]) {
             for (name, value) in scope.bindings.iter() {
                 if seen.insert(*name) {
                     bindings.push((*name, value));
@@ -319,6 +593,22 @@ mod tests {
         assert_eq!(env.get(name), Some(&Value::Integer(42)));
     }
 
+    #[test]
+    fn uninitialized_names_hide_outer_bindings() {
+        let name: InternedString = "x".into();
+        let mut env = Env::new();
+
+        env.define(name, Value::Integer(1));
+        env.push_scope();
+        env.declare_uninitialized(name);
+        assert_eq!(env.get(name), None);
+        assert!(env.is_uninitialized(name));
+
+        env.define(name, Value::Integer(2));
+        assert_eq!(env.get(name), Some(&Value::Integer(2)));
+        assert!(!env.is_uninitialized(name));
+    }
+
     #[test]
     fn shadowing_in_nested_scope() {
         let name: InternedString = "x".into();
@@ -427,6 +717,18 @@ mod tests {
         assert_eq!(bindings.get(&y), Some(&&Value::Integer(2)));
     }
 
+    #[test]
+    fn env_iter_in_definition_order() {
+        let mut env = Env::new();
+        let names: Vec<InternedString> = ["z", "a", "m"].map(Into::into).into();
+        for (i, name) in names.iter().enumerate() {
+            env.define(*name, Value::Integer(i as i64));
+        }
+
+        let order: Vec<_> = env.iter().map(|(name, _)| name).take(3).collect();
+        assert_eq!(order, names);
+    }
+
     #[test]
     fn env_iter_with_shadowing() {
         let mut env = Env::new();
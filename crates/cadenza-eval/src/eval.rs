@@ -11,15 +11,26 @@
 use crate::{
     compiler::Compiler,
     context::{Eval, EvalContext},
+    decimal::Decimal,
     diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
     env::Env,
-    interner::InternedString,
-    value::{BuiltinFn, Type, Value},
+    interner::{
+        InternedString, Storage, Strings, parse_big_integer, parse_integer, small_integer,
+        without_underscores,
+    },
+    num,
+    special_form::BuiltinSpecialForm,
+    stack, suggest,
+    value::{BuiltinFn, Type, UserFunction, Value},
 };
 use cadenza_syntax::{
     ast::{Apply, Attr, Expr, Ident, Literal, LiteralValue, Root, Synthetic},
     span::Span,
 };
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
 /// Evaluates a complete source file (Root node).
 ///
@@ -29,32 +40,209 @@ use cadenza_syntax::{
 /// primary purpose.
 ///
 /// This function implements function hoisting by performing two passes:
-/// 1. First pass: scan for function definitions and register them in the compiler
+/// 1. First pass: scan for unit, constant and function definitions and
+///    register them in the compiler
 /// 2. Second pass: evaluate all expressions normally
 ///
 /// This continues evaluation even when expressions fail, recording
-/// errors in the compiler. On error, `Value::Nil` is used as the result for
-/// that expression. Check `compiler.has_errors()` after calling to see if
-/// any errors occurred.
+/// errors in the compiler. On error, the poisoned [`Value::Error`] is used as
+/// the result for that expression, and any variable the expression was
+/// defining is bound to it, so later uses unwind silently instead of
+/// reporting the same failure again. Check `compiler.has_errors()` after
+/// calling to see if any errors occurred. Only `exit` and `abort` stop
+/// evaluation early (see [`Compiler::exit_status`]).
 pub fn eval(root: &Root, env: &mut Env, compiler: &mut Compiler) -> Vec<Value> {
-    // First pass: hoist function definitions
+    // First pass: hoist unit, constant and function definitions
+    hoist_units(root, env, compiler);
+    hoist_constants(root, env, compiler);
     hoist_functions(root, env, compiler);
 
     // Second pass: evaluate all expressions
+    eval_items(root.items(), env, compiler)
+}
+
+/// Evaluates the top-level expressions `items` in order, as the second pass
+/// of [`eval`] does, without hoisting.
+pub(crate) fn eval_items(
+    items: impl IntoIterator<Item = Expr>,
+    env: &mut Env,
+    compiler: &mut Compiler,
+) -> Vec<Value> {
     let mut ctx = EvalContext::new(env, compiler);
     let mut results = Vec::new();
-    for expr in root.items() {
+    for expr in items {
         match expr.eval(&mut ctx) {
             Ok(value) => results.push(value),
             Err(diagnostic) => {
                 ctx.compiler.record_diagnostic(*diagnostic);
-                results.push(Value::Nil);
+                results.push(Value::Error);
+            }
+        }
+        if ctx.compiler.exit_status().is_some() {
+            ctx.compiler.executor_mut().cancel();
+            return results;
+        }
+
+        // Attributes only apply to the definition immediately following them
+        if !is_attribute(&expr) {
+            for attr in ctx.compiler.take_attributes() {
+                let warning = Diagnostic::syntax(format!(
+                    "attribute @{} is not followed by a definition",
+                    attr.name
+                ))
+                .with_span(expr.span())
+                .set_level(crate::diagnostic::DiagnosticLevel::Warning);
+                ctx.compiler.record_diagnostic(*warning);
             }
         }
     }
+
+    // Join any tasks that were spawned but never awaited
+    for diagnostic in crate::task::run_until_idle(ctx.compiler) {
+        ctx.compiler.record_diagnostic(*diagnostic);
+    }
+
     results
 }
 
+/// Returns true if the expression is an attribute (`@name ...`).
+pub(crate) fn is_attribute(expr: &Expr) -> bool {
+    match expr {
+        Expr::Apply(apply) => {
+            matches!(apply.callee(), Some(Expr::Op(op)) if op.syntax().text() == "@")
+        }
+        _ => false,
+    }
+}
+
+/// First pass: register the units defined with `measure` (hoisting).
+///
+/// Units are hoisted ahead of functions, so the IR generated for a function
+/// can construct quantities of units defined anywhere in the file. Errors are
+/// left for the second pass to report.
+fn hoist_units(root: &Root, env: &mut Env, compiler: &mut Compiler) {
+    let measure: InternedString = "measure".into();
+    let Some(Value::SpecialForm(form)) = env.get(measure).cloned() else {
+        return;
+    };
+    let mut ctx = EvalContext::new(env, compiler);
+    for expr in root.items() {
+        let Expr::Apply(apply) = expr else {
+            continue;
+        };
+        let args = match apply.callee() {
+            // measure name
+            Some(callee) if extract_identifier(&callee) == Some(measure) => apply.all_arguments(),
+            // measure name = base scale
+            Some(Expr::Op(op)) if op.syntax().text() == "=" => {
+                let args = apply.all_arguments();
+                let [Expr::Apply(lhs), rhs] = &args[..] else {
+                    continue;
+                };
+                if lhs.callee().and_then(|callee| extract_identifier(&callee)) != Some(measure) {
+                    continue;
+                }
+                let mut args = lhs.all_arguments();
+                args.push(rhs.clone());
+                args
+            }
+            _ => continue,
+        };
+        let _ = form.eval(&args, &mut ctx);
+    }
+}
+
+/// First pass: generate the constants defined with top-level `let`s as IR
+/// globals (hoisting).
+///
+/// A constant is bound once at the top level, never assigned, and initialized
+/// with literals, arithmetic, units and earlier constants, so evaluating it
+/// early is safe. The values only live in a scope dropped afterwards: the
+/// second pass still defines the variables in order.
+fn hoist_constants(root: &Root, env: &mut Env, compiler: &mut Compiler) {
+    if !compiler.is_ir_enabled() {
+        return;
+    }
+
+    let mut lets = Vec::new();
+    let mut bindings: HashMap<InternedString, usize> = HashMap::new();
+    for expr in root.items() {
+        let Expr::Apply(apply) = expr else {
+            continue;
+        };
+        if !matches!(apply.callee(), Some(Expr::Op(op)) if op.syntax().text() == "=") {
+            continue;
+        }
+        let args = apply.all_arguments();
+        let [lhs, rhs] = &args[..] else {
+            continue;
+        };
+        let name = match lhs {
+            // name = value
+            Expr::Ident(ident) => ident.syntax().text().interned(),
+            // let name = value
+            Expr::Apply(lhs) => {
+                let is_let = lhs
+                    .callee()
+                    .and_then(|callee| extract_identifier(&callee))
+                    .is_some_and(|callee| &*callee == "let");
+                match &lhs.all_arguments()[..] {
+                    [Expr::Ident(ident)] if is_let => {
+                        let name = ident.syntax().text().interned();
+                        lets.push((name, rhs.clone()));
+                        name
+                    }
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        *bindings.entry(name).or_default() += 1;
+    }
+
+    env.push_scope();
+    let mut ctx = EvalContext::new(env, compiler);
+    let mut constants = HashSet::new();
+    for (name, rhs) in lets {
+        if bindings[&name] != 1 || !is_constant(&rhs, &constants, ctx.compiler) {
+            continue;
+        }
+        if let Ok(value) = rhs.eval(&mut ctx)
+            && ctx.compiler.generate_ir_global(name, &value)
+        {
+            ctx.env.define(name, value);
+            constants.insert(name);
+        }
+    }
+    env.pop_scope();
+}
+
+/// Returns true if `expr` is made of literals, arithmetic, units and
+/// `constants`.
+fn is_constant(expr: &Expr, constants: &HashSet<InternedString>, compiler: &Compiler) -> bool {
+    match expr {
+        Expr::Literal(_) => true,
+        Expr::Ident(ident) => constants.contains(&ident.syntax().text().interned()),
+        Expr::Apply(apply) => {
+            let is_pure = match apply.callee() {
+                Some(Expr::Op(op)) => matches!(
+                    op.syntax().text().to_string().as_str(),
+                    "+" | "-" | "*" | "/" | "%"
+                ),
+                Some(callee) => extract_identifier(&callee)
+                    .is_some_and(|name| compiler.units().get(name).is_some()),
+                None => false,
+            };
+            is_pure
+                && apply
+                    .all_arguments()
+                    .iter()
+                    .all(|arg| is_constant(arg, constants, compiler))
+        }
+        _ => false,
+    }
+}
+
 /// First pass: scan for function definitions and register them (hoisting).
 ///
 /// This scans top-level expressions looking for function definitions of the form
@@ -63,6 +251,16 @@ pub fn eval(root: &Root, env: &mut Env, compiler: &mut Compiler) -> Vec<Value> {
 /// we delegate to that macro.
 #[allow(clippy::collapsible_if)]
 fn hoist_functions(root: &Root, env: &mut Env, compiler: &mut Compiler) {
+    // Attributes are applied during the second pass; keep hoisting from consuming
+    // any that are still pending (e.g. from a previous REPL line)
+    let pending = compiler.take_attributes();
+
+    // Declare every function before generating the IR of any, so mutually
+    // recursive functions can call the ones defined after them
+    for name in root.items().filter_map(|expr| function_name(&expr)) {
+        compiler.declare_ir_function(name);
+    }
+
     let mut ctx = EvalContext::new(env, compiler);
 
     for expr in root.items() {
@@ -127,6 +325,31 @@ fn hoist_functions(root: &Root, env: &mut Env, compiler: &mut Compiler) {
             }
         }
     }
+
+    for attr in pending {
+        ctx.compiler.push_attribute(attr);
+    }
+}
+
+/// Returns the name `expr` defines if it's a function definition,
+/// `fn name params... = body`.
+fn function_name(expr: &Expr) -> Option<InternedString> {
+    let Expr::Apply(apply) = expr else {
+        return None;
+    };
+    if &*extract_identifier(&apply.callee()?)? != "=" {
+        return None;
+    }
+    let Some(Expr::Apply(lhs)) = apply.all_arguments().into_iter().next() else {
+        return None;
+    };
+    if &*extract_identifier(&lhs.callee()?)? != "fn" {
+        return None;
+    }
+    match lhs.all_arguments().first()? {
+        Expr::Ident(name) => Some(name.syntax().text().interned()),
+        _ => None,
+    }
 }
 
 // =============================================================================
@@ -135,57 +358,156 @@ fn hoist_functions(root: &Root, env: &mut Env, compiler: &mut Compiler) {
 
 impl Eval for Expr {
     fn eval(&self, ctx: &mut EvalContext<'_>) -> Result<Value> {
+        // A diagnostic without a span takes the span of the innermost
+        // expression it passes through
+        let result = eval_expr(self, ctx).map_err(|diagnostic| diagnostic.or_span(self.span()));
+        trace(self, &result, ctx);
+        result
+    }
+}
+
+fn eval_expr(expr: &Expr, ctx: &mut EvalContext<'_>) -> Result<Value> {
+    step(expr, ctx)?;
+    match expr {
+        Expr::Literal(lit) => lit.eval(ctx),
+        Expr::Ident(ident) => ident.eval(ctx),
+        Expr::Apply(apply) => apply.eval(ctx),
+        Expr::Op(op) => {
+            // Operators as values (for higher-order usage)
+            // Use SyntaxText directly without allocating a String
+            let text = op.syntax().text();
+            let id: InternedString = text.interned();
+            Ok(Value::Symbol(id))
+        }
+        Expr::Synthetic(syn) => syn.eval(ctx),
+        Expr::Error(_) => Err(Diagnostic::syntax("encountered error node in AST")),
+    }
+}
+
+/// Counts the evaluation of `expr` against the fuel budget and coverage.
+fn step(expr: &Expr, ctx: &mut EvalContext<'_>) -> Result<()> {
+    ctx.compiler.consume_fuel()?;
+    if let Some(coverage) = ctx.compiler.coverage_mut() {
+        coverage.record(expr);
+    }
+    Ok(())
+}
+
+/// The result of evaluating an expression in tail position.
+///
+/// A call the expression ends with is returned unmade, so that a function
+/// whose body ends with it can make it in place of its own call instead of
+/// nested inside it (see [`apply_value`]).
+pub(crate) enum Tail {
+    /// The expression's value.
+    Value(Value),
+    /// A call still to be made, with the span of the expression making it.
+    Call {
+        callee: Value,
+        args: Vec<Value>,
+        span: Span,
+    },
+}
+
+impl Tail {
+    /// Makes the call, if there is one, and returns the expression's value.
+    pub(crate) fn finish(self, ctx: &mut EvalContext<'_>) -> Result<Value> {
         match self {
-            Expr::Literal(lit) => lit.eval(ctx),
-            Expr::Ident(ident) => ident.eval(ctx),
-            Expr::Apply(apply) => apply.eval(ctx),
-            Expr::Op(op) => {
-                // Operators as values (for higher-order usage)
-                // Use SyntaxText directly without allocating a String
-                let text = op.syntax().text();
-                let id: InternedString = text.interned();
-                Ok(Value::Symbol(id))
+            Tail::Value(value) => Ok(value),
+            Tail::Call { callee, args, span } => {
+                apply_value(callee, args, ctx).map_err(|diagnostic| diagnostic.or_span(span))
             }
-            Expr::Synthetic(syn) => syn.eval(ctx),
-            Expr::Error(_) => Err(Diagnostic::syntax("encountered error node in AST")),
         }
     }
 }
 
+/// Evaluates an expression in tail position, leaving a call it ends with
+/// unmade.
+///
+/// Calls end `if` branches, block bodies and `match` arms as well as plain
+/// applications.
+pub(crate) fn eval_tail(expr: &Expr, ctx: &mut EvalContext<'_>) -> Result<Tail> {
+    let Expr::Apply(apply) = expr else {
+        return expr.eval(ctx).map(Tail::Value);
+    };
+    let tail = step(expr, ctx)
+        .and_then(|()| eval_call(apply, ctx))
+        .map_err(|diagnostic| diagnostic.or_span(expr.span()));
+    if ctx.compiler.tracer().is_none() {
+        return tail;
+    }
+    // While tracing, only calls of user functions are left unmade, so the
+    // value of any other call is logged with the expression making it
+    let result = match tail {
+        Ok(
+            tail @ Tail::Call {
+                callee: Value::UserFunction(_),
+                ..
+            },
+        ) => return Ok(tail),
+        Ok(tail) => tail.finish(ctx),
+        Err(diagnostic) => Err(diagnostic),
+    };
+    trace(expr, &result, ctx);
+    result.map(Tail::Value)
+}
+
+/// Logs the evaluation of `expr` to `result`, if tracing is enabled.
+fn trace(expr: &Expr, result: &Result<Value>, ctx: &mut EvalContext<'_>) {
+    let depth = ctx.env.depth();
+    if let Some(tracer) = ctx.compiler.tracer_mut() {
+        tracer.record(expr, result, depth);
+    }
+}
+
 impl Eval for Literal {
     fn eval(&self, _ctx: &mut EvalContext<'_>) -> Result<Value> {
-        let value = self
-            .value()
-            .ok_or_else(|| Diagnostic::syntax("missing literal value"))?;
-
-        match value {
-            LiteralValue::Integer(int_val) => {
-                let text = int_val.syntax().text();
-                // Remove underscores for parsing
-                let clean = text.as_str().replace('_', "");
-                let n: i64 = clean.parse().map_err(|_| {
-                    Diagnostic::syntax(format!("invalid integer: {}", text.as_str()))
-                })?;
-                Ok(Value::Integer(n))
-            }
-            LiteralValue::Float(float_val) => {
-                let text = float_val.syntax().text();
-                let clean = text.as_str().replace('_', "");
-                let n: f64 = clean
-                    .parse()
-                    .map_err(|_| Diagnostic::syntax(format!("invalid float: {}", text.as_str())))?;
-                Ok(Value::Float(n))
-            }
-            LiteralValue::String(str_val) => {
-                let text = str_val.syntax().text().to_string();
-                Ok(Value::String(text))
+        literal_value(self)
+    }
+}
+
+/// Returns the value of a literal, which doesn't depend on the environment.
+pub(crate) fn literal_value(literal: &Literal) -> Result<Value> {
+    let value = literal
+        .value()
+        .ok_or_else(|| Diagnostic::syntax("missing literal value"))?;
+
+    match value {
+        LiteralValue::Integer(int_val) => {
+            let text = int_val.syntax().text();
+            if let Some(value) = small_integer(text.as_str()) {
+                return Ok(value.clone());
             }
-            LiteralValue::StringWithEscape(str_val) => {
-                let text = str_val.syntax().text().to_string();
-                // TODO: Process escape sequences
-                Ok(Value::String(text))
+            if let Ok(n) = parse_integer(text.as_str()) {
+                return Ok(Value::Integer(n));
             }
+            // Too large for an i64, or not an integer at all
+            parse_big_integer(text.as_str())
+                .map(Value::BigInt)
+                .ok_or_else(|| Diagnostic::syntax(format!("invalid integer: {}", text.as_str())))
+        }
+        LiteralValue::Float(float_val) => {
+            let text = float_val.syntax().text();
+            let n: f64 = without_underscores(text.as_str())
+                .parse()
+                .map_err(|_| Diagnostic::syntax(format!("invalid float: {}", text.as_str())))?;
+            Ok(Value::Float(n))
+        }
+        LiteralValue::Decimal(decimal_val) => {
+            let digits = decimal_val.digits();
+            Decimal::parse(&digits)
+                .map(Value::Decimal)
+                .ok_or_else(|| Diagnostic::syntax(format!("invalid decimal: {digits}d")))
         }
+        LiteralValue::String(str_val) => {
+            // The interner keeps the text alive, so the value borrows it
+            let text = Strings::resolve(str_val.syntax().text().interned().index());
+            Ok(Value::String(Cow::Borrowed(text)))
+        }
+        LiteralValue::StringWithEscape(str_val) => match str_val.unescaped() {
+            Ok(text) => Ok(Value::String(text.into())),
+            Err(error) => Err(Diagnostic::syntax(error.message).with_span(error.span)),
+        },
     }
 }
 
@@ -209,22 +531,33 @@ pub fn eval_ident_no_auto_apply(ident: &Ident, ctx: &mut EvalContext<'_>) -> Res
     let id: InternedString = text.interned();
 
     // First check the local environment
-    if let Some(value) = ctx.env.get(id) {
+    if let Some(value) = ctx.env.get_scoped(id) {
+        if matches!(value, Value::Error) {
+            return Err(Diagnostic::poisoned(id).with_span(ident.span()));
+        }
         return Ok(value.clone());
     }
+    if ctx.env.is_uninitialized(id) {
+        return Err(Diagnostic::uninitialized(id).with_span(ident.span()));
+    }
 
     // Then check compiler definitions
     if let Some(value) = ctx.compiler.get_var(id) {
         return Ok(value.clone());
     }
 
+    // Then fall back to the prelude
+    if let Some(value) = ctx.env.get_prelude(id) {
+        return Ok(value.clone());
+    }
+
     // Check if it's a registered unit name
     // If so, return a unit constructor value
     if let Some(unit) = ctx.compiler.units().get(id) {
         return Ok(Value::UnitConstructor(unit.clone()));
     }
 
-    Err(Diagnostic::undefined_variable(id).with_span(ident.span()))
+    Err(suggest::undefined_variable(id, ctx).with_span(ident.span()))
 }
 
 impl Eval for Ident {
@@ -236,58 +569,96 @@ impl Eval for Ident {
 
 impl Eval for Apply {
     fn eval(&self, ctx: &mut EvalContext<'_>) -> Result<Value> {
-        // Get the callee (innermost identifier in nested applications)
-        let callee_expr = self
-            .callee()
-            .ok_or_else(|| Diagnostic::syntax("missing callee in application"))?;
-
-        // Try to extract an identifier/operator name from the callee.
-        // If successful, check if it names a macro before evaluating.
-        if let Some(id) = extract_identifier(&callee_expr) {
-            // Check for macro in compiler
-            if let Some(macro_value) = ctx.compiler.get_macro(id) {
-                return apply_macro(macro_value.clone(), self, ctx);
-            }
+        eval_call(self, ctx)?.finish(ctx)
+    }
+}
 
-            // Check for macro or special form in environment
-            if let Some(Value::BuiltinMacro(_) | Value::SpecialForm(_)) = ctx.env.get(id) {
-                let macro_value = ctx.env.get(id).unwrap().clone();
-                return apply_macro(macro_value, self, ctx);
-            }
+/// Evaluates an application, leaving the call itself unmade unless the
+/// callee is a macro.
+fn eval_call(apply: &Apply, ctx: &mut EvalContext<'_>) -> Result<Tail> {
+    // Get the callee (innermost identifier in nested applications)
+    let callee_expr = apply
+        .callee()
+        .ok_or_else(|| Diagnostic::syntax("missing callee in application"))?;
+
+    // Try to extract an identifier/operator name from the callee.
+    // If successful, check if it names a macro before evaluating.
+    if let Some(id) = extract_identifier(&callee_expr) {
+        // Check for macro in compiler
+        if let Some(macro_value) = ctx.compiler.get_macro(id) {
+            return apply_macro(macro_value.clone(), apply, ctx).map(Tail::Value);
         }
 
-        // Not a macro call - evaluate the callee
-        // For identifiers and operators, we must NOT auto-apply since this is an application context
-        let callee = match &callee_expr {
-            Expr::Ident(ident) => eval_ident_no_auto_apply(ident, ctx)?,
-            Expr::Op(op) => {
-                // Look up operator in environment
-                let text = op.syntax().text();
-                let id: InternedString = text.interned();
-                let range = op.syntax().text_range();
-                let span = Span::new(range.start().into(), range.end().into());
-                ctx.env
-                    .get(id)
-                    .cloned()
-                    .ok_or_else(|| Diagnostic::undefined_variable(id).with_span(span))?
+        // Check for macro or special form in environment
+        if let Some(Value::BuiltinMacro(_) | Value::SpecialForm(_)) = ctx.env.get(id) {
+            let macro_value = ctx.env.get(id).unwrap().clone();
+            if let Value::SpecialForm(form) = macro_value
+                && let Some(eval_tail) = tail_form(form)
+            {
+                return eval_tail(&apply.all_arguments(), ctx);
             }
-            _ => callee_expr.eval(ctx)?,
-        };
-
-        // Get all arguments (flattened from nested Apply nodes)
-        let all_arg_exprs = self.all_arguments();
+            return apply_macro(macro_value, apply, ctx).map(Tail::Value);
+        }
+    }
 
-        // Evaluate all arguments
-        let mut args = Vec::new();
-        for arg_expr in all_arg_exprs {
-            let value = arg_expr.eval(ctx)?;
-            args.push(value);
+    // Not a macro call - evaluate the callee
+    // For identifiers and operators, we must NOT auto-apply since this is an application context
+    let callee = match &callee_expr {
+        Expr::Ident(ident) => eval_ident_no_auto_apply(ident, ctx)?,
+        Expr::Op(op) => {
+            // Look up operator in environment
+            let text = op.syntax().text();
+            let id: InternedString = text.interned();
+            let range = op.syntax().text_range();
+            let span = Span::new(range.start().into(), range.end().into());
+            ctx.env
+                .get(id)
+                .cloned()
+                .ok_or_else(|| Diagnostic::undefined_variable(id).with_span(span))?
+        }
+        // Synthetic functions, like `__escape__`, are looked up by name
+        Expr::Synthetic(syn) if ctx.env.get(syn.identifier().into()).is_some() => {
+            ctx.env.get(syn.identifier().into()).unwrap().clone()
         }
+        _ => callee_expr.eval(ctx)?,
+    };
 
-        apply_value(callee, args, ctx)
+    // Get all arguments (flattened from nested Apply nodes)
+    let all_arg_exprs = apply.all_arguments();
+
+    // Evaluate all arguments
+    let mut args = Vec::new();
+    for arg_expr in all_arg_exprs {
+        let value = arg_expr.eval(ctx)?;
+        args.push(value);
     }
+
+    Ok(Tail::Call {
+        callee,
+        args,
+        span: apply.span(),
+    })
+}
+
+/// Returns the tail position evaluation of a special form that ends with
+/// one of its arguments, if `form` is one.
+fn tail_form(form: &BuiltinSpecialForm) -> Option<TailForm> {
+    use crate::special_form::{block_form, do_form, if_form, match_form};
+    let forms: [(_, TailForm); 4] = [
+        (if_form::get(), if_form::eval_tail),
+        (block_form::get(), block_form::eval_tail),
+        (do_form::get(), do_form::eval_tail),
+        (match_form::get(), match_form::eval_tail),
+    ];
+    forms
+        .into_iter()
+        .find(|(candidate, _)| std::ptr::eq(*candidate, form))
+        .map(|(_, eval_tail)| eval_tail)
 }
 
+/// Evaluates a special form's arguments in tail position.
+type TailForm = fn(&[Expr], &mut EvalContext<'_>) -> Result<Tail>;
+
 /// Extracts an identifier from an expression if it is an Ident or Op node.
 /// Returns None for other expression types.
 pub fn extract_identifier(expr: &Expr) -> Option<InternedString> {
@@ -316,6 +687,7 @@ pub fn extract_identifier(expr: &Expr) -> Option<InternedString> {
 fn apply_macro(macro_value: Value, apply: &Apply, ctx: &mut EvalContext<'_>) -> Result<Value> {
     match macro_value {
         Value::BuiltinMacro(builtin) => {
+            let _span = tracing::debug_span!("expand").entered();
             // Collect unevaluated argument expressions (use all_arguments to get flattened args)
             let arg_exprs: Vec<Expr> = apply.all_arguments();
 
@@ -356,6 +728,8 @@ pub fn apply_value(callee: Value, args: Vec<Value>, ctx: &mut EvalContext<'_>) -
 
             let value = match &args[0] {
                 Value::Integer(n) => *n as f64,
+                Value::BigInt(n) => n.to_f64(),
+                Value::Decimal(d) => d.to_f64(),
                 Value::Float(f) => *f,
                 _ => {
                     return Err(Diagnostic::type_error(
@@ -449,60 +823,130 @@ pub fn apply_value(callee: Value, args: Vec<Value>, ctx: &mut EvalContext<'_>) -
                 ))),
             }
         }
-        Value::UserFunction(user_fn) => {
-            // Check arity
-            if args.len() != user_fn.params.len() {
-                return Err(Diagnostic::arity(user_fn.params.len(), args.len()));
+        Value::VariantConstructor { ty, variant } => {
+            // Variant constructors take the values of the variant's fields
+            let field_types = ty.variant(variant).unwrap_or_default();
+            if args.len() != field_types.len() {
+                return Err(Diagnostic::arity(field_types.len(), args.len()));
             }
-
-            // Create a new environment extending the captured environment
-            let mut call_env = user_fn.captured_env.clone();
-            call_env.push_scope();
-
-            // Bind parameters to arguments
-            for (param, arg) in user_fn.params.iter().zip(args.iter()) {
-                call_env.define(*param, arg.clone());
+            for (expected, value) in field_types.iter().zip(&args) {
+                let actual = value.type_of();
+                if !types_compatible(expected, &actual) {
+                    return Err(Diagnostic::type_error(expected.clone(), actual));
+                }
             }
-
-            // Evaluate the body in the new environment
-            let mut call_ctx = EvalContext::new(&mut call_env, ctx.compiler);
-            let result = user_fn.body.eval(&mut call_ctx)?;
-
-            Ok(result)
+            Ok(Value::Variant {
+                ty,
+                variant,
+                fields: args,
+            })
         }
+        Value::UserFunction(user_fn) => apply_user_function(user_fn, args, ctx),
         _ => Err(Diagnostic::not_callable(callee.type_of())),
     }
 }
 
-/// Helper function to create a Value from a numeric result and optional dimension.
+/// Applies a user function to arguments.
 ///
-/// Returns Quantity if dimension is Some, otherwise returns Float.
-fn create_numeric_value(
-    value: f64,
-    dimension: Option<crate::unit::DerivedDimension>,
-    unit: Option<crate::unit::Unit>,
-) -> Value {
-    match dimension {
-        Some(dim) if !dim.is_dimensionless() => {
-            // Create a quantity with the dimension
-            // For now, use a synthesized unit name based on the dimension
-            let unit = unit.unwrap_or_else(|| {
-                use crate::unit::Unit;
-                // Create a temporary unit for the dimension
-                let unit_name: InternedString = format!("{}", dim).as_str().into();
-                Unit::base(unit_name)
-            });
-            Value::Quantity {
-                value,
-                unit,
-                dimension: dim,
+/// Calls nest on the Rust stack, so each is checked against the recursion
+/// limit and moved to a fresh stack segment when the current one runs low
+/// (see [`stack`]). A call to a user function that the body ends with is
+/// made in place of this one instead, so tail recursion runs in constant
+/// space and doesn't count toward the limit.
+fn apply_user_function(
+    user_fn: UserFunction,
+    args: Vec<Value>,
+    ctx: &mut EvalContext<'_>,
+) -> Result<Value> {
+    ctx.compiler.enter_call()?;
+    let result = stack::grow(ctx, move |ctx| call_user_function(user_fn, args, ctx));
+    ctx.compiler.exit_call();
+    result
+}
+
+/// Evaluates the body of `user_fn`, and of each user function it ends by
+/// calling in turn.
+///
+/// A value passed on with `?` in the body is returned (see
+/// [`Diagnostic::into_propagated`]).
+fn call_user_function(
+    mut user_fn: UserFunction,
+    mut args: Vec<Value>,
+    ctx: &mut EvalContext<'_>,
+) -> Result<Value> {
+    // The span of the tail call being made, if this isn't the first call
+    let mut span = None;
+    loop {
+        // Memoized functions reuse results for previously seen arguments
+        let id = crate::memo::FunctionId::of(&user_fn);
+        let memoized = ctx.compiler.memo().is_memoized(&id);
+        let call_env = if args.len() != user_fn.params.len() {
+            Err(Diagnostic::arity(user_fn.params.len(), args.len()))
+        } else if memoized && let Some(result) = ctx.compiler.memo_mut().get(&id, &args) {
+            return Ok(result);
+        } else {
+            bind_arguments(&user_fn, &args, ctx.env)
+        };
+        let mut call_env = call_env.map_err(|diagnostic| match span {
+            Some(span) => diagnostic.or_span(span),
+            None => diagnostic,
+        })?;
+
+        // Evaluate the body in the new environment
+        let mut call_ctx = EvalContext::new(&mut call_env, ctx.compiler);
+        let tail = match eval_tail(&user_fn.body, &mut call_ctx) {
+            Ok(tail) => tail,
+            Err(diagnostic) => return diagnostic.into_propagated(),
+        };
+        match tail {
+            // A memoized function has to see its result to cache it
+            Tail::Call {
+                callee: Value::UserFunction(next),
+                args: next_args,
+                span: next_span,
+            } if !memoized => {
+                user_fn = next;
+                args = next_args;
+                span = Some(next_span);
+            }
+            tail => {
+                let result = tail
+                    .finish(&mut call_ctx)
+                    .or_else(Diagnostic::into_propagated)?;
+                if memoized {
+                    ctx.compiler.memo_mut().insert(&id, args, result.clone());
+                }
+                return Ok(result);
             }
         }
-        _ => {
-            // Dimensionless or no dimension - return plain float
-            Value::Float(value)
+    }
+}
+
+/// Returns the environment to evaluate the body of `user_fn` in: the one it
+/// captured, extended with its parameters bound to `args`.
+///
+/// A closure bound with `let rec` captured its own name uninitialized, so
+/// the name is bound to the closure here, for the duration of the call.
+fn bind_arguments(user_fn: &UserFunction, args: &[Value], env: &Env) -> Result<Env> {
+    let mut call_env = user_fn.captured_env.clone();
+    call_env.inherit_prelude(env);
+    call_env.push_scope();
+    if call_env.is_uninitialized(user_fn.name) {
+        call_env.define(user_fn.name, Value::UserFunction(user_fn.clone()));
+    }
+
+    // Bind parameters to arguments, destructuring the patterns
+    for (param, arg) in user_fn.params.iter().zip(args) {
+        match user_fn.patterns.iter().find(|(name, _)| name == param) {
+            Some((_, pattern)) => {
+                for (name, value) in crate::pattern::destructure(pattern, arg)? {
+                    call_env.define(name, value);
+                }
+            }
+            None => call_env.define(*param, arg.clone()),
         }
     }
+    Ok(call_env)
 }
 
 /// Helper function to check if two types are compatible.
@@ -513,8 +957,10 @@ fn create_numeric_value(
 ///
 /// TODO: Replace with proper type unification when the type system is more complete.
 fn types_compatible(expected: &Type, actual: &Type) -> bool {
-    // For struct types, enforce nominal typing - names must match
-    if let (Type::Struct { name: n1, .. }, Type::Struct { name: n2, .. }) = (expected, actual) {
+    // For struct and enum types, enforce nominal typing - names must match
+    if let (Type::Struct { name: n1, .. }, Type::Struct { name: n2, .. })
+    | (Type::Enum { name: n1, .. }, Type::Enum { name: n2, .. }) = (expected, actual)
+    {
         return n1 == n2;
     }
 
@@ -544,7 +990,7 @@ impl Eval for Synthetic {
         let ident = self.identifier();
 
         match ident {
-            "__list__" | "__record__" | "__block__" | "__index__" => {
+            "__list__" | "__record__" | "__block__" | "__index__" | "__interpolate__" => {
                 // These should not appear as standalone expressions
                 // They're always wrapped in Apply nodes
                 Ok(Value::Nil)
@@ -564,111 +1010,10 @@ impl Eval for Synthetic {
 pub fn builtin_add() -> BuiltinFn {
     BuiltinFn {
         name: "+",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer, Type::Integer], Type::Integer),
-            Type::function(vec![Type::Float, Type::Float], Type::Float),
-        ]),
-        func: |args, _ctx| {
-            if args.len() != 2 {
-                return Err(Diagnostic::arity(2, args.len()));
-            }
-
-            match (&args[0], &args[1]) {
-                (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a + b)),
-                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
-                // Handle quantity addition - dimensions must match
-                (
-                    Value::Quantity {
-                        value: v1,
-                        unit: u1,
-                        dimension: d1,
-                    },
-                    Value::Quantity {
-                        value: v2,
-                        unit: u2,
-                        dimension: _d2,
-                    },
-                ) => {
-                    // Check if dimensions are compatible
-                    if u1.dimension != u2.dimension {
-                        return Err(Diagnostic::syntax(format!(
-                            "cannot add quantities with incompatible dimensions: {} and {}",
-                            u1.name, u2.name
-                        )));
-                    }
-
-                    // Convert second quantity to first unit's scale
-                    let scale_ratio = u2.scale / u1.scale;
-                    let v2_converted = v2 * scale_ratio;
-
-                    let result = v1 + v2_converted;
-                    Ok(Value::Quantity {
-                        value: result,
-                        unit: u1.clone(),
-                        dimension: d1.clone(),
-                    })
-                }
-                // Allow adding plain numbers to quantities
-                (
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                    Value::Integer(n),
-                )
-                | (
-                    Value::Integer(n),
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                ) => {
-                    let result = value + (*n as f64);
-                    Ok(Value::Quantity {
-                        value: result,
-                        unit: unit.clone(),
-                        dimension: dimension.clone(),
-                    })
-                }
-                (
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                    Value::Float(f),
-                )
-                | (
-                    Value::Float(f),
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                ) => {
-                    let result = value + f;
-                    Ok(Value::Quantity {
-                        value: result,
-                        unit: unit.clone(),
-                        dimension: dimension.clone(),
-                    })
-                }
-                // Type mismatch - reject mixed integer/float operations
-                (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => {
-                    Err(Diagnostic::type_error(args[0].type_of(), args[1].type_of()))
-                }
-                // For non-numeric types, report type error
-                (Value::Integer(_), b) | (Value::Float(_), b) => Err(Diagnostic::type_error(
-                    Type::union(vec![Type::Integer, Type::Float]),
-                    b.type_of(),
-                )),
-                (a, _) => Err(Diagnostic::type_error(
-                    Type::union(vec![Type::Integer, Type::Float]),
-                    a.type_of(),
-                )),
-            }
+        signature: num::overloads(|ty| Type::function(vec![ty.clone(), ty.clone()], ty)),
+        func: |args, _ctx| match args {
+            [lhs, rhs] => num::arithmetic(num::Op::Add, lhs, rhs),
+            _ => Err(Diagnostic::arity(2, args.len())),
         },
     }
 }
@@ -677,153 +1022,22 @@ pub fn builtin_add() -> BuiltinFn {
 pub fn builtin_sub() -> BuiltinFn {
     BuiltinFn {
         name: "-",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer], Type::Integer),
-            Type::function(vec![Type::Float], Type::Float),
-            Type::function(vec![Type::Integer, Type::Integer], Type::Integer),
-            Type::function(vec![Type::Float, Type::Float], Type::Float),
-        ]),
-        func: |args, _ctx| {
-            match args.len() {
-                1 => {
-                    // Unary negation
-                    match &args[0] {
-                        Value::Integer(a) => Ok(Value::Integer(-a)),
-                        Value::Float(a) => Ok(Value::Float(-a)),
-                        Value::Quantity {
-                            value,
-                            unit,
-                            dimension,
-                        } => Ok(Value::Quantity {
-                            value: -value,
-                            unit: unit.clone(),
-                            dimension: dimension.clone(),
-                        }),
-                        a => Err(Diagnostic::type_error(
-                            Type::union(vec![Type::Integer, Type::Float]),
-                            a.type_of(),
-                        )),
-                    }
-                }
-                2 => {
-                    // Binary subtraction
-                    match (&args[0], &args[1]) {
-                        (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a - b)),
-                        (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
-                        // Handle quantity subtraction - dimensions must match
-                        (
-                            Value::Quantity {
-                                value: v1,
-                                unit: u1,
-                                dimension: d1,
-                            },
-                            Value::Quantity {
-                                value: v2,
-                                unit: u2,
-                                dimension: _d2,
-                            },
-                        ) => {
-                            // Check if dimensions are compatible
-                            if u1.dimension != u2.dimension {
-                                return Err(Diagnostic::syntax(format!(
-                                    "cannot subtract quantities with incompatible dimensions: {} and {}",
-                                    u1.name, u2.name
-                                )));
-                            }
-
-                            // Convert second quantity to first unit's scale
-                            let scale_ratio = u2.scale / u1.scale;
-                            let v2_converted = v2 * scale_ratio;
-
-                            let result = v1 - v2_converted;
-                            Ok(Value::Quantity {
-                                value: result,
-                                unit: u1.clone(),
-                                dimension: d1.clone(),
-                            })
-                        }
-                        // Allow subtracting plain numbers from quantities
-                        (
-                            Value::Quantity {
-                                value,
-                                unit,
-                                dimension,
-                            },
-                            Value::Integer(n),
-                        ) => {
-                            let result = value - (*n as f64);
-                            Ok(Value::Quantity {
-                                value: result,
-                                unit: unit.clone(),
-                                dimension: dimension.clone(),
-                            })
-                        }
-                        (
-                            Value::Integer(n),
-                            Value::Quantity {
-                                value,
-                                unit,
-                                dimension,
-                            },
-                        ) => {
-                            let result = (*n as f64) - value;
-                            Ok(Value::Quantity {
-                                value: result,
-                                unit: unit.clone(),
-                                dimension: dimension.clone(),
-                            })
-                        }
-                        (
-                            Value::Quantity {
-                                value,
-                                unit,
-                                dimension,
-                            },
-                            Value::Float(f),
-                        ) => {
-                            let result = value - f;
-                            Ok(Value::Quantity {
-                                value: result,
-                                unit: unit.clone(),
-                                dimension: dimension.clone(),
-                            })
-                        }
-                        (
-                            Value::Float(f),
-                            Value::Quantity {
-                                value,
-                                unit,
-                                dimension,
-                            },
-                        ) => {
-                            let result = f - value;
-                            Ok(Value::Quantity {
-                                value: result,
-                                unit: unit.clone(),
-                                dimension: dimension.clone(),
-                            })
-                        }
-                        // Type mismatch - reject mixed integer/float operations
-                        (Value::Integer(_), Value::Float(_))
-                        | (Value::Float(_), Value::Integer(_)) => {
-                            Err(Diagnostic::type_error(args[0].type_of(), args[1].type_of()))
-                        }
-                        // For non-numeric types, report type error
-                        (Value::Integer(_), b) | (Value::Float(_), b) => {
-                            Err(Diagnostic::type_error(
-                                Type::union(vec![Type::Integer, Type::Float]),
-                                b.type_of(),
-                            ))
-                        }
-                        (a, _) => Err(Diagnostic::type_error(
-                            Type::union(vec![Type::Integer, Type::Float]),
-                            a.type_of(),
-                        )),
-                    }
-                }
-                0 => Err(Diagnostic::arity(1, 0)),
-                _ => Err(Diagnostic::arity(2, args.len())),
-            }
+        signature: Type::union(
+            num::DEFAULTS
+                .into_iter()
+                .map(|ty| Type::function(vec![ty.clone()], ty))
+                .chain(
+                    num::DEFAULTS
+                        .into_iter()
+                        .map(|ty| Type::function(vec![ty.clone(), ty.clone()], ty)),
+                )
+                .collect(),
+        ),
+        func: |args, _ctx| match args {
+            [value] => num::negate(value),
+            [lhs, rhs] => num::arithmetic(num::Op::Sub, lhs, rhs),
+            [] => Err(Diagnostic::arity(1, 0)),
+            _ => Err(Diagnostic::arity(2, args.len())),
         },
     }
 }
@@ -832,98 +1046,10 @@ pub fn builtin_sub() -> BuiltinFn {
 pub fn builtin_mul() -> BuiltinFn {
     BuiltinFn {
         name: "*",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer, Type::Integer], Type::Integer),
-            Type::function(vec![Type::Float, Type::Float], Type::Float),
-        ]),
-        func: |args, _ctx| {
-            if args.len() != 2 {
-                return Err(Diagnostic::arity(2, args.len()));
-            }
-
-            match (&args[0], &args[1]) {
-                // Integer multiplication
-                (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a * b)),
-                // Float multiplication
-                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
-                // Quantity multiplication with dimensions
-                (
-                    Value::Quantity {
-                        value: v1,
-                        unit: _,
-                        dimension: d1,
-                    },
-                    Value::Quantity {
-                        value: v2,
-                        unit: _,
-                        dimension: d2,
-                    },
-                ) => {
-                    let result_val = v1 * v2;
-                    let result_dim = d1.multiply(d2);
-                    Ok(create_numeric_value(result_val, Some(result_dim), None))
-                }
-                // Quantity * scalar
-                (
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                    Value::Float(f),
-                )
-                | (
-                    Value::Float(f),
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                ) => {
-                    let result = value * f;
-                    Ok(Value::Quantity {
-                        value: result,
-                        unit: unit.clone(),
-                        dimension: dimension.clone(),
-                    })
-                }
-                (
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                    Value::Integer(n),
-                )
-                | (
-                    Value::Integer(n),
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                ) => {
-                    let result = value * (*n as f64);
-                    Ok(Value::Quantity {
-                        value: result,
-                        unit: unit.clone(),
-                        dimension: dimension.clone(),
-                    })
-                }
-                // Type mismatch - reject mixed integer/float operations
-                (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => {
-                    Err(Diagnostic::type_error(args[0].type_of(), args[1].type_of()))
-                }
-                // For non-numeric types, report type error
-                (Value::Integer(_), b) | (Value::Float(_), b) => Err(Diagnostic::type_error(
-                    Type::union(vec![Type::Integer, Type::Float]),
-                    b.type_of(),
-                )),
-                (a, _) => Err(Diagnostic::type_error(
-                    Type::union(vec![Type::Integer, Type::Float]),
-                    a.type_of(),
-                )),
-            }
+        signature: num::overloads(|ty| Type::function(vec![ty.clone(), ty.clone()], ty)),
+        func: |args, _ctx| match args {
+            [lhs, rhs] => num::arithmetic(num::Op::Mul, lhs, rhs),
+            _ => Err(Diagnostic::arity(2, args.len())),
         },
     }
 }
@@ -932,138 +1058,22 @@ pub fn builtin_mul() -> BuiltinFn {
 pub fn builtin_div() -> BuiltinFn {
     BuiltinFn {
         name: "/",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer, Type::Integer], Type::Integer),
-            Type::function(vec![Type::Float, Type::Float], Type::Float),
-        ]),
-        func: |args, _ctx| {
-            if args.len() != 2 {
-                return Err(Diagnostic::arity(2, args.len()));
-            }
+        signature: num::overloads(|ty| Type::function(vec![ty.clone(), ty.clone()], ty)),
+        func: |args, _ctx| match args {
+            [lhs, rhs] => num::arithmetic(num::Op::Div, lhs, rhs),
+            _ => Err(Diagnostic::arity(2, args.len())),
+        },
+    }
+}
 
-            match (&args[0], &args[1]) {
-                // Integer division
-                (Value::Integer(a), Value::Integer(b)) => {
-                    if *b == 0 {
-                        return Err(Diagnostic::syntax("division by zero"));
-                    }
-                    Ok(Value::Integer(a / b))
-                }
-                // Float division
-                (Value::Float(a), Value::Float(b)) => {
-                    if *b == 0.0 {
-                        return Err(Diagnostic::syntax("division by zero"));
-                    }
-                    Ok(Value::Float(a / b))
-                }
-                // Quantity division with dimensions
-                (
-                    Value::Quantity {
-                        value: v1,
-                        unit: _,
-                        dimension: d1,
-                    },
-                    Value::Quantity {
-                        value: v2,
-                        unit: _,
-                        dimension: d2,
-                    },
-                ) => {
-                    if *v2 == 0.0 {
-                        return Err(Diagnostic::syntax("division by zero"));
-                    }
-                    let result_val = v1 / v2;
-                    let result_dim = d1.divide(d2);
-                    Ok(create_numeric_value(result_val, Some(result_dim), None))
-                }
-                // Quantity / scalar
-                (
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                    Value::Float(f),
-                ) => {
-                    if *f == 0.0 {
-                        return Err(Diagnostic::syntax("division by zero"));
-                    }
-                    let result = value / f;
-                    Ok(Value::Quantity {
-                        value: result,
-                        unit: unit.clone(),
-                        dimension: dimension.clone(),
-                    })
-                }
-                (
-                    Value::Quantity {
-                        value,
-                        unit,
-                        dimension,
-                    },
-                    Value::Integer(n),
-                ) => {
-                    if *n == 0 {
-                        return Err(Diagnostic::syntax("division by zero"));
-                    }
-                    let result = value / (*n as f64);
-                    Ok(Value::Quantity {
-                        value: result,
-                        unit: unit.clone(),
-                        dimension: dimension.clone(),
-                    })
-                }
-                // scalar / Quantity - inverts dimension
-                (
-                    Value::Float(f),
-                    Value::Quantity {
-                        value, dimension, ..
-                    },
-                ) => {
-                    if *value == 0.0 {
-                        return Err(Diagnostic::syntax("division by zero"));
-                    }
-                    let result_val = f / value;
-                    // Invert the dimension
-                    use crate::unit::DerivedDimension;
-                    let inverted_dim = DerivedDimension {
-                        numerator: dimension.denominator.clone(),
-                        denominator: dimension.numerator.clone(),
-                    };
-                    Ok(create_numeric_value(result_val, Some(inverted_dim), None))
-                }
-                (
-                    Value::Integer(n),
-                    Value::Quantity {
-                        value, dimension, ..
-                    },
-                ) => {
-                    if *value == 0.0 {
-                        return Err(Diagnostic::syntax("division by zero"));
-                    }
-                    let result_val = (*n as f64) / value;
-                    // Invert the dimension
-                    use crate::unit::DerivedDimension;
-                    let inverted_dim = DerivedDimension {
-                        numerator: dimension.denominator.clone(),
-                        denominator: dimension.numerator.clone(),
-                    };
-                    Ok(create_numeric_value(result_val, Some(inverted_dim), None))
-                }
-                // Type mismatch - reject mixed integer/float operations
-                (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => {
-                    Err(Diagnostic::type_error(args[0].type_of(), args[1].type_of()))
-                }
-                // For non-numeric types, report type error
-                (Value::Integer(_), b) | (Value::Float(_), b) => Err(Diagnostic::type_error(
-                    Type::union(vec![Type::Integer, Type::Float]),
-                    b.type_of(),
-                )),
-                (a, _) => Err(Diagnostic::type_error(
-                    Type::union(vec![Type::Integer, Type::Float]),
-                    a.type_of(),
-                )),
-            }
+/// Creates the `%` remainder operator, for integers.
+pub fn builtin_rem() -> BuiltinFn {
+    BuiltinFn {
+        name: "%",
+        signature: Type::function(vec![Type::Integer, Type::Integer], Type::Integer),
+        func: |args, _ctx| match args {
+            [lhs, rhs] => num::arithmetic(num::Op::Rem, lhs, rhs),
+            _ => Err(Diagnostic::arity(2, args.len())),
         },
     }
 }
@@ -1078,17 +1088,7 @@ pub fn builtin_eq() -> BuiltinFn {
                 return Err(Diagnostic::arity(2, args.len()));
             }
 
-            let a = &args[0];
-            let b = &args[1];
-
-            // Check that types match
-            let type_a = a.type_of();
-            let type_b = b.type_of();
-            if type_a != type_b {
-                return Err(Diagnostic::type_error(type_a, type_b));
-            }
-
-            Ok(Value::Bool(a == b))
+            equal(&args[0], &args[1]).map(Value::Bool)
         },
     }
 }
@@ -1103,61 +1103,34 @@ pub fn builtin_ne() -> BuiltinFn {
                 return Err(Diagnostic::arity(2, args.len()));
             }
 
-            let a = &args[0];
-            let b = &args[1];
-
-            // Check that types match
-            let type_a = a.type_of();
-            let type_b = b.type_of();
-            if type_a != type_b {
-                return Err(Diagnostic::type_error(type_a, type_b));
-            }
-
-            Ok(Value::Bool(a != b))
+            equal(&args[0], &args[1]).map(|equal| Value::Bool(!equal))
         },
     }
 }
 
-/// Helper function to compare two values using their PartialOrd implementation.
-/// Returns a type error if the values have different types or are not comparable.
+/// Checks two values of the same type for equality. Numbers of different
+/// types are compared with [`num::compare`], so an integer equals the
+/// decimal it lifts to, as it orders.
+fn equal(a: &Value, b: &Value) -> Result<bool> {
+    if a.type_of().same_as(&b.type_of()) {
+        return Ok(a == b);
+    }
+    num::compare(a, b).map(|ordering| ordering == std::cmp::Ordering::Equal)
+}
+
+/// Orders two numbers with [`num::compare`] and checks the ordering.
 fn compare_ordered<F>(a: &Value, b: &Value, check_ordering: F) -> Result<Value>
 where
     F: FnOnce(std::cmp::Ordering) -> bool,
 {
-    // Check that types match - strongly typed, no implicit conversions
-    let type_a = a.type_of();
-    let type_b = b.type_of();
-    if type_a != type_b {
-        return Err(Diagnostic::type_error(type_a, type_b));
-    }
-
-    match (a, b) {
-        (Value::Integer(a), Value::Integer(b)) => {
-            let ordering = a.cmp(b);
-            Ok(Value::Bool(check_ordering(ordering)))
-        }
-        (Value::Float(a), Value::Float(b)) => {
-            // For floats, use partial_cmp since they may be NaN
-            match a.partial_cmp(b) {
-                Some(ordering) => Ok(Value::Bool(check_ordering(ordering))),
-                None => Err(Diagnostic::syntax("cannot compare NaN values".to_string())),
-            }
-        }
-        _ => Err(Diagnostic::syntax(format!(
-            "cannot compare values of type {}",
-            a.type_of()
-        ))),
-    }
+    num::compare(a, b).map(|ordering| Value::Bool(check_ordering(ordering)))
 }
 
 /// Creates the `<` less-than operator.
 pub fn builtin_lt() -> BuiltinFn {
     BuiltinFn {
         name: "<",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer, Type::Integer], Type::Bool),
-            Type::function(vec![Type::Float, Type::Float], Type::Bool),
-        ]),
+        signature: num::overloads(|ty| Type::function(vec![ty.clone(), ty], Type::Bool)),
         func: |args, _ctx| {
             if args.len() != 2 {
                 return Err(Diagnostic::arity(2, args.len()));
@@ -1171,10 +1144,7 @@ pub fn builtin_lt() -> BuiltinFn {
 pub fn builtin_lte() -> BuiltinFn {
     BuiltinFn {
         name: "<=",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer, Type::Integer], Type::Bool),
-            Type::function(vec![Type::Float, Type::Float], Type::Bool),
-        ]),
+        signature: num::overloads(|ty| Type::function(vec![ty.clone(), ty], Type::Bool)),
         func: |args, _ctx| {
             if args.len() != 2 {
                 return Err(Diagnostic::arity(2, args.len()));
@@ -1188,10 +1158,7 @@ pub fn builtin_lte() -> BuiltinFn {
 pub fn builtin_gt() -> BuiltinFn {
     BuiltinFn {
         name: ">",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer, Type::Integer], Type::Bool),
-            Type::function(vec![Type::Float, Type::Float], Type::Bool),
-        ]),
+        signature: num::overloads(|ty| Type::function(vec![ty.clone(), ty], Type::Bool)),
         func: |args, _ctx| {
             if args.len() != 2 {
                 return Err(Diagnostic::arity(2, args.len()));
@@ -1205,10 +1172,7 @@ pub fn builtin_gt() -> BuiltinFn {
 pub fn builtin_gte() -> BuiltinFn {
     BuiltinFn {
         name: ">=",
-        signature: Type::union(vec![
-            Type::function(vec![Type::Integer, Type::Integer], Type::Bool),
-            Type::function(vec![Type::Float, Type::Float], Type::Bool),
-        ]),
+        signature: num::overloads(|ty| Type::function(vec![ty.clone(), ty], Type::Bool)),
         func: |args, _ctx| {
             if args.len() != 2 {
                 return Err(Diagnostic::arity(2, args.len()));
@@ -1271,7 +1235,16 @@ mod tests {
     fn eval_string() {
         assert_eq!(
             eval_single("\"hello\"").unwrap(),
-            Value::String("hello".to_string())
+            Value::String("hello".into())
+        );
+    }
+
+    #[test]
+    fn string_literals_borrow_interned_text() {
+        let value = eval_single("\"hello\"").unwrap();
+        assert!(
+            matches!(value, Value::String(Cow::Borrowed("hello"))),
+            "{value:?}"
         );
     }
 
@@ -1315,7 +1288,7 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err().kind,
-            crate::diagnostic::DiagnosticKind::UndefinedVariable(_)
+            crate::diagnostic::DiagnosticKind::UndefinedVariable { .. }
         ));
     }
 
@@ -1362,10 +1335,10 @@ mod tests {
 
         let results = eval(&root, &mut env, &mut compiler);
 
-        // Should have 3 results (1, Nil for error, 3)
+        // Should have 3 results (1, Error for error, 3)
         assert_eq!(results.len(), 3);
         assert_eq!(results[0], Value::Integer(1));
-        assert_eq!(results[1], Value::Nil); // error becomes Nil
+        assert_eq!(results[1], Value::Error); // error becomes Error
         assert_eq!(results[2], Value::Integer(3));
 
         // Compiler should have recorded 1 error
@@ -1386,11 +1359,11 @@ mod tests {
 
         // Should have 5 results
         assert_eq!(results.len(), 5);
-        assert_eq!(results[0], Value::Nil); // undefined_a error
+        assert_eq!(results[0], Value::Error); // undefined_a error
         assert_eq!(results[1], Value::Integer(1));
-        assert_eq!(results[2], Value::Nil); // undefined_b error
+        assert_eq!(results[2], Value::Error); // undefined_b error
         assert_eq!(results[3], Value::Integer(2));
-        assert_eq!(results[4], Value::Nil); // undefined_c error
+        assert_eq!(results[4], Value::Error); // undefined_c error
 
         // Compiler should have recorded 3 errors
         assert_eq!(compiler.num_diagnostics(), 3);
@@ -1400,7 +1373,7 @@ mod tests {
         for diag in compiler.diagnostics() {
             assert!(matches!(
                 &diag.kind,
-                crate::diagnostic::DiagnosticKind::UndefinedVariable(_)
+                crate::diagnostic::DiagnosticKind::UndefinedVariable { .. }
             ));
         }
     }
@@ -1424,4 +1397,62 @@ mod tests {
         assert_eq!(compiler.num_diagnostics(), 0);
         assert!(!compiler.has_errors());
     }
+
+    fn eval_with_options(src: &str, options: crate::CompileOptions) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::with_options(options);
+        let results = eval(&parse(src).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn tail_calls_run_in_constant_stack() {
+        // Far deeper than the recursion limit, through `if`, `match` and a
+        // block in tail position
+        let src = "\
+fn count n acc = match n
+    0 => acc
+    _ => (step n acc)
+fn step n acc =
+    let next = n - 1
+    if next >= 0 then count next (acc + 1) else acc
+count 20000 0
+";
+        let (results, compiler) = eval_with_options(
+            src,
+            crate::CompileOptions {
+                recursion_limit: 100,
+                ..Default::default()
+            },
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results.last(), Some(&Value::Integer(20000)));
+    }
+
+    #[test]
+    fn deep_recursion_grows_the_stack() {
+        // Each nested call takes kilobytes of stack, so a few thousand would
+        // overflow a 2 MiB test thread without new stack segments
+        let src = "fn sum n = if n == 0 then 0 else n + (sum (n - 1))\nsum 5000\n";
+        let (results, compiler) = eval_with_options(src, Default::default());
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results.last(), Some(&Value::Integer(12502500)));
+    }
+
+    #[test]
+    fn recursion_limit_stops_runaway_recursion() {
+        let src = "fn forever n = 1 + (forever n)\nforever 1\n";
+        let (_, compiler) = eval_with_options(
+            src,
+            crate::CompileOptions {
+                recursion_limit: 300,
+                ..Default::default()
+            },
+        );
+        let kinds: Vec<_> = compiler.diagnostics().iter().map(|d| d.kind()).collect();
+        assert!(matches!(
+            kinds.as_slice(),
+            [crate::DiagnosticKind::RecursionLimit { limit: 300 }]
+        ));
+    }
 }
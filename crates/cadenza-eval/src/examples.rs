@@ -0,0 +1,45 @@
+//! The bundled example programs.
+//!
+//! These are the `test-data/example-*.cdz` files, which are also snapshot
+//! tested and offered in the web playground. Each starts with a header
+//! comment: a title line, a short description, and a `Tags:` line listing
+//! its topics.
+//!
+//! ```
+//! let measures = cadenza_eval::examples::get("measures").unwrap();
+//! assert_eq!(measures.title, "Units of Measure");
+//! assert_eq!(measures.tags, ["units", "arithmetic"]);
+//! assert!(measures.source.contains("measure meter"));
+//! ```
+
+/// A bundled example program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Example {
+    /// The file name without the `example-NN-` prefix, like `measures`.
+    pub id: &'static str,
+    /// The first line of the header comment.
+    pub title: &'static str,
+    /// The rest of the header comment, joined into one line.
+    pub description: &'static str,
+    /// The topics the example covers, like `units`.
+    pub tags: &'static [&'static str],
+    /// The program.
+    pub source: &'static str,
+}
+
+/// Returns every example, in file name order.
+pub fn all() -> &'static [Example] {
+    crate::generated::examples::EXAMPLES
+}
+
+/// Returns the example with `id`.
+pub fn get(id: &str) -> Option<&'static Example> {
+    all().iter().find(|example| example.id == id)
+}
+
+/// Returns the examples tagged with `tag`, in file name order.
+pub fn tagged(tag: &str) -> impl Iterator<Item = &'static Example> {
+    all()
+        .iter()
+        .filter(move |example| example.tags.contains(&tag))
+}
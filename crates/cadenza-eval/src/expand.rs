@@ -0,0 +1,340 @@
+//! Macro expansion viewer.
+//!
+//! Renders a source file after macro and special-form expansion. Surface
+//! syntax such as infix operators, list/record literals, blocks, and pipelines
+//! is lowered to the prefix application form the evaluator actually sees:
+//!
+//! ```text
+//! let x = [1, 2] |> f   =>   (= (let x) (f (__list__ 1 2)))
+//! ```
+//!
+//! List comprehensions are rewritten into the `map` and `filter` calls they
+//! evaluate as:
+//!
+//! ```text
+//! [x * x for x in xs if x > 2]   =>   (map (-> x (* x x)) (filter (-> x (> x 2)) xs))
+//! ```
+//!
+//! Every application that dispatches to a macro or special form produces an
+//! [`ExpansionMarker`] linking the rendered region back to the originating
+//! invocation in the source. This is used by `cadenza expand` and the
+//! `cadenza/expand` LSP request.
+
+use crate::{
+    compiler::Compiler,
+    env::Env,
+    eval::extract_identifier,
+    interner::InternedString,
+    special_form::list_form::{Comprehension, comprehension},
+    value::Value,
+};
+use cadenza_syntax::{
+    ast::{Apply, Expr, Root},
+    span::Span,
+};
+
+/// Maximum line width before the pretty-printer breaks an application across lines.
+const MAX_WIDTH: usize = 80;
+
+/// The result of expanding a source file.
+#[derive(Debug, Clone, Default)]
+pub struct Expansion {
+    /// The expanded source, one top-level item per line (or indented group of lines).
+    pub text: String,
+    /// Markers linking regions of `text` back to the macro invocations they came from.
+    pub markers: Vec<ExpansionMarker>,
+}
+
+/// Links a region of expanded output to the macro invocation that produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpansionMarker {
+    /// Name of the macro or special form that was expanded.
+    pub name: InternedString,
+    /// Span of the invocation in the original source.
+    pub origin: Span,
+    /// Span of the expanded region in [`Expansion::text`].
+    pub expanded: Span,
+}
+
+impl Expansion {
+    /// Renders the expansion with a trailing comment after each top-level item
+    /// listing the macro invocations it came from.
+    ///
+    /// Positions are reported as 1-based `line:column` pairs in `source`.
+    pub fn annotated(&self, source: &str) -> String {
+        let mut out = String::new();
+        let mut line_start = 0;
+
+        for line in self.text.split_inclusive('\n') {
+            let line_end = line_start + line.len();
+            let content = line.trim_end_matches('\n');
+            out.push_str(content);
+
+            // Annotate with markers whose expanded region starts on this line
+            let origins: Vec<String> = self
+                .markers
+                .iter()
+                .filter(|m| m.expanded.start >= line_start && m.expanded.start < line_end)
+                .map(|m| {
+                    let (line, col) = line_col(source, m.origin.start);
+                    format!("{} @ {line}:{col}", &*m.name)
+                })
+                .collect();
+            if !origins.is_empty() {
+                out.push_str("  # ");
+                out.push_str(&origins.join(", "));
+            }
+            out.push('\n');
+
+            line_start = line_end;
+        }
+
+        out
+    }
+}
+
+/// Expands every top-level item in `root`.
+///
+/// The environment and compiler are only consulted to decide which callees are
+/// macros or special forms; nothing is evaluated.
+pub fn expand(root: &Root, env: &Env, compiler: &Compiler) -> Expansion {
+    let mut printer = Printer {
+        env,
+        compiler,
+        out: Expansion::default(),
+        flat: 0,
+    };
+
+    for expr in root.items() {
+        printer.expr(&expr, 0);
+        printer.out.text.push('\n');
+    }
+
+    printer.out
+}
+
+/// Converts a byte offset into a 1-based line and column.
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let col = before.rfind('\n').map_or(offset, |i| offset - i - 1) + 1;
+    (line, col)
+}
+
+struct Printer<'a> {
+    env: &'a Env,
+    compiler: &'a Compiler,
+    out: Expansion,
+    /// Nesting depth of flat rendering attempts; forms never break while non-zero.
+    flat: usize,
+}
+
+impl Printer<'_> {
+    /// Returns the name of the macro or special form `callee` refers to, if any.
+    fn macro_name(&self, callee: &Expr) -> Option<InternedString> {
+        let id = extract_identifier(callee)?;
+        if self.compiler.get_macro(id).is_some() {
+            return Some(id);
+        }
+        match self.env.get(id) {
+            Some(Value::BuiltinMacro(_) | Value::SpecialForm(_)) => Some(id),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, indent: usize) {
+        match expr {
+            Expr::Apply(apply) => self.apply(apply, indent),
+            Expr::Synthetic(syn) => self.out.text.push_str(syn.identifier()),
+            Expr::Error(_) => self.out.text.push_str("<error>"),
+            other => self.out.text.push_str(&other.syntax().text().to_string()),
+        }
+    }
+
+    fn apply(&mut self, apply: &Apply, indent: usize) {
+        let Some(callee) = apply.callee() else {
+            self.out.text.push_str("<error>");
+            return;
+        };
+        let mut args = apply.all_arguments();
+        let name = self.macro_name(&callee);
+
+        // Pipelines are rewritten into the application they denote
+        if name.as_deref() == Some("|>") && args.len() == 2 {
+            let rhs = args.pop().unwrap();
+            let lhs = args.pop().unwrap();
+            let (head, mut rest) = match &rhs {
+                Expr::Apply(rhs_apply) => match rhs_apply.callee() {
+                    Some(head) => (head, rhs_apply.all_arguments()),
+                    None => (rhs.clone(), vec![]),
+                },
+                _ => (rhs.clone(), vec![]),
+            };
+            rest.insert(0, lhs);
+            let start = self.out.text.len();
+            self.form(&head, &rest, indent);
+            self.mark(name.unwrap(), apply.span(), start);
+            return;
+        }
+
+        // Comprehensions are rewritten into `map` over `filter`
+        if name.as_deref() == Some("__list__")
+            && let Some(Ok(comprehension)) = comprehension(&args)
+        {
+            let start = self.out.text.len();
+            self.flat += 1;
+            self.out.text.push_str("(map ");
+            self.lambda(
+                &comprehension,
+                std::slice::from_ref(&comprehension.element),
+                indent,
+            );
+            self.out.text.push(' ');
+            self.filtered(&comprehension, comprehension.conditions.len(), indent);
+            self.out.text.push(')');
+            self.flat -= 1;
+            self.mark(name.unwrap(), apply.span(), start);
+            return;
+        }
+
+        let start = self.out.text.len();
+        self.form(&callee, &args, indent);
+        if let Some(name) = name {
+            self.mark(name, apply.span(), start);
+        }
+    }
+
+    /// Prints the list of a comprehension filtered by its first `conditions`.
+    fn filtered(&mut self, comprehension: &Comprehension, conditions: usize, indent: usize) {
+        let Some(condition) = conditions.checked_sub(1) else {
+            match &comprehension.list[..] {
+                [list] => self.expr(list, indent),
+                [head, args @ ..] => self.form(head, args, indent),
+                [] => self.out.text.push_str("<error>"),
+            }
+            return;
+        };
+        self.out.text.push_str("(filter ");
+        self.lambda(comprehension, &comprehension.conditions[condition], indent);
+        self.out.text.push(' ');
+        self.filtered(comprehension, condition, indent);
+        self.out.text.push(')');
+    }
+
+    /// Prints a function of the comprehension's variable returning `body`,
+    /// one expression or an application of the first to the rest.
+    fn lambda(&mut self, comprehension: &Comprehension, body: &[Expr], indent: usize) {
+        self.out.text.push_str("(-> ");
+        self.out.text.push_str(&comprehension.var);
+        self.out.text.push(' ');
+        match body {
+            [body] => self.expr(body, indent),
+            [head, args @ ..] => self.form(head, args, indent),
+            [] => self.out.text.push_str("<error>"),
+        }
+        self.out.text.push(')');
+    }
+
+    /// Prints `(head args...)`, breaking onto indented lines if it doesn't fit.
+    fn form(&mut self, head: &Expr, args: &[Expr], indent: usize) {
+        let start = self.out.text.len();
+        let markers = self.out.markers.len();
+
+        self.flat += 1;
+        self.out.text.push('(');
+        self.expr(head, indent + 1);
+        for arg in args {
+            self.out.text.push(' ');
+            self.expr(arg, indent + 1);
+        }
+        self.out.text.push(')');
+        self.flat -= 1;
+
+        let line_start = self.out.text[..start].rfind('\n').map_or(0, |i| i + 1);
+        if self.flat > 0 || self.out.text.len() - line_start <= MAX_WIDTH {
+            return;
+        }
+
+        // Too wide: re-render with each argument on its own line
+        self.out.text.truncate(start);
+        self.out.markers.truncate(markers);
+        self.out.text.push('(');
+        self.expr(head, indent + 1);
+        for arg in args {
+            self.out.text.push('\n');
+            self.out.text.push_str(&"  ".repeat(indent + 1));
+            self.expr(arg, indent + 1);
+        }
+        self.out.text.push(')');
+    }
+
+    fn mark(&mut self, name: InternedString, origin: Span, start: usize) {
+        self.out.markers.push(ExpansionMarker {
+            name,
+            origin,
+            expanded: Span::new(start, self.out.text.len()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadenza_syntax::parse::parse;
+
+    fn expand_src(src: &str) -> Expansion {
+        let root = parse(src).ast();
+        let env = Env::with_standard_builtins();
+        let compiler = Compiler::new();
+        expand(&root, &env, &compiler)
+    }
+
+    #[test]
+    fn lowers_infix_and_literals() {
+        let expansion = expand_src("let x = [1, 2] + 3\n");
+        assert_eq!(expansion.text, "(= (let x) (+ (__list__ 1 2) 3))\n");
+    }
+
+    #[test]
+    fn rewrites_pipelines() {
+        let expansion = expand_src("5 |> add 3\n10 |> double\n");
+        assert_eq!(expansion.text, "(add 5 3)\n(double 10)\n");
+        assert_eq!(expansion.markers.len(), 2);
+        assert_eq!(&*expansion.markers[0].name, "|>");
+        assert_eq!(expansion.markers[0].origin.start, 0);
+        assert_eq!(expansion.markers[1].origin.start, 11);
+    }
+
+    #[test]
+    fn rewrites_comprehensions() {
+        let expansion = expand_src("[x * x for x in range 0 n if x > 2 if even x]\n");
+        assert_eq!(
+            expansion.text,
+            "(map (-> x (* x x)) (filter (-> x (even x)) (filter (-> x (> x 2)) (range 0 n))))\n"
+        );
+    }
+
+    #[test]
+    fn markers_point_back_to_invocations() {
+        let src = "let x = 1\n";
+        let expansion = expand_src(src);
+        let names: Vec<&str> = expansion.markers.iter().map(|m| &*m.name).collect();
+        assert_eq!(names, vec!["let", "="]);
+        for marker in &expansion.markers {
+            let expanded = &expansion.text[marker.expanded.start..marker.expanded.end];
+            assert!(expanded.starts_with('(') && expanded.ends_with(')'));
+        }
+        assert_eq!(
+            expansion.annotated(src),
+            "(= (let x) 1)  # let @ 1:1, = @ 1:1\n"
+        );
+    }
+
+    #[test]
+    fn breaks_long_forms() {
+        let src = "let result = [aaaaaaaaaa, bbbbbbbbbb, cccccccccc, dddddddddd, eeeeeeeeee, ffffffffff]\n";
+        let expansion = expand_src(src);
+        assert!(expansion.text.contains("\n  "), "{}", expansion.text);
+    }
+}
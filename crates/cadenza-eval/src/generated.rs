@@ -1,2 +1,3 @@
+pub mod examples;
 #[cfg(test)]
 mod test_data;
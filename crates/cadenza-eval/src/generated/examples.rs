@@ -0,0 +1,77 @@
+// This file is auto-generated by the build script.
+// Do not edit manually - changes will be overwritten.
+
+use crate::examples::Example;
+
+pub static EXAMPLES: &[Example] = &[
+    Example {
+        id: "welcome",
+        title: "Welcome to Cadenza!",
+        description: "A functional language with units of measure",
+        tags: &["basics"],
+        source: include_str!("../../test-data/example-01-welcome.cdz"),
+    },
+    Example {
+        id: "literals",
+        title: "Literal Values",
+        description: "Different types of literals",
+        tags: &["basics", "literals"],
+        source: include_str!("../../test-data/example-02-literals.cdz"),
+    },
+    Example {
+        id: "arithmetic",
+        title: "Arithmetic Operations",
+        description: "Basic math with integers and floats",
+        tags: &["basics", "arithmetic"],
+        source: include_str!("../../test-data/example-03-arithmetic.cdz"),
+    },
+    Example {
+        id: "comparison",
+        title: "Comparison Operators",
+        description: "Compare numbers with ==, !=, <, >, <=, >=",
+        tags: &["basics", "comparison"],
+        source: include_str!("../../test-data/example-04-comparison.cdz"),
+    },
+    Example {
+        id: "variables",
+        title: "Variables with let",
+        description: "Define and use variables",
+        tags: &["basics", "variables"],
+        source: include_str!("../../test-data/example-05-variables.cdz"),
+    },
+    Example {
+        id: "functions",
+        title: "Functions",
+        description: "Define and call functions",
+        tags: &["functions"],
+        source: include_str!("../../test-data/example-06-functions.cdz"),
+    },
+    Example {
+        id: "measures",
+        title: "Units of Measure",
+        description: "Define and use physical units",
+        tags: &["units", "arithmetic"],
+        source: include_str!("../../test-data/example-07-measures.cdz"),
+    },
+    Example {
+        id: "lists",
+        title: "Lists",
+        description: "Collection of values in square brackets",
+        tags: &["collections"],
+        source: include_str!("../../test-data/example-08-lists.cdz"),
+    },
+    Example {
+        id: "assertions",
+        title: "Assertions - Runtime Checks",
+        description: "The assert macro allows you to verify conditions at runtime and provides detailed error messages when assertions fail.",
+        tags: &["testing"],
+        source: include_str!("../../test-data/example-09-assertions.cdz"),
+    },
+    Example {
+        id: "tuples",
+        title: "Tuples - Ordered Collections",
+        description: "Tuples group multiple values together into a single value",
+        tags: &["collections"],
+        source: include_str!("../../test-data/example-10-tuples.cdz"),
+    },
+];
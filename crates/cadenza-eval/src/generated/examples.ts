@@ -4,6 +4,9 @@
 export interface Example {
   id: string;
   name: string;
+  title: string;
+  description: string;
+  tags: string[];
   source: string;
 }
 
@@ -11,8 +14,12 @@ export const EXAMPLES: Example[] = [
   {
     id: "welcome",
     name: "Welcome",
+    title: "Welcome to Cadenza!",
+    description: "A functional language with units of measure",
+    tags: ["basics"],
     source: `# Welcome to Cadenza!
 # A functional language with units of measure
+# Tags: basics
 
 # Try some basic expressions
 42
@@ -31,8 +38,12 @@ square 5
   {
     id: "literals",
     name: "Literals",
+    title: "Literal Values",
+    description: "Different types of literals",
+    tags: ["basics", "literals"],
     source: `# Literal Values
 # Different types of literals
+# Tags: basics, literals
 
 # Integers
 42
@@ -53,8 +64,12 @@ square 5
   {
     id: "arithmetic",
     name: "Arithmetic",
+    title: "Arithmetic Operations",
+    description: "Basic math with integers and floats",
+    tags: ["basics", "arithmetic"],
     source: `# Arithmetic Operations
 # Basic math with integers and floats
+# Tags: basics, arithmetic
 
 # Addition and subtraction
 1 + 2
@@ -76,8 +91,12 @@ square 5
   {
     id: "comparison",
     name: "Comparison",
+    title: "Comparison Operators",
+    description: "Compare numbers with ==, !=, <, >, <=, >=",
+    tags: ["basics", "comparison"],
     source: `# Comparison Operators
 # Compare numbers with ==, !=, <, >, <=, >=
+# Tags: basics, comparison
 
 # Equality
 5 == 5
@@ -93,8 +112,12 @@ square 5
   {
     id: "variables",
     name: "Variables",
+    title: "Variables with let",
+    description: "Define and use variables",
+    tags: ["basics", "variables"],
     source: `# Variables with let
 # Define and use variables
+# Tags: basics, variables
 
 # Simple binding
 let x = 42
@@ -119,8 +142,12 @@ counter
   {
     id: "functions",
     name: "Functions",
+    title: "Functions",
+    description: "Define and call functions",
+    tags: ["functions"],
     source: `# Functions
 # Define and call functions
+# Tags: functions
 
 # Simple function
 fn double x = x * 2
@@ -139,8 +166,12 @@ capture
   {
     id: "measures",
     name: "Measures",
+    title: "Units of Measure",
+    description: "Define and use physical units",
+    tags: ["units", "arithmetic"],
     source: `# Units of Measure
 # Define and use physical units
+# Tags: units, arithmetic
 
 # Define base units
 measure meter
@@ -168,8 +199,12 @@ speed
   {
     id: "lists",
     name: "Lists",
+    title: "Lists",
+    description: "Collection of values in square brackets",
+    tags: ["collections"],
     source: `# Lists
 # Collection of values in square brackets
+# Tags: collections
 
 # Empty list
 []
@@ -198,10 +233,14 @@ let b = 200
   {
     id: "assertions",
     name: "Assertions",
+    title: "Assertions - Runtime Checks",
+    description: "The assert macro allows you to verify conditions at runtime and provides detailed error messages when assertions fail.",
+    tags: ["testing"],
     source: `# Assertions - Runtime Checks
 # 
 # The assert macro allows you to verify conditions at runtime
 # and provides detailed error messages when assertions fail.
+# Tags: testing
 
 # Basic assertion - verifies a condition is true
 let x = 5
@@ -226,14 +265,18 @@ assert result == 5 "expected result to be 5"
   {
     id: "tuples",
     name: "Tuples",
+    title: "Tuples - Ordered Collections",
+    description: "Tuples group multiple values together into a single value",
+    tags: ["collections"],
     source: `# Tuples - Ordered Collections
 # Tuples group multiple values together into a single value
+# Tags: collections
 
 # Basic tuple with two elements (a pair)
 let point = (3, 4)
 point
 
-# Empty tuple
+# The empty tuple is the unit value, nil
 ()
 
 # Single-element tuple (note the trailing comma)
@@ -251,10 +294,12 @@ matrix
 # Lists: [1, 2, 3] - homogeneous, mutable
 # Tuples: (1, 2, 3) - can be heterogeneous, immutable
 
-# Tuples are useful for returning multiple values
-# (though Cadenza doesn't have tuple destructuring yet)
+# Tuples are useful for returning multiple values,
+# which \`let\` can destructure
 let coordinates = (10, 20, 30)
 coordinates
+let (x, y, z) = coordinates
+x + y + z
 `,
   },
 ];
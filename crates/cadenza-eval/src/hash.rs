@@ -0,0 +1,397 @@
+//! Stable hashing of values.
+//!
+//! [`stable_hash`] computes a canonical 64-bit hash for hashable values. The
+//! hash is FNV-1a over a tagged encoding of the value, so it is identical
+//! across runs, platforms, and compiler versions; snapshot outputs and cache
+//! keys derived from it are deterministic.
+//!
+//! Functions, macros, constructors, and runtime handles (tasks and channels)
+//! are not hashable, nor are NaN floats (which are not equal to themselves).
+//! A value is hashable if everything it contains is.
+//!
+//! A range equals the list of its elements, so both hash by the range's
+//! bounds: a range never walks its elements, and a list of consecutive
+//! integers hashes as the range it spells.
+//!
+//! [`HashKey`] wraps a hashable value so records, tuples, and lists can be
+//! used as keys in Rust maps and sets, and `hash value` exposes the hash to
+//! Cadenza code:
+//!
+//! ```cadenza
+//! hash {x = 1, y = 2}
+//! hash (1, "a")
+//! ```
+
+use crate::{
+    diagnostic::Diagnostic,
+    value::{BuiltinFn, Type, Value},
+};
+use std::hash::{Hash, Hasher};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// An FNV-1a hasher fed with a fixed, platform-independent encoding.
+struct Fnv(u64);
+
+impl Fnv {
+    fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn tag(&mut self, tag: u8) {
+        self.bytes(&[tag]);
+    }
+
+    fn u64(&mut self, n: u64) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u64(s.len() as u64);
+        self.bytes(s.as_bytes());
+    }
+
+    fn f64(&mut self, f: f64) -> Option<()> {
+        if f.is_nan() {
+            return None;
+        }
+        // 0.0 and -0.0 compare equal, so they must hash equally
+        let f = if f == 0.0 { 0.0 } else { f };
+        self.u64(f.to_bits());
+        Some(())
+    }
+
+    fn value(&mut self, value: &Value) -> Option<()> {
+        match value {
+            Value::Nil => self.tag(0),
+            Value::Bool(b) => {
+                self.tag(1);
+                self.bytes(&[*b as u8]);
+            }
+            Value::Symbol(s) => {
+                self.tag(2);
+                self.str(s);
+            }
+            Value::Integer(n) => {
+                self.tag(3);
+                self.u64(*n as u64);
+            }
+            // Big integers are only ever outside the i64 range, so they
+            // can't collide with an equal `Integer`
+            Value::BigInt(n) => {
+                self.tag(14);
+                self.str(&n.to_string());
+            }
+            Value::Float(f) => {
+                self.tag(4);
+                self.f64(*f)?;
+            }
+            // Decimals are kept in lowest terms, so equal ones show the same
+            Value::Decimal(d) => {
+                self.tag(15);
+                self.str(&format!("{d:?}"));
+            }
+            Value::String(s) => {
+                self.tag(5);
+                self.str(s);
+            }
+            // A range equals the list of its elements, so a list that is a
+            // run of consecutive integers hashes as the range it spells;
+            // this keeps hashing a range independent of its length
+            Value::List(items) => match integer_run(items) {
+                Some((start, end)) => self.range(start, end),
+                None => {
+                    self.tag(6);
+                    self.u64(items.len() as u64);
+                    for item in items {
+                        self.value(item)?;
+                    }
+                }
+            },
+            Value::Range { start, end } => self.range(*start, *end),
+            Value::Tuple {
+                type_name,
+                elements,
+            } => {
+                self.tag(7);
+                self.type_name(type_name.as_deref());
+                self.u64(elements.len() as u64);
+                for element in elements {
+                    self.value(element)?;
+                }
+            }
+            Value::Record { type_name, fields } => {
+                self.tag(8);
+                self.type_name(type_name.as_deref());
+                self.u64(fields.len() as u64);
+                // Structural records equal in any field order, so they hash
+                // in name order; a struct's fields are always in definition
+                // order
+                let mut fields: Vec<_> = fields.iter().collect();
+                if type_name.is_none() {
+                    fields.sort_by_key(|(name, _)| &**name);
+                }
+                for (name, value) in fields {
+                    self.str(name);
+                    self.value(value)?;
+                }
+            }
+            Value::Variant {
+                ty,
+                variant,
+                fields,
+            } => {
+                self.tag(11);
+                self.str(&ty.to_string());
+                self.str(variant);
+                self.u64(fields.len() as u64);
+                for field in fields {
+                    self.value(field)?;
+                }
+            }
+            Value::Type(ty) => {
+                self.tag(9);
+                self.str(&ty.to_string());
+            }
+            Value::Quantity { value, unit, .. } => {
+                self.tag(10);
+                self.f64(*value)?;
+                self.str(&unit.name);
+            }
+            Value::Vector(elements) => {
+                self.tag(12);
+                self.u64(elements.len() as u64);
+                for element in elements {
+                    self.value(element)?;
+                }
+            }
+            Value::Matrix {
+                rows,
+                columns,
+                elements,
+            } => {
+                self.tag(13);
+                self.u64(*rows as u64);
+                self.u64(*columns as u64);
+                for element in elements {
+                    self.value(element)?;
+                }
+            }
+            Value::StructConstructor { .. }
+            | Value::VariantConstructor { .. }
+            | Value::UnitConstructor(_)
+            | Value::BuiltinFn(_)
+            | Value::BuiltinMacro(_)
+            | Value::SpecialForm(_)
+            | Value::UserFunction(_)
+            | Value::Task(_)
+            | Value::Channel(_)
+            | Value::Error => return None,
+        }
+        Some(())
+    }
+
+    fn range(&mut self, start: i64, end: i64) {
+        // Every empty range is the empty list
+        let (start, end) = if start < end { (start, end) } else { (0, 0) };
+        self.tag(16);
+        self.u64(start as u64);
+        self.u64(end as u64);
+    }
+
+    fn type_name(&mut self, name: Option<&str>) {
+        match name {
+            None => self.tag(0),
+            Some(name) => {
+                self.tag(1);
+                self.str(name);
+            }
+        }
+    }
+}
+
+/// Returns the bounds of the range `items` spells, if they are consecutive
+/// ascending integers (or empty).
+fn integer_run(items: &[Value]) -> Option<(i64, i64)> {
+    let start = match items.first() {
+        None => return Some((0, 0)),
+        Some(Value::Integer(n)) => *n,
+        Some(_) => return None,
+    };
+    let mut end = start;
+    for item in items {
+        match item {
+            Value::Integer(n) if *n == end => end = end.checked_add(1)?,
+            _ => return None,
+        }
+    }
+    Some((start, end))
+}
+
+/// Returns the stable hash of `value`, or `None` if it is not hashable.
+pub fn stable_hash(value: &Value) -> Option<u64> {
+    let mut hasher = Fnv(FNV_OFFSET);
+    hasher.value(value)?;
+    Some(hasher.0)
+}
+
+/// Returns true if `value` can be hashed.
+pub fn is_hashable(value: &Value) -> bool {
+    stable_hash(value).is_some()
+}
+
+/// A hashable value usable as a map or set key.
+///
+/// Equality is value equality, so structurally equal records and tuples
+/// (and nominal ones with the same type name) are the same key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashKey {
+    value: Value,
+    hash: u64,
+}
+
+impl HashKey {
+    /// Wraps `value`, or returns `None` if it is not hashable.
+    pub fn new(value: Value) -> Option<Self> {
+        let hash = stable_hash(&value)?;
+        Some(Self { value, hash })
+    }
+
+    /// Returns the wrapped value.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Returns the stable hash of the wrapped value.
+    pub fn stable_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Unwraps the value.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+}
+
+// Hashable values never contain NaN, so equality is reflexive.
+impl Eq for HashKey {}
+
+impl Hash for HashKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Creates the `hash` builtin: `hash value` returns the value's stable hash.
+pub fn builtin_hash() -> BuiltinFn {
+    BuiltinFn {
+        name: "hash",
+        signature: Type::function(vec![Type::Unknown], Type::Integer),
+        func: |args, _ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            match stable_hash(&args[0]) {
+                Some(hash) => Ok(Value::Integer(hash as i64)),
+                None => Err(Diagnostic::syntax(format!(
+                    "value of type {} is not hashable",
+                    args[0].type_of()
+                ))),
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn record(type_name: Option<&str>, fields: &[(&str, Value)]) -> Value {
+        Value::Record {
+            type_name: type_name.map(Into::into),
+            fields: fields
+                .iter()
+                .map(|(name, value)| ((*name).into(), value.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn hashes_are_stable() {
+        // These must never change: they end up in snapshots and cache keys
+        assert_eq!(stable_hash(&Value::Nil), Some(0xaf63bd4c8601b7df));
+        assert_eq!(stable_hash(&Value::Integer(1)), Some(0x98699ea0c41a69f3));
+    }
+
+    #[test]
+    fn equal_values_hash_equally() {
+        assert_eq!(
+            stable_hash(&Value::Float(0.0)),
+            stable_hash(&Value::Float(-0.0))
+        );
+        assert_ne!(
+            stable_hash(&Value::Integer(1)),
+            stable_hash(&Value::Float(1.0))
+        );
+        assert_ne!(
+            stable_hash(&record(None, &[("x", Value::Integer(1))])),
+            stable_hash(&record(Some("P"), &[("x", Value::Integer(1))]))
+        );
+
+        let xy = record(None, &[("x", Value::Integer(1)), ("y", Value::Integer(2))]);
+        let yx = record(None, &[("y", Value::Integer(2)), ("x", Value::Integer(1))]);
+        assert_eq!(xy, yx);
+        assert_eq!(stable_hash(&xy), stable_hash(&yx));
+
+        let range = Value::Range { start: 1, end: 3 };
+        let list = Value::List(vec![Value::Integer(1), Value::Integer(2)]);
+        assert_eq!(range, list);
+        assert_eq!(stable_hash(&range), stable_hash(&list));
+        assert_eq!(
+            stable_hash(&Value::Range { start: 3, end: 1 }),
+            stable_hash(&Value::List(vec![]))
+        );
+        assert_ne!(
+            stable_hash(&range),
+            stable_hash(&Value::List(vec![Value::Integer(2), Value::Integer(1)]))
+        );
+    }
+
+    #[test]
+    fn ranges_hash_without_iterating() {
+        // Would take minutes if the range were walked element by element
+        let range = Value::Range {
+            start: i64::MIN,
+            end: i64::MAX,
+        };
+        assert!(stable_hash(&range).is_some());
+    }
+
+    #[test]
+    fn records_and_tuples_are_set_keys() {
+        let point = record(Some("Point"), &[("x", Value::Integer(1))]);
+        let pair = Value::Tuple {
+            type_name: None,
+            elements: vec![Value::Integer(1), Value::String("a".into())],
+        };
+
+        let mut set = HashSet::new();
+        assert!(set.insert(HashKey::new(point.clone()).unwrap()));
+        assert!(set.insert(HashKey::new(pair.clone()).unwrap()));
+        assert!(!set.insert(HashKey::new(point).unwrap()));
+        assert!(set.contains(&HashKey::new(pair).unwrap()));
+    }
+
+    #[test]
+    fn functions_and_nan_are_not_hashable() {
+        assert!(!is_hashable(&Value::Float(f64::NAN)));
+        assert!(!is_hashable(&Value::List(vec![Value::BuiltinFn(
+            builtin_hash()
+        )])));
+    }
+}
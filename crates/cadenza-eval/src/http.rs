@@ -0,0 +1,310 @@
+//! HTTP client builtins.
+//!
+//! `http_get url` and `http_post url body` perform a blocking HTTP or HTTPS
+//! request and return `Ok` with a response record, or `Err` with a message.
+//! Requests require the [`Network`](crate::system::Capability::Network)
+//! capability.
+//!
+//! ```cadenza
+//! let response = http_get "https://example.com/parts"
+//! # Ok {status = 200, headers = [("content-type", "application/json")], body = "..."}
+//! ```
+//!
+//! Responses pair naturally with [`json_decode`](crate::json). Requests are
+//! made with [`ureq`], which verifies certificates against the Mozilla root
+//! store, follows redirects and gives up after [`TIMEOUT`]. Error statuses
+//! are responses like any other; only failing to get a response is an
+//! `Err`. Bodies are read up to ureq's 10 MB limit. WebAssembly has no
+//! sockets, so there every request is an `Err`.
+//!
+//! The module and both builtins need the `http` feature, so embedders that
+//! don't make requests don't depend on ureq.
+
+use crate::{
+    diagnostic::Diagnostic,
+    system::{Capability, denied, err, expect_string, ok},
+    value::{BuiltinFn, Type, Value},
+};
+use std::time::Duration;
+
+/// How long a whole request, from connecting to reading the body, may take.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A parsed HTTP response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// The status code.
+    pub status: u16,
+    /// Header names (lowercased) and values, grouped by name in the order
+    /// each name first appeared.
+    pub headers: Vec<(String, String)>,
+    /// The decoded body.
+    pub body: String,
+}
+
+impl Response {
+    /// Converts the response into a `{status, headers, body}` record.
+    pub fn into_value(self) -> Value {
+        let headers = self
+            .headers
+            .into_iter()
+            .map(|(name, value)| Value::Tuple {
+                type_name: None,
+                elements: vec![Value::String(name.into()), Value::String(value.into())],
+            })
+            .collect();
+        Value::Record {
+            type_name: None,
+            fields: vec![
+                ("status".into(), Value::Integer(self.status as i64)),
+                ("headers".into(), Value::List(headers)),
+                ("body".into(), Value::String(self.body.into())),
+            ],
+        }
+    }
+}
+
+/// Sends a request and reads the full response.
+pub fn request(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+) -> std::result::Result<Response, String> {
+    request_within(method, url, body, TIMEOUT)
+}
+
+/// Sends a request that gives up after `timeout`.
+#[cfg(not(target_family = "wasm"))]
+fn request_within(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    timeout: Duration,
+) -> std::result::Result<Response, String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .user_agent("cadenza")
+        .build()
+        .into();
+    let response = match (method, body) {
+        ("GET", None) => agent.get(url).call(),
+        ("POST", body) => agent.post(url).send(body.unwrap_or_default()),
+        _ => return Err(format!("{url}: unsupported method {method}")),
+    };
+    let mut response = response.map_err(|e| format!("{url}: {e}"))?;
+
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            (name.as_str().to_string(), value)
+        })
+        .collect();
+    let body = response
+        .body_mut()
+        .read_to_vec()
+        .map_err(|e| format!("{url}: {e}"))?;
+    Ok(Response {
+        status: response.status().as_u16(),
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+/// Fails: WebAssembly has no sockets to send a request on.
+#[cfg(target_family = "wasm")]
+fn request_within(
+    _method: &str,
+    url: &str,
+    _body: Option<&str>,
+    _timeout: Duration,
+) -> std::result::Result<Response, String> {
+    Err(format!(
+        "{url}: HTTP requests are not available in WebAssembly"
+    ))
+}
+
+fn respond(method: &str, url: &str, body: Option<&str>) -> Value {
+    match request(method, url, body) {
+        Ok(response) => ok(response.into_value()),
+        Err(message) => err(message),
+    }
+}
+
+/// Creates the `http_get` builtin: `http_get url`.
+pub fn builtin_http_get() -> BuiltinFn {
+    BuiltinFn {
+        name: "http_get",
+        signature: Type::function(vec![Type::String], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let url = expect_string(&args[0])?;
+            if !ctx.compiler.capabilities().allows(Capability::Network) {
+                return Ok(denied(Capability::Network, "http_get"));
+            }
+            Ok(respond("GET", url, None))
+        },
+    }
+}
+
+/// Creates the `http_post` builtin: `http_post url body`.
+pub fn builtin_http_post() -> BuiltinFn {
+    BuiltinFn {
+        name: "http_post",
+        signature: Type::function(vec![Type::String, Type::String], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 2 {
+                return Err(Diagnostic::arity(2, args.len()));
+            }
+            let url = expect_string(&args[0])?;
+            let body = expect_string(&args[1])?;
+            if !ctx.compiler.capabilities().allows(Capability::Network) {
+                return Ok(denied(Capability::Network, "http_post"));
+            }
+            Ok(respond("POST", url, Some(body)))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// Serves a single canned response on `address` and returns the URL and
+    /// the request it received.
+    fn serve_once(
+        address: &str,
+        response: &'static str,
+    ) -> Option<(String, thread::JoinHandle<String>)> {
+        let listener = TcpListener::bind(address).ok()?;
+        let url = format!("http://{}/data", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0; 4096];
+            while !is_complete(&received) {
+                let n = stream.read(&mut buf).unwrap();
+                assert!(n > 0, "connection closed mid-request");
+                received.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&received).into_owned()
+        });
+        Some((url, handle))
+    }
+
+    /// Returns true once a full request (headers plus any declared body) has arrived.
+    fn is_complete(raw: &[u8]) -> bool {
+        let text = String::from_utf8_lossy(raw);
+        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let len = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().to_string())
+            })
+            .map_or(0, |len| len.parse().unwrap());
+        body.len() >= len
+    }
+
+    #[test]
+    fn get_reads_status_headers_and_body() {
+        let (url, server) = serve_once(
+            "127.0.0.1:0",
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello",
+        )
+        .unwrap();
+        let response = request("GET", &url, None).unwrap();
+        assert!(server.join().unwrap().starts_with("GET /data HTTP/1.1\r\n"));
+
+        assert_eq!(response.status, 200);
+        assert!(
+            response
+                .headers
+                .contains(&("content-type".to_string(), "text/plain".to_string())),
+            "{:?}",
+            response.headers
+        );
+        assert_eq!(response.body, "hello");
+    }
+
+    #[test]
+    fn post_sends_body_and_decodes_chunks() {
+        let (url, server) = serve_once(
+            "127.0.0.1:0",
+            "HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        let response = request("POST", &url, Some("payload")).unwrap();
+        let received = server.join().unwrap();
+        assert!(
+            received
+                .to_ascii_lowercase()
+                .contains("content-length: 7\r\n"),
+            "{received}"
+        );
+        assert!(received.ends_with("payload"), "{received}");
+
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, "abcde");
+    }
+
+    #[test]
+    fn error_statuses_are_responses() {
+        let (url, server) = serve_once(
+            "127.0.0.1:0",
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 7\r\n\r\nmissing",
+        )
+        .unwrap();
+        let response = request("GET", &url, None).unwrap();
+        server.join().unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.body, "missing");
+    }
+
+    #[test]
+    fn ipv6_hosts_keep_their_port() {
+        // Hosts without IPv6 can't run this
+        let Some((url, server)) =
+            serve_once("[::1]:0", "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nv6")
+        else {
+            return;
+        };
+        assert!(url.starts_with("http://[::1]:"), "{url}");
+        let response = request("GET", &url, None).unwrap();
+        server.join().unwrap();
+        assert_eq!(response.body, "v6");
+    }
+
+    #[test]
+    fn gives_up_after_the_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/slow", listener.local_addr().unwrap());
+        // Accept the connection but never answer
+        let server = thread::spawn(move || listener.accept().map(|(stream, _)| stream));
+
+        let started = std::time::Instant::now();
+        let result = request_within("GET", &url, None, Duration::from_millis(200));
+        assert!(result.is_err(), "{result:?}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+        drop(server.join());
+    }
+
+    #[test]
+    fn rejects_unsupported_urls() {
+        assert!(request("GET", "ftp://example.com", None).is_err());
+        assert!(request("GET", "not a url", None).is_err());
+    }
+}
@@ -14,9 +14,11 @@
 // Re-export string interning from cadenza-tree
 pub use cadenza_tree::interner::{Interned, InternedString, Storage, Strings};
 
+use crate::{bigint::BigInt, value::Value};
 use std::{
+    borrow::Cow,
     num::{ParseFloatError, ParseIntError},
-    sync::OnceLock,
+    sync::{LazyLock, OnceLock},
 };
 
 // =============================================================================
@@ -59,9 +61,7 @@ impl IntegerData {
             return index;
         }
         let index = self.values.len() as u32;
-        // Parse the integer, removing underscores
-        let clean = s.replace('_', "");
-        let value = clean.parse::<i64>();
+        let value = parse_integer(s);
         self.values.push(value);
         self.map.insert(s.to_string(), index);
         index
@@ -72,6 +72,63 @@ impl IntegerData {
     }
 }
 
+/// Parses an integer literal: decimal, or hex, octal or binary after a `0x`,
+/// `0o` or `0b` prefix, with `_` separating digits anywhere.
+pub fn parse_integer(s: &str) -> Result<i64, ParseIntError> {
+    let clean = without_underscores(s);
+    let (digits, radix) = split_radix(&clean);
+    i64::from_str_radix(digits, radix)
+}
+
+/// Parses an integer literal like [`parse_integer`], at any size.
+///
+/// Returns `None` if the literal isn't a valid integer.
+pub fn parse_big_integer(s: &str) -> Option<BigInt> {
+    let clean = without_underscores(s);
+    let (digits, radix) = split_radix(&clean);
+    BigInt::parse(digits, radix)
+}
+
+/// Removes digit separators from a numeric literal.
+///
+/// Most literals have none, so they are parsed in place without allocating.
+pub(crate) fn without_underscores(text: &str) -> Cow<'_, str> {
+    if text.contains('_') {
+        Cow::Owned(text.replace('_', ""))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// The largest integer in the small-integer table.
+const SMALL_INTEGER_MAX: usize = 256;
+
+/// Shared values for the integers 0 to [`SMALL_INTEGER_MAX`].
+static SMALL_INTEGERS: LazyLock<Vec<Value>> =
+    LazyLock::new(|| (0..=SMALL_INTEGER_MAX as i64).map(Value::Integer).collect());
+
+/// Returns the shared value for a plain decimal literal from 0 to 256.
+///
+/// Returns `None` for larger literals and for ones with a radix prefix or
+/// digit separators, which go through [`parse_integer`].
+pub fn small_integer(s: &str) -> Option<&'static Value> {
+    if s.is_empty() || s.len() > 3 || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n = s.bytes().fold(0, |n, b| n * 10 + usize::from(b - b'0'));
+    SMALL_INTEGERS.get(n)
+}
+
+/// Splits an integer literal's radix prefix from its digits.
+fn split_radix(s: &str) -> (&str, u32) {
+    match s.get(..2) {
+        Some("0x") => (&s[2..], 16),
+        Some("0o") => (&s[2..], 8),
+        Some("0b") => (&s[2..], 2),
+        _ => (s, 10),
+    }
+}
+
 static INTEGER_STORAGE: OnceLock<std::sync::Mutex<IntegerData>> = OnceLock::new();
 
 fn integer_storage() -> &'static std::sync::Mutex<IntegerData> {
@@ -133,9 +190,7 @@ impl FloatData {
             return index;
         }
         let index = self.values.len() as u32;
-        // Parse the float, removing underscores
-        let clean = s.replace('_', "");
-        let value = clean.parse::<f64>();
+        let value = without_underscores(s).parse::<f64>();
         self.values.push(value);
         self.map.insert(s.to_string(), index);
         index
@@ -249,6 +304,15 @@ mod tests {
             assert_eq!(*i, Ok(1_000_000));
         }
 
+        #[test]
+        fn handles_radix_prefixes() {
+            let parsed = ["0xFF", "0o755", "0b1010", "0xdead_beef"]
+                .map(|s| *InternedInteger::from(s).as_ref().unwrap());
+            assert_eq!(parsed, [0xFF, 0o755, 0b1010, 0xdead_beef]);
+            let i: InternedInteger = "0b102".into();
+            assert!(i.is_err());
+        }
+
         #[test]
         fn returns_err_for_invalid() {
             let i: InternedInteger = "not_a_number".into();
@@ -265,6 +329,19 @@ mod tests {
             assert_eq!(i1.index(), i2.index());
         }
 
+        #[test]
+        fn small_integers_are_shared() {
+            assert_eq!(small_integer("0"), Some(&Value::Integer(0)));
+            assert_eq!(small_integer("256"), Some(&Value::Integer(256)));
+            assert!(std::ptr::eq(
+                small_integer("42").unwrap(),
+                small_integer("042").unwrap()
+            ));
+            for literal in ["257", "1000", "0x1", "1_0", "", "-1"] {
+                assert_eq!(small_integer(literal), None, "{literal}");
+            }
+        }
+
         #[test]
         fn handles_negative() {
             let i: InternedInteger = "-42".into();
@@ -4,14 +4,25 @@
 //! The IR is target-independent and designed for WASM code generation with WasmGC.
 
 mod builder;
+mod call_graph;
 mod generator;
+pub mod host;
+mod lambda_lift;
+mod link;
 mod optimize;
+mod ownership;
+mod peephole;
+mod size_report;
 mod types;
 mod wasm;
 
 pub use builder::*;
+pub use call_graph::{CallEdge, CallGraph, Recursion};
 pub use generator::*;
+pub use lambda_lift::LiftedFunction;
+pub use link::{LinkError, link};
 pub use optimize::*;
+pub use size_report::{SizeEntry, SizeKind, SizeReport};
 pub use types::*;
 pub use wasm::*;
 
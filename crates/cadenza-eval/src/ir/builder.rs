@@ -4,7 +4,7 @@
 //! functions, and basic blocks. It handles automatic ID assignment and provides
 //! methods for emitting instructions.
 
-use super::types::*;
+use super::{host::HostFunction, types::*};
 use crate::{InternedString, Type};
 use std::sync::Arc;
 
@@ -12,6 +12,7 @@ use std::sync::Arc;
 pub struct IrBuilder {
     module: IrModule,
     next_function_id: u32,
+    next_global_id: u32,
 }
 
 impl IrBuilder {
@@ -20,6 +21,7 @@ impl IrBuilder {
         Self {
             module: IrModule::new(),
             next_function_id: 0,
+            next_global_id: 0,
         }
     }
 
@@ -30,9 +32,27 @@ impl IrBuilder {
         params: Vec<(InternedString, Type)>,
         return_ty: Type,
     ) -> FunctionBuilder {
+        let id = self.declare_function();
+        self.define_function(id, name, params, return_ty)
+    }
+
+    /// Reserve the ID of a function to be built later with
+    /// [`define_function`](Self::define_function), so calls can refer to it
+    /// first.
+    pub fn declare_function(&mut self) -> FunctionId {
         let id = FunctionId(self.next_function_id);
         self.next_function_id += 1;
+        id
+    }
 
+    /// Start building the function declared as `id`.
+    pub fn define_function(
+        &mut self,
+        id: FunctionId,
+        name: InternedString,
+        params: Vec<(InternedString, Type)>,
+        return_ty: Type,
+    ) -> FunctionBuilder {
         let param_count = params.len() as u32;
 
         FunctionBuilder {
@@ -40,6 +60,7 @@ impl IrBuilder {
             name,
             params,
             return_ty,
+            fallible: false,
             blocks: Vec::new(),
             next_block_id: 0,
             next_value_id: param_count, // Parameters use first N value IDs
@@ -53,6 +74,42 @@ impl IrBuilder {
         id
     }
 
+    /// Import the host function `function`, or return its ID if it is
+    /// already imported.
+    pub fn import(&mut self, function: &'static HostFunction) -> FunctionId {
+        if let Some(import) = self
+            .module
+            .imports
+            .iter()
+            .find(|import| import.function == function)
+        {
+            return import.id;
+        }
+        let id = FunctionId(self.next_function_id);
+        self.next_function_id += 1;
+        self.module.imports.push(IrImport { id, function });
+        id
+    }
+
+    /// Record the function declared as `id` as the extern `name`, defined by
+    /// another module and resolved when the modules are linked.
+    pub fn extern_function(&mut self, id: FunctionId, name: InternedString) {
+        self.module.externs.push(IrExtern { id, name });
+    }
+
+    /// Add a module-level constant named `name`.
+    pub fn global(&mut self, name: InternedString, ty: Type, value: IrConst) -> GlobalId {
+        let id = GlobalId(self.next_global_id);
+        self.next_global_id += 1;
+        self.module.globals.push(IrGlobal {
+            id,
+            name,
+            ty,
+            value,
+        });
+        id
+    }
+
     /// Export a function.
     pub fn export_function(&mut self, name: InternedString, func_id: FunctionId) {
         self.module.exports.push(IrExport {
@@ -62,10 +119,10 @@ impl IrBuilder {
     }
 
     /// Export a constant.
-    pub fn export_constant(&mut self, name: InternedString, value_id: ValueId) {
+    pub fn export_constant(&mut self, name: InternedString, global: GlobalId) {
         self.module.exports.push(IrExport {
             name,
-            kind: IrExportKind::Constant(value_id),
+            kind: IrExportKind::Constant(global),
         });
     }
 
@@ -92,6 +149,7 @@ pub struct FunctionBuilder {
     name: InternedString,
     params: Vec<(InternedString, Type)>,
     return_ty: Type,
+    fallible: bool,
     blocks: Vec<IrBlock>,
     next_block_id: u32,
     next_value_id: u32,
@@ -103,6 +161,12 @@ impl FunctionBuilder {
         self.id
     }
 
+    /// Marks the function as fallible, so it can fail and call fallible
+    /// functions.
+    pub fn set_fallible(&mut self) {
+        self.fallible = true;
+    }
+
     /// Get the next block ID that will be allocated.
     #[allow(dead_code)]
     pub(crate) fn next_block_id(&self) -> u32 {
@@ -154,6 +218,37 @@ impl FunctionBuilder {
         id
     }
 
+    /// Add the incoming `value` from the block `from` to the phi `phi` of the
+    /// completed block `block`.
+    ///
+    /// A loop header is completed before the body that jumps back to it, so
+    /// its phis get their values from the body afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block hasn't been added or has no such phi.
+    pub fn add_phi_incoming(
+        &mut self,
+        block: BlockId,
+        phi: ValueId,
+        value: ValueId,
+        from: BlockId,
+    ) {
+        let incoming = self
+            .blocks
+            .iter_mut()
+            .filter(|candidate| candidate.id == block)
+            .flat_map(|block| &mut block.instructions)
+            .find_map(|instr| match instr {
+                IrInstr::Phi {
+                    result, incoming, ..
+                } if *result == phi => Some(incoming),
+                _ => None,
+            })
+            .expect("phi not found in a completed block");
+        incoming.push((value, from));
+    }
+
     /// Build and return the final IR function.
     /// The first block added becomes the entry block.
     ///
@@ -175,6 +270,7 @@ impl FunctionBuilder {
                 name,
                 ty,
                 value_id: ValueId(i as u32),
+                dimension: None,
             })
             .collect();
 
@@ -183,6 +279,8 @@ impl FunctionBuilder {
             name: self.name,
             params,
             return_ty: self.return_ty,
+            return_dimension: None,
+            fallible: self.fallible,
             blocks: self.blocks,
             entry_block,
         }
@@ -221,6 +319,18 @@ impl BlockBuilder {
         result
     }
 
+    /// Emit a read of the module-level constant `global`.
+    pub fn global(&mut self, global: GlobalId, ty: Type, source: SourceLocation) -> ValueId {
+        let result = self.alloc_value();
+        self.instructions.push(IrInstr::Global {
+            result,
+            ty,
+            global,
+            source,
+        });
+        result
+    }
+
     /// Emit a binary operation.
     pub fn binop(
         &mut self,
@@ -357,6 +467,32 @@ impl BlockBuilder {
         });
         result
     }
+
+    /// Emit a check that traps with `message` unless `cond` is true.
+    pub fn assert(&mut self, cond: ValueId, message: InternedString, source: SourceLocation) {
+        self.instructions.push(IrInstr::Assert {
+            cond,
+            message,
+            source,
+        });
+    }
+
+    /// Emit a check that the divisor `rhs` isn't zero, which traps with the
+    /// evaluator's "division by zero" error.
+    ///
+    /// `ty` is the type of the divisor: a float is compared with `0.0`, and
+    /// anything else with the integer `0`, as the code generator treats
+    /// unknown types as integers.
+    pub fn check_divisor(&mut self, rhs: ValueId, ty: &Type, source: SourceLocation) {
+        let (zero, ty) = match ty {
+            Type::Float => (IrConst::Float(0.0), Type::Float),
+            _ => (IrConst::Integer(0), Type::Integer),
+        };
+        let zero = self.const_val(zero, ty.clone(), source);
+        let nonzero = self.binop(BinOp::Ne, rhs, zero, ty, source);
+        self.assert(nonzero, "division by zero".into(), source);
+    }
+
     /// Complete the block with a return terminator.
     /// Returns the block and the next value ID to use.
     pub fn ret(self, value: Option<ValueId>, source: SourceLocation) -> (IrBlock, u32) {
@@ -371,6 +507,21 @@ impl BlockBuilder {
         )
     }
 
+    /// Complete the block with a failure: the function returns the error
+    /// `message` instead of a value.
+    /// Returns the block and the next value ID to use.
+    pub fn fail(self, message: InternedString, source: SourceLocation) -> (IrBlock, u32) {
+        let next_value_id = self.next_value_id;
+        (
+            IrBlock {
+                id: self.id,
+                instructions: self.instructions,
+                terminator: IrTerminator::Fail { message, source },
+            },
+            next_value_id,
+        )
+    }
+
     /// Complete the block with a conditional branch.
     /// Returns the block and the next value ID to use.
     pub fn branch(
@@ -0,0 +1,339 @@
+//! Call graph analysis: worst-case stack depth and unbounded recursion.
+//!
+//! Generated modules often run where the stack is small, such as embedded
+//! hosts and browsers, so the compiler bounds how deep calls can nest. A
+//! [`CallGraph`] has an edge for every call from one module function to
+//! another; calls to the host don't use the module's stack and are left out.
+//!
+//! A tail call replaces its caller's frame instead of adding one, so it
+//! doesn't deepen the stack. Only the calls the code generator emits as
+//! `return_call` count as tail calls: a call whose result is returned right
+//! away, outside any branch, to a function that is fallible exactly when its
+//! caller is (see [`IrFunction::fallible`]).
+//!
+//! Recursion through tail calls alone runs in constant stack space. Any other
+//! cycle in the graph can recurse without bound, which
+//! [`CallGraph::unbounded_recursion`] reports with the cycle it found.
+
+use super::{BlockId, FunctionId, IrFunction, IrInstr, IrModule, IrTerminator, SourceLocation};
+use crate::{
+    InternedString,
+    diagnostic::{Diagnostic, StackFrame},
+};
+use std::collections::{HashMap, HashSet};
+
+/// A call from one module function to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEdge {
+    pub callee: FunctionId,
+    /// Whether the call replaces the caller's frame.
+    pub tail: bool,
+    pub source: SourceLocation,
+}
+
+/// The calls between the functions of a module.
+#[derive(Debug, Clone)]
+pub struct CallGraph {
+    /// The functions, in module order.
+    functions: Vec<FunctionId>,
+    names: HashMap<FunctionId, InternedString>,
+    calls: HashMap<FunctionId, Vec<CallEdge>>,
+    /// The strongly connected components, callees before their callers.
+    components: Vec<Vec<FunctionId>>,
+}
+
+/// A cycle of calls that can recurse without bound.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recursion {
+    /// The functions in the cycle, in call order, starting and ending with
+    /// the same function.
+    pub cycle: Vec<InternedString>,
+    /// Where each function in the cycle calls the next.
+    pub calls: Vec<SourceLocation>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of `module`.
+    pub fn new(module: &IrModule) -> Self {
+        let fallible: HashMap<FunctionId, bool> = module
+            .functions
+            .iter()
+            .map(|func| (func.id, func.fallible))
+            .collect();
+        let mut calls = HashMap::new();
+        for func in &module.functions {
+            let tails = tail_calls(func);
+            let edges = func
+                .blocks
+                .iter()
+                .flat_map(|block| {
+                    block
+                        .instructions
+                        .iter()
+                        .enumerate()
+                        .map(move |(i, instr)| (block, i, instr))
+                })
+                .filter_map(|(block, i, instr)| {
+                    let IrInstr::Call {
+                        func: callee,
+                        source,
+                        ..
+                    } = instr
+                    else {
+                        return None;
+                    };
+                    // Host functions don't run on the module's stack
+                    let callee_fallible = *fallible.get(callee)?;
+                    Some(CallEdge {
+                        callee: *callee,
+                        tail: tails.contains(&(block.id, i)) && callee_fallible == func.fallible,
+                        source: *source,
+                    })
+                })
+                .collect();
+            calls.insert(func.id, edges);
+        }
+
+        let functions: Vec<_> = module.functions.iter().map(|func| func.id).collect();
+        let names = module
+            .functions
+            .iter()
+            .map(|func| (func.id, func.name))
+            .collect();
+        let components = strongly_connected_components(&functions, &calls);
+        Self {
+            functions,
+            names,
+            calls,
+            components,
+        }
+    }
+
+    /// Returns the calls `function` makes to other module functions.
+    pub fn calls(&self, function: FunctionId) -> &[CallEdge] {
+        self.calls.get(&function).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the most frames that can be on the stack while each function
+    /// runs, counting its own, or `None` for a function that can recurse
+    /// without bound.
+    pub fn max_depths(&self) -> HashMap<FunctionId, Option<usize>> {
+        let mut depths: HashMap<FunctionId, Option<usize>> = HashMap::new();
+        for component in &self.components {
+            let members: HashSet<_> = component.iter().copied().collect();
+            let mut depth = Some(1);
+            for &function in component {
+                for call in self.calls(function) {
+                    let callee = if members.contains(&call.callee) {
+                        // A tail call within the component reuses the frame;
+                        // any other call can repeat forever
+                        if call.tail { Some(1) } else { None }
+                    } else if call.tail {
+                        depths[&call.callee]
+                    } else {
+                        depths[&call.callee].map(|depth| depth + 1)
+                    };
+                    depth = depth.zip(callee).map(|(a, b)| a.max(b));
+                }
+            }
+            for &function in component {
+                depths.insert(function, depth);
+            }
+        }
+        depths
+    }
+
+    /// Returns the most frames that can be on the stack while `function`
+    /// runs, counting its own, or `None` if it can recurse without bound.
+    pub fn max_depth(&self, function: FunctionId) -> Option<usize> {
+        self.max_depths().get(&function).copied().flatten()
+    }
+
+    /// Returns a cycle through a call that isn't a tail call for each group
+    /// of mutually recursive functions that has one, in module order.
+    pub fn unbounded_recursion(&self) -> Vec<Recursion> {
+        let order: HashMap<_, _> = self
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect();
+        let mut found = vec![];
+        for component in &self.components {
+            let members: HashSet<_> = component.iter().copied().collect();
+            // Start from the first function in module order with a non-tail
+            // call back into the group
+            let mut starts: Vec<_> = component.clone();
+            starts.sort_by_key(|id| order[id]);
+            let Some((caller, call)) = starts.iter().find_map(|&caller| {
+                self.calls(caller)
+                    .iter()
+                    .find(|call| !call.tail && members.contains(&call.callee))
+                    .map(|call| (caller, call))
+            }) else {
+                continue;
+            };
+            let mut cycle = vec![self.names[&caller]];
+            let mut calls = vec![call.source];
+            for (function, call) in self.path(call.callee, caller, &members) {
+                cycle.push(self.names[&function]);
+                calls.extend(call.map(|call| call.source));
+            }
+            found.push((order[&caller], Recursion { cycle, calls }));
+        }
+        found.sort_by_key(|(order, _)| *order);
+        found.into_iter().map(|(_, recursion)| recursion).collect()
+    }
+
+    /// Returns the shortest path of calls from `from` to `to` within
+    /// `members`: each function on the way with the call that leaves it,
+    /// ending with `to`.
+    fn path(
+        &self,
+        from: FunctionId,
+        to: FunctionId,
+        members: &HashSet<FunctionId>,
+    ) -> Vec<(FunctionId, Option<&CallEdge>)> {
+        let mut previous: HashMap<FunctionId, (FunctionId, &CallEdge)> = HashMap::new();
+        let mut queue = std::collections::VecDeque::from([from]);
+        let mut seen = HashSet::from([from]);
+        while let Some(function) = queue.pop_front() {
+            if function == to {
+                break;
+            }
+            for call in self.calls(function) {
+                if members.contains(&call.callee) && seen.insert(call.callee) {
+                    previous.insert(call.callee, (function, call));
+                    queue.push_back(call.callee);
+                }
+            }
+        }
+
+        let mut path = vec![(to, None)];
+        let mut function = to;
+        while function != from {
+            let (caller, call) = previous[&function];
+            path.push((caller, Some(call)));
+            function = caller;
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl Recursion {
+    /// Returns the warning for this recursion, at the call that starts it,
+    /// with a frame for each call in the cycle.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = *Diagnostic::unbounded_recursion(&self.cycle);
+        let start = self.calls[0];
+        diagnostic.file = Some(start.file);
+        diagnostic.span = Some(start.span);
+        for (name, call) in self.cycle.iter().zip(&self.calls) {
+            diagnostic.push_frame(StackFrame::new(
+                Some(*name),
+                Some(call.file),
+                Some(call.span),
+            ));
+        }
+        diagnostic
+    }
+}
+
+/// Returns the calls in `func` that the code generator emits as tail calls,
+/// if their callee's fallibility matches, by block and instruction index.
+///
+/// Blocks inside a branch don't have them, so these are the blocks reached
+/// from the entry through jumps alone.
+fn tail_calls(func: &IrFunction) -> HashSet<(BlockId, usize)> {
+    let blocks: HashMap<_, _> = func.blocks.iter().map(|block| (block.id, block)).collect();
+    let mut tails = HashSet::new();
+    let mut seen = HashSet::new();
+    let mut next = Some(func.entry_block);
+    while let Some(id) = next.take() {
+        let Some(block) = blocks.get(&id) else {
+            break;
+        };
+        if !seen.insert(id) {
+            break;
+        }
+        match &block.terminator {
+            IrTerminator::Return { value, .. } => {
+                if let Some(IrInstr::Call { result, .. }) = block.instructions.last()
+                    && result == value
+                {
+                    tails.insert((id, block.instructions.len() - 1));
+                }
+            }
+            IrTerminator::Jump { target, .. } => next = Some(*target),
+            IrTerminator::Branch { .. } | IrTerminator::Fail { .. } => {}
+        }
+    }
+    tails
+}
+
+/// Returns the strongly connected components of the call graph, each
+/// component after the ones it calls (Tarjan's algorithm).
+fn strongly_connected_components(
+    functions: &[FunctionId],
+    calls: &HashMap<FunctionId, Vec<CallEdge>>,
+) -> Vec<Vec<FunctionId>> {
+    struct State<'a> {
+        calls: &'a HashMap<FunctionId, Vec<CallEdge>>,
+        index: HashMap<FunctionId, usize>,
+        lowlink: HashMap<FunctionId, usize>,
+        stack: Vec<FunctionId>,
+        on_stack: HashSet<FunctionId>,
+        components: Vec<Vec<FunctionId>>,
+    }
+
+    fn visit(state: &mut State, function: FunctionId) {
+        let index = state.index.len();
+        state.index.insert(function, index);
+        state.lowlink.insert(function, index);
+        state.stack.push(function);
+        state.on_stack.insert(function);
+
+        let callees: Vec<_> = state.calls[&function]
+            .iter()
+            .map(|call| call.callee)
+            .collect();
+        for callee in callees {
+            if !state.index.contains_key(&callee) {
+                visit(state, callee);
+                let low = state.lowlink[&function].min(state.lowlink[&callee]);
+                state.lowlink.insert(function, low);
+            } else if state.on_stack.contains(&callee) {
+                let low = state.lowlink[&function].min(state.index[&callee]);
+                state.lowlink.insert(function, low);
+            }
+        }
+
+        if state.lowlink[&function] == index {
+            let mut component = vec![];
+            while let Some(member) = state.stack.pop() {
+                state.on_stack.remove(&member);
+                component.push(member);
+                if member == function {
+                    break;
+                }
+            }
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        calls,
+        index: HashMap::new(),
+        lowlink: HashMap::new(),
+        stack: vec![],
+        on_stack: HashSet::new(),
+        components: vec![],
+    };
+    for &function in functions {
+        if !state.index.contains_key(&function) {
+            visit(&mut state, function);
+        }
+    }
+    state.components
+}
@@ -7,19 +7,30 @@
 //! we work with Values rather than AST nodes, making the transformation simpler.
 
 use super::{
-    BinOp as IrBinOp, BlockBuilder, BlockId, FunctionBuilder, FunctionId, IrBlock, IrBuilder,
-    IrConst, SourceLocation, ValueId,
+    BinOp as IrBinOp, BlockBuilder, BlockId, FunctionBuilder, FunctionId, GlobalId, IrBlock,
+    IrBuilder, IrConst, SourceLocation, UnOp as IrUnOp, ValueId, host,
+    lambda_lift::{Capture, LiftedFunction, env_type, free_variables},
+    ownership::insert_deleters,
 };
 use crate::{
-    diagnostic::{Diagnostic, Result},
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
     env::Env,
-    interner::InternedString,
-    special_form,
+    eval::extract_identifier,
+    interner::{InternedString, parse_integer},
+    linalg::{self, Shape},
+    module_graph::{FunctionDef, function_definition},
+    result, special_form,
     typeinfer::{InferType, TypeEnv, TypeInferencer},
+    unit::{DerivedDimension, Unit, UnitRegistry},
     value::{Type, UserFunction, Value},
 };
-use cadenza_syntax::ast::Expr;
-use std::collections::HashMap;
+use cadenza_syntax::{SyntaxNode, ast::Expr, span::Span};
+use cadenza_tree::SourceFile;
+use std::{
+    collections::{HashMap, HashSet},
+    num::IntErrorKind,
+    sync::Arc,
+};
 
 /// Context for IR generation from AST.
 ///
@@ -33,6 +44,22 @@ pub struct IrGenContext<'a> {
     env: &'a Env,
     /// Maps SSA value IDs to their inferred types.
     value_types: HashMap<ValueId, Type>,
+    /// Maps the SSA values holding quantities to their dimensions.
+    dimensions: HashMap<ValueId, DerivedDimension>,
+    /// Maps the SSA values holding vectors and matrices to their shapes.
+    shapes: HashMap<ValueId, Shape>,
+    /// The parameters of the function being generated.
+    params: HashSet<ValueId>,
+    /// The IR function being generated, if any.
+    function: Option<InternedString>,
+    /// Nested functions in scope, by their name in the source.
+    local_functions: HashMap<InternedString, LiftedFunction>,
+    /// Variables whose linear values have moved elsewhere.
+    moved: HashSet<InternedString>,
+    /// The closure environments of the function being generated.
+    environments: Vec<ValueId>,
+    /// Whether the function being generated can fail.
+    fallible: bool,
 }
 
 impl<'a> IrGenContext<'a> {
@@ -43,13 +70,109 @@ impl<'a> IrGenContext<'a> {
             type_env: TypeEnv::new(),
             env,
             value_types: HashMap::new(),
+            dimensions: HashMap::new(),
+            shapes: HashMap::new(),
+            params: HashSet::new(),
+            function: None,
+            local_functions: HashMap::new(),
+            moved: HashSet::new(),
+            environments: Vec::new(),
+            fallible: false,
         }
     }
 
+    /// Marks the function being generated as fallible (see
+    /// [`IrFunction::fallible`](super::IrFunction::fallible)).
+    pub fn set_fallible(&mut self) {
+        self.fallible = true;
+    }
+
     /// Bind a variable name to an SSA value and add its type to the environment.
     pub fn bind_var(&mut self, name: InternedString, value: ValueId, ty: &InferType) {
         self.variables.insert(name, value);
         self.type_env.insert(name, ty.clone());
+        self.moved.remove(&name);
+    }
+
+    /// Returns the current bindings of `names`, for [`Self::restore_vars`] to
+    /// put back when a scope that may shadow them ends.
+    pub fn save_vars(&self, names: &[InternedString]) -> Vec<SavedVar> {
+        names
+            .iter()
+            .map(|&name| SavedVar {
+                name,
+                value: self.variables.get(&name).copied(),
+                ty: self.type_env.get(name).cloned(),
+            })
+            .collect()
+    }
+
+    /// Puts back the bindings saved by [`Self::save_vars`], unbinding the
+    /// names that weren't bound then.
+    pub fn restore_vars(&mut self, saved: Vec<SavedVar>) {
+        for SavedVar { name, value, ty } in saved {
+            match value {
+                Some(value) => self.variables.insert(name, value),
+                None => self.variables.remove(&name),
+            };
+            match ty {
+                Some(ty) => self.type_env.insert(name, ty),
+                None => {
+                    self.type_env.remove(name);
+                }
+            }
+        }
+    }
+
+    /// Records that `expr` moves its value, if it names a variable holding a
+    /// linear value.
+    ///
+    /// Using the variable afterwards is a use-after-move error.
+    pub fn consume(&mut self, expr: &Expr) {
+        let Expr::Ident(ident) = expr else {
+            return;
+        };
+        let name = ident.syntax().text().interned();
+        let is_linear = self
+            .lookup_var(name)
+            .and_then(|value| self.get_value_type(value))
+            .is_some_and(Type::is_linear);
+        if is_linear {
+            self.moved.insert(name);
+        }
+    }
+
+    /// Returns true if the value of the variable `name` has moved.
+    pub fn is_moved(&self, name: InternedString) -> bool {
+        self.moved.contains(&name)
+    }
+
+    /// Returns the variables that have moved so far.
+    ///
+    /// Control flow forms generate each branch from the same state and merge
+    /// the results with [`Self::set_moved_vars`]: a variable moved on any path
+    /// counts as moved.
+    pub fn moved_vars(&self) -> HashSet<InternedString> {
+        self.moved.clone()
+    }
+
+    /// Replaces the set of moved variables.
+    pub fn set_moved_vars(&mut self, moved: HashSet<InternedString>) {
+        self.moved = moved;
+    }
+
+    /// Returns the SSA values the variables are bound to.
+    ///
+    /// Control flow forms generate each branch from the same bindings and
+    /// join the variables a branch reassigns with phis, replacing the bindings
+    /// with [`Self::set_bindings`].
+    pub fn bindings(&self) -> HashMap<InternedString, ValueId> {
+        self.variables.clone()
+    }
+
+    /// Replaces the variable bindings.
+    pub fn set_bindings(&mut self, bindings: HashMap<InternedString, ValueId>) {
+        self.variables = bindings;
     }
 
     /// Look up a variable binding.
@@ -81,6 +204,64 @@ impl<'a> IrGenContext<'a> {
     pub fn get_value_type(&self, value: ValueId) -> Option<&Type> {
         self.value_types.get(&value)
     }
+
+    /// Store the dimension of an SSA value holding a quantity.
+    pub fn set_dimension(&mut self, value: ValueId, dimension: DerivedDimension) {
+        self.dimensions.insert(value, dimension);
+    }
+
+    /// Get the dimension of an SSA value, if it holds a quantity.
+    pub fn dimension(&self, value: ValueId) -> Option<&DerivedDimension> {
+        self.dimensions.get(&value)
+    }
+
+    /// Store the shape of an SSA value holding a vector or a matrix.
+    pub(crate) fn set_shape(&mut self, value: ValueId, shape: Shape) {
+        self.shapes.insert(value, shape);
+    }
+
+    /// Get the shape of an SSA value, if it holds a vector or a matrix.
+    pub(crate) fn shape(&self, value: ValueId) -> Option<&Shape> {
+        self.shapes.get(&value)
+    }
+
+    /// Bind a lifted nested function to its name in the source.
+    pub fn bind_local_function(&mut self, name: InternedString, func: LiftedFunction) {
+        self.local_functions.insert(name, func);
+    }
+
+    /// Look up a nested function in scope.
+    pub fn lookup_local_function(&self, name: InternedString) -> Option<&LiftedFunction> {
+        self.local_functions.get(&name)
+    }
+
+    /// Resolves `name` to a capture if it names a value local to the function
+    /// being generated: a variable, or the environment of a nested function.
+    fn capture(&self, name: InternedString) -> Option<Capture> {
+        if let Some(func) = self.lookup_local_function(name) {
+            let (value, ty) = func.env.clone()?;
+            return Some(Capture {
+                name,
+                value,
+                ty,
+                function: Some(func.id),
+            });
+        }
+        let value = self.lookup_var(name)?;
+        Some(Capture {
+            name,
+            value,
+            ty: self.get_value_type(value).cloned().unwrap_or(Type::Unknown),
+            function: None,
+        })
+    }
+}
+
+/// A variable binding saved by [`IrGenContext::save_vars`].
+pub struct SavedVar {
+    name: InternedString,
+    value: Option<ValueId>,
+    ty: Option<InferType>,
 }
 
 /// State for IR generation with support for multiple basic blocks.
@@ -138,6 +319,18 @@ impl<'a> IrGenState<'a> {
         self.func_builder.block_with_id(id)
     }
 
+    /// Add the incoming `value` from the block `from` to the phi `phi` of the
+    /// completed block `block` (see [`FunctionBuilder::add_phi_incoming`]).
+    pub fn add_phi_incoming(
+        &mut self,
+        block: BlockId,
+        phi: ValueId,
+        value: ValueId,
+        from: BlockId,
+    ) {
+        self.func_builder.add_phi_incoming(block, phi, value, from);
+    }
+
     /// Complete the current block with a terminator and add it to the function.
     ///
     /// After calling this, there is no current block.
@@ -147,13 +340,37 @@ impl<'a> IrGenState<'a> {
     }
 }
 
+/// A nested function being lifted by [`IrGenerator::lift_function`].
+struct Lifted<'a> {
+    /// The function's name in the source.
+    name: InternedString,
+    /// The variables captured from the enclosing function.
+    captures: &'a [Capture],
+    /// The nested functions without an environment, callable directly.
+    functions: HashMap<InternedString, LiftedFunction>,
+}
+
 /// IR Generator - converts evaluated values to IR.
 pub struct IrGenerator {
     builder: IrBuilder,
     /// Maps function names to their function IDs for call generation.
     functions: HashMap<InternedString, FunctionId>,
+    /// The IDs of the top-level functions declared ahead of their
+    /// definitions, so calls can refer to functions defined later.
+    declared: HashMap<InternedString, FunctionId>,
     /// Type inferencer for determining expression types.
     type_inferencer: TypeInferencer,
+    /// The units quantities can be constructed with.
+    units: UnitRegistry,
+    /// The shapes of the vectors and matrices functions return.
+    return_shapes: HashMap<FunctionId, Shape>,
+    /// Maps the names of module-level constants to their global IDs.
+    globals: HashMap<InternedString, GlobalId>,
+    /// The file source locations name.
+    file: InternedString,
+    /// The root of the syntax tree the last source location was in, and its
+    /// text, for finding lines and columns.
+    source_file: Option<(SyntaxNode, SourceFile)>,
 }
 
 impl IrGenerator {
@@ -162,10 +379,28 @@ impl IrGenerator {
         Self {
             builder: IrBuilder::new(),
             functions: HashMap::new(),
+            declared: HashMap::new(),
             type_inferencer: TypeInferencer::new(),
+            units: UnitRegistry::new(),
+            return_shapes: HashMap::new(),
+            globals: HashMap::new(),
+            file: InternedString::new("input"),
+            source_file: None,
         }
     }
 
+    /// Sets the file named in the source locations of the IR generated from
+    /// now on.
+    pub fn set_file(&mut self, file: InternedString) {
+        self.file = file;
+    }
+
+    /// Registers `unit`, so applying it to a number in a function constructs
+    /// a quantity.
+    pub fn register_unit(&mut self, unit: Unit) {
+        self.units.register(unit);
+    }
+
     /// Helper to create a fresh type variable for a parameter.
     fn create_param_type_var(&mut self, name: InternedString, ctx: &mut IrGenContext) {
         let type_var = self.type_inferencer.fresh_var();
@@ -177,6 +412,7 @@ impl IrGenerator {
     ///
     /// Returns the concrete type if inference succeeds, otherwise Unknown.
     fn infer_concrete_type(&mut self, expr: &Expr, ctx: &IrGenContext) -> Type {
+        let _span = tracing::debug_span!("infer").entered();
         self.type_inferencer
             .infer_expr(expr, ctx.type_env())
             .ok()
@@ -196,20 +432,18 @@ impl IrGenerator {
             Value::String(s) => Some(IrConst::String(InternedString::new(s))),
             Value::Quantity {
                 value,
-                unit: _,
+                unit,
                 dimension,
             } => {
-                // Convert DerivedDimension to a single Dimension for IR
-                // For now, use the base dimension if it exists, otherwise skip
-                // TODO: Properly represent derived dimensions in IR
-                if let Some((dim, _power)) = dimension.numerator.first() {
+                // Compiled code holds quantities in base units
+                let value = value * unit.scale + unit.offset;
+                if dimension.is_dimensionless() {
+                    Some(IrConst::Float(value))
+                } else {
                     Some(IrConst::Quantity {
-                        value: *value,
-                        dimension: *dim,
+                        value,
+                        dimension: dimension.clone(),
                     })
-                } else {
-                    // No base dimension, treat as plain float
-                    Some(IrConst::Float(*value))
                 }
             }
             // Non-constant values
@@ -217,81 +451,623 @@ impl IrGenerator {
         }
     }
 
+    /// Generate a module-level constant named `name` holding `value`.
+    ///
+    /// Returns `None` if `value` has no constant representation. Functions
+    /// generated afterwards read the constant when they refer to `name`
+    /// without binding it themselves.
+    pub fn gen_global(&mut self, name: InternedString, value: &Value) -> Option<GlobalId> {
+        let value = self.value_to_const(value)?;
+        let ty = match &value {
+            IrConst::Nil => Type::Nil,
+            IrConst::Bool(_) => Type::Bool,
+            IrConst::Integer(_) => Type::Integer,
+            IrConst::Float(_) | IrConst::Quantity { .. } => Type::Float,
+            IrConst::String(_) => Type::String,
+        };
+        let id = self.builder.global(name, ty, value);
+        self.globals.insert(name, id);
+        Some(id)
+    }
+
     /// Generate IR for a user function.
     ///
     /// Converts a UserFunction value to an IR function.
     /// Returns the function ID on success.
     pub fn gen_function(&mut self, func: &UserFunction, env: &Env) -> Result<FunctionId> {
-        let name = func.name;
+        check_no_patterns(func)?;
+        self.gen_function_body(func.name, &func.params, &func.body, None, env)
+    }
+
+    /// Generate an IR function named `name` from its parameters and body.
+    ///
+    /// Top-level functions pass `lifted: None` and are registered by name for
+    /// calls. A lifted nested function passes its name in the source, the
+    /// variables it captures, and the nested functions it can call directly
+    /// (see [`lambda_lift`](super::lambda_lift)).
+    fn gen_function_body(
+        &mut self,
+        name: InternedString,
+        params: &[InternedString],
+        body: &Expr,
+        lifted: Option<Lifted<'_>>,
+        env: &Env,
+    ) -> Result<FunctionId> {
+        let captures = lifted.as_ref().map_or(&[][..], |lifted| lifted.captures);
 
         // Create a type environment for inference
         let mut inference_ctx = IrGenContext::new(env);
+        self.add_signatures(&mut inference_ctx);
+        for capture in captures.iter().filter(|capture| capture.function.is_none()) {
+            inference_ctx
+                .type_env_mut()
+                .insert(capture.name, InferType::Concrete(capture.ty.clone()));
+        }
 
         // Create type variables for parameters
-        for p in &func.params {
+        for p in params {
             self.create_param_type_var(*p, &mut inference_ctx);
         }
 
         // Infer the return type by inferring the body expression
-        let return_ty = self.infer_concrete_type(&func.body, &inference_ctx);
+        let return_ty = self.infer_concrete_type(body, &inference_ctx);
+
+        // A lifted function that captures variables takes its environment first
+        let env_param =
+            (!captures.is_empty()).then(|| (InternedString::new("env"), env_type(captures)));
 
         // Try to get inferred types for parameters from the type environment
-        let param_types: Vec<(InternedString, Type)> = func
-            .params
-            .iter()
-            .map(|p| {
+        let param_types: Vec<(InternedString, Type)> = env_param
+            .clone()
+            .into_iter()
+            .chain(params.iter().map(|p| {
                 // Try to infer the parameter type from the type environment
                 let ty = inference_ctx
                     .type_env()
                     .get(*p)
                     .and_then(|infer_ty| infer_ty.to_concrete().ok())
+                    .or_else(|| self.param_type_from_use(*p, body))
                     .unwrap_or(Type::Unknown);
                 (*p, ty)
-            })
+            }))
             .collect();
 
-        let mut func_builder = self.builder.function(name, param_types.clone(), return_ty);
-        // Register the function early so recursive calls can find it
+        // A top-level function declared ahead of its definition keeps its ID
+        let func_id = match lifted {
+            None => self.declared.remove(&name),
+            Some(_) => None,
+        }
+        .unwrap_or_else(|| self.builder.declare_function());
+        let mut func_builder =
+            self.builder
+                .define_function(func_id, name, param_types.clone(), return_ty);
         let func_id = func_builder.id();
-        self.functions.insert(name, func_id);
 
         // Create the entry block
         let entry_block = func_builder.block();
 
         // Create context for IR generation (parameters get bound as SSA values)
         let mut ctx = IrGenContext::new(env);
+        self.add_signatures(&mut ctx);
+        ctx.function = Some(name);
+
+        // Register the function early so recursive calls can find it
+        match &lifted {
+            None => {
+                self.functions.insert(name, func_id);
+            }
+            Some(lifted) => {
+                ctx.local_functions = lifted.functions.clone();
+                ctx.bind_local_function(
+                    lifted.name,
+                    LiftedFunction {
+                        id: func_id,
+                        env: env_param.as_ref().map(|(_, ty)| (ValueId(0), ty.clone())),
+                    },
+                );
+            }
+        }
 
         // Bind parameters to their SSA values (v0, v1, ...)
         // Also add them to the type environment and value types map
-        for (i, param_name) in func.params.iter().enumerate() {
+        let first_param = usize::from(env_param.is_some());
+        for (i, (param_name, param_ty)) in param_types.iter().enumerate().skip(first_param) {
             let value_id = ValueId(i as u32);
-            let param_ty = &param_types[i].1; // Get the type from param_types
             let infer_ty = InferType::Concrete(param_ty.clone());
             ctx.bind_var(*param_name, value_id, &infer_ty);
             ctx.set_value_type(value_id, param_ty.clone());
+            ctx.params.insert(value_id);
         }
 
         // Create state for multi-block generation
         let mut state = IrGenState::new(&mut func_builder, entry_block);
 
-        // Generate IR for the function body
-        let result = self.gen_expr_with_state(&func.body, &mut state, &mut ctx)?;
+        // Unpack the captured variables from the environment
+        if env_param.is_some() {
+            let source = self.source_of(body);
+            for capture in captures {
+                let block = state.current_block();
+                let value = block.field(ValueId(0), capture.name, capture.ty.clone(), source);
+                match capture.function {
+                    Some(id) => ctx.bind_local_function(
+                        capture.name,
+                        LiftedFunction {
+                            id,
+                            env: Some((value, capture.ty.clone())),
+                        },
+                    ),
+                    None => {
+                        ctx.bind_var(
+                            capture.name,
+                            value,
+                            &InferType::Concrete(capture.ty.clone()),
+                        );
+                        ctx.set_value_type(value, capture.ty.clone());
+                    }
+                }
+            }
+        }
 
-        // Complete the current block with a return
-        let block = state
-            .current_block
-            .take()
-            .expect("No current block available for function return");
-        let (block_inst, next_val) = block.ret(Some(result), self.dummy_source());
-        state.complete_current_block(block_inst, next_val);
+        // Generate IR for the function body, which returns its value unless
+        // it fails
+        let source = self.source_of(body);
+        let returned = match special_form::try_form::failure(std::slice::from_ref(body), env) {
+            Some(message) => {
+                let block = state.current_block.take().unwrap();
+                let (block_inst, next_val) = block.fail(message?, source);
+                state.complete_current_block(block_inst, next_val);
+                ctx.set_fallible();
+                None
+            }
+            None => {
+                let result = self.gen_expr_with_state(body, &mut state, &mut ctx)?;
+                let block = state
+                    .current_block
+                    .take()
+                    .expect("No current block available for function return");
+                let (block_inst, next_val) = block.ret(Some(result), source);
+                state.complete_current_block(block_inst, next_val);
+                Some(result)
+            }
+        };
+        if ctx.fallible {
+            func_builder.set_fallible();
+        }
 
-        // Build the function
-        let ir_func = func_builder.build();
+        // Build the function, deleting the linear values it doesn't move. A
+        // function returning a `Result` or an `Option` returns its success
+        // value, or nothing if it always fails.
+        let mut ir_func = func_builder.build();
+        let is_array = matches!(ir_func.return_ty, Type::Vector | Type::Matrix);
+        if ir_func.return_ty == Type::Unknown || result::is_builtin(&ir_func.return_ty) || is_array
+        {
+            ir_func.return_ty = match returned {
+                Some(value) => ctx.get_value_type(value).cloned().unwrap_or(Type::Unknown),
+                None => Type::Nil,
+            };
+        }
+        for param in &mut ir_func.params {
+            if param.ty == Type::Unknown
+                && let Some(ty) = ctx.get_value_type(param.value_id)
+            {
+                param.ty = ty.clone();
+            }
+            param.dimension = ctx.dimension(param.value_id).cloned();
+        }
+        ir_func.return_dimension = returned.and_then(|value| ctx.dimension(value).cloned());
+        if let Some(shape) = returned.and_then(|value| ctx.shape(value)) {
+            self.return_shapes.insert(func_id, shape.clone());
+        }
+        if env_param.is_some() {
+            ctx.environments.push(ValueId(0));
+        }
+        insert_deleters(&mut ir_func, &ctx.environments);
         self.builder.add_function(ir_func);
 
         Ok(func_id)
     }
 
+    /// Lambda-lift the nested function definition `func` to a top-level IR
+    /// function and bind it in `ctx`.
+    ///
+    /// The variables of the enclosing function used by `func` are packed into
+    /// an environment record here, at the definition. Returns nil, the value
+    /// of a definition.
+    fn lift_function(
+        &mut self,
+        func: &FunctionDef,
+        block: &mut BlockBuilder,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
+    ) -> Result<ValueId> {
+        let captures: Vec<Capture> = free_variables(func)
+            .into_iter()
+            .filter_map(|name| ctx.capture(name))
+            .collect();
+        if let Some(moved) = captures.iter().find(|capture| ctx.is_moved(capture.name)) {
+            return Err(Diagnostic::use_after_move(moved.name).with_span(func.span));
+        }
+
+        let env = if captures.is_empty() {
+            None
+        } else {
+            let names: Arc<[InternedString]> = captures.iter().map(|c| c.name).collect();
+            let values = captures.iter().map(|c| c.value).collect();
+            let ty = env_type(&captures);
+            let value = block.record(names, values, ty.clone(), source);
+            ctx.set_value_type(value, ty.clone());
+            ctx.environments.push(value);
+            Some((value, ty))
+        };
+
+        // Nested functions without an environment stay callable from the body
+        let functions = ctx
+            .local_functions
+            .iter()
+            .filter(|(_, f)| f.env.is_none())
+            .map(|(name, f)| (*name, f.clone()))
+            .collect();
+
+        let name = match ctx.function {
+            Some(outer) => InternedString::new(&format!("{outer}.{}", func.name)),
+            None => func.name,
+        };
+        let lifted = Lifted {
+            name: func.name,
+            captures: &captures,
+            functions,
+        };
+        let id = self.gen_function_body(name, &func.params, &func.body, Some(lifted), ctx.env())?;
+        ctx.bind_local_function(func.name, LiftedFunction { id, env });
+
+        Ok(block.const_val(IrConst::Nil, Type::Nil, source))
+    }
+
+    /// Resolve a call to `name`, returning the function and the environment
+    /// to pass before the arguments, if any.
+    fn resolve_call(
+        &self,
+        name: InternedString,
+        ctx: &IrGenContext,
+    ) -> Result<(FunctionId, Option<ValueId>)> {
+        if let Some(func) = ctx.lookup_local_function(name) {
+            return Ok((func.id, func.env.as_ref().map(|(value, _)| *value)));
+        }
+        let func_id = self.function(name).ok_or_else(|| {
+            Diagnostic::syntax(format!("Unknown function in IR generation: {}", name))
+        })?;
+        Ok((func_id, None))
+    }
+
+    /// Returns whether `name` is a function of the module or a nested function
+    /// in scope, which takes precedence over a host function of that name.
+    /// Returns the field of a call of `Ok` or `Some` with `args`, which
+    /// compiled code represents by its field (see
+    /// [`try_form`](special_form::try_form)).
+    ///
+    /// `Err` and `None` make the function fail, so they can only end its body
+    /// or a branch of an `if`.
+    fn variant_field<'e>(
+        &self,
+        name: InternedString,
+        args: &'e [Expr],
+        ctx: &IrGenContext,
+    ) -> Result<Option<&'e Expr>> {
+        let variant = match ctx.env().get(name) {
+            Some(Value::VariantConstructor { ty, variant }) if result::is_builtin(ty) => variant,
+            _ => return Ok(None),
+        };
+        match (&**variant, args) {
+            ("Ok" | "Some", [field]) => Ok(Some(field)),
+            ("Ok" | "Some", _) => Err(Diagnostic::arity(1, args.len())),
+            _ => Err(Diagnostic::syntax(format!(
+                "{} can only end a function or a branch of an if in compiled code",
+                &**variant
+            ))),
+        }
+    }
+
+    fn is_defined(&self, name: InternedString, ctx: &IrGenContext) -> bool {
+        ctx.lookup_local_function(name).is_some() || self.function(name).is_some()
+    }
+
+    /// Returns the ID of the top-level function `name`, whether it's been
+    /// generated or only declared.
+    fn function(&self, name: InternedString) -> Option<FunctionId> {
+        self.functions
+            .get(&name)
+            .or_else(|| self.declared.get(&name))
+            .copied()
+    }
+
+    /// Returns the type of the parameter `param` from its use in `body` as
+    /// an argument to a host function that isn't overloaded, such as `x` in
+    /// `sin x`, to an intrinsic that takes a float, such as `sqrt`, or to a
+    /// unit, which takes a float.
+    ///
+    /// Inference doesn't resolve parameter types yet, and an untyped
+    /// parameter passed to the host would otherwise be lowered as an integer.
+    fn param_type_from_use(&self, param: InternedString, body: &Expr) -> Option<Type> {
+        let signatures: HashMap<_, _> = host::signatures()
+            .chain(intrinsic_signatures())
+            .filter(|(name, _)| self.function(*name).is_none())
+            .collect();
+        body.syntax()
+            .descendants_with_tokens()
+            .filter_map(
+                |element| match Expr::cast_syntax_node(element.as_node()?)? {
+                    Expr::Apply(apply) => Some(apply),
+                    _ => None,
+                },
+            )
+            .find_map(|apply| {
+                let Some(Expr::Ident(callee)) = apply.callee() else {
+                    return None;
+                };
+                let callee = callee.syntax().text().interned();
+                let args = apply.all_arguments();
+                let position = args.iter().position(|arg| {
+                    matches!(arg, Expr::Ident(ident) if ident.syntax().text().interned() == param)
+                })?;
+                if self.units.get(callee).is_some() && self.function(callee).is_none() {
+                    return (position == 0).then_some(Type::Float);
+                }
+                let Type::Fn(types) = signatures.get(&callee)? else {
+                    return None;
+                };
+                // The last type is the result
+                types[..types.len() - 1].get(position).cloned()
+            })
+    }
+
+    /// Adds the types of the host functions, float intrinsics, units and
+    /// module-level constants not shadowed by the module's own functions to
+    /// `ctx`, so arguments passed to them and the values they return are
+    /// inferred.
+    fn add_signatures(&mut self, ctx: &mut IrGenContext) {
+        for (name, ty) in host::signatures().chain(intrinsic_signatures()) {
+            if self.function(name).is_none() {
+                ctx.type_env_mut()
+                    .insert(name, InferType::from_concrete(&ty));
+            }
+        }
+        // A unit takes an integer or a float and returns a float
+        let number = InferType::Var(self.type_inferencer.fresh_var());
+        let unit_ty = InferType::Fn(vec![number], Box::new(InferType::Concrete(Type::Float)));
+        for unit in self.units.all_units() {
+            if self.function(unit.name).is_none() {
+                ctx.type_env_mut().insert(unit.name, unit_ty.clone());
+            }
+        }
+        for (&name, &id) in &self.globals {
+            if let Some(global) = self.builder.module().global(id)
+                && self.function(name).is_none()
+            {
+                ctx.type_env_mut()
+                    .insert(name, InferType::Concrete(global.ty.clone()));
+            }
+        }
+    }
+
+    /// Returns the unit `name` refers to in `ctx`, unless a variable or
+    /// function shadows it.
+    fn unit(&self, name: InternedString, ctx: &IrGenContext) -> Option<Unit> {
+        if ctx.lookup_var(name).is_some() || self.is_defined(name, ctx) {
+            return None;
+        }
+        self.units.get(name).cloned()
+    }
+
+    /// Generate the quantity `unit` applied to `arg`, such as `inch 2`, where
+    /// `value` is the generated argument unless it's a number literal.
+    ///
+    /// Compiled code holds quantities as floats in the base units of their
+    /// dimension, so the argument is converted to base units here: a number
+    /// literal at compile time, and any other value at run time.
+    fn gen_quantity(
+        &mut self,
+        unit: &Unit,
+        arg: &Expr,
+        value: Option<ValueId>,
+        block: &mut BlockBuilder,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
+    ) -> Result<ValueId> {
+        let dimension = DerivedDimension::from_dimension(unit.dimension);
+        let result = match (number_literal(arg), value) {
+            (Some(number), _) => block.const_val(
+                IrConst::Quantity {
+                    value: number * unit.scale + unit.offset,
+                    dimension: dimension.clone(),
+                },
+                Type::Float,
+                source,
+            ),
+            (None, Some(value)) => {
+                if let Some(existing) = ctx.dimension(value) {
+                    return Err(Diagnostic::syntax(format!(
+                        "cannot construct a {} quantity from a {existing} quantity",
+                        unit.name
+                    )));
+                }
+                match ctx.get_value_type(value) {
+                    Some(Type::Float | Type::Unknown) | None => {}
+                    Some(ty) => return Err(Diagnostic::type_error(Type::Float, ty.clone())),
+                }
+                // A base unit needs no conversion, but a parameter still gets
+                // a new value, or it would take on the quantity's dimension
+                let is_param = ctx.params.contains(&value);
+                let mut result = value;
+                if unit.scale != 1.0 || (unit.offset == 0.0 && is_param) {
+                    let scale = block.const_val(IrConst::Float(unit.scale), Type::Float, source);
+                    result = block.binop(IrBinOp::Mul, result, scale, Type::Float, source);
+                }
+                if unit.offset != 0.0 {
+                    let offset = block.const_val(IrConst::Float(unit.offset), Type::Float, source);
+                    result = block.binop(IrBinOp::Add, result, offset, Type::Float, source);
+                }
+                result
+            }
+            (None, None) => return Err(Diagnostic::syntax("Missing unit argument")),
+        };
+        ctx.set_value_type(result, Type::Float);
+        ctx.set_dimension(result, dimension);
+        Ok(result)
+    }
+
+    /// Generate the binary operation `op`, named `name`, on `lhs` and `rhs`,
+    /// the operands of `apply`.
+    ///
+    /// The dimensions of quantity operands are checked and propagated (see
+    /// [`binop_dimension`]).
+    #[allow(clippy::too_many_arguments)]
+    fn gen_binop(
+        &mut self,
+        name: &str,
+        op: IrBinOp,
+        lhs: ValueId,
+        rhs: ValueId,
+        apply: &cadenza_syntax::ast::Apply,
+        block: &mut BlockBuilder,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
+    ) -> Result<ValueId> {
+        if let Some(result) = self.gen_host_operator(name, lhs, rhs, block, ctx, source) {
+            return result;
+        }
+
+        let dimension = binop_dimension(op, lhs, rhs, ctx)?;
+
+        // Infer the type of the binary operation, or else take arithmetic's
+        // from its operands
+        let mut ty = self.infer_concrete_type(&Expr::Apply(apply.clone()), ctx);
+        let is_arithmetic = matches!(
+            op,
+            IrBinOp::Add | IrBinOp::Sub | IrBinOp::Mul | IrBinOp::Div | IrBinOp::Rem
+        );
+        if ty == Type::Unknown
+            && is_arithmetic
+            && let (Some(lhs_ty), Some(rhs_ty)) = (ctx.get_value_type(lhs), ctx.get_value_type(rhs))
+            && lhs_ty == rhs_ty
+        {
+            ty = lhs_ty.clone();
+        }
+
+        // The evaluator rejects a zero divisor, so the compiled code does too
+        if matches!(op, IrBinOp::Div | IrBinOp::Rem) {
+            block.check_divisor(
+                rhs,
+                ctx.get_value_type(rhs).unwrap_or(&Type::Unknown),
+                source,
+            );
+        }
+
+        let result = block.binop(op, lhs, rhs, ty.clone(), source);
+        ctx.set_value_type(result, ty);
+        if let Some(dimension) = dimension {
+            ctx.set_dimension(result, dimension);
+        }
+        Ok(result)
+    }
+
+    /// Generate the instruction for a math builtin applied to `args` (see
+    /// [`intrinsic`]).
+    ///
+    /// `sqrt`, `floor`, `ceil` and `round` take a float, and `abs`, `min` and
+    /// `max` an integer or a float, the same type for both operands.
+    fn gen_intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        args: Vec<ValueId>,
+        block: &mut BlockBuilder,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
+    ) -> Result<ValueId> {
+        let type_of = |value| ctx.get_value_type(value).cloned().unwrap_or(Type::Unknown);
+        let (result, ty) = match (intrinsic, args.as_slice()) {
+            (Intrinsic::Unary(op), &[operand]) => {
+                let ty = match type_of(operand) {
+                    ty @ (Type::Integer | Type::Unknown) if op == IrUnOp::Abs => ty,
+                    Type::Float | Type::Unknown => Type::Float,
+                    ty => return Err(Diagnostic::type_error(Type::Float, ty)),
+                };
+                (block.unop(op, operand, ty.clone(), source), ty)
+            }
+            (Intrinsic::Binary(op), &[lhs, rhs]) => {
+                let ty = match (type_of(lhs), type_of(rhs)) {
+                    (Type::Unknown, ty) | (ty, Type::Unknown) => ty,
+                    (lhs_ty, rhs_ty) if lhs_ty == rhs_ty => lhs_ty,
+                    (lhs_ty, rhs_ty) => return Err(Diagnostic::type_error(lhs_ty, rhs_ty)),
+                };
+                if !matches!(ty, Type::Integer | Type::Float | Type::Unknown) {
+                    return Err(Diagnostic::type_error(Type::Float, ty));
+                }
+                (block.binop(op, lhs, rhs, ty.clone(), source), ty)
+            }
+            (Intrinsic::Unary(_), _) => return Err(Diagnostic::arity(1, args.len())),
+            (Intrinsic::Binary(_), _) => return Err(Diagnostic::arity(2, args.len())),
+        };
+        ctx.set_value_type(result, ty);
+        Ok(result)
+    }
+
+    /// Generate a call to the host function for `callee` that takes `args`
+    /// (see [`host::resolve`]).
+    fn gen_host_call(
+        &mut self,
+        callee: &str,
+        args: Vec<ValueId>,
+        block: &mut BlockBuilder,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
+    ) -> Result<ValueId> {
+        let types: Vec<Type> = args
+            .iter()
+            .map(|arg| ctx.get_value_type(*arg).cloned().unwrap_or(Type::Unknown))
+            .collect();
+        let function = host::resolve(callee, &types).ok_or_else(|| {
+            let types: Vec<_> = types.iter().map(ToString::to_string).collect();
+            Diagnostic::syntax(format!(
+                "No host function for {callee} takes ({})",
+                types.join(", ")
+            ))
+        })?;
+        let id = self.builder.import(function);
+        let ty = function.return_type();
+        let result = block.call(id, args, ty.clone(), source);
+        ctx.set_value_type(result, ty);
+        Ok(result)
+    }
+
+    /// Generate the comparison of two strings with the host, for `==` and
+    /// `!=`.
+    ///
+    /// Returns `None` for other operators and operands.
+    fn gen_host_operator(
+        &mut self,
+        op: &str,
+        lhs: ValueId,
+        rhs: ValueId,
+        block: &mut BlockBuilder,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
+    ) -> Option<Result<ValueId>> {
+        let is_string = |value| ctx.get_value_type(value) == Some(&Type::String);
+        if !(matches!(op, "==" | "!=") && is_string(lhs) && is_string(rhs)) {
+            return None;
+        }
+        let equal = match self.gen_host_call("==", vec![lhs, rhs], block, ctx, source) {
+            Ok(equal) => equal,
+            Err(error) => return Some(Err(error)),
+        };
+        if op == "==" {
+            return Some(Ok(equal));
+        }
+        let result = block.unop(IrUnOp::Not, equal, Type::Bool, source);
+        ctx.set_value_type(result, Type::Bool);
+        Some(Ok(result))
+    }
+
     /// Generate IR for an expression using IrGenState for multi-block support.
     ///
     /// Returns the SSA value ID for the result of the expression.
@@ -301,11 +1077,11 @@ impl IrGenerator {
         state: &mut IrGenState,
         ctx: &mut IrGenContext,
     ) -> Result<ValueId> {
-        let source = self.dummy_source();
+        let source = self.source_of(expr);
 
         match expr {
             Expr::Literal(lit) => self.gen_literal(lit, state.current_block(), ctx, source),
-            Expr::Ident(ident) => self.gen_ident(ident, ctx),
+            Expr::Ident(ident) => self.gen_ident(ident, state.current_block(), ctx, source),
             Expr::Apply(apply) => self.gen_apply_with_state(apply, state, ctx, source),
             _ => Err(Diagnostic::syntax(format!(
                 "Unsupported expression type for IR generation: {:?}",
@@ -323,11 +1099,11 @@ impl IrGenerator {
         block: &mut BlockBuilder,
         ctx: &mut IrGenContext,
     ) -> Result<ValueId> {
-        let source = self.dummy_source();
+        let source = self.source_of(expr);
 
         match expr {
             Expr::Literal(lit) => self.gen_literal(lit, block, ctx, source),
-            Expr::Ident(ident) => self.gen_ident(ident, ctx),
+            Expr::Ident(ident) => self.gen_ident(ident, block, ctx, source),
             Expr::Apply(apply) => self.gen_apply(apply, block, ctx, source),
             _ => Err(Diagnostic::syntax(format!(
                 "Unsupported expression type for IR generation: {:?}",
@@ -353,10 +1129,14 @@ impl IrGenerator {
         let (const_val, ty) = match value {
             LiteralValue::Integer(i) => {
                 let text = i.syntax().text();
-                let value = text
-                    .to_string()
-                    .parse::<i64>()
-                    .map_err(|e| Diagnostic::syntax(format!("Invalid integer literal: {}", e)))?;
+                let value = parse_integer(text.as_str()).map_err(|e| match e.kind() {
+                    // The evaluator makes this a big integer, which
+                    // compiled code doesn't have
+                    IntErrorKind::PosOverflow => Diagnostic::syntax(format!(
+                        "Integer literal {text} doesn't fit in 64 bits in compiled code"
+                    )),
+                    _ => Diagnostic::syntax(format!("Invalid integer literal: {}", e)),
+                })?;
                 (IrConst::Integer(value), Type::Integer)
             }
             LiteralValue::Float(f) => {
@@ -367,6 +1147,12 @@ impl IrGenerator {
                     .map_err(|e| Diagnostic::syntax(format!("Invalid float literal: {}", e)))?;
                 (IrConst::Float(value), Type::Float)
             }
+            LiteralValue::Decimal(d) => {
+                return Err(Diagnostic::syntax(format!(
+                    "Decimal literal {}d isn't supported in compiled code",
+                    d.digits()
+                )));
+            }
             LiteralValue::String(s) => {
                 let text = s.syntax().text().interned();
                 (IrConst::String(text), Type::String)
@@ -386,15 +1172,41 @@ impl IrGenerator {
     }
 
     /// Generate IR for an identifier (variable reference).
+    ///
+    /// A name the function doesn't bind is `true` or `false`, or reads the
+    /// module-level constant of that name, if there is one.
     fn gen_ident(
         &mut self,
         ident: &cadenza_syntax::ast::Ident,
-        ctx: &IrGenContext,
+        block: &mut BlockBuilder,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
     ) -> Result<ValueId> {
         let name = ident.syntax().text().interned();
-        ctx.lookup_var(name).ok_or_else(|| {
-            Diagnostic::syntax(format!("Undefined variable in IR generation: {}", name))
-        })
+        if ctx.is_moved(name) {
+            return Err(Diagnostic::use_after_move(name).with_span(ident.span()));
+        }
+        if let Some(value) = ctx.lookup_var(name) {
+            return Ok(value);
+        }
+        if let Some(&Value::Bool(b)) = ctx.env().get(name) {
+            let result = block.const_val(IrConst::Bool(b), Type::Bool, source);
+            ctx.set_value_type(result, Type::Bool);
+            return Ok(result);
+        }
+        let global = self
+            .globals
+            .get(&name)
+            .and_then(|&id| self.builder.module().global(id))
+            .ok_or_else(|| {
+                Diagnostic::syntax(format!("Undefined variable in IR generation: {}", name))
+            })?;
+        let result = block.global(global.id, global.ty.clone(), source);
+        ctx.set_value_type(result, global.ty.clone());
+        if let IrConst::Quantity { dimension, .. } = &global.value {
+            ctx.set_dimension(result, dimension.clone());
+        }
+        Ok(result)
     }
 
     /// Try to generate IR for a special form by name.
@@ -452,12 +1264,103 @@ impl IrGenerator {
 
         // Check if it's a special form
         if let Value::SpecialForm(special_form) = value {
-            // TODO: The hardcoded check for "match" is a temporary solution.
+            // TODO: The hardcoded checks for "match" and "if" are a temporary solution.
             // A better approach would be to extend the SpecialForm trait with a method
             // indicating whether the form needs multi-block generation, or to unify
             // the single-block and multi-block APIs so all special forms can use IrGenState.
-            // For now, "match" is the only special form that needs multi-block support.
+            // For now, "match", "if", "&&", "||", loops and blocks (`__block__`
+            // and `do`), which can contain them, are the only special forms that need multi-block
+            // support.
+            if name == "if" {
+                // Each part of the if is one expression or an application
+                // written without parentheses
+                let mut gen_part_adapter =
+                    |part: &[Expr], state: &mut IrGenState, ctx: &mut IrGenContext| match part {
+                        [expr] => self.gen_expr_with_state(expr, state, ctx),
+                        [callee, args @ ..] => {
+                            let name = extract_identifier(callee).ok_or_else(|| {
+                                Diagnostic::syntax("Unsupported callee type for IR generation")
+                                    .with_span(callee.span())
+                            })?;
+                            let source = self.source_of(callee);
+                            self.gen_call_with_state(name, args, None, state, ctx, source)
+                        }
+                        [] => Err(Diagnostic::syntax("if is missing a value")),
+                    };
+
+                return Some(special_form::if_form::ir_if_with_state(
+                    &args,
+                    state,
+                    ctx,
+                    source,
+                    &mut gen_part_adapter,
+                ));
+            }
+            if name == "__block__" {
+                // Blocks generate each expression with state, so an if or
+                // match can end the body of a function
+                let mut result = None;
+                for expr in &args {
+                    result = Some(self.gen_expr_with_state(expr, state, ctx));
+                    if let Some(Err(_)) = result {
+                        break;
+                    }
+                }
+                // A block ending with a binding returns nil, as an empty one
+                // does
+                if let Some(Ok(_)) = result
+                    && args
+                        .last()
+                        .is_some_and(special_form::block_form::is_binding)
+                {
+                    result = None;
+                }
+                return Some(result.unwrap_or_else(|| {
+                    Ok(state
+                        .current_block()
+                        .const_val(IrConst::Nil, Type::Nil, source))
+                }));
+            }
+            // A `do` block is generated with state as its block is
+            if name == "do"
+                && let [body] = &args[..]
+            {
+                return Some(self.gen_expr_with_state(body, state, ctx));
+            }
+            // A loop is an anonymous function whose parameters start with `for`
+            if name == "->"
+                && let [params, body] = &args[..]
+                && let Some(header) = special_form::for_form::header(params)
+            {
+                let mut gen_expr_adapter =
+                    |expr: &Expr, state: &mut IrGenState, ctx: &mut IrGenContext| {
+                        self.gen_expr_with_state(expr, state, ctx)
+                    };
+
+                return Some(special_form::for_form::ir_for_with_state(
+                    &header,
+                    body,
+                    state,
+                    ctx,
+                    source,
+                    &mut gen_expr_adapter,
+                ));
+            }
+            if name == "&&" || name == "||" {
+                // The right operand is only evaluated in its own block
+                let mut gen_expr_adapter =
+                    |expr: &Expr, state: &mut IrGenState, ctx: &mut IrGenContext| {
+                        self.gen_expr_with_state(expr, state, ctx)
+                    };
+                let ir_fn = if name == "&&" {
+                    special_form::and_form::ir_and_with_state
+                } else {
+                    special_form::or_form::ir_or_with_state
+                };
+                return Some(ir_fn(&args, state, ctx, source, &mut gen_expr_adapter));
+            }
             if name == "match" {
+                let units = self.units.clone();
                 // Create a mutable closure for generating sub-expressions with state
                 let mut gen_expr_adapter =
                     |expr: &Expr, state: &mut IrGenState, ctx: &mut IrGenContext| {
@@ -466,6 +1369,7 @@ impl IrGenerator {
 
                 return Some(special_form::match_form::ir_match_with_state(
                     &args,
+                    &units,
                     state,
                     ctx,
                     source,
@@ -495,6 +1399,11 @@ impl IrGenerator {
         ctx: &mut IrGenContext,
         source: SourceLocation,
     ) -> Result<ValueId> {
+        // Nested function definitions are lambda-lifted
+        if let Some(func) = function_definition(&Expr::Apply(apply.clone())) {
+            return self.lift_function(&func, state.current_block(), ctx, source);
+        }
+
         // Check if this is an operator application
         let callee = apply
             .callee()
@@ -544,38 +1453,23 @@ impl IrGenerator {
                 let lhs = self.gen_expr_with_state(&args[0], state, ctx)?;
                 let rhs = self.gen_expr_with_state(&args[1], state, ctx)?;
 
-                // Infer the type of the binary operation
-                let inferred_ty = self.infer_concrete_type(&Expr::Apply(apply.clone()), ctx);
-
-                // Emit binary operation with inferred type
                 let block = state.current_block();
-                let result = block.binop(ir_op, lhs, rhs, inferred_ty.clone(), source);
-                ctx.set_value_type(result, inferred_ty);
-                return Ok(result);
+                if let Some(result) = linalg::ir_arithmetic(ir_op, lhs, rhs, block, ctx, source) {
+                    return result;
+                }
+                return self.gen_binop(&name, ir_op, lhs, rhs, apply, block, ctx, source);
             }
 
-            // Not an operator - try to look up as a function
-            let func_name = InternedString::new(&name);
-            let func_id = self.functions.get(&func_name).copied().ok_or_else(|| {
-                Diagnostic::syntax(format!("Unknown function in IR generation: {}", func_name))
-            })?;
-
-            // Generate IR for arguments
+            // Not an operator - try to look up as a function, or else the host
             let args = apply.all_arguments();
-            let arg_values: Result<Vec<ValueId>> = args
-                .iter()
-                .map(|arg| self.gen_expr_with_state(arg, state, ctx))
-                .collect();
-            let arg_values = arg_values?;
-
-            // Infer the return type of the function call
-            let inferred_ty = self.infer_concrete_type(&Expr::Apply(apply.clone()), ctx);
-
-            // Emit call instruction with inferred return type
-            let block = state.current_block();
-            let result = block.call(func_id, arg_values, inferred_ty.clone(), source);
-            ctx.set_value_type(result, inferred_ty);
-            return Ok(result);
+            return self.gen_call_with_state(
+                InternedString::new(&name),
+                &args,
+                Some(apply),
+                state,
+                ctx,
+                source,
+            );
         }
 
         Err(Diagnostic::syntax(format!(
@@ -584,6 +1478,90 @@ impl IrGenerator {
         )))
     }
 
+    /// Generate IR for a call of the unit, host function or module function
+    /// `func_name` with `args` (state-based version).
+    ///
+    /// `apply` is the application the call was written as, if any, from which
+    /// the result type is inferred; otherwise the callee's return type is used.
+    fn gen_call_with_state(
+        &mut self,
+        func_name: InternedString,
+        args: &[Expr],
+        apply: Option<&cadenza_syntax::ast::Apply>,
+        state: &mut IrGenState,
+        ctx: &mut IrGenContext,
+        source: SourceLocation,
+    ) -> Result<ValueId> {
+        if let Some(unit) = self.unit(func_name, ctx) {
+            let [arg] = args else {
+                return Err(Diagnostic::arity(1, args.len()));
+            };
+            let value = match number_literal(arg) {
+                Some(_) => None,
+                None => Some(self.gen_expr_with_state(arg, state, ctx)?),
+            };
+            let block = state.current_block();
+            return self.gen_quantity(&unit, arg, value, block, ctx, source);
+        }
+        if let Some(field) = self.variant_field(func_name, args, ctx)? {
+            return self.gen_expr_with_state(field, state, ctx);
+        }
+        if !self.is_defined(func_name, ctx)
+            && let Some(intrinsic) = intrinsic(&func_name)
+        {
+            let mut arg_values = Vec::with_capacity(args.len());
+            for arg in args {
+                arg_values.push(self.gen_expr_with_state(arg, state, ctx)?);
+            }
+            let block = state.current_block();
+            return self.gen_intrinsic(intrinsic, arg_values, block, ctx, source);
+        }
+        if !self.is_defined(func_name, ctx) && linalg::is_builtin(&func_name) {
+            let mut values = Vec::new();
+            for operand in linalg::ir_operands(&func_name, args)? {
+                values.push(match number_literal(&operand) {
+                    Some(number) => float_literal(number, state.current_block(), ctx, source),
+                    None => self.gen_expr_with_state(&operand, state, ctx)?,
+                });
+            }
+            let block = state.current_block();
+            return linalg::ir_call(&func_name, args, values, block, ctx, source);
+        }
+        if !self.is_defined(func_name, ctx) && host::is_callee(&func_name) {
+            let mut arg_values = Vec::with_capacity(args.len());
+            for arg in args {
+                arg_values.push(self.gen_expr_with_state(arg, state, ctx)?);
+            }
+            return self.gen_host_call(&func_name, arg_values, state.current_block(), ctx, source);
+        }
+        let (func_id, env) = self.resolve_call(func_name, ctx)?;
+
+        // Generate IR for arguments, after the environment of a lifted function
+        let mut arg_values: Vec<ValueId> = env.into_iter().collect();
+        for arg in args {
+            arg_values.push(self.gen_expr_with_state(arg, state, ctx)?);
+            ctx.consume(arg);
+        }
+
+        // Infer the return type of the function call
+        let inferred_ty = apply.map_or(Type::Unknown, |apply| {
+            self.infer_concrete_type(&Expr::Apply(apply.clone()), ctx)
+        });
+        let (inferred_ty, dimension) = self.call_result(func_id, inferred_ty);
+
+        // Emit call instruction with inferred return type
+        let block = state.current_block();
+        let result = block.call(func_id, arg_values, inferred_ty.clone(), source);
+        ctx.set_value_type(result, inferred_ty);
+        if let Some(dimension) = dimension {
+            ctx.set_dimension(result, dimension);
+        }
+        if let Some(shape) = self.return_shapes.get(&func_id) {
+            ctx.set_shape(result, shape.clone());
+        }
+        Ok(result)
+    }
+
     /// Generate IR for an application (function call or operator).
     fn gen_apply(
         &mut self,
@@ -592,6 +1570,11 @@ impl IrGenerator {
         ctx: &mut IrGenContext,
         source: SourceLocation,
     ) -> Result<ValueId> {
+        // Nested function definitions are lambda-lifted
+        if let Some(func) = function_definition(&Expr::Apply(apply.clone())) {
+            return self.lift_function(&func, block, ctx, source);
+        }
+
         // Check if this is an operator application
         let callee = apply
             .callee()
@@ -639,37 +1622,77 @@ impl IrGenerator {
                 let lhs = self.gen_expr(&args[0], block, ctx)?;
                 let rhs = self.gen_expr(&args[1], block, ctx)?;
 
-                // Infer the type of the binary operation
-                // Note: We need to clone Apply to wrap it as Expr for type inference
-                let inferred_ty = self.infer_concrete_type(&Expr::Apply(apply.clone()), ctx);
-
-                // Emit binary operation with inferred type
-                let result = block.binop(ir_op, lhs, rhs, inferred_ty.clone(), source);
-                ctx.set_value_type(result, inferred_ty);
-                return Ok(result);
+                if let Some(result) = linalg::ir_arithmetic(ir_op, lhs, rhs, block, ctx, source) {
+                    return result;
+                }
+                return self.gen_binop(&name, ir_op, lhs, rhs, apply, block, ctx, source);
             }
 
-            // Not an operator - try to look up as a function
+            // Not an operator - try to look up as a function, or else the host
             let func_name = InternedString::new(&name);
-            let func_id = self.functions.get(&func_name).copied().ok_or_else(|| {
-                Diagnostic::syntax(format!("Unknown function in IR generation: {}", func_name))
-            })?;
-
-            // Generate IR for arguments
             let args = apply.all_arguments();
-            let arg_values: Result<Vec<ValueId>> = args
-                .iter()
-                .map(|arg| self.gen_expr(arg, block, ctx))
-                .collect();
-            let arg_values = arg_values?;
+            if let Some(unit) = self.unit(func_name, ctx) {
+                let [arg] = &args[..] else {
+                    return Err(Diagnostic::arity(1, args.len()));
+                };
+                let value = match number_literal(arg) {
+                    Some(_) => None,
+                    None => Some(self.gen_expr(arg, block, ctx)?),
+                };
+                return self.gen_quantity(&unit, arg, value, block, ctx, source);
+            }
+            if let Some(field) = self.variant_field(func_name, &args, ctx)? {
+                return self.gen_expr(field, block, ctx);
+            }
+            if !self.is_defined(func_name, ctx)
+                && let Some(intrinsic) = intrinsic(&name)
+            {
+                let mut arg_values = Vec::with_capacity(args.len());
+                for arg in &args {
+                    arg_values.push(self.gen_expr(arg, block, ctx)?);
+                }
+                return self.gen_intrinsic(intrinsic, arg_values, block, ctx, source);
+            }
+            if !self.is_defined(func_name, ctx) && linalg::is_builtin(&name) {
+                let mut values = Vec::new();
+                for operand in linalg::ir_operands(&name, &args)? {
+                    values.push(match number_literal(&operand) {
+                        Some(number) => float_literal(number, block, ctx, source),
+                        None => self.gen_expr(&operand, block, ctx)?,
+                    });
+                }
+                return linalg::ir_call(&name, &args, values, block, ctx, source);
+            }
+            if !self.is_defined(func_name, ctx) && host::is_callee(&name) {
+                let mut arg_values = Vec::with_capacity(args.len());
+                for arg in &args {
+                    arg_values.push(self.gen_expr(arg, block, ctx)?);
+                }
+                return self.gen_host_call(&name, arg_values, block, ctx, source);
+            }
+            let (func_id, env) = self.resolve_call(func_name, ctx)?;
+
+            // Generate IR for arguments, after the environment of a lifted function
+            let mut arg_values: Vec<ValueId> = env.into_iter().collect();
+            for arg in &args {
+                arg_values.push(self.gen_expr(arg, block, ctx)?);
+                ctx.consume(arg);
+            }
 
             // Infer the return type of the function call
             // Note: We need to clone Apply to wrap it as Expr for type inference
             let inferred_ty = self.infer_concrete_type(&Expr::Apply(apply.clone()), ctx);
+            let (inferred_ty, dimension) = self.call_result(func_id, inferred_ty);
 
             // Emit call instruction with inferred return type
             let result = block.call(func_id, arg_values, inferred_ty.clone(), source);
             ctx.set_value_type(result, inferred_ty);
+            if let Some(dimension) = dimension {
+                ctx.set_dimension(result, dimension);
+            }
+            if let Some(shape) = self.return_shapes.get(&func_id) {
+                ctx.set_shape(result, shape.clone());
+            }
             return Ok(result);
         }
 
@@ -679,6 +1702,23 @@ impl IrGenerator {
         )))
     }
 
+    /// Returns the type and dimension of the result of calling `func_id`,
+    /// preferring the `inferred` type when it's known.
+    ///
+    /// A function already generated has its return type and dimension; a
+    /// recursive call, to a function still being generated, has neither.
+    fn call_result(&self, func_id: FunctionId, inferred: Type) -> (Type, Option<DerivedDimension>) {
+        let Some(func) = self.builder.module().function(func_id) else {
+            return (inferred, None);
+        };
+        let ty = match inferred {
+            Type::Unknown | Type::Vector | Type::Matrix => func.return_ty.clone(),
+            ty if result::is_builtin(&ty) => func.return_ty.clone(),
+            ty => ty,
+        };
+        (ty, func.return_dimension.clone())
+    }
+
     /// Map operator string to IR binary operator.
     fn map_operator(&self, op: &str) -> Result<IrBinOp> {
         match op {
@@ -699,18 +1739,48 @@ impl IrGenerator {
         }
     }
 
-    /// Create a dummy source location.
-    /// TODO: Extract actual file, line, column from expr.span()
-    fn dummy_source(&self) -> SourceLocation {
+    /// Returns the source location of `expr`.
+    fn source_of(&mut self, expr: &Expr) -> SourceLocation {
+        let mut root = expr.syntax();
+        while let Some(parent) = root.parent() {
+            root = parent;
+        }
+        let source_file = match &self.source_file {
+            Some((cached, source_file)) if cached == root => source_file,
+            _ => {
+                let text = root.text().to_string();
+                let source_file = SourceFile::new(&*self.file, text);
+                &self.source_file.insert((root.clone(), source_file)).1
+            }
+        };
+
+        // Spans of applications run to the end of their trailing whitespace
+        let span = expr.span();
+        let trailing = source_file.source[span.start..span.end].trim_end().len();
+        let span = Span::new(span.start, span.start + trailing);
+        let (line, column) = source_file.line_col(span.start);
+        let column = source_file
+            .line_text(line)
+            .and_then(|text| text.get(..column))
+            .map_or(column, |before| before.chars().count());
         SourceLocation {
-            file: InternedString::new("input"),
-            line: 1,
-            column: 0,
+            file: self.file,
+            line: line as u32 + 1,
+            column: column as u32 + 1,
+            span,
         }
     }
 
     /// Build and return the final IR module.
-    pub fn build(self) -> super::IrModule {
+    ///
+    /// Functions declared but never defined, such as those of another
+    /// package, become externs for [`link`](super::link) to resolve.
+    pub fn build(mut self) -> super::IrModule {
+        let mut declared: Vec<_> = self.declared.drain().collect();
+        declared.sort_by_key(|(_, id)| id.0);
+        for (name, id) in declared {
+            self.builder.extern_function(id, name);
+        }
         self.builder.build()
     }
 
@@ -724,13 +1794,36 @@ impl IrGenerator {
         &mut self.builder
     }
 
-    /// Check if a function with the given name already exists in the IR module.
+    /// Declares the top-level function `name` ahead of its definition, so the
+    /// IR of functions generated before it can call it, as mutually recursive
+    /// functions do.
+    pub fn declare_function(&mut self, name: InternedString) {
+        if self.function(name).is_none() {
+            let id = self.builder.declare_function();
+            self.declared.insert(name, id);
+        }
+    }
+
+    /// Check if IR has already been generated for the function with the given
+    /// name.
+    ///
+    /// A function whose generation failed counts too, so its errors are only
+    /// reported once.
     pub fn has_function(&self, name: InternedString) -> bool {
-        self.builder
-            .module()
-            .functions
-            .iter()
-            .any(|f| f.name == name)
+        self.functions.contains_key(&name)
+    }
+
+    /// Returns the id of the top-level function named `name`, if its IR was
+    /// generated.
+    pub fn function_id(&self, name: InternedString) -> Option<FunctionId> {
+        let id = *self.functions.get(&name)?;
+        self.builder.module().function(id).map(|_| id)
+    }
+
+    /// Returns the id of the module-level constant named `name`, if one was
+    /// generated.
+    pub fn global_id(&self, name: InternedString) -> Option<GlobalId> {
+        self.globals.get(&name).copied()
     }
 }
 
@@ -740,6 +1833,149 @@ impl Default for IrGenerator {
     }
 }
 
+/// Returns an error if `func` destructures any of its parameters, which IR
+/// generation doesn't support yet.
+fn check_no_patterns(func: &UserFunction) -> Result<()> {
+    match func.patterns.first() {
+        Some((_, pattern)) => Err(Diagnostic::syntax(
+            "destructuring parameters not yet supported in IR",
+        )
+        .with_span(pattern.span())),
+        None => Ok(()),
+    }
+}
+
+/// An instruction a math builtin lowers to instead of a call.
+#[derive(Debug, Clone, Copy)]
+enum Intrinsic {
+    Unary(IrUnOp),
+    Binary(IrBinOp),
+}
+
+/// Returns the instruction the math builtin `name` lowers to, if it has one
+/// (see [`crate::math`]).
+fn intrinsic(name: &str) -> Option<Intrinsic> {
+    match name {
+        "abs" => Some(Intrinsic::Unary(IrUnOp::Abs)),
+        "sqrt" => Some(Intrinsic::Unary(IrUnOp::Sqrt)),
+        "floor" => Some(Intrinsic::Unary(IrUnOp::Floor)),
+        "ceil" => Some(Intrinsic::Unary(IrUnOp::Ceil)),
+        "round" => Some(Intrinsic::Unary(IrUnOp::Round)),
+        "min" => Some(Intrinsic::Binary(IrBinOp::Min)),
+        "max" => Some(Intrinsic::Binary(IrBinOp::Max)),
+        _ => None,
+    }
+}
+
+/// Returns the names and types of the intrinsics that only take floats, for
+/// type inference.
+fn intrinsic_signatures() -> impl Iterator<Item = (InternedString, Type)> {
+    ["sqrt", "floor", "ceil", "round"]
+        .into_iter()
+        .map(|name| (name.into(), Type::function(vec![Type::Float], Type::Float)))
+}
+
+/// Returns the value of `expr` if it's a number literal, or a negated one.
+fn number_literal(expr: &Expr) -> Option<f64> {
+    use cadenza_syntax::ast::LiteralValue;
+
+    let lit = match expr {
+        Expr::Literal(lit) => lit,
+        Expr::Apply(apply) => {
+            let (Some(Expr::Op(op)), [operand]) = (apply.callee(), &apply.all_arguments()[..])
+            else {
+                return None;
+            };
+            return (op.syntax().text() == "-")
+                .then(|| number_literal(operand))
+                .flatten()
+                .map(|number| -number);
+        }
+        _ => return None,
+    };
+    match lit.value()? {
+        LiteralValue::Integer(i) => parse_integer(i.syntax().text().as_str())
+            .ok()
+            .map(|i| i as f64),
+        LiteralValue::Float(f) => f.syntax().text().to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Generates the number literal `number` as a float, as the elements of
+/// vectors and matrices are (see [`crate::linalg`]).
+fn float_literal(
+    number: f64,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> ValueId {
+    let result = block.const_val(IrConst::Float(number), Type::Float, source);
+    ctx.set_value_type(result, Type::Float);
+    result
+}
+
+/// Returns the dimension of the result of `op` on `lhs` and `rhs`.
+///
+/// Quantities added, subtracted or compared need the same dimension. A plain
+/// number combined with a quantity this way is taken in its base units, and a
+/// parameter combined with one takes its dimension.
+pub(crate) fn binop_dimension(
+    op: IrBinOp,
+    lhs: ValueId,
+    rhs: ValueId,
+    ctx: &mut IrGenContext,
+) -> Result<Option<DerivedDimension>> {
+    let lhs_dim = ctx.dimension(lhs).cloned();
+    let rhs_dim = ctx.dimension(rhs).cloned();
+    if lhs_dim.is_none() && rhs_dim.is_none() {
+        return Ok(None);
+    }
+    let verb = match op {
+        IrBinOp::Mul | IrBinOp::Div => {
+            let dimensionless = || DerivedDimension {
+                numerator: vec![],
+                denominator: vec![],
+            };
+            let lhs_dim = lhs_dim.unwrap_or_else(dimensionless);
+            let rhs_dim = rhs_dim.unwrap_or_else(dimensionless);
+            let dimension = if op == IrBinOp::Mul {
+                lhs_dim.multiply(&rhs_dim)
+            } else {
+                lhs_dim.divide(&rhs_dim)
+            };
+            return Ok((!dimension.is_dimensionless()).then_some(dimension));
+        }
+        IrBinOp::Add => "add",
+        IrBinOp::Sub => "subtract",
+        IrBinOp::Rem => "divide",
+        IrBinOp::Eq | IrBinOp::Ne | IrBinOp::Lt | IrBinOp::Le | IrBinOp::Gt | IrBinOp::Ge => {
+            "compare"
+        }
+        _ => return Ok(None),
+    };
+    let dimension = match (lhs_dim, rhs_dim) {
+        (Some(lhs_dim), Some(rhs_dim)) if lhs_dim != rhs_dim => {
+            return Err(Diagnostic::syntax(format!(
+                "cannot {verb} quantities with incompatible dimensions: {lhs_dim} and {rhs_dim}"
+            )));
+        }
+        (Some(dimension), _) | (_, Some(dimension)) => dimension,
+        (None, None) => unreachable!("one operand is a quantity"),
+    };
+    for operand in [lhs, rhs] {
+        if ctx.params.contains(&operand) && ctx.dimension(operand).is_none() {
+            // Quantities are floats
+            if matches!(ctx.get_value_type(operand), None | Some(Type::Unknown)) {
+                ctx.set_value_type(operand, Type::Float);
+            }
+            ctx.set_dimension(operand, dimension.clone());
+        }
+    }
+    // Comparisons return plain booleans
+    Ok((verb != "compare").then_some(dimension))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -767,7 +2003,7 @@ mod tests {
         let s = String::from("hello");
         let expected_s = InternedString::new("hello");
         assert_eq!(
-            generator.value_to_const(&Value::String(s)),
+            generator.value_to_const(&Value::String(s.into())),
             Some(IrConst::String(expected_s))
         );
     }
@@ -786,6 +2022,7 @@ mod tests {
         let func = UserFunction {
             name: InternedString::new("add"),
             params: vec![InternedString::new("a"), InternedString::new("b")],
+            patterns: vec![],
             body,
             captured_env: Env::new(),
         };
@@ -825,6 +2062,7 @@ mod tests {
         let func = UserFunction {
             name: InternedString::new("get_answer"),
             params: vec![],
+            patterns: vec![],
             body,
             captured_env: Env::new(),
         };
@@ -862,6 +2100,7 @@ mod tests {
         let func = UserFunction {
             name: InternedString::new("calc"),
             params: vec![InternedString::new("x"), InternedString::new("y")],
+            patterns: vec![],
             body,
             captured_env: Env::new(),
         };
@@ -899,6 +2138,7 @@ mod tests {
         let double_func = UserFunction {
             name: InternedString::new("double"),
             params: vec![InternedString::new("x")],
+            patterns: vec![],
             body,
             captured_env: Env::new(),
         };
@@ -916,6 +2156,7 @@ mod tests {
         let quadruple_func = UserFunction {
             name: InternedString::new("quadruple"),
             params: vec![InternedString::new("y")],
+            patterns: vec![],
             body: body2,
             captured_env: Env::new(),
         };
@@ -952,6 +2193,7 @@ mod tests {
         let add_func = UserFunction {
             name: InternedString::new("add"),
             params: vec![InternedString::new("a"), InternedString::new("b")],
+            patterns: vec![],
             body,
             captured_env: Env::new(),
         };
@@ -969,6 +2211,7 @@ mod tests {
         let compute_func = UserFunction {
             name: InternedString::new("compute"),
             params: vec![InternedString::new("x"), InternedString::new("y")],
+            patterns: vec![],
             body: body2,
             captured_env: Env::new(),
         };
@@ -1006,6 +2249,7 @@ mod tests {
         let countdown_func = UserFunction {
             name: InternedString::new("countdown"),
             params: vec![InternedString::new("n")],
+            patterns: vec![],
             body,
             captured_env: Env::new(),
         };
@@ -1041,6 +2285,7 @@ mod tests {
         let func = UserFunction {
             name: InternedString::new("sign"),
             params: vec![InternedString::new("x")],
+            patterns: vec![],
             body,
             captured_env: Env::new(),
         };
@@ -0,0 +1,353 @@
+//! The host interface generated modules import.
+//!
+//! Much of what Cadenza programs do (math beyond arithmetic, strings, output)
+//! has no WebAssembly instruction and no lowering yet. Until there is one,
+//! generated modules call the host for it, through a small versioned
+//! interface described in WIT (see [`WIT`]). The `host` directory of this
+//! crate has the WIT file and ready-made host implementations for JavaScript
+//! (`cadenza-host.js`) and wasmtime (`wasmtime.rs`).
+//!
+//! Each [`HostFunction`] is imported as a core function from the module named
+//! by its [`Interface`], such as `cadenza:host/math@0.1.0`. The IR generator
+//! lowers a call to the Cadenza name of a host function
+//! ([`HostFunction::callee`]), chosen by argument type (see [`resolve`]), to a
+//! call to an import, and the code generator imports the functions a module
+//! calls.
+//!
+//! Strings are host values that the module holds by handle: a string literal
+//! is stored in the module's memory and copied to the host with
+//! [`FROM_UTF8`], and the other string functions take and return handles.
+//! Handles are never released, so a long-running host should drop its
+//! strings along with the instance.
+//!
+//! `print` exists only in generated modules, where the host decides where
+//! output goes; the evaluator has no output of its own. `exit` and `abort`
+//! end the program, so the host doesn't return from them (a WASI host can
+//! implement `exit` with `proc_exit`), and the module traps if it does.
+
+use crate::{InternedString, Type};
+use wasm_encoder::ValType;
+
+/// The version of the host interface, part of each import's module name.
+pub const VERSION: &str = "0.1.0";
+
+/// The host interface in WIT.
+pub const WIT: &str = include_str!("../../host/cadenza-host.wit");
+
+/// An interface of the host: a group of related functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interface {
+    Math,
+    Strings,
+    Io,
+    Process,
+}
+
+impl Interface {
+    /// Returns the name of the interface in WIT.
+    pub fn name(self) -> &'static str {
+        match self {
+            Interface::Math => "math",
+            Interface::Strings => "strings",
+            Interface::Io => "io",
+            Interface::Process => "process",
+        }
+    }
+
+    /// Returns the name of the core module its functions are imported from.
+    pub fn module(self) -> String {
+        format!("cadenza:host/{}@{VERSION}", self.name())
+    }
+}
+
+/// The type of a host function's parameter or result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostType {
+    Integer,
+    Float,
+    Bool,
+    /// A handle to a host string.
+    Text,
+    /// A string in the module's memory, passed as a pointer and a length.
+    Utf8,
+}
+
+impl HostType {
+    /// Returns the Cadenza type of the values passed as this type.
+    pub fn cadenza(self) -> Type {
+        match self {
+            HostType::Integer => Type::Integer,
+            HostType::Float => Type::Float,
+            HostType::Bool => Type::Bool,
+            HostType::Text | HostType::Utf8 => Type::String,
+        }
+    }
+
+    /// Returns the core WebAssembly types this type is lowered to.
+    pub fn wasm(self) -> &'static [ValType] {
+        match self {
+            HostType::Integer => &[ValType::I64],
+            HostType::Float => &[ValType::F64],
+            HostType::Bool | HostType::Text => &[ValType::I32],
+            HostType::Utf8 => &[ValType::I32, ValType::I32],
+        }
+    }
+}
+
+/// A function the host provides.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct HostFunction {
+    pub interface: Interface,
+    /// The name of the function in WIT, which is also its import name.
+    pub name: &'static str,
+    /// The Cadenza function or operator lowered to this function, if any.
+    pub callee: Option<&'static str>,
+    pub params: &'static [HostType],
+    pub result: Option<HostType>,
+}
+
+impl HostFunction {
+    /// Returns the Cadenza type of a call's result: nil if it has none.
+    pub fn return_type(&self) -> Type {
+        self.result.map_or(Type::Nil, HostType::cadenza)
+    }
+
+    /// Returns the function's core WebAssembly parameter and result types.
+    pub fn signature(&self) -> (Vec<ValType>, Vec<ValType>) {
+        let params = self
+            .params
+            .iter()
+            .flat_map(|param| param.wasm().iter().copied())
+            .collect();
+        let results = self
+            .result
+            .map_or(Vec::new(), |result| result.wasm().to_vec());
+        (params, results)
+    }
+
+    /// Returns whether a call never returns: every function of the process
+    /// interface ends the program.
+    pub fn diverges(&self) -> bool {
+        self.interface == Interface::Process
+    }
+
+    /// Returns whether the function takes arguments of types `args`, where
+    /// an unknown type matches anything.
+    fn accepts(&self, args: &[Type]) -> bool {
+        self.params.len() == args.len()
+            && self
+                .params
+                .iter()
+                .zip(args)
+                .all(|(param, arg)| matches!(arg, Type::Unknown) || param.cadenza() == *arg)
+    }
+}
+
+/// Copies a string literal out of the module's memory.
+pub const FROM_UTF8: HostFunction = HostFunction {
+    interface: Interface::Strings,
+    name: "from-utf8",
+    callee: None,
+    params: &[HostType::Utf8],
+    result: Some(HostType::Text),
+};
+
+macro_rules! math {
+    ($name:literal, $arity:literal) => {
+        HostFunction {
+            interface: Interface::Math,
+            name: $name,
+            callee: Some($name),
+            params: &[HostType::Float; $arity],
+            result: Some(HostType::Float),
+        }
+    };
+}
+
+/// Every host function, in WIT order.
+pub static FUNCTIONS: &[HostFunction] = &[
+    math!("sin", 1),
+    math!("cos", 1),
+    math!("tan", 1),
+    math!("asin", 1),
+    math!("acos", 1),
+    math!("atan", 1),
+    math!("atan2", 2),
+    math!("exp", 1),
+    math!("ln", 1),
+    math!("log10", 1),
+    math!("pow", 2),
+    FROM_UTF8,
+    HostFunction {
+        interface: Interface::Strings,
+        name: "concat",
+        callee: Some("concat"),
+        params: &[HostType::Text, HostType::Text],
+        result: Some(HostType::Text),
+    },
+    HostFunction {
+        interface: Interface::Strings,
+        name: "equals",
+        callee: Some("=="),
+        params: &[HostType::Text, HostType::Text],
+        result: Some(HostType::Bool),
+    },
+    HostFunction {
+        interface: Interface::Strings,
+        name: "length",
+        callee: Some("len"),
+        params: &[HostType::Text],
+        result: Some(HostType::Integer),
+    },
+    HostFunction {
+        interface: Interface::Strings,
+        name: "from-int",
+        callee: Some("to_string"),
+        params: &[HostType::Integer],
+        result: Some(HostType::Text),
+    },
+    HostFunction {
+        interface: Interface::Strings,
+        name: "from-float",
+        callee: Some("to_string"),
+        params: &[HostType::Float],
+        result: Some(HostType::Text),
+    },
+    HostFunction {
+        interface: Interface::Strings,
+        name: "contains",
+        callee: Some("contains"),
+        params: &[HostType::Text, HostType::Text],
+        result: Some(HostType::Bool),
+    },
+    HostFunction {
+        interface: Interface::Strings,
+        name: "to-upper",
+        callee: Some("to_upper"),
+        params: &[HostType::Text],
+        result: Some(HostType::Text),
+    },
+    HostFunction {
+        interface: Interface::Io,
+        name: "print",
+        callee: Some("print"),
+        params: &[HostType::Text],
+        result: None,
+    },
+    HostFunction {
+        interface: Interface::Process,
+        name: "exit",
+        callee: Some("exit"),
+        params: &[HostType::Integer],
+        result: None,
+    },
+    HostFunction {
+        interface: Interface::Process,
+        name: "abort",
+        callee: Some("abort"),
+        params: &[HostType::Text],
+        result: None,
+    },
+];
+
+/// Returns the host function that a call to `callee` with arguments of types
+/// `args` lowers to, if any.
+///
+/// Unknown argument types match anything, so a call whose types aren't
+/// inferred still resolves when only one function fits.
+pub fn resolve(callee: &str, args: &[Type]) -> Option<&'static HostFunction> {
+    let mut candidates = FUNCTIONS
+        .iter()
+        .filter(|function| function.callee == Some(callee) && function.accepts(args));
+    let function = candidates.next()?;
+    candidates.next().is_none().then_some(function)
+}
+
+/// Returns whether `name` is the Cadenza name of any host function.
+pub fn is_callee(name: &str) -> bool {
+    FUNCTIONS
+        .iter()
+        .any(|function| function.callee == Some(name))
+}
+
+/// Returns the Cadenza names of the host functions that aren't overloaded,
+/// with their types, for type inference.
+pub fn signatures() -> impl Iterator<Item = (InternedString, Type)> {
+    FUNCTIONS.iter().filter_map(|function| {
+        let callee = function.callee?;
+        let overloads = FUNCTIONS
+            .iter()
+            .filter(|other| other.callee == Some(callee))
+            .count();
+        let is_operator = !callee.starts_with(|c: char| c.is_alphabetic());
+        (overloads == 1 && !is_operator).then(|| {
+            let params = function
+                .params
+                .iter()
+                .map(|param| param.cadenza())
+                .collect();
+            (
+                callee.into(),
+                Type::function(params, function.return_type()),
+            )
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn functions_match_the_wit() {
+        for function in FUNCTIONS {
+            let interface = function.interface.name();
+            let start = WIT
+                .find(&format!("interface {interface} {{"))
+                .unwrap_or_else(|| panic!("no interface {interface}"));
+            let end = start + WIT[start..].find("\n}").unwrap();
+            assert!(
+                WIT[start..end].contains(&format!("    {}: func(", function.name)),
+                "{interface} has no {}",
+                function.name
+            );
+        }
+        assert!(WIT.contains(&format!("package cadenza:host@{VERSION};")));
+    }
+
+    #[test]
+    fn shims_implement_every_function() {
+        let js = include_str!("../../host/cadenza-host.js");
+        let wasmtime = include_str!("../../host/wasmtime.rs");
+        for function in FUNCTIONS {
+            assert!(
+                js.contains(&format!("\"{}\":", function.name)),
+                "JS shim is missing {}",
+                function.name
+            );
+            assert!(
+                wasmtime.contains(&format!("\"{}\",", function.name)),
+                "wasmtime shim is missing {}",
+                function.name
+            );
+        }
+        assert!(js.contains(&format!("VERSION = \"{VERSION}\"")));
+        assert!(wasmtime.contains(&format!("VERSION: &str = \"{VERSION}\"")));
+    }
+
+    #[test]
+    fn resolves_by_argument_type() {
+        let name = |callee, args: &[Type]| resolve(callee, args).map(|function| function.name);
+        assert_eq!(name("to_string", &[Type::Integer]), Some("from-int"));
+        assert_eq!(name("to_string", &[Type::Float]), Some("from-float"));
+        assert_eq!(name("to_string", &[Type::Unknown]), None);
+        assert_eq!(name("sin", &[Type::Unknown]), Some("sin"));
+        assert_eq!(name("len", &[Type::list(Type::Integer)]), None);
+        assert_eq!(name("==", &[Type::String, Type::String]), Some("equals"));
+
+        let signatures: Vec<_> = signatures().map(|(name, _)| name.to_string()).collect();
+        assert!(signatures.contains(&"pow".to_string()));
+        assert!(!signatures.contains(&"to_string".to_string()));
+        assert!(!signatures.contains(&"==".to_string()));
+    }
+}
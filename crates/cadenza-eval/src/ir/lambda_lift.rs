@@ -0,0 +1,110 @@
+//! Lambda lifting.
+//!
+//! IR functions are flat, so a function defined inside another function's body
+//! is lifted to a top-level IR function when its enclosing function is
+//! generated:
+//!
+//! ```cadenza
+//! fn outer x =
+//!     fn add y = x + y
+//!     add 1
+//! ```
+//!
+//! becomes, in IR:
+//!
+//! ```text
+//! fn outer.add(env: { x = integer }, y) = (field env.x) + y
+//! fn outer(x) = call outer.add (record { x = x }) 1
+//! ```
+//!
+//! 1. **Free variables**: the variables of the enclosing function that the
+//!    nested body mentions ([`free_variables`]) are its captures.
+//! 2. **Environment**: the captures are packed into a record at the
+//!    definition, in order of first use.
+//! 3. **Lifting**: the lifted function takes the environment as its first
+//!    parameter and unpacks each capture with a field access on entry.
+//! 4. **Call sites**: calls to the nested function pass the environment
+//!    before the arguments ([`LiftedFunction`]).
+//!
+//! A nested function that captures another nested function captures its
+//! environment, so the callee can still be called from the lifted body. A
+//! function that captures nothing takes no environment and is called
+//! directly.
+
+use super::{FunctionId, ValueId};
+use crate::{interner::InternedString, module_graph::FunctionDef, value::Type};
+use cadenza_syntax::token::Kind;
+
+/// A nested function that has been lifted to a top-level IR function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiftedFunction {
+    /// The lifted function.
+    pub id: FunctionId,
+    /// The environment record passed as the first argument, with its type,
+    /// or `None` if the function captures nothing.
+    pub env: Option<(ValueId, Type)>,
+}
+
+/// A variable of an enclosing function captured by a lifted function.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Capture {
+    /// The captured name.
+    pub name: InternedString,
+    /// The captured value in the enclosing function.
+    pub value: ValueId,
+    /// The type of the captured value.
+    pub ty: Type,
+    /// If the capture is the environment of a lifted function, that function.
+    pub function: Option<FunctionId>,
+}
+
+/// Returns the type of the environment record holding `captures`.
+pub(crate) fn env_type(captures: &[Capture]) -> Type {
+    Type::Record(
+        captures
+            .iter()
+            .map(|capture| (capture.name, capture.ty.clone()))
+            .collect(),
+    )
+}
+
+/// Returns the identifiers in the body of `func` that aren't its parameters
+/// or its own name, in order of first use.
+///
+/// The analysis is syntactic and conservative: a name shadowed by a `let` in
+/// the body is still reported. Callers keep only the names that resolve to
+/// variables of the enclosing function.
+pub(crate) fn free_variables(func: &FunctionDef) -> Vec<InternedString> {
+    let mut names = Vec::new();
+    for token in func
+        .body
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == Kind::Identifier)
+    {
+        let name = token.text().interned();
+        if name != func.name && !func.params.contains(&name) && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_graph::function_definition;
+    use cadenza_syntax::parse::parse;
+
+    #[test]
+    fn free_variables_skip_params_and_self() {
+        let root = parse("fn go n = go (n - step) + base * step\n").ast();
+        let func = function_definition(&root.items().next().unwrap()).unwrap();
+        let free: Vec<String> = free_variables(&func)
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(free, vec!["step", "base"]);
+    }
+}
@@ -0,0 +1,314 @@
+//! Linking separately compiled modules.
+//!
+//! A package can be compiled to an [`IrModule`] on its own, calling the
+//! functions of other packages through externs (see [`IrExtern`]). [`link`]
+//! combines the modules of a program into one for the final WASM emit: it
+//! merges their functions, globals, imports and exports, resolves each
+//! extern by name to the function another module exports, and renumbers the
+//! function and global IDs so they are unique in the combined module.
+//!
+//! Value and block IDs are local to their function, so they are kept.
+
+use super::{
+    FunctionId, GlobalId, IrExport, IrExportKind, IrExtern, IrGlobal, IrImport, IrInstr, IrModule,
+};
+use crate::InternedString;
+use std::{collections::HashMap, fmt};
+
+/// An error linking modules (see [`link`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkError {
+    /// More than one module exports `name`.
+    DuplicateExport { name: InternedString },
+    /// The extern `name` doesn't name a function exported by any module.
+    Unresolved { name: InternedString },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::DuplicateExport { name } => {
+                write!(f, "{name} is exported by more than one module")
+            }
+            LinkError::Unresolved { name } => {
+                write!(
+                    f,
+                    "{name} is declared but no module exports a function by that name"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// Links `modules` into one module.
+///
+/// The definitions keep their order: the functions and globals of the first
+/// module come first. A host function imported by several modules is
+/// imported once. Externs that another module's export resolves are calls
+/// to that function; an extern that no module resolves, or a name exported
+/// twice, is an error, and every error is reported.
+pub fn link(modules: &[IrModule]) -> Result<IrModule, Vec<LinkError>> {
+    let mut linked = IrModule::new();
+    let mut ids = Ids::default();
+    let mut errors = Vec::new();
+
+    // Give every definition its ID in the linked module
+    let mut renumberings: Vec<_> = modules
+        .iter()
+        .map(|module| {
+            let mut renumbering = Renumbering::default();
+            for import in &module.imports {
+                let id = match linked
+                    .imports
+                    .iter()
+                    .find(|linked| linked.function == import.function)
+                {
+                    Some(linked) => linked.id,
+                    None => {
+                        let id = ids.function();
+                        linked.imports.push(IrImport {
+                            id,
+                            function: import.function,
+                        });
+                        id
+                    }
+                };
+                renumbering.functions.insert(import.id, id);
+            }
+            for func in &module.functions {
+                renumbering.functions.insert(func.id, ids.function());
+            }
+            for global in &module.globals {
+                let id = renumbering.global(global.id, &mut ids);
+                linked.globals.push(IrGlobal {
+                    id,
+                    ..global.clone()
+                });
+            }
+            renumbering
+        })
+        .collect();
+
+    // The exports are the symbols externs resolve to
+    let mut symbols = HashMap::new();
+    for (module, renumbering) in modules.iter().zip(&mut renumberings) {
+        for export in &module.exports {
+            let kind = match export.kind {
+                IrExportKind::Function(id) => {
+                    IrExportKind::Function(renumbering.function(id, &mut ids))
+                }
+                IrExportKind::Constant(id) => {
+                    IrExportKind::Constant(renumbering.global(id, &mut ids))
+                }
+            };
+            if symbols.insert(export.name, kind.clone()).is_some() {
+                report(
+                    &mut errors,
+                    LinkError::DuplicateExport { name: export.name },
+                );
+            }
+            linked.exports.push(IrExport {
+                name: export.name,
+                kind,
+            });
+        }
+    }
+
+    for (module, renumbering) in modules.iter().zip(&mut renumberings) {
+        for IrExtern { id, name } in &module.externs {
+            match symbols.get(name) {
+                Some(IrExportKind::Function(resolved)) => {
+                    renumbering.functions.insert(*id, *resolved);
+                }
+                _ => report(&mut errors, LinkError::Unresolved { name: *name }),
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    for (module, renumbering) in modules.iter().zip(&mut renumberings) {
+        for func in &module.functions {
+            let mut func = func.clone();
+            func.id = renumbering.function(func.id, &mut ids);
+            let instructions = func
+                .blocks
+                .iter_mut()
+                .flat_map(|block| &mut block.instructions);
+            for instr in instructions {
+                match instr {
+                    IrInstr::Call { func, .. } => *func = renumbering.function(*func, &mut ids),
+                    IrInstr::Global { global, .. } => {
+                        *global = renumbering.global(*global, &mut ids)
+                    }
+                    _ => {}
+                }
+            }
+            linked.functions.push(func);
+        }
+    }
+    Ok(linked)
+}
+
+/// Adds `error` to `errors` unless it was already reported.
+fn report(errors: &mut Vec<LinkError>, error: LinkError) {
+    if !errors.contains(&error) {
+        errors.push(error);
+    }
+}
+
+/// The next unused IDs of the linked module.
+#[derive(Default)]
+struct Ids {
+    next_function: u32,
+    next_global: u32,
+}
+
+impl Ids {
+    fn function(&mut self) -> FunctionId {
+        let id = FunctionId(self.next_function);
+        self.next_function += 1;
+        id
+    }
+
+    fn global(&mut self) -> GlobalId {
+        let id = GlobalId(self.next_global);
+        self.next_global += 1;
+        id
+    }
+}
+
+/// The IDs of one module's definitions in the linked module.
+#[derive(Default)]
+struct Renumbering {
+    functions: HashMap<FunctionId, FunctionId>,
+    globals: HashMap<GlobalId, GlobalId>,
+}
+
+impl Renumbering {
+    /// Returns the linked ID of the function `id`. An ID the module neither
+    /// defines nor declares gets a fresh ID, so it stays undefined rather
+    /// than naming another module's function.
+    fn function(&mut self, id: FunctionId, ids: &mut Ids) -> FunctionId {
+        *self.functions.entry(id).or_insert_with(|| ids.function())
+    }
+
+    /// Returns the linked ID of the global `id`.
+    fn global(&mut self, id: GlobalId, ids: &mut Ids) -> GlobalId {
+        *self.globals.entry(id).or_insert_with(|| ids.global())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CompileOptions, Type,
+        ir::{IrBuilder, IrConst, SourceLocation, generate_wasm, host},
+    };
+
+    fn source() -> SourceLocation {
+        SourceLocation {
+            file: InternedString::new("test.cdz"),
+            line: 1,
+            column: 1,
+            span: 0.into(),
+        }
+    }
+
+    /// A module exporting `name`, which returns the global `value` plus one.
+    fn library(name: &str, value: i64) -> IrModule {
+        let mut builder = IrBuilder::new();
+        builder.import(&host::FROM_UTF8);
+        let global = builder.global(
+            InternedString::new("base"),
+            Type::Integer,
+            IrConst::Integer(value),
+        );
+        let mut func = builder.function(InternedString::new(name), vec![], Type::Integer);
+        let mut block = func.block();
+        let base = block.global(global, Type::Integer, source());
+        let one = block.const_val(IrConst::Integer(1), Type::Integer, source());
+        let sum = block.binop(super::super::BinOp::Add, base, one, Type::Integer, source());
+        let (block, next) = block.ret(Some(sum), source());
+        func.add_block(block, next);
+        let id = builder.add_function(func.build());
+        builder.export_function(InternedString::new(name), id);
+        builder.build()
+    }
+
+    /// A module exporting `main`, which calls the extern `callee`.
+    fn program(callee: &str) -> IrModule {
+        let mut builder = IrBuilder::new();
+        builder.import(&host::FROM_UTF8);
+        let external = builder.declare_function();
+        builder.extern_function(external, InternedString::new(callee));
+        let mut func = builder.function(InternedString::new("main"), vec![], Type::Integer);
+        let mut block = func.block();
+        let result = block.call(external, vec![], Type::Integer, source());
+        let (block, next) = block.ret(Some(result), source());
+        func.add_block(block, next);
+        let id = builder.add_function(func.build());
+        builder.export_function(InternedString::new("main"), id);
+        builder.build()
+    }
+
+    #[test]
+    fn resolves_externs_to_exports() {
+        let modules = [program("next"), library("next", 41)];
+        assert!(generate_wasm(&modules[0], &CompileOptions::default()).is_err());
+
+        let linked = link(&modules).unwrap();
+        assert_eq!(linked.imports.len(), 1);
+        assert!(linked.externs.is_empty());
+        let [main, next] = &linked.functions[..] else {
+            panic!("expected two functions: {linked}");
+        };
+        assert_ne!(main.id, next.id);
+        assert_ne!(main.id, linked.imports[0].id);
+        let callee = main.blocks[0]
+            .instructions
+            .iter()
+            .find_map(|instr| match instr {
+                IrInstr::Call { func, .. } => Some(*func),
+                _ => None,
+            });
+        assert_eq!(callee, Some(next.id));
+        assert!(generate_wasm(&linked, &CompileOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn renumbers_globals() {
+        let linked = link(&[library("first", 1), library("second", 2)]).unwrap();
+        let ids: Vec<_> = linked.globals.iter().map(|global| global.id).collect();
+        assert_eq!(ids, [GlobalId(0), GlobalId(1)]);
+        let read =
+            linked.functions[1].blocks[0]
+                .instructions
+                .iter()
+                .find_map(|instr| match instr {
+                    IrInstr::Global { global, .. } => Some(*global),
+                    _ => None,
+                });
+        assert_eq!(read, Some(GlobalId(1)));
+    }
+
+    #[test]
+    fn reports_duplicate_exports_and_unresolved_externs() {
+        let errors = link(&[library("next", 1), library("next", 2), program("missing")]);
+        assert_eq!(
+            errors,
+            Err(vec![
+                LinkError::DuplicateExport {
+                    name: InternedString::new("next"),
+                },
+                LinkError::Unresolved {
+                    name: InternedString::new("missing"),
+                },
+            ])
+        );
+    }
+}
@@ -57,6 +57,10 @@ fn eliminate_common_subexpressions_in_function(func: &mut IrFunction) -> bool {
                     let record = *replacements.get(record).unwrap_or(record);
                     Some(ExprKey::Field(record, *field))
                 }
+                // Each read of a linear constant is a new value to own
+                IrInstr::Global { global, ty, .. } if !ty.is_linear() => {
+                    Some(ExprKey::Global(*global))
+                }
                 _ => None,
             };
 
@@ -121,6 +125,11 @@ fn eliminate_common_subexpressions_in_function(func: &mut IrFunction) -> bool {
                         }
                     }
                 }
+                IrInstr::Drop { value, .. } | IrInstr::Assert { cond: value, .. } => {
+                    if let Some(&new_value) = replacements.get(value) {
+                        *value = new_value;
+                    }
+                }
                 _ => {}
             }
         }
@@ -150,4 +159,5 @@ enum ExprKey {
     BinOp(BinOp, ValueId, ValueId),
     UnOp(UnOp, ValueId),
     Field(ValueId, InternedString),
+    Global(GlobalId),
 }
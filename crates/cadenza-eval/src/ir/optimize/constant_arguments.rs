@@ -0,0 +1,219 @@
+//! Interprocedural constant argument propagation pass.
+//!
+//! Macro-generated code often calls a helper with the same constant in some
+//! argument at every call site. This pass finds the parameters that receive
+//! one constant from every call, moves the constant into the function, and
+//! removes the parameter and the arguments passed for it. Constant folding
+//! can then fold the constant into the function body, dead code elimination
+//! removes the constants the callers no longer pass, and common
+//! subexpression elimination merges what's left.
+//!
+//! A constant is a literal or a read of a module-level constant. Only
+//! functions whose every call is in the module are specialized: exported
+//! functions keep their parameters, since the host can call them with any
+//! arguments, and so do functions that are never called.
+
+use super::{OptimizationPass, types::*};
+use std::collections::{HashMap, HashSet};
+
+/// Interprocedural constant argument propagation pass.
+///
+/// Replaces each parameter that every call passes the same constant with
+/// that constant.
+pub struct ConstantArgumentPass;
+
+impl OptimizationPass for ConstantArgumentPass {
+    fn run(&mut self, module: &mut IrModule) -> bool {
+        let exported: HashSet<FunctionId> = module
+            .exports
+            .iter()
+            .filter_map(|export| match export.kind {
+                IrExportKind::Function(func) => Some(func),
+                IrExportKind::Constant(_) => None,
+            })
+            .collect();
+        let arity: HashMap<FunctionId, usize> = module
+            .functions
+            .iter()
+            .filter(|func| !exported.contains(&func.id))
+            .map(|func| (func.id, func.params.len()))
+            .collect();
+
+        // Meet the arguments of every call to each function
+        let mut arguments: HashMap<FunctionId, Vec<Argument>> = HashMap::new();
+        for func in &module.functions {
+            let constants = constants_in(func);
+            for instr in func.blocks.iter().flat_map(|block| &block.instructions) {
+                let IrInstr::Call {
+                    func: callee, args, ..
+                } = instr
+                else {
+                    continue;
+                };
+                let Some(&arity) = arity.get(callee) else {
+                    continue;
+                };
+                let seen = arguments
+                    .entry(*callee)
+                    .or_insert_with(|| vec![Argument::Unseen; arity]);
+                for (seen, arg) in seen.iter_mut().zip(args) {
+                    seen.meet(constants.get(arg).copied());
+                }
+            }
+        }
+
+        // Specialize the functions, then the calls to them
+        let mut removed: HashMap<FunctionId, Vec<bool>> = HashMap::new();
+        for func in &mut module.functions {
+            let Some(arguments) = arguments.get(&func.id) else {
+                continue;
+            };
+            if !arguments
+                .iter()
+                .any(|arg| matches!(arg, Argument::Constant(_)))
+            {
+                continue;
+            }
+            let entry = func
+                .blocks
+                .iter_mut()
+                .find(|block| block.id == func.entry_block)
+                .expect("function has no entry block");
+            let mut params = vec![];
+            let mut defined = vec![];
+            for (param, arg) in func.params.drain(..).zip(arguments) {
+                match arg {
+                    Argument::Constant(instr) => defined.push(with_result(instr, param.value_id)),
+                    Argument::Unseen | Argument::Varying => params.push(param),
+                }
+            }
+            entry.instructions.splice(0..0, defined);
+            func.params = params;
+            removed.insert(
+                func.id,
+                arguments
+                    .iter()
+                    .map(|arg| matches!(arg, Argument::Constant(_)))
+                    .collect(),
+            );
+        }
+
+        for instr in module
+            .functions
+            .iter_mut()
+            .flat_map(|func| &mut func.blocks)
+            .flat_map(|block| &mut block.instructions)
+        {
+            if let IrInstr::Call { func, args, .. } = instr
+                && let Some(removed) = removed.get(func)
+            {
+                let mut removed = removed.iter();
+                args.retain(|_| !removed.next().copied().unwrap_or(false));
+            }
+        }
+
+        !removed.is_empty()
+    }
+
+    fn name(&self) -> &str {
+        "constant_arguments"
+    }
+}
+
+/// What the calls to a function pass for one of its parameters.
+#[derive(Clone)]
+enum Argument {
+    /// No call has been seen yet.
+    Unseen,
+    /// Every call passes the constant defined by this instruction.
+    Constant(IrInstr),
+    /// The calls pass different or non-constant values.
+    Varying,
+}
+
+impl Argument {
+    /// Combines what the calls seen so far pass with the argument of another
+    /// call, defined by `instr` if it is a constant.
+    fn meet(&mut self, instr: Option<&IrInstr>) {
+        *self = match (&*self, instr) {
+            (Argument::Unseen, Some(instr)) => Argument::Constant(instr.clone()),
+            (Argument::Constant(seen), Some(instr)) if same_constant(seen, instr) => return,
+            _ => Argument::Varying,
+        };
+    }
+}
+
+/// Returns the instructions of `func` that define constants, by their
+/// result.
+fn constants_in(func: &IrFunction) -> HashMap<ValueId, &IrInstr> {
+    func.blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|instr| match instr {
+            IrInstr::Const { result, .. } | IrInstr::Global { result, .. } => {
+                Some((*result, instr))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns whether two constant instructions define the same value, with the
+/// same type.
+///
+/// Floats are compared by their bits, so `0.0` and `-0.0` differ and a NaN
+/// matches itself.
+fn same_constant(a: &IrInstr, b: &IrInstr) -> bool {
+    match (a, b) {
+        (
+            IrInstr::Const {
+                ty: a_ty,
+                value: a_value,
+                ..
+            },
+            IrInstr::Const {
+                ty: b_ty,
+                value: b_value,
+                ..
+            },
+        ) => {
+            a_ty == b_ty
+                && match (a_value, b_value) {
+                    (IrConst::Float(a), IrConst::Float(b)) => a.to_bits() == b.to_bits(),
+                    (
+                        IrConst::Quantity {
+                            value: a,
+                            dimension: a_dimension,
+                        },
+                        IrConst::Quantity {
+                            value: b,
+                            dimension: b_dimension,
+                        },
+                    ) => a.to_bits() == b.to_bits() && a_dimension == b_dimension,
+                    _ => a_value == b_value,
+                }
+        }
+        (
+            IrInstr::Global {
+                ty: a_ty,
+                global: a,
+                ..
+            },
+            IrInstr::Global {
+                ty: b_ty,
+                global: b,
+                ..
+            },
+        ) => a_ty == b_ty && a == b,
+        _ => false,
+    }
+}
+
+/// Returns a copy of the constant instruction `instr` defining `result`.
+fn with_result(instr: &IrInstr, result: ValueId) -> IrInstr {
+    let mut instr = instr.clone();
+    if let IrInstr::Const { result: r, .. } | IrInstr::Global { result: r, .. } = &mut instr {
+        *r = result;
+    }
+    instr
+}
@@ -1,7 +1,8 @@
 //! Constant folding optimization pass.
 //!
 //! This pass evaluates operations on constant values at compile time,
-//! replacing them with their computed results.
+//! replacing them with their computed results, and removes the asserts whose
+//! condition is always true.
 
 use super::{OptimizationPass, types::*};
 use std::collections::HashMap;
@@ -101,6 +102,19 @@ fn fold_constants_in_function(func: &mut IrFunction) -> bool {
                 _ => {}
             }
         }
+
+        // A check that always passes has nothing to check
+        let original_len = block.instructions.len();
+        block.instructions.retain(|instr| {
+            !matches!(
+                instr,
+                IrInstr::Assert { cond, .. }
+                    if matches!(const_values.get(cond), Some(IrConst::Bool(true)))
+            )
+        });
+        if block.instructions.len() < original_len {
+            changed = true;
+        }
     }
 
     changed
@@ -109,13 +123,16 @@ fn fold_constants_in_function(func: &mut IrFunction) -> bool {
 /// Attempt to fold a binary operation on two constants.
 fn fold_binop(op: BinOp, lhs: &IrConst, rhs: &IrConst) -> Option<IrConst> {
     match (lhs, rhs) {
-        // Integer arithmetic
+        // Integer arithmetic, left unfolded to trap at run time if it
+        // overflows
         (IrConst::Integer(a), IrConst::Integer(b)) => match op {
-            BinOp::Add => Some(IrConst::Integer(a.wrapping_add(*b))),
-            BinOp::Sub => Some(IrConst::Integer(a.wrapping_sub(*b))),
-            BinOp::Mul => Some(IrConst::Integer(a.wrapping_mul(*b))),
-            BinOp::Div if *b != 0 => Some(IrConst::Integer(a / b)),
-            BinOp::Rem if *b != 0 => Some(IrConst::Integer(a % b)),
+            BinOp::Add => a.checked_add(*b).map(IrConst::Integer),
+            BinOp::Sub => a.checked_sub(*b).map(IrConst::Integer),
+            BinOp::Mul => a.checked_mul(*b).map(IrConst::Integer),
+            BinOp::Div => a.checked_div(*b).map(IrConst::Integer),
+            BinOp::Rem => a.checked_rem(*b).map(IrConst::Integer),
+            BinOp::Min => Some(IrConst::Integer(*a.min(b))),
+            BinOp::Max => Some(IrConst::Integer(*a.max(b))),
             BinOp::Eq => Some(IrConst::Bool(a == b)),
             BinOp::Ne => Some(IrConst::Bool(a != b)),
             BinOp::Lt => Some(IrConst::Bool(a < b)),
@@ -159,12 +176,18 @@ fn fold_binop(op: BinOp, lhs: &IrConst, rhs: &IrConst) -> Option<IrConst> {
 fn fold_unop(op: UnOp, operand: &IrConst) -> Option<IrConst> {
     match operand {
         IrConst::Integer(n) => match op {
-            UnOp::Neg => Some(IrConst::Integer(-n)),
+            UnOp::Neg => n.checked_neg().map(IrConst::Integer),
+            UnOp::Abs => n.checked_abs().map(IrConst::Integer),
             UnOp::BitNot => Some(IrConst::Integer(!n)),
             _ => None,
         },
         IrConst::Float(f) => match op {
             UnOp::Neg => Some(IrConst::Float(-f)),
+            UnOp::Abs => Some(IrConst::Float(f.abs())),
+            UnOp::Sqrt => Some(IrConst::Float(f.sqrt())),
+            UnOp::Floor => Some(IrConst::Float(f.floor())),
+            UnOp::Ceil => Some(IrConst::Float(f.ceil())),
+            UnOp::Round => Some(IrConst::Float(f.round_ties_even())),
             _ => None,
         },
         IrConst::Bool(b) => match op {
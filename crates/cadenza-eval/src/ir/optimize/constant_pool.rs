@@ -0,0 +1,169 @@
+//! Constant pooling pass.
+//!
+//! This pass moves constants that several instructions repeat into a pool of
+//! module-level constants, shared by every function, and replaces each
+//! repeat with a read of its pool entry. Repeats of a constant equal to an
+//! existing module-level constant read that instead.
+//!
+//! Only constants that are worth sharing are pooled: strings, which the code
+//! generator already stores once, and numbers whose encoding in a WASM
+//! instruction is long enough that the global pays for itself (see
+//! [`pays_off`]). Pooling hides a constant from constant folding, so this
+//! pass runs once the other passes are done (see
+//! [`OptimizationPipeline::add_final_pass`](super::OptimizationPipeline::add_final_pass)).
+
+use super::{OptimizationPass, types::*};
+use crate::{InternedString, Type};
+use std::collections::HashMap;
+
+/// Constant pooling pass.
+///
+/// Pools the constants repeated across the module into module-level
+/// constants named `pool0`, `pool1` and so on.
+pub struct ConstantPoolPass;
+
+impl OptimizationPass for ConstantPoolPass {
+    fn run(&mut self, module: &mut IrModule) -> bool {
+        // Count the uses of each constant worth pooling
+        let mut uses: HashMap<(PoolKey, Type), usize> = HashMap::new();
+        let mut order = vec![];
+        for instr in instructions(module) {
+            if let IrInstr::Const { value, ty, .. } = instr
+                && let Some(key) = PoolKey::new(value)
+            {
+                let count = uses.entry((key.clone(), ty.clone())).or_default();
+                if *count == 0 {
+                    order.push((key, ty.clone(), value.clone()));
+                }
+                *count += 1;
+            }
+        }
+
+        // Pool the constants that pay off, reusing the existing constants
+        let existing: HashMap<(PoolKey, Type), GlobalId> = module
+            .globals
+            .iter()
+            .filter_map(|global| {
+                let key = PoolKey::new(&global.value)?;
+                Some(((key, global.ty.clone()), global.id))
+            })
+            .collect();
+        let mut pool = HashMap::new();
+        let mut next_id = module.globals.iter().map(|g| g.id.0 + 1).max().unwrap_or(0);
+        let mut pooled = 0;
+        for (key, ty, value) in order {
+            let key = (key, ty);
+            if !pays_off(&key.0, uses[&key]) {
+                continue;
+            }
+            let id = match existing.get(&key) {
+                Some(&id) => id,
+                None => {
+                    let id = GlobalId(next_id);
+                    next_id += 1;
+                    module.globals.push(IrGlobal {
+                        id,
+                        name: InternedString::new(&format!("pool{pooled}")),
+                        ty: key.1.clone(),
+                        value,
+                    });
+                    pooled += 1;
+                    id
+                }
+            };
+            pool.insert(key, id);
+        }
+
+        let mut changed = false;
+        for func in &mut module.functions {
+            for block in &mut func.blocks {
+                for instr in &mut block.instructions {
+                    let IrInstr::Const {
+                        result,
+                        ty,
+                        value,
+                        source,
+                    } = instr
+                    else {
+                        continue;
+                    };
+                    let Some(key) = PoolKey::new(value) else {
+                        continue;
+                    };
+                    if let Some(&global) = pool.get(&(key, ty.clone())) {
+                        *instr = IrInstr::Global {
+                            result: *result,
+                            ty: ty.clone(),
+                            global,
+                            source: *source,
+                        };
+                        changed = true;
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    fn name(&self) -> &str {
+        "constant_pool"
+    }
+}
+
+/// A constant that can be pooled, compared by its bits.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum PoolKey {
+    Integer(i64),
+    Float(u64),
+    String(InternedString),
+}
+
+impl PoolKey {
+    fn new(value: &IrConst) -> Option<Self> {
+        match value {
+            IrConst::Integer(i) => Some(PoolKey::Integer(*i)),
+            // The dimension of a quantity is in its type
+            IrConst::Float(value) | IrConst::Quantity { value, .. } => {
+                Some(PoolKey::Float(value.to_bits()))
+            }
+            IrConst::String(text) => Some(PoolKey::String(*text)),
+            IrConst::Nil | IrConst::Bool(_) => None,
+        }
+    }
+}
+
+/// Returns whether pooling a constant used `count` times makes the module
+/// smaller.
+///
+/// In WASM, each use of a pooled number is a `global.get` instead of a
+/// constant instruction, saving the bytes of the immediate but about one,
+/// while the global costs its type, mutability and initializer: about the
+/// immediate and four more bytes. A string is stored once either way, so
+/// pooling one only shrinks the IR.
+fn pays_off(key: &PoolKey, count: usize) -> bool {
+    let immediate = match key {
+        PoolKey::Integer(i) => signed_leb128_len(*i),
+        PoolKey::Float(_) => 8,
+        PoolKey::String(_) => return count > 1,
+    };
+    count * (immediate - 1) > immediate + 4
+}
+
+/// Returns the length of `value` in signed LEB128, the encoding of an
+/// `i64.const` immediate.
+fn signed_leb128_len(mut value: i64) -> usize {
+    let mut len = 1;
+    while !(-64..64).contains(&value) {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+fn instructions(module: &IrModule) -> impl Iterator<Item = &IrInstr> {
+    module
+        .functions
+        .iter()
+        .flat_map(|func| &func.blocks)
+        .flat_map(|block| &block.instructions)
+}
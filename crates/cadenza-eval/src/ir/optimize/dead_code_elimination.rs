@@ -1,7 +1,8 @@
 //! Dead code elimination optimization pass.
 //!
 //! This pass removes instructions that produce values that are never used.
-//! It also removes unreachable basic blocks.
+//! It also removes unreachable basic blocks, and module-level constants that
+//! are neither read nor exported.
 
 use super::{OptimizationPass, types::*};
 use std::collections::HashSet;
@@ -19,6 +20,7 @@ impl OptimizationPass for DeadCodeEliminationPass {
         for func in &mut module.functions {
             changed |= eliminate_dead_code_in_function(func);
         }
+        changed |= eliminate_unused_globals(module);
 
         changed
     }
@@ -28,6 +30,32 @@ impl OptimizationPass for DeadCodeEliminationPass {
     }
 }
 
+/// Remove the module-level constants that no function reads and no export
+/// names.
+fn eliminate_unused_globals(module: &mut IrModule) -> bool {
+    let reads = module
+        .functions
+        .iter()
+        .flat_map(|func| &func.blocks)
+        .flat_map(|block| &block.instructions)
+        .filter_map(|instr| match instr {
+            IrInstr::Global { global, .. } => Some(*global),
+            _ => None,
+        });
+    let exports = module
+        .exports
+        .iter()
+        .filter_map(|export| match export.kind {
+            IrExportKind::Constant(global) => Some(global),
+            IrExportKind::Function(_) => None,
+        });
+    let used: HashSet<GlobalId> = reads.chain(exports).collect();
+
+    let original_len = module.globals.len();
+    module.globals.retain(|global| used.contains(&global.id));
+    module.globals.len() != original_len
+}
+
 /// Eliminate dead code in a function.
 fn eliminate_dead_code_in_function(func: &mut IrFunction) -> bool {
     let mut changed = false;
@@ -47,16 +75,25 @@ fn eliminate_dead_code_in_function(func: &mut IrFunction) -> bool {
             _ => {}
         }
 
-        // Mark values used in call instructions (side effects)
+        // Mark values used in call instructions and checks (side effects)
         for instr in &block.instructions {
-            if let IrInstr::Call { args, .. } = instr {
-                for arg in args {
-                    used_values.insert(*arg);
+            match instr {
+                IrInstr::Call { args, .. } => {
+                    for arg in args {
+                        used_values.insert(*arg);
+                    }
+                }
+                IrInstr::Assert { cond, .. } => {
+                    used_values.insert(*cond);
                 }
+                _ => {}
             }
         }
     }
 
+    // Parameters are never removed, so neither are their deleters
+    let params: HashSet<ValueId> = func.params.iter().map(|param| param.value_id).collect();
+
     // Iteratively mark all values that are transitively used
     let mut worklist: Vec<ValueId> = used_values.iter().copied().collect();
     while let Some(value_id) = worklist.pop() {
@@ -111,7 +148,10 @@ fn eliminate_dead_code_in_function(func: &mut IrFunction) -> bool {
                                 }
                             }
                         }
-                        IrInstr::Const { .. } => {}
+                        IrInstr::Const { .. }
+                        | IrInstr::Global { .. }
+                        | IrInstr::Drop { .. }
+                        | IrInstr::Assert { .. } => {}
                     }
                 }
             }
@@ -127,6 +167,11 @@ fn eliminate_dead_code_in_function(func: &mut IrFunction) -> bool {
                 return true;
             }
 
+            // A deleter goes with the value it deletes
+            if let IrInstr::Drop { value, .. } = instr {
+                return used_values.contains(value) || params.contains(value);
+            }
+
             // Keep instructions that don't produce values or produce used values
             match instr.result_value() {
                 Some(result) => used_values.contains(&result),
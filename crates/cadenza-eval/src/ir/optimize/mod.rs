@@ -5,6 +5,10 @@
 //! - Constant folding: Evaluate operations on constant values at compile time
 //! - Dead code elimination: Remove instructions that produce unused values
 //! - Common subexpression elimination: Detect and eliminate redundant computations
+//! - Constant argument propagation: Move the constant every call passes for a
+//!   parameter into the function
+//! - Constant pooling: Share the constants repeated across functions as
+//!   module-level constants
 
 use super::types as ir_types;
 
@@ -12,14 +16,19 @@ use super::types as ir_types;
 use ir_types as types;
 
 mod common_subexpression_elimination;
+mod constant_arguments;
 mod constant_folding;
+mod constant_pool;
 mod dead_code_elimination;
 
 pub use common_subexpression_elimination::CommonSubexpressionEliminationPass;
+pub use constant_arguments::ConstantArgumentPass;
 pub use constant_folding::ConstantFoldingPass;
+pub use constant_pool::ConstantPoolPass;
 pub use dead_code_elimination::DeadCodeEliminationPass;
 
 use super::types::IrModule;
+use crate::options::OptLevel;
 
 /// Trait for IR optimization passes.
 ///
@@ -38,12 +47,19 @@ pub trait OptimizationPass {
 /// Manages and runs a sequence of optimization passes.
 pub struct OptimizationPipeline {
     passes: Vec<Box<dyn OptimizationPass>>,
+    final_passes: Vec<Box<dyn OptimizationPass>>,
 }
 
 impl OptimizationPipeline {
+    /// The iteration limit used when optimizing a compiled module.
+    pub const MAX_ITERATIONS: usize = 10;
+
     /// Create a new empty optimization pipeline.
     pub fn new() -> Self {
-        Self { passes: Vec::new() }
+        Self {
+            passes: Vec::new(),
+            final_passes: Vec::new(),
+        }
     }
 
     /// Add an optimization pass to the pipeline.
@@ -51,10 +67,17 @@ impl OptimizationPipeline {
         self.passes.push(pass);
     }
 
+    /// Add a pass that runs once, after the other passes stop modifying the
+    /// module.
+    pub fn add_final_pass(&mut self, pass: Box<dyn OptimizationPass>) {
+        self.final_passes.push(pass);
+    }
+
     /// Run all optimization passes in the pipeline.
     ///
     /// Runs each pass in sequence, repeating until no pass modifies the module
-    /// or until a maximum iteration count is reached.
+    /// or until a maximum iteration count is reached, then runs the final
+    /// passes.
     ///
     /// Returns the total number of passes that modified the module.
     pub fn run(&mut self, module: &mut IrModule, max_iterations: usize) -> usize {
@@ -78,6 +101,12 @@ impl OptimizationPipeline {
             }
         }
 
+        for pass in &mut self.final_passes {
+            if pass.run(module) {
+                total_changes += 1;
+            }
+        }
+
         total_changes
     }
 
@@ -87,8 +116,25 @@ impl OptimizationPipeline {
         pipeline.add_pass(Box::new(ConstantFoldingPass));
         pipeline.add_pass(Box::new(DeadCodeEliminationPass));
         pipeline.add_pass(Box::new(CommonSubexpressionEliminationPass));
+        pipeline.add_pass(Box::new(ConstantArgumentPass));
+        pipeline.add_final_pass(Box::new(ConstantPoolPass));
         pipeline
     }
+
+    /// Create the optimization pipeline for an optimization level.
+    pub fn for_level(level: OptLevel) -> Self {
+        match level {
+            OptLevel::O0 => Self::new(),
+            OptLevel::O1 => {
+                let mut pipeline = Self::new();
+                pipeline.add_pass(Box::new(ConstantFoldingPass));
+                pipeline.add_pass(Box::new(DeadCodeEliminationPass));
+                pipeline.add_final_pass(Box::new(ConstantPoolPass));
+                pipeline
+            }
+            OptLevel::O2 => Self::default_pipeline(),
+        }
+    }
 }
 
 impl Default for OptimizationPipeline {
@@ -108,6 +154,7 @@ mod tests {
             file: InternedString::new("test.cdz"),
             line: 1,
             column: 1,
+            span: 0.into(),
         }
     }
 
@@ -251,6 +298,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dead_code_elimination_of_globals() {
+        let mut builder = crate::ir::IrBuilder::new();
+        let read = builder.global("read".into(), Type::Integer, IrConst::Integer(1));
+        builder.global("unused".into(), Type::Integer, IrConst::Integer(2));
+        let exported = builder.global("exported".into(), Type::Float, IrConst::Float(3.0));
+        builder.export_constant("exported".into(), exported);
+
+        let mut func_builder = builder.function(InternedString::new("test"), vec![], Type::Integer);
+        let mut block_builder = func_builder.block();
+        let v0 = block_builder.global(read, Type::Integer, dummy_source());
+        let (block, next_value_id) = block_builder.ret(Some(v0), dummy_source());
+        func_builder.add_block(block, next_value_id);
+        let func = func_builder.build();
+        builder.add_function(func);
+        let mut module = builder.build();
+
+        assert!(DeadCodeEliminationPass.run(&mut module));
+        let names: Vec<_> = module.globals.iter().map(|g| g.name.to_string()).collect();
+        assert_eq!(names, ["read", "exported"]);
+    }
+
     #[test]
     fn test_common_subexpression_elimination() {
         let mut module = IrModule::new();
@@ -466,4 +535,150 @@ mod tests {
             "Folded constant should preserve Float type"
         );
     }
+
+    #[test]
+    fn test_constant_pool() {
+        let mut builder = crate::ir::IrBuilder::new();
+        builder.global(
+            InternedString::new("half"),
+            Type::Float,
+            IrConst::Float(0.5),
+        );
+
+        // Two functions, each with: 6.02e23, 0.5, "hello" and 2
+        for name in ["first", "second"] {
+            let mut func_builder = builder.function(InternedString::new(name), vec![], Type::Float);
+            let mut block_builder = func_builder.block();
+            let big = block_builder.const_val(IrConst::Float(6.02e23), Type::Float, dummy_source());
+            let half = block_builder.const_val(IrConst::Float(0.5), Type::Float, dummy_source());
+            let text = IrConst::String(InternedString::new("hello"));
+            block_builder.const_val(text, Type::String, dummy_source());
+            block_builder.const_val(IrConst::Integer(2), Type::Integer, dummy_source());
+            let product = block_builder.binop(BinOp::Mul, big, half, Type::Float, dummy_source());
+            let (block, next_value_id) = block_builder.ret(Some(product), dummy_source());
+            func_builder.add_block(block, next_value_id);
+            let func = func_builder.build();
+            builder.add_function(func);
+        }
+        let mut module = builder.build();
+
+        assert!(ConstantPoolPass.run(&mut module));
+        let globals: Vec<_> = module
+            .globals
+            .iter()
+            .map(|global| format!("{} = {}", global.name, global.value))
+            .collect();
+        assert_eq!(
+            globals,
+            [
+                "half = 0.5",
+                "pool0 = 602000000000000000000000",
+                "pool1 = \"hello\""
+            ]
+        );
+
+        // Small integers stay inline, and the rest read the pool
+        for func in &module.functions {
+            let block = &func.blocks[0];
+            let reads: Vec<_> = block
+                .instructions
+                .iter()
+                .filter_map(|instr| match instr {
+                    IrInstr::Global { global, .. } => {
+                        Some(module.global(*global).unwrap().name.to_string())
+                    }
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(reads, ["pool0", "half", "pool1"]);
+            assert!(matches!(
+                block.instructions[3],
+                IrInstr::Const {
+                    value: IrConst::Integer(2),
+                    ..
+                }
+            ));
+        }
+        assert!(!ConstantPoolPass.run(&mut module));
+    }
+
+    #[test]
+    fn test_constant_arguments() {
+        let mut builder = crate::ir::IrBuilder::new();
+        let params = || {
+            vec![
+                (InternedString::new("a"), Type::Integer),
+                (InternedString::new("b"), Type::Integer),
+            ]
+        };
+
+        // Build: fn add(a, b) { ret a + b }, and an exported copy
+        let mut ids = vec![];
+        for name in ["add", "exported"] {
+            let mut func_builder =
+                builder.function(InternedString::new(name), params(), Type::Integer);
+            let mut block_builder = func_builder.block();
+            let sum = block_builder.binop(
+                BinOp::Add,
+                ValueId(0),
+                ValueId(1),
+                Type::Integer,
+                dummy_source(),
+            );
+            let (block, next_value_id) = block_builder.ret(Some(sum), dummy_source());
+            func_builder.add_block(block, next_value_id);
+            ids.push(builder.add_function(func_builder.build()));
+        }
+        builder.export_function(InternedString::new("exported"), ids[1]);
+
+        // Build: fn main() { add(1, 10) + add(2, 10) + exported(1, 10) }
+        let mut func_builder = builder.function(InternedString::new("main"), vec![], Type::Integer);
+        let mut block_builder = func_builder.block();
+        let mut results = vec![];
+        for (func, a) in [(ids[0], 1), (ids[0], 2), (ids[1], 1)] {
+            let a = block_builder.const_val(IrConst::Integer(a), Type::Integer, dummy_source());
+            let b = block_builder.const_val(IrConst::Integer(10), Type::Integer, dummy_source());
+            results.push(block_builder.call(func, vec![a, b], Type::Integer, dummy_source()));
+        }
+        let sum = block_builder.binop(
+            BinOp::Add,
+            results[0],
+            results[1],
+            Type::Integer,
+            dummy_source(),
+        );
+        let (block, next_value_id) = block_builder.ret(Some(sum), dummy_source());
+        func_builder.add_block(block, next_value_id);
+        builder.add_function(func_builder.build());
+        let mut module = builder.build();
+
+        assert!(ConstantArgumentPass.run(&mut module));
+
+        // Only b is the same constant in every call to add
+        let add = &module.functions[0];
+        assert_eq!(add.params.len(), 1);
+        assert_eq!(add.params[0].value_id, ValueId(0));
+        assert!(matches!(
+            add.blocks[0].instructions[0],
+            IrInstr::Const {
+                result: ValueId(1),
+                value: IrConst::Integer(10),
+                ..
+            }
+        ));
+
+        // The host can call an exported function with anything
+        assert_eq!(module.functions[1].params.len(), 2);
+
+        let arities: Vec<_> = module.functions[2].blocks[0]
+            .instructions
+            .iter()
+            .filter_map(|instr| match instr {
+                IrInstr::Call { args, .. } => Some(args.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(arities, [1, 1, 2]);
+        assert!(!ConstantArgumentPass.run(&mut module));
+    }
 }
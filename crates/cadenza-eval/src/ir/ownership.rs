@@ -0,0 +1,466 @@
+//! Ownership analysis and deleter insertion.
+//!
+//! Linear values (see [`Type::is_linear`]) have exactly one owner. A function
+//! owns its linear parameters and the linear values its instructions create,
+//! and it gives up ownership by moving a value:
+//!
+//! - into a call, as an argument,
+//! - into a record or list, as an element,
+//! - into a phi, on the way out of a branch, or
+//! - to its caller, by returning it.
+//!
+//! A failure (see [`IrTerminator::Fail`]) is an exit too, but a call that
+//! passes a failure on leaves from the middle of a block, and the values
+//! owned there aren't deleted yet.
+//!
+//! [`insert_deleters`] adds a [`IrInstr::Drop`] for every owned value that is
+//! never moved, at each exit of its scope: the blocks dominated by the block
+//! that defines it. An exit is a return, or an edge to a block outside the
+//! scope; a conditional branch that only leaves the scope on one side gets a
+//! new block on that edge to hold the deleter.
+//!
+//! ```text
+//! fn check s =                       fn check s =
+//!     let v1: string = const "ok"        let v1: string = const "ok"
+//!     let v2: bool = binop eq v0 v1      let v2: bool = binop eq v0 v1
+//!     ret v2                             drop v1
+//!                                        ret v2
+//! ```
+//!
+//! A field access borrows from its record, so its result is never dropped;
+//! the record is. Closure environments hold references to their captures, so
+//! building one doesn't move the captures and passing one to a lifted
+//! function doesn't move the environment: the function that builds it drops
+//! it.
+//!
+//! Use-after-move is checked by name while the IR is generated (see
+//! [`IrGenContext::consume`](super::IrGenContext::consume)), so every value
+//! reaching this pass is moved at most once.
+
+use super::{BlockId, IrFunction, IrInstr, IrTerminator, SourceLocation, ValueId};
+use crate::value::Type;
+use std::collections::{HashMap, HashSet};
+
+/// Inserts deleters into `func` for the linear values it owns and never
+/// moves.
+///
+/// `environments` are the closure environments in `func`: the environment
+/// parameter of a lifted function and the environments it builds for its own
+/// nested functions.
+pub(crate) fn insert_deleters(func: &mut IrFunction, environments: &[ValueId]) {
+    let owned = owned_values(func, environments);
+    let consumed = consumed_values(func, environments);
+    let mut dominators = dominators(func);
+    let mut next_block = func.blocks.iter().map(|block| block.id.0 + 1).max();
+
+    // Values are deleted in the reverse of the order they were created
+    for (value, ty, defined_in) in owned.into_iter().rev() {
+        if consumed.contains(&value) {
+            continue;
+        }
+        let in_scope = |block: &BlockId| dominators[block].contains(&defined_in);
+
+        // Each exit is a block to drop the value at the end of, and the
+        // successor to split off if only one edge leaves the scope
+        let mut exits = Vec::new();
+        for (i, block) in func.blocks.iter().enumerate() {
+            if !in_scope(&block.id) {
+                continue;
+            }
+            match &block.terminator {
+                IrTerminator::Return { .. } | IrTerminator::Fail { .. } => exits.push((i, None)),
+                IrTerminator::Jump { target, .. } if !in_scope(target) => exits.push((i, None)),
+                IrTerminator::Jump { .. } => {}
+                IrTerminator::Branch {
+                    then_block,
+                    else_block,
+                    ..
+                } => match (in_scope(then_block), in_scope(else_block)) {
+                    (false, false) => exits.push((i, None)),
+                    (true, false) => exits.push((i, Some(*else_block))),
+                    (false, true) => exits.push((i, Some(*then_block))),
+                    (true, true) => {}
+                },
+            }
+        }
+
+        for (i, split) in exits {
+            let source = *terminator_source(&func.blocks[i].terminator);
+            let at = match split {
+                None => i,
+                Some(target) => {
+                    let id = BlockId(next_block.unwrap_or_default());
+                    next_block = Some(id.0 + 1);
+                    // Splitting an edge leaves the other blocks' dominators
+                    // as they were
+                    let mut dom = dominators[&func.blocks[i].id].clone();
+                    dom.insert(id);
+                    dominators.insert(id, dom);
+                    split_edge(func, i, target, id, source)
+                }
+            };
+            func.blocks[at].instructions.push(IrInstr::Drop {
+                value,
+                ty: ty.clone(),
+                source,
+            });
+        }
+    }
+}
+
+/// Returns the linear values `func` owns, in the order they are created, with
+/// their types and the blocks that define them.
+fn owned_values(func: &IrFunction, environments: &[ValueId]) -> Vec<(ValueId, Type, BlockId)> {
+    let params = func
+        .params
+        .iter()
+        .filter(|param| !environments.contains(&param.value_id))
+        .map(|param| (param.value_id, param.ty.clone(), func.entry_block));
+
+    let results = func.blocks.iter().flat_map(|block| {
+        block.instructions.iter().filter_map(move |instr| {
+            let ty = match instr {
+                IrInstr::Const { ty, .. }
+                | IrInstr::BinOp { ty, .. }
+                | IrInstr::UnOp { ty, .. }
+                | IrInstr::Call { ty, .. }
+                | IrInstr::Record { ty, .. }
+                | IrInstr::Tuple { ty, .. }
+                | IrInstr::Phi { ty, .. }
+                | IrInstr::Global { ty, .. } => ty,
+                IrInstr::Field { .. } | IrInstr::Drop { .. } | IrInstr::Assert { .. } => {
+                    return None;
+                }
+            };
+            Some((instr.result_value()?, ty.clone(), block.id))
+        })
+    });
+
+    params
+        .chain(results)
+        .filter(|(_, ty, _)| ty.is_linear())
+        .collect()
+}
+
+/// Returns the values `func` moves somewhere else.
+fn consumed_values(func: &IrFunction, environments: &[ValueId]) -> HashSet<ValueId> {
+    let mut consumed = HashSet::new();
+    for block in &func.blocks {
+        for instr in &block.instructions {
+            match instr {
+                IrInstr::Call { args, .. } => consumed.extend(
+                    args.iter()
+                        .filter(|arg| !environments.contains(arg))
+                        .copied(),
+                ),
+                IrInstr::Record {
+                    result,
+                    field_values,
+                    ..
+                } if !environments.contains(result) => consumed.extend(field_values),
+                IrInstr::Tuple { elements, .. } => consumed.extend(elements),
+                IrInstr::Phi { incoming, .. } => {
+                    consumed.extend(incoming.iter().map(|(value, _)| *value))
+                }
+                _ => {}
+            }
+        }
+        if let IrTerminator::Return {
+            value: Some(value), ..
+        } = &block.terminator
+        {
+            consumed.insert(*value);
+        }
+    }
+    consumed
+}
+
+/// Returns the dominators of each block in `func`, including the block
+/// itself.
+fn dominators(func: &IrFunction) -> HashMap<BlockId, HashSet<BlockId>> {
+    let mut preds: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+    for block in &func.blocks {
+        for succ in successors(&block.terminator) {
+            preds.entry(succ).or_default().push(block.id);
+        }
+    }
+
+    let all: HashSet<BlockId> = func.blocks.iter().map(|block| block.id).collect();
+    let mut dominators: HashMap<BlockId, HashSet<BlockId>> = func
+        .blocks
+        .iter()
+        .map(|block| {
+            let unreachable = block.id != func.entry_block && !preds.contains_key(&block.id);
+            if block.id == func.entry_block || unreachable {
+                (block.id, HashSet::from([block.id]))
+            } else {
+                (block.id, all.clone())
+            }
+        })
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in &func.blocks {
+            if block.id == func.entry_block {
+                continue;
+            }
+            let Some(block_preds) = preds.get(&block.id) else {
+                continue;
+            };
+            let mut dom = block_preds
+                .iter()
+                .map(|pred| dominators[pred].clone())
+                .reduce(|a, b| a.intersection(&b).copied().collect())
+                .unwrap_or_default();
+            dom.insert(block.id);
+            if dom != dominators[&block.id] {
+                dominators.insert(block.id, dom);
+                changed = true;
+            }
+        }
+    }
+    dominators
+}
+
+/// Inserts a new block `id` on the edge from `func.blocks[from]` to `target`,
+/// returning its index.
+fn split_edge(
+    func: &mut IrFunction,
+    from: usize,
+    target: BlockId,
+    id: BlockId,
+    source: SourceLocation,
+) -> usize {
+    let from_id = func.blocks[from].id;
+    if let IrTerminator::Branch {
+        then_block,
+        else_block,
+        ..
+    } = &mut func.blocks[from].terminator
+    {
+        for succ in [then_block, else_block] {
+            if *succ == target {
+                *succ = id;
+            }
+        }
+    }
+
+    // The target's phis now receive this edge's values from the new block
+    for block in func.blocks.iter_mut().filter(|block| block.id == target) {
+        for instr in &mut block.instructions {
+            if let IrInstr::Phi { incoming, .. } = instr {
+                for (_, pred) in incoming.iter_mut().filter(|(_, pred)| *pred == from_id) {
+                    *pred = id;
+                }
+            }
+        }
+    }
+
+    func.blocks.push(super::IrBlock {
+        id,
+        instructions: Vec::new(),
+        terminator: IrTerminator::Jump { target, source },
+    });
+    func.blocks.len() - 1
+}
+
+fn successors(terminator: &IrTerminator) -> Vec<BlockId> {
+    match terminator {
+        IrTerminator::Branch {
+            then_block,
+            else_block,
+            ..
+        } => vec![*then_block, *else_block],
+        IrTerminator::Jump { target, .. } => vec![*target],
+        IrTerminator::Return { .. } | IrTerminator::Fail { .. } => vec![],
+    }
+}
+
+fn terminator_source(terminator: &IrTerminator) -> &SourceLocation {
+    match terminator {
+        IrTerminator::Branch { source, .. }
+        | IrTerminator::Jump { source, .. }
+        | IrTerminator::Return { source, .. }
+        | IrTerminator::Fail { source, .. } => source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{IrBlock, IrConst, IrParam};
+
+    fn source() -> SourceLocation {
+        SourceLocation {
+            file: "test.cdz".into(),
+            line: 1,
+            column: 1,
+            span: 0.into(),
+        }
+    }
+
+    fn string(result: u32, text: &str) -> IrInstr {
+        IrInstr::Const {
+            result: ValueId(result),
+            ty: Type::String,
+            value: IrConst::String(text.into()),
+            source: source(),
+        }
+    }
+
+    fn function(params: Vec<IrParam>, blocks: Vec<IrBlock>) -> IrFunction {
+        IrFunction {
+            id: crate::ir::FunctionId(0),
+            name: "f".into(),
+            params,
+            return_ty: Type::Unknown,
+            return_dimension: None,
+            fallible: false,
+            blocks,
+            entry_block: BlockId(0),
+        }
+    }
+
+    fn drops(block: &IrBlock) -> Vec<ValueId> {
+        block
+            .instructions
+            .iter()
+            .filter_map(|instr| match instr {
+                IrInstr::Drop { value, .. } => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drops_unmoved_values_before_return() {
+        let mut func = function(
+            vec![IrParam {
+                name: "s".into(),
+                ty: Type::String,
+                value_id: ValueId(0),
+                dimension: None,
+            }],
+            vec![IrBlock {
+                id: BlockId(0),
+                instructions: vec![
+                    string(1, "a"),
+                    string(2, "b"),
+                    IrInstr::Call {
+                        result: None,
+                        ty: Type::Nil,
+                        func: crate::ir::FunctionId(1),
+                        args: vec![ValueId(2)],
+                        source: source(),
+                    },
+                ],
+                terminator: IrTerminator::Return {
+                    value: None,
+                    source: source(),
+                },
+            }],
+        );
+        insert_deleters(&mut func, &[]);
+        assert_eq!(drops(&func.blocks[0]), vec![ValueId(1), ValueId(0)]);
+    }
+
+    #[test]
+    fn environments_are_borrowed() {
+        let mut func = function(
+            vec![IrParam {
+                name: "env".into(),
+                ty: Type::Record(vec![("s".into(), Type::String)]),
+                value_id: ValueId(0),
+                dimension: None,
+            }],
+            vec![IrBlock {
+                id: BlockId(0),
+                instructions: vec![
+                    string(1, "a"),
+                    IrInstr::Record {
+                        result: ValueId(2),
+                        ty: Type::Record(vec![("s".into(), Type::String)]),
+                        field_names: vec!["s".into()].into(),
+                        field_values: vec![ValueId(1)],
+                        source: source(),
+                    },
+                    IrInstr::Call {
+                        result: None,
+                        ty: Type::Nil,
+                        func: crate::ir::FunctionId(1),
+                        args: vec![ValueId(2)],
+                        source: source(),
+                    },
+                ],
+                terminator: IrTerminator::Return {
+                    value: None,
+                    source: source(),
+                },
+            }],
+        );
+        insert_deleters(&mut func, &[ValueId(0), ValueId(2)]);
+        assert_eq!(drops(&func.blocks[0]), vec![ValueId(2), ValueId(1)]);
+    }
+
+    #[test]
+    fn splits_edges_leaving_the_scope() {
+        // block 0 branches to 1 or 2; block 1 creates v2 and branches to 3
+        // (in scope) or 2 (out of scope)
+        let branch = |cond, then_block, else_block| IrTerminator::Branch {
+            cond: ValueId(cond),
+            then_block: BlockId(then_block),
+            else_block: BlockId(else_block),
+            source: source(),
+        };
+        let ret = IrTerminator::Return {
+            value: None,
+            source: source(),
+        };
+        let mut func = function(
+            vec![IrParam {
+                name: "c".into(),
+                ty: Type::Bool,
+                value_id: ValueId(0),
+                dimension: None,
+            }],
+            vec![
+                IrBlock {
+                    id: BlockId(0),
+                    instructions: vec![],
+                    terminator: branch(0, 1, 2),
+                },
+                IrBlock {
+                    id: BlockId(1),
+                    instructions: vec![string(2, "a")],
+                    terminator: branch(0, 3, 2),
+                },
+                IrBlock {
+                    id: BlockId(2),
+                    instructions: vec![],
+                    terminator: ret.clone(),
+                },
+                IrBlock {
+                    id: BlockId(3),
+                    instructions: vec![],
+                    terminator: ret,
+                },
+            ],
+        );
+        insert_deleters(&mut func, &[]);
+
+        assert_eq!(drops(&func.blocks[3]), vec![ValueId(2)]);
+        assert!(drops(&func.blocks[2]).is_empty());
+        let split = &func.blocks[4];
+        assert_eq!(drops(split), vec![ValueId(2)]);
+        assert_eq!(
+            split.terminator,
+            IrTerminator::Jump {
+                target: BlockId(2),
+                source: source(),
+            }
+        );
+        assert_eq!(func.blocks[1].terminator, branch(0, 3, 4));
+    }
+}
@@ -0,0 +1,527 @@
+//! Peephole optimization of emitted WASM function bodies.
+//!
+//! The code generator gives every SSA value a local of its own, so most values
+//! are stored with `local.set` only to be loaded again a few instructions
+//! later. A [`Body`] buffers a function's instructions so that
+//! [`Body::finish`] can clean them up before they are encoded:
+//!
+//! - A local that is stored once and read once, where the instructions in
+//!   between leave the stack as they found it, stays on the stack instead.
+//! - `local.set x` followed by `local.get x` becomes `local.tee x`.
+//! - A store to a local that is never read becomes a `drop`, and a `drop` of
+//!   a constant or a local disappears along with the value.
+//!
+//! Then the remaining locals are allocated: locals of the same type whose
+//! values are never live at the same time share one, so a long function
+//! needs about as many locals as it has values live at once, not one per
+//! value.
+//!
+//! No instruction moves past another, so side effects and traps happen in
+//! the same order as before.
+//!
+//! Each instruction keeps the source location of the IR it came from, and
+//! [`Body::finish`] returns where the calls and the instructions that can
+//! trap ended up, for the module's source map. A failed check's trap also
+//! keeps its message.
+
+use super::SourceLocation;
+use crate::InternedString;
+use wasm_encoder::{Function, Instruction, ValType};
+
+/// The instructions of a function body, before encoding.
+pub(super) struct Body {
+    /// The number of parameters, which are never removed.
+    params: u32,
+    /// The types of the locals after the parameters.
+    locals: Vec<ValType>,
+    instructions: Vec<Instruction<'static>>,
+    /// Where each instruction came from, if known.
+    sources: Vec<Option<Origin>>,
+    /// The source location of the instructions appended next.
+    source: Option<SourceLocation>,
+}
+
+/// Where an instruction came from.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Origin {
+    pub source: SourceLocation,
+    /// Why the instruction traps, if it's the trap of a failed check.
+    pub message: Option<InternedString>,
+}
+
+/// How often a local is written and read.
+#[derive(Clone, Copy, Default)]
+struct Uses {
+    sets: u32,
+    gets: u32,
+}
+
+impl Body {
+    /// Creates an empty body for a function with `params` parameters and
+    /// locals of types `locals`.
+    pub fn new(params: u32, locals: Vec<ValType>) -> Self {
+        Self {
+            params,
+            locals,
+            instructions: Vec::new(),
+            sources: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Attributes the instructions appended from now on to `source`.
+    pub fn at(&mut self, source: SourceLocation) -> &mut Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Appends an instruction.
+    pub fn instruction(&mut self, instruction: &Instruction<'static>) -> &mut Self {
+        self.instructions.push(instruction.clone());
+        self.sources.push(self.source.map(|source| Origin {
+            source,
+            message: None,
+        }));
+        self
+    }
+
+    /// Appends an `unreachable` that traps because a check failed with
+    /// `message`.
+    pub fn trap(&mut self, message: InternedString) -> &mut Self {
+        self.instructions.push(Instruction::Unreachable);
+        self.sources.push(self.source.map(|source| Origin {
+            source,
+            message: Some(message),
+        }));
+        self
+    }
+
+    /// Optimizes the body, allocates its locals and encodes it.
+    ///
+    /// `signatures` holds the number of parameters and results of each
+    /// function, by WASM function index, which tells how a call changes the
+    /// stack.
+    ///
+    /// Also returns the byte offset in the encoded body, locals included, and
+    /// the origin of each call and each instruction that can trap.
+    pub fn finish(mut self, signatures: &[(u32, u32)]) -> (Function, Vec<(u32, Origin)>) {
+        while self.sink(signatures) || self.tee() || self.drop_dead_stores() {}
+        self.allocate_locals();
+
+        let mut function = Function::new_with_locals_types(self.locals);
+        let mut sites = Vec::new();
+        for (instruction, origin) in self.instructions.iter().zip(self.sources) {
+            if let Some(origin) = origin
+                && may_trap(instruction)
+            {
+                sites.push((function.byte_len() as u32, origin));
+            }
+            function.instruction(instruction);
+        }
+        (function, sites)
+    }
+
+    fn uses(&self) -> Vec<Uses> {
+        let mut uses = vec![Uses::default(); self.params as usize + self.locals.len()];
+        for instruction in &self.instructions {
+            match instruction {
+                Instruction::LocalSet(local) | Instruction::LocalTee(local) => {
+                    uses[*local as usize].sets += 1
+                }
+                Instruction::LocalGet(local) => uses[*local as usize].gets += 1,
+                _ => {}
+            }
+        }
+        uses
+    }
+
+    /// Keeps one value on the stack instead of in a local, returning whether
+    /// one was found.
+    fn sink(&mut self, signatures: &[(u32, u32)]) -> bool {
+        let uses = self.uses();
+        for set in 0..self.instructions.len() {
+            let Instruction::LocalSet(local) = self.instructions[set] else {
+                continue;
+            };
+            let Uses { sets, gets } = uses[local as usize];
+            if local < self.params || sets != 1 || gets != 1 {
+                continue;
+            }
+            if let Some(get) = self.find_get(set + 1, local, signatures) {
+                for index in [get, set] {
+                    self.instructions.remove(index);
+                    self.sources.remove(index);
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns the index of the `local.get` of `local` at or after `start`,
+    /// if everything before it is straight-line code that leaves the stack
+    /// at its starting height and never pops below it.
+    fn find_get(&self, start: usize, local: u32, signatures: &[(u32, u32)]) -> Option<usize> {
+        let mut height = 0u32;
+        for (index, instruction) in self.instructions.iter().enumerate().skip(start) {
+            if matches!(instruction, Instruction::LocalGet(l) if *l == local) {
+                return (height == 0).then_some(index);
+            }
+            let (pops, pushes) = stack_effect(instruction, signatures)?;
+            height = height.checked_sub(pops)? + pushes;
+        }
+        None
+    }
+
+    /// Turns each `local.set x; local.get x` into `local.tee x`.
+    fn tee(&mut self) -> bool {
+        let mut changed = false;
+        let mut instructions = Vec::with_capacity(self.instructions.len());
+        let mut sources = Vec::with_capacity(self.sources.len());
+        for (instruction, source) in self.instructions.drain(..).zip(self.sources.drain(..)) {
+            if let Instruction::LocalGet(get) = instruction
+                && let Some(Instruction::LocalSet(set)) = instructions.last()
+                && *set == get
+            {
+                *instructions.last_mut().unwrap() = Instruction::LocalTee(get);
+                changed = true;
+            } else {
+                instructions.push(instruction);
+                sources.push(source);
+            }
+        }
+        self.instructions = instructions;
+        self.sources = sources;
+        changed
+    }
+
+    /// Removes stores to locals that are never read, and the values they
+    /// stored where that has no effect.
+    fn drop_dead_stores(&mut self) -> bool {
+        let uses = self.uses();
+        let is_dead = |local: u32| local >= self.params && uses[local as usize].gets == 0;
+        let mut changed = false;
+        let mut instructions: Vec<Instruction<'static>> =
+            Vec::with_capacity(self.instructions.len());
+        let mut sources = Vec::with_capacity(self.sources.len());
+        for (instruction, source) in self.instructions.drain(..).zip(self.sources.drain(..)) {
+            let instruction = match instruction {
+                Instruction::LocalTee(local) if is_dead(local) => {
+                    changed = true;
+                    continue;
+                }
+                Instruction::LocalSet(local) if is_dead(local) => {
+                    changed = true;
+                    Instruction::Drop
+                }
+                instruction => instruction,
+            };
+            if matches!(instruction, Instruction::Drop)
+                && instructions.last().is_some_and(is_pure_push)
+            {
+                instructions.pop();
+                sources.pop();
+                changed = true;
+                continue;
+            }
+            instructions.push(instruction);
+            sources.push(source);
+        }
+        self.instructions = instructions;
+        self.sources = sources;
+        changed
+    }
+
+    /// Assigns the locals to as few slots as it can: locals of the same type
+    /// share a slot when their live ranges don't overlap, and locals no
+    /// instruction uses get none.
+    ///
+    /// A local is live from its first use to its last, in instruction order.
+    /// Without loops, that range covers every path between the two, whatever
+    /// branches lie in between. A local that is read before it is written
+    /// reads its default value, so it never takes over a used slot.
+    fn allocate_locals(&mut self) {
+        let params = self.params as usize;
+        let mut ranges: Vec<Option<(usize, usize)>> = vec![None; self.locals.len()];
+        let mut read_first = vec![false; self.locals.len()];
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let (Instruction::LocalGet(local)
+            | Instruction::LocalSet(local)
+            | Instruction::LocalTee(local)) = instruction
+            else {
+                continue;
+            };
+            let Some(local) = (*local as usize).checked_sub(params) else {
+                continue;
+            };
+            match &mut ranges[local] {
+                Some((_, end)) => *end = index,
+                range @ None => {
+                    *range = Some((index, index));
+                    read_first[local] = matches!(instruction, Instruction::LocalGet(_));
+                }
+            }
+        }
+        let has_loops = self
+            .instructions
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::Loop(_)));
+
+        let mut order: Vec<_> = (0..self.locals.len())
+            .filter_map(|local| Some((ranges[local]?, local)))
+            .collect();
+        order.sort();
+        // The type of each slot and the end of the last range assigned to it
+        let mut slots: Vec<(ValType, usize)> = Vec::new();
+        let mut renumbered = vec![0; self.locals.len()];
+        for ((start, end), local) in order {
+            let ty = self.locals[local];
+            let free = slots.iter().position(|&(slot_ty, slot_end)| {
+                slot_ty == ty && slot_end < start && !has_loops && !read_first[local]
+            });
+            let slot = free.unwrap_or_else(|| {
+                slots.push((ty, end));
+                slots.len() - 1
+            });
+            slots[slot].1 = end;
+            renumbered[local] = self.params + slot as u32;
+        }
+
+        for instruction in &mut self.instructions {
+            if let Instruction::LocalGet(local)
+            | Instruction::LocalSet(local)
+            | Instruction::LocalTee(local) = instruction
+                && *local >= self.params
+            {
+                *local = renumbered[(*local - self.params) as usize];
+            }
+        }
+        self.locals = slots.into_iter().map(|(ty, _)| ty).collect();
+    }
+}
+
+/// Returns whether `instruction` pushes a value without any other effect.
+fn is_pure_push(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::LocalGet(_)
+            | Instruction::GlobalGet(_)
+            | Instruction::I32Const(_)
+            | Instruction::I64Const(_)
+            | Instruction::F64Const(_)
+    )
+}
+
+/// Returns whether `instruction` is a call or can trap, so a trap can be
+/// reported at it or in a function it calls.
+fn may_trap(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Call(_)
+            | Instruction::ReturnCall(_)
+            | Instruction::Unreachable
+            | Instruction::I64DivS
+            | Instruction::I64RemS
+    )
+}
+
+/// Returns how many values `instruction` pops and pushes, or `None` for
+/// control flow and instructions the optimizer doesn't know.
+fn stack_effect(instruction: &Instruction, signatures: &[(u32, u32)]) -> Option<(u32, u32)> {
+    use Instruction::*;
+    Some(match instruction {
+        LocalGet(_) | GlobalGet(_) | I32Const(_) | I64Const(_) | F64Const(_) => (0, 1),
+        LocalSet(_) | Drop => (1, 0),
+        LocalTee(_) | I32Eqz | F64Neg => (1, 1),
+        I64Add | I64Sub | I64Mul | I64DivS | I64RemS | I64Eq | I64Ne | I64LtS | I64LeS | I64GtS
+        | I64GeS | F64Add | F64Sub | F64Mul | F64Div | F64Eq | F64Ne | F64Lt | F64Le | F64Gt
+        | F64Ge => (2, 1),
+        Select => (3, 1),
+        Call(function) => *signatures.get(*function as usize)?,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn optimize(params: u32, locals: Vec<ValType>, instructions: &[Instruction<'static>]) -> Body {
+        let mut body = Body::new(params, locals);
+        for instruction in instructions {
+            body.instruction(instruction);
+        }
+        while body.sink(&[(2, 1)]) || body.tee() || body.drop_dead_stores() {}
+        body.allocate_locals();
+        body
+    }
+
+    fn listing(body: &Body) -> Vec<String> {
+        body.instructions
+            .iter()
+            .map(|instruction| format!("{instruction:?}"))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_single_use_values_on_the_stack() {
+        use Instruction::*;
+        // v2 = 1; v3 = 2; v4 = v2 + v3; return v4
+        let body = optimize(
+            0,
+            vec![ValType::I64; 3],
+            &[
+                I64Const(1),
+                LocalSet(0),
+                I64Const(2),
+                LocalSet(1),
+                LocalGet(0),
+                LocalGet(1),
+                I64Add,
+                LocalSet(2),
+                LocalGet(2),
+                End,
+            ],
+        );
+        assert_eq!(
+            listing(&body),
+            ["I64Const(1)", "I64Const(2)", "I64Add", "End"]
+        );
+        assert!(body.locals.is_empty());
+    }
+
+    #[test]
+    fn tees_values_read_twice() {
+        use Instruction::*;
+        // v1 = p0 * p0; return v1 + v1
+        let body = optimize(
+            1,
+            vec![ValType::I64],
+            &[
+                LocalGet(0),
+                LocalGet(0),
+                I64Mul,
+                LocalSet(1),
+                LocalGet(1),
+                LocalGet(1),
+                I64Add,
+                End,
+            ],
+        );
+        assert_eq!(
+            listing(&body),
+            [
+                "LocalGet(0)",
+                "LocalGet(0)",
+                "I64Mul",
+                "LocalTee(1)",
+                "LocalGet(1)",
+                "I64Add",
+                "End"
+            ]
+        );
+        assert_eq!(body.locals, [ValType::I64]);
+    }
+
+    #[test]
+    fn keeps_values_across_control_flow_in_locals() {
+        use Instruction::*;
+        let instructions = [
+            I64Const(1),
+            LocalSet(1),
+            LocalGet(0),
+            If(wasm_encoder::BlockType::Empty),
+            End,
+            LocalGet(1),
+            End,
+        ];
+        let body = optimize(1, vec![ValType::I64], &instructions);
+        assert_eq!(listing(&body).len(), instructions.len());
+    }
+
+    #[test]
+    fn drops_unread_values() {
+        use Instruction::*;
+        // A call's unused result is dropped, but the call stays
+        let body = optimize(
+            2,
+            vec![ValType::I64; 2],
+            &[
+                I64Const(7),
+                LocalSet(2),
+                LocalGet(0),
+                LocalGet(1),
+                Call(0),
+                LocalSet(3),
+                End,
+            ],
+        );
+        assert_eq!(
+            listing(&body),
+            ["LocalGet(0)", "LocalGet(1)", "Call(0)", "Drop", "End"]
+        );
+        assert!(body.locals.is_empty());
+    }
+
+    #[test]
+    fn never_reorders_the_stack() {
+        use Instruction::*;
+        // v1 is pushed before p0, so it can't stay on the stack below p0
+        let instructions = [
+            I64Const(1),
+            LocalSet(1),
+            LocalGet(0),
+            LocalGet(1),
+            I64Sub,
+            End,
+        ];
+        let body = optimize(1, vec![ValType::I64], &instructions);
+        assert_eq!(listing(&body).len(), instructions.len());
+    }
+
+    #[test]
+    fn shares_locals_with_disjoint_live_ranges() {
+        use Instruction::*;
+        // Each of locals 1-3 is read twice, so they stay locals, but each
+        // is dead before the next is written. Local 4 is a float and local
+        // 5 is read before it is written, so neither can share.
+        let mut instructions = vec![];
+        for local in 1..=3 {
+            instructions.extend([
+                LocalGet(0),
+                LocalSet(local),
+                LocalGet(local),
+                LocalGet(local),
+                I64Add,
+                LocalSet(0),
+            ]);
+        }
+        instructions.extend([
+            F64Const(1.0.into()),
+            LocalTee(4),
+            LocalGet(4),
+            F64Add,
+            Drop,
+            LocalGet(5),
+            LocalGet(5),
+            I64Add,
+            LocalSet(0),
+            End,
+        ]);
+        let locals = vec![
+            ValType::I64,
+            ValType::I64,
+            ValType::I64,
+            ValType::F64,
+            ValType::I64,
+        ];
+        let body = optimize(1, locals, &instructions);
+        assert_eq!(body.locals, [ValType::I64, ValType::F64, ValType::I64]);
+        let listing = listing(&body);
+        assert_eq!(listing[1], "LocalTee(1)");
+        assert_eq!(listing[5], "LocalTee(1)");
+        assert_eq!(listing[9], "LocalTee(1)");
+        assert!(listing.contains(&"LocalTee(2)".to_string()));
+        assert!(listing.contains(&"LocalGet(3)".to_string()));
+    }
+}
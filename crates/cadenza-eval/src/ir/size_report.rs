@@ -0,0 +1,375 @@
+//! Binary size reports.
+//!
+//! A [`SizeReport`] attributes every byte of a WASM binary to what it
+//! encodes, for trimming modules that run on small embedded targets: each
+//! function's code, each module-level constant (including the constant pool
+//! entries `pool0`, `pool1`, ... made by the optimizer), the string data,
+//! each custom section, and the rest of each standard section. Functions,
+//! constants and data are named from the binary's `name` section, which the
+//! code generator always emits.
+
+use std::{collections::HashMap, fmt, ops::Range};
+use wasmparser::{KnownCustom, Name, Parser, Payload, TypeRef};
+
+/// What a [`SizeEntry`] counts the bytes of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SizeKind {
+    /// A function's body in the code section.
+    Function,
+    /// A module-level constant in the global section.
+    Constant,
+    /// A data segment, such as the string literals.
+    Data,
+    /// A custom section, such as the source map or build metadata.
+    Custom,
+    /// The rest of a standard section: its header, and entries such as the
+    /// types, imports and exports.
+    Section,
+}
+
+impl SizeKind {
+    /// Returns the kind's name as shown in reports.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SizeKind::Function => "function",
+            SizeKind::Constant => "constant",
+            SizeKind::Data => "data",
+            SizeKind::Custom => "custom",
+            SizeKind::Section => "section",
+        }
+    }
+}
+
+impl fmt::Display for SizeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// The bytes of a binary attributed to one function, constant or section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeEntry {
+    pub kind: SizeKind,
+    pub name: String,
+    pub bytes: usize,
+}
+
+/// The size of a WASM binary broken down by what the bytes encode.
+///
+/// The entries' bytes add up to the total, and are ordered from largest to
+/// smallest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeReport {
+    pub total: usize,
+    pub entries: Vec<SizeEntry>,
+}
+
+impl SizeReport {
+    /// Reads the size report of `binary`.
+    pub fn read(binary: &[u8]) -> Result<Self, String> {
+        let invalid = |e: wasmparser::BinaryReaderError| format!("Failed to parse WASM: {}", e);
+        let names = Names::read(binary)?;
+
+        // The module header holds the magic number and version
+        let mut entries = vec![SizeEntry {
+            kind: SizeKind::Section,
+            name: "header".to_string(),
+            bytes: 8,
+        }];
+        let mut section_start = 8;
+        let mut imported_functions = 0;
+        let mut next_function = 0;
+        let mut code = 0;
+        for payload in Parser::new(0).parse_all(binary) {
+            let payload = payload.map_err(invalid)?;
+            let mut items = vec![];
+            let (name, range) = match &payload {
+                Payload::ImportSection(reader) => {
+                    for import in reader.clone() {
+                        if let TypeRef::Func(_) = import.map_err(invalid)?.ty {
+                            imported_functions += 1;
+                        }
+                    }
+                    ("import", reader.range())
+                }
+                Payload::GlobalSection(reader) => {
+                    for item in reader.clone().into_iter_with_offsets() {
+                        let (offset, _) = item.map_err(invalid)?;
+                        items.push(offset);
+                    }
+                    ("global", reader.range())
+                }
+                Payload::DataSection(reader) => {
+                    for item in reader.clone().into_iter_with_offsets() {
+                        let (offset, _) = item.map_err(invalid)?;
+                        items.push(offset);
+                    }
+                    ("data", reader.range())
+                }
+                Payload::CodeSectionStart { range, .. } => {
+                    // The bodies, which follow, are taken out of the section
+                    next_function = imported_functions;
+                    code = entries.len();
+                    section(&mut entries, "code", range.end - section_start);
+                    section_start = range.end;
+                    continue;
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let bytes = body.range().len() + leb128_len(body.range().len());
+                    entries[code].bytes -= bytes;
+                    entries.push(SizeEntry {
+                        kind: SizeKind::Function,
+                        name: names.function(next_function),
+                        bytes,
+                    });
+                    next_function += 1;
+                    continue;
+                }
+                Payload::CustomSection(reader) => {
+                    let range = reader.range();
+                    entries.push(SizeEntry {
+                        kind: SizeKind::Custom,
+                        name: reader.name().to_string(),
+                        bytes: range.end - section_start,
+                    });
+                    section_start = range.end;
+                    continue;
+                }
+                Payload::End(_) => break,
+                other => match other.as_section() {
+                    Some((id, range)) => (section_name(id), range),
+                    None => continue,
+                },
+            };
+
+            // Each entry runs up to the next one, and the last to the end of
+            // the section
+            let Range { end, .. } = range;
+            let mut attributed = 0;
+            for (index, offset) in items.iter().enumerate() {
+                let next = items.get(index + 1).copied().unwrap_or(end);
+                let (kind, name) = if name == "global" {
+                    (SizeKind::Constant, names.global(index as u32))
+                } else {
+                    (SizeKind::Data, names.data(index as u32))
+                };
+                entries.push(SizeEntry {
+                    kind,
+                    name,
+                    bytes: next - offset,
+                });
+                attributed += next - offset;
+            }
+            section(&mut entries, name, end - section_start - attributed);
+            section_start = end;
+        }
+
+        let total = binary.len();
+        entries.retain(|entry| entry.bytes > 0);
+        entries.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then(a.kind.cmp(&b.kind))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        debug_assert_eq!(entries.iter().map(|e| e.bytes).sum::<usize>(), total);
+        Ok(Self { total, entries })
+    }
+
+    /// Returns the report as JSON:
+    /// `{"total": 120, "entries": [{"kind": "function", "name": "f", "bytes": 40}, ...]}`.
+    pub fn to_json(&self) -> String {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                serde_json::json!({
+                    "kind": entry.kind.as_str(),
+                    "name": entry.name,
+                    "bytes": entry.bytes,
+                })
+            })
+            .collect();
+        serde_json::json!({ "total": self.total, "entries": entries }).to_string()
+    }
+}
+
+/// Shows the report as a table, with each entry's share of the total.
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>8}  {:>6}  {:<8}  name", "bytes", "share", "kind")?;
+        for entry in &self.entries {
+            let share = entry.bytes as f64 * 100.0 / self.total as f64;
+            writeln!(
+                f,
+                "{:>8}  {:>5.1}%  {:<8}  {}",
+                entry.bytes, share, entry.kind, entry.name
+            )?;
+        }
+        write!(f, "{:>8}  {:>5.1}%  total", self.total, 100.0)
+    }
+}
+
+/// Adds the `bytes` of the section `name` not attributed to its entries.
+fn section(entries: &mut Vec<SizeEntry>, name: &str, bytes: usize) {
+    entries.push(SizeEntry {
+        kind: SizeKind::Section,
+        name: name.to_string(),
+        bytes,
+    });
+}
+
+/// Returns the name of the standard section `id`.
+fn section_name(id: u8) -> &'static str {
+    match id {
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        13 => "tag",
+        _ => "unknown",
+    }
+}
+
+/// Returns the length of `n` encoded as an unsigned LEB128.
+fn leb128_len(mut n: usize) -> usize {
+    let mut len = 1;
+    while n >= 0x80 {
+        n >>= 7;
+        len += 1;
+    }
+    len
+}
+
+/// The names of a binary's functions, globals and data segments, from its
+/// `name` section.
+#[derive(Default)]
+struct Names {
+    functions: HashMap<u32, String>,
+    globals: HashMap<u32, String>,
+    data: HashMap<u32, String>,
+}
+
+impl Names {
+    fn read(binary: &[u8]) -> Result<Self, String> {
+        let invalid = |e: wasmparser::BinaryReaderError| format!("Failed to parse WASM: {}", e);
+        let mut names = Self::default();
+        for payload in Parser::new(0).parse_all(binary) {
+            let Payload::CustomSection(reader) = payload.map_err(invalid)? else {
+                continue;
+            };
+            let KnownCustom::Name(reader) = reader.as_known() else {
+                continue;
+            };
+            for subsection in reader {
+                let (map, target) = match subsection.map_err(invalid)? {
+                    Name::Function(map) => (map, &mut names.functions),
+                    Name::Global(map) => (map, &mut names.globals),
+                    Name::Data(map) => (map, &mut names.data),
+                    _ => continue,
+                };
+                for naming in map {
+                    let naming = naming.map_err(invalid)?;
+                    target.insert(naming.index, naming.name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    fn function(&self, index: u32) -> String {
+        self.functions
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("function {index}"))
+    }
+
+    fn global(&self, index: u32) -> String {
+        self.globals
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("global {index}"))
+    }
+
+    fn data(&self, index: u32) -> String {
+        self.data
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("data {index}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Env, ir::generate_wasm};
+    use cadenza_syntax::parse::parse;
+
+    fn compile(source: &str) -> Vec<u8> {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::with_ir();
+        crate::eval(&parse(source).ast(), &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        let module = compiler.build_ir_module().unwrap();
+        generate_wasm(&module, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn attributes_every_byte() {
+        let wasm = compile(
+            "let limit = 100\nlet greeting = \"hello\"\n\
+             fn greet x = greeting\n\
+             fn clamp x = if x > limit then limit else if x < 0 then 0 else x\n",
+        );
+        let report = SizeReport::read(&wasm).unwrap();
+        assert_eq!(report.total, wasm.len());
+        assert_eq!(
+            report.entries.iter().map(|e| e.bytes).sum::<usize>(),
+            wasm.len()
+        );
+
+        let entry = |kind, name: &str| {
+            report
+                .entries
+                .iter()
+                .find(|entry| entry.kind == kind && entry.name == name)
+                .unwrap_or_else(|| panic!("no {kind} {name}:\n{report}"))
+                .bytes
+        };
+        assert!(entry(SizeKind::Function, "clamp") > entry(SizeKind::Function, "greet"));
+        // The type, mutability, `i64.const 100` and `end`
+        assert_eq!(entry(SizeKind::Constant, "limit"), 6);
+        assert!(entry(SizeKind::Data, "strings") > "hello".len());
+        assert!(entry(SizeKind::Custom, "cadenza.build") > 0);
+        assert!(entry(SizeKind::Custom, "name") > 0);
+        assert!(entry(SizeKind::Section, "type") > 0);
+    }
+
+    #[test]
+    fn writes_a_table_and_json() {
+        let wasm = compile("fn double x = x * 2\n");
+        let report = SizeReport::read(&wasm).unwrap();
+        let table = report.to_string();
+        assert!(table.starts_with("   bytes   share  kind      name\n"));
+        assert!(table.contains("function  double\n"), "{table}");
+        assert!(table.contains(&format!("{:>8}  100.0%  total", wasm.len())));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["total"], wasm.len());
+        assert!(
+            json["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|entry| entry["kind"] == "function" && entry["name"] == "double")
+        );
+    }
+}
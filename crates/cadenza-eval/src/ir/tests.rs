@@ -63,6 +63,7 @@ fn dummy_source() -> SourceLocation {
         file: InternedString::new("test.cdz"),
         line: 1,
         column: 0,
+        span: 0.into(),
     }
 }
 
@@ -204,14 +205,18 @@ fn test_function() {
                 name: InternedString::new("a"),
                 ty: Type::Integer,
                 value_id: ValueId(0),
+                dimension: None,
             },
             IrParam {
                 name: InternedString::new("b"),
                 ty: Type::Integer,
                 value_id: ValueId(1),
+                dimension: None,
             },
         ],
         return_ty: Type::Integer,
+        return_dimension: None,
+        fallible: false,
         blocks: vec![IrBlock {
             id: BlockId(0),
             instructions: vec![IrInstr::BinOp {
@@ -241,11 +246,16 @@ fn test_function() {
 #[test]
 fn test_module() {
     let module = IrModule {
+        imports: vec![],
+        externs: vec![],
+        globals: vec![],
         functions: vec![IrFunction {
             id: FunctionId(0),
             name: InternedString::new("main"),
             params: vec![],
             return_ty: Type::Integer,
+            return_dimension: None,
+            fallible: false,
             blocks: vec![IrBlock {
                 id: BlockId(0),
                 instructions: vec![IrInstr::Const {
@@ -441,3 +451,615 @@ fn test_builder_record_construction() {
     let output = module.to_string();
     assert!(output.contains("record { x = v0, y = v1 }"));
 }
+
+/// Evaluates `src` with IR generation and returns the diagnostics.
+fn ir_diagnostics(src: &str) -> Vec<crate::Diagnostic> {
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    compiler.take_diagnostics()
+}
+
+#[test]
+fn test_use_after_move() {
+    let src = "fn keep s = s\nfn twice n =\n    let a = \"x\"\n    let b = a\n    keep a\n";
+    let diagnostics = ir_diagnostics(src);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert!(diagnostics[0].is_error());
+    assert!(matches!(
+        diagnostics[0].kind(),
+        crate::DiagnosticKind::UseAfterMove(name) if &**name == "a"
+    ));
+    assert_eq!(
+        diagnostics[0].span,
+        Some(cadenza_syntax::span::Span::new(66, 67))
+    );
+
+    // Passing a value to a function moves it too
+    let src = "fn both a b = a\nfn dup n =\n    let a = \"x\"\n    both a a\n";
+    assert_eq!(ir_diagnostics(src).len(), 1);
+}
+
+#[test]
+fn test_host_imports() {
+    let src = "fn angle y x = atan2 y x\n\
+               fn greet name = print (concat \"hello, \" name)\n\
+               fn describe = concat \"n = \" (to_string 2)\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+    let module = compiler.build_ir_module().unwrap();
+    assert_eq!(module.imports.len(), 4, "{module}");
+
+    let wat = super::wasm::generate_wat(&module).unwrap();
+    for import in [
+        r#"(import "cadenza:host/math@0.1.0" "atan2""#,
+        r#"(import "cadenza:host/strings@0.1.0" "concat""#,
+        r#"(import "cadenza:host/strings@0.1.0" "from-int""#,
+        r#"(import "cadenza:host/strings@0.1.0" "from-utf8""#,
+        r#"(import "cadenza:host/io@0.1.0" "print""#,
+    ] {
+        assert!(wat.contains(import), "missing {import} in\n{wat}");
+    }
+    assert!(wat.contains(r#"(export "memory" (memory 0))"#), "{wat}");
+    assert!(wat.contains(r#""hello, n = ""#), "{wat}");
+}
+
+#[test]
+fn test_host_string_equality() {
+    let src = "fn is_hi name = (concat \"h\" name) == \"hi\"\n\
+               fn not_hi name = (concat \"h\" name) != \"hi\"\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    let output = compiler.build_ir_module().unwrap().to_string();
+    assert!(
+        output.contains("# import cadenza:host/strings@0.1.0.equals"),
+        "{output}"
+    );
+    assert!(output.contains("let v5: bool = unop not v4"), "{output}");
+}
+
+#[test]
+fn test_host_call_type_errors() {
+    let diagnostics = ir_diagnostics("fn describe n = to_string n\n");
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert!(
+        diagnostics[0]
+            .to_string()
+            .contains("No host function for to_string takes (unknown)"),
+        "{}",
+        diagnostics[0]
+    );
+}
+
+#[test]
+fn test_quantity_dimensions() {
+    let src = "measure millimeter\n\
+               measure inch = millimeter 25.4\n\
+               measure second\n\
+               fn grow x = x + 1inch\n\
+               fn speed d t = (inch d) / (second t)\n\
+               fn count n = n + 1\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+    let module = compiler.build_ir_module().unwrap();
+    let output = module.to_string();
+    assert!(output.contains("const 25.4 millimeter"), "{output}");
+
+    let binary = super::wasm::generate_wasm(&module, &Default::default()).unwrap();
+    let dimensions = super::wasm::DimensionMetadata::read(&binary)
+        .unwrap()
+        .expect("no dimensions section");
+    let names: Vec<_> = dimensions.functions.keys().map(String::as_str).collect();
+    assert_eq!(names, ["grow", "speed"]);
+    let grow = &dimensions.functions["grow"];
+    assert_eq!(grow.params, [Some("millimeter".to_string())]);
+    assert_eq!(grow.result.as_deref(), Some("millimeter"));
+    let speed = &dimensions.functions["speed"];
+    assert_eq!(speed.params, [None, None]);
+    assert_eq!(speed.result.as_deref(), Some("millimeter/second"));
+}
+
+#[test]
+fn test_quantity_dimension_errors() {
+    let diagnostics = ir_diagnostics(
+        "measure meter\n\
+         measure second\n\
+         fn bad = 1meter + 1second\n",
+    );
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert!(
+        diagnostics[0]
+            .to_string()
+            .contains("cannot add quantities with incompatible dimensions"),
+        "{}",
+        diagnostics[0]
+    );
+}
+
+#[test]
+fn test_global_constants() {
+    let src = "let limit = 10\n\
+               let name = \"cadenza\"\n\
+               let total = 0\n\
+               total = total + 1\n\
+               fn clamp x = x % limit\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+    let mut module = compiler.build_ir_module().unwrap();
+    // `total` is assigned, so it isn't a constant
+    let names: Vec<_> = module.globals.iter().map(|g| g.name.to_string()).collect();
+    assert_eq!(names, ["limit", "name"]);
+
+    let limit = module.globals[0].id;
+    module.exports.push(IrExport {
+        name: "limit".into(),
+        kind: IrExportKind::Constant(limit),
+    });
+    let wat = super::wasm::generate_wat(&module).unwrap();
+    assert!(
+        wat.contains("(global $limit (;0;) i64 i64.const 10)"),
+        "{wat}"
+    );
+    assert!(wat.contains(r#"(export "limit" (global $limit))"#), "{wat}");
+
+    let name = module.globals[1].id;
+    module.exports.push(IrExport {
+        name: "name".into(),
+        kind: IrExportKind::Constant(name),
+    });
+    let error = super::wasm::generate_wat(&module).unwrap_err();
+    assert!(error.contains("string constants"), "{error}");
+}
+
+#[test]
+fn test_tuple_returns() {
+    let src = "fn divmod a b = (a / b, a - (a / b) * b)\n\
+               fn sum_divmod a b =\n    let (q, r) = divmod a b\n    q + r\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+    let module = compiler.build_ir_module().unwrap();
+    let wat = super::wasm::generate_wat(&module).unwrap();
+    // Tuples are never boxed: `divmod` returns both elements as results
+    assert!(wat.contains("(result i64 i64)"), "{wat}");
+    assert!(!wat.contains("struct"), "{wat}");
+
+    let src = "fn pair x = (x, x)\nfn bad x =\n    let (a, b, c) = pair x\n    a\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    // The evaluator can't see the mismatch until `bad` is called
+    let warnings: Vec<_> = compiler
+        .diagnostics()
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w.contains("Failed to generate IR for function bad")),
+        "{warnings:?}"
+    );
+}
+
+#[test]
+fn test_long_functions_reuse_locals() {
+    // Each value is read twice, so it needs a local, but it is dead once
+    // the next one is computed
+    let mut src = String::from("fn chain x =\n    let v0 = x\n");
+    for i in 1..=100 {
+        src.push_str(&format!("    let v{i} = v{} * v{} + 1\n", i - 1, i - 1));
+    }
+    src.push_str("    v100\n");
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(&src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+    let module = compiler.build_ir_module().unwrap();
+    let wat = super::wasm::generate_wat(&module).unwrap();
+    let locals = wat
+        .lines()
+        .find(|line| line.trim_start().starts_with("(local "))
+        .map_or(0, |line| line.matches("i64").count());
+    assert!(locals <= 2, "{locals} locals:\n{wat}");
+}
+
+#[test]
+fn test_trap_source_map() {
+    let src = "fn div a b = a / b\nfn run a =\n    let x = div a 0\n    x + 1\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    let module = compiler.build_ir_module().unwrap();
+    let wasm = super::wasm::generate_wasm(&module, &Default::default()).unwrap();
+    let source_map = SourceMap::read(&wasm).unwrap().unwrap();
+
+    let [check, div, call, overflow] = &source_map.sites[..] else {
+        panic!("{source_map:?}");
+    };
+    assert_eq!(wasm[check.offset as usize], 0x00, "unreachable");
+    assert_eq!(check.message.as_deref(), Some("division by zero"));
+    assert_eq!(wasm[div.offset as usize], 0x7f, "i64.div_s");
+    assert_eq!(
+        (div.function.as_str(), div.line, div.column),
+        ("div", 1, 14)
+    );
+    assert_eq!(&src[div.span.start..div.span.end], "a / b");
+    assert_eq!(div.message, None);
+    assert_eq!(wasm[call.offset as usize], 0x10, "call");
+    assert_eq!(
+        (call.function.as_str(), call.line, call.column),
+        ("run", 3, 13)
+    );
+    // `x + 1` checks that the sum fits in an i64
+    assert_eq!(wasm[overflow.offset as usize], 0x00, "unreachable");
+    assert_eq!(overflow.message.as_deref(), Some("integer overflow"));
+    assert_eq!(&src[overflow.span.start..overflow.span.end], "x + 1");
+
+    // Engines report the trapping instruction, then the calls that led to it
+    let diagnostic = source_map.trap_diagnostic("unreachable", &[check.offset, call.offset]);
+    assert_eq!(diagnostic.kind().code(), "E0016");
+    assert_eq!(diagnostic.span, Some(div.span));
+    let frames: Vec<_> = diagnostic
+        .stack_trace
+        .iter()
+        .map(|frame| frame.name.unwrap().to_string())
+        .collect();
+    assert_eq!(frames, ["div", "run"]);
+    assert!(
+        diagnostic
+            .to_string()
+            .starts_with("error: trap: division by zero")
+    );
+
+    // Any other trap keeps the engine's message
+    let diagnostic = source_map.trap_diagnostic("integer overflow", &[div.offset, call.offset]);
+    assert!(
+        diagnostic
+            .to_string()
+            .starts_with("error: trap: integer overflow")
+    );
+}
+
+#[test]
+fn test_checked_assert() {
+    let src = "fn check x =\n    assert x > 0 \"x must be positive\"\n    assert x < 10\n    assert 1 == 1\n    x\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    let mut module = compiler.build_ir_module().unwrap();
+    let ir = module.to_string();
+    assert!(
+        ir.contains("assert v2 \"x must be positive\\n  condition: x > 0\""),
+        "{ir}"
+    );
+    assert!(ir.contains("\"Assertion failed: x < 10\""), "{ir}");
+
+    // An assert that always passes is folded away
+    OptimizationPipeline::default_pipeline().run(&mut module, 10);
+    assert_eq!(module.to_string().matches("assert ").count(), 2);
+
+    let wasm = super::wasm::generate_wasm(&module, &Default::default()).unwrap();
+    let source_map = SourceMap::read(&wasm).unwrap().unwrap();
+    let messages: Vec<_> = source_map
+        .sites
+        .iter()
+        .filter_map(|site| site.message.as_deref())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "x must be positive\n  condition: x > 0",
+            "Assertion failed: x < 10"
+        ]
+    );
+}
+
+#[test]
+fn test_fallible_functions() {
+    let mut builder = IrBuilder::new();
+    let x = || vec![(InternedString::new("x"), Type::Integer)];
+
+    // fn find x = fail "not found"
+    let mut find = builder.function(InternedString::new("find"), x(), Type::Integer);
+    find.set_fallible();
+    let block = find.block();
+    let (block, next_val) = block.fail(InternedString::new("not found"), dummy_source());
+    find.add_block(block, next_val);
+    let find = builder.add_function(find.build());
+
+    // fn lookup x = (find x) + x, passing on find's failure
+    let mut lookup = builder.function(InternedString::new("lookup"), x(), Type::Integer);
+    lookup.set_fallible();
+    let mut block = lookup.block();
+    let found = block.call(find, vec![ValueId(0)], Type::Integer, dummy_source());
+    let sum = block.binop(BinOp::Add, found, ValueId(0), Type::Integer, dummy_source());
+    let (block, next_val) = block.ret(Some(sum), dummy_source());
+    lookup.add_block(block, next_val);
+    let lookup = builder.add_function(lookup.build());
+    builder.export_function(InternedString::new("lookup"), lookup);
+
+    let module = builder.module().clone();
+    let ir = module.to_string();
+    assert!(ir.contains("fallible"), "{ir}");
+    assert!(ir.contains("fail \"not found\""), "{ir}");
+
+    let wasm = super::wasm::generate_wasm(&module, &Default::default()).unwrap();
+    let wat = super::wasm::binary_to_wat(&wasm).unwrap();
+    assert!(
+        wat.contains("(param i64) (result i64 i32)"),
+        "fallible functions return a status:\n{wat}"
+    );
+
+    let source_map = SourceMap::read(&wasm).unwrap().unwrap();
+    let [failure] = &source_map.failures[..] else {
+        panic!("{source_map:?}");
+    };
+    assert_eq!(failure.function, "find");
+    assert_eq!(failure.message, "not found");
+    assert!(source_map.failure_diagnostic(0).is_none());
+    let diagnostic = source_map.failure_diagnostic(1).unwrap();
+    assert_eq!(diagnostic.kind().code(), "E0017");
+    assert!(
+        diagnostic
+            .to_string()
+            .starts_with("error: failure: not found")
+    );
+
+    // Only a fallible function can call one
+    let mut main = builder.function(InternedString::new("main"), x(), Type::Integer);
+    let mut block = main.block();
+    let found = block.call(find, vec![ValueId(0)], Type::Integer, dummy_source());
+    let (block, next_val) = block.ret(Some(found), dummy_source());
+    main.add_block(block, next_val);
+    builder.add_function(main.build());
+    let error = super::wasm::generate_wasm(&builder.build(), &Default::default()).unwrap_err();
+    assert!(error.contains("must be fallible too"), "{error}");
+}
+
+#[test]
+fn test_constant_pool_codegen() {
+    let src = "let c = 299792458.0\nfn light = c\nfn slope = atan2 299792458.0 299792458.0\nfn wave = sin 6.2831853\nfn tilt = cos 6.2831853\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    compiler.set_options(crate::options::CompileOptions {
+        opt_level: crate::options::OptLevel::O2,
+        ..Default::default()
+    });
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    let module = compiler.build_ir_module().unwrap();
+    let ir = module.to_string();
+    // Repeats of an existing constant read it
+    assert!(
+        ir.contains("global global1 pool0: float = const 6.2831853"),
+        "{ir}"
+    );
+    assert_eq!(ir.matches("const 299792458").count(), 1, "{ir}");
+    assert_eq!(ir.matches("= global global0").count(), 3, "{ir}");
+
+    // Each pooled constant is emitted once, as an immutable global
+    let wasm = super::wasm::generate_wasm(&module, &Default::default()).unwrap();
+    let wat = super::wasm::binary_to_wat(&wasm).unwrap();
+    assert_eq!(wat.matches("f64.const").count(), 2, "{wat}");
+    assert_eq!(wat.matches("global.get").count(), 5, "{wat}");
+}
+
+#[test]
+fn test_call_graph_depth_and_recursion() {
+    let src = "fn leaf x = x + 1\nfn mid x = (leaf x) * 2\nfn top x = mid x\nfn outer x = 1 + (mid x)\nfn countdown n = countdown (n - 1)\nfn fact n = n * (fact (n - 1))\nfn factorial n = fact n\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    let module = compiler.build_ir_module().unwrap();
+    let graph = CallGraph::new(&module);
+    let depth = |name: &str| {
+        let func = module.functions.iter().find(|f| &*f.name == name).unwrap();
+        graph.max_depth(func.id)
+    };
+    assert_eq!(depth("leaf"), Some(1));
+    assert_eq!(depth("mid"), Some(2));
+    // A tail call replaces its caller's frame
+    assert_eq!(depth("top"), Some(2));
+    assert_eq!(depth("outer"), Some(3));
+    assert_eq!(depth("countdown"), Some(1));
+    assert_eq!(depth("fact"), None);
+    assert_eq!(depth("factorial"), None);
+
+    // Building the module reports the recursion
+    let warnings: Vec<_> = compiler
+        .diagnostics()
+        .iter()
+        .filter(|d| d.kind().code() == "W0003")
+        .map(|d| d.to_string())
+        .collect();
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(
+        warnings[0].starts_with("warning: unbounded recursion: fact -> fact"),
+        "{}",
+        warnings[0]
+    );
+}
+
+#[test]
+fn test_call_graph_mutual_recursion() {
+    let mut builder = IrBuilder::new();
+    let x = || vec![(InternedString::new("x"), Type::Integer)];
+    let mut even = builder.function(InternedString::new("even"), x(), Type::Integer);
+    let mut odd = builder.function(InternedString::new("odd"), x(), Type::Integer);
+    let mut ping = builder.function(InternedString::new("ping"), x(), Type::Integer);
+    let mut pong = builder.function(InternedString::new("pong"), x(), Type::Integer);
+    let (even_id, odd_id, ping_id, pong_id) = (even.id(), odd.id(), ping.id(), pong.id());
+
+    // even and odd, and ping, only make tail calls; pong doesn't
+    for (func, callee) in [
+        (&mut even, odd_id),
+        (&mut odd, even_id),
+        (&mut ping, pong_id),
+    ] {
+        let mut block = func.block();
+        let result = block.call(callee, vec![ValueId(0)], Type::Integer, dummy_source());
+        let (block, next_val) = block.ret(Some(result), dummy_source());
+        func.add_block(block, next_val);
+    }
+    let mut block = pong.block();
+    let result = block.call(ping_id, vec![ValueId(0)], Type::Integer, dummy_source());
+    let sum = block.binop(
+        BinOp::Add,
+        result,
+        ValueId(0),
+        Type::Integer,
+        dummy_source(),
+    );
+    let (block, next_val) = block.ret(Some(sum), dummy_source());
+    pong.add_block(block, next_val);
+    for func in [even, odd, ping, pong] {
+        builder.add_function(func.build());
+    }
+    let module = builder.build();
+
+    let graph = CallGraph::new(&module);
+    assert_eq!(graph.max_depth(even_id), Some(1));
+    assert_eq!(graph.max_depth(ping_id), None);
+    assert!(graph.calls(ping_id)[0].tail);
+    assert!(!graph.calls(pong_id)[0].tail);
+
+    let [recursion] = &graph.unbounded_recursion()[..] else {
+        panic!("{:?}", graph.unbounded_recursion());
+    };
+    assert_eq!(
+        recursion.cycle,
+        ["pong", "ping", "pong"].map(InternedString::new)
+    );
+    let diagnostic = recursion.diagnostic();
+    assert_eq!(diagnostic.kind().code(), "W0003");
+    assert!(diagnostic.is_warning());
+    assert_eq!(diagnostic.stack_trace.len(), 2);
+    assert!(
+        diagnostic
+            .to_string()
+            .starts_with("warning: unbounded recursion: pong -> ping -> pong")
+    );
+}
+
+#[test]
+fn test_constant_argument_codegen() {
+    let src = "fn scale x factor = x * factor\nfn double x = scale x 2\nfn ten = scale 5 2\nfn area w h = w * h\nfn nine = area 3 3\n";
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    compiler.set_options(crate::options::CompileOptions {
+        opt_level: crate::options::OptLevel::O2,
+        ..Default::default()
+    });
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    let module = compiler.build_ir_module().unwrap();
+    let ir = module.to_string();
+    let func = |name: &str| module.functions.iter().find(|f| &*f.name == name).unwrap();
+
+    // Every call passes 2 as the factor, so it moves into scale
+    assert_eq!(func("scale").params.len(), 1, "{ir}");
+    assert!(
+        ir.contains("fn scale x =\n    block block_0 =\n        let v1: integer = const 2"),
+        "{ir}"
+    );
+    assert!(ir.contains("call func0 v0\n"), "{ir}");
+
+    // With both arguments constant, the area folds to a constant
+    assert!(func("area").params.is_empty(), "{ir}");
+    assert!(ir.contains("const 9"), "{ir}");
+    assert!(!ir.contains("const 3"), "{ir}");
+
+    super::wasm::generate_wasm(&module, &Default::default()).unwrap();
+}
+
+#[test]
+fn test_deterministic_float_codegen() {
+    let src = "fn third = 1.0 / 3.0\nfn area = 1.5 * 2.0\nfn count = 1 + 2\n";
+    let options = crate::options::CompileOptions {
+        features: [crate::options::CompileOptions::DETERMINISTIC_FLOATS.to_string()].into(),
+        ..Default::default()
+    };
+    let mut env = crate::Env::with_standard_builtins();
+    let mut compiler = crate::Compiler::with_ir();
+    compiler.set_options(options.clone());
+    crate::eval(
+        &cadenza_syntax::parse::parse(src).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    let module = compiler.build_ir_module().unwrap();
+    let binary = super::wasm::generate_wasm(&module, &options).unwrap();
+    let wat = super::wasm::binary_to_wat(&binary).unwrap();
+
+    // Each float operation replaces a NaN result with the canonical NaN;
+    // integer arithmetic is left alone
+    assert_eq!(wat.matches("select").count(), 2, "{wat}");
+    assert!(wat.contains("f64.const nan (;=NaN;)"), "{wat}");
+    assert!(!wat.contains("-nan"), "{wat}");
+
+    let plain = super::wasm::generate_wasm(&module, &Default::default()).unwrap();
+    assert!(
+        !super::wasm::binary_to_wat(&plain)
+            .unwrap()
+            .contains("select")
+    );
+}
@@ -1,6 +1,8 @@
 //! Core types for the Cadenza IR.
 
-use crate::{Dimension, InternedString, Type};
+use super::host::HostFunction;
+use crate::{DerivedDimension, InternedString, Type};
+use cadenza_syntax::span::Span;
 use std::sync::Arc;
 
 /// Source location for tracking origins of IR nodes.
@@ -8,8 +10,12 @@ use std::sync::Arc;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SourceLocation {
     pub file: InternedString,
+    /// The line of the start of the node, from 1.
     pub line: u32,
+    /// The column of the start of the node, in characters from 1.
     pub column: u32,
+    /// The node's byte range in the file.
+    pub span: Span,
 }
 
 /// A unique identifier for values in SSA form.
@@ -42,6 +48,16 @@ impl std::fmt::Display for FunctionId {
     }
 }
 
+/// A unique identifier for module-level constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlobalId(pub u32);
+
+impl std::fmt::Display for GlobalId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "global{}", self.0)
+    }
+}
+
 /// Constants in IR.
 #[derive(Debug, Clone, PartialEq)]
 pub enum IrConst {
@@ -50,10 +66,11 @@ pub enum IrConst {
     Integer(i64),
     Float(f64),
     String(InternedString),
-    /// Quantity with dimension (e.g., 5.0 meters)
+    /// Quantity with dimension (e.g., 5.0 meters), in the base units of its
+    /// dimension
     Quantity {
         value: f64,
-        dimension: Dimension,
+        dimension: DerivedDimension,
     },
 }
 
@@ -65,8 +82,7 @@ impl std::fmt::Display for IrConst {
             IrConst::Integer(i) => write!(f, "{}", i),
             IrConst::Float(fl) => write!(f, "{}", fl),
             IrConst::String(s) => write!(f, "\"{}\"", s),
-            // Note: Using Debug for Dimension since it doesn't implement Display
-            IrConst::Quantity { value, dimension } => write!(f, "{}{:?}", value, dimension),
+            IrConst::Quantity { value, dimension } => write!(f, "{} {}", value, dimension),
         }
     }
 }
@@ -80,6 +96,8 @@ pub enum BinOp {
     Mul,
     Div,
     Rem,
+    Min,
+    Max,
 
     // Comparison
     Eq,
@@ -109,6 +127,8 @@ impl std::fmt::Display for BinOp {
             BinOp::Mul => write!(f, "mul"),
             BinOp::Div => write!(f, "div"),
             BinOp::Rem => write!(f, "rem"),
+            BinOp::Min => write!(f, "min"),
+            BinOp::Max => write!(f, "max"),
             BinOp::Eq => write!(f, "eq"),
             BinOp::Ne => write!(f, "ne"),
             BinOp::Lt => write!(f, "lt"),
@@ -130,6 +150,11 @@ impl std::fmt::Display for BinOp {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UnOp {
     Neg,    // Numeric negation
+    Abs,    // Absolute value
+    Sqrt,   // Float square root
+    Floor,  // Float rounding down
+    Ceil,   // Float rounding up
+    Round,  // Float rounding to nearest, ties to even
     Not,    // Logical not
     BitNot, // Bitwise not (future)
 }
@@ -138,6 +163,11 @@ impl std::fmt::Display for UnOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             UnOp::Neg => write!(f, "neg"),
+            UnOp::Abs => write!(f, "abs"),
+            UnOp::Sqrt => write!(f, "sqrt"),
+            UnOp::Floor => write!(f, "floor"),
+            UnOp::Ceil => write!(f, "ceil"),
+            UnOp::Round => write!(f, "round"),
             UnOp::Not => write!(f, "not"),
             UnOp::BitNot => write!(f, "bitnot"),
         }
@@ -226,6 +256,31 @@ pub enum IrInstr {
         incoming: Vec<(ValueId, BlockId)>,
         source: SourceLocation,
     },
+
+    /// Read a module-level constant
+    /// %result: ty = global <global>
+    Global {
+        result: ValueId,
+        ty: Type,
+        global: GlobalId,
+        source: SourceLocation,
+    },
+
+    /// Delete a linear value whose owner goes out of scope
+    /// drop %value
+    Drop {
+        value: ValueId,
+        ty: Type,
+        source: SourceLocation,
+    },
+
+    /// Trap with `message` unless the boolean %cond holds
+    /// assert %cond "message"
+    Assert {
+        cond: ValueId,
+        message: InternedString,
+        source: SourceLocation,
+    },
 }
 
 impl IrInstr {
@@ -238,8 +293,10 @@ impl IrInstr {
             | IrInstr::Record { result, .. }
             | IrInstr::Field { result, .. }
             | IrInstr::Tuple { result, .. }
-            | IrInstr::Phi { result, .. } => Some(*result),
+            | IrInstr::Phi { result, .. }
+            | IrInstr::Global { result, .. } => Some(*result),
             IrInstr::Call { result, .. } => *result,
+            IrInstr::Drop { .. } | IrInstr::Assert { .. } => None,
         }
     }
 
@@ -253,7 +310,10 @@ impl IrInstr {
             | IrInstr::Record { source, .. }
             | IrInstr::Field { source, .. }
             | IrInstr::Tuple { source, .. }
-            | IrInstr::Phi { source, .. } => source,
+            | IrInstr::Phi { source, .. }
+            | IrInstr::Global { source, .. }
+            | IrInstr::Drop { source, .. }
+            | IrInstr::Assert { source, .. } => source,
         }
     }
 }
@@ -362,6 +422,20 @@ impl std::fmt::Display for IrInstr {
                 }
                 Ok(())
             }
+            IrInstr::Global {
+                result, ty, global, ..
+            } => {
+                // Constant reads with global prefix: let v0: integer = global global0
+                write!(f, "let {}: {} = global {}", result, ty, global)
+            }
+            IrInstr::Drop { value, .. } => {
+                // Deleters as statements: drop v1
+                write!(f, "drop {}", value)
+            }
+            IrInstr::Assert { cond, message, .. } => {
+                // Checks as statements: assert v1 "division by zero"
+                write!(f, "assert {} {:?}", cond, &**message)
+            }
         }
     }
 }
@@ -391,6 +465,13 @@ pub enum IrTerminator {
         value: Option<ValueId>, // None for void functions
         source: SourceLocation,
     },
+
+    /// Return from a fallible function with an error instead of a value
+    /// fail "message"
+    Fail {
+        message: InternedString,
+        source: SourceLocation,
+    },
 }
 
 impl std::fmt::Display for IrTerminator {
@@ -417,6 +498,10 @@ impl std::fmt::Display for IrTerminator {
                     write!(f, "ret")
                 }
             }
+            IrTerminator::Fail { message, .. } => {
+                // Failure with fail keyword: fail "not found"
+                write!(f, "fail {:?}", &**message)
+            }
         }
     }
 }
@@ -447,6 +532,8 @@ pub struct IrParam {
     pub name: InternedString,
     pub ty: Type,
     pub value_id: ValueId, // SSA value for this parameter
+    /// The dimension of the quantity the parameter takes, in base units.
+    pub dimension: Option<DerivedDimension>,
 }
 
 impl std::fmt::Display for IrParam {
@@ -463,6 +550,12 @@ pub struct IrFunction {
     pub name: InternedString,
     pub params: Vec<IrParam>,
     pub return_ty: Type,
+    /// The dimension of the quantity the function returns, in base units.
+    pub return_dimension: Option<DerivedDimension>,
+    /// Whether the function can fail (see [`IrTerminator::Fail`]). A call to
+    /// a fallible function passes its failure on, as `?` does, so only a
+    /// fallible function can make one.
+    pub fallible: bool,
     pub blocks: Vec<IrBlock>,
     pub entry_block: BlockId,
 }
@@ -473,8 +566,18 @@ impl std::fmt::Display for IrFunction {
         write!(f, "@t")?;
         for param in &self.params {
             write!(f, " {}", param.ty)?;
+            if let Some(dimension) = &param.dimension {
+                write!(f, "[{dimension}]")?;
+            }
+        }
+        write!(f, " -> {}", self.return_ty)?;
+        if let Some(dimension) = &self.return_dimension {
+            write!(f, "[{dimension}]")?;
         }
-        writeln!(f, " -> {}", self.return_ty)?;
+        if self.fallible {
+            write!(f, " fallible")?;
+        }
+        writeln!(f)?;
 
         // Function signature with parameter names
         write!(f, "fn {}", self.name)?;
@@ -502,7 +605,14 @@ pub struct IrExport {
 #[derive(Debug, Clone, PartialEq)]
 pub enum IrExportKind {
     Function(FunctionId),
-    Constant(ValueId),
+    Constant(GlobalId),
+}
+
+/// A definition exported from a module (see [`IrModule::exported`]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exported<'a> {
+    Function(&'a IrFunction),
+    Constant(&'a IrGlobal),
 }
 
 impl std::fmt::Display for IrExport {
@@ -512,16 +622,81 @@ impl std::fmt::Display for IrExport {
                 // Export as a comment
                 write!(f, "# export {} as function {}", self.name, func_id)
             }
-            IrExportKind::Constant(val_id) => {
-                write!(f, "# export {} as constant {}", self.name, val_id)
+            IrExportKind::Constant(global) => {
+                write!(f, "# export {} as constant {}", self.name, global)
             }
         }
     }
 }
 
+/// A host function called by a module (see [`host`](super::host)).
+///
+/// Calls to it use its function ID, like calls to the module's own functions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrImport {
+    pub id: FunctionId,
+    pub function: &'static HostFunction,
+}
+
+impl std::fmt::Display for IrImport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "# import {}.{} as function {}",
+            self.function.interface.module(),
+            self.function.name,
+            self.id
+        )
+    }
+}
+
+/// A function a module calls but doesn't define, left for [`link`] to
+/// resolve by name against the exports of the modules linked with it.
+///
+/// Calls to it use its function ID, like calls to the module's own functions.
+///
+/// [`link`]: super::link
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrExtern {
+    pub id: FunctionId,
+    pub name: InternedString,
+}
+
+impl std::fmt::Display for IrExtern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "# extern {} as function {}", self.name, self.id)
+    }
+}
+
+/// An immutable module-level value: a top-level `let` whose initializer is
+/// a constant.
+///
+/// Functions read it with [`IrInstr::Global`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrGlobal {
+    pub id: GlobalId,
+    pub name: InternedString,
+    pub ty: Type,
+    pub value: IrConst,
+}
+
+impl std::fmt::Display for IrGlobal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Globals as declarations: global global0 width: float = const 50.8 millimeter
+        write!(
+            f,
+            "global {} {}: {} = const {}",
+            self.id, self.name, self.ty, self.value
+        )
+    }
+}
+
 /// Complete IR module.
 #[derive(Debug, Clone, PartialEq)]
 pub struct IrModule {
+    pub imports: Vec<IrImport>,
+    pub externs: Vec<IrExtern>,
+    pub globals: Vec<IrGlobal>,
     pub functions: Vec<IrFunction>,
     pub exports: Vec<IrExport>,
 }
@@ -530,10 +705,56 @@ impl IrModule {
     /// Create a new empty IR module.
     pub fn new() -> Self {
         Self {
+            imports: Vec::new(),
+            externs: Vec::new(),
+            globals: Vec::new(),
             functions: Vec::new(),
             exports: Vec::new(),
         }
     }
+
+    /// Returns the host function imported as `id`, if it is an import.
+    pub fn import(&self, id: FunctionId) -> Option<&'static HostFunction> {
+        self.imports
+            .iter()
+            .find(|import| import.id == id)
+            .map(|import| import.function)
+    }
+
+    /// Returns the name of the function declared as `id`, if it is an
+    /// extern.
+    pub fn extern_name(&self, id: FunctionId) -> Option<InternedString> {
+        self.externs
+            .iter()
+            .find(|ext| ext.id == id)
+            .map(|ext| ext.name)
+    }
+
+    /// Returns the global with `id`, if the module defines it.
+    pub fn global(&self, id: GlobalId) -> Option<&IrGlobal> {
+        self.globals.iter().find(|global| global.id == id)
+    }
+
+    /// Returns the function with `id`, if the module defines it.
+    pub fn function(&self, id: FunctionId) -> Option<&IrFunction> {
+        self.functions.iter().find(|func| func.id == id)
+    }
+
+    /// Returns each export's name with the definition it exports, in export
+    /// order.
+    ///
+    /// This is the module's interface for generating bindings, such as WIT
+    /// worlds or TypeScript declarations: functions carry their parameter
+    /// names and types and their return type, and constants their type.
+    pub fn exported(&self) -> impl Iterator<Item = (InternedString, Exported<'_>)> {
+        self.exports.iter().filter_map(|export| {
+            let item = match export.kind {
+                IrExportKind::Function(id) => Exported::Function(self.function(id)?),
+                IrExportKind::Constant(id) => Exported::Constant(self.global(id)?),
+            };
+            Some((export.name, item))
+        })
+    }
 }
 
 impl Default for IrModule {
@@ -547,6 +768,27 @@ impl std::fmt::Display for IrModule {
         writeln!(f, "# IR Module")?;
         writeln!(f)?;
 
+        if !self.imports.is_empty() {
+            for import in &self.imports {
+                writeln!(f, "{}", import)?;
+            }
+            writeln!(f)?;
+        }
+
+        if !self.externs.is_empty() {
+            for ext in &self.externs {
+                writeln!(f, "{}", ext)?;
+            }
+            writeln!(f)?;
+        }
+
+        if !self.globals.is_empty() {
+            for global in &self.globals {
+                writeln!(f, "{}", global)?;
+            }
+            writeln!(f)?;
+        }
+
         for func in &self.functions {
             writeln!(f, "{}", func)?;
             writeln!(f)?;
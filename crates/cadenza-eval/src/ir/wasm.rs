@@ -8,21 +8,103 @@
 //! - WasmGC (Garbage Collection) proposal
 //! - Reference types
 //! - Component Model (for future interop)
+//!
+//! Operations with no lowering yet, such as math functions, strings and
+//! output, call the host through imports (see [`host`](super::host)). A
+//! module with string literals stores them in a data segment of its memory,
+//! which it exports as `memory` for the host to read.
+//!
+//! Module-level constants become immutable WASM globals, which can be
+//! exported. A string constant has no global: it is stored with the string
+//! literals, and each read copies it to the host like a literal.
+//!
+//! Code generation gives every SSA value its own local. A peephole pass (see
+//! [`peephole`](super::peephole)) then keeps the values that are only passed
+//! to the next instruction on the stack, and lets values that are never live
+//! at the same time share a local.
+//!
+//! Tuples are never boxed: a tuple value occupies one local per element, and
+//! a function returning a tuple returns its elements as multiple results.
+//!
+//! Quantities are `f64`s in the base units of their dimension: the IR
+//! generator converts a number to base units where a unit is applied to it.
+//! A module whose functions take or return quantities describes their
+//! dimensions in a `cadenza.dimensions` custom section (see
+//! [`DimensionMetadata`]), so hosts can keep units straight across the
+//! boundary.
+//!
+//! A module with calls or instructions that can trap, such as integer
+//! division, maps them back to their source in a `cadenza.sourcemap` custom
+//! section (see [`SourceMap`]), so hosts can report traps as diagnostics.
+//!
+//! A fallible function (see [`IrFunction::fallible`]) returns an `i32` status
+//! after its results: 0 when it returned normally, and otherwise one more
+//! than the index of the failure in the source map. A call to a fallible
+//! function returns the callee's status from the caller when it is nonzero,
+//! which is how an error propagates. Plain status codes need no WASM
+//! exception handling, which not every engine supports yet.
+//!
+//! With the [`deterministic-floats`](CompileOptions::DETERMINISTIC_FLOATS)
+//! feature, float results are the same bits on every engine: WebAssembly
+//! lets an engine choose the sign and payload of a NaN that arithmetic
+//! produces, so each float addition, subtraction, multiplication and division
+//! replaces a NaN result with the canonical NaN, as do float constants. The
+//! code generator never emits fused operations.
+//!
+//! [`generate_wasm`] produces reproducible builds: exports are emitted in name
+//! order, nothing time- or host-dependent is embedded, and the output carries
+//! two custom sections. `cadenza.options` holds the [`CompileOptions`] used
+//! for the build, and `cadenza.build` (always last) holds the compiler version
+//! and a BLAKE3 hash of every byte before it (see [`BuildMetadata`]).
 
 use super::{
-    BinOp, BlockId, IrBlock, IrConst, IrFunction, IrInstr, IrModule, IrTerminator, UnOp, ValueId,
+    BinOp, BlockId, GlobalId, IrBlock, IrConst, IrFunction, IrInstr, IrModule, IrTerminator, UnOp,
+    ValueId,
+    host::{self, HostFunction},
+    peephole::{Body, Origin},
+};
+use crate::{
+    InternedString, Type,
+    diagnostic::{Diagnostic, DiagnosticKind, StackFrame},
+    options::{CompileOptions, Target},
+};
+use cadenza_syntax::span::Span;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
 };
-use crate::Type;
-use std::collections::{HashMap, HashSet};
 use wasm_encoder::*;
 
-/// Tracks where SSA values are located in WASM (parameters, locals, or stack).
+/// Tracks where SSA values are located in WASM (parameters, locals, or stack),
+/// and how the function returns them.
+///
+/// A tuple is never boxed: it takes one local per scalar element, in order,
+/// and its value ID maps to the first of them. Nil, the unit value, takes no
+/// locals at all.
 struct ValueLocationTracker {
     /// Maps SSA ValueId to WASM local index.
     /// Function parameters are locals 0..N, other values get locals N+1..
     value_to_local: HashMap<ValueId, u32>,
+    /// The types of the tuple and nil values, which take several locals or
+    /// none.
+    tuples: HashMap<ValueId, Type>,
     /// Next available local index for allocating new locals.
     next_local_idx: u32,
+    /// The results of the function if it's fallible, which a failure returns
+    /// zeros for, along with its status.
+    fallible: Option<Vec<ValType>>,
+    /// The local holding the status of the last call to a fallible function.
+    status: Option<u32>,
+    /// The error code of each block that fails.
+    failures: HashMap<BlockId, u32>,
+    /// The headers of the loops, which the blocks at the end of a loop body
+    /// jump back to.
+    loop_headers: HashSet<BlockId>,
+    /// For each loop being generated, innermost last, how many blocks deep
+    /// in its body the code being generated is: the label of a `br` back to
+    /// its start.
+    loops: RefCell<Vec<u32>>,
 }
 
 impl ValueLocationTracker {
@@ -38,19 +120,43 @@ impl ValueLocationTracker {
 
         Self {
             value_to_local,
+            tuples: HashMap::new(),
             next_local_idx: num_params,
+            fallible: None,
+            status: None,
+            failures: HashMap::new(),
+            loop_headers: HashSet::new(),
+            loops: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Records that the code being generated enters a block, `if` or
+    /// `loop`, inside the innermost loop.
+    fn enter_block(&self) {
+        if let Some(depth) = self.loops.borrow_mut().last_mut() {
+            *depth += 1;
+        }
+    }
+
+    /// Records that the code being generated leaves the block it entered last.
+    fn exit_block(&self) {
+        if let Some(depth) = self.loops.borrow_mut().last_mut() {
+            *depth -= 1;
         }
     }
 
-    /// Allocate a local for a ValueId if it doesn't already have one.
-    /// Returns the local index.
-    fn allocate_local(&mut self, value_id: ValueId) -> u32 {
+    /// Allocate the locals for a ValueId of type `ty` if it doesn't already
+    /// have them. Returns the first local index.
+    fn allocate_local(&mut self, value_id: ValueId, ty: &Type) -> u32 {
         if let Some(&local_idx) = self.value_to_local.get(&value_id) {
             local_idx
         } else {
             let local_idx = self.next_local_idx;
-            self.next_local_idx += 1;
+            self.next_local_idx += scalar_count(ty);
             self.value_to_local.insert(value_id, local_idx);
+            if matches!(ty, Type::Tuple(_) | Type::Nil) {
+                self.tuples.insert(value_id, ty.clone());
+            }
             local_idx
         }
     }
@@ -59,6 +165,129 @@ impl ValueLocationTracker {
     fn get_local(&self, value_id: ValueId) -> Option<u32> {
         self.value_to_local.get(&value_id).copied()
     }
+
+    /// Returns the locals holding `value_id`: one for a scalar, one per
+    /// scalar element for a tuple and none for nil.
+    fn locals(&self, value_id: ValueId) -> Result<std::ops::Range<u32>, String> {
+        let first = self
+            .get_local(value_id)
+            .ok_or_else(|| format!("No local for value {}", value_id))?;
+        let count = self.tuples.get(&value_id).map_or(1, scalar_count);
+        Ok(first..first + count)
+    }
+
+    /// Returns the locals holding element `index` of the tuple `tuple`.
+    fn element_locals(&self, tuple: ValueId, index: usize) -> Result<std::ops::Range<u32>, String> {
+        let Some(Type::Tuple(elements)) = self.tuples.get(&tuple) else {
+            return Err(format!("Value {} is not a tuple", tuple));
+        };
+        let element = elements
+            .get(index)
+            .ok_or_else(|| format!("Tuple {} has no element {}", tuple, index))?;
+        let first =
+            self.locals(tuple)?.start + elements[..index].iter().map(scalar_count).sum::<u32>();
+        Ok(first..first + scalar_count(element))
+    }
+
+    /// Pushes the value `value_id` onto the stack.
+    fn load(&self, func: &mut Body, value_id: ValueId) -> Result<(), String> {
+        for local in self.locals(value_id)? {
+            func.instruction(&Instruction::LocalGet(local));
+        }
+        Ok(())
+    }
+
+    /// Pops the value `value_id` off the stack into its locals.
+    fn store(&self, func: &mut Body, value_id: ValueId) -> Result<(), String> {
+        for local in self.locals(value_id)?.rev() {
+            func.instruction(&Instruction::LocalSet(local));
+        }
+        Ok(())
+    }
+
+    /// Pushes what the function returns for `value`, along with the success
+    /// status if it's fallible.
+    fn load_return(&self, func: &mut Body, value: Option<ValueId>) -> Result<(), String> {
+        if let Some(value_id) = value {
+            self.load(func, value_id)?;
+        }
+        if self.fallible.is_some() {
+            func.instruction(&Instruction::I32Const(0));
+        }
+        Ok(())
+    }
+
+    /// Returns from the function with zeros for its results and the failure
+    /// status that `status` pushes.
+    fn return_failure(&self, func: &mut Body, status: Instruction<'static>) -> Result<(), String> {
+        let results = self
+            .fallible
+            .as_ref()
+            .ok_or("Only a fallible function can fail")?;
+        for ty in results {
+            func.instruction(&match ty {
+                ValType::I64 => Instruction::I64Const(0),
+                ValType::F64 => Instruction::F64Const(0.0.into()),
+                _ => Instruction::I32Const(0),
+            });
+        }
+        func.instruction(&status);
+        func.instruction(&Instruction::Return);
+        Ok(())
+    }
+}
+
+/// Returns the loop headers of `func`: the blocks a jump goes back to from
+/// a block reachable from them.
+fn loop_headers(func: &IrFunction) -> HashSet<BlockId> {
+    let blocks: HashMap<BlockId, &IrBlock> =
+        func.blocks.iter().map(|block| (block.id, block)).collect();
+    let successors = |id: BlockId| match blocks.get(&id).map(|block| &block.terminator) {
+        Some(IrTerminator::Jump { target, .. }) => vec![*target],
+        Some(IrTerminator::Branch {
+            then_block,
+            else_block,
+            ..
+        }) => vec![*then_block, *else_block],
+        _ => vec![],
+    };
+
+    // A depth-first search finds the jumps to a block on the current path
+    let mut headers = HashSet::new();
+    let mut on_path = HashSet::new();
+    let mut done = HashSet::new();
+    let mut stack = vec![(func.entry_block, successors(func.entry_block))];
+    on_path.insert(func.entry_block);
+    while let Some((id, next)) = stack.last_mut() {
+        let id = *id;
+        match next.pop() {
+            Some(target) if on_path.contains(&target) => {
+                headers.insert(target);
+            }
+            Some(target) if !done.contains(&target) => {
+                on_path.insert(target);
+                stack.push((target, successors(target)));
+            }
+            Some(_) => {}
+            None => {
+                on_path.remove(&id);
+                done.insert(id);
+                stack.pop();
+            }
+        }
+    }
+    headers
+}
+
+/// Returns the number of WASM values a value of type `ty` lowers to: the
+/// scalar elements of a tuple, nested tuples included, none for nil, or else
+/// one.
+fn scalar_count(ty: &Type) -> u32 {
+    match ty {
+        Type::Tuple(elements) => elements.iter().map(scalar_count).sum(),
+        Type::Nil => 0,
+        _ => 1,
+    }
 }
 
 /// Information about an if-then-else-merge pattern with phi node.
@@ -82,16 +311,53 @@ pub struct WasmCodegen {
     module: Module,
     /// Type section for function signatures.
     types: TypeSection,
+    /// Import section for host functions.
+    imports: ImportSection,
     /// Function section for function type indices.
     functions: FunctionSection,
     /// Code section for function bodies.
     code: CodeSection,
+    /// Global section for module-level constants.
+    globals: GlobalSection,
     /// Export section.
     exports: ExportSection,
     /// Map from IR function IDs to WASM function indices.
     function_indices: HashMap<super::FunctionId, u32>,
+    /// Map from IR global IDs to WASM global indices.
+    global_indices: HashMap<GlobalId, u32>,
+    /// The string constants, which are read like string literals.
+    string_globals: HashMap<GlobalId, InternedString>,
+    /// The number of parameters and results of each function, imports
+    /// included, by WASM index.
+    signatures: Vec<(u32, u32)>,
+    /// WASM indices of the fallible functions.
+    fallible: HashSet<u32>,
+    /// WASM indices of the imported functions that never return.
+    diverging: HashSet<u32>,
+    /// The number of imported functions, which come first in the index space.
+    import_count: u32,
+    /// The string literals, laid out in memory from address 0.
+    data: Vec<u8>,
+    /// The address and length of each string literal in `data`.
+    strings: HashMap<InternedString, (u32, u32)>,
+    /// The WASM index of the host's `from-utf8`, if any string is used.
+    from_utf8: Option<u32>,
+    /// The name of each function with code, in order, and its calls and
+    /// instructions that can trap, by offset in its body.
+    trap_sites: Vec<(InternedString, Vec<(u32, Origin)>)>,
+    /// Every failure, by error code minus one.
+    failures: Vec<SourceMapFailure>,
     /// Counter for WASM function indices.
     next_function_index: u32,
+    /// The name of each function, imports included, by WASM index.
+    function_names: NameMap,
+    /// The name of each global by WASM index.
+    global_names: NameMap,
+    /// Serialized compile options to embed along with the build metadata, if any.
+    metadata: Option<String>,
+    /// Whether every NaN is made canonical, so float results don't depend on
+    /// the engine.
+    deterministic_floats: bool,
 }
 
 impl WasmCodegen {
@@ -100,16 +366,76 @@ impl WasmCodegen {
         Self {
             module: Module::new(),
             types: TypeSection::new(),
+            imports: ImportSection::new(),
             functions: FunctionSection::new(),
             code: CodeSection::new(),
+            globals: GlobalSection::new(),
             exports: ExportSection::new(),
             function_indices: HashMap::new(),
+            global_indices: HashMap::new(),
+            string_globals: HashMap::new(),
+            signatures: Vec::new(),
+            fallible: HashSet::new(),
+            diverging: HashSet::new(),
+            import_count: 0,
+            data: Vec::new(),
+            strings: HashMap::new(),
+            from_utf8: None,
+            trap_sites: Vec::new(),
+            failures: Vec::new(),
             next_function_index: 0,
+            function_names: NameMap::new(),
+            global_names: NameMap::new(),
+            metadata: None,
+            deterministic_floats: false,
+        }
+    }
+
+    /// Create a WASM code generator that embeds `options` and the build
+    /// metadata in the output.
+    pub fn with_options(options: &CompileOptions) -> Self {
+        Self {
+            metadata: Some(options.to_metadata()),
+            deterministic_floats: options.deterministic_floats(),
+            ..Self::new()
         }
     }
 
     /// Generate WASM binary from IR module.
     pub fn generate(&mut self, ir: &IrModule) -> Result<Vec<u8>, String> {
+        // A call to an extern has nothing to call until the module is linked
+        let calls = ir
+            .functions
+            .iter()
+            .flat_map(|func| &func.blocks)
+            .flat_map(|block| &block.instructions);
+        for instr in calls {
+            if let IrInstr::Call { func, .. } = instr
+                && let Some(name) = ir.extern_name(*func)
+            {
+                return Err(format!(
+                    "Cannot call {name}: it is declared but not defined; \
+                     link the module defining it first"
+                ));
+            }
+        }
+
+        // Lay out the string literals, then import the host functions, which
+        // take the first function indices
+        self.add_strings(ir);
+        let mut imports: Vec<_> = ir
+            .imports
+            .iter()
+            .map(|import| (Some(import.id), import.function))
+            .collect();
+        if !self.data.is_empty() && !imports.iter().any(|(_, f)| **f == host::FROM_UTF8) {
+            imports.push((None, &host::FROM_UTF8));
+        }
+        for (id, function) in imports {
+            self.add_import(id, function);
+        }
+        self.add_globals(ir);
+
         // Generate type signatures for all functions
         for func in &ir.functions {
             self.add_function_type(func)?;
@@ -120,34 +446,237 @@ impl WasmCodegen {
             self.add_function_code(func)?;
         }
 
-        // Generate exports
+        // Generate exports in name order so the output doesn't depend on
+        // definition order
+        let mut exports = Vec::new();
         for export in &ir.exports {
             match &export.kind {
                 super::IrExportKind::Function(func_id) => {
                     if let Some(&func_idx) = self.function_indices.get(func_id) {
-                        self.exports
-                            .export(&export.name.to_string(), ExportKind::Func, func_idx);
+                        exports.push((export.name.to_string(), ExportKind::Func, func_idx));
                     }
                 }
-                super::IrExportKind::Constant(_) => {
-                    // Constants are not exported as WASM exports directly
-                    // They would need to be wrapped in getter functions
+                super::IrExportKind::Constant(global) => {
+                    let Some(&global_idx) = self.global_indices.get(global) else {
+                        return Err(format!(
+                            "Cannot export {}: string constants have no WASM global",
+                            export.name
+                        ));
+                    };
+                    exports.push((export.name.to_string(), ExportKind::Global, global_idx));
                 }
             }
         }
+        let memory = (!self.data.is_empty()).then(|| {
+            let mut memory = MemorySection::new();
+            memory.memory(MemoryType {
+                minimum: (self.data.len() as u64).div_ceil(65536),
+                maximum: None,
+                memory64: false,
+                shared: false,
+                page_size_log2: None,
+            });
+            exports.push(("memory".to_string(), ExportKind::Memory, 0));
+            memory
+        });
+        exports.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, kind, index) in exports {
+            self.exports.export(&name, kind, index);
+        }
 
         // Assemble the module in the correct section order
-        // Order: Type, Import, Function, Table, Memory, Global, Export, Start, Element, DataCount, Code
+        // Order: Type, Import, Function, Table, Memory, Global, Export, Start, Element, DataCount, Code, Data
         self.module.section(&self.types);
+        if self.import_count > 0 {
+            self.module.section(&self.imports);
+        }
         self.module.section(&self.functions);
+        if let Some(memory) = &memory {
+            self.module.section(memory);
+        }
+        if !self.globals.is_empty() {
+            self.module.section(&self.globals);
+        }
         self.module.section(&self.exports);
         self.module.section(&self.code);
+        let source_map = self.source_map()?;
+        if memory.is_some() {
+            let mut data = DataSection::new();
+            data.active(0, &ConstExpr::i32_const(0), self.data.iter().copied());
+            self.module.section(&data);
+        }
+        // Name the functions, globals and string data for debuggers, stack
+        // traces and size reports
+        let mut names = NameSection::new();
+        names.functions(&self.function_names);
+        if !self.global_names.is_empty() {
+            names.globals(&self.global_names);
+        }
+        if memory.is_some() {
+            let mut data_names = NameMap::new();
+            data_names.append(0, "strings");
+            names.data(&data_names);
+        }
+        self.module.section(&names);
+        let dimensions = DimensionMetadata::from_module(ir);
+        if !dimensions.functions.is_empty() {
+            self.module.section(&CustomSection {
+                name: Cow::Borrowed(DimensionMetadata::SECTION_NAME),
+                data: Cow::Owned(dimensions.to_json().into_bytes()),
+            });
+        }
+        if !source_map.sites.is_empty() || !source_map.failures.is_empty() {
+            self.module.section(&CustomSection {
+                name: Cow::Borrowed(SourceMap::SECTION_NAME),
+                data: Cow::Owned(source_map.to_json().into_bytes()),
+            });
+        }
+        if let Some(metadata) = &self.metadata {
+            self.module.section(&CustomSection {
+                name: Cow::Borrowed(CompileOptions::SECTION_NAME),
+                data: Cow::Borrowed(metadata.as_bytes()),
+            });
+            let build = BuildMetadata::build_section(self.module.as_slice());
+            self.module.section(&CustomSection {
+                name: Cow::Borrowed(BuildMetadata::SECTION_NAME),
+                data: Cow::Owned(build.into_bytes()),
+            });
+        }
 
         // Use std::mem::replace to move out the module
         let module = std::mem::replace(&mut self.module, Module::new());
         Ok(module.finish())
     }
 
+    /// Returns the source map of the module assembled so far, which must end
+    /// with the code section.
+    fn source_map(&self) -> Result<SourceMap, String> {
+        let mut bodies = vec![];
+        for payload in wasmparser::Parser::new(0).parse_all(self.module.as_slice()) {
+            let payload = payload.map_err(|e| format!("Failed to parse WASM: {}", e))?;
+            if let wasmparser::Payload::CodeSectionEntry(body) = payload {
+                bodies.push(body.range().start as u32);
+            }
+        }
+        let sites = bodies
+            .into_iter()
+            .zip(&self.trap_sites)
+            .flat_map(|(start, (function, sites))| {
+                sites.iter().map(move |(offset, origin)| SourceMapSite {
+                    offset: start + offset,
+                    function: function.to_string(),
+                    file: origin.source.file.to_string(),
+                    line: origin.source.line,
+                    column: origin.source.column,
+                    span: origin.source.span,
+                    message: origin.message.map(|message| message.to_string()),
+                })
+            })
+            .collect();
+        Ok(SourceMap {
+            sites,
+            failures: self.failures.clone(),
+        })
+    }
+
+    /// Lays out the distinct string literals and string constants of `ir` in
+    /// memory, in the order they first appear.
+    fn add_strings(&mut self, ir: &IrModule) {
+        let globals = ir.globals.iter().map(|global| &global.value);
+        let literals = ir
+            .functions
+            .iter()
+            .flat_map(|func| &func.blocks)
+            .flat_map(|block| &block.instructions)
+            .filter_map(|instr| match instr {
+                IrInstr::Const { value, .. } => Some(value),
+                _ => None,
+            });
+        for value in globals.chain(literals) {
+            if let IrConst::String(text) = value
+                && !self.strings.contains_key(text)
+            {
+                let address = self.data.len() as u32;
+                self.data.extend_from_slice(text.as_bytes());
+                self.strings.insert(*text, (address, text.len() as u32));
+            }
+        }
+    }
+
+    /// Adds an immutable WASM global for each constant of `ir`, except the
+    /// strings, which are laid out with the literals.
+    fn add_globals(&mut self, ir: &IrModule) {
+        for global in &ir.globals {
+            let (val_type, init) = match &global.value {
+                IrConst::String(text) => {
+                    self.string_globals.insert(global.id, *text);
+                    continue;
+                }
+                // Nil has no value to keep
+                IrConst::Nil => continue,
+                IrConst::Bool(b) => (ValType::I32, ConstExpr::i32_const(*b as i32)),
+                IrConst::Integer(i) => (ValType::I64, ConstExpr::i64_const(*i)),
+                IrConst::Float(value) | IrConst::Quantity { value, .. } => {
+                    (ValType::F64, ConstExpr::f64_const(self.float(*value)))
+                }
+            };
+            let index = self.globals.len();
+            self.globals.global(
+                GlobalType {
+                    val_type,
+                    mutable: false,
+                    shared: false,
+                },
+                &init,
+            );
+            self.global_indices.insert(global.id, index);
+            self.global_names.append(index, &global.name);
+        }
+    }
+
+    /// Import the host function `function`, for calls to the IR function
+    /// `id` if it has one.
+    fn add_import(&mut self, id: Option<super::FunctionId>, function: &'static HostFunction) {
+        let (params, results) = function.signature();
+        self.signatures
+            .push((params.len() as u32, results.len() as u32));
+        let type_idx = self.types.len();
+        self.types.ty().function(params, results);
+        self.imports.import(
+            &function.interface.module(),
+            function.name,
+            EntityType::Function(type_idx),
+        );
+
+        let index = self.next_function_index;
+        self.next_function_index += 1;
+        self.import_count += 1;
+        self.function_names.append(index, function.name);
+        if let Some(id) = id {
+            self.function_indices.insert(id, index);
+        }
+        if *function == host::FROM_UTF8 {
+            self.from_utf8 = Some(index);
+        }
+        if function.diverges() {
+            self.diverging.insert(index);
+        }
+    }
+
+    /// Returns whether the IR function `id` is fallible.
+    fn is_fallible(&self, id: super::FunctionId) -> bool {
+        self.function_indices
+            .get(&id)
+            .is_some_and(|index| self.fallible.contains(index))
+    }
+
+    /// Returns whether the IR function `id` is an imported host function.
+    fn is_import(&self, id: super::FunctionId) -> bool {
+        self.function_indices
+            .get(&id)
+            .is_some_and(|&index| index < self.import_count)
+    }
+
     /// Add a function type signature.
     fn add_function_type(&mut self, func: &IrFunction) -> Result<(), String> {
         // Convert parameter types to WASM types
@@ -157,11 +686,17 @@ impl WasmCodegen {
             .map(|p| self.type_to_wasm(&p.ty))
             .collect::<Result<Vec<_>, _>>()?;
 
-        // Convert return type to WASM type
-        let return_type = self.type_to_wasm(&func.return_ty)?;
-        let results = vec![return_type];
+        // Convert return type to WASM types: a tuple returns each element,
+        // and a fallible function returns its status after them
+        let mut results = self.wasm_types(&func.return_ty)?;
+        if func.fallible {
+            results.push(ValType::I32);
+            self.fallible.insert(self.next_function_index);
+        }
 
         // Add to type section
+        self.signatures
+            .push((param_types.len() as u32, results.len() as u32));
         let type_idx = self.types.len();
         self.types.ty().function(param_types, results);
 
@@ -171,6 +706,8 @@ impl WasmCodegen {
         // Record the function index
         self.function_indices
             .insert(func.id, self.next_function_index);
+        self.function_names
+            .append(self.next_function_index, &func.name);
         self.next_function_index += 1;
 
         Ok(())
@@ -181,28 +718,19 @@ impl WasmCodegen {
         // Create a value location tracker for this function
         let mut tracker = ValueLocationTracker::new(&func.params);
 
-        // First pass: analyze which values need locals
-        // For now, we'll allocate a local for every SSA value (simple but correct)
-        for block in &func.blocks {
-            for instr in &block.instructions {
-                if let Some(result) = instr.result_value() {
-                    tracker.allocate_local(result);
-                }
-            }
-        }
-
-        // Determine local types for non-parameter locals
+        // Allocate locals for every SSA value (simple but correct), in the
+        // order they are defined, with their types
         let mut local_types = vec![];
-        // We need to know the type of each allocated local
-        // For now, we'll collect them from instructions
-        let mut value_types: HashMap<ValueId, &Type> = HashMap::new();
-        for param in &func.params {
-            value_types.insert(param.value_id, &param.ty);
-        }
         for block in &func.blocks {
             for instr in &block.instructions {
                 if let Some(result) = instr.result_value() {
                     let ty = match instr {
+                        // A comparison's type is that of its operands
+                        IrInstr::BinOp {
+                            op:
+                                BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge,
+                            ..
+                        } => &Type::Bool,
                         IrInstr::Const { ty, .. }
                         | IrInstr::BinOp { ty, .. }
                         | IrInstr::UnOp { ty, .. }
@@ -210,38 +738,69 @@ impl WasmCodegen {
                         | IrInstr::Record { ty, .. }
                         | IrInstr::Field { ty, .. }
                         | IrInstr::Tuple { ty, .. }
-                        | IrInstr::Phi { ty, .. } => ty,
+                        | IrInstr::Phi { ty, .. }
+                        | IrInstr::Global { ty, .. }
+                        | IrInstr::Drop { ty, .. } => ty,
+                        IrInstr::Assert { .. } => continue,
                     };
-                    value_types.insert(result, ty);
+                    if tracker.get_local(result).is_none() {
+                        tracker.allocate_local(result, ty);
+                        for wasm_ty in self.wasm_types(ty)? {
+                            local_types.push(wasm_ty);
+                        }
+                    }
                 }
             }
         }
 
-        // Build the locals list (excluding parameters)
-        // Create a reverse mapping for efficient lookup
-        let mut local_to_value: HashMap<u32, ValueId> = HashMap::new();
-        for (&value_id, &local_idx) in &tracker.value_to_local {
-            local_to_value.insert(local_idx, value_id);
+        // A fallible function returns a status after its results: zero, or
+        // else the error code of a failure, its own or a callee's
+        if func.fallible {
+            tracker.fallible = Some(self.wasm_types(&func.return_ty)?);
         }
-
-        for local_idx in func.params.len() as u32..tracker.next_local_idx {
-            let value_id = local_to_value
-                .get(&local_idx)
-                .ok_or_else(|| format!("Local {} has no corresponding ValueId", local_idx))?;
-
-            let ty = value_types
-                .get(value_id)
-                .ok_or_else(|| format!("No type found for value {}", value_id))?;
-            let wasm_ty = self.type_to_wasm(ty)?;
-            local_types.push((1, wasm_ty));
+        let calls_fallible = func
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .any(|instr| matches!(instr, IrInstr::Call { func, .. } if self.is_fallible(*func)));
+        if calls_fallible {
+            if !func.fallible {
+                return Err(format!(
+                    "Function {} calls a fallible function, so it must be fallible too",
+                    func.name
+                ));
+            }
+            tracker.status = Some(tracker.next_local_idx);
+            tracker.next_local_idx += 1;
+            local_types.push(ValType::I32);
         }
+        for block in &func.blocks {
+            if let IrTerminator::Fail { message, source } = &block.terminator {
+                self.failures.push(SourceMapFailure {
+                    function: func.name.to_string(),
+                    file: source.file.to_string(),
+                    line: source.line,
+                    column: source.column,
+                    span: source.span,
+                    message: message.to_string(),
+                });
+                tracker
+                    .failures
+                    .insert(block.id, self.failures.len() as u32);
+            }
+        }
+
+        tracker.loop_headers = loop_headers(func);
 
-        let mut function = Function::new(local_types);
+        let mut body = Body::new(func.params.len() as u32, local_types);
 
         // Generate code for all blocks with proper control flow
-        self.generate_function_body(&mut function, func, &tracker)?;
+        self.generate_function_body(&mut body, func, &tracker)?;
 
+        // Most of those locals only pass a value to the next instruction
+        let (function, sites) = body.finish(&self.signatures);
         self.code.function(&function);
+        self.trap_sites.push((func.name, sites));
         Ok(())
     }
 
@@ -251,7 +810,7 @@ impl WasmCodegen {
     /// structured WASM control flow (if/else/block/loop).
     fn generate_function_body(
         &self,
-        func: &mut Function,
+        func: &mut Body,
         ir_func: &IrFunction,
         tracker: &ValueLocationTracker,
     ) -> Result<(), String> {
@@ -284,7 +843,10 @@ impl WasmCodegen {
         else_block_id: BlockId,
         blocks: &HashMap<BlockId, &IrBlock>,
     ) -> Option<MergePhiPattern> {
-        // Get the then and else blocks
+        // Get the blocks the branches end in, after any if-then-else nested
+        // in them
+        let then_block_id = self.branch_exit(then_block_id, blocks)?;
+        let else_block_id = self.branch_exit(else_block_id, blocks)?;
         let then_block = blocks.get(&then_block_id)?;
         let else_block = blocks.get(&else_block_id)?;
 
@@ -340,41 +902,181 @@ impl WasmCodegen {
         None
     }
 
+    /// Returns the block a branch starting at `block_id` ends in.
+    ///
+    /// A branch that itself ends in an if-then-else with a phi continues in
+    /// that if-then-else's merge block, so this follows nested conditionals
+    /// such as `else if` chains.
+    fn branch_exit(
+        &self,
+        mut block_id: BlockId,
+        blocks: &HashMap<BlockId, &IrBlock>,
+    ) -> Option<BlockId> {
+        loop {
+            match &blocks.get(&block_id)?.terminator {
+                IrTerminator::Branch {
+                    then_block,
+                    else_block,
+                    ..
+                } => {
+                    block_id = self
+                        .detect_merge_phi_pattern(*then_block, *else_block, blocks)?
+                        .merge_block;
+                }
+                _ => return Some(block_id),
+            }
+        }
+    }
+
+    /// Returns true if the code starting at `block_id` always returns or
+    /// fails, jumping from block to block without branching.
+    fn leaves_function(&self, mut block_id: BlockId, blocks: &HashMap<BlockId, &IrBlock>) -> bool {
+        loop {
+            match blocks.get(&block_id).map(|block| &block.terminator) {
+                Some(IrTerminator::Return { .. } | IrTerminator::Fail { .. }) => return true,
+                Some(IrTerminator::Jump { target, .. }) => block_id = *target,
+                _ => return false,
+            }
+        }
+    }
+
     /// Generate code for a block that's part of a phi branch.
     ///
     /// This is similar to `generate_block_recursive` but ensures the block
     /// leaves the specified value on the stack instead of jumping to the merge block.
+    /// An if-then-else nested in the branch is generated as a nested `if`,
+    /// and the branch continues in its merge block.
+    #[allow(clippy::too_many_arguments)]
     fn generate_block_for_phi_branch(
         &self,
-        func: &mut Function,
-        block_id: BlockId,
+        func: &mut Body,
+        mut block_id: BlockId,
         result_value: ValueId,
+        phi_pattern: &MergePhiPattern,
         blocks: &HashMap<BlockId, &IrBlock>,
         tracker: &ValueLocationTracker,
         visited: &mut HashSet<BlockId>,
     ) -> Result<(), String> {
-        visited.insert(block_id);
+        loop {
+            visited.insert(block_id);
 
-        let block = blocks
-            .get(&block_id)
-            .ok_or_else(|| format!("Block {} not found", block_id))?;
+            let block = blocks
+                .get(&block_id)
+                .ok_or_else(|| format!("Block {} not found", block_id))?;
 
-        // Generate all instructions (excluding phi nodes)
-        for instr in &block.instructions {
-            if matches!(instr, IrInstr::Phi { .. }) {
-                // Skip phi nodes in branch blocks
-                continue;
+            // Generate all instructions (excluding phi nodes, which the
+            // nested if that ends in this block has set)
+            for instr in &block.instructions {
+                if matches!(instr, IrInstr::Phi { .. }) {
+                    continue;
+                }
+                self.generate_instruction(func, instr, tracker)?;
             }
-            self.generate_instruction(func, instr, tracker)?;
+
+            let IrTerminator::Branch {
+                cond,
+                then_block,
+                else_block,
+                ..
+            } = &block.terminator
+            else {
+                break;
+            };
+            let phi_pattern = self
+                .detect_merge_phi_pattern(*then_block, *else_block, blocks)
+                .ok_or("Branches without a result inside a branch not yet supported")?;
+            self.generate_phi_if(
+                func,
+                *cond,
+                *then_block,
+                *else_block,
+                &phi_pattern,
+                blocks,
+                tracker,
+                visited,
+            )?;
+            block_id = phi_pattern.merge_block;
         }
 
-        // Instead of generating the terminator (which would be a jump),
-        // load the result value onto the stack
-        let local_idx = tracker
-            .get_local(result_value)
-            .ok_or_else(|| format!("No local for phi branch value {}", result_value))?;
-        func.instruction(&Instruction::LocalGet(local_idx));
+        // Instead of generating the terminator (which would be a jump), set
+        // the merge block's other phis and load the result value onto the
+        // stack
+        self.copy_phis(
+            func,
+            block_id,
+            phi_pattern.merge_block,
+            Some(phi_pattern.phi_result),
+            blocks,
+            tracker,
+        )?;
+        tracker.load(func, result_value)?;
+
+        Ok(())
+    }
+
+    /// Generate an `if` with a result for a branch on `cond` whose branches
+    /// join in `phi_pattern`'s merge block, storing the result in the phi's
+    /// local.
+    #[allow(clippy::too_many_arguments)]
+    fn generate_phi_if(
+        &self,
+        func: &mut Body,
+        cond: ValueId,
+        then_block: BlockId,
+        else_block: BlockId,
+        phi_pattern: &MergePhiPattern,
+        blocks: &HashMap<BlockId, &IrBlock>,
+        tracker: &ValueLocationTracker,
+        visited: &mut HashSet<BlockId>,
+    ) -> Result<(), String> {
+        // Load condition
+        let cond_local = tracker
+            .get_local(cond)
+            .ok_or_else(|| format!("No local for branch condition {}", cond))?;
+        func.instruction(&Instruction::LocalGet(cond_local));
+
+        // Generate if with result type (the phi's type), which nil has none of
+        let block_type = match self.wasm_types(&phi_pattern.phi_type)?[..] {
+            [] => wasm_encoder::BlockType::Empty,
+            [ty] => wasm_encoder::BlockType::Result(ty),
+            _ => return Err("Branches producing tuples not yet supported in WASM".into()),
+        };
+        func.instruction(&Instruction::If(block_type));
+        tracker.enter_block();
+
+        // Generate then block (nested in control structure)
+        // It should end by loading the then_value onto the stack
+        self.generate_block_for_phi_branch(
+            func,
+            then_block,
+            phi_pattern.then_value,
+            phi_pattern,
+            blocks,
+            tracker,
+            visited,
+        )?;
+
+        // Emit else
+        func.instruction(&Instruction::Else);
+
+        // Generate else block (nested in control structure)
+        // It should end by loading the else_value onto the stack
+        self.generate_block_for_phi_branch(
+            func,
+            else_block,
+            phi_pattern.else_value,
+            phi_pattern,
+            blocks,
+            tracker,
+            visited,
+        )?;
+
+        // End if-else
+        func.instruction(&Instruction::End);
+        tracker.exit_block();
 
+        // Store the result (now on stack) to the phi result's local
+        tracker.store(func, phi_pattern.phi_result)?;
         Ok(())
     }
 
@@ -393,7 +1095,7 @@ impl WasmCodegen {
     ///   (e.g., Relooper) to map to WASM's structured control flow.
     fn generate_block_recursive(
         &self,
-        func: &mut Function,
+        func: &mut Body,
         block_id: BlockId,
         blocks: &HashMap<BlockId, &IrBlock>,
         tracker: &ValueLocationTracker,
@@ -416,8 +1118,11 @@ impl WasmCodegen {
         for (idx, instr) in block.instructions.iter().enumerate() {
             // Check for tail call optimization
             let is_last_instr = idx == block.instructions.len() - 1;
+            // A tail call returns what the callee does, which must be what
+            // this function returns: a status only if both are fallible
             let can_tail_call = is_last_instr
-                && matches!(instr, IrInstr::Call { .. })
+                && matches!(instr, IrInstr::Call { func, .. }
+                    if !self.is_import(*func) && self.is_fallible(*func) == tracker.fallible.is_some())
                 && matches!(&block.terminator, IrTerminator::Return { .. })
                 && !in_control_structure; // Don't use tail call in nested structures
 
@@ -439,6 +1144,7 @@ impl WasmCodegen {
                 };
 
                 if ret_value_matches {
+                    func.at(*instr.source());
                     self.generate_tail_call(func, *func_id, args, tracker)?;
                     return Ok(()); // Tail call ends the block
                 }
@@ -451,13 +1157,19 @@ impl WasmCodegen {
         // Generate terminator
         match &block.terminator {
             IrTerminator::Return { value, .. } => {
-                if let Some(value_id) = value {
-                    let local_idx = tracker
-                        .get_local(*value_id)
-                        .ok_or_else(|| format!("No local for return value {}", value_id))?;
-                    func.instruction(&Instruction::LocalGet(local_idx));
+                tracker.load_return(func, *value)?;
+                // Only emit End if we're not in a control structure (if-else),
+                // which has to return explicitly
+                if in_control_structure {
+                    func.instruction(&Instruction::Return);
+                } else {
+                    func.instruction(&Instruction::End);
                 }
-                // Only emit End if we're not in a control structure (if-else)
+            }
+            IrTerminator::Fail { source, .. } => {
+                func.at(*source);
+                let code = tracker.failures[&block_id];
+                tracker.return_failure(func, Instruction::I32Const(code as i32))?;
                 if !in_control_structure {
                     func.instruction(&Instruction::End);
                 }
@@ -468,103 +1180,94 @@ impl WasmCodegen {
                 else_block,
                 ..
             } => {
-                // Load condition
-                let cond_local = tracker
-                    .get_local(*cond)
-                    .ok_or_else(|| format!("No local for branch condition {}", cond))?;
-                func.instruction(&Instruction::LocalGet(cond_local));
-
                 // Check if this is an if-then-else-merge pattern with phi node
-                if let Some(phi_pattern) =
+                if let Some(mut phi_pattern) =
                     self.detect_merge_phi_pattern(*then_block, *else_block, blocks)
                 {
-                    // Generate if with result type (the phi's type)
-                    let phi_wasm_type = self.type_to_wasm(&phi_pattern.phi_type)?;
-                    func.instruction(&Instruction::If(wasm_encoder::BlockType::Result(
-                        phi_wasm_type,
-                    )));
-
-                    // Generate then block (nested in control structure)
-                    // It should end by loading the then_value onto the stack
-                    self.generate_block_for_phi_branch(
-                        func,
-                        *then_block,
-                        phi_pattern.then_value,
-                        blocks,
-                        tracker,
-                        visited,
-                    )?;
-
-                    // Emit else
-                    func.instruction(&Instruction::Else);
-
-                    // Generate else block (nested in control structure)
-                    // It should end by loading the else_value onto the stack
-                    self.generate_block_for_phi_branch(
-                        func,
-                        *else_block,
-                        phi_pattern.else_value,
-                        blocks,
-                        tracker,
-                        visited,
-                    )?;
-
-                    // End if-else
-                    func.instruction(&Instruction::End);
-
-                    // Store the result (now on stack) to the phi result's local
-                    let phi_local = tracker.get_local(phi_pattern.phi_result).ok_or_else(|| {
-                        format!("No local for phi result {}", phi_pattern.phi_result)
-                    })?;
-                    func.instruction(&Instruction::LocalSet(phi_local));
-
-                    // Mark the merge block as visited so we skip generating it later
-                    // But still generate the rest of the merge block (after the phi)
-                    visited.insert(phi_pattern.merge_block);
-
-                    // Continue with the rest of the merge block (after the phi node)
-                    let merge_block = blocks.get(&phi_pattern.merge_block).ok_or_else(|| {
-                        format!("Merge block {} not found", phi_pattern.merge_block)
-                    })?;
-
-                    // Generate instructions after the phi node
-                    for instr in merge_block.instructions.iter().skip(1) {
-                        self.generate_instruction(func, instr, tracker)?;
-                    }
+                    let (mut cond, mut then_block, mut else_block) =
+                        (*cond, *then_block, *else_block);
+                    loop {
+                        self.generate_phi_if(
+                            func,
+                            cond,
+                            then_block,
+                            else_block,
+                            &phi_pattern,
+                            blocks,
+                            tracker,
+                            visited,
+                        )?;
+
+                        // Mark the merge block as visited so we skip generating it later
+                        // But still generate the rest of the merge block (after the phi)
+                        visited.insert(phi_pattern.merge_block);
+
+                        // Continue with the rest of the merge block (after the phi node)
+                        let merge_block =
+                            blocks.get(&phi_pattern.merge_block).ok_or_else(|| {
+                                format!("Merge block {} not found", phi_pattern.merge_block)
+                            })?;
+
+                        // Generate instructions after the phi nodes
+                        for instr in &merge_block.instructions {
+                            if !matches!(instr, IrInstr::Phi { .. }) {
+                                self.generate_instruction(func, instr, tracker)?;
+                            }
+                        }
 
-                    // Generate the merge block's terminator
-                    match &merge_block.terminator {
-                        IrTerminator::Return { value, .. } => {
-                            if let Some(value_id) = value {
-                                let local_idx = tracker.get_local(*value_id).ok_or_else(|| {
-                                    format!("No local for return value {}", value_id)
-                                })?;
-                                func.instruction(&Instruction::LocalGet(local_idx));
+                        // Generate the merge block's terminator
+                        match &merge_block.terminator {
+                            IrTerminator::Return { value, .. } => {
+                                tracker.load_return(func, *value)?;
+                                if !in_control_structure {
+                                    func.instruction(&Instruction::End);
+                                }
                             }
-                            if !in_control_structure {
-                                func.instruction(&Instruction::End);
+                            IrTerminator::Fail { .. } => {
+                                let code = tracker.failures[&phi_pattern.merge_block];
+                                tracker.return_failure(func, Instruction::I32Const(code as i32))?;
+                                if !in_control_structure {
+                                    func.instruction(&Instruction::End);
+                                }
+                            }
+                            IrTerminator::Jump { target, .. } => {
+                                // Continue with the target block
+                                self.generate_jump(
+                                    func,
+                                    phi_pattern.merge_block,
+                                    *target,
+                                    blocks,
+                                    tracker,
+                                    visited,
+                                    in_control_structure,
+                                )?;
+                            }
+                            IrTerminator::Branch {
+                                cond: next_cond,
+                                then_block: next_then,
+                                else_block: next_else,
+                                ..
+                            } => {
+                                // The merge block starts another if-then-else,
+                                // as after `a && b` in `a && b || c`
+                                phi_pattern = self
+                                    .detect_merge_phi_pattern(*next_then, *next_else, blocks)
+                                    .ok_or("Nested branches in merge block not yet supported")?;
+                                (cond, then_block, else_block) =
+                                    (*next_cond, *next_then, *next_else);
+                                continue;
                             }
                         }
-                        IrTerminator::Jump { target, .. } => {
-                            // Continue with the target block
-                            self.generate_block_recursive(
-                                func,
-                                *target,
-                                blocks,
-                                tracker,
-                                visited,
-                                in_control_structure,
-                            )?;
-                        }
-                        IrTerminator::Branch { .. } => {
-                            return Err(
-                                "Nested branches in merge block not yet supported".to_string()
-                            );
-                        }
+                        break;
                     }
                 } else {
                     // No phi pattern detected, use simple if-else structure
+                    let cond_local = tracker
+                        .get_local(*cond)
+                        .ok_or_else(|| format!("No local for branch condition {}", cond))?;
+                    func.instruction(&Instruction::LocalGet(cond_local));
                     func.instruction(&Instruction::If(wasm_encoder::BlockType::Empty));
+                    tracker.enter_block();
 
                     // Generate then block (nested in control structure)
                     self.generate_block_recursive(
@@ -591,19 +1294,24 @@ impl WasmCodegen {
 
                     // End if-else
                     func.instruction(&Instruction::End);
+                    tracker.exit_block();
 
                     // If we're at function level (not nested in another control structure),
                     // and both branches returned, we still need to close the function body
                     if !in_control_structure {
+                        if self.leaves_function(*then_block, blocks)
+                            && self.leaves_function(*else_block, blocks)
+                        {
+                            func.instruction(&Instruction::Unreachable);
+                        }
                         func.instruction(&Instruction::End);
                     }
                 }
             }
             IrTerminator::Jump { target, .. } => {
-                // For now, just recurse into the target block
-                // In a more sophisticated implementation, this would use WASM's br instruction
-                self.generate_block_recursive(
+                self.generate_jump(
                     func,
+                    block_id,
                     *target,
                     blocks,
                     tracker,
@@ -616,23 +1324,177 @@ impl WasmCodegen {
         Ok(())
     }
 
+    /// Generate a jump from the block `from` to the block `target`.
+    ///
+    /// A jump to a loop header sets the header's phis, and then either starts
+    /// the loop or, from the end of its body, branches back to its start.
+    /// Any other jump continues with the target block.
+    #[allow(clippy::too_many_arguments)]
+    fn generate_jump(
+        &self,
+        func: &mut Body,
+        from: BlockId,
+        target: BlockId,
+        blocks: &HashMap<BlockId, &IrBlock>,
+        tracker: &ValueLocationTracker,
+        visited: &mut HashSet<BlockId>,
+        in_control_structure: bool,
+    ) -> Result<(), String> {
+        if !tracker.loop_headers.contains(&target) {
+            return self.generate_block_recursive(
+                func,
+                target,
+                blocks,
+                tracker,
+                visited,
+                in_control_structure,
+            );
+        }
+
+        self.copy_phis(func, from, target, None, blocks, tracker)?;
+        if visited.contains(&target) {
+            let depth = *tracker
+                .loops
+                .borrow()
+                .last()
+                .ok_or("Jump back to a loop outside of it")?;
+            func.instruction(&Instruction::Br(depth));
+            return Ok(());
+        }
+        self.generate_loop(func, target, blocks, tracker, visited, in_control_structure)
+    }
+
+    /// Generate the loop starting at the header `header_id`, whose branch
+    /// enters the body or leaves the loop for the exit block:
+    ///
+    /// ```wat
+    /// block
+    ///   loop
+    ///     ;; header
+    ///     local.get $cond
+    ///     i32.eqz
+    ///     br_if 1
+    ///     ;; body, ending with `br 0`
+    ///   end
+    /// end
+    /// ;; exit
+    /// ```
+    fn generate_loop(
+        &self,
+        func: &mut Body,
+        header_id: BlockId,
+        blocks: &HashMap<BlockId, &IrBlock>,
+        tracker: &ValueLocationTracker,
+        visited: &mut HashSet<BlockId>,
+        in_control_structure: bool,
+    ) -> Result<(), String> {
+        visited.insert(header_id);
+        let header = blocks
+            .get(&header_id)
+            .ok_or_else(|| format!("Block {} not found", header_id))?;
+        let IrTerminator::Branch {
+            cond,
+            then_block,
+            else_block,
+            ..
+        } = &header.terminator
+        else {
+            return Err(format!("Loop header {} doesn't branch", header_id));
+        };
+
+        func.instruction(&Instruction::Block(wasm_encoder::BlockType::Empty));
+        func.instruction(&Instruction::Loop(wasm_encoder::BlockType::Empty));
+        tracker.enter_block();
+        tracker.enter_block();
+        tracker.loops.borrow_mut().push(0);
+
+        // The header's phis are set by the jumps to it
+        for instr in &header.instructions {
+            if !matches!(instr, IrInstr::Phi { .. }) {
+                self.generate_instruction(func, instr, tracker)?;
+            }
+        }
+        tracker.load(func, *cond)?;
+        func.instruction(&Instruction::I32Eqz);
+        func.instruction(&Instruction::BrIf(1));
+
+        self.generate_block_recursive(func, *then_block, blocks, tracker, visited, true)?;
+
+        tracker.loops.borrow_mut().pop();
+        func.instruction(&Instruction::End);
+        func.instruction(&Instruction::End);
+        tracker.exit_block();
+        tracker.exit_block();
+
+        self.generate_block_recursive(
+            func,
+            *else_block,
+            blocks,
+            tracker,
+            visited,
+            in_control_structure,
+        )
+    }
+
+    /// Set the phis of the block `target` to their values from the block
+    /// `from`, skipping the phi `skip`.
+    ///
+    /// The phis are set all at once, as a phi's value can be another phi of
+    /// the same block: a loop variable carried over from the last iteration.
+    fn copy_phis(
+        &self,
+        func: &mut Body,
+        from: BlockId,
+        target: BlockId,
+        skip: Option<ValueId>,
+        blocks: &HashMap<BlockId, &IrBlock>,
+        tracker: &ValueLocationTracker,
+    ) -> Result<(), String> {
+        let target_block = blocks
+            .get(&target)
+            .ok_or_else(|| format!("Block {} not found", target))?;
+        let mut phis = Vec::new();
+        for instr in &target_block.instructions {
+            let IrInstr::Phi {
+                result, incoming, ..
+            } = instr
+            else {
+                continue;
+            };
+            if Some(*result) == skip {
+                continue;
+            }
+            let value = incoming
+                .iter()
+                .find(|(_, block)| *block == from)
+                .map(|(value, _)| *value)
+                .ok_or_else(|| format!("Phi {} has no value from block {}", result, from))?;
+            phis.push((*result, value));
+        }
+        for &(_, value) in &phis {
+            tracker.load(func, value)?;
+        }
+        for &(result, _) in phis.iter().rev() {
+            tracker.store(func, result)?;
+        }
+        Ok(())
+    }
+
     /// Generate code for an IR instruction.
     fn generate_instruction(
         &self,
-        func: &mut Function,
+        func: &mut Body,
         instr: &IrInstr,
         tracker: &ValueLocationTracker,
     ) -> Result<(), String> {
+        func.at(*instr.source());
         match instr {
             IrInstr::Const {
                 result, value, ty, ..
             } => {
                 // Generate the constant and store it in the result local
                 self.generate_const(func, value, ty)?;
-                let local_idx = tracker
-                    .get_local(*result)
-                    .ok_or_else(|| format!("No local for value {}", result))?;
-                func.instruction(&Instruction::LocalSet(local_idx));
+                tracker.store(func, *result)?;
             }
             IrInstr::BinOp {
                 result,
@@ -648,6 +1510,15 @@ impl WasmCodegen {
                     .get_local(*result)
                     .ok_or_else(|| format!("No local for value {}", result))?;
                 func.instruction(&Instruction::LocalSet(local_idx));
+                if self.deterministic_floats
+                    && *ty == Type::Float
+                    && matches!(
+                        op,
+                        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Min | BinOp::Max
+                    )
+                {
+                    canonicalize_nan(func, local_idx);
+                }
             }
             IrInstr::UnOp {
                 result,
@@ -662,6 +1533,12 @@ impl WasmCodegen {
                     .get_local(*result)
                     .ok_or_else(|| format!("No local for value {}", result))?;
                 func.instruction(&Instruction::LocalSet(local_idx));
+                if self.deterministic_floats
+                    && *ty == Type::Float
+                    && matches!(op, UnOp::Sqrt | UnOp::Floor | UnOp::Ceil | UnOp::Round)
+                {
+                    canonicalize_nan(func, local_idx);
+                }
             }
             IrInstr::Call {
                 result,
@@ -671,10 +1548,7 @@ impl WasmCodegen {
             } => {
                 // Load arguments onto stack in order
                 for &arg_value_id in args {
-                    let arg_local = tracker
-                        .get_local(arg_value_id)
-                        .ok_or_else(|| format!("No local for argument value {}", arg_value_id))?;
-                    func.instruction(&Instruction::LocalGet(arg_local));
+                    tracker.load(func, arg_value_id)?;
                 }
 
                 // Get the WASM function index for this IR function
@@ -687,24 +1561,57 @@ impl WasmCodegen {
                 // Emit call instruction
                 func.instruction(&Instruction::Call(func_idx));
 
+                // The host ends the program, so a call that returns traps
+                if self.diverging.contains(&func_idx) {
+                    func.instruction(&Instruction::Unreachable);
+                }
+
+                // A failure in a fallible function fails the caller too
+                if self.fallible.contains(&func_idx) {
+                    let status = tracker
+                        .status
+                        .ok_or("Call to a fallible function has no status local")?;
+                    func.instruction(&Instruction::LocalSet(status));
+                    func.instruction(&Instruction::LocalGet(status));
+                    func.instruction(&Instruction::If(BlockType::Empty));
+                    tracker.return_failure(func, Instruction::LocalGet(status))?;
+                    func.instruction(&Instruction::End);
+                }
+
                 // Store result if function returns a value
                 if let Some(result_id) = result {
-                    let local_idx = tracker
-                        .get_local(*result_id)
-                        .ok_or_else(|| format!("No local for call result {}", result_id))?;
-                    func.instruction(&Instruction::LocalSet(local_idx));
+                    tracker.store(func, *result_id)?;
                 }
             }
             IrInstr::Record { .. } => {
                 // Records would require struct types from GC proposal
                 return Err("Record types not yet implemented for WASM".to_string());
             }
-            IrInstr::Field { .. } => {
-                return Err("Field access not yet implemented for WASM".to_string());
+            IrInstr::Field {
+                result,
+                record,
+                field,
+                ..
+            } => {
+                // The elements of a tuple are its fields 0, 1, ...
+                let index = field
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|_| tracker.tuples.contains_key(record))
+                    .ok_or_else(|| "Field access not yet implemented for WASM".to_string())?;
+                for local in tracker.element_locals(*record, index)? {
+                    func.instruction(&Instruction::LocalGet(local));
+                }
+                tracker.store(func, *result)?;
             }
-            IrInstr::Tuple { .. } => {
-                // Tuples could be represented as structs or arrays
-                return Err("Tuple types not yet implemented for WASM".to_string());
+            IrInstr::Tuple {
+                result, elements, ..
+            } => {
+                // A tuple lives in the locals of its elements
+                for &element in elements {
+                    tracker.load(func, element)?;
+                }
+                tracker.store(func, *result)?;
             }
             IrInstr::Phi { .. } => {
                 // Phi nodes are handled specially in the phi-merge pattern detection.
@@ -717,21 +1624,45 @@ impl WasmCodegen {
                     "Phi node encountered outside of if-then-else-merge pattern".to_string()
                 );
             }
+            IrInstr::Global {
+                result, global, ty, ..
+            } => {
+                match self.string_globals.get(global) {
+                    Some(text) => self.generate_const(func, &IrConst::String(*text), ty)?,
+                    // Nil has no global, nor any value to load
+                    None if *ty == Type::Nil => {}
+                    None => {
+                        let global_idx = self
+                            .global_indices
+                            .get(global)
+                            .ok_or_else(|| format!("No WASM global for {}", global))?;
+                        func.instruction(&Instruction::GlobalGet(*global_idx));
+                    }
+                }
+                tracker.store(func, *result)?;
+            }
+            IrInstr::Drop { .. } => {
+                // Only scalars have a WASM representation so far, and they
+                // own no memory, so there is nothing to delete yet
+            }
+            IrInstr::Assert { cond, message, .. } => {
+                // Trap unless the condition holds; the source map keeps the
+                // message for the host to report
+                tracker.load(func, *cond)?;
+                func.instruction(&Instruction::I32Eqz);
+                func.instruction(&Instruction::If(BlockType::Empty));
+                func.trap(*message);
+                func.instruction(&Instruction::End);
+            }
         }
         Ok(())
     }
 
     /// Generate code for a constant.
-    fn generate_const(
-        &self,
-        func: &mut Function,
-        value: &IrConst,
-        _ty: &Type,
-    ) -> Result<(), String> {
+    fn generate_const(&self, func: &mut Body, value: &IrConst, _ty: &Type) -> Result<(), String> {
         match value {
             IrConst::Nil => {
-                // Nil could be represented as ref.null
-                func.instruction(&Instruction::I32Const(0));
+                // Nil lowers to no values at all
             }
             IrConst::Bool(b) => {
                 func.instruction(&Instruction::I32Const(if *b { 1 } else { 0 }));
@@ -740,41 +1671,54 @@ impl WasmCodegen {
                 func.instruction(&Instruction::I64Const(*i));
             }
             IrConst::Float(f) => {
-                func.instruction(&Instruction::F64Const((*f).into()));
+                func.instruction(&Instruction::F64Const(self.float(*f)));
             }
-            IrConst::String(_s) => {
-                // Strings would require GC arrays or imports
-                return Err("String constants not yet implemented for WASM".to_string());
+            IrConst::String(text) => {
+                // Copy the literal out of memory into a host string
+                let (address, len) = self.strings[text];
+                let from_utf8 = self
+                    .from_utf8
+                    .ok_or_else(|| "String literal without a from-utf8 import".to_string())?;
+                func.instruction(&Instruction::I32Const(address as i32));
+                func.instruction(&Instruction::I32Const(len as i32));
+                func.instruction(&Instruction::Call(from_utf8));
             }
             IrConst::Quantity { value, .. } => {
                 // Treat quantities as floats for now
-                func.instruction(&Instruction::F64Const((*value).into()));
+                func.instruction(&Instruction::F64Const(self.float(*value)));
             }
         }
         Ok(())
     }
 
+    /// Returns the encoding of the float constant `value`, with a NaN made
+    /// canonical if floats must be deterministic.
+    ///
+    /// Constants computed by the compiler carry the NaN bits of its platform.
+    fn float(&self, value: f64) -> Ieee64 {
+        if self.deterministic_floats && value.is_nan() {
+            CANONICAL_NAN.into()
+        } else {
+            value.into()
+        }
+    }
+
     /// Generate code for a binary operation.
     fn generate_binop(
         &self,
-        func: &mut Function,
+        func: &mut Body,
         op: BinOp,
         lhs: ValueId,
         rhs: ValueId,
         ty: &Type,
         tracker: &ValueLocationTracker,
     ) -> Result<(), String> {
-        // Load LHS from local
         let lhs_local = tracker
             .get_local(lhs)
             .ok_or_else(|| format!("No local for LHS value {}", lhs))?;
-        func.instruction(&Instruction::LocalGet(lhs_local));
-
-        // Load RHS from local
         let rhs_local = tracker
             .get_local(rhs)
             .ok_or_else(|| format!("No local for RHS value {}", rhs))?;
-        func.instruction(&Instruction::LocalGet(rhs_local));
 
         // For unknown types, default to integer operations
         let effective_ty = if matches!(ty, Type::Unknown) {
@@ -783,6 +1727,14 @@ impl WasmCodegen {
             ty
         };
 
+        if *effective_ty == Type::Integer {
+            Self::check_overflow(func, op, lhs_local, rhs_local);
+        }
+
+        // Load LHS and RHS from their locals
+        func.instruction(&Instruction::LocalGet(lhs_local));
+        func.instruction(&Instruction::LocalGet(rhs_local));
+
         match op {
             BinOp::Add => match effective_ty {
                 Type::Integer => {
@@ -826,6 +1778,27 @@ impl WasmCodegen {
                 }
                 _ => return Err(format!("Rem not supported for type {:?}", ty)),
             },
+            BinOp::Min | BinOp::Max => match effective_ty {
+                Type::Integer => {
+                    // select(lhs, rhs, lhs < rhs) for min, or > for max
+                    func.instruction(&Instruction::LocalGet(lhs_local));
+                    func.instruction(&Instruction::LocalGet(rhs_local));
+                    func.instruction(&if op == BinOp::Min {
+                        Instruction::I64LtS
+                    } else {
+                        Instruction::I64GtS
+                    });
+                    func.instruction(&Instruction::Select);
+                }
+                Type::Float => {
+                    func.instruction(&if op == BinOp::Min {
+                        Instruction::F64Min
+                    } else {
+                        Instruction::F64Max
+                    });
+                }
+                _ => return Err(format!("{op} not supported for type {:?}", ty)),
+            },
             BinOp::Eq => match effective_ty {
                 Type::Integer => {
                     func.instruction(&Instruction::I64Eq);
@@ -880,9 +1853,13 @@ impl WasmCodegen {
                 }
                 _ => return Err(format!("Ge not supported for type {:?}", ty)),
             },
-            BinOp::And | BinOp::Or => {
-                // Logical operations on booleans (represented as i32)
-                return Err("Logical operations not yet implemented".to_string());
+            // Booleans are i32s holding 0 or 1. Both operands are evaluated
+            // here: `&&` and `||` in source are lowered to branches instead
+            BinOp::And => {
+                func.instruction(&Instruction::I32And);
+            }
+            BinOp::Or => {
+                func.instruction(&Instruction::I32Or);
             }
             BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => {
                 return Err("Bitwise operations not yet implemented".to_string());
@@ -893,9 +1870,102 @@ impl WasmCodegen {
     }
 
     /// Generate code for a unary operation.
+    /// Traps with "integer overflow" if the integer operation `op` on the
+    /// locals `lhs` and `rhs` overflows an `i64`.
+    ///
+    /// The evaluator promotes such a result to a big integer (see
+    /// [`crate::bigint`]), which compiled code doesn't have. Division needs
+    /// no check, as `i64.div_s` traps by itself.
+    fn check_overflow(func: &mut Body, op: BinOp, lhs: u32, rhs: u32) {
+        let compare = |func: &mut Body, local, instruction| {
+            func.instruction(&Instruction::LocalGet(local));
+            func.instruction(&Instruction::I64Const(0));
+            func.instruction(&instruction);
+        };
+        // Whether `lhs` is past the bound `limit` offset by `rhs` with
+        // `offset`, the instruction that keeps the bound in range
+        let past = |func: &mut Body, limit, offset, instruction| {
+            func.instruction(&Instruction::LocalGet(lhs));
+            func.instruction(&Instruction::I64Const(limit));
+            func.instruction(&Instruction::LocalGet(rhs));
+            func.instruction(&offset);
+            func.instruction(&instruction);
+        };
+        match op {
+            // Adding a positive number overflows past i64::MAX - rhs, and a
+            // negative one past i64::MIN - rhs
+            BinOp::Add => {
+                compare(func, rhs, Instruction::I64GtS);
+                past(func, i64::MAX, Instruction::I64Sub, Instruction::I64GtS);
+                func.instruction(&Instruction::I32And);
+                compare(func, rhs, Instruction::I64LtS);
+                past(func, i64::MIN, Instruction::I64Sub, Instruction::I64LtS);
+                func.instruction(&Instruction::I32And);
+            }
+            // Subtracting is adding the negation, with the bounds mirrored
+            BinOp::Sub => {
+                compare(func, rhs, Instruction::I64LtS);
+                past(func, i64::MAX, Instruction::I64Add, Instruction::I64GtS);
+                func.instruction(&Instruction::I32And);
+                compare(func, rhs, Instruction::I64GtS);
+                past(func, i64::MIN, Instruction::I64Add, Instruction::I64LtS);
+                func.instruction(&Instruction::I32And);
+            }
+            // A product overflowed if dividing it by `lhs` doesn't give back
+            // `rhs`, except that -1 * i64::MIN must be caught first, as the
+            // division would trap on it
+            BinOp::Mul => {
+                let message = InternedString::new("integer overflow");
+                func.instruction(&Instruction::LocalGet(lhs));
+                func.instruction(&Instruction::I64Const(0));
+                func.instruction(&Instruction::I64Ne);
+                func.instruction(&Instruction::If(BlockType::Empty));
+                func.instruction(&Instruction::LocalGet(lhs));
+                func.instruction(&Instruction::I64Const(-1));
+                func.instruction(&Instruction::I64Eq);
+                func.instruction(&Instruction::LocalGet(rhs));
+                func.instruction(&Instruction::I64Const(i64::MIN));
+                func.instruction(&Instruction::I64Eq);
+                func.instruction(&Instruction::I32And);
+                func.instruction(&Instruction::If(BlockType::Empty));
+                func.trap(message);
+                func.instruction(&Instruction::End);
+                func.instruction(&Instruction::LocalGet(lhs));
+                func.instruction(&Instruction::LocalGet(rhs));
+                func.instruction(&Instruction::I64Mul);
+                func.instruction(&Instruction::LocalGet(lhs));
+                func.instruction(&Instruction::I64DivS);
+                func.instruction(&Instruction::LocalGet(rhs));
+                func.instruction(&Instruction::I64Ne);
+                func.instruction(&Instruction::If(BlockType::Empty));
+                func.trap(message);
+                func.instruction(&Instruction::End);
+                func.instruction(&Instruction::End);
+                return;
+            }
+            _ => return,
+        }
+        func.instruction(&Instruction::I32Or);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.trap(InternedString::new("integer overflow"));
+        func.instruction(&Instruction::End);
+    }
+
+    /// Traps with "integer overflow" if the integer in `local` is
+    /// `i64::MIN`, which has no negation in range (see
+    /// [`Self::check_overflow`]).
+    fn check_negation(func: &mut Body, local: u32) {
+        func.instruction(&Instruction::LocalGet(local));
+        func.instruction(&Instruction::I64Const(i64::MIN));
+        func.instruction(&Instruction::I64Eq);
+        func.instruction(&Instruction::If(BlockType::Empty));
+        func.trap(InternedString::new("integer overflow"));
+        func.instruction(&Instruction::End);
+    }
+
     fn generate_unop(
         &self,
-        func: &mut Function,
+        func: &mut Body,
         op: UnOp,
         operand: ValueId,
         ty: &Type,
@@ -904,12 +1974,12 @@ impl WasmCodegen {
         match op {
             UnOp::Neg => match ty {
                 Type::Integer => {
-                    // Integer negation: compute 0 - operand
-                    func.instruction(&Instruction::I64Const(0));
-                    // Load operand from local
                     let operand_local = tracker
                         .get_local(operand)
                         .ok_or_else(|| format!("No local for operand value {}", operand))?;
+                    Self::check_negation(func, operand_local);
+                    // Integer negation: compute 0 - operand
+                    func.instruction(&Instruction::I64Const(0));
                     func.instruction(&Instruction::LocalGet(operand_local));
                     // Subtract: 0 - operand
                     func.instruction(&Instruction::I64Sub);
@@ -925,6 +1995,46 @@ impl WasmCodegen {
                 }
                 _ => return Err(format!("Neg not supported for type {:?}", ty)),
             },
+            UnOp::Abs => {
+                let operand_local = tracker
+                    .get_local(operand)
+                    .ok_or_else(|| format!("No local for operand value {}", operand))?;
+                // For unknown types, default to integer operations
+                match ty {
+                    Type::Integer | Type::Unknown => {
+                        Self::check_negation(func, operand_local);
+                        // select(operand, 0 - operand, operand >= 0)
+                        func.instruction(&Instruction::LocalGet(operand_local));
+                        func.instruction(&Instruction::I64Const(0));
+                        func.instruction(&Instruction::LocalGet(operand_local));
+                        func.instruction(&Instruction::I64Sub);
+                        func.instruction(&Instruction::LocalGet(operand_local));
+                        func.instruction(&Instruction::I64Const(0));
+                        func.instruction(&Instruction::I64GeS);
+                        func.instruction(&Instruction::Select);
+                    }
+                    Type::Float => {
+                        func.instruction(&Instruction::LocalGet(operand_local));
+                        func.instruction(&Instruction::F64Abs);
+                    }
+                    _ => return Err(format!("Abs not supported for type {:?}", ty)),
+                }
+            }
+            UnOp::Sqrt | UnOp::Floor | UnOp::Ceil | UnOp::Round => {
+                if *ty != Type::Float {
+                    return Err(format!("{op} not supported for type {:?}", ty));
+                }
+                let operand_local = tracker
+                    .get_local(operand)
+                    .ok_or_else(|| format!("No local for operand value {}", operand))?;
+                func.instruction(&Instruction::LocalGet(operand_local));
+                func.instruction(&match op {
+                    UnOp::Sqrt => Instruction::F64Sqrt,
+                    UnOp::Floor => Instruction::F64Floor,
+                    UnOp::Ceil => Instruction::F64Ceil,
+                    _ => Instruction::F64Nearest,
+                });
+            }
             UnOp::Not => {
                 // Logical not: operand == 0
                 // Load operand from local
@@ -947,17 +2057,14 @@ impl WasmCodegen {
     /// This optimizes the pattern of call + return into a single return_call.
     fn generate_tail_call(
         &self,
-        func: &mut Function,
+        func: &mut Body,
         func_id: super::FunctionId,
         args: &[ValueId],
         tracker: &ValueLocationTracker,
     ) -> Result<(), String> {
         // Load arguments onto stack in order (same as regular call)
         for &arg_value_id in args {
-            let arg_local = tracker
-                .get_local(arg_value_id)
-                .ok_or_else(|| format!("No local for argument value {}", arg_value_id))?;
-            func.instruction(&Instruction::LocalGet(arg_local));
+            tracker.load(func, arg_value_id)?;
         }
 
         // Get the WASM function index for this IR function
@@ -978,16 +2085,38 @@ impl WasmCodegen {
         Ok(())
     }
 
+    /// Convert an IR type to the WASM value types it lowers to: one for a
+    /// scalar, each scalar element of a tuple, or none for nil.
+    fn wasm_types(&self, ty: &Type) -> Result<Vec<ValType>, String> {
+        match ty {
+            Type::Tuple(elements) => {
+                let mut types = Vec::new();
+                for element in elements {
+                    types.extend(self.wasm_types(element)?);
+                }
+                Ok(types)
+            }
+            Type::Nil => Ok(vec![]),
+            ty => Ok(vec![self.type_to_wasm(ty)?]),
+        }
+    }
+
     /// Convert an IR type to a WASM value type.
     fn type_to_wasm(&self, ty: &Type) -> Result<ValType, String> {
         match ty {
-            Type::Nil => Ok(ValType::I32),  // Represent nil as i32 0
+            Type::Nil => {
+                // Nil lowers to no values where it's supported (see `wasm_types`)
+                Err("Nil parameters not yet supported in WASM".to_string())
+            }
             Type::Bool => Ok(ValType::I32), // Represent bool as i32
             Type::Integer => Ok(ValType::I64),
             Type::Float => Ok(ValType::F64),
-            Type::Symbol | Type::String => {
-                // Strings would use externref or GC array types
-                Err("String type not yet supported in WASM".to_string())
+            Type::Decimal => Err("Decimal type not supported in WASM".to_string()),
+            // Strings are handles to host strings (see `host`)
+            Type::String => Ok(ValType::I32),
+            Type::Symbol => {
+                // Symbols would use externref or GC array types
+                Err("Symbol type not yet supported in WASM".to_string())
             }
             Type::Fn(_) => {
                 // Function types would use funcref
@@ -1002,17 +2131,25 @@ impl WasmCodegen {
                 Err("Struct types not yet supported in WASM".to_string())
             }
             Type::Tuple(_) => {
-                // Tuples could use struct types
-                Err("Tuple types not yet supported in WASM".to_string())
+                // Tuples are flattened where they're supported (see `wasm_types`)
+                Err("Tuple parameters not yet supported in WASM".to_string())
             }
             Type::List(_) => {
                 // Lists would use GC arrays
                 Err("List types not yet supported in WASM".to_string())
             }
-            Type::Enum(_) | Type::Union(_) => {
+            Type::Enum { .. } | Type::Union(_) => {
                 // Union types would need runtime type tags
                 Err("Union/Enum types not yet supported in WASM".to_string())
             }
+            Type::Vector | Type::Matrix => {
+                // Compiled code holds vectors and matrices as tuples of floats
+                Err("Vector and matrix types are lowered to tuples".to_string())
+            }
+            Type::Task(_) | Type::Channel(_) => {
+                // Tasks and channels only exist in the evaluator's executor
+                Err("Task and channel types not yet supported in WASM".to_string())
+            }
             Type::Trait { .. } | Type::Constrained { .. } => {
                 // Trait and constrained types are compile-time only (resolved by monomorphization)
                 Err("Trait and constrained types are compile-time only".to_string())
@@ -1050,6 +2187,470 @@ pub fn validate_wasm(binary: &[u8]) -> Result<(), String> {
         .map_err(|e| format!("WASM validation failed: {}", e))
 }
 
+/// The canonical NaN: positive, with only the most significant bit of the
+/// payload set.
+const CANONICAL_NAN: f64 = f64::from_bits(0x7ff8_0000_0000_0000);
+
+/// Replaces the float in `local` with the canonical NaN if it's a NaN.
+fn canonicalize_nan(func: &mut Body, local: u32) {
+    // select(value, NaN, value == value): only a NaN isn't equal to itself
+    func.instruction(&Instruction::LocalGet(local));
+    func.instruction(&Instruction::F64Const(CANONICAL_NAN.into()));
+    func.instruction(&Instruction::LocalGet(local));
+    func.instruction(&Instruction::LocalGet(local));
+    func.instruction(&Instruction::F64Eq);
+    func.instruction(&Instruction::Select);
+    func.instruction(&Instruction::LocalSet(local));
+}
+
+/// Generate a validated WASM binary from an IR module for `options.target`.
+///
+/// The options and build metadata are embedded in the binary so the build can
+/// be reproduced and verified; the same IR and options always produce the same
+/// bytes.
+pub fn generate_wasm(ir: &IrModule, options: &CompileOptions) -> Result<Vec<u8>, String> {
+    let _span = tracing::debug_span!("codegen").entered();
+    let binary = match options.target {
+        Target::Wasm => WasmCodegen::with_options(options).generate(ir)?,
+    };
+    validate_wasm(&binary)?;
+    Ok(binary)
+}
+
+/// Read the compile options embedded by [`generate_wasm`], if present.
+pub fn embedded_options(binary: &[u8]) -> Result<Option<CompileOptions>, String> {
+    for payload in wasmparser::Parser::new(0).parse_all(binary) {
+        let payload = payload.map_err(|e| format!("Failed to parse WASM: {}", e))?;
+        if let wasmparser::Payload::CustomSection(section) = payload
+            && section.name() == CompileOptions::SECTION_NAME
+        {
+            let text = std::str::from_utf8(section.data())
+                .map_err(|e| format!("Invalid options section: {}", e))?;
+            return CompileOptions::from_metadata(text).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// The compiler version recorded in build metadata.
+pub const COMPILER_VERSION: &str = concat!("cadenza ", env!("CARGO_PKG_VERSION"));
+
+/// Provenance embedded in a WASM binary by [`generate_wasm`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildMetadata {
+    /// The compiler that produced the binary.
+    pub compiler: String,
+    /// Hex BLAKE3 hash of the binary up to the `cadenza.build` section.
+    pub hash: String,
+    /// The options the binary was compiled with.
+    pub options: CompileOptions,
+}
+
+impl BuildMetadata {
+    /// The name of the WASM custom section holding the compiler version and hash.
+    pub const SECTION_NAME: &'static str = "cadenza.build";
+
+    /// Reads and verifies the build metadata of `binary`.
+    ///
+    /// Returns `None` if the binary has no build metadata, and an error if the
+    /// recorded hash doesn't match the content.
+    pub fn read(binary: &[u8]) -> Result<Option<Self>, String> {
+        let Some(options) = embedded_options(binary)? else {
+            return Ok(None);
+        };
+        for payload in wasmparser::Parser::new(0).parse_all(binary) {
+            let payload = payload.map_err(|e| format!("Failed to parse WASM: {}", e))?;
+            let wasmparser::Payload::CustomSection(section) = payload else {
+                continue;
+            };
+            if section.name() != Self::SECTION_NAME {
+                continue;
+            }
+
+            let json: serde_json::Value = serde_json::from_slice(section.data())
+                .map_err(|e| format!("Invalid build section: {}", e))?;
+            let field = |name: &str| {
+                json.get(name)
+                    .and_then(|value| value.as_str())
+                    .map(String::from)
+                    .ok_or_else(|| format!("Build section is missing '{}'", name))
+            };
+            let metadata = Self {
+                compiler: field("compiler")?,
+                hash: field("hash")?,
+                options,
+            };
+
+            // The section id byte and size precede the section contents
+            let range = section.range();
+            let size_len = leb128_len(range.len() as u64);
+            let content = &binary[..range.start - size_len - 1];
+            if content_hash(content) != metadata.hash {
+                return Err("WASM content does not match its build hash".to_string());
+            }
+            return Ok(Some(metadata));
+        }
+        Ok(None)
+    }
+
+    /// Serializes the metadata to canonical JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "compiler": self.compiler,
+            "hash": self.hash,
+            "options": self.options.to_json(),
+        })
+        .to_string()
+    }
+
+    /// Returns the `cadenza.build` section contents for a binary prefix.
+    fn build_section(content: &[u8]) -> String {
+        serde_json::json!({
+            "compiler": COMPILER_VERSION,
+            "hash": content_hash(content),
+        })
+        .to_string()
+    }
+}
+
+/// The dimensions of the quantities a module's functions take and return,
+/// embedded by the code generator in the `cadenza.dimensions` custom section.
+///
+/// Functions take and return quantities as `f64`s in the base units of their
+/// dimension. A dimension is written as in diagnostics, with its base units:
+/// `millimeter`, `meter/second`, `meter·kilogram/second^2`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DimensionMetadata {
+    /// The functions that take or return a quantity, by name.
+    pub functions: BTreeMap<String, FunctionDimensions>,
+}
+
+/// The dimensions of a function's parameters and result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionDimensions {
+    /// The dimension of each parameter, or `None` if it isn't a quantity.
+    pub params: Vec<Option<String>>,
+    /// The dimension of the result, or `None` if it isn't a quantity.
+    pub result: Option<String>,
+}
+
+impl DimensionMetadata {
+    /// The name of the WASM custom section holding the dimensions.
+    pub const SECTION_NAME: &'static str = "cadenza.dimensions";
+
+    /// Collects the dimensions of the functions of `ir`.
+    pub fn from_module(ir: &IrModule) -> Self {
+        let functions = ir
+            .functions
+            .iter()
+            .filter(|func| {
+                func.return_dimension.is_some()
+                    || func.params.iter().any(|param| param.dimension.is_some())
+            })
+            .map(|func| {
+                let dimensions = FunctionDimensions {
+                    params: func
+                        .params
+                        .iter()
+                        .map(|param| param.dimension.as_ref().map(ToString::to_string))
+                        .collect(),
+                    result: func.return_dimension.as_ref().map(ToString::to_string),
+                };
+                (func.name.to_string(), dimensions)
+            })
+            .collect();
+        Self { functions }
+    }
+
+    /// Reads the dimensions embedded in `binary`, if it has any.
+    pub fn read(binary: &[u8]) -> Result<Option<Self>, String> {
+        for payload in wasmparser::Parser::new(0).parse_all(binary) {
+            let payload = payload.map_err(|e| format!("Failed to parse WASM: {}", e))?;
+            if let wasmparser::Payload::CustomSection(section) = payload
+                && section.name() == Self::SECTION_NAME
+            {
+                return Self::from_json(section.data()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Serializes the dimensions to canonical JSON.
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+
+    /// Returns the dimensions as a JSON value.
+    pub fn to_value(&self) -> serde_json::Value {
+        let functions: serde_json::Map<String, serde_json::Value> = self
+            .functions
+            .iter()
+            .map(|(name, func)| {
+                let value = serde_json::json!({
+                    "params": func.params,
+                    "result": func.result,
+                });
+                (name.clone(), value)
+            })
+            .collect();
+        serde_json::json!({ "functions": functions })
+    }
+
+    fn from_json(data: &[u8]) -> Result<Self, String> {
+        let invalid = || "Invalid dimensions section".to_string();
+        let json: serde_json::Value = serde_json::from_slice(data)
+            .map_err(|e| format!("Invalid dimensions section: {}", e))?;
+        let dimension = |value: &serde_json::Value| match value {
+            serde_json::Value::Null => Ok(None),
+            serde_json::Value::String(dimension) => Ok(Some(dimension.clone())),
+            _ => Err(invalid()),
+        };
+        let mut functions = BTreeMap::new();
+        for (name, func) in json["functions"].as_object().ok_or_else(invalid)? {
+            let params = func["params"]
+                .as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(dimension)
+                .collect::<Result<_, _>>()?;
+            let result = dimension(&func["result"])?;
+            functions.insert(name.clone(), FunctionDimensions { params, result });
+        }
+        Ok(Self { functions })
+    }
+}
+
+/// Where a module's calls and the instructions that can trap came from,
+/// embedded by the code generator in the `cadenza.sourcemap` custom section.
+///
+/// Runtimes report a trap with the byte offset in the module of each frame's
+/// instruction: the one that trapped, then the calls that led to it. The
+/// source map translates those offsets back to Cadenza source, so a host can
+/// report the trap like any other diagnostic (see
+/// [`SourceMap::trap_diagnostic`]). The host shims in the `host` directory
+/// show how to get the offsets from wasmtime and from JavaScript engines.
+///
+/// It also holds the failures (see [`IrTerminator::Fail`]), by error code:
+/// the status a fallible function returns is zero, or else the index of its
+/// failure in [`SourceMap::failures`] plus one (see
+/// [`SourceMap::failure_diagnostic`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The mapped instructions, in order.
+    pub sites: Vec<SourceMapSite>,
+    /// The failures, in order of error code.
+    pub failures: Vec<SourceMapFailure>,
+}
+
+/// The source of a call or of an instruction that can trap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapSite {
+    /// The byte offset of the instruction in the module.
+    pub offset: u32,
+    /// The function the instruction is in.
+    pub function: String,
+    pub file: String,
+    /// The line of the expression the instruction came from, from 1.
+    pub line: u32,
+    /// The column of the expression, in characters from 1.
+    pub column: u32,
+    /// The byte range of the expression in the file.
+    pub span: Span,
+    /// The message of the failed check that traps here: an assertion or a
+    /// zero divisor.
+    pub message: Option<String>,
+}
+
+/// The source of a failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapFailure {
+    /// The function that fails.
+    pub function: String,
+    pub file: String,
+    /// The line of the expression that fails, from 1.
+    pub line: u32,
+    /// The column of the expression, in characters from 1.
+    pub column: u32,
+    /// The byte range of the expression in the file.
+    pub span: Span,
+    pub message: String,
+}
+
+impl SourceMap {
+    /// The name of the WASM custom section holding the source map.
+    pub const SECTION_NAME: &'static str = "cadenza.sourcemap";
+
+    /// Reads the source map embedded in `binary`, if it has one.
+    pub fn read(binary: &[u8]) -> Result<Option<Self>, String> {
+        for payload in wasmparser::Parser::new(0).parse_all(binary) {
+            let payload = payload.map_err(|e| format!("Failed to parse WASM: {}", e))?;
+            if let wasmparser::Payload::CustomSection(section) = payload
+                && section.name() == Self::SECTION_NAME
+            {
+                return Self::from_json(section.data()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Serializes the source map to canonical JSON.
+    pub fn to_json(&self) -> String {
+        let sites: Vec<_> = self
+            .sites
+            .iter()
+            .map(|site| {
+                let mut json = serde_json::json!({
+                    "offset": site.offset,
+                    "function": site.function,
+                    "file": site.file,
+                    "line": site.line,
+                    "column": site.column,
+                    "span": [site.span.start, site.span.end],
+                });
+                if let Some(message) = &site.message {
+                    json["message"] = message.clone().into();
+                }
+                json
+            })
+            .collect();
+        let mut json = serde_json::json!({ "sites": sites });
+        if !self.failures.is_empty() {
+            let failures: Vec<_> = self
+                .failures
+                .iter()
+                .map(|failure| {
+                    serde_json::json!({
+                        "function": failure.function,
+                        "file": failure.file,
+                        "line": failure.line,
+                        "column": failure.column,
+                        "span": [failure.span.start, failure.span.end],
+                        "message": failure.message,
+                    })
+                })
+                .collect();
+            json["failures"] = failures.into();
+        }
+        json.to_string()
+    }
+
+    /// Returns a diagnostic for the failure a fallible function returned
+    /// `status` for, or `None` if the status is success or unknown.
+    pub fn failure_diagnostic(&self, status: u32) -> Option<Diagnostic> {
+        let failure = self.failures.get(status.checked_sub(1)? as usize)?;
+        let file = InternedString::new(&failure.file);
+        let mut diagnostic =
+            Diagnostic::new(DiagnosticKind::Failure(failure.message.clone()), None);
+        diagnostic.file = Some(file);
+        diagnostic.span = Some(failure.span);
+        diagnostic.push_frame(StackFrame::new(
+            Some(InternedString::new(&failure.function)),
+            Some(file),
+            Some(failure.span),
+        ));
+        Some(diagnostic)
+    }
+
+    /// Returns the site of the instruction at `offset`, or else of the
+    /// closest mapped instruction before it.
+    pub fn lookup(&self, offset: u32) -> Option<&SourceMapSite> {
+        let after = self.sites.partition_point(|site| site.offset <= offset);
+        after.checked_sub(1).map(|index| &self.sites[index])
+    }
+
+    /// Returns a diagnostic for a trap with `message`, where `offsets` are
+    /// the module offsets of the frames' instructions, innermost first.
+    ///
+    /// The diagnostic points at the innermost frame the source map knows,
+    /// and its stack trace lists every frame it knows. When the trap is a
+    /// failed check's, the diagnostic has the check's message instead of the
+    /// engine's, the same as the evaluator reports.
+    pub fn trap_diagnostic(&self, message: &str, offsets: &[u32]) -> Diagnostic {
+        let message = offsets
+            .first()
+            .and_then(|&offset| self.lookup(offset).filter(|site| site.offset == offset))
+            .and_then(|site| site.message.as_deref())
+            .unwrap_or(message);
+        let mut diagnostic = Diagnostic::new(DiagnosticKind::Trap(message.to_string()), None);
+        for site in offsets.iter().filter_map(|&offset| self.lookup(offset)) {
+            let file = InternedString::new(&site.file);
+            if diagnostic.span.is_none() {
+                diagnostic.file = Some(file);
+                diagnostic.span = Some(site.span);
+            }
+            diagnostic.push_frame(StackFrame::new(
+                Some(InternedString::new(&site.function)),
+                Some(file),
+                Some(site.span),
+            ));
+        }
+        diagnostic
+    }
+
+    fn from_json(data: &[u8]) -> Result<Self, String> {
+        let invalid = || "Invalid source map section".to_string();
+        let json: serde_json::Value = serde_json::from_slice(data)
+            .map_err(|e| format!("Invalid source map section: {}", e))?;
+        let number = |site: &serde_json::Value, name: &str| site[name].as_u64().ok_or_else(invalid);
+        let text = |site: &serde_json::Value, name: &str| {
+            site[name].as_str().map(str::to_string).ok_or_else(invalid)
+        };
+        let span = |site: &serde_json::Value| {
+            let bound = |index: usize| {
+                site["span"]
+                    .get(index)
+                    .and_then(serde_json::Value::as_u64)
+                    .ok_or_else(invalid)
+            };
+            Ok::<_, String>(Span::new(bound(0)? as usize, bound(1)? as usize))
+        };
+        let mut sites = vec![];
+        for site in json["sites"].as_array().ok_or_else(invalid)? {
+            sites.push(SourceMapSite {
+                offset: number(site, "offset")? as u32,
+                function: text(site, "function")?,
+                file: text(site, "file")?,
+                line: number(site, "line")? as u32,
+                column: number(site, "column")? as u32,
+                span: span(site)?,
+                message: site["message"].as_str().map(str::to_string),
+            });
+        }
+        let mut failures = vec![];
+        if let Some(json) = json.get("failures") {
+            for failure in json.as_array().ok_or_else(invalid)? {
+                failures.push(SourceMapFailure {
+                    function: text(failure, "function")?,
+                    file: text(failure, "file")?,
+                    line: number(failure, "line")? as u32,
+                    column: number(failure, "column")? as u32,
+                    span: span(failure)?,
+                    message: text(failure, "message")?,
+                });
+            }
+        }
+        Ok(Self { sites, failures })
+    }
+}
+
+/// Returns the hex BLAKE3 hash of `bytes`.
+fn content_hash(bytes: &[u8]) -> String {
+    cadenza_syntax::hash::Hasher::hash(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Returns the number of bytes in the unsigned LEB128 encoding of `n`.
+fn leb128_len(mut n: u64) -> usize {
+    let mut len = 1;
+    while n >= 0x80 {
+        n >>= 7;
+        len += 1;
+    }
+    len
+}
+
 /// Generate WAT from IR module.
 pub fn generate_wat(ir: &IrModule) -> Result<String, String> {
     let mut codegen = WasmCodegen::new();
@@ -1071,6 +2672,7 @@ mod tests {
             file: InternedString::new("test.cdz"),
             line: 1,
             column: 0,
+            span: 0.into(),
         }
     }
 
@@ -1082,6 +2684,8 @@ mod tests {
             name: InternedString::new("get_answer"),
             params: vec![],
             return_ty: Type::Integer,
+            return_dimension: None,
+            fallible: false,
             blocks: vec![IrBlock {
                 id: super::super::BlockId(0),
                 instructions: vec![IrInstr::Const {
@@ -1099,6 +2703,9 @@ mod tests {
         };
 
         let module = IrModule {
+            imports: vec![],
+            externs: vec![],
+            globals: vec![],
             functions: vec![func],
             exports: vec![],
         };
@@ -1119,7 +2726,8 @@ mod tests {
         assert_eq!(codegen.type_to_wasm(&Type::Integer).unwrap(), ValType::I64);
         assert_eq!(codegen.type_to_wasm(&Type::Float).unwrap(), ValType::F64);
         assert_eq!(codegen.type_to_wasm(&Type::Bool).unwrap(), ValType::I32);
-        assert!(codegen.type_to_wasm(&Type::String).is_err());
+        assert_eq!(codegen.type_to_wasm(&Type::String).unwrap(), ValType::I32);
+        assert!(codegen.type_to_wasm(&Type::Symbol).is_err());
     }
 
     #[test]
@@ -1133,14 +2741,18 @@ mod tests {
                     name: InternedString::new("a"),
                     value_id: ValueId(0),
                     ty: Type::Integer,
+                    dimension: None,
                 },
                 IrParam {
                     name: InternedString::new("b"),
                     value_id: ValueId(1),
                     ty: Type::Integer,
+                    dimension: None,
                 },
             ],
             return_ty: Type::Integer,
+            return_dimension: None,
+            fallible: false,
             blocks: vec![IrBlock {
                 id: BlockId(0),
                 instructions: vec![IrInstr::BinOp {
@@ -1167,8 +2779,11 @@ mod tests {
                 name: InternedString::new("x"),
                 value_id: ValueId(0),
                 ty: Type::Integer,
+                dimension: None,
             }],
             return_ty: Type::Integer,
+            return_dimension: None,
+            fallible: false,
             blocks: vec![IrBlock {
                 id: BlockId(0),
                 instructions: vec![
@@ -1195,6 +2810,9 @@ mod tests {
         };
 
         let module = IrModule {
+            imports: vec![],
+            externs: vec![],
+            globals: vec![],
             functions: vec![add_func, compute_func],
             exports: vec![],
         };
@@ -1216,7 +2834,7 @@ mod tests {
         println!("Generated WAT:\n{}", wat_text);
 
         // Verify the WAT contains the expected elements
-        assert!(wat_text.contains("call 0")); // Call to function 0 (add)
+        assert!(wat_text.contains("call $add")); // Call to function 0 (add)
     }
 
     #[test]
@@ -1232,8 +2850,11 @@ mod tests {
                 name: InternedString::new("n"),
                 value_id: ValueId(0),
                 ty: Type::Integer,
+                dimension: None,
             }],
             return_ty: Type::Integer,
+            return_dimension: None,
+            fallible: false,
             blocks: vec![IrBlock {
                 id: BlockId(0),
                 instructions: vec![
@@ -1268,6 +2889,9 @@ mod tests {
         };
 
         let module = IrModule {
+            imports: vec![],
+            externs: vec![],
+            globals: vec![],
             functions: vec![countdown_func],
             exports: vec![],
         };
@@ -1289,7 +2913,7 @@ mod tests {
         println!("Generated WAT for recursive function:\n{}", wat_text);
 
         // Verify the WAT contains the recursive call
-        assert!(wat_text.contains("call 0")); // Recursive call to function 0
+        assert!(wat_text.contains("call $countdown")); // Recursive call to function 0
     }
 
     #[test]
@@ -1305,8 +2929,11 @@ mod tests {
                 name: InternedString::new("x"),
                 value_id: ValueId(0),
                 ty: Type::Integer,
+                dimension: None,
             }],
             return_ty: Type::Integer,
+            return_dimension: None,
+            fallible: false,
             blocks: vec![
                 // Entry block: compare x < 0
                 IrBlock {
@@ -1367,6 +2994,9 @@ mod tests {
         };
 
         let module = IrModule {
+            imports: vec![],
+            externs: vec![],
+            globals: vec![],
             functions: vec![sign_func],
             exports: vec![],
         };
@@ -1434,6 +3064,8 @@ mod tests {
             name: InternedString::new("test_func"),
             params: vec![],
             return_ty: Type::Integer,
+            return_dimension: None,
+            fallible: false,
             blocks: vec![IrBlock {
                 id: BlockId(0),
                 instructions: vec![IrInstr::Const {
@@ -1451,6 +3083,9 @@ mod tests {
         };
 
         let module = IrModule {
+            imports: vec![],
+            externs: vec![],
+            globals: vec![],
             functions: vec![func],
             exports: vec![],
         };
@@ -1463,4 +3098,52 @@ mod tests {
             result.err()
         );
     }
+
+    #[test]
+    fn test_generate_wasm_embeds_options() {
+        let module = IrModule {
+            imports: vec![],
+            externs: vec![],
+            globals: vec![],
+            functions: vec![],
+            exports: vec![],
+        };
+        let options = CompileOptions {
+            fuel: Some(100),
+            ..CompileOptions::default()
+        };
+
+        let binary = generate_wasm(&module, &options).unwrap();
+        assert_eq!(embedded_options(&binary), Ok(Some(options.clone())));
+        assert_eq!(generate_wasm(&module, &options).unwrap(), binary);
+
+        let metadata = BuildMetadata::read(&binary).unwrap().unwrap();
+        assert_eq!(metadata.compiler, COMPILER_VERSION);
+        assert_eq!(metadata.hash.len(), 64);
+        assert_eq!(metadata.options, options);
+
+        // Plain codegen embeds nothing
+        let binary = WasmCodegen::new().generate(&module).unwrap();
+        assert_eq!(embedded_options(&binary), Ok(None));
+    }
+
+    #[test]
+    fn test_build_hash_detects_tampering() {
+        let module = IrModule {
+            imports: vec![],
+            externs: vec![],
+            globals: vec![],
+            functions: vec![],
+            exports: vec![],
+        };
+        let mut binary = generate_wasm(&module, &CompileOptions::default()).unwrap();
+
+        // Flip a byte of the options section, which the hash covers
+        let pos = binary
+            .windows(4)
+            .position(|window| window == b"warn")
+            .unwrap();
+        binary[pos] = b'W';
+        assert!(BuildMetadata::read(&binary).is_err());
+    }
 }
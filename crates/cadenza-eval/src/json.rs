@@ -0,0 +1,211 @@
+//! JSON decoding.
+//!
+//! `json_decode text` parses JSON into values: objects become records, arrays
+//! become lists, and numbers become integers when they fit (floats otherwise).
+//!
+//! An optional second argument decodes against a type, checking the shape and
+//! building nominal structs:
+//!
+//! ```cadenza
+//! struct Part {
+//!   name = String,
+//!   layers = Integer,
+//! }
+//!
+//! let part = json_decode response.body Part
+//! # Ok (Part {name = bracket, layers = 120})
+//! ```
+//!
+//! Like the other host-facing builtins, decoding failures are returned as
+//! `Err message` instead of raising a diagnostic.
+
+use crate::{
+    diagnostic::Diagnostic,
+    interner::InternedString,
+    system::{err, expect_string, ok},
+    value::{BuiltinFn, Type, Value},
+};
+use serde_json::Value as Json;
+
+/// Converts parsed JSON into a value.
+pub fn to_value(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Nil,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(s) => Value::String(s.into()),
+        Json::Array(items) => Value::List(items.into_iter().map(to_value).collect()),
+        Json::Object(fields) => Value::Record {
+            type_name: None,
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| (InternedString::from(name.as_str()), to_value(value)))
+                .collect(),
+        },
+    }
+}
+
+/// Decodes `json` as `ty`, reporting the first mismatch with its path.
+///
+/// `struct_name` names the nominal struct to build when decoding a record.
+pub fn decode_as(
+    json: Json,
+    ty: &Type,
+    struct_name: Option<InternedString>,
+) -> Result<Value, String> {
+    decode_at(json, ty, struct_name, &mut String::from("$"))
+}
+
+fn decode_at(
+    json: Json,
+    ty: &Type,
+    struct_name: Option<InternedString>,
+    path: &mut String,
+) -> Result<Value, String> {
+    let mismatch = |json: &Json, path: &str| {
+        let found = match json {
+            Json::Null => "null",
+            Json::Bool(_) => "bool",
+            Json::Number(_) => "number",
+            Json::String(_) => "string",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        };
+        format!("at {path}: expected {ty}, found {found}")
+    };
+
+    match (ty, json) {
+        (Type::Unknown, json) => Ok(to_value(json)),
+        (Type::Nil, Json::Null) => Ok(Value::Nil),
+        (Type::Bool, Json::Bool(b)) => Ok(Value::Bool(b)),
+        (Type::String, Json::String(s)) => Ok(Value::String(s.into())),
+        (Type::Integer, Json::Number(n)) if n.is_i64() => Ok(Value::Integer(n.as_i64().unwrap())),
+        (Type::Float, Json::Number(n)) => Ok(Value::Float(n.as_f64().unwrap_or(f64::NAN))),
+        (Type::List(element), Json::Array(items)) => {
+            let mut values = Vec::with_capacity(items.len());
+            for (i, item) in items.into_iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{i}]"));
+                values.push(decode_at(item, element, None, path)?);
+                path.truncate(len);
+            }
+            Ok(Value::List(values))
+        }
+        (Type::Record(field_types), Json::Object(mut object)) => {
+            let mut fields = Vec::with_capacity(field_types.len());
+            for (name, field_ty) in field_types {
+                let len = path.len();
+                path.push('.');
+                path.push_str(name);
+                let Some(field) = object.remove(&**name) else {
+                    return Err(format!("at {path}: missing field"));
+                };
+                fields.push((*name, decode_at(field, field_ty, None, path)?));
+                path.truncate(len);
+            }
+            Ok(Value::Record {
+                type_name: struct_name,
+                fields,
+            })
+        }
+        (Type::Struct { name, fields }, json @ Json::Object(_)) => {
+            decode_at(json, &Type::Record(fields.clone()), Some(*name), path)
+        }
+        (_, json) => Err(mismatch(&json, path)),
+    }
+}
+
+/// Creates the `json_decode` builtin: `json_decode text` or `json_decode text type`.
+pub fn builtin_json_decode() -> BuiltinFn {
+    BuiltinFn {
+        name: "json_decode",
+        signature: Type::function(vec![Type::String], Type::Unknown),
+        func: |args, _ctx| {
+            if args.is_empty() || args.len() > 2 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let text = expect_string(&args[0])?;
+            let (ty, struct_name) = match args.get(1) {
+                None => (Type::Unknown, None),
+                Some(Value::Type(ty)) => (ty.clone(), None),
+                Some(Value::StructConstructor { name, field_types }) => {
+                    (Type::Record(field_types.clone()), Some(*name))
+                }
+                Some(other) => return Err(Diagnostic::type_error(Type::Type, other.type_of())),
+            };
+
+            let json = match serde_json::from_str(text) {
+                Ok(json) => json,
+                Err(e) => return Ok(err(format!("invalid JSON: {e}"))),
+            };
+            Ok(match decode_as(json, &ty, struct_name) {
+                Ok(value) => ok(value),
+                Err(message) => err(message),
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Json {
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn decodes_untyped() {
+        let value = to_value(parse(r#"{"a": [1, 2.5, null], "b": true}"#));
+        assert_eq!(
+            value,
+            Value::Record {
+                type_name: None,
+                fields: vec![
+                    (
+                        "a".into(),
+                        Value::List(vec![Value::Integer(1), Value::Float(2.5), Value::Nil])
+                    ),
+                    ("b".into(), Value::Bool(true)),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_object_fields_in_document_order() {
+        let value = to_value(parse(r#"{"z": 1, "a": 2, "m": 3}"#));
+        assert_eq!(value.to_string(), "{z = 1, a = 2, m = 3}");
+    }
+
+    #[test]
+    fn decodes_into_structs() {
+        let ty = Type::Record(vec![
+            ("name".into(), Type::String),
+            ("layers".into(), Type::list(Type::Float)),
+        ]);
+        let value = decode_as(
+            parse(r#"{"layers": [1, 0.2], "name": "bracket", "extra": 1}"#),
+            &ty,
+            Some("Part".into()),
+        )
+        .unwrap();
+        assert_eq!(
+            value.to_string(),
+            "Part {name = bracket, layers = [1, 0.2]}"
+        );
+    }
+
+    #[test]
+    fn reports_mismatch_paths() {
+        let ty = Type::Record(vec![("layers".into(), Type::list(Type::Integer))]);
+        let error = decode_as(parse(r#"{"layers": [1, "two"]}"#), &ty, None).unwrap_err();
+        assert_eq!(error, "at $.layers[1]: expected integer, found string");
+
+        let error = decode_as(parse("{}"), &ty, None).unwrap_err();
+        assert_eq!(error, "at $.layers: missing field");
+    }
+}
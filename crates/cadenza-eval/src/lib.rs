@@ -16,26 +16,101 @@
 //! - [`EvalContext`]: Consolidated evaluation context for all eval arguments
 //! - [`Eval`]: Trait for evaluatable expressions
 //! - [`eval`]: The main evaluation function
+//! - [`coverage`]: Expression coverage with lcov and HTML reports
+//! - [`args`]: Command-line flag parsing for scripts
+//! - [`bigint`]: Arbitrary-precision integers for overflowing arithmetic
+//! - [`dead_code`]: Unused-function warnings from the call graph
+//! - [`decimal`]: Exact decimal numbers written with a `d` suffix
+//! - [`derive`]: Trait implementations generated by `@derive`
+//! - [`examples`]: The bundled example programs
+//! - [`expand`]: Macro expansion viewer
+//! - [`hash`]: Stable value hashing and hashable map keys
+//! - `http` / [`json`]: HTTP client (with the `http` feature) and JSON decoding builtins
+//! - [`linalg`]: Vectors and matrices with per-element units
+//! - [`messages`]: Message catalogs for translating diagnostics
+//! - [`module_graph`]: Multi-file compilation units linked into one IR module
+//! - [`num`]: The numeric tower the arithmetic operators dispatch through
+//! - [`options`]: Compile options shared by evaluation, IR, and codegen
+//! - [`prelude`]: Standard library functions written in Cadenza
+//! - [`reflect`]: Compile-time reflection for macros
+//! - [`result`]: The built-in `Option` and `Result` types
+//! - [`rich`]: The rich display protocol for laying out values
+//! - [`symbol_index`]: Incremental workspace symbol search
+//! - [`system`]: Capability-gated file, process, and environment access
+//! - [`task`]: Tasks and channels on a cooperative executor
+//! - [`totality`]: Termination checking for `@total` functions
+//! - [`trace`]: Expression-level logging of evaluation
 //! - [`typeinfer`]: Hindley-Milner type inference
 //! - [`trait_registry`]: Trait definitions and implementations
+//!
+//! # Stability
+//!
+//! Only the `stable` module, enabled by the `stable` feature, follows
+//! semantic versioning: it's the embedding API (an `Engine`, values,
+//! diagnostics, options and capabilities). Everything else is unstable and
+//! may change in any release.
+//!
+//! # Tracing
+//!
+//! Each pipeline stage runs in a [`tracing`] span named after it, so a
+//! subscriber can time or log them: `parse` and `eval` per module (inside a
+//! `module` span with a `file` field), `expand` for builtin macro
+//! applications, `lower` and `infer` for IR generation, `optimize`, and
+//! `codegen`. Spans nest, e.g. `infer` runs inside `lower`, which runs inside
+//! `eval`.
 
+pub mod args;
+pub mod bigint;
 mod compiler;
 mod context;
+pub mod coverage;
 pub mod db;
+pub mod dead_code;
+pub mod decimal;
+pub mod derive;
 mod diagnostic;
 mod env;
 mod eval;
+pub mod examples;
+pub mod expand;
 mod generated;
+pub mod hash;
+#[cfg(feature = "http")]
+pub mod http;
 pub mod interner;
 pub mod ir;
+pub mod json;
+pub mod linalg;
+pub mod list;
 mod map;
+pub mod math;
+pub mod memo;
+pub mod messages;
+pub mod module_graph;
+pub mod num;
+pub mod options;
+mod pattern;
+pub mod prelude;
+pub mod reflect;
+pub mod result;
+pub mod rich;
 pub mod special_form;
+#[cfg(feature = "stable")]
+pub mod stable;
+mod stack;
+pub mod string;
+mod suggest;
+pub mod symbol_index;
+pub mod system;
+pub mod task;
+pub mod totality;
+pub mod trace;
 pub mod trait_registry; // New module
 pub mod typeinfer;
 pub mod unit;
 mod value;
 
-pub use compiler::Compiler;
+pub use compiler::{Compiler, Export};
 pub use context::{Eval, EvalContext};
 pub use db::{CadenzaDb, CadenzaDbImpl, SourceFile};
 pub use diagnostic::{
@@ -45,17 +120,18 @@ pub use diagnostic::{
 pub use diagnostic::{Error, ErrorKind};
 pub use env::Env;
 pub use eval::{
-    builtin_add, builtin_div, builtin_eq, builtin_gt, builtin_gte, builtin_lt, builtin_lte,
-    builtin_mul, builtin_ne, builtin_sub, eval,
+    apply_value, builtin_add, builtin_div, builtin_eq, builtin_gt, builtin_gte, builtin_lt,
+    builtin_lte, builtin_mul, builtin_ne, builtin_sub, eval, extract_identifier,
 };
 pub use interner::InternedString;
 pub use map::Map;
+pub use options::CompileOptions;
 pub use special_form::BuiltinSpecialForm;
 pub use trait_registry::{TraitDef, TraitImpl, TraitRegistry}; // Export trait types
 pub use typeinfer::{Constraint, InferType, Substitution, TypeEnv, TypeInferencer, TypeVar};
 pub use unit::{DerivedDimension, Dimension, Unit, UnitRegistry};
 pub use value::{
-    BuiltinFn, BuiltinMacro, MethodSignature, SourceInfo, TrackedValue, TraitRef, Type,
+    Attribute, BuiltinFn, BuiltinMacro, MethodSignature, SourceInfo, TrackedValue, TraitRef, Type,
     UserFunction, Value,
 }; // Export trait-related types from value
 
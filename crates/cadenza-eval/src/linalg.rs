@@ -0,0 +1,892 @@
+//! Vectors and matrices.
+//!
+//! `vector` builds a vector from a list, and `matrix` a matrix from a list of
+//! rows. Each element is a float or a quantity with its own unit, so a
+//! vector can hold a position in millimeters or a joint state mixing meters
+//! and radians:
+//!
+//! ```cadenza
+//! measure millimeter
+//! measure inch = millimeter 25.4
+//! let offset = vector [2millimeter, 1inch]
+//! (vector [1millimeter, 1millimeter]) + offset # vector [3millimeter, 26.4millimeter]
+//! let rotate = matrix [[0, -1], [1, 0]]
+//! rotate * offset                              # vector [-25.4millimeter, 2millimeter]
+//! dot offset offset                            # 649.16millimeter^2
+//! ```
+//!
+//! The arithmetic operators work on them as well as on numbers (see
+//! [`crate::num`]):
+//!
+//! - `+` and `-` combine two vectors of the same length, or two matrices of
+//!   the same shape, element by element, and `-` negates every element
+//! - `*` and `/` scale a vector or matrix by a number or quantity
+//! - `*` of two matrices, or of a matrix and a vector, is the matrix product
+//!
+//! `dot`, `cross` and `transpose` are builtins. Every element is combined by
+//! the numeric tower, so units are converted and multiplied per element, and
+//! adding elements of different dimensions is an error.
+//!
+//! Linear algebra is over the reals: integers become floats when a vector or
+//! matrix is built, and an integer scaling one is taken as a float.
+//!
+//! Compiled code holds a vector or matrix as a tuple of floats, a matrix row
+//! by row, and unrolls the operations element by element; the generator
+//! tracks the shape and the dimension of each element. It builds them from
+//! list literals, and its elements and scaling factors must be floats or
+//! number literals.
+
+use crate::{
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
+    interner::InternedString,
+    ir::{BinOp, BlockBuilder, IrConst, IrGenContext, SourceLocation, ValueId, binop_dimension},
+    list,
+    num::{self, Op},
+    special_form::list_form,
+    unit::DerivedDimension,
+    value::{BuiltinFn, Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+
+/// Returns `value` as an element of a vector or matrix: a float or a
+/// quantity, converting an integer.
+fn element(value: &Value) -> Result<Value> {
+    match value {
+        Value::Integer(n) => Ok(Value::Float(*n as f64)),
+        Value::BigInt(n) => Ok(Value::Float(n.to_f64())),
+        Value::Decimal(d) => Ok(Value::Float(d.to_f64())),
+        Value::Float(_) | Value::Quantity { .. } => Ok(value.clone()),
+        other => Err(Diagnostic::type_error(Type::Float, other.type_of())),
+    }
+}
+
+/// Returns the elements of the list `value` as vector elements.
+fn elements(value: &Value) -> Result<Vec<Value>> {
+    let elements = list::elements(value)
+        .ok_or_else(|| Diagnostic::type_error(Type::list(Type::Float), value.type_of()))?;
+    elements.map(|value| element(&value)).collect()
+}
+
+/// Returns the builtins bound in the [prelude](crate::prelude): `vector`,
+/// `matrix`, `dot`, `cross` and `transpose`.
+pub fn prelude_builtins() -> Vec<BuiltinFn> {
+    vec![
+        BuiltinFn {
+            name: "vector",
+            signature: Type::function(vec![Type::list(Type::Float)], Type::Vector),
+            func: |args, _ctx| match args {
+                [list] => Ok(Value::Vector(elements(list)?)),
+                _ => Err(Diagnostic::arity(1, args.len())),
+            },
+        },
+        BuiltinFn {
+            name: "matrix",
+            signature: Type::function(vec![Type::list(Type::list(Type::Float))], Type::Matrix),
+            func: |args, _ctx| match args {
+                [rows] => matrix(rows),
+                _ => Err(Diagnostic::arity(1, args.len())),
+            },
+        },
+        BuiltinFn {
+            name: "dot",
+            signature: Type::function(vec![Type::Vector, Type::Vector], Type::Float),
+            func: |args, _ctx| match args {
+                [Value::Vector(a), Value::Vector(b)] => {
+                    same_length(a, b, "take the dot product of")?;
+                    sum_of_products(a.iter().zip(b))
+                }
+                [a, b] => Err(not_vectors(a, b)),
+                _ => Err(Diagnostic::arity(2, args.len())),
+            },
+        },
+        BuiltinFn {
+            name: "cross",
+            signature: Type::function(vec![Type::Vector, Type::Vector], Type::Vector),
+            func: |args, _ctx| match args {
+                [Value::Vector(a), Value::Vector(b)] => cross(a, b),
+                [a, b] => Err(not_vectors(a, b)),
+                _ => Err(Diagnostic::arity(2, args.len())),
+            },
+        },
+        BuiltinFn {
+            name: "transpose",
+            signature: Type::function(vec![Type::Matrix], Type::Matrix),
+            func: |args, _ctx| match args {
+                [
+                    Value::Matrix {
+                        rows,
+                        columns,
+                        elements,
+                    },
+                ] => {
+                    let elements = (0..*columns)
+                        .flat_map(|column| {
+                            (0..*rows).map(move |row| elements[row * columns + column].clone())
+                        })
+                        .collect();
+                    Ok(Value::Matrix {
+                        rows: *columns,
+                        columns: *rows,
+                        elements,
+                    })
+                }
+                [other] => Err(Diagnostic::type_error(Type::Matrix, other.type_of())),
+                _ => Err(Diagnostic::arity(1, args.len())),
+            },
+        },
+    ]
+}
+
+/// Builds a matrix from a list of rows of the same length.
+fn matrix(rows: &Value) -> Result<Value> {
+    let rows = list::elements(rows)
+        .ok_or_else(|| Diagnostic::type_error(Type::list(Type::list(Type::Float)), rows.type_of()))?
+        .map(|row| elements(&row))
+        .collect::<Result<Vec<_>>>()?;
+    let columns = rows.first().map_or(0, Vec::len);
+    if let Some((index, row)) = rows
+        .iter()
+        .enumerate()
+        .find(|(_, row)| row.len() != columns)
+    {
+        return Err(Diagnostic::syntax(format!(
+            "matrix rows must have the same length: row 1 has {columns} elements, but row {} has {}",
+            index + 1,
+            row.len()
+        )));
+    }
+    Ok(Value::Matrix {
+        rows: rows.len(),
+        columns,
+        elements: rows.into_iter().flatten().collect(),
+    })
+}
+
+/// Returns the result of `op` on `lhs` and `rhs` if either is a vector or a
+/// matrix, or `None` if both are numbers.
+pub fn arithmetic(op: Op, lhs: &Value, rhs: &Value) -> Option<Result<Value>> {
+    let is_array = |value: &Value| matches!(value, Value::Vector(_) | Value::Matrix { .. });
+    if !is_array(lhs) && !is_array(rhs) {
+        return None;
+    }
+    let elementwise = |a: &[Value], b: &[Value]| -> Result<Vec<Value>> {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| num::arithmetic(op, a, b))
+            .collect()
+    };
+    let scaled = |elements: &[Value], factor: &Value, factor_first: bool| -> Result<Vec<Value>> {
+        let factor = element(factor)?;
+        elements
+            .iter()
+            .map(|element| match factor_first {
+                true => num::arithmetic(op, &factor, element),
+                false => num::arithmetic(op, element, &factor),
+            })
+            .collect()
+    };
+
+    Some(match (op, lhs, rhs) {
+        (Op::Add | Op::Sub, Value::Vector(a), Value::Vector(b)) => {
+            same_length(a, b, verb(op)).and_then(|()| elementwise(a, b).map(Value::Vector))
+        }
+        (
+            Op::Add | Op::Sub,
+            Value::Matrix {
+                rows,
+                columns,
+                elements: a,
+            },
+            Value::Matrix { elements: b, .. },
+        ) => same_shape(lhs, rhs, verb(op)).and_then(|()| {
+            Ok(Value::Matrix {
+                rows: *rows,
+                columns: *columns,
+                elements: elementwise(a, b)?,
+            })
+        }),
+        (Op::Mul, Value::Matrix { .. }, Value::Matrix { .. } | Value::Vector(_)) => {
+            product(lhs, rhs)
+        }
+        (Op::Mul | Op::Div, Value::Vector(elements), factor) if !is_array(factor) => {
+            scaled(elements, factor, false).map(Value::Vector)
+        }
+        (Op::Mul, factor, Value::Vector(elements)) if !is_array(factor) => {
+            scaled(elements, factor, true).map(Value::Vector)
+        }
+        (
+            Op::Mul | Op::Div,
+            Value::Matrix {
+                rows,
+                columns,
+                elements,
+            },
+            factor,
+        ) if !is_array(factor) => scaled(elements, factor, false).map(|elements| Value::Matrix {
+            rows: *rows,
+            columns: *columns,
+            elements,
+        }),
+        (
+            Op::Mul,
+            factor,
+            Value::Matrix {
+                rows,
+                columns,
+                elements,
+            },
+        ) if !is_array(factor) => scaled(elements, factor, true).map(|elements| Value::Matrix {
+            rows: *rows,
+            columns: *columns,
+            elements,
+        }),
+        (Op::Mul, Value::Vector(_), Value::Vector(_)) => Err(Diagnostic::syntax(
+            "`*` of two vectors is ambiguous: use `dot` or `cross`",
+        )),
+        _ => Err(Diagnostic::syntax(format!(
+            "cannot {} a {} and a {}",
+            verb(op),
+            lhs.type_of(),
+            rhs.type_of()
+        ))),
+    })
+}
+
+/// Returns `value` negated element by element if it is a vector or a
+/// matrix, or `None` if it's a number.
+pub fn negate(value: &Value) -> Option<Result<Value>> {
+    let negated = |elements: &[Value]| elements.iter().map(num::negate).collect::<Result<_>>();
+    match value {
+        Value::Vector(elements) => Some(negated(elements).map(Value::Vector)),
+        Value::Matrix {
+            rows,
+            columns,
+            elements,
+        } => Some(negated(elements).map(|elements| Value::Matrix {
+            rows: *rows,
+            columns: *columns,
+            elements,
+        })),
+        _ => None,
+    }
+}
+
+/// The matrix product of `lhs`, a matrix, and `rhs`, a matrix or a vector
+/// (a single column).
+fn product(lhs: &Value, rhs: &Value) -> Result<Value> {
+    let Value::Matrix {
+        rows,
+        columns: inner,
+        elements: a,
+    } = lhs
+    else {
+        unreachable!("the left operand of a product is a matrix");
+    };
+    let (b, rhs_rows, columns) = match rhs {
+        Value::Vector(b) => (b, b.len(), 1),
+        Value::Matrix {
+            rows,
+            columns,
+            elements,
+        } => (elements, *rows, *columns),
+        _ => unreachable!("the right operand of a product is a matrix or a vector"),
+    };
+    if *inner != rhs_rows {
+        return Err(Diagnostic::syntax(format!(
+            "cannot multiply a {rows}x{inner} matrix by a {}: it needs {inner} rows, not {rhs_rows}",
+            match rhs {
+                Value::Vector(_) => format!("vector of length {rhs_rows}"),
+                _ => format!("{rhs_rows}x{columns} matrix"),
+            }
+        )));
+    }
+    let mut elements = Vec::with_capacity(rows * columns);
+    for row in 0..*rows {
+        for column in 0..columns {
+            let terms = (0..*inner).map(|k| (&a[row * inner + k], &b[k * columns + column]));
+            elements.push(sum_of_products(terms)?);
+        }
+    }
+    Ok(match rhs {
+        Value::Vector(_) => Value::Vector(elements),
+        _ => Value::Matrix {
+            rows: *rows,
+            columns,
+            elements,
+        },
+    })
+}
+
+/// Returns the sum of the products of each pair, or `0.0` if there are
+/// none.
+fn sum_of_products<'a>(pairs: impl Iterator<Item = (&'a Value, &'a Value)>) -> Result<Value> {
+    let mut sum: Option<Value> = None;
+    for (a, b) in pairs {
+        let product = num::arithmetic(Op::Mul, a, b)?;
+        sum = Some(match sum {
+            Some(sum) => num::arithmetic(Op::Add, &sum, &product)?,
+            None => product,
+        });
+    }
+    Ok(sum.unwrap_or(Value::Float(0.0)))
+}
+
+/// The cross product of two vectors of length 3.
+fn cross(a: &[Value], b: &[Value]) -> Result<Value> {
+    let ([a1, a2, a3], [b1, b2, b3]) = (a, b) else {
+        return Err(Diagnostic::syntax(format!(
+            "the cross product takes vectors of length 3, not {} and {}",
+            a.len(),
+            b.len()
+        )));
+    };
+    let term = |p: &Value, q: &Value, r: &Value, s: &Value| {
+        num::arithmetic(
+            Op::Sub,
+            &num::arithmetic(Op::Mul, p, q)?,
+            &num::arithmetic(Op::Mul, r, s)?,
+        )
+    };
+    Ok(Value::Vector(vec![
+        term(a2, b3, a3, b2)?,
+        term(a3, b1, a1, b3)?,
+        term(a1, b2, a2, b1)?,
+    ]))
+}
+
+fn verb(op: Op) -> &'static str {
+    match op {
+        Op::Add => "add",
+        Op::Sub => "subtract",
+        Op::Mul => "multiply",
+        Op::Div => "divide",
+        Op::Rem => "take the remainder of",
+    }
+}
+
+fn same_length(a: &[Value], b: &[Value], verb: &str) -> Result<()> {
+    if a.len() != b.len() {
+        return Err(Diagnostic::syntax(format!(
+            "cannot {verb} vectors of lengths {} and {}",
+            a.len(),
+            b.len()
+        )));
+    }
+    Ok(())
+}
+
+fn same_shape(a: &Value, b: &Value, verb: &str) -> Result<()> {
+    let shape = |value: &Value| match value {
+        Value::Matrix { rows, columns, .. } => (*rows, *columns),
+        _ => unreachable!("only matrices have a shape"),
+    };
+    let ((r1, c1), (r2, c2)) = (shape(a), shape(b));
+    if (r1, c1) != (r2, c2) {
+        return Err(Diagnostic::syntax(format!(
+            "cannot {verb} a {r1}x{c1} matrix and a {r2}x{c2} matrix"
+        )));
+    }
+    Ok(())
+}
+
+fn not_vectors(a: &Value, b: &Value) -> Box<Diagnostic> {
+    let other = if matches!(a, Value::Vector(_)) { b } else { a };
+    Diagnostic::type_error(Type::Vector, other.type_of())
+}
+
+/// The shape of a vector or matrix in compiled code, with the dimension of
+/// each element in the order of the tuple holding them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Shape {
+    /// The rows and columns of a matrix, or `None` for a vector.
+    pub matrix: Option<(usize, usize)>,
+    /// The dimension of each element, or `None` for a plain float.
+    pub dimensions: Vec<Option<DerivedDimension>>,
+}
+
+/// Returns whether `name` is one of the builtins compiled code lowers.
+pub(crate) fn is_builtin(name: &str) -> bool {
+    matches!(name, "vector" | "matrix" | "dot" | "cross" | "transpose")
+}
+
+/// Returns the expressions to generate for a call of the builtin `name` with
+/// `args`: the elements of the list literal of `vector`, the elements of the
+/// rows of `matrix` in order, and the arguments of the others.
+pub(crate) fn ir_operands(name: &str, args: &[Expr]) -> Result<Vec<Expr>> {
+    match name {
+        "vector" => literal(args).and_then(|list| list_literal(&list)),
+        "matrix" => {
+            let rows = literal(args).and_then(|list| list_literal(&list))?;
+            let rows = rows.iter().map(list_literal).collect::<Result<Vec<_>>>()?;
+            Ok(rows.into_iter().flatten().collect())
+        }
+        _ => Ok(args.to_vec()),
+    }
+}
+
+/// Generates the call of the builtin `name` with `args`, whose operands (see
+/// [`ir_operands`]) were generated as `values`.
+pub(crate) fn ir_call(
+    name: &str,
+    args: &[Expr],
+    values: Vec<ValueId>,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> Result<ValueId> {
+    match name {
+        "vector" => {
+            check_elements(&values, ctx)?;
+            Ok(build(values, None, block, ctx, source))
+        }
+        "matrix" => {
+            let rows = literal(args).and_then(|list| list_literal(&list))?;
+            let mut lengths = rows
+                .iter()
+                .map(|row| list_literal(row).map(|row| row.len()));
+            let columns = lengths.next().transpose()?.unwrap_or(0);
+            if let Some(row) = lengths.position(|length| length.ok() != Some(columns)) {
+                return Err(Diagnostic::syntax(format!(
+                    "matrix rows must have the same length: row {} differs from row 1",
+                    row + 2
+                )));
+            }
+            check_elements(&values, ctx)?;
+            Ok(build(
+                values,
+                Some((rows.len(), columns)),
+                block,
+                ctx,
+                source,
+            ))
+        }
+        "dot" => {
+            let [a, b] = vectors(&values, ctx)?;
+            if a.dimensions.len() != b.dimensions.len() {
+                return Err(Diagnostic::syntax(format!(
+                    "cannot take the dot product of vectors of lengths {} and {}",
+                    a.dimensions.len(),
+                    b.dimensions.len()
+                )));
+            }
+            let a = ir_elements(values[0], block, ctx, source);
+            let b = ir_elements(values[1], block, ctx, source);
+            ir_sum_of_products(a.into_iter().zip(b), block, ctx, source)
+        }
+        "cross" => {
+            let [a, b] = vectors(&values, ctx)?;
+            if a.dimensions.len() != 3 || b.dimensions.len() != 3 {
+                return Err(Diagnostic::syntax(format!(
+                    "the cross product takes vectors of length 3, not {} and {}",
+                    a.dimensions.len(),
+                    b.dimensions.len()
+                )));
+            }
+            let a = ir_elements(values[0], block, ctx, source);
+            let b = ir_elements(values[1], block, ctx, source);
+            let mut term = |p: usize, q: usize| -> Result<ValueId> {
+                let lhs = ir_binop(BinOp::Mul, a[p], b[q], block, ctx, source)?;
+                let rhs = ir_binop(BinOp::Mul, a[q], b[p], block, ctx, source)?;
+                ir_binop(BinOp::Sub, lhs, rhs, block, ctx, source)
+            };
+            let result = vec![term(1, 2)?, term(2, 0)?, term(0, 1)?];
+            Ok(build(result, None, block, ctx, source))
+        }
+        "transpose" => {
+            let &[value] = &values[..] else {
+                return Err(Diagnostic::arity(1, values.len()));
+            };
+            let Some(Shape {
+                matrix: Some((rows, columns)),
+                ..
+            }) = ctx.shape(value).cloned()
+            else {
+                return Err(Diagnostic::type_error(Type::Matrix, type_of(value, ctx)));
+            };
+            let elements = ir_elements(value, block, ctx, source);
+            let transposed = (0..columns)
+                .flat_map(|column| (0..rows).map(move |row| row * columns + column))
+                .map(|index| elements[index])
+                .collect();
+            Ok(build(transposed, Some((columns, rows)), block, ctx, source))
+        }
+        _ => unreachable!("{name} is not a linear algebra builtin"),
+    }
+}
+
+/// Generates the arithmetic operator `op` on `lhs` and `rhs` element by
+/// element if either is a vector or a matrix, or returns `None` if neither
+/// is.
+pub(crate) fn ir_arithmetic(
+    op: BinOp,
+    lhs: ValueId,
+    rhs: ValueId,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> Option<Result<ValueId>> {
+    let (lhs_shape, rhs_shape) = (ctx.shape(lhs).cloned(), ctx.shape(rhs).cloned());
+    if lhs_shape.is_none() && rhs_shape.is_none() {
+        return None;
+    }
+    let operator = match op {
+        BinOp::Add => Op::Add,
+        BinOp::Sub => Op::Sub,
+        BinOp::Mul => Op::Mul,
+        BinOp::Div => Op::Div,
+        BinOp::Rem => Op::Rem,
+        _ => {
+            return Some(Err(Diagnostic::syntax(format!(
+                "`{op}` of vectors and matrices is not supported"
+            ))));
+        }
+    };
+    Some((|| match (operator, lhs_shape, rhs_shape) {
+        (Op::Add | Op::Sub, Some(a), Some(b)) => {
+            if a.matrix != b.matrix || a.dimensions.len() != b.dimensions.len() {
+                return Err(Diagnostic::syntax(format!(
+                    "cannot {} a {} and a {}",
+                    verb(operator),
+                    describe(&a),
+                    describe(&b)
+                )));
+            }
+            let a_elements = ir_elements(lhs, block, ctx, source);
+            let b_elements = ir_elements(rhs, block, ctx, source);
+            let result = a_elements
+                .into_iter()
+                .zip(b_elements)
+                .map(|(a, b)| ir_binop(op, a, b, block, ctx, source))
+                .collect::<Result<_>>()?;
+            Ok(build(result, a.matrix, block, ctx, source))
+        }
+        (
+            Op::Mul,
+            Some(
+                a @ Shape {
+                    matrix: Some(_), ..
+                },
+            ),
+            Some(b),
+        ) => ir_product(lhs, &a, rhs, &b, block, ctx, source),
+        (Op::Mul | Op::Div, Some(shape), None) => {
+            check_scalar(rhs, ctx)?;
+            if op == BinOp::Div {
+                block.check_divisor(rhs, &Type::Float, source);
+            }
+            let result = ir_elements(lhs, block, ctx, source)
+                .into_iter()
+                .map(|element| ir_binop(op, element, rhs, block, ctx, source))
+                .collect::<Result<_>>()?;
+            Ok(build(result, shape.matrix, block, ctx, source))
+        }
+        (Op::Mul, None, Some(shape)) => {
+            check_scalar(lhs, ctx)?;
+            let result = ir_elements(rhs, block, ctx, source)
+                .into_iter()
+                .map(|element| ir_binop(op, lhs, element, block, ctx, source))
+                .collect::<Result<_>>()?;
+            Ok(build(result, shape.matrix, block, ctx, source))
+        }
+        (Op::Mul, Some(_), Some(_)) => Err(Diagnostic::syntax(
+            "`*` of two vectors is ambiguous: use `dot` or `cross`",
+        )),
+        _ => Err(Diagnostic::syntax(format!(
+            "cannot {} a {} and a {}",
+            verb(operator),
+            type_of(lhs, ctx),
+            type_of(rhs, ctx)
+        ))),
+    })())
+}
+
+/// Generates the matrix product of the matrix `lhs` and the matrix or
+/// vector `rhs`.
+#[allow(clippy::too_many_arguments)]
+fn ir_product(
+    lhs: ValueId,
+    lhs_shape: &Shape,
+    rhs: ValueId,
+    rhs_shape: &Shape,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> Result<ValueId> {
+    let (rows, inner) = lhs_shape.matrix.expect("the left operand is a matrix");
+    let (rhs_rows, columns) = rhs_shape.matrix.unwrap_or((rhs_shape.dimensions.len(), 1));
+    if inner != rhs_rows {
+        return Err(Diagnostic::syntax(format!(
+            "cannot multiply a {rows}x{inner} matrix by a {}: it needs {inner} rows, not {rhs_rows}",
+            describe(rhs_shape)
+        )));
+    }
+    let a = ir_elements(lhs, block, ctx, source);
+    let b = ir_elements(rhs, block, ctx, source);
+    let mut result = Vec::with_capacity(rows * columns);
+    for row in 0..rows {
+        for column in 0..columns {
+            let terms = (0..inner).map(|k| (a[row * inner + k], b[k * columns + column]));
+            result.push(ir_sum_of_products(terms, block, ctx, source)?);
+        }
+    }
+    let matrix = rhs_shape.matrix.map(|_| (rows, columns));
+    Ok(build(result, matrix, block, ctx, source))
+}
+
+/// Generates the sum of the products of each pair, or `0.0` if there are
+/// none.
+fn ir_sum_of_products(
+    pairs: impl Iterator<Item = (ValueId, ValueId)>,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> Result<ValueId> {
+    let mut sum = None;
+    for (a, b) in pairs {
+        let product = ir_binop(BinOp::Mul, a, b, block, ctx, source)?;
+        sum = Some(match sum {
+            Some(sum) => ir_binop(BinOp::Add, sum, product, block, ctx, source)?,
+            None => product,
+        });
+    }
+    Ok(sum.unwrap_or_else(|| {
+        let zero = block.const_val(IrConst::Float(0.0), Type::Float, source);
+        ctx.set_value_type(zero, Type::Float);
+        zero
+    }))
+}
+
+/// Generates `op` on two floats, checking and propagating their dimensions.
+fn ir_binop(
+    op: BinOp,
+    lhs: ValueId,
+    rhs: ValueId,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> Result<ValueId> {
+    let dimension = binop_dimension(op, lhs, rhs, ctx)?;
+    let result = block.binop(op, lhs, rhs, Type::Float, source);
+    ctx.set_value_type(result, Type::Float);
+    if let Some(dimension) = dimension {
+        ctx.set_dimension(result, dimension);
+    }
+    Ok(result)
+}
+
+/// Generates the tuple holding a vector, or a matrix of `matrix` rows and
+/// columns, of `elements`.
+fn build(
+    elements: Vec<ValueId>,
+    matrix: Option<(usize, usize)>,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> ValueId {
+    let dimensions = elements
+        .iter()
+        .map(|element| ctx.dimension(*element).cloned())
+        .collect();
+    let ty = Type::Tuple(vec![Type::Float; elements.len()]);
+    let result = block.tuple(elements, ty.clone(), source);
+    ctx.set_value_type(result, ty);
+    ctx.set_shape(result, Shape { matrix, dimensions });
+    result
+}
+
+/// Generates reading the elements of the vector or matrix `value`.
+fn ir_elements(
+    value: ValueId,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> Vec<ValueId> {
+    let shape = ctx.shape(value).cloned().expect("the value has a shape");
+    let mut elements = Vec::with_capacity(shape.dimensions.len());
+    for (index, dimension) in shape.dimensions.into_iter().enumerate() {
+        let field = InternedString::new(&index.to_string());
+        let element = block.field(value, field, Type::Float, source);
+        ctx.set_value_type(element, Type::Float);
+        if let Some(dimension) = dimension {
+            ctx.set_dimension(element, dimension);
+        }
+        elements.push(element);
+    }
+    elements
+}
+
+/// Checks the elements of a vector or matrix are floats, which parameters
+/// of unknown type are taken to be.
+fn check_elements(values: &[ValueId], ctx: &mut IrGenContext) -> Result<()> {
+    values
+        .iter()
+        .try_for_each(|value| check_scalar(*value, ctx))
+}
+
+/// Checks `value`, an element or a factor scaling a vector or matrix, is a
+/// float.
+fn check_scalar(value: ValueId, ctx: &mut IrGenContext) -> Result<()> {
+    match ctx.get_value_type(value) {
+        Some(Type::Float) => Ok(()),
+        Some(Type::Unknown) | None => {
+            ctx.set_value_type(value, Type::Float);
+            Ok(())
+        }
+        Some(ty) => Err(Diagnostic::syntax(format!(
+            "compiled code builds and scales vectors and matrices with floats, not {ty}"
+        ))),
+    }
+}
+
+/// Returns the shapes of the two vectors in `values`.
+fn vectors(values: &[ValueId], ctx: &IrGenContext) -> Result<[Shape; 2]> {
+    let &[a, b] = values else {
+        return Err(Diagnostic::arity(2, values.len()));
+    };
+    let vector = |value| match ctx.shape(value) {
+        Some(shape) if shape.matrix.is_none() => Ok(shape.clone()),
+        _ => Err(Diagnostic::type_error(Type::Vector, type_of(value, ctx))),
+    };
+    Ok([vector(a)?, vector(b)?])
+}
+
+/// Returns the type of `value` as the evaluator would name it.
+fn type_of(value: ValueId, ctx: &IrGenContext) -> Type {
+    match ctx.shape(value) {
+        Some(Shape { matrix: None, .. }) => Type::Vector,
+        Some(Shape {
+            matrix: Some(_), ..
+        }) => Type::Matrix,
+        None => ctx.get_value_type(value).cloned().unwrap_or(Type::Unknown),
+    }
+}
+
+/// Describes the shape, as in "vector of length 3" or "2x3 matrix".
+fn describe(shape: &Shape) -> String {
+    match shape.matrix {
+        Some((rows, columns)) => format!("{rows}x{columns} matrix"),
+        None => format!("vector of length {}", shape.dimensions.len()),
+    }
+}
+
+/// Returns the only argument of `vector` or `matrix`.
+fn literal(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [arg] => Ok(arg.clone()),
+        _ => Err(Diagnostic::arity(1, args.len())),
+    }
+}
+
+/// Returns the elements of the list literal `expr`.
+fn list_literal(expr: &Expr) -> Result<Vec<Expr>> {
+    let elements = match expr {
+        Expr::Apply(apply) => apply
+            .callee()
+            .and_then(|callee| extract_identifier(&callee))
+            .filter(|callee| &**callee == "__list__")
+            .map(|_| apply.all_arguments()),
+        _ => None,
+    };
+    match elements {
+        Some(elements) if list_form::comprehension(&elements).is_none() => Ok(elements),
+        _ => Err(Diagnostic::syntax(
+            "compiled code builds vectors and matrices from list literals",
+        )
+        .with_span(expr.span())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> (Vec<String>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        (results.iter().map(Value::to_string).collect(), compiler)
+    }
+
+    #[test]
+    fn operations_follow_the_rules_of_linear_algebra() {
+        let (results, compiler) = eval_all(
+            "
+let a = matrix [[1, 2], [3, 4]]
+let v = vector [1, 1]
+a * v
+a * (transpose a)
+a + a - a
+2 * v / 4
+dot v (vector [3, 4])
+let w = -v
+cross (vector [1, 0, 0]) (vector [0, 1, 0])
+w
+",
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(
+            results[2..],
+            [
+                "vector [3, 7]",
+                "matrix [[5, 11], [11, 25]]",
+                "matrix [[1, 2], [3, 4]]",
+                "vector [0.5, 0.5]",
+                "7",
+                "vector [-1, -1]",
+                "vector [0, 0, 1]",
+                "vector [-1, -1]",
+            ]
+        );
+    }
+
+    #[test]
+    fn elements_keep_their_units() {
+        let (results, compiler) = eval_all(
+            "
+measure millimeter
+measure inch = millimeter 25.4
+measure second
+let offset = vector [1inch, 2millimeter]
+offset + (vector [1millimeter, 1millimeter])
+dot offset (vector [1, 1])
+(vector [1millimeter, 1second]) + (vector [1millimeter, 1millimeter])
+",
+        );
+        assert_eq!(results[4], "vector [1.0393700787401574inch, 3millimeter]");
+        assert_eq!(results[5], "1.078740157480315inch");
+        let messages: Vec<String> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(messages.len(), 1, "{messages:?}");
+        assert!(
+            messages[0].contains("incompatible dimensions"),
+            "{messages:?}"
+        );
+    }
+
+    #[test]
+    fn shapes_must_fit() {
+        let (_, compiler) = eval_all(
+            "
+(vector [1, 2]) + (vector [1, 2, 3])
+(matrix [[1, 2]]) * (matrix [[1, 2]])
+matrix [[1, 2], [3]]
+(vector [1, 2]) * (vector [1, 2])
+",
+        );
+        let messages: Vec<String> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(messages.len(), 4, "{messages:?}");
+        assert!(messages[0].contains("vectors of lengths 2 and 3"));
+        assert!(messages[1].contains("cannot multiply a 1x2 matrix by a 1x2 matrix"));
+        assert!(messages[2].contains("row 2 has 1"));
+        assert!(messages[3].contains("use `dot` or `cross`"));
+    }
+}
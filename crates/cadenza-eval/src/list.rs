@@ -0,0 +1,391 @@
+//! List primitives.
+//!
+//! `len`, `nth` and `push` are the primitives the rest of the list library is
+//! built on, and are defined as builtins. Most helpers (`sum`, `reverse`,
+//! `range`, ...) are written in Cadenza on top of them in the
+//! [`prelude`](crate::prelude).
+//!
+//! The core traversals (`map`, `filter`, `fold`) and `head` and `tail` are
+//! native too, so they run in constant stack however long the list is, but
+//! [`prelude_builtins`] places them in the prelude, where user definitions
+//! with the same name shadow them.
+//!
+//! Every list function also takes a [`Value::Range`] (`1..10` or `1..=10`).
+//! A range's elements are only computed as they're used, and `take`, `drop`
+//! and `tail` of a range are ranges themselves, so `1..1000000 |> take 5`
+//! never builds the whole range.
+
+use crate::{
+    diagnostic::{Diagnostic, Result},
+    eval::apply_value,
+    value::{BuiltinFn, Type, Value},
+};
+
+/// Creates the `len` builtin: the number of elements in a list or characters in a string.
+pub fn builtin_len() -> BuiltinFn {
+    BuiltinFn {
+        name: "len",
+        signature: Type::function(vec![Type::list(Type::Unknown)], Type::Integer),
+        func: |args, _ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            match &args[0] {
+                Value::List(items) => Ok(Value::Integer(items.len() as i64)),
+                Value::Range { start, end } => Ok(Value::Integer(range_len(*start, *end))),
+                Value::String(s) => Ok(Value::Integer(s.chars().count() as i64)),
+                other => Err(Diagnostic::type_error(
+                    Type::list(Type::Unknown),
+                    other.type_of(),
+                )),
+            }
+        },
+    }
+}
+
+/// Creates the `push` builtin: `push xs x` returns `xs` with `x` appended.
+pub fn builtin_push() -> BuiltinFn {
+    BuiltinFn {
+        name: "push",
+        signature: Type::function(
+            vec![Type::list(Type::Unknown), Type::Unknown],
+            Type::list(Type::Unknown),
+        ),
+        func: |args, _ctx| {
+            if args.len() != 2 {
+                return Err(Diagnostic::arity(2, args.len()));
+            }
+            let mut items: Vec<Value> = elements_of(&args[0])?.collect();
+            items.push(args[1].clone());
+            Ok(Value::List(items))
+        },
+    }
+}
+
+/// Creates the `nth` builtin: `nth xs i` returns the element at index `i`.
+///
+/// This is `xs[i]` as a function, for use where index syntax isn't available.
+pub fn builtin_nth() -> BuiltinFn {
+    BuiltinFn {
+        name: "nth",
+        signature: Type::function(
+            vec![Type::list(Type::Unknown), Type::Integer],
+            Type::Unknown,
+        ),
+        func: |args, _ctx| {
+            if args.len() != 2 {
+                return Err(Diagnostic::arity(2, args.len()));
+            }
+            let index = match &args[1] {
+                Value::Integer(i) => *i,
+                other => return Err(Diagnostic::type_error(Type::Integer, other.type_of())),
+            };
+            match &args[0] {
+                Value::List(items) => usize::try_from(index)
+                    .ok()
+                    .and_then(|i| items.get(i))
+                    .cloned()
+                    .ok_or_else(|| {
+                        Diagnostic::syntax(format!(
+                            "index out of bounds: index {} is out of range for list of length {}",
+                            index,
+                            items.len()
+                        ))
+                    }),
+                Value::Range { start, end } => {
+                    let len = range_len(*start, *end);
+                    if (0..len).contains(&index) {
+                        Ok(Value::Integer(start + index))
+                    } else {
+                        Err(Diagnostic::syntax(format!(
+                            "index out of bounds: index {index} is out of range for list of length {len}"
+                        )))
+                    }
+                }
+                other => Err(Diagnostic::type_error(
+                    Type::list(Type::Unknown),
+                    other.type_of(),
+                )),
+            }
+        },
+    }
+}
+
+/// Creates the `..` operator: `start..end` is the range of integers from
+/// `start` up to but not including `end`.
+pub fn builtin_range() -> BuiltinFn {
+    BuiltinFn {
+        name: "..",
+        signature: Type::function(
+            vec![Type::Integer, Type::Integer],
+            Type::list(Type::Integer),
+        ),
+        func: |args, _ctx| {
+            let (start, end) = range_bounds(args)?;
+            Ok(Value::Range { start, end })
+        },
+    }
+}
+
+/// Creates the `..=` operator: `start..=last` is the range of integers from
+/// `start` up to and including `last`.
+pub fn builtin_range_inclusive() -> BuiltinFn {
+    BuiltinFn {
+        name: "..=",
+        signature: Type::function(
+            vec![Type::Integer, Type::Integer],
+            Type::list(Type::Integer),
+        ),
+        func: |args, _ctx| {
+            let (start, last) = range_bounds(args)?;
+            let end = last
+                .checked_add(1)
+                .ok_or_else(|| Diagnostic::syntax(format!("range end {last} is too large")))?;
+            Ok(Value::Range { start, end })
+        },
+    }
+}
+
+/// Returns the integer bounds given to a range operator.
+fn range_bounds(args: &[Value]) -> Result<(i64, i64)> {
+    match args {
+        [Value::Integer(start), Value::Integer(end)] => Ok((*start, *end)),
+        [Value::Integer(_), other] | [other, _] => {
+            Err(Diagnostic::type_error(Type::Integer, other.type_of()))
+        }
+        _ => Err(Diagnostic::arity(2, args.len())),
+    }
+}
+
+/// Returns the number of integers in `start..end`.
+fn range_len(start: i64, end: i64) -> i64 {
+    end.saturating_sub(start).max(0)
+}
+
+/// Returns the native list functions that live in the prelude: `map`,
+/// `filter`, `fold`, `head`, `tail`, `take` and `drop`.
+pub fn prelude_builtins() -> Vec<BuiltinFn> {
+    vec![
+        builtin_map(),
+        builtin_filter(),
+        builtin_fold(),
+        builtin_head(),
+        builtin_tail(),
+        builtin_take(),
+        builtin_drop(),
+    ]
+}
+
+/// The elements of a list or range; a range's are computed as they're used.
+pub(crate) enum Elements<'a> {
+    List(std::slice::Iter<'a, Value>),
+    Range(std::ops::Range<i64>),
+}
+
+impl Iterator for Elements<'_> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            Elements::List(items) => items.next().cloned(),
+            Elements::Range(range) => range.next().map(Value::Integer),
+        }
+    }
+}
+
+/// Returns the elements of `value`, or `None` if it is neither a list nor a
+/// range.
+pub(crate) fn elements(value: &Value) -> Option<Elements<'_>> {
+    match value {
+        Value::List(items) => Some(Elements::List(items.iter())),
+        Value::Range { start, end } => Some(Elements::Range(*start..*end)),
+        _ => None,
+    }
+}
+
+/// Returns the elements of `value`, which must be a list or range.
+fn elements_of(value: &Value) -> Result<Elements<'_>> {
+    elements(value)
+        .ok_or_else(|| Diagnostic::type_error(Type::list(Type::Unknown), value.type_of()))
+}
+
+/// Creates the `map` builtin: `map f xs` applies `f` to each element of `xs`.
+pub fn builtin_map() -> BuiltinFn {
+    BuiltinFn {
+        name: "map",
+        signature: Type::function(
+            vec![
+                Type::function(vec![Type::Unknown], Type::Unknown),
+                Type::list(Type::Unknown),
+            ],
+            Type::list(Type::Unknown),
+        ),
+        func: |args, ctx| {
+            let [f, xs] = args else {
+                return Err(Diagnostic::arity(2, args.len()));
+            };
+            let mut mapped = Vec::new();
+            for item in elements_of(xs)? {
+                mapped.push(apply_value(f.clone(), vec![item], ctx)?);
+            }
+            Ok(Value::List(mapped))
+        },
+    }
+}
+
+/// Creates the `filter` builtin: `filter f xs` keeps the elements of `xs`
+/// for which `f` returns true.
+pub fn builtin_filter() -> BuiltinFn {
+    BuiltinFn {
+        name: "filter",
+        signature: Type::function(
+            vec![
+                Type::function(vec![Type::Unknown], Type::Bool),
+                Type::list(Type::Unknown),
+            ],
+            Type::list(Type::Unknown),
+        ),
+        func: |args, ctx| {
+            let [f, xs] = args else {
+                return Err(Diagnostic::arity(2, args.len()));
+            };
+            let mut kept = Vec::new();
+            for item in elements_of(xs)? {
+                match apply_value(f.clone(), vec![item.clone()], ctx)? {
+                    Value::Bool(true) => kept.push(item),
+                    Value::Bool(false) => {}
+                    other => return Err(Diagnostic::type_error(Type::Bool, other.type_of())),
+                }
+            }
+            Ok(Value::List(kept))
+        },
+    }
+}
+
+/// Creates the `fold` builtin: `fold f acc xs` combines the elements of `xs`
+/// from the left, starting from `acc`.
+pub fn builtin_fold() -> BuiltinFn {
+    BuiltinFn {
+        name: "fold",
+        signature: Type::function(
+            vec![
+                Type::function(vec![Type::Unknown, Type::Unknown], Type::Unknown),
+                Type::Unknown,
+                Type::list(Type::Unknown),
+            ],
+            Type::Unknown,
+        ),
+        func: |args, ctx| {
+            let [f, acc, xs] = args else {
+                return Err(Diagnostic::arity(3, args.len()));
+            };
+            let mut acc = acc.clone();
+            for item in elements_of(xs)? {
+                acc = apply_value(f.clone(), vec![acc, item], ctx)?;
+            }
+            Ok(acc)
+        },
+    }
+}
+
+/// Creates the `head` builtin: the first element of a non-empty list.
+pub fn builtin_head() -> BuiltinFn {
+    BuiltinFn {
+        name: "head",
+        signature: Type::function(vec![Type::list(Type::Unknown)], Type::Unknown),
+        func: |args, _ctx| {
+            let [xs] = args else {
+                return Err(Diagnostic::arity(1, args.len()));
+            };
+            elements_of(xs)?
+                .next()
+                .ok_or_else(|| Diagnostic::syntax("head of an empty list"))
+        },
+    }
+}
+
+/// Creates the `tail` builtin: a non-empty list without its first element.
+pub fn builtin_tail() -> BuiltinFn {
+    BuiltinFn {
+        name: "tail",
+        signature: Type::function(vec![Type::list(Type::Unknown)], Type::list(Type::Unknown)),
+        func: |args, _ctx| {
+            let [xs] = args else {
+                return Err(Diagnostic::arity(1, args.len()));
+            };
+            match xs {
+                Value::List(items) if items.is_empty() => {
+                    Err(Diagnostic::syntax("tail of an empty list"))
+                }
+                Value::List(items) => Ok(Value::List(items[1..].to_vec())),
+                Value::Range { start, end } if start >= end => {
+                    Err(Diagnostic::syntax("tail of an empty list"))
+                }
+                Value::Range { start, end } => Ok(Value::Range {
+                    start: start + 1,
+                    end: *end,
+                }),
+                other => Err(Diagnostic::type_error(
+                    Type::list(Type::Unknown),
+                    other.type_of(),
+                )),
+            }
+        },
+    }
+}
+
+/// Creates the `take` builtin: `take xs n` is the first `n` elements of
+/// `xs`, or all of them if it has fewer.
+pub fn builtin_take() -> BuiltinFn {
+    BuiltinFn {
+        name: "take",
+        signature: Type::function(
+            vec![Type::list(Type::Unknown), Type::Integer],
+            Type::list(Type::Unknown),
+        ),
+        func: |args, _ctx| {
+            let n = count(args)?;
+            match &args[0] {
+                Value::Range { start, end } => Ok(Value::Range {
+                    start: *start,
+                    end: (*end).min(start.saturating_add(n)),
+                }),
+                xs => Ok(Value::List(elements_of(xs)?.take(n as usize).collect())),
+            }
+        },
+    }
+}
+
+/// Creates the `drop` builtin: `drop xs n` is `xs` without its first `n`
+/// elements, or empty if it has fewer.
+pub fn builtin_drop() -> BuiltinFn {
+    BuiltinFn {
+        name: "drop",
+        signature: Type::function(
+            vec![Type::list(Type::Unknown), Type::Integer],
+            Type::list(Type::Unknown),
+        ),
+        func: |args, _ctx| {
+            let n = count(args)?;
+            match &args[0] {
+                Value::Range { start, end } => Ok(Value::Range {
+                    start: (*end).min(start.saturating_add(n)).max(*start),
+                    end: *end,
+                }),
+                xs => Ok(Value::List(elements_of(xs)?.skip(n as usize).collect())),
+            }
+        },
+    }
+}
+
+/// Returns the element count given to `take` or `drop`.
+fn count(args: &[Value]) -> Result<i64> {
+    match args {
+        [_, Value::Integer(n)] if *n >= 0 => Ok(*n),
+        [_, Value::Integer(n)] => Err(Diagnostic::syntax(format!(
+            "expected a count of at least 0, found {n}"
+        ))),
+        [_, other] => Err(Diagnostic::type_error(Type::Integer, other.type_of())),
+        _ => Err(Diagnostic::arity(2, args.len())),
+    }
+}
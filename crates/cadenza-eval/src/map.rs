@@ -2,10 +2,14 @@
 //!
 //! The std HashMap has DoS protection which is unnecessary overhead
 //! for a local compiler. We use rustc-hash's FxHasher instead.
+//!
+//! The map iterates in insertion order, so anything listed from it, like
+//! the bindings of a scope or the compiler's definitions, comes out in the
+//! order it was defined rather than in hash order.
 
 use crate::interner::InternedString;
-use rustc_hash::FxHasher;
-use std::{collections::HashMap, hash::BuildHasherDefault};
+use indexmap::IndexMap;
+use rustc_hash::FxBuildHasher;
 
-/// A hash map using FxHash for interned string keys.
-pub type Map<V> = HashMap<InternedString, V, BuildHasherDefault<FxHasher>>;
+/// An insertion-ordered hash map using FxHash for interned string keys.
+pub type Map<V> = IndexMap<InternedString, V, FxBuildHasher>;
@@ -0,0 +1,270 @@
+//! Math functions.
+//!
+//! The trigonometric and exponential functions are the functions of the
+//! host's `math` interface (see [`ir::host`](crate::ir::host)), so code that
+//! uses them both evaluates and compiles: compiled modules import them from
+//! the host. They take floats, and integers are converted.
+//!
+//! Their results depend on the implementation of the host, or of the
+//! compiler's own platform when they are evaluated, so the
+//! [`deterministic-floats`](crate::CompileOptions::DETERMINISTIC_FLOATS)
+//! feature forbids them.
+//!
+//! `sqrt`, `floor`, `ceil`, `round`, `abs`, `min` and `max` are exact, so
+//! they are allowed with that feature, and compile to WebAssembly
+//! instructions instead of host calls. `abs`, `min` and `max` keep integers
+//! integers and decimals decimals. `round` rounds halfway cases to the even
+//! neighbor, and `min` and `max` of a NaN are NaN, like the WebAssembly
+//! instructions. `min` and `max` are [prelude](crate::prelude) functions, so
+//! a program may define its own.
+//!
+//! ```cadenza
+//! atan2 1.0 1.0
+//! pow 2 10
+//! sqrt 2.0
+//! round 2.5          # 2.0
+//! max (abs (-3)) 2   # 3
+//! ```
+
+use crate::{
+    context::EvalContext,
+    diagnostic::Diagnostic,
+    num,
+    value::{BuiltinFn, Type, Value},
+};
+use std::cmp::Ordering;
+
+/// Returns the float value of `value`, converting an integer or a decimal.
+fn float(value: &Value) -> Result<f64, Box<Diagnostic>> {
+    match value {
+        Value::Float(x) => Ok(*x),
+        Value::Integer(n) => Ok(*n as f64),
+        Value::BigInt(n) => Ok(n.to_f64()),
+        Value::Decimal(d) => Ok(d.to_f64()),
+        other => Err(Diagnostic::type_error(Type::Float, other.type_of())),
+    }
+}
+
+/// Fails if the compile options forbid nondeterministic float operations.
+fn deterministic(name: &str, ctx: &EvalContext<'_>) -> Result<(), Box<Diagnostic>> {
+    if ctx.compiler.options().deterministic_floats() {
+        return Err(Diagnostic::nondeterministic_float(name.into()));
+    }
+    Ok(())
+}
+
+macro_rules! unary {
+    (exact $(#[$doc:meta])* $builtin:ident, $name:literal, $f:expr) => {
+        unary!(@build false, $(#[$doc])* $builtin, $name, $f);
+    };
+    ($(#[$doc:meta])* $builtin:ident, $name:literal, $f:expr) => {
+        unary!(@build true, $(#[$doc])* $builtin, $name, $f);
+    };
+    (@build $checked:literal, $(#[$doc:meta])* $builtin:ident, $name:literal, $f:expr) => {
+        $(#[$doc])*
+        pub fn $builtin() -> BuiltinFn {
+            BuiltinFn {
+                name: $name,
+                signature: Type::function(vec![Type::Float], Type::Float),
+                func: |args, ctx| {
+                    if $checked {
+                        deterministic($name, ctx)?;
+                    }
+                    if args.len() != 1 {
+                        return Err(Diagnostic::arity(1, args.len()));
+                    }
+                    let f: fn(f64) -> f64 = $f;
+                    Ok(Value::Float(f(float(&args[0])?)))
+                },
+            }
+        }
+    };
+}
+
+macro_rules! binary {
+    ($(#[$doc:meta])* $builtin:ident, $name:literal, $f:expr) => {
+        $(#[$doc])*
+        pub fn $builtin() -> BuiltinFn {
+            BuiltinFn {
+                name: $name,
+                signature: Type::function(vec![Type::Float, Type::Float], Type::Float),
+                func: |args, ctx| {
+                    deterministic($name, ctx)?;
+                    if args.len() != 2 {
+                        return Err(Diagnostic::arity(2, args.len()));
+                    }
+                    let f: fn(f64, f64) -> f64 = $f;
+                    Ok(Value::Float(f(float(&args[0])?, float(&args[1])?)))
+                },
+            }
+        }
+    };
+}
+
+unary!(
+    /// Creates the `sin` builtin: the sine of an angle in radians.
+    builtin_sin, "sin", f64::sin
+);
+unary!(
+    /// Creates the `cos` builtin: the cosine of an angle in radians.
+    builtin_cos, "cos", f64::cos
+);
+unary!(
+    /// Creates the `tan` builtin: the tangent of an angle in radians.
+    builtin_tan, "tan", f64::tan
+);
+unary!(
+    /// Creates the `asin` builtin: the arcsine, in radians.
+    builtin_asin, "asin", f64::asin
+);
+unary!(
+    /// Creates the `acos` builtin: the arccosine, in radians.
+    builtin_acos, "acos", f64::acos
+);
+unary!(
+    /// Creates the `atan` builtin: the arctangent, in radians.
+    builtin_atan, "atan", f64::atan
+);
+binary!(
+    /// Creates the `atan2` builtin: `atan2 y x` is the angle of the point
+    /// `(x, y)`, in radians.
+    builtin_atan2, "atan2", f64::atan2
+);
+unary!(
+    /// Creates the `exp` builtin: e raised to a power.
+    builtin_exp, "exp", f64::exp
+);
+unary!(
+    /// Creates the `ln` builtin: the natural logarithm.
+    builtin_ln, "ln", f64::ln
+);
+unary!(
+    /// Creates the `log10` builtin: the base-10 logarithm.
+    builtin_log10, "log10", f64::log10
+);
+binary!(
+    /// Creates the `pow` builtin: `pow base exponent`.
+    builtin_pow, "pow", f64::powf
+);
+
+unary!(exact
+    /// Creates the `sqrt` builtin: the square root.
+    builtin_sqrt, "sqrt", f64::sqrt
+);
+unary!(exact
+    /// Creates the `floor` builtin: the largest whole number not above a
+    /// number.
+    builtin_floor, "floor", f64::floor
+);
+unary!(exact
+    /// Creates the `ceil` builtin: the smallest whole number not below a
+    /// number.
+    builtin_ceil, "ceil", f64::ceil
+);
+unary!(exact
+    /// Creates the `round` builtin: the nearest whole number, rounding
+    /// halfway cases to the even one.
+    builtin_round, "round", f64::round_ties_even
+);
+
+/// Creates the `abs` builtin: the absolute value of an integer, a float or a
+/// decimal.
+pub fn builtin_abs() -> BuiltinFn {
+    BuiltinFn {
+        name: "abs",
+        signature: Type::function(vec![Type::Unknown], Type::Unknown),
+        func: |args, _ctx| {
+            let [x] = args else {
+                return Err(Diagnostic::arity(1, args.len()));
+            };
+            match x {
+                // The absolute value of `i64::MIN` is a big integer, and a
+                // decimal stays exact
+                Value::Integer(_) | Value::BigInt(_) | Value::Decimal(_) => {
+                    match num::compare(x, &Value::Integer(0))? {
+                        Ordering::Less => num::negate(x),
+                        _ => Ok(x.clone()),
+                    }
+                }
+                other => Ok(Value::Float(float(other)?.abs())),
+            }
+        },
+    }
+}
+
+/// Creates the `min` builtin: the smaller of two numbers.
+pub fn builtin_min() -> BuiltinFn {
+    BuiltinFn {
+        name: "min",
+        signature: Type::function(vec![Type::Unknown, Type::Unknown], Type::Unknown),
+        func: |args, _ctx| {
+            let [a, b] = args else {
+                return Err(Diagnostic::arity(2, args.len()));
+            };
+            pick(a, b, Ordering::Less)
+        },
+    }
+}
+
+/// Creates the `max` builtin: the larger of two numbers.
+pub fn builtin_max() -> BuiltinFn {
+    BuiltinFn {
+        name: "max",
+        signature: Type::function(vec![Type::Unknown, Type::Unknown], Type::Unknown),
+        func: |args, _ctx| {
+            let [a, b] = args else {
+                return Err(Diagnostic::arity(2, args.len()));
+            };
+            pick(a, b, Ordering::Greater)
+        },
+    }
+}
+
+/// Returns whichever of `a` and `b` compares as `keep` to the other:
+/// integers or decimals if both are, and floats otherwise, where a NaN wins
+/// and `-0.0` is below `0.0`.
+fn pick(a: &Value, b: &Value, keep: Ordering) -> Result<Value, Box<Diagnostic>> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        return Ok(Value::Integer(if x.cmp(y) == keep { *x } else { *y }));
+    }
+    if let (Value::Decimal(x), Value::Decimal(y)) = (a, b) {
+        return Ok(Value::Decimal(if x.cmp(y) == keep { x } else { y }.clone()));
+    }
+    let (x, y) = (float(a)?, float(b)?);
+    let result = if x.is_nan() || y.is_nan() {
+        f64::NAN
+    } else if x.total_cmp(&y) == keep {
+        x
+    } else {
+        y
+    };
+    Ok(Value::Float(result))
+}
+
+/// Returns every math builtin that isn't a prelude function.
+pub fn builtins() -> [BuiltinFn; 16] {
+    [
+        builtin_sin(),
+        builtin_cos(),
+        builtin_tan(),
+        builtin_asin(),
+        builtin_acos(),
+        builtin_atan(),
+        builtin_atan2(),
+        builtin_exp(),
+        builtin_ln(),
+        builtin_log10(),
+        builtin_pow(),
+        builtin_sqrt(),
+        builtin_floor(),
+        builtin_ceil(),
+        builtin_round(),
+        builtin_abs(),
+    ]
+}
+
+/// Returns the math builtins bound in the [prelude](crate::prelude): `min`
+/// and `max`.
+pub fn prelude_builtins() -> [BuiltinFn; 2] {
+    [builtin_min(), builtin_max()]
+}
@@ -0,0 +1,263 @@
+//! Result caches for memoized functions.
+//!
+//! Functions annotated with `@memoize` get a cache in the compiler keyed by
+//! their argument values. Caches are bounded; when full, the oldest entry is
+//! evicted. A cache belongs to a function's definition, not its name, so a
+//! nested or shadowing function with the same name has its own, and
+//! evaluating a definition again, such as a nested function on each call of
+//! the function around it, starts it afresh.
+//!
+//! ```cadenza
+//! @memoize
+//! fn fib n = match n
+//!     0 => 0
+//!     1 => 1
+//!     _ => (fib (n - 1)) + (fib (n - 2))
+//!
+//! @memoize 16
+//! fn expensive x = x * x
+//! ```
+//!
+//! The example is evaluated by this module's tests.
+//!
+//! Memoization assumes the function is pure; results are reused without
+//! re-evaluating the body. Arguments are keyed by their
+//! [stable hash](crate::hash); calls with unhashable arguments (such as
+//! functions) are never cached.
+
+use crate::{
+    hash::HashKey,
+    value::{UserFunction, Value},
+};
+use cadenza_syntax::SyntaxNode;
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+
+/// The number of entries a memoized function caches when no limit is given.
+pub const DEFAULT_LIMIT: usize = 1024;
+
+/// A bounded cache of results for a single function.
+#[derive(Debug, Clone)]
+pub struct FunctionCache {
+    limit: usize,
+    entries: FxHashMap<Vec<HashKey>, Value>,
+    /// Keys in insertion order, for eviction.
+    order: VecDeque<Vec<HashKey>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl FunctionCache {
+    /// Creates an empty cache holding at most `limit` entries.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            entries: FxHashMap::default(),
+            order: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the maximum number of entries this cache holds.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of cached entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of lookups that found a cached result.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Returns the number of lookups that missed.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Looks up the result for `args`.
+    pub fn get(&mut self, args: &[Value]) -> Option<Value> {
+        let found = key(args.to_vec()).and_then(|key| self.entries.get(&key).cloned());
+        if found.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        found
+    }
+
+    /// Stores the result for `args`, evicting the oldest entry if full.
+    ///
+    /// Nothing is stored if any argument is unhashable.
+    pub fn insert(&mut self, args: Vec<Value>, result: Value) {
+        if self.limit == 0 {
+            return;
+        }
+        let Some(key) = key(args) else {
+            return;
+        };
+        if self.entries.insert(key.clone(), result).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        while self.entries.len() > self.limit {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Builds a cache key from call arguments, if they are all hashable.
+fn key(args: Vec<Value>) -> Option<Vec<HashKey>> {
+    args.into_iter().map(HashKey::new).collect()
+}
+
+/// Identifies a function by its definition: the syntax node of its body,
+/// which is different for every `fn` written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionId(SyntaxNode);
+
+impl FunctionId {
+    /// Returns the identity of the definition `function` was made by.
+    pub fn of(function: &UserFunction) -> Self {
+        Self(function.body.syntax().clone())
+    }
+}
+
+/// Caches for all memoized functions, keyed by function definition.
+#[derive(Debug, Clone, Default)]
+pub struct MemoTable {
+    caches: FxHashMap<FunctionId, FunctionCache>,
+}
+
+impl MemoTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables memoization for the function `id` with a fresh cache.
+    pub fn enable(&mut self, id: FunctionId, limit: usize) {
+        self.caches.insert(id, FunctionCache::new(limit));
+    }
+
+    /// Drops the cache for the function `id`, disabling memoization for it.
+    pub fn invalidate(&mut self, id: &FunctionId) {
+        self.caches.remove(id);
+    }
+
+    /// Returns true if the function `id` is memoized.
+    pub fn is_memoized(&self, id: &FunctionId) -> bool {
+        self.caches.contains_key(id)
+    }
+
+    /// Returns the cache for the function `id`, if it is memoized.
+    pub fn cache(&self, id: &FunctionId) -> Option<&FunctionCache> {
+        self.caches.get(id)
+    }
+
+    /// Looks up a cached result for calling the function `id` with `args`.
+    pub fn get(&mut self, id: &FunctionId, args: &[Value]) -> Option<Value> {
+        self.caches.get_mut(id)?.get(args)
+    }
+
+    /// Records the result of calling the function `id` with `args`, if it is
+    /// memoized.
+    pub fn insert(&mut self, id: &FunctionId, args: Vec<Value>, result: Value) {
+        if let Some(cache) = self.caches.get_mut(id) {
+            cache.insert(args, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Env};
+    use cadenza_syntax::parse::parse;
+
+    /// Returns the Cadenza example in this module's documentation.
+    fn doc_example() -> String {
+        include_str!("memo.rs")
+            .lines()
+            .map_while(|line| line.strip_prefix("//!"))
+            .skip_while(|line| line.trim() != "```cadenza")
+            .skip(1)
+            .take_while(|line| line.trim() != "```")
+            .map(|line| format!("{}\n", line.strip_prefix(' ').unwrap_or(line)))
+            .collect()
+    }
+
+    #[test]
+    fn doc_example_evaluates() {
+        let src = format!("{}fib 30\nexpensive 12\n", doc_example());
+        let parsed = parse(&src);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parsed.ast(), &mut env, &mut compiler);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(
+            results[results.len() - 2..],
+            [Value::Integer(832040), Value::Integer(144)]
+        );
+    }
+
+    #[test]
+    fn caches_and_evicts_oldest() {
+        let mut cache = FunctionCache::new(2);
+        cache.insert(vec![Value::Integer(1)], Value::Integer(10));
+        cache.insert(vec![Value::Integer(2)], Value::Integer(20));
+        cache.insert(vec![Value::Integer(3)], Value::Integer(30));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&[Value::Integer(1)]), None);
+        assert_eq!(cache.get(&[Value::Integer(3)]), Some(Value::Integer(30)));
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 1);
+    }
+
+    #[test]
+    fn records_are_keys() {
+        let point = |x| Value::Record {
+            type_name: Some("Point".into()),
+            fields: vec![("x".into(), Value::Integer(x))],
+        };
+        let mut cache = FunctionCache::new(DEFAULT_LIMIT);
+        cache.insert(vec![point(1)], Value::Integer(10));
+
+        assert_eq!(cache.get(&[point(1)]), Some(Value::Integer(10)));
+        assert_eq!(cache.get(&[point(2)]), None);
+    }
+
+    #[test]
+    fn only_memoized_functions_are_cached() {
+        let root = cadenza_syntax::parse::parse("1\n2\n").ast();
+        let [f, g] = [0, 1].map(|i| FunctionId(root.items().nth(i).unwrap().syntax().clone()));
+        let mut table = MemoTable::new();
+        table.enable(f.clone(), DEFAULT_LIMIT);
+
+        table.insert(&f, vec![], Value::Integer(1));
+        table.insert(&g, vec![], Value::Integer(2));
+        assert_eq!(table.get(&f, &[]), Some(Value::Integer(1)));
+        assert_eq!(table.get(&g, &[]), None);
+
+        table.invalidate(&f);
+        assert!(!table.is_memoized(&f));
+        assert_eq!(table.get(&f, &[]), None);
+    }
+}
@@ -0,0 +1,244 @@
+//! Message catalogs for diagnostics.
+//!
+//! The text of each diagnostic comes from a catalog that maps its code to a
+//! template, such as `"E0001": "undefined variable: {name}"`. Placeholders
+//! in braces are replaced with the diagnostic's parameters (see
+//! [`DiagnosticKind::params`]), and `{{` and `}}` write literal braces.
+//!
+//! The English catalog is built in. A deployment can ship a translation as a
+//! JSON object in the same shape and [`install`] it at startup, after which
+//! every diagnostic displays in that language:
+//!
+//! ```
+//! use cadenza_eval::messages::Catalog;
+//!
+//! let catalog = Catalog::from_json(r#"{ "E0001": "variable non définie : {name}" }"#).unwrap();
+//! assert_eq!(
+//!     catalog.format("E0001", &[("name", "x".into())]),
+//!     "variable non définie : x"
+//! );
+//! // Codes the translation leaves out use the English text
+//! assert_eq!(
+//!     catalog.format("E0015", &[("name", "x".into())]),
+//!     "use of moved value: x"
+//! );
+//! ```
+//!
+//! Some codes also have a help template, keyed by the code followed by
+//! `.help` (`"E0001.help": "did you mean `{suggestion}`?"`), shown below the
+//! message when the diagnostic has advice to give.
+//!
+//! Free-form details, such as the message of a syntax error, are passed
+//! through as a parameter and stay in English.
+
+use crate::{diagnostic::DiagnosticKind, interner::InternedString, map::Map};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// A catalog of diagnostic message templates, keyed by diagnostic code.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    messages: Map<String>,
+}
+
+impl Catalog {
+    /// Returns the built-in English catalog.
+    pub fn english() -> &'static Self {
+        static ENGLISH: OnceLock<Catalog> = OnceLock::new();
+        ENGLISH.get_or_init(|| {
+            let messages =
+                parse(include_str!("messages/en.json")).expect("the English catalog is valid JSON");
+            Self { messages }
+        })
+    }
+
+    /// Loads a catalog from a JSON object mapping diagnostic codes to
+    /// templates.
+    ///
+    /// # Errors
+    /// Returns an error if the JSON is malformed, or names a code or a
+    /// placeholder that the English catalog doesn't have.
+    pub fn from_json(json: &str) -> std::result::Result<Self, String> {
+        let messages = parse(json)?;
+        let english = Self::english();
+        for (code, template) in &messages {
+            let Some(reference) = english.messages.get(code) else {
+                return Err(format!("unknown diagnostic code {}", &**code));
+            };
+            let known = placeholders(reference)?;
+            for name in placeholders(template)? {
+                if !known.contains(&name) {
+                    return Err(format!(
+                        "{} has no parameter {{{name}}}; expected one of {}",
+                        &**code,
+                        known
+                            .iter()
+                            .map(|name| format!("{{{name}}}"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(Self { messages })
+    }
+
+    /// Returns the template for `code`, falling back to English.
+    pub fn template(&self, code: &str) -> &str {
+        let code = InternedString::from(code);
+        self.messages
+            .get(&code)
+            .or_else(|| Self::english().messages.get(&code))
+            .map_or("", String::as_str)
+    }
+
+    /// Returns the message for `code` with its placeholders replaced by
+    /// `params`. A placeholder without a parameter is left as written.
+    pub fn format(&self, code: &str, params: &[(&str, String)]) -> String {
+        let template = self.template(code);
+        let mut message = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            message.push_str(&rest[..i]);
+            let brace = &rest[i..];
+            if brace.starts_with("{{") || brace.starts_with("}}") {
+                message.push_str(&brace[..1]);
+                rest = &brace[2..];
+                continue;
+            }
+            let placeholder = brace
+                .strip_prefix('{')
+                .and_then(|inner| Some(&inner[..inner.find('}')?]));
+            match placeholder.and_then(|name| params.iter().find(|(param, _)| *param == name)) {
+                Some((name, value)) => {
+                    message.push_str(value);
+                    rest = &brace[name.len() + 2..];
+                }
+                None => {
+                    message.push_str(&brace[..1]);
+                    rest = &brace[1..];
+                }
+            }
+        }
+        message.push_str(rest);
+        message
+    }
+}
+
+/// Parses a JSON object of templates.
+fn parse(json: &str) -> std::result::Result<Map<String>, String> {
+    let messages: std::collections::BTreeMap<String, String> =
+        serde_json::from_str(json).map_err(|e| format!("invalid message catalog: {e}"))?;
+    Ok(messages
+        .into_iter()
+        .map(|(code, template)| (code.as_str().into(), template))
+        .collect())
+}
+
+/// Returns the names of the placeholders in `template`.
+fn placeholders(template: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        let brace = &rest[i..];
+        if brace.starts_with("{{") || brace.starts_with("}}") {
+            rest = &brace[2..];
+            continue;
+        }
+        let end = brace
+            .strip_prefix('{')
+            .and_then(|inner| inner.find('}'))
+            .ok_or_else(|| format!("unbalanced brace in {template:?}"))?;
+        names.push(&brace[1..end + 1]);
+        rest = &brace[end + 2..];
+    }
+    Ok(names)
+}
+
+static INSTALLED: RwLock<Option<Arc<Catalog>>> = RwLock::new(None);
+
+/// Makes `catalog` the catalog diagnostics display with, for the rest of the
+/// process.
+pub fn install(catalog: Catalog) {
+    *INSTALLED.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(catalog));
+}
+
+/// Returns the message for `kind` from the installed catalog, or from the
+/// English one if none is installed.
+pub fn message(kind: &DiagnosticKind) -> String {
+    format_installed(kind.code(), kind)
+}
+
+/// Returns the help for `kind` from the installed catalog, the template
+/// keyed by the kind's code followed by `.help`.
+pub(crate) fn help(kind: &DiagnosticKind) -> String {
+    format_installed(&format!("{}.help", kind.code()), kind)
+}
+
+fn format_installed(code: &str, kind: &DiagnosticKind) -> String {
+    let installed = INSTALLED.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let catalog = installed.as_deref().unwrap_or(Catalog::english());
+    catalog.format(code, &kind.params())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Catalog;
+    use crate::{DiagnosticKind, Type};
+
+    #[test]
+    fn english_catalog_interpolates_parameters() {
+        let kinds = [
+            DiagnosticKind::TypeError {
+                expected: Type::Integer,
+                actual: Type::String,
+            },
+            DiagnosticKind::OutOfFuel { limit: 10 },
+            DiagnosticKind::DuplicateSymbol {
+                name: "f".into(),
+                module: "a".into(),
+            },
+            DiagnosticKind::OverlappingImpl {
+                trait_name: "Eq".into(),
+                for_type: Type::Integer,
+                existing: Type::Unknown,
+            },
+        ];
+        let messages: Vec<String> = kinds
+            .iter()
+            .map(|kind| Catalog::english().format(kind.code(), &kind.params()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "type error: expected integer, got string",
+                "out of fuel: evaluation exceeded 10 steps",
+                "duplicate symbol: f is already defined in module a",
+                "conflicting implementations of trait Eq: integer overlaps unknown",
+            ]
+        );
+    }
+
+    #[test]
+    fn translations_are_checked_against_the_english_catalog() {
+        let catalog = Catalog::from_json(
+            r#"{ "E0003": "{{arité}} : {actual} arguments au lieu de {expected}" }"#,
+        )
+        .unwrap();
+        let kind = DiagnosticKind::ArityError {
+            expected: 2,
+            actual: 3,
+        };
+        assert_eq!(
+            catalog.format(kind.code(), &kind.params()),
+            "{arité} : 3 arguments au lieu de 2"
+        );
+
+        let unknown_code = Catalog::from_json(r#"{ "E9999": "?" }"#).unwrap_err();
+        assert_eq!(unknown_code, "unknown diagnostic code E9999");
+        let unknown_param = Catalog::from_json(r#"{ "E0001": "{nom} ?" }"#).unwrap_err();
+        assert_eq!(
+            unknown_param,
+            "E0001 has no parameter {nom}; expected one of {name}"
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Multi-file compilation units.
+//!
+//! A [`ModuleGraph`] holds the source of several named modules. Each module
+//! lists its dependencies with top-level `import` forms:
+//!
+//! ```cadenza
+//! # geometry.cdz
+//! fn square x = x * x
+//!
+//! # main.cdz
+//! import geometry
+//! fn area w = square w
+//! ```
+//!
+//! [`ModuleGraph::eval`] evaluates the modules in dependency order into a
+//! single [`Env`] and [`Compiler`], so with IR enabled every function from
+//! every file lands in one linked [`IrModule`](crate::ir::IrModule) and calls
+//! across files resolve to the callee's function id.
+//!
+//! All modules share one namespace: a top-level function defined in two
+//! modules is reported as a duplicate symbol at the second definition.
+//! Diagnostics from a module are tagged with its name.
+//!
+//! A module can be written in any syntax with a [`Frontend`], such as SQL or
+//! Markdown (see [`ModuleGraph::add_with`]); imports work across syntaxes.
+
+use crate::{
+    compiler::Compiler,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic},
+    env::Env,
+    eval::extract_identifier,
+    interner::InternedString,
+    map::Map,
+    value::Value,
+};
+use cadenza_syntax::{
+    ast::{Expr, Root},
+    frontend::{Cadenza, Frontend},
+    span::Span,
+};
+use std::sync::Arc;
+
+/// A named module in a [`ModuleGraph`].
+#[derive(Debug, Clone)]
+pub struct ModuleSource {
+    /// The module name used by `import`.
+    pub name: InternedString,
+    /// The module's source text.
+    pub source: String,
+    /// The syntax the source is written in.
+    pub frontend: Arc<dyn Frontend>,
+    /// The modules this one imports, with the span of each name.
+    pub imports: Vec<(InternedString, Span)>,
+}
+
+/// A set of modules compiled together.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph {
+    modules: Vec<ModuleSource>,
+}
+
+impl ModuleGraph {
+    /// Creates an empty module graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a module, reading its imports from the source.
+    pub fn add(&mut self, name: impl Into<InternedString>, source: impl Into<String>) {
+        self.add_with(name, source, Arc::new(Cadenza));
+    }
+
+    /// Adds a module written in the syntax of `frontend`.
+    pub fn add_with(
+        &mut self,
+        name: impl Into<InternedString>,
+        source: impl Into<String>,
+        frontend: Arc<dyn Frontend>,
+    ) {
+        let source = source.into();
+        let imports = imports(&frontend.parse(&source).ast());
+        self.modules.push(ModuleSource {
+            name: name.into(),
+            source,
+            frontend,
+            imports,
+        });
+    }
+
+    /// Returns the modules in the order they were added.
+    pub fn modules(&self) -> &[ModuleSource] {
+        &self.modules
+    }
+
+    /// Returns the module named `name`.
+    pub fn get(&self, name: InternedString) -> Option<&ModuleSource> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+
+    /// Orders the modules so each comes after the modules it imports.
+    ///
+    /// Ties keep the order the modules were added in, so the result is
+    /// deterministic.
+    ///
+    /// # Errors
+    ///
+    /// Returns unresolved-import and import-cycle diagnostics.
+    pub fn resolve(&self) -> Result<Vec<&ModuleSource>, Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        for module in &self.modules {
+            for (import, span) in &module.imports {
+                if self.get(*import).is_none() {
+                    diagnostics.push(
+                        *Diagnostic::unresolved_import(*import)
+                            .with_span(*span)
+                            .with_file(module.name),
+                    );
+                }
+            }
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+
+        let mut order = Vec::with_capacity(self.modules.len());
+        let mut path = Vec::new();
+        for module in &self.modules {
+            if let Err(diagnostic) = self.visit(module, None, &mut path, &mut order) {
+                return Err(vec![*diagnostic]);
+            }
+        }
+        Ok(order)
+    }
+
+    /// Visits `module`, which `via` imports at the given span.
+    fn visit<'a>(
+        &'a self,
+        module: &'a ModuleSource,
+        via: Option<(InternedString, Span)>,
+        path: &mut Vec<InternedString>,
+        order: &mut Vec<&'a ModuleSource>,
+    ) -> Result<(), Box<Diagnostic>> {
+        if order.iter().any(|done| done.name == module.name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|name| *name == module.name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(module.name);
+            // Report the import that closes the cycle
+            let diagnostic = Diagnostic::import_cycle(&cycle);
+            return Err(match via {
+                Some((importer, span)) => diagnostic.with_file(importer).with_span(span),
+                None => diagnostic.with_file(module.name),
+            });
+        }
+
+        path.push(module.name);
+        for (import, span) in &module.imports {
+            if let Some(dependency) = self.get(*import) {
+                self.visit(dependency, Some((module.name, *span)), path, order)?;
+            }
+        }
+        path.pop();
+        order.push(module);
+        Ok(())
+    }
+
+    /// Evaluates every module in dependency order.
+    ///
+    /// Returns the results of each module's top-level expressions, in
+    /// evaluation order. Diagnostics are recorded in `compiler`; if the graph
+    /// doesn't resolve, nothing is evaluated, and once a module calls `exit`
+    /// or `abort`, no later module is.
+    pub fn eval(
+        &self,
+        env: &mut Env,
+        compiler: &mut Compiler,
+    ) -> Vec<(InternedString, Vec<Value>)> {
+        let order = match self.resolve() {
+            Ok(order) => order,
+            Err(diagnostics) => {
+                for diagnostic in diagnostics {
+                    compiler.record_diagnostic(diagnostic);
+                }
+                return Vec::new();
+            }
+        };
+
+        // The module that first defined each top-level function
+        let mut owners: Map<InternedString> = Map::default();
+        let mut results = Vec::with_capacity(order.len());
+
+        for module in order {
+            let _span = tracing::info_span!("module", file = %module.name).entered();
+            let parsed =
+                tracing::debug_span!("parse").in_scope(|| module.frontend.parse(&module.source));
+            if !parsed.errors.is_empty() {
+                for error in parsed.errors {
+                    let error = module.frontend.map_error(error);
+                    let diagnostic = *Box::<Diagnostic>::from(error);
+                    compiler.record_diagnostic(diagnostic.with_file(module.name));
+                }
+                continue;
+            }
+            let root = parsed.ast();
+            if let Some(coverage) = compiler.coverage_mut() {
+                coverage.add_file(module.name, &module.source, &root);
+            }
+
+            let first_diagnostic = compiler.num_diagnostics();
+            for FunctionDef { name, span, .. } in
+                root.items().filter_map(|item| function_definition(&item))
+            {
+                match owners.get(&name) {
+                    Some(owner) if *owner != module.name => {
+                        let diagnostic = Diagnostic::duplicate_symbol(name, *owner);
+                        compiler.record_diagnostic(*diagnostic.with_span(span));
+                    }
+                    Some(_) => {}
+                    None => {
+                        owners.insert(name, module.name);
+                    }
+                }
+            }
+
+            let values =
+                tracing::debug_span!("eval").in_scope(|| crate::eval(&root, env, compiler));
+            compiler.tag_diagnostics_from(first_diagnostic, module.name);
+            compiler.add_module(module.name);
+            results.push((module.name, values));
+            if compiler.exit_status().is_some() {
+                break;
+            }
+        }
+
+        results
+    }
+}
+
+/// Returns the modules named by the top-level `import` forms in `root`.
+fn imports(root: &Root) -> Vec<(InternedString, Span)> {
+    let mut imports = Vec::new();
+    for expr in root.items() {
+        let Expr::Apply(apply) = expr else {
+            continue;
+        };
+        let is_import = apply
+            .callee()
+            .and_then(|callee| extract_identifier(&callee))
+            .is_some_and(|id| &*id == "import");
+        if !is_import {
+            continue;
+        }
+        for arg in apply.all_arguments() {
+            if let Some(name) = extract_identifier(&arg) {
+                imports.push((name, arg.span()));
+            }
+        }
+    }
+    imports
+}
+
+/// A function definition (`fn name params... = body`).
+pub(crate) struct FunctionDef {
+    /// The function name.
+    pub name: InternedString,
+    /// The span of the name in the definition.
+    pub span: Span,
+    /// The parameter names.
+    pub params: Vec<InternedString>,
+    /// The function body.
+    pub body: Expr,
+}
+
+/// Returns the function defined by `expr`, if it is a function definition.
+pub(crate) fn function_definition(expr: &Expr) -> Option<FunctionDef> {
+    let Expr::Apply(apply) = expr else {
+        return None;
+    };
+    let callee = extract_identifier(&apply.callee()?)?;
+    let mut args = apply.all_arguments();
+    if &*callee != "=" || args.len() != 2 {
+        return None;
+    }
+    let body = args.pop()?;
+    let Expr::Apply(lhs) = &args[0] else {
+        return None;
+    };
+    if &*extract_identifier(&lhs.callee()?)? != "fn" {
+        return None;
+    }
+    let mut lhs_args = lhs.all_arguments().into_iter();
+    let name_expr = lhs_args.next()?;
+    let params = lhs_args
+        .map(|param| match param {
+            Expr::Ident(ident) => Some(ident.syntax().text().interned()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some(FunctionDef {
+        name: extract_identifier(&name_expr)?,
+        span: name_expr.span(),
+        params,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiagnosticKind;
+    use cadenza_syntax::parse::{Parse, ParseError, parse};
+
+    fn graph(modules: &[(&str, &str)]) -> ModuleGraph {
+        let mut graph = ModuleGraph::new();
+        for (name, source) in modules {
+            graph.add(*name, *source);
+        }
+        graph
+    }
+
+    #[test]
+    fn dependencies_are_evaluated_first() {
+        let graph = graph(&[
+            ("main", "import geometry\nfn area w = square w\narea 3\n"),
+            ("geometry", "fn square x = x * x\n"),
+        ]);
+        let order: Vec<&str> = graph
+            .resolve()
+            .unwrap()
+            .iter()
+            .map(|module| &*module.name)
+            .collect();
+        assert_eq!(order, vec!["geometry", "main"]);
+
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::with_ir();
+        let results = graph.eval(&mut env, &mut compiler);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results[1].1[2], Value::Integer(9));
+
+        let module = compiler.build_ir_module().unwrap();
+        let names: Vec<&str> = module.functions.iter().map(|f| &*f.name).collect();
+        assert_eq!(names, vec!["square", "area"]);
+    }
+
+    /// Cadenza with `def` for `fn`.
+    #[derive(Debug)]
+    struct Def;
+
+    impl Frontend for Def {
+        fn name(&self) -> &str {
+            "def"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["def"]
+        }
+
+        fn parse(&self, source: &str) -> Parse {
+            parse(&source.replace("def ", "fn "))
+        }
+
+        fn map_error(&self, error: ParseError) -> ParseError {
+            ParseError {
+                message: format!("def: {}", error.message),
+                ..error
+            }
+        }
+    }
+
+    #[test]
+    fn modules_can_be_written_in_other_syntaxes() {
+        let mut graph = graph(&[(
+            "main",
+            "import geometry
+square 4
+",
+        )]);
+        graph.add_with(
+            "geometry",
+            "def square x = x * x
+",
+            Arc::new(Def),
+        );
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = graph.eval(&mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results[1].1[1], Value::Integer(16));
+
+        let mut graph = ModuleGraph::new();
+        graph.add_with("broken", "def f x = (x\n", Arc::new(Def));
+        let mut compiler = Compiler::new();
+        graph.eval(&mut env, &mut compiler);
+        let message = compiler.diagnostics()[0].kind().to_string();
+        assert_eq!(message, "parse error: def: expected )");
+    }
+
+    #[test]
+    fn reports_unresolved_imports_and_cycles() {
+        let errors = graph(&[("main", "import missing\n")])
+            .resolve()
+            .unwrap_err();
+        assert!(matches!(
+            errors[0].kind(),
+            DiagnosticKind::UnresolvedImport(name) if &**name == "missing"
+        ));
+        assert_eq!(errors[0].file, Some("main".into()));
+
+        let errors = graph(&[("a", "import b\n"), ("b", "import a\n")])
+            .resolve()
+            .unwrap_err();
+        assert!(matches!(
+            errors[0].kind(),
+            DiagnosticKind::ImportCycle(cycle) if cycle == "a -> b -> a"
+        ));
+        assert_eq!(errors[0].file, Some("b".into()));
+        assert_eq!(errors[0].span, Some(Span::new(7, 8)));
+    }
+
+    #[test]
+    fn reports_duplicate_symbols() {
+        let graph = graph(&[
+            ("a", "fn helper x = x\n"),
+            ("b", "import a\nfn helper x = x + 1\n"),
+        ]);
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        graph.eval(&mut env, &mut compiler);
+
+        let diagnostic = &compiler.diagnostics()[0];
+        assert!(matches!(
+            diagnostic.kind(),
+            DiagnosticKind::DuplicateSymbol { name, module }
+                if &**name == "helper" && &**module == "a"
+        ));
+        assert_eq!(diagnostic.file, Some("b".into()));
+    }
+
+    #[test]
+    fn import_outside_a_graph_is_unresolved() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        crate::eval(&parse("import geometry\n").ast(), &mut env, &mut compiler);
+        assert!(matches!(
+            compiler.diagnostics()[0].kind(),
+            DiagnosticKind::UnresolvedImport(_)
+        ));
+    }
+}
@@ -0,0 +1,856 @@
+//! The numeric tower.
+//!
+//! The arithmetic operators and the ordering comparisons work on any number,
+//! through a small hierarchy of traits:
+//!
+//! - [`Num`] is a number: `+`, `-`, `*`, negation and ordering
+//! - [`Integral`] numbers divide with truncation (`/`) and have a remainder
+//!   (`%`)
+//! - [`Fractional`] numbers divide exactly (`/`)
+//!
+//! `Integer` is integral, and `Float`, [`Decimal`] and [`Quantity`] are
+//! fractional. An integer operation that overflows an `i64` is retried on
+//! [`BigInt`]s, and a big result that fits goes back to an `i64` (see
+//! [`crate::bigint`]). Two numbers of the same type combine; an integer and
+//! a float don't, as there are no implicit conversions. An integer next to a
+//! decimal is exact, so it combines as a decimal (see [`crate::decimal`]). A plain number combines with a quantity as a
+//! number of the quantity's unit for `+`, `-` and comparisons, and as a
+//! dimensionless factor for `*` and `/`.
+//!
+//! The operators also work on vectors and matrices, element by element,
+//! which [`crate::linalg`] handles before any of the numeric types.
+//!
+//! # Adding a numeric type
+//!
+//! A new type (a big integer, a decimal, a rational) gets a [`Value`]
+//! variant, implements [`Num`] and either [`Integral`] or [`Fractional`], and
+//! adds a case to [`Number`]. The operators then handle it like the
+//! built-in types, and it must pass the conformance suite in this module's
+//! tests, which checks the laws the operators rely on.
+//!
+//! # Inference defaulting
+//!
+//! An operator's signature lists one overload per numeric type, in the order
+//! of [`DEFAULTS`] (see [`overloads`]). Type inference picks the first
+//! overload its arguments fit, so an operation on numbers of unknown type
+//! defaults to `Integer`, then `Float`, then `Decimal`.
+
+use crate::{
+    bigint::BigInt,
+    decimal::Decimal,
+    diagnostic::{Diagnostic, Result},
+    linalg,
+    unit::{DerivedDimension, Unit},
+    value::{Type, Value},
+};
+use std::cmp::Ordering;
+
+/// The numeric types an overloaded operator is inferred to operate on, in
+/// order of preference.
+pub const DEFAULTS: [Type; 3] = [Type::Integer, Type::Float, Type::Decimal];
+
+/// Returns the signature of an operator overloaded over the numeric types,
+/// with `overload` giving the signature for each of [`DEFAULTS`].
+pub fn overloads(overload: impl Fn(Type) -> Type) -> Type {
+    Type::union(DEFAULTS.into_iter().map(overload).collect())
+}
+
+/// A number: addition, subtraction, multiplication, negation and ordering.
+pub trait Num: Clone + Sized {
+    /// Returns the number in `value`, if it holds one of this type.
+    fn from_value(value: &Value) -> Option<Self>;
+
+    /// Converts the number to a value.
+    fn into_value(self) -> Value;
+
+    /// Returns the integer `n` as a number of this type.
+    fn from_integer(n: i64) -> Self;
+
+    fn add(&self, rhs: &Self) -> Result<Self>;
+
+    fn sub(&self, rhs: &Self) -> Result<Self>;
+
+    fn mul(&self, rhs: &Self) -> Result<Self>;
+
+    fn neg(&self) -> Result<Self>;
+
+    /// Orders two numbers, or returns an error if they can't be compared.
+    fn compare(&self, rhs: &Self) -> Result<Ordering>;
+}
+
+/// A number that divides with truncation, with a remainder.
+pub trait Integral: Num {
+    /// Divides, rounding toward zero.
+    fn quot(&self, rhs: &Self) -> Result<Self>;
+
+    /// The remainder of [`Integral::quot`], with the sign of `self`.
+    fn rem(&self, rhs: &Self) -> Result<Self>;
+}
+
+/// A number that divides exactly.
+pub trait Fractional: Num {
+    fn div(&self, rhs: &Self) -> Result<Self>;
+}
+
+impl Num for i64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Integer(self)
+    }
+
+    fn from_integer(n: i64) -> Self {
+        n
+    }
+
+    fn add(&self, rhs: &Self) -> Result<Self> {
+        self.checked_add(*rhs)
+            .ok_or_else(|| overflow(self, "+", rhs))
+    }
+
+    fn sub(&self, rhs: &Self) -> Result<Self> {
+        self.checked_sub(*rhs)
+            .ok_or_else(|| overflow(self, "-", rhs))
+    }
+
+    fn mul(&self, rhs: &Self) -> Result<Self> {
+        self.checked_mul(*rhs)
+            .ok_or_else(|| overflow(self, "*", rhs))
+    }
+
+    fn neg(&self) -> Result<Self> {
+        self.checked_neg()
+            .ok_or_else(|| Diagnostic::syntax(format!("integer overflow: -{self}")))
+    }
+
+    fn compare(&self, rhs: &Self) -> Result<Ordering> {
+        Ok(self.cmp(rhs))
+    }
+}
+
+impl Integral for i64 {
+    fn quot(&self, rhs: &Self) -> Result<Self> {
+        if *rhs == 0 {
+            return Err(Diagnostic::syntax("division by zero"));
+        }
+        self.checked_div(*rhs)
+            .ok_or_else(|| overflow(self, "/", rhs))
+    }
+
+    fn rem(&self, rhs: &Self) -> Result<Self> {
+        if *rhs == 0 {
+            return Err(Diagnostic::syntax("division by zero"));
+        }
+        self.checked_rem(*rhs)
+            .ok_or_else(|| overflow(self, "%", rhs))
+    }
+}
+
+/// The error for an integer operation whose result doesn't fit.
+fn overflow(lhs: &i64, op: &str, rhs: &i64) -> Box<Diagnostic> {
+    Diagnostic::syntax(format!("integer overflow: {lhs} {op} {rhs}"))
+}
+
+impl Num for BigInt {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::BigInt(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    /// Demotes the number to an `i64` if it fits in one.
+    fn into_value(self) -> Value {
+        match self.to_i64() {
+            Some(n) => Value::Integer(n),
+            None => Value::BigInt(self),
+        }
+    }
+
+    fn from_integer(n: i64) -> Self {
+        n.into()
+    }
+
+    fn add(&self, rhs: &Self) -> Result<Self> {
+        Ok(self + rhs)
+    }
+
+    fn sub(&self, rhs: &Self) -> Result<Self> {
+        Ok(self - rhs)
+    }
+
+    fn mul(&self, rhs: &Self) -> Result<Self> {
+        Ok(self * rhs)
+    }
+
+    fn neg(&self) -> Result<Self> {
+        Ok(-self)
+    }
+
+    fn compare(&self, rhs: &Self) -> Result<Ordering> {
+        Ok(self.cmp(rhs))
+    }
+}
+
+impl Integral for BigInt {
+    fn quot(&self, rhs: &Self) -> Result<Self> {
+        let (quotient, _) = self
+            .div_rem(rhs)
+            .ok_or_else(|| Diagnostic::syntax("division by zero"))?;
+        Ok(quotient)
+    }
+
+    fn rem(&self, rhs: &Self) -> Result<Self> {
+        let (_, remainder) = self
+            .div_rem(rhs)
+            .ok_or_else(|| Diagnostic::syntax("division by zero"))?;
+        Ok(remainder)
+    }
+}
+
+impl Num for f64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Float(self)
+    }
+
+    fn from_integer(n: i64) -> Self {
+        n as f64
+    }
+
+    fn add(&self, rhs: &Self) -> Result<Self> {
+        Ok(self + rhs)
+    }
+
+    fn sub(&self, rhs: &Self) -> Result<Self> {
+        Ok(self - rhs)
+    }
+
+    fn mul(&self, rhs: &Self) -> Result<Self> {
+        Ok(self * rhs)
+    }
+
+    fn neg(&self) -> Result<Self> {
+        Ok(-self)
+    }
+
+    fn compare(&self, rhs: &Self) -> Result<Ordering> {
+        self.partial_cmp(rhs)
+            .ok_or_else(|| Diagnostic::syntax("cannot compare NaN values"))
+    }
+}
+
+impl Fractional for f64 {
+    fn div(&self, rhs: &Self) -> Result<Self> {
+        if *rhs == 0.0 {
+            return Err(Diagnostic::syntax("division by zero"));
+        }
+        Ok(self / rhs)
+    }
+}
+
+impl Num for Decimal {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Decimal(d) => Some(d.clone()),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Decimal(self)
+    }
+
+    fn from_integer(n: i64) -> Self {
+        n.into()
+    }
+
+    fn add(&self, rhs: &Self) -> Result<Self> {
+        let numerator =
+            &(self.numerator() * rhs.denominator()) + &(rhs.numerator() * self.denominator());
+        Ok(fraction(numerator, self.denominator() * rhs.denominator()))
+    }
+
+    fn sub(&self, rhs: &Self) -> Result<Self> {
+        self.add(&rhs.neg()?)
+    }
+
+    fn mul(&self, rhs: &Self) -> Result<Self> {
+        Ok(fraction(
+            self.numerator() * rhs.numerator(),
+            self.denominator() * rhs.denominator(),
+        ))
+    }
+
+    fn neg(&self) -> Result<Self> {
+        Ok(fraction(-self.numerator(), self.denominator().clone()))
+    }
+
+    fn compare(&self, rhs: &Self) -> Result<Ordering> {
+        Ok(self.cmp(rhs))
+    }
+}
+
+impl Fractional for Decimal {
+    fn div(&self, rhs: &Self) -> Result<Self> {
+        Decimal::new(
+            self.numerator() * rhs.denominator(),
+            self.denominator() * rhs.numerator(),
+        )
+        .ok_or_else(|| Diagnostic::syntax("division by zero"))
+    }
+}
+
+/// Returns the decimal `numerator / denominator` for a denominator that is
+/// a product of nonzero denominators.
+fn fraction(numerator: BigInt, denominator: BigInt) -> Decimal {
+    Decimal::new(numerator, denominator).expect("the denominator isn't zero")
+}
+
+/// A number with a unit of measure, such as `3 meter`.
+///
+/// Quantities of the same dimension add, subtract and compare after
+/// converting the right-hand side to the left-hand side's unit. Multiplying
+/// and dividing combine their dimensions, and a result without a dimension
+/// is a plain float.
+#[derive(Debug, Clone)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+    pub dimension: DerivedDimension,
+}
+
+impl Quantity {
+    /// Returns `value` as a dimensionless quantity.
+    fn scalar(value: f64) -> Self {
+        Self {
+            value,
+            unit: Unit::base("1".into()),
+            dimension: DerivedDimension {
+                numerator: vec![],
+                denominator: vec![],
+            },
+        }
+    }
+
+    /// Returns the plain number `value` as the quantity it stands for when
+    /// combined with `self` by `op`: a number of `self`'s unit for `+`, `-`
+    /// and comparisons (`None`), and a dimensionless factor otherwise.
+    fn lift(&self, op: Option<Op>, value: f64) -> Self {
+        match op {
+            None | Some(Op::Add | Op::Sub) => Self {
+                value,
+                unit: self.unit.clone(),
+                dimension: self.dimension.clone(),
+            },
+            Some(Op::Mul | Op::Div | Op::Rem) => Self::scalar(value),
+        }
+    }
+
+    /// Returns `rhs`'s value in `self`'s unit, or an error naming `verb` if
+    /// their dimensions differ.
+    fn converted(&self, rhs: &Self, verb: &str) -> Result<f64> {
+        if self.unit.dimension != rhs.unit.dimension {
+            return Err(Diagnostic::syntax(format!(
+                "cannot {verb} quantities with incompatible dimensions: {} and {}",
+                self.unit.name, rhs.unit.name
+            )));
+        }
+        Ok(rhs.value * (rhs.unit.scale / self.unit.scale))
+    }
+
+    /// Returns the product (`Op::Mul`) or quotient (`Op::Div`) of `self` and
+    /// `rhs`, keeping the unit of whichever of them gave the dimension if only
+    /// one did, and otherwise in the base unit of the new dimension.
+    fn combined(&self, rhs: &Self, op: Op) -> Self {
+        let (dimension, apply): (_, fn(f64, f64) -> f64) = match op {
+            Op::Div => (self.dimension.divide(&rhs.dimension), |a, b| a / b),
+            _ => (self.dimension.multiply(&rhs.dimension), |a, b| a * b),
+        };
+        let (value, unit) = if rhs.dimension.is_dimensionless() {
+            (apply(self.value, rhs.value), self.unit.clone())
+        } else if self.dimension.is_dimensionless() && dimension == rhs.dimension {
+            (apply(self.value, rhs.value), rhs.unit.clone())
+        } else {
+            let value = apply(self.value * self.unit.scale, rhs.value * rhs.unit.scale);
+            (value, Unit::base(dimension.to_string().as_str().into()))
+        };
+        Self {
+            value,
+            unit,
+            dimension,
+        }
+    }
+}
+
+impl Num for Quantity {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Quantity {
+                value,
+                unit,
+                dimension,
+            } => Some(Self {
+                value: *value,
+                unit: unit.clone(),
+                dimension: dimension.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        if self.dimension.is_dimensionless() {
+            return Value::Float(self.value);
+        }
+        Value::Quantity {
+            value: self.value,
+            unit: self.unit,
+            dimension: self.dimension,
+        }
+    }
+
+    fn from_integer(n: i64) -> Self {
+        Self::scalar(n as f64)
+    }
+
+    fn add(&self, rhs: &Self) -> Result<Self> {
+        let rhs = self.converted(rhs, "add")?;
+        Ok(Self {
+            value: self.value + rhs,
+            ..self.clone()
+        })
+    }
+
+    fn sub(&self, rhs: &Self) -> Result<Self> {
+        let rhs = self.converted(rhs, "subtract")?;
+        Ok(Self {
+            value: self.value - rhs,
+            ..self.clone()
+        })
+    }
+
+    fn mul(&self, rhs: &Self) -> Result<Self> {
+        Ok(self.combined(rhs, Op::Mul))
+    }
+
+    fn neg(&self) -> Result<Self> {
+        Ok(Self {
+            value: -self.value,
+            ..self.clone()
+        })
+    }
+
+    fn compare(&self, rhs: &Self) -> Result<Ordering> {
+        let rhs = self.converted(rhs, "compare")?;
+        self.value.compare(&rhs)
+    }
+}
+
+impl Fractional for Quantity {
+    fn div(&self, rhs: &Self) -> Result<Self> {
+        if rhs.value == 0.0 {
+            return Err(Diagnostic::syntax("division by zero"));
+        }
+        Ok(self.combined(rhs, Op::Div))
+    }
+}
+
+/// A binary arithmetic operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// A number of any type in the tower.
+enum Number {
+    Integer(i64),
+    BigInt(BigInt),
+    Float(f64),
+    Decimal(Decimal),
+    Quantity(Quantity),
+}
+
+impl Number {
+    /// Returns the number in `value`, or `None` if it isn't one.
+    fn of(value: &Value) -> Option<Self> {
+        None.or_else(|| i64::from_value(value).map(Number::Integer))
+            .or_else(|| BigInt::from_value(value).map(Number::BigInt))
+            .or_else(|| f64::from_value(value).map(Number::Float))
+            .or_else(|| Decimal::from_value(value).map(Number::Decimal))
+            .or_else(|| Quantity::from_value(value).map(Number::Quantity))
+    }
+
+    /// Returns the plain number as a float, or `None` for a quantity.
+    fn scalar(&self) -> Option<f64> {
+        match self {
+            Number::Integer(n) => Some(*n as f64),
+            Number::BigInt(n) => Some(n.to_f64()),
+            Number::Float(f) => Some(*f),
+            Number::Decimal(d) => Some(d.to_f64()),
+            Number::Quantity(_) => None,
+        }
+    }
+}
+
+/// Returns the numbers `lhs` and `rhs` hold, with a plain number next to a
+/// quantity lifted to one for `op` (see [`Quantity::lift`]), an `i64`
+/// next to a big integer lifted to one, and an integer next to a decimal
+/// lifted to one, or an error if they can't be combined.
+fn operands(lhs: &Value, rhs: &Value, op: Option<Op>) -> Result<(Number, Number)> {
+    let expected = || Type::union(DEFAULTS.to_vec());
+    let a = Number::of(lhs).ok_or_else(|| Diagnostic::type_error(expected(), lhs.type_of()))?;
+    let b = Number::of(rhs).ok_or_else(|| Diagnostic::type_error(expected(), rhs.type_of()))?;
+    match (a, b) {
+        (a @ Number::Quantity(_), b @ Number::Quantity(_)) => Ok((a, b)),
+        (Number::Quantity(q), n) => {
+            let lifted = q.lift(op, n.scalar().expect("only quantities aren't scalars"));
+            Ok((Number::Quantity(q), Number::Quantity(lifted)))
+        }
+        (n, Number::Quantity(q)) => {
+            let lifted = q.lift(op, n.scalar().expect("only quantities aren't scalars"));
+            Ok((Number::Quantity(lifted), Number::Quantity(q)))
+        }
+        (Number::Integer(a), b @ Number::BigInt(_)) => Ok((Number::BigInt(a.into()), b)),
+        (a @ Number::BigInt(_), Number::Integer(b)) => Ok((a, Number::BigInt(b.into()))),
+        (Number::Integer(a), b @ Number::Decimal(_)) => Ok((Number::Decimal(a.into()), b)),
+        (Number::BigInt(a), b @ Number::Decimal(_)) => Ok((Number::Decimal(a.into()), b)),
+        (a @ Number::Decimal(_), Number::Integer(b)) => Ok((a, Number::Decimal(b.into()))),
+        (a @ Number::Decimal(_), Number::BigInt(b)) => Ok((a, Number::Decimal(b.into()))),
+        (a, b) if std::mem::discriminant(&a) == std::mem::discriminant(&b) => Ok((a, b)),
+        _ => Err(Diagnostic::type_error(lhs.type_of(), rhs.type_of())),
+    }
+}
+
+/// Applies the arithmetic operator `op` to two numbers.
+pub fn arithmetic(op: Op, lhs: &Value, rhs: &Value) -> Result<Value> {
+    if let Some(result) = linalg::arithmetic(op, lhs, rhs) {
+        return result;
+    }
+    match operands(lhs, rhs, Some(op))? {
+        // An overflow is retried on big integers; any other error, such as
+        // a division by zero, fails the same way again
+        (Number::Integer(a), Number::Integer(b)) => {
+            integral(op, &a, &b).or_else(|_| integral(op, &BigInt::from(a), &BigInt::from(b)))
+        }
+        (Number::BigInt(a), Number::BigInt(b)) => integral(op, &a, &b),
+        (Number::Float(a), Number::Float(b)) => fractional(op, &a, &b, lhs),
+        (Number::Decimal(a), Number::Decimal(b)) => fractional(op, &a, &b, lhs),
+        (Number::Quantity(a), Number::Quantity(b)) => fractional(op, &a, &b, lhs),
+        _ => unreachable!("operands pairs numbers of the same type"),
+    }
+}
+
+fn integral<T: Integral>(op: Op, a: &T, b: &T) -> Result<Value> {
+    let result = match op {
+        Op::Add => a.add(b),
+        Op::Sub => a.sub(b),
+        Op::Mul => a.mul(b),
+        Op::Div => a.quot(b),
+        Op::Rem => a.rem(b),
+    };
+    result.map(Num::into_value)
+}
+
+fn fractional<T: Fractional>(op: Op, a: &T, b: &T, lhs: &Value) -> Result<Value> {
+    let result = match op {
+        Op::Add => a.add(b),
+        Op::Sub => a.sub(b),
+        Op::Mul => a.mul(b),
+        Op::Div => a.div(b),
+        Op::Rem => return Err(Diagnostic::type_error(Type::Integer, lhs.type_of())),
+    };
+    result.map(Num::into_value)
+}
+
+/// Negates a number.
+pub fn negate(value: &Value) -> Result<Value> {
+    if let Some(result) = linalg::negate(value) {
+        return result;
+    }
+    let expected = || Type::union(DEFAULTS.to_vec());
+    match Number::of(value).ok_or_else(|| Diagnostic::type_error(expected(), value.type_of()))? {
+        Number::Integer(n) => match n.neg() {
+            Ok(n) => Ok(n.into_value()),
+            Err(_) => BigInt::from(n).neg().map(Num::into_value),
+        },
+        Number::BigInt(n) => n.neg().map(Num::into_value),
+        Number::Float(f) => f.neg().map(Num::into_value),
+        Number::Decimal(d) => d.neg().map(Num::into_value),
+        Number::Quantity(q) => q.neg().map(Num::into_value),
+    }
+}
+
+/// Orders two numbers of the same type.
+///
+/// # Errors
+/// Returns an error if either isn't a number, they are numbers of different
+/// types, or they don't have an order (NaN, or quantities of different
+/// dimensions).
+pub fn compare(lhs: &Value, rhs: &Value) -> Result<Ordering> {
+    if Number::of(lhs).is_none() || Number::of(rhs).is_none() {
+        let (type_a, type_b) = (lhs.type_of(), rhs.type_of());
+        if type_a != type_b {
+            return Err(Diagnostic::type_error(type_a, type_b));
+        }
+        return Err(Diagnostic::syntax(format!(
+            "cannot compare values of type {type_a}"
+        )));
+    }
+    match operands(lhs, rhs, None)? {
+        (Number::Integer(a), Number::Integer(b)) => a.compare(&b),
+        (Number::BigInt(a), Number::BigInt(b)) => a.compare(&b),
+        (Number::Float(a), Number::Float(b)) => a.compare(&b),
+        (Number::Decimal(a), Number::Decimal(b)) => a.compare(&b),
+        (Number::Quantity(a), Number::Quantity(b)) => a.compare(&b),
+        _ => unreachable!("operands pairs numbers of the same type"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit::Dimension;
+
+    /// The laws every numeric type must satisfy, checked over `samples`,
+    /// which should include zero, one and a negative number.
+    ///
+    /// Arithmetic must be exact on the samples: pick values a float
+    /// represents exactly.
+    mod conformance {
+        use super::*;
+
+        fn eq<T: Num>(a: &T, b: &T) -> bool {
+            a.compare(b).ok() == Some(Ordering::Equal)
+        }
+
+        pub fn num<T: Num + std::fmt::Debug>(samples: &[T]) {
+            let zero = T::from_integer(0);
+            let one = T::from_integer(1);
+            for a in samples {
+                assert!(eq(&a.add(&zero).unwrap(), a), "{a:?} + 0");
+                assert!(eq(&a.mul(&one).unwrap(), a), "{a:?} * 1");
+                assert!(eq(&a.sub(a).unwrap(), &zero), "{a:?} - {a:?}");
+                assert!(eq(&a.neg().unwrap().neg().unwrap(), a), "--{a:?}");
+                assert!(
+                    eq(&a.add(&a.neg().unwrap()).unwrap(), &zero),
+                    "{a:?} + -{a:?}"
+                );
+                assert_eq!(a.compare(a).ok(), Some(Ordering::Equal), "{a:?} <=> {a:?}");
+                for b in samples {
+                    let ab = a.add(b).unwrap();
+                    assert!(eq(&ab, &b.add(a).unwrap()), "{a:?} + {b:?} commutes");
+                    assert!(eq(&a.mul(b).unwrap(), &b.mul(a).unwrap()), "{a:?} * {b:?}");
+                    assert!(eq(&ab.sub(b).unwrap(), a), "{a:?} + {b:?} - {b:?}");
+                    assert_eq!(
+                        a.compare(b).unwrap(),
+                        b.compare(a).unwrap().reverse(),
+                        "{a:?} <=> {b:?} is antisymmetric"
+                    );
+                    for c in samples {
+                        let left = ab.add(c).unwrap();
+                        let right = a.add(&b.add(c).unwrap()).unwrap();
+                        assert!(eq(&left, &right), "({a:?} + {b:?}) + {c:?}");
+                        let product = a.mul(&b.add(c).unwrap()).unwrap();
+                        let sum = a.mul(b).unwrap().add(&a.mul(c).unwrap()).unwrap();
+                        assert!(eq(&product, &sum), "{a:?} * ({b:?} + {c:?})");
+                    }
+                }
+            }
+        }
+
+        pub fn integral<T: Integral + std::fmt::Debug>(samples: &[T]) {
+            num(samples);
+            let zero = T::from_integer(0);
+            for a in samples {
+                assert!(a.quot(&zero).is_err(), "{a:?} / 0");
+                for b in samples.iter().filter(|b| !eq(*b, &zero)) {
+                    let q = a.quot(b).unwrap();
+                    let r = a.rem(b).unwrap();
+                    let back = b.mul(&q).unwrap().add(&r).unwrap();
+                    assert!(eq(&back, a), "{b:?} * ({a:?} / {b:?}) + {a:?} % {b:?}");
+                }
+            }
+        }
+
+        pub fn fractional<T: Fractional + std::fmt::Debug>(samples: &[T]) {
+            num(samples);
+            let zero = T::from_integer(0);
+            let one = T::from_integer(1);
+            for a in samples {
+                assert!(a.div(&zero).is_err(), "{a:?} / 0");
+                for b in samples.iter().filter(|b| !eq(*b, &zero)) {
+                    let back = a.div(b).unwrap().mul(b).unwrap();
+                    assert!(eq(&back, a), "{a:?} / {b:?} * {b:?}");
+                }
+                if !eq(a, &zero) {
+                    assert!(eq(&a.div(a).unwrap(), &one), "{a:?} / {a:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integers_conform() {
+        conformance::integral(&[0i64, 1, -1, 7, -12, 1000]);
+    }
+
+    #[test]
+    fn big_integers_conform() {
+        let samples = [0, 1, -1, 7, i64::MAX, i64::MIN].map(BigInt::from);
+        let huge = BigInt::parse("-123456789012345678901234567890", 10).unwrap();
+        conformance::integral(&[&samples[..], &[huge]].concat());
+    }
+
+    #[test]
+    fn floats_conform() {
+        conformance::fractional(&[0.0f64, 1.0, -1.0, 0.5, 4.0, -2.25]);
+    }
+
+    #[test]
+    fn decimals_conform() {
+        let third = Decimal::new(1.into(), 3.into()).unwrap();
+        let samples = ["0", "1", "-1", "0.5", "-2.25", "0.1"].map(|s| Decimal::parse(s).unwrap());
+        conformance::fractional(&[&samples[..], &[third]].concat());
+    }
+
+    #[test]
+    fn quantities_conform() {
+        // Dimensionless quantities, so that products keep the dimension
+        let samples = [0.0, 1.0, -1.0, 0.5, 4.0, -2.25].map(Quantity::scalar);
+        conformance::fractional(&samples);
+    }
+
+    fn meters(value: f64) -> Value {
+        let unit = Unit::base("meter".into());
+        Value::Quantity {
+            value,
+            dimension: DerivedDimension::from_dimension(unit.dimension),
+            unit,
+        }
+    }
+
+    #[test]
+    fn combines_numbers_of_the_same_type() {
+        let add = |a, b| arithmetic(Op::Add, &a, &b);
+        assert_eq!(
+            add(Value::Integer(2), Value::Integer(3)).ok(),
+            Some(Value::Integer(5))
+        );
+        assert_eq!(
+            add(Value::Float(0.5), Value::Float(2.0)).ok(),
+            Some(Value::Float(2.5))
+        );
+        assert!(add(Value::Integer(1), Value::Float(1.0)).is_err());
+        assert_eq!(
+            add(Value::Integer(i64::MAX), Value::Integer(1)).ok(),
+            BigInt::parse("9223372036854775808", 10).map(Value::BigInt)
+        );
+        assert_eq!(
+            arithmetic(Op::Rem, &Value::Integer(-7), &Value::Integer(3)).ok(),
+            Some(Value::Integer(-1))
+        );
+        assert!(arithmetic(Op::Rem, &Value::Float(7.0), &Value::Float(3.0)).is_err());
+    }
+
+    #[test]
+    fn promotes_integers_that_overflow() {
+        let big = arithmetic(Op::Mul, &Value::Integer(i64::MIN), &Value::Integer(-1)).unwrap();
+        assert_eq!(big.to_string(), "9223372036854775808");
+        assert_eq!(big.type_of(), Type::Integer);
+        assert_eq!(
+            arithmetic(Op::Sub, &big, &Value::Integer(1)).ok(),
+            Some(Value::Integer(i64::MAX))
+        );
+        assert_eq!(negate(&big).ok(), Some(Value::Integer(i64::MIN)));
+        assert_eq!(negate(&Value::Integer(i64::MIN)).ok().as_ref(), Some(&big));
+        assert_eq!(
+            compare(&Value::Integer(i64::MAX), &big).ok(),
+            Some(Ordering::Less)
+        );
+        assert!(arithmetic(Op::Div, &big, &Value::Integer(0)).is_err());
+        assert!(arithmetic(Op::Add, &big, &Value::Float(1.0)).is_err());
+    }
+
+    #[test]
+    fn decimals_are_exact() {
+        let decimal = |s| Value::Decimal(Decimal::parse(s).unwrap());
+        assert_eq!(
+            arithmetic(Op::Add, &decimal("0.1"), &decimal("0.2")).ok(),
+            Some(decimal("0.3"))
+        );
+        assert_eq!(
+            arithmetic(Op::Mul, &Value::Integer(3), &decimal("1.5")).ok(),
+            Some(decimal("4.5"))
+        );
+        let third = arithmetic(Op::Div, &decimal("1"), &Value::Integer(3)).unwrap();
+        assert_eq!(third.to_string(), "1/3");
+        assert_eq!(
+            arithmetic(Op::Mul, &third, &Value::Integer(3)).ok(),
+            Some(decimal("1"))
+        );
+        assert_eq!(negate(&decimal("2.5")).ok(), Some(decimal("-2.5")));
+        assert_eq!(
+            compare(&third, &decimal("0.3")).ok(),
+            Some(Ordering::Greater)
+        );
+        assert!(arithmetic(Op::Add, &decimal("1"), &Value::Float(1.0)).is_err());
+        assert!(arithmetic(Op::Rem, &decimal("1"), &decimal("1")).is_err());
+        assert!(arithmetic(Op::Div, &decimal("1"), &decimal("0")).is_err());
+    }
+
+    #[test]
+    fn lifts_plain_numbers_next_to_quantities() {
+        assert_eq!(
+            arithmetic(Op::Add, &meters(2.0), &Value::Integer(1)).ok(),
+            Some(meters(3.0))
+        );
+        assert_eq!(
+            arithmetic(Op::Mul, &Value::Float(2.0), &meters(1.5)).ok(),
+            Some(meters(3.0))
+        );
+        assert_eq!(
+            arithmetic(Op::Div, &meters(3.0), &meters(1.5)).ok(),
+            Some(Value::Float(2.0))
+        );
+        assert_eq!(
+            compare(&meters(2.0), &Value::Integer(3)).ok(),
+            Some(Ordering::Less)
+        );
+
+        let seconds = Value::Quantity {
+            value: 1.0,
+            unit: Unit::base("second".into()),
+            dimension: DerivedDimension::from_dimension(Dimension::new("second".into())),
+        };
+        assert!(compare(&meters(1.0), &seconds).is_err());
+    }
+
+    #[test]
+    fn overloads_list_the_defaults_first() {
+        let ty = overloads(|ty| Type::function(vec![ty.clone()], ty));
+        assert_eq!(
+            ty,
+            Type::union(vec![
+                Type::function(vec![Type::Integer], Type::Integer),
+                Type::function(vec![Type::Float], Type::Float),
+                Type::function(vec![Type::Decimal], Type::Decimal),
+            ])
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Compile options.
+//!
+//! [`CompileOptions`] collects the settings that affect what a build produces:
+//! the optimization level, the code generation target, enabled feature flags,
+//! how warnings are treated, the evaluation fuel budget, how deeply calls may
+//! nest, and whether the prelude is loaded. The same value is handed to the evaluator (through
+//! [`Compiler::with_options`](crate::Compiler::with_options) and
+//! [`Env::with_options`](crate::Env::with_options)), to IR optimization, and to
+//! WASM code generation ([`generate_wasm`](crate::ir::generate_wasm)).
+//!
+//! Feature flags turn on behavior that isn't the default. The
+//! [`DETERMINISTIC_FLOATS`](CompileOptions::DETERMINISTIC_FLOATS) feature makes
+//! float results bit-identical on every host, for outputs such as G-code that
+//! are cached and verified by their bytes.
+//!
+//! The defaults match the behavior before options existed: no optimization,
+//! warnings reported but not fatal, unlimited fuel, and the prelude loaded.
+//! Calls nest at most [`DEFAULT_RECURSION_LIMIT`](CompileOptions::DEFAULT_RECURSION_LIMIT)
+//! deep, which used to be bounded by the thread's stack instead.
+//!
+//! Options serialize to a canonical JSON string with [`CompileOptions::to_metadata`],
+//! which is embedded in generated WASM so a build can be reproduced from its
+//! output.
+
+use serde_json::{Value as Json, json};
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+/// How aggressively IR is optimized before code generation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    /// No optimization.
+    #[default]
+    O0,
+    /// Constant folding, dead code elimination and constant pooling.
+    O1,
+    /// Every optimization pass, including common subexpression elimination
+    /// and constant argument propagation.
+    O2,
+}
+
+impl OptLevel {
+    /// All optimization levels.
+    pub const ALL: [OptLevel; 3] = [OptLevel::O0, OptLevel::O1, OptLevel::O2];
+
+    /// Returns the name used on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OptLevel::O0 => "0",
+            OptLevel::O1 => "1",
+            OptLevel::O2 => "2",
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OptLevel::ALL
+            .into_iter()
+            .find(|level| level.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown optimization level '{s}', expected one of: {}",
+                    OptLevel::ALL.map(OptLevel::as_str).join(", ")
+                )
+            })
+    }
+}
+
+/// The code generation target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// WebAssembly with the GC proposal.
+    #[default]
+    Wasm,
+}
+
+impl Target {
+    /// All targets.
+    pub const ALL: [Target; 1] = [Target::Wasm];
+
+    /// Returns the name used on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Target::Wasm => "wasm",
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Target::ALL
+            .into_iter()
+            .find(|target| target.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown target '{s}', expected one of: {}",
+                    Target::ALL.map(Target::as_str).join(", ")
+                )
+            })
+    }
+}
+
+/// How warnings are reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WarningLevel {
+    /// Warnings are dropped.
+    Allow,
+    /// Warnings are reported.
+    #[default]
+    Warn,
+    /// Warnings are promoted to errors.
+    Deny,
+}
+
+impl WarningLevel {
+    /// All warning levels.
+    pub const ALL: [WarningLevel; 3] =
+        [WarningLevel::Allow, WarningLevel::Warn, WarningLevel::Deny];
+
+    /// Returns the name used on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WarningLevel::Allow => "allow",
+            WarningLevel::Warn => "warn",
+            WarningLevel::Deny => "deny",
+        }
+    }
+}
+
+impl fmt::Display for WarningLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WarningLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WarningLevel::ALL
+            .into_iter()
+            .find(|level| level.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown warning level '{s}', expected one of: {}",
+                    WarningLevel::ALL.map(WarningLevel::as_str).join(", ")
+                )
+            })
+    }
+}
+
+/// Settings for a single compilation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// How aggressively IR is optimized.
+    pub opt_level: OptLevel,
+    /// The code generation target.
+    pub target: Target,
+    /// Enabled feature flags, kept sorted so serialization is stable.
+    pub features: BTreeSet<String>,
+    /// How warnings are reported.
+    pub warnings: WarningLevel,
+    /// The maximum number of evaluation steps, or `None` for no limit.
+    pub fuel: Option<u64>,
+    /// The maximum depth of nested function calls during evaluation. Tail
+    /// calls don't nest.
+    pub recursion_limit: u64,
+    /// Whether the standard prelude is loaded.
+    pub prelude: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            opt_level: OptLevel::default(),
+            target: Target::default(),
+            features: BTreeSet::new(),
+            warnings: WarningLevel::default(),
+            fuel: None,
+            recursion_limit: Self::DEFAULT_RECURSION_LIMIT,
+            prelude: true,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// The default [`recursion_limit`](Self::recursion_limit).
+    pub const DEFAULT_RECURSION_LIMIT: u64 = 10_000;
+
+    /// The name of the WASM custom section holding the serialized options.
+    pub const SECTION_NAME: &'static str = "cadenza.options";
+
+    /// The feature that forbids float behavior that can differ between hosts.
+    ///
+    /// With it, generated code replaces every NaN that arithmetic produces
+    /// with the canonical NaN, whose bits WebAssembly leaves up to the engine
+    /// otherwise, and the math functions, which hosts implement with their
+    /// own rounding and fused operations, are errors.
+    pub const DETERMINISTIC_FLOATS: &'static str = "deterministic-floats";
+
+    /// Returns true if `feature` is enabled.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+
+    /// Returns true if the [`DETERMINISTIC_FLOATS`](Self::DETERMINISTIC_FLOATS)
+    /// feature is enabled.
+    pub fn deterministic_floats(&self) -> bool {
+        self.has_feature(Self::DETERMINISTIC_FLOATS)
+    }
+
+    /// Serializes the options to canonical JSON.
+    ///
+    /// Keys are sorted and every field is written, so equal options always
+    /// produce identical bytes.
+    pub fn to_metadata(&self) -> String {
+        self.to_json().to_string()
+    }
+
+    /// Returns the options as a JSON object.
+    pub fn to_json(&self) -> Json {
+        json!({
+            "features": self.features,
+            "fuel": self.fuel,
+            "opt_level": self.opt_level.as_str(),
+            "prelude": self.prelude,
+            "recursion_limit": self.recursion_limit,
+            "target": self.target.as_str(),
+            "warnings": self.warnings.as_str(),
+        })
+    }
+
+    /// Parses options serialized by [`CompileOptions::to_metadata`].
+    pub fn from_metadata(text: &str) -> Result<Self, String> {
+        let json: Json =
+            serde_json::from_str(text).map_err(|e| format!("invalid options metadata: {e}"))?;
+        let field = |name: &str| {
+            json.get(name)
+                .ok_or_else(|| format!("options metadata is missing '{name}'"))
+        };
+        let string = |name: &str| {
+            field(name)?
+                .as_str()
+                .ok_or_else(|| format!("options metadata field '{name}' must be a string"))
+        };
+
+        let features = field("features")?
+            .as_array()
+            .and_then(|items| {
+                items
+                    .iter()
+                    .map(|item| item.as_str().map(String::from))
+                    .collect::<Option<BTreeSet<_>>>()
+            })
+            .ok_or("options metadata field 'features' must be a list of strings")?;
+        let fuel = match field("fuel")? {
+            Json::Null => None,
+            fuel => Some(
+                fuel.as_u64()
+                    .ok_or("options metadata field 'fuel' must be an integer")?,
+            ),
+        };
+        let recursion_limit = field("recursion_limit")?
+            .as_u64()
+            .ok_or("options metadata field 'recursion_limit' must be an integer")?;
+        let prelude = field("prelude")?
+            .as_bool()
+            .ok_or("options metadata field 'prelude' must be a boolean")?;
+
+        Ok(Self {
+            opt_level: string("opt_level")?.parse()?,
+            target: string("target")?.parse()?,
+            features,
+            warnings: string("warnings")?.parse()?,
+            fuel,
+            recursion_limit,
+            prelude,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_metadata_is_stable() {
+        assert_eq!(
+            CompileOptions::default().to_metadata(),
+            r#"{"features":[],"fuel":null,"opt_level":"0","prelude":true,"recursion_limit":10000,"target":"wasm","warnings":"warn"}"#
+        );
+    }
+
+    #[test]
+    fn metadata_round_trips() {
+        let options = CompileOptions {
+            opt_level: OptLevel::O2,
+            features: ["units".into(), "async".into()].into_iter().collect(),
+            warnings: WarningLevel::Deny,
+            fuel: Some(10_000),
+            recursion_limit: 500,
+            prelude: false,
+            ..CompileOptions::default()
+        };
+        let metadata = options.to_metadata();
+        assert!(metadata.contains(r#""features":["async","units"]"#));
+        assert_eq!(CompileOptions::from_metadata(&metadata), Ok(options));
+    }
+
+    #[test]
+    fn rejects_unknown_values() {
+        assert!("3".parse::<OptLevel>().is_err());
+        assert!("x86".parse::<Target>().is_err());
+        assert!(CompileOptions::from_metadata("{}").is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Patterns for `let` bindings, function parameters and `match` arms.
+//!
+//! A pattern is written with the same syntax as the value it takes apart:
+//!
+//! - `x` binds the whole value, and `_` matches it without binding it
+//! - `42`, `"hi"`, `true`, `(-1)` and `()` (nil) match only that value
+//! - `{ x, y: py, pos: { z } }` binds the named fields of a record (fields
+//!   not named are ignored)
+//! - `(q, r)` binds the elements of a tuple
+//! - `[a, b, c]` binds the elements of a list of exactly that length
+//! - `Empty` and `(Circle r)` match an enum variant and bind its fields
+//! - `meter x` matches a quantity of the dimension `meter` measures and binds
+//!   its magnitude in meters, converting from the quantity's unit. Units are
+//!   only known to `match`, so only its arms take unit patterns
+//!
+//! Fields and elements are themselves patterns, so patterns nest to any
+//! depth. In a `let` or a parameter, a value that doesn't match is an error
+//! reported at the part of the pattern that didn't match; `match` tries the
+//! next arm instead.
+
+use crate::{
+    Eval,
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::{extract_identifier, literal_value},
+    interner::InternedString,
+    num,
+    special_form::{
+        enum_form::is_variant_name,
+        record_form::{as_record_pattern, record_fields},
+    },
+    unit::{DerivedDimension, Unit, UnitRegistry},
+    value::{Type, Value},
+};
+use cadenza_syntax::{ast::Expr, span::Span};
+
+/// A pattern, checked and ready to match values.
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern {
+    pub kind: PatternKind,
+    /// The span of the pattern, where mismatches are reported.
+    pub span: Span,
+}
+
+/// The kinds of [`Pattern`].
+#[derive(Debug, Clone)]
+pub(crate) enum PatternKind {
+    /// `_` matches any value.
+    Wildcard,
+    /// A variable matches any value and binds it.
+    Bind(InternedString),
+    /// A literal matches an equal value.
+    Literal(Value),
+    /// A record pattern matches a record with each of the named fields.
+    Record(Vec<(InternedString, Pattern)>),
+    /// A tuple pattern matches a tuple with as many elements.
+    Tuple(Vec<Pattern>),
+    /// A list pattern matches a list with as many elements.
+    List(Vec<Pattern>),
+    /// A variant pattern matches the enum variant with that name, with each
+    /// of its fields.
+    Variant(InternedString, Vec<Pattern>),
+    /// A unit pattern matches a quantity of the unit's dimension, with its
+    /// magnitude in the unit.
+    Quantity(Unit, Box<Pattern>),
+}
+
+/// Returns whether `expr` is a record, tuple, list or variant pattern,
+/// rather than a plain variable.
+pub(crate) fn is_pattern(expr: &Expr) -> bool {
+    as_record_pattern(expr).is_some()
+        || as_sequence_pattern(expr, "__tuple__").is_some()
+        || as_sequence_pattern(expr, "__list__").is_some()
+        || as_variant_pattern(expr).is_some()
+}
+
+/// Returns the variant name and field patterns of `pattern` if it is a
+/// variant with fields (`Circle r`).
+fn as_variant_pattern(pattern: &Expr) -> Option<(InternedString, Vec<Expr>)> {
+    let Expr::Apply(apply) = pattern else {
+        return None;
+    };
+    let Expr::Ident(callee) = apply.callee()? else {
+        return None;
+    };
+    let name = callee.syntax().text().interned();
+    is_variant_name(&name).then(|| (name, apply.all_arguments()))
+}
+
+/// Returns the unit name and magnitude pattern of `pattern` if it is a
+/// lowercase name applied to one pattern (`meter x`).
+fn as_unit_pattern(pattern: &Expr) -> Option<(InternedString, Expr)> {
+    let Expr::Apply(apply) = pattern else {
+        return None;
+    };
+    let Expr::Ident(callee) = apply.callee()? else {
+        return None;
+    };
+    let name = callee.syntax().text().interned();
+    let [magnitude] = <[Expr; 1]>::try_from(apply.all_arguments()).ok()?;
+    (!is_variant_name(&name)).then_some((name, magnitude))
+}
+
+/// Returns the elements of `pattern` if it is an application of `form`
+/// (`__tuple__` or `__list__`).
+fn as_sequence_pattern(pattern: &Expr, form: &str) -> Option<Vec<Expr>> {
+    let Expr::Apply(apply) = pattern else {
+        return None;
+    };
+    let callee = extract_identifier(&apply.callee()?)?;
+    (&*callee == form).then(|| apply.all_arguments())
+}
+
+impl Pattern {
+    /// Checks the pattern written as `expr`.
+    pub fn parse(expr: &Expr) -> Result<Self> {
+        Self::parse_with(expr, None)
+    }
+
+    /// Checks the pattern written as `expr`, which may match quantities by
+    /// the `units` defined.
+    pub fn parse_with_units(expr: &Expr, units: &UnitRegistry) -> Result<Self> {
+        Self::parse_with(expr, Some(units))
+    }
+
+    fn parse_with(expr: &Expr, units: Option<&UnitRegistry>) -> Result<Self> {
+        let span = expr.span();
+        let kind = match expr {
+            Expr::Ident(ident) => match ident.syntax().text().as_str() {
+                "_" => PatternKind::Wildcard,
+                "true" => PatternKind::Literal(Value::Bool(true)),
+                "false" => PatternKind::Literal(Value::Bool(false)),
+                name if is_variant_name(name) => PatternKind::Variant(name.into(), vec![]),
+                _ => PatternKind::Bind(ident.syntax().text().interned()),
+            },
+            Expr::Literal(literal) => PatternKind::Literal(literal_value(literal)?),
+            _ => {
+                if let Some(fields) = as_record_pattern(expr) {
+                    let mut patterns = Vec::with_capacity(fields.len());
+                    for field in record_fields(&fields)? {
+                        let pattern = match &field.value {
+                            Some(value) => Self::parse_with(value, units)?,
+                            None => Self {
+                                kind: PatternKind::Bind(field.name),
+                                span: field.span,
+                            },
+                        };
+                        patterns.push((field.name, pattern));
+                    }
+                    PatternKind::Record(patterns)
+                } else if let Some(elements) = as_sequence_pattern(expr, "__tuple__") {
+                    if elements.is_empty() {
+                        // `()` is the unit value, nil
+                        PatternKind::Literal(Value::Nil)
+                    } else {
+                        PatternKind::Tuple(Self::parse_all(&elements, units)?)
+                    }
+                } else if let Some(elements) = as_sequence_pattern(expr, "__list__") {
+                    PatternKind::List(Self::parse_all(&elements, units)?)
+                } else if let Some(value) = negative_literal(expr) {
+                    PatternKind::Literal(value?)
+                } else if let Some((name, fields)) = as_variant_pattern(expr) {
+                    PatternKind::Variant(name, Self::parse_all(&fields, units)?)
+                } else if let Some(units) = units
+                    && let Some((name, magnitude)) = as_unit_pattern(expr)
+                {
+                    let unit = units.get(name).cloned().ok_or_else(|| {
+                        Diagnostic::syntax(format!("unknown unit '{}' in pattern", &*name))
+                            .with_span(span)
+                    })?;
+                    let magnitude = Self::parse_with(&magnitude, Some(units))?;
+                    PatternKind::Quantity(unit, Box::new(magnitude))
+                } else {
+                    return Err(Diagnostic::syntax(
+                        "patterns must be names, literals, records, tuples, lists or variants",
+                    )
+                    .with_span(span));
+                }
+            }
+        };
+        Ok(Self { kind, span })
+    }
+
+    fn parse_all(exprs: &[Expr], units: Option<&UnitRegistry>) -> Result<Vec<Self>> {
+        exprs
+            .iter()
+            .map(|expr| Self::parse_with(expr, units))
+            .collect()
+    }
+
+    /// Matches `value` against the pattern and returns the resulting
+    /// bindings in pattern order, or the error describing the first part
+    /// that didn't match.
+    pub fn destructure(&self, value: &Value) -> Result<Vec<(InternedString, Value)>> {
+        let mut bindings = Vec::new();
+        self.destructure_into(value, &mut bindings)?;
+        Ok(bindings)
+    }
+
+    fn destructure_into(
+        &self,
+        value: &Value,
+        bindings: &mut Vec<(InternedString, Value)>,
+    ) -> Result<()> {
+        let (elements, values, expected) = match &self.kind {
+            PatternKind::Wildcard => return Ok(()),
+            PatternKind::Bind(name) => {
+                bindings.push((*name, value.clone()));
+                return Ok(());
+            }
+            PatternKind::Literal(literal) => {
+                if literal == value {
+                    return Ok(());
+                }
+                return Err(Diagnostic::syntax(format!(
+                    "expected {literal:?}, but the value is {value:?}"
+                ))
+                .with_span(self.span));
+            }
+            PatternKind::Record(fields) => {
+                let Value::Record { fields: values, .. } = value else {
+                    return Err(
+                        Diagnostic::type_error(Type::Record(vec![]), value.type_of())
+                            .with_span(self.span),
+                    );
+                };
+                for (name, pattern) in fields {
+                    let field_value = values
+                        .iter()
+                        .find(|(field, _)| field == name)
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| {
+                            Diagnostic::field_not_found(*name, "record").with_span(pattern.span)
+                        })?;
+                    pattern.destructure_into(field_value, bindings)?;
+                }
+                return Ok(());
+            }
+            PatternKind::Tuple(elements) => {
+                let Value::Tuple {
+                    elements: values, ..
+                } = value
+                else {
+                    return Err(Diagnostic::type_error(Type::Tuple(vec![]), value.type_of())
+                        .with_span(self.span));
+                };
+                (elements, values, "tuple")
+            }
+            PatternKind::List(elements) => {
+                let Value::List(values) = value else {
+                    return Err(
+                        Diagnostic::type_error(Type::list(Type::Unknown), value.type_of())
+                            .with_span(self.span),
+                    );
+                };
+                (elements, values, "list")
+            }
+            PatternKind::Variant(name, elements) => {
+                let values = match value {
+                    Value::Variant {
+                        variant, fields, ..
+                    } if variant == name => fields,
+                    _ => {
+                        return Err(Diagnostic::syntax(format!(
+                            "expected variant {}, but the value is {value:?}",
+                            &**name
+                        ))
+                        .with_span(self.span));
+                    }
+                };
+                (elements, values, "variant")
+            }
+            PatternKind::Quantity(unit, magnitude) => {
+                let dimension = DerivedDimension::from_dimension(unit.dimension);
+                let converted = match value {
+                    Value::Quantity {
+                        value,
+                        unit: from,
+                        dimension: from_dimension,
+                    } if *from_dimension == dimension => from.convert_to(*value, unit),
+                    _ => None,
+                };
+                let Some(converted) = converted else {
+                    return Err(Diagnostic::syntax(format!(
+                        "expected a quantity of {dimension}, but the value is {value:?}"
+                    ))
+                    .with_span(self.span));
+                };
+                return magnitude.destructure_into(&Value::Float(converted), bindings);
+            }
+        };
+
+        if elements.len() != values.len() {
+            return Err(Diagnostic::syntax(format!(
+                "{expected} pattern expects {} elements, but the {expected} has {}",
+                elements.len(),
+                values.len()
+            ))
+            .with_span(self.span));
+        }
+        for (element, value) in elements.iter().zip(values) {
+            element.destructure_into(value, bindings)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the variables the pattern binds, in pattern order.
+    pub fn variables(&self) -> Vec<InternedString> {
+        let mut names = Vec::new();
+        self.variables_into(&mut names);
+        names
+    }
+
+    fn variables_into(&self, names: &mut Vec<InternedString>) {
+        match &self.kind {
+            PatternKind::Wildcard | PatternKind::Literal(_) => {}
+            PatternKind::Bind(name) => names.push(*name),
+            PatternKind::Quantity(_, magnitude) => magnitude.variables_into(names),
+            PatternKind::Record(fields) => {
+                for (_, pattern) in fields {
+                    pattern.variables_into(names);
+                }
+            }
+            PatternKind::Tuple(elements)
+            | PatternKind::List(elements)
+            | PatternKind::Variant(_, elements) => {
+                for element in elements {
+                    element.variables_into(names);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the value of `expr` if it is a negated number literal, `(-1)`.
+fn negative_literal(expr: &Expr) -> Option<Result<Value>> {
+    let Expr::Apply(apply) = expr else {
+        return None;
+    };
+    if &*extract_identifier(&apply.callee()?)? != "-" {
+        return None;
+    }
+    let args = apply.all_arguments();
+    let [Expr::Literal(literal)] = args.as_slice() else {
+        return None;
+    };
+    Some(literal_value(literal).and_then(|value| match value {
+        Value::Integer(_) | Value::BigInt(_) | Value::Decimal(_) => num::negate(&value),
+        Value::Float(n) => Ok(Value::Float(-n)),
+        other => Err(Diagnostic::type_error(Type::Integer, other.type_of()).with_span(expr.span())),
+    }))
+}
+
+/// Evaluates `value` and binds the variables of `pattern` in the
+/// environment. Returns the evaluated value.
+///
+/// If evaluation or destructuring fails, every variable of the pattern is
+/// poisoned with [`Value::Error`] so later uses don't report the failure
+/// again.
+pub(crate) fn bind(pattern: &Expr, value: &Expr, ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let pattern = Pattern::parse(pattern)?;
+    let bindings = value
+        .eval(ctx)
+        .and_then(|value| Ok((pattern.destructure(&value)?, value)));
+    match bindings {
+        Ok((bindings, value)) => {
+            for (name, part) in bindings {
+                ctx.env.define(name, part);
+            }
+            Ok(value)
+        }
+        Err(error) => {
+            for name in pattern.variables() {
+                ctx.env.define(name, Value::Error);
+            }
+            Err(error)
+        }
+    }
+}
+
+/// Matches `value` against the pattern written as `pattern` and returns the
+/// resulting bindings in pattern order.
+pub(crate) fn destructure(pattern: &Expr, value: &Value) -> Result<Vec<(InternedString, Value)>> {
+    Pattern::parse(pattern)?.destructure(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn nested_patterns_bind_every_variable() {
+        let (results, compiler) = eval_all(
+            "
+let [a, b, c] = [1, 2, 3]
+let { pos = [x, (y, z)] } = { pos = [4, (5, 6)] }
+[a, b, c, x, y, z]
+",
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results[2].to_string(), "[1, 2, 3, 4, 5, 6]");
+    }
+
+    #[test]
+    fn shape_mismatch_is_an_error_and_poisons_the_variables() {
+        let (_, compiler) = eval_all(
+            "
+let [a, b] = [1, 2, 3]
+let { p = (q, r) } = { p = [1, 2] }
+a + q
+",
+        );
+        let messages: Vec<String> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        assert!(
+            messages[0].contains("list pattern expects 2 elements, but the list has 3"),
+            "{messages:?}"
+        );
+    }
+}
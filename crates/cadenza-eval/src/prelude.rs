@@ -0,0 +1,210 @@
+//! The standard prelude, written in Cadenza.
+//!
+//! Higher-level library functions (combinators such as `compose` and `flip`,
+//! `clamp`, and list helpers like `sum` and `range`) live in `prelude.cdz`,
+//! which is compiled into the crate and evaluated once per process, the first
+//! time [`Env::with_standard_builtins`] needs it. Use [`Env::without_prelude`]
+//! to skip it.
+//! The native list functions of [`list::prelude_builtins`], `min` and `max`
+//! from [`math::prelude_builtins`], the vector and matrix functions of
+//! [`linalg::prelude_builtins`], the decimal conversions of
+//! [`decimal::prelude_builtins`], and `argparse` from
+//! [`args::prelude_builtins`] are bound in the prelude first, so prelude code
+//! can use them.
+//!
+//! Prelude functions have the lowest lookup priority: a user definition with
+//! the same name (including a hoisted `fn`) shadows the prelude one.
+
+use crate::{
+    args,
+    compiler::Compiler,
+    context::{Eval, EvalContext},
+    decimal,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    env::{Env, Scope},
+    linalg, list, math,
+    value::Value,
+};
+use cadenza_syntax::parse::parse;
+use std::sync::{Arc, OnceLock};
+
+/// The prelude source.
+pub const SOURCE: &str = include_str!("prelude.cdz");
+
+/// Returns the functions the prelude defines, evaluating it on first use.
+///
+/// Every environment with the standard built-ins shares the result.
+pub(crate) fn standard() -> Result<Arc<Scope>> {
+    static PRELUDE: OnceLock<Result<Arc<Scope>>> = OnceLock::new();
+    PRELUDE
+        .get_or_init(|| load(SOURCE, &Env::without_prelude()).map(Arc::new))
+        .clone()
+}
+
+/// Evaluates `source` as the prelude and returns the functions it defines.
+///
+/// `env` must already contain the standard builtins the prelude relies on.
+/// Items are evaluated in order without hoisting, and each function is bound
+/// as soon as it is defined, so later prelude functions can call earlier ones.
+/// The first item that fails to evaluate is returned as an error.
+fn load(source: &str, env: &Env) -> Result<Scope> {
+    let root = parse(source).ast();
+    let mut env = env.clone();
+    env.push_scope();
+    let mut scope = Scope::new();
+
+    for builtin in list::prelude_builtins()
+        .into_iter()
+        .chain(math::prelude_builtins())
+        .chain(linalg::prelude_builtins())
+        .chain(decimal::prelude_builtins())
+        .chain(args::prelude_builtins())
+    {
+        env.define(builtin.name.into(), Value::BuiltinFn(builtin.clone()));
+        scope.define(builtin.name.into(), Value::BuiltinFn(builtin));
+    }
+
+    for expr in root.items() {
+        let mut compiler = Compiler::new();
+        let mut ctx = EvalContext::new(&mut env, &mut compiler);
+        expr.eval(&mut ctx).map_err(|err| {
+            Diagnostic::internal(format!("the prelude failed to evaluate: {}", err.kind))
+                .or_span(expr.span())
+        })?;
+
+        for (name, value) in compiler.defs() {
+            env.define(*name, value.clone());
+            scope.define(*name, value.clone());
+        }
+    }
+
+    Ok(scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_src(env: &mut Env, src: &str) -> (Vec<Value>, Compiler) {
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(src).ast(), env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn prelude_evaluates() {
+        assert!(Env::try_with_standard_builtins().is_ok());
+    }
+
+    #[test]
+    fn prelude_failures_are_errors() {
+        let err = super::load("fn ok x = x\nmissing 1\n", &Env::without_prelude()).unwrap_err();
+        assert!(
+            err.to_string().contains("the prelude failed to evaluate"),
+            "{err}"
+        );
+        assert_eq!(err.span.map(|span| span.start), Some(12));
+    }
+
+    #[test]
+    fn prelude_functions_are_available() {
+        let mut env = Env::with_standard_builtins();
+        let src = r#"
+map (x -> x * 2) [1, 2, 3]
+filter (x -> x > 2) [1, 2, 3, 4]
+fold max 0 [3, 7, 2]
+sum (range 1 5)
+reverse [1, 2, 3]
+clamp 15 0 10
+compose (x -> x * 2) (x -> x * 2) 3
+head [1, 2, 3]
+tail [1, 2, 3]
+length [1, 2, 3]
+"#;
+        let (results, compiler) = eval_src(&mut env, src);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        let shown: Vec<String> = results.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            shown,
+            vec![
+                "[2, 4, 6]",
+                "[3, 4]",
+                "7",
+                "10",
+                "[3, 2, 1]",
+                "10",
+                "12",
+                "1",
+                "[2, 3]",
+                "3"
+            ]
+        );
+    }
+
+    #[test]
+    fn native_list_functions_handle_long_lists() {
+        let mut env = Env::with_standard_builtins();
+        // Closures copy the environment they capture, so keep the list out
+        // of it to keep each call cheap
+        let (mut results, compiler) = eval_src(
+            &mut env,
+            "sum (map (x -> x * 2) (filter (x -> x < 5000) (0..10000)))\n",
+        );
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        let xs = (0..10_000).map(Value::Integer).collect();
+        env.define("xs".into(), Value::List(xs));
+        let (more, compiler) = eval_src(&mut env, "len (tail xs)\n");
+        results.extend(more);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results, [Value::Integer(24_995_000), Value::Integer(9_999)]);
+    }
+
+    #[test]
+    fn user_definitions_shadow_native_list_functions() {
+        let mut env = Env::with_standard_builtins();
+        let (results, compiler) = eval_src(
+            &mut env,
+            "fn head xs = 0
+head [1, 2]
+",
+        );
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results[1], Value::Integer(0));
+    }
+
+    #[test]
+    fn user_definitions_shadow_the_prelude() {
+        let mut env = Env::with_standard_builtins();
+        let (results, compiler) = eval_src(&mut env, "fn clamp x = 0\nclamp 5\n");
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results[1], Value::Integer(0));
+    }
+
+    #[test]
+    fn prelude_can_be_skipped() {
+        let mut env = Env::without_prelude();
+        let (_, compiler) = eval_src(&mut env, "clamp 15 0 10\n");
+        assert!(compiler.has_errors());
+    }
+}
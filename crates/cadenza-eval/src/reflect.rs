@@ -0,0 +1,238 @@
+//! Compile-time reflection for macros.
+//!
+//! Macros receive their arguments unevaluated. The query helpers on
+//! [`EvalContext`] describe an argument without evaluating it, so a macro can
+//! generate code from its shape, such as deriving equality for a struct from
+//! its fields:
+//!
+//! - [`EvalContext::reflect_type`]: the type inferred for an expression
+//! - [`EvalContext::reflect_arity`]: how many parameters a function takes
+//! - [`EvalContext::reflect_fields`]: the fields of a record literal, a struct
+//!   or a record-typed expression
+//!
+//! The `typeof`, `arity` and `fields` special forms expose the same queries
+//! to Cadenza code.
+//!
+//! # Staging
+//!
+//! A macro expands while the file is still being evaluated, so reflection
+//! sees the program as it stands at the expansion:
+//!
+//! 1. Reflection never evaluates. The helpers borrow the context immutably,
+//!    so a query can't run code, define names or use fuel.
+//! 2. Only what is defined where the macro expands is visible: functions and
+//!    units (which are hoisted) and the `let`s and `struct`s evaluated before
+//!    it. Reflecting on a name defined later, or nowhere, is an error
+//!    (`E0019`) rather than an `Unknown` a macro could silently build on.
+//! 3. Results are static. A type describes every value the expression can
+//!    have; where inference can't tell, the type is `Unknown`.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
+    interner::InternedString,
+    special_form::{lambda_form, record_form},
+    typeinfer::{TypeEnv, TypeInferencer},
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+
+impl EvalContext<'_> {
+    /// Returns the type inferred for `expr` from the definitions made so far,
+    /// without evaluating it.
+    ///
+    /// The name `expr` is, or the function it applies, must be defined.
+    pub fn reflect_type(&self, expr: &Expr) -> Result<Type> {
+        let subject = match expr {
+            Expr::Apply(apply) => apply.callee(),
+            other => Some(other.clone()),
+        };
+        if let Some(subject @ Expr::Ident(_)) = subject {
+            self.definition(&subject)?;
+        }
+
+        let type_env = TypeEnv::from_context(self.env, self.compiler);
+        let inferred = TypeInferencer::new()
+            .infer_expr(expr, &type_env)
+            .map_err(|e| {
+                Diagnostic::syntax(format!("Type inference failed for expression: {}", e))
+                    .with_span(expr.span())
+            })?;
+        Ok(inferred.to_concrete().unwrap_or(Type::Unknown))
+    }
+
+    /// Returns how many parameters the function `expr` takes: an anonymous
+    /// function, or the name of a function, constructor or unit.
+    pub fn reflect_arity(&self, expr: &Expr) -> Result<usize> {
+        if lambda_form::is_lambda(expr)
+            && let Expr::Apply(apply) = expr
+            && let Some(params) = apply.all_arguments().first()
+        {
+            return Ok(lambda_form::lambda_params(params)?.len());
+        }
+
+        let value = self.definition(expr)?;
+        match value {
+            Value::UserFunction(func) => Ok(func.params.len()),
+            Value::StructConstructor { .. } | Value::UnitConstructor(_) => Ok(1),
+            other => match other.type_of() {
+                Type::Fn(types) => Ok(types.len() - 1),
+                ty => Err(
+                    Diagnostic::type_error(Type::function(vec![], Type::Unknown), ty)
+                        .with_span(expr.span()),
+                ),
+            },
+        }
+    }
+
+    /// Returns the names and types of the fields of `expr`, in order: a
+    /// record literal, the name of a struct, or an expression whose type is
+    /// a record or struct.
+    pub fn reflect_fields(&self, expr: &Expr) -> Result<Vec<(InternedString, Type)>> {
+        if let Some(args) = record_form::as_record_pattern(expr) {
+            let mut fields = Vec::with_capacity(args.len());
+            for field in record_form::record_fields(&args)? {
+                let ty = match &field.value {
+                    Some(value) => self.reflect_type(value)?,
+                    None => self.definition_type(field.name, expr)?,
+                };
+                fields.push((field.name, ty));
+            }
+            return Ok(fields);
+        }
+
+        if let Expr::Ident(_) = expr
+            && let Value::StructConstructor { field_types, .. } = self.definition(expr)?
+        {
+            return Ok(field_types);
+        }
+
+        match self.reflect_type(expr)? {
+            Type::Record(fields) | Type::Struct { fields, .. } => Ok(fields),
+            ty => Err(Diagnostic::type_error(Type::Record(vec![]), ty).with_span(expr.span())),
+        }
+    }
+
+    /// Returns the value the identifier `expr` is defined as where the macro
+    /// expands, or the error for reflecting on a name that isn't defined.
+    fn definition(&self, expr: &Expr) -> Result<Value> {
+        let name = extract_identifier(expr).ok_or_else(|| {
+            Diagnostic::syntax("expected a name to reflect on").with_span(expr.span())
+        })?;
+        self.env
+            .get_scoped(name)
+            .or_else(|| self.compiler.get_var(name))
+            .or_else(|| self.env.get_prelude(name))
+            .cloned()
+            .or_else(|| {
+                let unit = self.compiler.units().get(name)?;
+                Some(Value::UnitConstructor(unit.clone()))
+            })
+            .ok_or_else(|| Diagnostic::not_yet_defined(name).with_span(expr.span()))
+    }
+
+    /// Returns the type of the variable `name`, used as the shorthand field
+    /// `{ name }` in `record`.
+    fn definition_type(&self, name: InternedString, record: &Expr) -> Result<Type> {
+        self.env
+            .get_scoped(name)
+            .or_else(|| self.compiler.get_var(name))
+            .map(Value::type_of)
+            .ok_or_else(|| Diagnostic::not_yet_defined(name).with_span(record.span()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Compiler, Env, EvalContext,
+        diagnostic::{Diagnostic, Result},
+        value::{BuiltinMacro, Type, Value},
+    };
+    use cadenza_syntax::{ast::Expr, parse::parse};
+
+    /// A macro that reports the fields of a struct as a list of their names,
+    /// the way a derive macro would read them.
+    fn field_names(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+        let [expr] = args else {
+            return Err(Diagnostic::arity(1, args.len()));
+        };
+        let names = ctx
+            .reflect_fields(expr)?
+            .into_iter()
+            .map(|(name, _)| Value::String(name.to_string().into()))
+            .collect();
+        Ok(Value::List(names))
+    }
+
+    fn eval_with_macro(input: &str) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        compiler.define_macro(
+            "field_names".into(),
+            Value::BuiltinMacro(BuiltinMacro {
+                name: "field_names",
+                signature: Type::function(vec![Type::Unknown], Type::list(Type::String)),
+                func: field_names,
+            }),
+        );
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn macro_reflects_on_struct_fields() {
+        let (results, compiler) = eval_with_macro(
+            "
+struct Point { x = Float, y = Float }
+field_names Point
+field_names { a = 1, b = \"two\" }
+",
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        let shown: Vec<String> = results[1..].iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, ["[x, y]", "[a, b]"]);
+    }
+
+    #[test]
+    fn reflecting_before_definition_is_an_error() {
+        let (_, compiler) = eval_with_macro(
+            "
+field_names Later
+struct Later { x = Float }
+",
+        );
+        let codes: Vec<_> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.kind().code())
+            .collect();
+        assert_eq!(codes, ["E0019"]);
+    }
+
+    #[test]
+    fn reflection_does_not_evaluate() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        crate::eval(
+            &parse("fn boom x = assert false\n").ast(),
+            &mut env,
+            &mut compiler,
+        );
+        let root = parse("boom 1\n").ast();
+        let expr = root.items().next().unwrap();
+
+        let ctx = EvalContext::new(&mut env, &mut compiler);
+        assert_eq!(ctx.reflect_arity(&expr).ok(), None);
+        let Expr::Apply(apply) = &expr else {
+            panic!("expected an application");
+        };
+        assert_eq!(ctx.reflect_arity(&apply.callee().unwrap()).unwrap(), 1);
+        assert!(matches!(
+            ctx.reflect_type(&apply.callee().unwrap()).unwrap(),
+            Type::Fn(_)
+        ));
+        assert!(!compiler.has_errors());
+    }
+}
@@ -0,0 +1,125 @@
+//! The built-in `Option` and `Result` types.
+//!
+//! Both are ordinary enums (see [`enum_form`](crate::special_form::enum_form)),
+//! bound in every environment with the standard built-ins, as if defined by:
+//!
+//! ```cadenza
+//! enum Option = Some value | None
+//! enum Result = Ok value | Err error
+//! ```
+//!
+//! Host functions that can fail return a `Result` (see [`system`](crate::system)).
+//! The try operators `x?` and `x |?` unwrap an `Ok` or a `Some`, and return an
+//! `Err` or a `None` from the enclosing function (see
+//! [`try_form`](crate::special_form::try_form)).
+
+use crate::{
+    diagnostic::{Diagnostic, Result},
+    interner::InternedString,
+    value::{Type, Value},
+};
+
+/// Returns the `Option` type: `Some value` or `None`.
+pub fn option_type() -> Type {
+    Type::Enum {
+        name: "Option".into(),
+        variants: vec![
+            ("Some".into(), vec![Type::Unknown]),
+            ("None".into(), vec![]),
+        ],
+    }
+}
+
+/// Returns the `Result` type: `Ok value` or `Err error`.
+pub fn result_type() -> Type {
+    Type::Enum {
+        name: "Result".into(),
+        variants: vec![
+            ("Ok".into(), vec![Type::Unknown]),
+            ("Err".into(), vec![Type::Unknown]),
+        ],
+    }
+}
+
+/// Returns true if `ty` is `Option` or `Result`.
+pub fn is_builtin(ty: &Type) -> bool {
+    *ty == option_type() || *ty == result_type()
+}
+
+/// Wraps a present value.
+pub fn some(value: Value) -> Value {
+    variant(option_type(), "Some", vec![value])
+}
+
+/// Returns the missing value.
+pub fn none() -> Value {
+    variant(option_type(), "None", vec![])
+}
+
+/// Wraps a successful result.
+pub fn ok(value: Value) -> Value {
+    variant(result_type(), "Ok", vec![value])
+}
+
+/// Wraps a failed result.
+pub fn err(error: Value) -> Value {
+    variant(result_type(), "Err", vec![error])
+}
+
+fn variant(ty: Type, name: &str, fields: Vec<Value>) -> Value {
+    Value::Variant {
+        ty,
+        variant: name.into(),
+        fields,
+    }
+}
+
+/// Returns the bindings of the types and their variants: the types, the
+/// constructors of `Some`, `Ok` and `Err`, and `None`.
+pub(crate) fn bindings() -> Vec<(InternedString, Value)> {
+    let mut bindings = Vec::new();
+    for ty in [option_type(), result_type()] {
+        let Type::Enum { name, variants } = &ty else {
+            unreachable!("Option and Result are enums");
+        };
+        bindings.push((*name, Value::Type(ty.clone())));
+        for (variant, fields) in variants {
+            let value = if fields.is_empty() {
+                self::variant(ty.clone(), variant, vec![])
+            } else {
+                Value::VariantConstructor {
+                    ty: ty.clone(),
+                    variant: *variant,
+                }
+            };
+            bindings.push((*variant, value));
+        }
+    }
+    bindings
+}
+
+/// Returns the value `value?` evaluates to: the field of an `Ok` or a
+/// `Some`.
+///
+/// An `Err` or a `None` fails with a diagnostic that carries it to the
+/// enclosing function, which returns it (see [`Diagnostic::propagated`]).
+pub(crate) fn unwrap(value: Value) -> Result<Value> {
+    match value {
+        Value::Variant {
+            ty,
+            variant,
+            mut fields,
+        } if is_builtin(&ty) => match &*variant {
+            "Ok" | "Some" => Ok(fields.remove(0)),
+            _ => Err(Diagnostic::propagated(Value::Variant {
+                ty,
+                variant,
+                fields,
+            })),
+        },
+        value => Err(Diagnostic::type_error(
+            Type::union(vec![result_type(), option_type()]),
+            value.type_of(),
+        )),
+    }
+}
@@ -0,0 +1,172 @@
+//! The rich display protocol.
+//!
+//! Front ends that show values to people lay them out with [`Rich::of`] and
+//! render the layout however suits them: the REPL aligns tables, truncates
+//! long values, and colors atoms by [`AtomKind`]. Keeping the layout separate
+//! from rendering means every front end agrees on how values are structured,
+//! such as which lists show as tables.
+//!
+//! ```
+//! use cadenza_eval::{Value, rich::Rich};
+//!
+//! let row = |n: i64| Value::Record {
+//!     type_name: None,
+//!     fields: vec![("n".into(), Value::Integer(n))],
+//! };
+//! let rich = Rich::of(&Value::List(vec![row(1), row(2)]));
+//! assert!(matches!(rich, Rich::Table { .. }));
+//! ```
+
+use crate::value::Value;
+
+/// What an atom is, for styling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtomKind {
+    Nil,
+    Bool,
+    /// Integers, floats, and quantities
+    Number,
+    /// A string's contents, unquoted and unescaped
+    String,
+    Symbol,
+    /// Functions, macros, special forms, and constructors
+    Function,
+    Type,
+    /// Ranges, vectors, matrices, tasks, channels, errors, and enum variants
+    /// without fields
+    Other,
+}
+
+/// A value laid out for display.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rich {
+    /// A value shown as a single piece of text.
+    Atom { text: String, kind: AtomKind },
+    /// A list (`[a, b]`), or a tuple (`(a, b)`) with an optional type name.
+    Seq {
+        name: Option<String>,
+        tuple: bool,
+        items: Vec<Rich>,
+    },
+    /// A record (`{a: 1}`) with an optional type name.
+    Record {
+        name: Option<String>,
+        fields: Vec<(String, Rich)>,
+    },
+    /// A non-empty list of records that all have the same type name and
+    /// fields, shown as a row per record and a column per field.
+    Table {
+        name: Option<String>,
+        columns: Vec<String>,
+        rows: Vec<Vec<Rich>>,
+    },
+}
+
+impl Rich {
+    /// Lays out `value` for display.
+    pub fn of(value: &Value) -> Self {
+        let atom = |text: String, kind| Rich::Atom { text, kind };
+        match value {
+            Value::Nil => atom("nil".to_string(), AtomKind::Nil),
+            Value::Bool(b) => atom(b.to_string(), AtomKind::Bool),
+            Value::Integer(n) => atom(n.to_string(), AtomKind::Number),
+            Value::BigInt(n) => atom(n.to_string(), AtomKind::Number),
+            Value::Float(f) => atom(f.to_string(), AtomKind::Number),
+            Value::Decimal(d) => atom(format!("{d:?}"), AtomKind::Number),
+            Value::Quantity { .. } => atom(value.to_string(), AtomKind::Number),
+            Value::String(s) => atom(s.to_string(), AtomKind::String),
+            Value::Symbol(s) => atom(format!(":{s}"), AtomKind::Symbol),
+            Value::List(items) => table(items).unwrap_or_else(|| Rich::Seq {
+                name: None,
+                tuple: false,
+                items: items.iter().map(Rich::of).collect(),
+            }),
+            Value::Range { .. } | Value::Vector(_) | Value::Matrix { .. } => {
+                atom(value.to_string(), AtomKind::Other)
+            }
+            Value::Tuple {
+                type_name,
+                elements,
+            } => Rich::Seq {
+                name: type_name.map(|name| name.to_string()),
+                tuple: true,
+                items: elements.iter().map(Rich::of).collect(),
+            },
+            Value::Record { type_name, fields } => Rich::Record {
+                name: type_name.map(|name| name.to_string()),
+                fields: fields
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), Rich::of(value)))
+                    .collect(),
+            },
+            Value::StructConstructor { name, field_types } => {
+                let fields = field_types
+                    .iter()
+                    .map(|(name, ty)| format!("{name}: {ty}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                atom(format!("{name} {{{fields}}}"), AtomKind::Function)
+            }
+            Value::Variant {
+                variant, fields, ..
+            } => {
+                if fields.is_empty() {
+                    atom(variant.to_string(), AtomKind::Other)
+                } else {
+                    Rich::Seq {
+                        name: Some(variant.to_string()),
+                        tuple: true,
+                        items: fields.iter().map(Rich::of).collect(),
+                    }
+                }
+            }
+            Value::VariantConstructor { variant, .. } => {
+                atom(format!("<variant {variant}>"), AtomKind::Function)
+            }
+            Value::UserFunction(f) => atom(format!("<function {}>", f.name), AtomKind::Function),
+            Value::BuiltinFn(f) => atom(format!("<builtin {}>", f.name), AtomKind::Function),
+            Value::BuiltinMacro(_) => atom("<macro>".to_string(), AtomKind::Function),
+            Value::SpecialForm(_) => atom("<special-form>".to_string(), AtomKind::Function),
+            Value::UnitConstructor(unit) => {
+                atom(format!("<unit {}>", unit.name), AtomKind::Function)
+            }
+            Value::Type(t) => atom(format!("<type {t}>"), AtomKind::Type),
+            Value::Task(id) => atom(format!("<task {}>", id.0), AtomKind::Other),
+            Value::Channel(id) => atom(format!("<channel {}>", id.0), AtomKind::Other),
+            Value::Error => atom("<error>".to_string(), AtomKind::Other),
+        }
+    }
+}
+
+/// Lays out `items` as a table if they're records of the same shape.
+fn table(items: &[Value]) -> Option<Rich> {
+    let Some(Value::Record {
+        type_name,
+        fields: first,
+    }) = items.first()
+    else {
+        return None;
+    };
+    let mut rows = Vec::with_capacity(items.len());
+    for item in items {
+        let Value::Record {
+            type_name: row_name,
+            fields,
+        } = item
+        else {
+            return None;
+        };
+        let same_shape = row_name == type_name
+            && fields.len() == first.len()
+            && fields.iter().zip(first).all(|((a, _), (b, _))| a == b);
+        if !same_shape {
+            return None;
+        }
+        rows.push(fields.iter().map(|(_, value)| Rich::of(value)).collect());
+    }
+    Some(Rich::Table {
+        name: type_name.map(|name| name.to_string()),
+        columns: first.iter().map(|(name, _)| name.to_string()).collect(),
+        rows,
+    })
+}
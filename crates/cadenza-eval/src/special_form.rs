@@ -30,16 +30,26 @@
 
 pub mod add_form;
 pub mod and_form;
+pub mod arity_form;
 pub mod assert_form;
 pub mod assign_form;
+pub mod attr_form;
 pub mod block_form;
 pub mod div_form;
+pub mod do_form;
+pub mod enum_form;
 pub mod eq_form;
 pub mod field_access_form;
+pub mod fields_form;
 pub mod fn_form;
+pub mod for_form;
 pub mod ge_form;
 pub mod gt_form;
+pub mod if_form;
+pub mod import_form;
 pub mod index_form;
+pub mod interpolate_form;
+pub mod lambda_form;
 pub mod le_form;
 pub mod let_form;
 pub mod list_form;
@@ -51,8 +61,11 @@ pub mod ne_form;
 pub mod or_form;
 pub mod pipeline_form;
 pub mod record_form;
+pub mod spawn_form;
 pub mod struct_form;
 pub mod sub_form;
+pub mod table_form;
+pub mod try_form;
 pub mod tuple_form;
 pub mod typeof_form;
 
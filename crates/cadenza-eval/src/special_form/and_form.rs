@@ -4,8 +4,8 @@ use crate::{
     Eval,
     context::EvalContext,
     diagnostic::{Diagnostic, Result},
-    ir::{BinOp, BlockBuilder, IrGenContext, SourceLocation, ValueId},
-    special_form::BuiltinSpecialForm,
+    ir::{BlockBuilder, IrGenContext, IrGenState, SourceLocation, ValueId},
+    special_form::{BuiltinSpecialForm, if_form, match_form::GenExpr},
     value::{Type, Value},
 };
 use cadenza_syntax::ast::Expr;
@@ -48,20 +48,25 @@ fn eval_and(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
 }
 
 fn ir_and(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "&& special form IR generation not yet implemented (use ir_and_with_state instead)",
+    ))
+}
+
+/// IR generation for `&&`, which only evaluates the right operand when the
+/// left one is true (see [`if_form::ir_short_circuit_with_state`]).
+pub fn ir_and_with_state(
     args: &[Expr],
-    block: &mut BlockBuilder,
+    state: &mut IrGenState,
     ctx: &mut IrGenContext,
     source: SourceLocation,
-    gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+    gen_expr: &mut GenExpr<'_>,
 ) -> Result<ValueId> {
-    if args.len() != 2 {
-        return Err(Diagnostic::syntax(format!(
-            "Binary operator && expects 2 arguments, got {}",
-            args.len()
-        )));
-    }
-
-    let lhs = gen_expr(&args[0], block, ctx)?;
-    let rhs = gen_expr(&args[1], block, ctx)?;
-    Ok(block.binop(BinOp::And, lhs, rhs, Type::Bool, source))
+    if_form::ir_short_circuit_with_state("&&", args, state, ctx, source, gen_expr)
 }
@@ -0,0 +1,95 @@
+//! The `arity` special form for parameter count queries.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{Diagnostic, Result},
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::sync::OnceLock;
+
+/// Returns the `arity` special form for querying how many parameters a
+/// function takes.
+///
+/// # Evaluation
+/// - Takes 1 argument: a function name or an anonymous function
+/// - Counts the parameters without evaluating the argument
+/// - Reports an error if the name isn't defined yet
+/// - Returns the count as an Integer
+///
+/// # IR Generation
+/// - Not yet supported for arity
+///
+/// # Examples
+/// ```cadenza
+/// fn add x y = x + y
+/// arity add          // Returns 2
+/// arity (x -> x)     // Returns 1
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static ARITY_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    ARITY_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "arity",
+        signature: Type::function(vec![Type::Unknown], Type::Integer),
+        eval_fn: eval_arity,
+        ir_fn: ir_arity,
+    })
+}
+
+fn eval_arity(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let [expr] = args else {
+        return Err(Diagnostic::syntax("arity expects 1 argument: function"));
+    };
+    let arity = ctx.reflect_arity(expr)?;
+    Ok(Value::Integer(arity as i64))
+}
+
+fn ir_arity(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "arity not yet supported in IR generation",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    #[test]
+    fn test_arity_of_functions() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse(
+            "
+fn add x y = x + y
+arity add
+arity (fn x y z -> x)
+arity len
+",
+        )
+        .ast();
+        let results = crate::eval(&root, &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(
+            results[1..],
+            [Value::Integer(2), Value::Integer(3), Value::Integer(1)]
+        );
+    }
+
+    #[test]
+    fn test_arity_of_non_function_is_an_error() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse("let x = 1\narity x\n").ast();
+        crate::eval(&root, &mut env, &mut compiler);
+        assert!(compiler.has_errors());
+    }
+}
@@ -4,11 +4,11 @@ use crate::{
     Eval,
     context::EvalContext,
     diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
-    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    ir::{BlockBuilder, IrConst, IrGenContext, SourceLocation, ValueId},
     special_form::BuiltinSpecialForm,
     value::{Type, Value},
 };
-use cadenza_syntax::ast::Expr;
+use cadenza_syntax::ast::{Expr, LiteralValue};
 use std::sync::OnceLock;
 
 /// Returns the `assert` special form for runtime assertions.
@@ -23,7 +23,9 @@ use std::sync::OnceLock;
 /// - If true, returns Nil
 ///
 /// # IR Generation
-/// - Not yet supported for assertions
+/// - Generates IR for the condition
+/// - Emits an assert instruction that traps with the message if it's false;
+///   a custom message must be a string literal
 ///
 /// # Examples
 /// ```cadenza
@@ -67,24 +69,20 @@ fn eval_assert(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
 
     // If condition is false, create assertion failure
     if !condition_result {
-        // Get the condition expression text for error message
-        let condition_text = condition_expr.syntax().text();
-
         // Build the error message
         let message = if args.len() == 2 {
             // Custom message provided
             let msg_expr = &args[1];
             let msg_value = msg_expr.eval(ctx)?;
             match msg_value {
-                Value::String(s) => format!("{}\n  condition: {}", s, condition_text.as_str()),
+                Value::String(s) => failure_message(condition_expr, Some(&s)),
                 _ => {
                     return Err(Diagnostic::type_error(Type::String, msg_value.type_of())
                         .with_span(msg_expr.span()));
                 }
             }
         } else {
-            // No custom message, add descriptive prefix
-            format!("Assertion failed: {}", condition_text.as_str())
+            failure_message(condition_expr, None)
         };
 
         return Err(Diagnostic::assertion_failed(message).with_span(condition_expr.span()));
@@ -95,16 +93,59 @@ fn eval_assert(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
 }
 
 fn ir_assert(
-    _args: &[Expr],
-    _block: &mut BlockBuilder,
-    _ctx: &mut IrGenContext,
-    _source: SourceLocation,
-    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+    args: &[Expr],
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
 ) -> Result<ValueId> {
-    // TODO: Add assertion instruction to IR
-    Err(Diagnostic::syntax(
-        "assert not yet supported in IR generation",
-    ))
+    if args.is_empty() || args.len() > 2 {
+        return Err(Diagnostic::syntax(
+            "assert expects 1 or 2 arguments: condition [message]",
+        ));
+    }
+
+    let condition_expr = &args[0];
+
+    // The message is part of the module, so it has to be known now
+    let message = match args.get(1) {
+        Some(msg_expr) => {
+            let Expr::Literal(lit) = msg_expr else {
+                return Err(
+                    Diagnostic::syntax("assert message must be a string literal")
+                        .with_span(msg_expr.span()),
+                );
+            };
+            let Some(LiteralValue::String(msg)) = lit.value() else {
+                return Err(
+                    Diagnostic::syntax("assert message must be a string literal")
+                        .with_span(msg_expr.span()),
+                );
+            };
+            failure_message(condition_expr, Some(&msg.syntax().text()))
+        }
+        None => failure_message(condition_expr, None),
+    };
+
+    let cond = gen_expr(condition_expr, block, ctx)?;
+    block.assert(cond, message.as_str().into(), source);
+
+    // Assertion passed
+    let result = block.const_val(IrConst::Nil, Type::Nil, source);
+    ctx.set_value_type(result, Type::Nil);
+    Ok(result)
+}
+
+/// Returns the message of a failed assertion of `condition`, which quotes
+/// its source, with the custom `message` if there is one.
+fn failure_message(condition: &Expr, message: Option<&str>) -> String {
+    let condition_text = condition.syntax().text().to_string();
+    let condition_text = condition_text.trim_end();
+    match message {
+        Some(message) => format!("{}\n  condition: {}", message, condition_text),
+        // No custom message, add descriptive prefix
+        None => format!("Assertion failed: {}", condition_text),
+    }
 }
 
 #[cfg(test)]
@@ -6,7 +6,8 @@ use crate::{
     diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
     interner::InternedString,
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
-    special_form::BuiltinSpecialForm,
+    pattern,
+    special_form::{BuiltinSpecialForm, record_form::as_record_pattern},
     value::{Type, Value},
 };
 use cadenza_syntax::ast::{Apply, Expr};
@@ -23,15 +24,22 @@ use std::sync::OnceLock;
 /// the `=` operator delegates to that macro by calling it with `[lhs_args..., rhs]`.
 ///
 /// When the LHS is a plain identifier, `=` performs a direct reassignment to that variable.
+/// If the RHS fails, the variable is poisoned with [`Value::Error`].
+///
+/// When the LHS is a record pattern, `=` binds each variable in the pattern to
+/// the matching field of the RHS, as `let` does.
 ///
 /// # IR Generation
-/// Not yet supported in IR generation.
+/// - Delegates `let` bindings to the `let` special form
+/// - A reassignment binds the variable to the new SSA value
+/// - Other assignments are not yet supported
 ///
 /// # Examples
 /// ```cadenza
 /// let x = 42          # Delegates to `let` with `[x, 42]`
 /// x = 50              # Direct reassignment to existing variable `x`
 /// record.field = 100  # Field assignment
+/// { x: px, y } = point # Binds `px` and `y`
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
@@ -53,6 +61,11 @@ fn eval_assign(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
     let lhs_expr = &args[0];
     let rhs_expr = &args[1];
 
+    // Record patterns bind their variables instead of building a record
+    if as_record_pattern(lhs_expr).is_some() {
+        return pattern::bind(lhs_expr, rhs_expr, ctx);
+    }
+
     // Check if LHS is a macro application - delegate if so
     // EXCEPT for field access (.) which should be handled as field assignment
     if let Expr::Apply(apply) = lhs_expr
@@ -110,7 +123,15 @@ fn eval_assign(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
             let text = ident.syntax().text();
             let name: InternedString = text.to_string().as_str().into();
 
-            let rhs_value = rhs_expr.eval(ctx)?;
+            let rhs_value = match rhs_expr.eval(ctx) {
+                Ok(value) => value,
+                Err(error) => {
+                    if let Some(var) = ctx.env.get_mut(name) {
+                        *var = Value::Error;
+                    }
+                    return Err(error);
+                }
+            };
 
             // Check if the variable exists (must be declared with `let` first)
             if let Some(var) = ctx.env.get_mut(name) {
@@ -120,9 +141,10 @@ fn eval_assign(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
                 Err(Diagnostic::undefined_variable(name).with_span(ident.span()))
             }
         }
-        _ => Err(Diagnostic::syntax(
+        other => Err(Diagnostic::syntax(
             "left side of = must be an identifier, field access (e.g., record.field), or a macro application (e.g., let x, fn name, measure unit)",
-        )),
+        )
+        .with_span(other.span())),
     }
 }
 
@@ -165,6 +187,23 @@ fn ir_assign(
         }
     }
 
+    // Reassigning a variable binds its name to the new value, which the
+    // control flow forms join with the old one where paths meet
+    if let Expr::Ident(ident) = lhs_expr {
+        let name = ident.syntax().text().interned();
+        if ctx.lookup_var(name).is_none() {
+            return Err(Diagnostic::undefined_variable(name).with_span(ident.span()));
+        }
+        let value_id = gen_expr(rhs_expr, block, ctx)?;
+        ctx.consume(rhs_expr);
+        let ty = ctx
+            .get_value_type(value_id)
+            .cloned()
+            .unwrap_or(Type::Unknown);
+        ctx.bind_var(name, value_id, &crate::InferType::Concrete(ty));
+        return Ok(value_id);
+    }
+
     // For now, other assignment patterns are not supported in IR generation
     Err(Diagnostic::syntax(
         "assignment operator (without let) not yet supported in IR generation",
@@ -212,21 +251,27 @@ fn handle_field_assignment(
             let id: InternedString = text.to_string().as_str().into();
             (id, ident.span())
         }
-        _ => {
+        other => {
             return Err(Diagnostic::syntax(
                 "field assignment requires a variable name for the record",
-            ));
+            )
+            .with_span(other.span()));
         }
     };
 
     // Get the field name (second argument)
+    let field_span = args[1].span();
     let field_name = match &args[1] {
         Expr::Ident(ident) => {
             let text = ident.syntax().text();
             let id: InternedString = text.to_string().as_str().into();
             id
         }
-        _ => return Err(Diagnostic::syntax("field name must be an identifier")),
+        _ => {
+            return Err(
+                Diagnostic::syntax("field name must be an identifier").with_span(field_span)
+            );
+        }
     };
 
     // Evaluate the RHS value
@@ -263,10 +308,7 @@ fn handle_field_assignment(
             if found {
                 Ok(new_value)
             } else {
-                Err(Diagnostic::syntax(format!(
-                    "field '{}' not found in record",
-                    &*field_name
-                )))
+                Err(Diagnostic::field_not_found(field_name, "record").with_span(field_span))
             }
         }
         _ => Err(Diagnostic::type_error(
@@ -0,0 +1,161 @@
+//! The `@` special form for attributes.
+
+use crate::{
+    Eval,
+    compiler::Export,
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
+    interner::InternedString,
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Attribute, Type, Value},
+};
+use cadenza_syntax::{ast::Expr, span::Span};
+use std::sync::OnceLock;
+
+/// Returns the `@` special form for attributes.
+///
+/// The `@` special form attaches an attribute to the definition that follows it.
+///
+/// # Evaluation
+/// - Takes 1 argument: an attribute name, optionally applied to arguments
+/// - Evaluates the attribute arguments, except the trait names of `@derive`,
+///   which become symbols
+/// - Queues the attribute in the compiler until the next definition consumes it
+/// - Returns Nil
+///
+/// # IR Generation
+/// - Attributes produce no IR
+///
+/// # Examples
+/// ```cadenza
+/// @memoize
+/// fn square x = x * x
+///
+/// @memoize 16
+/// fn cube x = x * x * x
+///
+/// @test
+/// fn squares = assert (square 3) == 9
+///
+/// @export
+/// fn area w h = w * h
+///
+/// @export "max-width"
+/// let max_width = 40
+///
+/// @derive(Eq, Show)
+/// struct Point { x = Integer, y = Integer }
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static ATTR_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    ATTR_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "@",
+        signature: Type::function(vec![Type::Unknown], Type::Nil),
+        eval_fn: eval_attr,
+        ir_fn: ir_attr,
+    })
+}
+
+fn eval_attr(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Diagnostic::arity(1, args.len()));
+    }
+
+    // The attribute is either a bare name (`@memoize`) or an application (`@memoize 16`)
+    let (name_expr, arg_exprs) = match &args[0] {
+        Expr::Apply(apply) => {
+            let callee = apply
+                .callee()
+                .ok_or_else(|| Diagnostic::syntax("missing attribute name"))?;
+            (callee, apply.all_arguments())
+        }
+        other => (other.clone(), Vec::new()),
+    };
+
+    let name = extract_identifier(&name_expr).ok_or_else(|| {
+        Diagnostic::syntax("attribute name must be an identifier").with_span(name_expr.span())
+    })?;
+
+    let values = if &*name == "derive" {
+        derive_traits(&arg_exprs)?
+    } else {
+        let mut values = Vec::with_capacity(arg_exprs.len());
+        for arg in &arg_exprs {
+            values.push(arg.eval(ctx)?);
+        }
+        values
+    };
+
+    ctx.compiler
+        .push_attribute(Attribute { name, args: values });
+    Ok(Value::Nil)
+}
+
+/// Returns the trait names `@derive` is applied to, written
+/// `@derive(Eq, Show)` or `@derive Eq Show`, as symbols.
+fn derive_traits(exprs: &[Expr]) -> Result<Vec<Value>> {
+    let mut traits = Vec::with_capacity(exprs.len());
+    for expr in exprs {
+        let names = match expr {
+            Expr::Apply(apply)
+                if apply
+                    .callee()
+                    .and_then(|callee| extract_identifier(&callee))
+                    .is_some_and(|id| &*id == "__tuple__") =>
+            {
+                apply.all_arguments()
+            }
+            other => vec![other.clone()],
+        };
+        for name in names {
+            let Expr::Ident(ident) = &name else {
+                return Err(
+                    Diagnostic::syntax("@derive expects trait names").with_span(name.span())
+                );
+            };
+            traits.push(Value::Symbol(ident.syntax().text().interned()));
+        }
+    }
+    Ok(traits)
+}
+
+/// Applies an `@export` attribute with `args` to the definition `name`,
+/// whose name is at `span`.
+///
+/// The definition is exported under its own name, or under the name given as
+/// the attribute's string argument.
+pub fn export(
+    name: InternedString,
+    span: Span,
+    args: &[Value],
+    ctx: &mut EvalContext<'_>,
+) -> Result<()> {
+    let export_name = match args {
+        [] => name,
+        [Value::String(export_name)] => InternedString::new(export_name),
+        [other] => {
+            return Err(Diagnostic::type_error(Type::String, other.type_of()).with_span(span));
+        }
+        args => return Err(Diagnostic::arity(1, args.len()).with_span(span)),
+    };
+    ctx.compiler.add_export(Export {
+        name,
+        export_name,
+        span,
+    });
+    Ok(())
+}
+
+fn ir_attr(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "attributes are not supported in IR generation",
+    ))
+}
@@ -4,6 +4,7 @@ use crate::{
     Eval,
     context::EvalContext,
     diagnostic::Result,
+    eval::{self, Tail, extract_identifier},
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
     special_form::BuiltinSpecialForm,
     value::{Type, Value},
@@ -15,16 +16,19 @@ use std::sync::OnceLock;
 ///
 /// The `__block__` special form creates a new lexical scope, evaluates each
 /// expression in sequence, and returns the value of the last expression.
+/// A block ending with a binding (`let x = ...` or `x = ...`) returns nil,
+/// the unit value, rather than the value that happened to be bound.
 ///
 /// # Evaluation
 /// - Creates a new scope
 /// - Evaluates each expression in sequence
-/// - Returns the last expression's value
+/// - Returns the last expression's value, or nil if it's a binding
 /// - Pops the scope on exit
 ///
 /// # IR Generation
 /// - Generates IR for each expression in sequence
-/// - Returns the last expression's ValueId
+/// - Returns the last expression's ValueId, or a nil constant if it's a
+///   binding
 ///
 /// # Examples
 /// ```cadenza
@@ -44,27 +48,47 @@ pub fn get() -> &'static BuiltinSpecialForm {
 }
 
 fn eval_block(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
-    if args.is_empty() {
-        return Ok(Value::Nil);
-    }
+    eval_tail(args, ctx)?.finish(ctx)
+}
+
+/// Evaluates a block, leaving a call its last expression ends with unmade.
+pub(crate) fn eval_tail(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Tail> {
+    let Some((last, init)) = args.split_last() else {
+        return Ok(Tail::Value(Value::Nil));
+    };
 
     // Push a new scope for the block
     ctx.env.push_scope();
 
-    // Evaluate each expression in sequence
-    let mut result = Value::Nil;
-    for expr in args {
-        result = expr.eval(ctx)?;
-        // Continue evaluation even if one expression returns Nil
+    // Evaluate each expression in sequence, the last in tail position
+    for expr in init {
+        expr.eval(ctx)?;
     }
+    let result = if is_binding(last) {
+        last.eval(ctx)?;
+        Tail::Value(Value::Nil)
+    } else {
+        eval::eval_tail(last, ctx)?
+    };
 
     // Pop the scope when exiting the block
     ctx.env.pop_scope();
 
-    // Return the last expression's value
     Ok(result)
 }
 
+/// Returns whether `expr` binds a name, with `let` or `=`, rather than
+/// computing a value.
+pub(crate) fn is_binding(expr: &Expr) -> bool {
+    let Expr::Apply(apply) = expr else {
+        return false;
+    };
+    apply
+        .callee()
+        .and_then(|callee| extract_identifier(&callee))
+        .is_some_and(|id| matches!(&*id, "let" | "="))
+}
+
 fn ir_block(
     args: &[Expr],
     block: &mut BlockBuilder,
@@ -84,7 +108,10 @@ fn ir_block(
         result = Some(gen_expr(expr, block, ctx)?);
     }
 
-    // Return the last expression's value ID
+    // Return the last expression's value ID, unless it's a binding
+    if args.last().is_some_and(is_binding) {
+        return Ok(block.const_val(crate::ir::IrConst::Nil, Type::Nil, source));
+    }
     Ok(result.unwrap())
 }
 
@@ -20,6 +20,7 @@ use std::sync::OnceLock;
 ///
 /// # IR Generation
 /// - Generates IR for both operands
+/// - Emits a check that traps on a zero divisor
 /// - Emits a binary div instruction
 ///
 /// # Examples
@@ -97,16 +98,21 @@ fn ir_div(
     let rhs = gen_expr(&args[1], block, ctx)?;
 
     // Infer the result type based on operand types
-    // Division always returns Float for numeric types
+    // Integer division truncates, like the evaluator's
     // No coercion - operands must be the same type
     let ty = match (ctx.get_value_type(lhs), ctx.get_value_type(rhs)) {
-        (Some(Type::Integer), Some(Type::Integer)) => Type::Float,
+        (Some(Type::Integer), Some(Type::Integer)) => Type::Integer,
         (Some(Type::Float), Some(Type::Float)) => Type::Float,
         // For quantities or unknown types, fall back to Unknown
         _ => Type::Unknown,
     };
 
-    // Emit binary div instruction
+    // Trap on a zero divisor, then emit binary div instruction
+    block.check_divisor(
+        rhs,
+        ctx.get_value_type(rhs).unwrap_or(&Type::Unknown),
+        source,
+    );
     let result = block.binop(BinOp::Div, lhs, rhs, ty.clone(), source);
     ctx.set_value_type(result, ty);
     Ok(result)
@@ -0,0 +1,104 @@
+//! The `do` special form for block expressions.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{Diagnostic, Result},
+    eval::{self, Tail},
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::sync::OnceLock;
+
+/// Returns the `do` special form for block expressions.
+///
+/// An indented block is only parsed after an operator such as the `=` of a
+/// `let` or `fn`. `do` followed by an indented block makes a block anywhere an
+/// expression can go, such as a list element or a function argument; the
+/// parser passes the block to `do` as a `__block__` application (see
+/// [`super::block_form`]).
+///
+/// # Evaluation
+/// - Takes exactly 1 argument: the block, or a single expression
+/// - Evaluates it in a new scope and returns the last expression's value
+/// - The last expression is in tail position
+///
+/// # IR Generation
+/// - Generates IR for the block
+///
+/// # Examples
+/// ```cadenza
+/// let totals = [1, do
+///     let x = 2
+///     x * 3
+/// ]
+/// print (do
+///     let name = "world"
+///     "hello ${name}")
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static DO_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    DO_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "do",
+        signature: Type::function(vec![Type::Unknown], Type::Unknown),
+        eval_fn: eval_do,
+        ir_fn: ir_do,
+    })
+}
+
+fn eval_do(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    eval_tail(args, ctx)?.finish(ctx)
+}
+
+/// Evaluates a `do` block, leaving a call its last expression ends with
+/// unmade.
+pub(crate) fn eval_tail(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Tail> {
+    let [body] = args else {
+        return Err(Diagnostic::arity(1, args.len()));
+    };
+    eval::eval_tail(body, ctx)
+}
+
+fn ir_do(
+    args: &[Expr],
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    let [body] = args else {
+        return Err(Diagnostic::arity(1, args.len()));
+    };
+    gen_expr(body, block, ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    #[test]
+    fn test_do_special_form_eval() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let input = "
+let x = 1
+let xs = [x, do
+    let x = 2
+    x * 3
+]
+xs
+x
+";
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(
+            results[2..],
+            [
+                Value::List(vec![Value::Integer(1), Value::Integer(6)]),
+                Value::Integer(1),
+            ]
+        );
+    }
+}
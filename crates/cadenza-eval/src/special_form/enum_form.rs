@@ -0,0 +1,281 @@
+//! The `enum` special form for defining sum types.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    interner::InternedString,
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::{ast::Expr, span::Span, token::Kind};
+use std::sync::OnceLock;
+
+/// Returns the `enum` special form for defining sum types.
+///
+/// The `enum` special form defines a nominal type whose values are one of
+/// several variants, each with its own positional fields.
+///
+/// # Evaluation
+/// - Takes 2 arguments: the enum name (identifier) and the variants, given as
+///   the right-hand side of `=`
+/// - Variants are separated by `|`; each is a capitalised name followed by
+///   its fields
+/// - A field named after a type (`Integer`, a struct, ...) has that type; any
+///   other name documents the field and accepts any value
+/// - Binds the enum name to its type, each variant without fields to its
+///   value and each variant with fields to a constructor for it
+/// - Returns the enum type as a Type value
+///
+/// # IR Generation
+/// - Not yet implemented (returns error)
+///
+/// # Examples
+/// ```cadenza
+/// enum Shape = Circle Float | Rect w h | Empty
+///
+/// fn area s = match s
+///     (Circle r) => 3.14 * r * r
+///     (Rect w h) => w * h
+///     Empty => 0
+///
+/// area (Rect 2 3)
+/// ```
+///
+/// The variants can also be written one per line:
+///
+/// ```cadenza
+/// enum Color =
+///     | Red
+///     | Green
+///     | Blue
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static ENUM_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    ENUM_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "enum",
+        // Type signature: enum Name = Variant fields | ...
+        // First arg is Symbol (the enum name)
+        // Second arg is the variants, read from their syntax
+        // Returns Type (the enum type definition)
+        signature: Type::function(vec![Type::Symbol, Type::Unknown], Type::Type),
+        eval_fn: eval_enum,
+        ir_fn: ir_enum,
+    })
+}
+
+fn eval_enum(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let [name, variants] = args else {
+        return Err(Diagnostic::syntax(
+            "enum expects a name and its variants (e.g., enum Shape = Circle r | Rect w h)",
+        ));
+    };
+
+    let Expr::Ident(ident) = name else {
+        return Err(Diagnostic::syntax("enum name must be an identifier").with_span(name.span()));
+    };
+    let enum_name = ident.syntax().text().interned();
+
+    let mut defs: Vec<(InternedString, Vec<Type>)> = Vec::new();
+    for words in variant_words(variants)? {
+        let Some(((variant, span), fields)) = words.split_first() else {
+            return Err(
+                Diagnostic::syntax("enum variants must not be empty").with_span(variants.span())
+            );
+        };
+        if !is_variant_name(variant) {
+            return Err(Diagnostic::syntax(format!(
+                "enum variant '{}' must start with an uppercase letter",
+                &**variant
+            ))
+            .with_span(*span));
+        }
+        if defs.iter().any(|(name, _)| name == variant) {
+            return Err(Diagnostic::syntax(format!(
+                "enum variant '{}' is defined more than once",
+                &**variant
+            ))
+            .with_span(*span));
+        }
+        let fields = fields
+            .iter()
+            .map(|(field, span)| field_type(*field, *span, enum_name, ctx))
+            .collect::<Result<_>>()?;
+        defs.push((*variant, fields));
+    }
+
+    let ty = Type::Enum {
+        name: enum_name,
+        variants: defs.clone(),
+    };
+
+    ctx.env.define(enum_name, Value::Type(ty.clone()));
+    for (variant, fields) in defs {
+        let value = if fields.is_empty() {
+            Value::Variant {
+                ty: ty.clone(),
+                variant,
+                fields: vec![],
+            }
+        } else {
+            Value::VariantConstructor {
+                ty: ty.clone(),
+                variant,
+            }
+        };
+        ctx.env.define(variant, value);
+    }
+
+    Ok(Value::Type(ty))
+}
+
+/// Splits the variants of an enum definition on `|` into the words of each
+/// variant, with their spans.
+///
+/// The variants are read from the tokens rather than the parsed expression:
+/// `|` binds tighter than application, so `Circle r | Rect w h` parses as
+/// `Circle (r | Rect) w h`.
+fn variant_words(variants: &Expr) -> Result<Vec<Vec<(InternedString, Span)>>> {
+    let mut result = vec![vec![]];
+    for token in variants
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        let range = token.text_range();
+        let span = Span::new(range.start().into(), range.end().into());
+        match token.kind() {
+            kind if kind.is_trivia() => {}
+            Kind::Identifier => result
+                .last_mut()
+                .unwrap()
+                .push((token.text().interned(), span)),
+            Kind::Pipe => result.push(vec![]),
+            _ => {
+                return Err(Diagnostic::syntax(
+                    "enum variants must be a name followed by its fields, separated by `|`",
+                )
+                .with_span(span));
+            }
+        }
+    }
+    // A leading `|`, as in the one-per-line form, starts the first variant
+    if result.len() > 1 && result[0].is_empty() {
+        result.remove(0);
+    }
+    Ok(result)
+}
+
+/// Returns whether `name` is written like a variant, starting with an
+/// uppercase letter.
+pub(crate) fn is_variant_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// Returns the type of the variant field written as `field`.
+fn field_type(
+    field: InternedString,
+    span: Span,
+    enum_name: InternedString,
+    ctx: &EvalContext<'_>,
+) -> Result<Type> {
+    // A lowercase name only documents the field, and the enum itself can't be
+    // referred to until it is defined
+    if !is_variant_name(&field) || field == enum_name {
+        return Ok(Type::Unknown);
+    }
+    match ctx.env.get(field) {
+        Some(Value::Type(ty)) => Ok(ty.clone()),
+        Some(Value::StructConstructor { name, field_types }) => Ok(Type::Struct {
+            name: *name,
+            fields: field_types.clone(),
+        }),
+        _ => Err(Diagnostic::syntax(format!("unknown type '{}'", &*field)).with_span(span)),
+    }
+}
+
+fn ir_enum(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "enum special form IR generation not yet implemented",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Env};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> (Vec<Value>, Env, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        (results, env, compiler)
+    }
+
+    #[test]
+    fn test_enum_definition() {
+        let (results, env, compiler) = eval_all("enum Shape = Circle Float | Rect w h | Empty");
+
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        match &results[0] {
+            Value::Type(Type::Enum { name, variants }) => {
+                assert_eq!(&**name, "Shape");
+                let variants: Vec<_> = variants
+                    .iter()
+                    .map(|(name, fields)| (name.to_string(), fields.clone()))
+                    .collect();
+                assert_eq!(
+                    variants,
+                    vec![
+                        ("Circle".to_string(), vec![Type::Float]),
+                        ("Rect".to_string(), vec![Type::Unknown, Type::Unknown]),
+                        ("Empty".to_string(), vec![]),
+                    ]
+                );
+            }
+            other => panic!("Expected enum type, got {other:?}"),
+        }
+        assert!(matches!(
+            env.get(InternedString::new("Circle")),
+            Some(Value::VariantConstructor { .. })
+        ));
+        assert!(matches!(
+            env.get(InternedString::new("Empty")),
+            Some(Value::Variant { .. })
+        ));
+    }
+
+    #[test]
+    fn test_enum_one_variant_per_line() {
+        let (results, _, compiler) = eval_all(
+            "
+enum Color =
+    | Red
+    | Green
+    | Blue
+",
+        );
+
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(
+            results[0].to_string(),
+            "enum Color = Red | Green | Blue",
+            "{:?}",
+            results[0]
+        );
+    }
+
+    #[test]
+    fn test_enum_variant_must_be_capitalised() {
+        let (_, _, compiler) = eval_all("enum Shape = circle r");
+
+        assert!(compiler.has_errors());
+    }
+}
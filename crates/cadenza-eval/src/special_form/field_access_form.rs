@@ -9,27 +9,37 @@ use crate::{
     special_form::BuiltinSpecialForm,
     value::{Type, Value},
 };
-use cadenza_syntax::ast::Expr;
+use cadenza_syntax::ast::{Expr, LiteralValue};
 use std::sync::OnceLock;
 
 /// Returns the `.` special form for field access.
 ///
-/// The `.` special form accesses a field from a record value.
+/// The `.` special form accesses a field from a record value, or an element
+/// of a tuple by its index.
 ///
 /// # Evaluation
 /// - Takes exactly 2 arguments: record expression and field name identifier
+///   or element index
 /// - Evaluates the record expression
 /// - Extracts the field name (unevaluated identifier)
-/// - Returns the field value from the record
+/// - Returns the field value from the record, or the element of the tuple
+///
+/// The lexer reads `pair.0.1` as `pair` and the number `0.1`, so a number
+/// with a fraction indexes twice: element 1 of element 0.
 ///
 /// # IR Generation
-/// - Not yet implemented (returns error)
+/// - Generates a field access for each index of a tuple element
+/// - Record fields are not yet implemented (returns error)
 ///
 /// # Examples
 /// ```cadenza
 /// let point = { x = 10, y = 20 }
 /// point.x  # returns 10
 /// point.y  # returns 20
+///
+/// let pair = ((1, 2), 3)
+/// pair.1    # returns 3
+/// pair.0.1  # returns 2
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static FIELD_ACCESS_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
@@ -50,6 +60,12 @@ fn eval_field_access(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value>
     // Evaluate the record (first argument)
     let record_value = args[0].eval(ctx)?;
 
+    if let Some(indices) = tuple_indices(&args[1]) {
+        return indices.into_iter().try_fold(record_value, |value, index| {
+            element(value, index).map_err(|error| error.with_span(args[1].span()))
+        });
+    }
+
     // Extract the field name from the second argument (must be an identifier)
     let (field_name, field_span) = match &args[1] {
         Expr::Ident(ident) => {
@@ -57,7 +73,11 @@ fn eval_field_access(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value>
             let id: InternedString = text.to_string().as_str().into();
             (id, ident.span())
         }
-        _ => return Err(Diagnostic::syntax("field name must be an identifier")),
+        other => {
+            return Err(
+                Diagnostic::syntax("field name must be an identifier").with_span(other.span())
+            );
+        }
     };
 
     // Extract the record fields
@@ -74,35 +94,90 @@ fn eval_field_access(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value>
                 Some(name) => format!("struct {}", &*name),
                 None => "record".to_string(),
             };
-            Err(Diagnostic::syntax(format!(
-                "field '{}' not found in {}",
-                &*field_name, type_description
-            ))
-            .with_span(field_span))
+            Err(Diagnostic::field_not_found(field_name, type_description).with_span(field_span))
         }
-        other => Err(Diagnostic::type_error(
-            Type::Record(vec![]),
-            other.type_of(),
-        )),
+        other => Err(
+            Diagnostic::type_error(Type::Record(vec![]), other.type_of()).with_span(args[0].span()),
+        ),
+    }
+}
+
+/// Returns the element indices `expr` names if it's the right-hand side of a
+/// tuple projection: an integer, or a number with a fraction for two indices.
+pub(crate) fn tuple_indices(expr: &Expr) -> Option<Vec<usize>> {
+    let Expr::Literal(literal) = expr else {
+        return None;
+    };
+    let text = match literal.value()? {
+        LiteralValue::Integer(integer) => integer.syntax().text().to_string(),
+        LiteralValue::Float(float) => float.syntax().text().to_string(),
+        _ => return None,
+    };
+    text.split('.')
+        .map(|index| {
+            index
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+                .then(|| index.parse().ok())
+                .flatten()
+        })
+        .collect()
+}
+
+/// Returns element `index` of the tuple `value`.
+fn element(value: Value, index: usize) -> Result<Value> {
+    match value {
+        Value::Tuple { mut elements, .. } => {
+            let len = elements.len();
+            if index >= len {
+                return Err(Diagnostic::index_out_of_bounds(index, len));
+            }
+            Ok(elements.swap_remove(index))
+        }
+        other => Err(Diagnostic::type_error(Type::Tuple(vec![]), other.type_of())),
     }
 }
 
 fn ir_field_access(
-    _args: &[Expr],
-    _block: &mut BlockBuilder,
-    _ctx: &mut IrGenContext,
-    _source: SourceLocation,
-    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+    args: &[Expr],
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
 ) -> Result<ValueId> {
-    Err(Diagnostic::syntax(
-        ". special form IR generation not yet implemented",
-    ))
+    let [tuple, index] = args else {
+        return Err(Diagnostic::arity(2, args.len()));
+    };
+    let Some(indices) = tuple_indices(index) else {
+        return Err(Diagnostic::syntax(
+            ". special form IR generation not yet implemented for records",
+        ));
+    };
+
+    let mut value = gen_expr(tuple, block, ctx)?;
+    for index in indices {
+        let ty = match ctx.get_value_type(value) {
+            Some(Type::Tuple(types)) => types.get(index).cloned().ok_or_else(|| {
+                Diagnostic::index_out_of_bounds(index, types.len()).with_span(args[1].span())
+            })?,
+            Some(Type::Unknown) | None => Type::Unknown,
+            Some(ty) => {
+                return Err(
+                    Diagnostic::type_error(Type::Tuple(vec![]), ty.clone()).with_span(tuple.span())
+                );
+            }
+        };
+        let field = InternedString::new(&index.to_string());
+        value = block.field(value, field, ty.clone(), source);
+        ctx.set_value_type(value, ty);
+    }
+    Ok(value)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Compiler, Env};
+    use crate::{Compiler, DiagnosticKind, Env};
     use cadenza_syntax::parse::parse;
 
     #[test]
@@ -158,4 +233,32 @@ fn make_point x y = { x = x, y = y }
         assert_eq!(results.len(), 2);
         assert_eq!(results[1], Value::Integer(5));
     }
+
+    #[test]
+    fn test_tuple_element_access() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = r#"
+let pair = ((1, 2), "three")
+pair.1
+pair.0.1
+pair.2
+"#;
+        let parsed = parse(input);
+        let root = parsed.ast();
+
+        let results = crate::eval(&root, &mut env, &mut compiler);
+
+        assert_eq!(results[1], Value::String("three".into()));
+        assert_eq!(results[2], Value::Integer(2));
+        let kinds: Vec<_> = compiler.diagnostics().iter().map(|d| d.kind()).collect();
+        assert!(
+            matches!(
+                kinds.as_slice(),
+                [DiagnosticKind::IndexOutOfBounds { index: 2, len: 2 }]
+            ),
+            "{kinds:?}"
+        );
+    }
 }
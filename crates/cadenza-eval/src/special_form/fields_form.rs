@@ -0,0 +1,109 @@
+//! The `fields` special form for record and struct field queries.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{Diagnostic, Result},
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::sync::OnceLock;
+
+/// Returns the `fields` special form for querying the fields of a record or
+/// struct.
+///
+/// # Evaluation
+/// - Takes 1 argument: a record literal, a struct name, or an expression of
+///   record or struct type
+/// - Looks up the fields without evaluating the argument
+/// - Reports an error if a name it uses isn't defined yet
+/// - Returns a record of each field's type, in field order
+///
+/// # IR Generation
+/// - Not yet supported for fields
+///
+/// # Examples
+/// ```cadenza
+/// struct Point { x = Float, y = Float }
+/// fields Point           // Returns {x = float, y = float}
+/// fields { a = 1 }       // Returns {a = integer}
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static FIELDS_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    FIELDS_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "fields",
+        signature: Type::function(vec![Type::Unknown], Type::Record(vec![])),
+        eval_fn: eval_fields,
+        ir_fn: ir_fields,
+    })
+}
+
+fn eval_fields(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let [expr] = args else {
+        return Err(Diagnostic::syntax("fields expects 1 argument: record"));
+    };
+    let fields = ctx
+        .reflect_fields(expr)?
+        .into_iter()
+        .map(|(name, ty)| (name, Value::Type(ty)))
+        .collect();
+    Ok(Value::Record {
+        type_name: None,
+        fields,
+    })
+}
+
+fn ir_fields(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "fields not yet supported in IR generation",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env};
+    use cadenza_syntax::parse::parse;
+
+    #[test]
+    fn test_fields_of_structs_and_records() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse(
+            "
+struct Point { x = Float, y = Float }
+let p = Point { x = 1.0, y = 2.0 }
+fields Point
+fields p
+fields { a = 1, b = \"two\" }
+",
+        )
+        .ast();
+        let results = crate::eval(&root, &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        let shown: Vec<String> = results[2..].iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            shown,
+            [
+                "{x = float, y = float}",
+                "{x = float, y = float}",
+                "{a = integer, b = string}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fields_before_definition_is_an_error() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse("fields p\nlet p = { a = 1 }\n").ast();
+        crate::eval(&root, &mut env, &mut compiler);
+        assert_eq!(compiler.diagnostics()[0].kind().code(), "E0019");
+    }
+}
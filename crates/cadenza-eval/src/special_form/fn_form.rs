@@ -2,13 +2,16 @@
 
 use crate::{
     context::EvalContext,
-    diagnostic::{Diagnostic, Result},
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, DiagnosticKind, DiagnosticLevel, Result},
     interner::InternedString,
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
-    special_form::BuiltinSpecialForm,
+    memo::{self, FunctionId},
+    pattern,
+    special_form::{BuiltinSpecialForm, attr_form},
+    totality,
     value::{Type, UserFunction, Value},
 };
-use cadenza_syntax::ast::Expr;
+use cadenza_syntax::{ast::Expr, span::Span};
 use std::sync::OnceLock;
 
 /// Returns the `fn` special form for function definitions.
@@ -17,17 +20,29 @@ use std::sync::OnceLock;
 ///
 /// # Evaluation
 /// - Takes at least 2 arguments: function name, parameters..., and body
+/// - A parameter is a name or a record, tuple or list pattern (see
+///   [`crate::pattern`]) that destructures its argument at each call
 /// - Creates a UserFunction value capturing the environment
 /// - Registers the function in the compiler (hoisting)
+/// - Applies pending attributes such as `@memoize`, and checks a `@total`
+///   function terminates (see [`crate::totality`])
 /// - Returns Nil
 ///
 /// # IR Generation
-/// - Not yet implemented (returns error)
+/// - Top-level functions get their own IR function when defined
+/// - Functions defined in a nested scope are lambda-lifted when their
+///   enclosing function is generated (see [`crate::ir`]), so they get no IR of
+///   their own here
+/// - Using a linear value after it has moved is reported as an error when
+///   the function's IR is generated
+/// - The special form itself has no IR (returns error)
 ///
 /// # Examples
 /// ```cadenza
 /// fn add a b = a + b
 /// fn zero_arity = 42
+/// fn norm { x, y } = x * x + y * y
+/// fn head ([first, second]) = first
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static FN_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
@@ -69,26 +84,40 @@ fn handle_function_definition(
     // First argument is the function name
     let name_ident = match &fn_args[0] {
         Expr::Ident(i) => i,
-        _ => {
-            return Err(Diagnostic::syntax(
-                "fn requires an identifier as the function name",
-            ));
+        other => {
+            return Err(
+                Diagnostic::syntax("fn requires an identifier as the function name")
+                    .with_span(other.span()),
+            );
         }
     };
+    let name_span = name_ident.span();
     let name_text = name_ident.syntax().text();
     let name: InternedString = name_text.to_string().as_str().into();
 
-    // Remaining arguments are parameters
+    // Remaining arguments are parameters, each a name or a pattern
     let mut params = Vec::new();
-    for arg in &fn_args[1..] {
+    let mut patterns = Vec::new();
+    for (i, arg) in fn_args[1..].iter().enumerate() {
         match arg {
             Expr::Ident(ident) => {
                 let param_text = ident.syntax().text();
                 let param_name: InternedString = param_text.to_string().as_str().into();
                 params.push(param_name);
             }
-            _ => {
-                return Err(Diagnostic::syntax("fn parameters must be identifiers"));
+            pattern if pattern::is_pattern(pattern) => {
+                // The argument is bound to a name no source can refer to,
+                // then destructured
+                pattern::Pattern::parse(pattern)?;
+                let param_name = InternedString::new(&format!("__param{i}__"));
+                params.push(param_name);
+                patterns.push((param_name, pattern.clone()));
+            }
+            other => {
+                return Err(
+                    Diagnostic::syntax("fn parameters must be identifiers or patterns")
+                        .with_span(other.span()),
+                );
             }
         }
     }
@@ -103,6 +132,7 @@ fn handle_function_definition(
     let user_fn_value = UserFunction {
         name,
         params,
+        patterns,
         body,
         captured_env,
     };
@@ -110,29 +140,120 @@ fn handle_function_definition(
     // Generate IR for the function if IR generation is enabled and it hasn't been generated already
     // This check prevents duplicate IR generation during hoisting and regular evaluation
     // Do this before moving the value into the compiler
-    if let Some(ir_gen) = ctx.compiler.ir_generator()
+    // Nested functions are lambda-lifted with their enclosing function instead
+    if ctx.env.depth() == 1
+        && let Some(ir_gen) = ctx.compiler.ir_generator()
         && !ir_gen.has_function(name)
         && let Some(Err(err)) = ctx
             .compiler
             .generate_ir_for_function(&user_fn_value, ctx.env)
     {
-        // Record as a warning diagnostic instead of printing to stderr
-        let warning = Diagnostic::syntax(format!(
-            "Failed to generate IR for function {}: {}",
-            name, err
-        ))
-        .set_level(crate::diagnostic::DiagnosticLevel::Warning);
-        ctx.compiler.record_diagnostic(warning);
+        if matches!(err.kind(), DiagnosticKind::UseAfterMove(_)) {
+            // Ownership errors are errors in the program, not in IR generation
+            ctx.compiler.record_diagnostic(*err.or_span(name_span));
+        } else {
+            // Record as a warning diagnostic instead of printing to stderr
+            let warning = Diagnostic::syntax(format!(
+                "Failed to generate IR for function {}: {}",
+                name, err
+            ))
+            .with_span(name_span)
+            .set_level(DiagnosticLevel::Warning);
+            ctx.compiler.record_diagnostic(*warning);
+        }
+    }
+
+    // Redefining a top-level function drops the cached results of the one it
+    // replaces. Nested functions only shadow, so the caches of functions
+    // with the same name are left alone
+    if ctx.env.depth() == 1
+        && let Some(Value::UserFunction(previous)) = ctx.compiler.get_var(name)
+    {
+        let previous = FunctionId::of(previous);
+        ctx.compiler.memo_mut().invalidate(&previous);
     }
+    // Evaluating the definition again starts from an empty cache, if any
+    let id = FunctionId::of(&user_fn_value);
+    ctx.compiler.memo_mut().invalidate(&id);
 
     // Register the function in the compiler (hoisting)
+    let arity = user_fn_value.params.len();
     ctx.compiler
         .define_var(name, Value::UserFunction(user_fn_value));
 
+    apply_attributes(name, id, name_span, arity, ctx)?;
+
     // Return nil
     Ok(Value::Nil)
 }
 
+/// Applies the pending attributes to the function that was just defined.
+///
+/// `id` identifies the function's definition, `span` is the span of the
+/// function name, where attribute diagnostics are reported, and `params` is
+/// how many parameters the function takes.
+fn apply_attributes(
+    name: InternedString,
+    id: FunctionId,
+    span: Span,
+    params: usize,
+    ctx: &mut EvalContext<'_>,
+) -> Result<()> {
+    for attr in ctx.compiler.take_attributes() {
+        match &*attr.name {
+            "memoize" => {
+                let limit = match attr.args.as_slice() {
+                    [] => memo::DEFAULT_LIMIT,
+                    [Value::Integer(n)] if *n >= 0 => *n as usize,
+                    [Value::Integer(n)] => {
+                        return Err(Diagnostic::syntax(format!(
+                            "@memoize limit must be non-negative, got {n}"
+                        ))
+                        .with_span(span));
+                    }
+                    [other] => {
+                        return Err(
+                            Diagnostic::type_error(Type::Integer, other.type_of()).with_span(span)
+                        );
+                    }
+                    args => return Err(Diagnostic::arity(1, args.len()).with_span(span)),
+                };
+                ctx.compiler.memo_mut().enable(id.clone(), limit);
+            }
+            "test" => {
+                if params > 0 {
+                    return Err(Diagnostic::syntax(format!(
+                        "test function {name} must not take parameters"
+                    ))
+                    .with_span(span));
+                }
+                ctx.compiler.add_test(name);
+            }
+            "export" => attr_form::export(name, span, &attr.args, ctx)?,
+            "total" => {
+                if !attr.args.is_empty() {
+                    return Err(Diagnostic::arity(0, attr.args.len()).with_span(span));
+                }
+                let warnings = match ctx.compiler.get_var(name) {
+                    Some(Value::UserFunction(function)) => totality::check(function, ctx.compiler),
+                    _ => Vec::new(),
+                };
+                for warning in warnings {
+                    ctx.compiler.record_diagnostic(warning);
+                }
+            }
+            other => {
+                let warning =
+                    Diagnostic::syntax(format!("unknown attribute @{other} on function {name}"))
+                        .with_span(span)
+                        .set_level(DiagnosticLevel::Warning);
+                ctx.compiler.record_diagnostic(*warning);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn ir_fn(
     _args: &[Expr],
     _block: &mut BlockBuilder,
@@ -173,6 +294,31 @@ add 1 2
         assert_eq!(results[1], Value::Integer(3));
     }
 
+    #[test]
+    fn test_fn_destructures_pattern_parameters() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = r#"
+fn norm { x, y } = x * x + y * y
+fn second [a, b] (c, d) = b + d
+norm { x = 3, y = 4 }
+second [1, 2] (3, 4)
+second [1] (3, 4)
+"#;
+        let root = parse(input).ast();
+        let results = crate::eval(&root, &mut env, &mut compiler);
+
+        assert_eq!(results[2..4], [Value::Integer(25), Value::Integer(6)]);
+        let diagnostics = compiler.diagnostics();
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .contains("list pattern expects 2 elements, but the list has 1")
+        );
+    }
+
     #[test]
     fn test_fn_zero_arity() {
         let mut env = Env::with_standard_builtins();
@@ -191,4 +337,145 @@ get_value
         assert_eq!(results[0], Value::Nil);
         assert_eq!(results[1], Value::Integer(42));
     }
+
+    #[test]
+    fn test_fn_memoize_caches_results() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = r#"
+@memoize 8
+fn square x = x * x
+square 4
+square 4
+square 5
+"#;
+        let parsed = parse(input);
+        let results = crate::eval(&parsed.ast(), &mut env, &mut compiler);
+
+        assert!(compiler.diagnostics().is_empty());
+        assert_eq!(results[3], Value::Integer(16));
+        let cache = compiler
+            .memo()
+            .cache(&function_id(&compiler, "square"))
+            .unwrap();
+        assert_eq!(cache.limit(), 8);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn test_fn_redefinition_invalidates_memo() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let parsed = parse("@memoize\nfn f x = x\nf 1\n");
+        crate::eval(&parsed.ast(), &mut env, &mut compiler);
+        let first = function_id(&compiler, "f");
+        assert!(compiler.memo().is_memoized(&first));
+
+        let parsed = parse("fn f x = x + 1\nf 1\n");
+        let results = crate::eval(&parsed.ast(), &mut env, &mut compiler);
+        assert!(!compiler.memo().is_memoized(&first));
+        assert!(!compiler.memo().is_memoized(&function_id(&compiler, "f")));
+        assert_eq!(results[1], Value::Integer(2));
+    }
+
+    #[test]
+    fn test_fn_memo_is_per_definition() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let first = "@memoize\nfn f x = x * 10\nf 1\n";
+        let results = crate::eval(&parse(first).ast(), &mut env, &mut compiler);
+        assert_eq!(results.last(), Some(&Value::Integer(10)));
+        let first = function_id(&compiler, "f");
+
+        // The nested `f` has a cache of its own, so it doesn't find the first
+        // one's result for the argument, or drop the first one's cache
+        let nested = r#"
+fn g x =
+    @memoize
+    fn f y = y + 1
+    f x
+g 1
+"#;
+        let results = crate::eval(&parse(nested).ast(), &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results.last(), Some(&Value::Integer(2)));
+        assert_eq!(
+            compiler.memo().cache(&first).map(|cache| cache.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_fn_memoize_negative_limit() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        crate::eval(
+            &parse("@memoize (0 - 1)\nfn f x = x\n").ast(),
+            &mut env,
+            &mut compiler,
+        );
+        let diagnostics = compiler.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .contains("@memoize limit must be non-negative, got -1"),
+            "{diagnostics:?}"
+        );
+    }
+
+    /// Returns the definition of the function `name` defines.
+    fn function_id(compiler: &Compiler, name: &str) -> FunctionId {
+        match compiler.get_var(name.into()) {
+            Some(Value::UserFunction(function)) => FunctionId::of(function),
+            other => panic!("{name} isn't a function: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_fn_test_attribute_registers_test() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let parsed = parse("@test\nfn passes = assert true\n@test\nfn takes x = x\n");
+        crate::eval(&parsed.ast(), &mut env, &mut compiler);
+
+        assert_eq!(compiler.tests(), ["passes".into()]);
+        assert!(compiler.has_errors());
+    }
+
+    #[test]
+    fn test_fn_export_attribute_records_export() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let parsed = parse("@export\nfn area w h = w * h\n@export \"next\"\nfn inc x = x + 1\n");
+        crate::eval(&parsed.ast(), &mut env, &mut compiler);
+
+        let exports: Vec<_> = compiler
+            .exports()
+            .iter()
+            .map(|export| (&*export.name, &*export.export_name))
+            .collect();
+        assert_eq!(exports, [("area", "area"), ("inc", "next")]);
+        assert!(!compiler.has_errors());
+    }
+
+    #[test]
+    fn test_attribute_without_definition_warns() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let parsed = parse("@memoize\nlet x = 1\n");
+        crate::eval(&parsed.ast(), &mut env, &mut compiler);
+
+        assert!(!compiler.has_errors());
+        assert_eq!(compiler.num_diagnostics(), 1);
+        assert!(compiler.pending_attributes().is_empty());
+    }
 }
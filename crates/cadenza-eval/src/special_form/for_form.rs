@@ -0,0 +1,385 @@
+//! The `for` special form for loops.
+
+use crate::{
+    Eval,
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::{Tail, eval_ident_no_auto_apply, extract_identifier},
+    interner::InternedString,
+    ir::{BinOp, BlockBuilder, IrConst, IrGenContext, IrGenState, SourceLocation, ValueId},
+    list,
+    special_form::{BuiltinSpecialForm, match_form::GenExpr},
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::sync::OnceLock;
+
+/// Returns the `for` special form for loops.
+///
+/// A loop is written as an anonymous function whose parameters start with
+/// `for`: `for x in xs -> body` evaluates `body` once for each element of
+/// `xs`, with `x` bound to it. The list may be a range (`for i in 0..n`) or
+/// an application without parentheses (`for i in range 0 n -> body`). The
+/// loop returns nil,
+/// so a body that computes something assigns it to a variable declared
+/// before the loop.
+///
+/// # Evaluation
+/// - Evaluates the list, which must be a list or range
+/// - Evaluates the body in a new scope for each element, in order
+/// - Returns nil
+///
+/// # IR Generation
+/// - Only loops over `start..end`, `start..=last` and `range start end` are
+///   supported, as there are no lists in compiled code
+/// - Generates a header block that tests the counter, the body, and a jump
+///   back to the header that increments it
+/// - A variable the body assigns gets a phi in the header joining its value
+///   before the loop with its value at the end of the body
+///
+/// # Examples
+/// ```cadenza
+/// for name in ["ada", "grace"] -> write_line name
+///
+/// fn triangle n =
+///     let total = 0
+///     for i in 1..=n ->
+///         total = total + i
+///     total
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static FOR_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    FOR_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "for",
+        signature: Type::function(vec![Type::Unknown], Type::Nil),
+        eval_fn: eval_for_without_body,
+        ir_fn: ir_for_without_body,
+    })
+}
+
+/// Returns the arguments of `for` if `expr` is the left-hand side of a loop:
+/// `for x in xs`.
+pub(crate) fn header(expr: &Expr) -> Option<Vec<Expr>> {
+    let Expr::Apply(apply) = expr else {
+        return None;
+    };
+    let callee = apply.callee()?;
+    extract_identifier(&callee)
+        .is_some_and(|id| &*id == "for")
+        .then(|| apply.all_arguments())
+}
+
+/// Splits the arguments of `for` into the loop variable and the parts of the
+/// list after `in`.
+fn split(header: &[Expr]) -> Result<(InternedString, &[Expr])> {
+    let [Expr::Ident(var), keyword, list @ ..] = header else {
+        return Err(Diagnostic::syntax("for expects `for x in xs -> body`"));
+    };
+    if extract_identifier(keyword).is_none_or(|id| &*id != "in") {
+        return Err(
+            Diagnostic::syntax("for expects `in` after the loop variable")
+                .with_span(keyword.span()),
+        );
+    }
+    if list.is_empty() {
+        return Err(
+            Diagnostic::syntax("for is missing a list after `in`").with_span(keyword.span())
+        );
+    }
+    Ok((var.syntax().text().interned(), list))
+}
+
+fn eval_for_without_body(_args: &[Expr], _ctx: &mut EvalContext<'_>) -> Result<Value> {
+    Err(Diagnostic::syntax(
+        "for needs a body: `for x in xs -> body`",
+    ))
+}
+
+/// Evaluates the loop `for header -> body`.
+pub(crate) fn eval_for(header: &[Expr], body: &Expr, ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let (var, list) = split(header)?;
+    let value = eval_list(list, ctx)?;
+    let Some(items) = list::elements(&value) else {
+        return Err(
+            Diagnostic::type_error(Type::list(Type::Unknown), value.type_of())
+                .with_span(list[0].span()),
+        );
+    };
+
+    for item in items {
+        ctx.env.push_scope();
+        ctx.env.define(var, item);
+        let result = body.eval(ctx);
+        ctx.env.pop_scope();
+        result?;
+    }
+    Ok(Value::Nil)
+}
+
+/// Evaluates the list of a loop: one expression, or an application of the
+/// first expression to the rest.
+pub(crate) fn eval_list(list: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let [callee, args @ ..] = list else {
+        unreachable!("the list of a loop is never empty");
+    };
+    if args.is_empty() {
+        return callee.eval(ctx);
+    }
+    let func = match callee {
+        Expr::Ident(ident) => eval_ident_no_auto_apply(ident, ctx)?,
+        _ => callee.eval(ctx)?,
+    };
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        values.push(arg.eval(ctx)?);
+    }
+    Tail::Call {
+        callee: func,
+        args: values,
+        span: callee.span().merge(args[args.len() - 1].span()),
+    }
+    .finish(ctx)
+}
+
+fn ir_for_without_body(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "for needs a body: `for x in xs -> body`",
+    ))
+}
+
+/// IR generation for the loop `for header -> body`, with multi-block
+/// support.
+///
+/// The loop lowers to a header block, which joins the counter and the
+/// variables the body assigns with phis and branches on `counter < end`,
+/// the body, which ends by jumping back to the header, and an exit block,
+/// where generation continues with those variables bound to the header's
+/// phis.
+pub fn ir_for_with_state(
+    header: &[Expr],
+    body: &Expr,
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_expr: &mut GenExpr<'_>,
+) -> Result<ValueId> {
+    let (var, list) = split(header)?;
+    let (start, end, inclusive) = range_bounds(list)?;
+
+    // Generate the bounds before the loop
+    let start = gen_expr(&start, state, ctx)?;
+    let mut end = gen_expr(&end, state, ctx)?;
+    if inclusive {
+        let block = state
+            .current_block
+            .as_mut()
+            .expect("No current block after generating the loop bounds");
+        let one = block.const_val(IrConst::Integer(1), Type::Integer, source);
+        end = block.binop(BinOp::Add, end, one, Type::Integer, source);
+        ctx.set_value_type(end, Type::Integer);
+    }
+
+    // The variables declared before the loop that the body assigns are
+    // carried from one iteration to the next
+    let mut carried = Vec::new();
+    assigned_variables(body, &mut carried);
+    carried.retain(|&name| name != var && ctx.lookup_var(name).is_some());
+    let locals = {
+        let mut locals = vec![var];
+        declared_variables(body, &mut locals);
+        locals
+    };
+    let saved = ctx.save_vars(&locals);
+
+    let header_id = state.alloc_block_id();
+    let body_id = state.alloc_block_id();
+    let exit_id = state.alloc_block_id();
+
+    let entry = state
+        .current_block
+        .take()
+        .expect("No entry block available for the loop");
+    let entry_id = entry.id();
+    let (entry, next_val) = entry.jump(header_id, source);
+    state.complete_current_block(entry, next_val);
+
+    // The header starts each iteration, with phis for the counter and each
+    // carried variable whose values from the body are added below
+    let mut header_block = state.create_block_with_id(header_id);
+    let counter = header_block.phi(vec![(start, entry_id)], Type::Integer, source);
+    ctx.set_value_type(counter, Type::Integer);
+    let mut phis = Vec::with_capacity(carried.len());
+    for &name in &carried {
+        let value = ctx.lookup_var(name).expect("carried variables are bound");
+        let ty = ctx.get_value_type(value).cloned().unwrap_or(Type::Unknown);
+        let phi = header_block.phi(vec![(value, entry_id)], ty.clone(), source);
+        ctx.set_value_type(phi, ty.clone());
+        ctx.bind_var(name, phi, &crate::InferType::Concrete(ty));
+        phis.push(phi);
+    }
+    // A comparison takes the type of its operands
+    let cond = header_block.binop(BinOp::Lt, counter, end, Type::Integer, source);
+    ctx.set_value_type(cond, Type::Bool);
+    let (header_block, next_val) = header_block.branch(cond, body_id, exit_id, source);
+    state.complete_current_block(header_block, next_val);
+
+    // The body, which may end in a merge block of its own, increments the
+    // counter and jumps back to the header
+    ctx.bind_var(var, counter, &crate::InferType::Concrete(Type::Integer));
+    state.current_block = Some(state.create_block_with_id(body_id));
+    gen_expr(body, state, ctx)?;
+    let mut latch = state
+        .current_block
+        .take()
+        .expect("Current block missing after generating the loop body");
+    let latch_id = latch.id();
+    let one = latch.const_val(IrConst::Integer(1), Type::Integer, source);
+    let next = latch.binop(BinOp::Add, counter, one, Type::Integer, source);
+    ctx.set_value_type(next, Type::Integer);
+    let (latch, next_val) = latch.jump(header_id, source);
+    state.complete_current_block(latch, next_val);
+
+    state.add_phi_incoming(header_id, counter, next, latch_id);
+    for (&name, &phi) in carried.iter().zip(&phis) {
+        let value = ctx.lookup_var(name).expect("carried variables are bound");
+        state.add_phi_incoming(header_id, phi, value, latch_id);
+    }
+
+    // After the loop, the carried variables hold their values from the
+    // header, and the names the body declared go out of scope
+    ctx.restore_vars(saved);
+    for (&name, &phi) in carried.iter().zip(&phis) {
+        let ty = ctx.get_value_type(phi).cloned().unwrap_or(Type::Unknown);
+        ctx.bind_var(name, phi, &crate::InferType::Concrete(ty));
+    }
+    let mut exit = state.create_block_with_id(exit_id);
+    let result = exit.const_val(IrConst::Nil, Type::Nil, source);
+    ctx.set_value_type(result, Type::Nil);
+    state.current_block = Some(exit);
+    Ok(result)
+}
+
+/// Returns the bounds of the list of a loop if it is `start..end`,
+/// `start..=last` or `range start end`, the only lists a compiled loop can
+/// iterate over, and whether the end is included.
+fn range_bounds(list: &[Expr]) -> Result<(Expr, Expr, bool)> {
+    let parts = match list {
+        [Expr::Apply(apply)] => {
+            let mut parts: Vec<Expr> = apply.callee().into_iter().collect();
+            parts.extend(apply.all_arguments());
+            parts
+        }
+        _ => list.to_vec(),
+    };
+    let inclusive = match &parts[..] {
+        [callee, _, _] => match extract_identifier(callee).as_deref() {
+            Some("range" | "..") => Some(false),
+            Some("..=") => Some(true),
+            _ => None,
+        },
+        _ => None,
+    };
+    match (&parts[..], inclusive) {
+        ([_, start, end], Some(inclusive)) => Ok((start.clone(), end.clone(), inclusive)),
+        _ => Err(
+            Diagnostic::syntax("only loops over a range like `start..end` can be compiled")
+                .with_span(list[0].span()),
+        ),
+    }
+}
+
+/// Collects the names `expr` reassigns with `name = value`, in order.
+fn assigned_variables(expr: &Expr, names: &mut Vec<InternedString>) {
+    visit_bindings(expr, &mut |keyword, name| {
+        if keyword.is_none() && !names.contains(&name) {
+            names.push(name);
+        }
+    });
+}
+
+/// Collects the names `expr` declares with `let name = value`.
+fn declared_variables(expr: &Expr, names: &mut Vec<InternedString>) {
+    visit_bindings(expr, &mut |keyword, name| {
+        if keyword.is_some() && !names.contains(&name) {
+            names.push(name);
+        }
+    });
+}
+
+/// Calls `f` with each binding of a plain name in `expr`: `name = value`
+/// with no keyword, and `let name = value` with `let`.
+fn visit_bindings(expr: &Expr, f: &mut dyn FnMut(Option<InternedString>, InternedString)) {
+    let Expr::Apply(apply) = expr else {
+        return;
+    };
+    let args = apply.all_arguments();
+    if let Some(callee) = apply.callee()
+        && extract_identifier(&callee).is_some_and(|id| &*id == "=")
+        && let Some(lhs) = args.first()
+    {
+        match lhs {
+            Expr::Ident(ident) => f(None, ident.syntax().text().interned()),
+            Expr::Apply(binding) => {
+                let keyword = binding
+                    .callee()
+                    .and_then(|callee| extract_identifier(&callee));
+                if let [Expr::Ident(ident)] = &binding.all_arguments()[..] {
+                    f(keyword, ident.syntax().text().interned());
+                }
+            }
+            _ => {}
+        }
+    }
+    for arg in &args {
+        visit_bindings(arg, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> Vec<Value> {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse(input).ast();
+        let results = crate::eval(&root, &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        results
+    }
+
+    #[test]
+    fn test_for_evaluates_the_body_for_each_element() {
+        let results = eval_all(
+            "
+let total = 0
+for x in [1, 2, 3] -> total = total + x
+for i in range 0 4 ->
+    let square = i * i
+    total = total + square
+for i in 1..=3 -> total = total + i
+total
+",
+        );
+        assert_eq!(
+            results[1..],
+            [Value::Nil, Value::Nil, Value::Nil, Value::Integer(26)]
+        );
+    }
+
+    #[test]
+    fn test_for_requires_a_list() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse("\nfor x in 3 -> x\n").ast();
+        crate::eval(&root, &mut env, &mut compiler);
+        assert!(compiler.has_errors());
+    }
+}
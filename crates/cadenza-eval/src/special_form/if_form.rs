@@ -0,0 +1,539 @@
+//! The `if` special form for conditionals.
+
+use crate::{
+    Eval,
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::{self, Tail, eval_ident_no_auto_apply, extract_identifier},
+    interner::InternedString,
+    ir::{BlockBuilder, BlockId, IrGenContext, IrGenState, SourceLocation, ValueId},
+    special_form::{BuiltinSpecialForm, match_form::GenExpr, try_form},
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::{collections::HashMap, sync::OnceLock};
+
+/// Returns the `if` special form for conditionals.
+///
+/// The `if` special form evaluates one of two branches depending on a
+/// boolean condition. The parser passes `if`, `then` and `else` through as
+/// plain arguments, so the form splits them itself: the condition runs up to
+/// `then`, the consequent up to the matching `else`, and the alternative to
+/// the end. A part written as several expressions (`then f x`) applies the
+/// first to the rest, and an `else` part starting with `if` is a nested
+/// conditional.
+///
+/// # Evaluation
+/// - Evaluates the condition (must be boolean)
+/// - Evaluates and returns the consequent if it's true, or else the
+///   alternative
+/// - Returns nil if the condition is false and there is no `else`
+///
+/// # IR Generation
+/// - Generates a branch on the condition to a block for each part
+/// - Joins the parts' results with a phi in a merge block, along with the
+///   values of each variable the parts reassign
+/// - A part that is an `Err` or a `None` makes the function fail instead
+/// - Requires an `else` part
+///
+/// # Examples
+/// ```cadenza
+/// if x > 0 then "positive" else "not positive"
+/// let magnitude = if x < 0 then 0 - x else x
+/// if n <= 1 then 1 else n * (fact (n - 1))
+/// if x > 0 then 1 else if x < 0 then 0 - 1 else 0
+///
+/// if ready
+///     then start x
+///     else wait x
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static IF_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    IF_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "if",
+        signature: Type::function(vec![Type::Bool], Type::Unknown),
+        eval_fn: eval_if,
+        ir_fn: ir_if,
+    })
+}
+
+/// The parts of an `if` expression, each a run of one or more expressions.
+pub(crate) struct Parts<'a> {
+    pub condition: &'a [Expr],
+    pub consequent: &'a [Expr],
+    pub alternative: Option<&'a [Expr]>,
+}
+
+/// Splits the arguments of `if` at its `then` and `else`.
+///
+/// An `else` belongs to the closest `if` before it that doesn't have one
+/// yet, so `if a then if b then 1 else 2` nests the `else` in the inner `if`.
+pub(crate) fn split(args: &[Expr]) -> Result<Parts<'_>> {
+    let then = args
+        .iter()
+        .position(|arg| is_keyword(arg, "then"))
+        .ok_or_else(|| Diagnostic::syntax("if expects `if condition then value else value`"))?;
+    let condition = &args[..then];
+    if condition.is_empty() {
+        return Err(Diagnostic::syntax("if is missing its condition").with_span(args[then].span()));
+    }
+
+    let rest = &args[then + 1..];
+    let mut open = 0;
+    let mut else_at = None;
+    for (i, arg) in rest.iter().enumerate() {
+        if is_keyword(arg, "if") {
+            open += 1;
+        } else if is_keyword(arg, "else") {
+            if open == 0 {
+                else_at = Some(i);
+                break;
+            }
+            open -= 1;
+        }
+    }
+
+    let (consequent, alternative) = match else_at {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+    if consequent.is_empty() {
+        return Err(
+            Diagnostic::syntax("if is missing a value after `then`").with_span(args[then].span())
+        );
+    }
+    if alternative.is_some_and(<[Expr]>::is_empty) {
+        return Err(Diagnostic::syntax("if is missing a value after `else`"));
+    }
+    Ok(Parts {
+        condition,
+        consequent,
+        alternative,
+    })
+}
+
+/// Returns whether `expr` is the bare identifier `keyword`.
+fn is_keyword(expr: &Expr, keyword: &str) -> bool {
+    matches!(expr, Expr::Ident(_)) && extract_identifier(expr).is_some_and(|id| &*id == keyword)
+}
+
+fn eval_if(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    eval_tail(args, ctx)?.finish(ctx)
+}
+
+/// Evaluates an `if`, leaving a call the chosen part ends with unmade.
+pub(crate) fn eval_tail(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Tail> {
+    let parts = split(args)?;
+
+    let condition = match eval_part(parts.condition, ctx)?.finish(ctx)? {
+        Value::Bool(b) => b,
+        other => {
+            return Err(Diagnostic::type_error(Type::Bool, other.type_of())
+                .with_span(parts.condition[0].span()));
+        }
+    };
+
+    match (condition, parts.alternative) {
+        (true, _) => eval_part(parts.consequent, ctx),
+        (false, Some(alternative)) => eval_part(alternative, ctx),
+        (false, None) => Ok(Tail::Value(Value::Nil)),
+    }
+}
+
+/// Evaluates one part of an `if`: a single expression, a nested `if`, or an
+/// application of the first expression to the rest, which is left unmade.
+fn eval_part(part: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Tail> {
+    let [callee, args @ ..] = part else {
+        return Err(Diagnostic::syntax("if is missing a value"));
+    };
+    if args.is_empty() {
+        return eval::eval_tail(callee, ctx);
+    }
+    if is_keyword(callee, "if") {
+        return eval_tail(args, ctx);
+    }
+
+    // Macros and special forms take their arguments unevaluated, and their
+    // applications must be written out in parentheses
+    if let Some(id) = extract_identifier(callee)
+        && (ctx.compiler.get_macro(id).is_some()
+            || matches!(
+                ctx.env.get(id),
+                Some(Value::BuiltinMacro(_) | Value::SpecialForm(_))
+            ))
+    {
+        return Err(Diagnostic::syntax(format!(
+            "wrap the application of {id} in an if branch in parentheses"
+        ))
+        .with_span(callee.span()));
+    }
+
+    let func = match callee {
+        Expr::Ident(ident) => eval_ident_no_auto_apply(ident, ctx)?,
+        _ => callee.eval(ctx)?,
+    };
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        values.push(arg.eval(ctx)?);
+    }
+    let span = callee.span().merge(args[args.len() - 1].span());
+    Ok(Tail::Call {
+        callee: func,
+        args: values,
+        span,
+    })
+}
+
+fn ir_if(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "if special form IR generation not yet implemented (use ir_if_with_state instead)",
+    ))
+}
+
+/// Generates the IR for one part of an `if` (see [`ir_if_with_state`]).
+pub type GenPart<'a> =
+    dyn FnMut(&[Expr], &mut IrGenState, &mut IrGenContext) -> Result<ValueId> + 'a;
+
+/// IR generation for if with multi-block support.
+///
+/// `gen_part` generates one part of the `if` that isn't a nested `if`: a
+/// single expression, or an application of the first expression to the rest.
+pub fn ir_if_with_state(
+    args: &[Expr],
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_part: &mut GenPart<'_>,
+) -> Result<ValueId> {
+    let parts = split(args)?;
+    let alternative = parts.alternative.ok_or_else(|| {
+        Diagnostic::syntax("if without else can't be compiled").with_span(parts.condition[0].span())
+    })?;
+
+    // Generate the condition in the entry block
+    let cond = ir_part(parts.condition, state, ctx, source, gen_part)?;
+
+    let then_block_id = state.alloc_block_id();
+    let else_block_id = state.alloc_block_id();
+    let merge_block_id = state.alloc_block_id();
+
+    let current = state
+        .current_block
+        .take()
+        .expect("No entry block available for branch instruction");
+    let (entry_block, next_val) = current.branch(cond, then_block_id, else_block_id, source);
+    state.complete_current_block(entry_block, next_val);
+
+    // Both branches start from the variables bound and moved before the if,
+    // and each moves its result into the phi
+    let bindings = ctx.bindings();
+    let moved = ctx.moved_vars();
+    let then_exit = ir_branch(
+        parts.consequent,
+        then_block_id,
+        merge_block_id,
+        state,
+        ctx,
+        source,
+        gen_part,
+    )?;
+    let then_bindings = ctx.bindings();
+    let then_moved = ctx.moved_vars();
+    ctx.set_bindings(bindings.clone());
+    ctx.set_moved_vars(moved);
+    let else_exit = ir_branch(
+        alternative,
+        else_block_id,
+        merge_block_id,
+        state,
+        ctx,
+        source,
+        gen_part,
+    )?;
+    let else_bindings = ctx.bindings();
+
+    // A variable moved on either path is moved after the if
+    let mut moved = ctx.moved_vars();
+    moved.extend(then_moved);
+    ctx.set_moved_vars(moved);
+
+    let mut merge = state.create_block_with_id(merge_block_id);
+    let result = match (then_exit, else_exit) {
+        (Some((then_value, then_exit)), Some((else_value, else_exit))) => {
+            let incoming = vec![(then_value, then_exit), (else_value, else_exit)];
+
+            // Infer the type from the branches (prefer non-Unknown types)
+            let result_ty = ctx
+                .get_value_type(then_value)
+                .or_else(|| ctx.get_value_type(else_value))
+                .cloned()
+                .unwrap_or(Type::Unknown);
+
+            let result = merge.phi(incoming, result_ty.clone(), source);
+            ctx.set_value_type(result, result_ty);
+
+            // A variable the branches leave bound to different values gets
+            // a phi of its own, and names they declare go out of scope
+            let mut names: Vec<_> = bindings.keys().copied().collect();
+            names.sort_by_key(|name| name.to_string());
+            let mut merged = bindings;
+            for name in names {
+                let (then_var, else_var) = (then_bindings[&name], else_bindings[&name]);
+                if then_var == else_var {
+                    merged.insert(name, then_var);
+                    continue;
+                }
+                let ty = ctx
+                    .get_value_type(then_var)
+                    .cloned()
+                    .unwrap_or(Type::Unknown);
+                let phi = merge.phi(
+                    vec![(then_var, then_exit), (else_var, else_exit)],
+                    ty.clone(),
+                    source,
+                );
+                ctx.set_value_type(phi, ty);
+                merged.insert(name, phi);
+            }
+            ctx.set_bindings(merged);
+            result
+        }
+        // Only the branch that doesn't fail reaches the merge block, with
+        // its variables
+        (Some((value, _)), None) => {
+            ctx.set_bindings(keep_bound(&bindings, then_bindings));
+            value
+        }
+        (None, Some((value, _))) => {
+            ctx.set_bindings(keep_bound(&bindings, else_bindings));
+            value
+        }
+        (None, None) => {
+            return Err(
+                Diagnostic::syntax("an if whose branches both fail can't be compiled")
+                    .with_span(parts.condition[0].span()),
+            );
+        }
+    };
+    state.current_block = Some(merge);
+
+    Ok(result)
+}
+
+/// IR generation for the short-circuit operators `&&` and `||`.
+///
+/// `lhs && rhs` is `if lhs then rhs else lhs` and `lhs || rhs` is
+/// `if lhs then lhs else rhs`: the left operand is generated in the current
+/// block, a branch on it skips the block for the right operand when it
+/// decides the result (`false` for `&&` and `true` for `||`), and a phi
+/// joins the operands in a merge block, which becomes the current block.
+pub fn ir_short_circuit_with_state(
+    operator: &str,
+    args: &[Expr],
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_expr: &mut GenExpr<'_>,
+) -> Result<ValueId> {
+    let [lhs, rhs] = args else {
+        return Err(Diagnostic::syntax(format!(
+            "Binary operator {operator} expects 2 arguments, got {}",
+            args.len()
+        )));
+    };
+    let lhs_value = gen_expr(lhs, state, ctx)?;
+
+    let rhs_block_id = state.alloc_block_id();
+    let skip_block_id = state.alloc_block_id();
+    let merge_block_id = state.alloc_block_id();
+
+    let current = state
+        .current_block
+        .take()
+        .expect("No entry block available for branch instruction");
+    let (then_block_id, else_block_id) = if operator == "&&" {
+        (rhs_block_id, skip_block_id)
+    } else {
+        (skip_block_id, rhs_block_id)
+    };
+    let (entry_block, next_val) = current.branch(lhs_value, then_block_id, else_block_id, source);
+    state.complete_current_block(entry_block, next_val);
+
+    // The right operand may end in the merge block of a nested if
+    state.current_block = Some(state.create_block_with_id(rhs_block_id));
+    let rhs_value = gen_expr(rhs, state, ctx)?;
+    let rhs_exit = state
+        .current_block
+        .take()
+        .expect("Current block missing after generating an operand");
+    let rhs_exit_id = rhs_exit.id();
+    let (block, next_val) = rhs_exit.jump(merge_block_id, source);
+    state.complete_current_block(block, next_val);
+
+    let skip = state.create_block_with_id(skip_block_id);
+    let (block, next_val) = skip.jump(merge_block_id, source);
+    state.complete_current_block(block, next_val);
+
+    let mut merge = state.create_block_with_id(merge_block_id);
+    let result = merge.phi(
+        vec![(rhs_value, rhs_exit_id), (lhs_value, skip_block_id)],
+        Type::Bool,
+        source,
+    );
+    ctx.set_value_type(result, Type::Bool);
+    state.current_block = Some(merge);
+    Ok(result)
+}
+
+/// Returns the bindings a branch ends with for the names bound before the
+/// branch.
+fn keep_bound(
+    before: &HashMap<InternedString, ValueId>,
+    mut after: HashMap<InternedString, ValueId>,
+) -> HashMap<InternedString, ValueId> {
+    after.retain(|name, _| before.contains_key(name));
+    after
+}
+
+/// Generates one branch of an `if` in the block `id`.
+///
+/// A branch that is an `Err` or a `None` makes the function fail there (see
+/// [`try_form`]), and returns `None`. Any other branch jumps to `merge`, and
+/// returns its value and the block it ends in.
+fn ir_branch(
+    part: &[Expr],
+    id: BlockId,
+    merge: BlockId,
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_part: &mut GenPart<'_>,
+) -> Result<Option<(ValueId, BlockId)>> {
+    let block = state.create_block_with_id(id);
+    if let Some(message) = try_form::failure(part, ctx.env()) {
+        let (block, next_val) = block.fail(message?, source);
+        state.complete_current_block(block, next_val);
+        ctx.set_fallible();
+        return Ok(None);
+    }
+
+    state.current_block = Some(block);
+    let value = ir_part(part, state, ctx, source, gen_part)?;
+    // The branch may have ended in a nested if's merge block
+    let block = state
+        .current_block
+        .take()
+        .expect("Current block missing after generating a branch");
+    let exit = block.id();
+    let (block, next_val) = block.jump(merge, source);
+    state.complete_current_block(block, next_val);
+    Ok(Some((value, exit)))
+}
+
+/// Generates one part of an `if`, nesting a part that starts with `if`.
+fn ir_part(
+    part: &[Expr],
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_part: &mut GenPart<'_>,
+) -> Result<ValueId> {
+    match part {
+        [callee, args @ ..] if !args.is_empty() && is_keyword(callee, "if") => {
+            ir_if_with_state(args, state, ctx, source, gen_part)
+        }
+        [expr] => {
+            let value = gen_part(part, state, ctx)?;
+            ctx.consume(expr);
+            Ok(value)
+        }
+        _ => gen_part(part, state, ctx),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> Vec<Value> {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse(input).ast();
+        let results = crate::eval(&root, &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        results
+    }
+
+    #[test]
+    fn test_if_special_form_eval() {
+        let results = eval_all(
+            "
+let x = 5
+if x > 0 then \"positive\" else \"not positive\"
+if x < 0 then \"negative\" else \"not negative\"
+if x == 5 then x + 1 else x - 1
+if false then 1
+",
+        );
+        assert_eq!(
+            results[1..],
+            [
+                Value::String("positive".into()),
+                Value::String("not negative".into()),
+                Value::Integer(6),
+                Value::Nil
+            ]
+        );
+    }
+
+    #[test]
+    fn test_if_nested() {
+        let results = eval_all(
+            "
+fn sign x = if x > 0 then 1 else if x < 0 then 0 - 1 else 0
+sign 3
+sign (0 - 3)
+sign 0
+if true then if false then 1 else 2 else 3
+",
+        );
+        assert_eq!(
+            results[1..],
+            [
+                Value::Integer(1),
+                Value::Integer(-1),
+                Value::Integer(0),
+                Value::Integer(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_if_applies_multi_expression_parts() {
+        let results = eval_all(
+            "
+fn double x = x * 2
+fn is_even n = if n == 0 then true else is_odd (n - 1)
+fn is_odd n = if n == 0 then false else is_even (n - 1)
+if is_even 4 then double 21 else double 0
+",
+        );
+        assert_eq!(results[3], Value::Integer(42));
+    }
+
+    #[test]
+    fn test_if_condition_must_be_bool() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse("if 1 then 2 else 3\n").ast();
+        crate::eval(&root, &mut env, &mut compiler);
+        assert!(compiler.has_errors());
+    }
+}
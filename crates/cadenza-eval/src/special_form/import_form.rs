@@ -0,0 +1,71 @@
+//! The `import` special form for depending on other modules.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::sync::OnceLock;
+
+/// Returns the `import` special form for depending on other modules.
+///
+/// Imports are resolved before evaluation by the [module graph](crate::module_graph),
+/// which evaluates every imported module first. At evaluation time `import`
+/// only checks that this happened; the imported module's definitions are
+/// already visible.
+///
+/// # Evaluation
+/// - Takes 1 or more arguments: module names
+/// - Fails with an unresolved-import error if a module hasn't been evaluated
+/// - Returns nil
+///
+/// # IR Generation
+/// - Not supported (imports only appear at the top level)
+///
+/// # Examples
+/// ```cadenza
+/// import geometry
+/// import units parsing
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static IMPORT_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    IMPORT_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "import",
+        signature: Type::function(vec![Type::Symbol], Type::Nil),
+        eval_fn: eval_import,
+        ir_fn: ir_import,
+    })
+}
+
+fn eval_import(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    if args.is_empty() {
+        return Err(Diagnostic::syntax("import expects at least 1 module name"));
+    }
+
+    for arg in args {
+        let Some(module) = extract_identifier(arg) else {
+            return Err(Diagnostic::syntax("import expects module names").with_span(arg.span()));
+        };
+        if !ctx.compiler.has_module(module) {
+            return Err(Diagnostic::unresolved_import(module).with_span(arg.span()));
+        }
+    }
+
+    Ok(Value::Nil)
+}
+
+fn ir_import(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "import is not supported in IR generation",
+    ))
+}
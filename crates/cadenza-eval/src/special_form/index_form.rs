@@ -82,6 +82,19 @@ fn eval_index(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
 
             Ok(elements[actual_index as usize].clone())
         }
+        Value::Range { start, end } => {
+            let len = end.saturating_sub(start).max(0);
+            let actual_index = if index < 0 { len + index } else { index };
+
+            if actual_index < 0 || actual_index >= len {
+                return Err(Diagnostic::syntax(format!(
+                    "index out of bounds: index {} is out of range for list of length {}",
+                    index, len
+                )));
+            }
+
+            Ok(Value::Integer(start + actual_index))
+        }
         _ => Err(Diagnostic::type_error(
             Type::list(Type::Unknown),
             array_value.type_of(),
@@ -0,0 +1,101 @@
+//! The `__interpolate__` special form for interpolated strings.
+
+use crate::{
+    Eval,
+    context::EvalContext,
+    diagnostic::Result,
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::{fmt::Write, sync::OnceLock};
+
+/// Returns the `__interpolate__` special form for interpolated strings.
+///
+/// The `__interpolate__` special form evaluates its arguments and joins them
+/// into a string. It is automatically used by the parser when a string
+/// literal contains `{...}`: the text between interpolations becomes string
+/// arguments and each interpolation an expression argument.
+///
+/// # Evaluation
+/// - Evaluates each argument expression
+/// - Splices strings in as they are and any other value as it displays
+/// - Returns the concatenated string
+///
+/// # IR Generation
+/// - Not yet supported
+///
+/// # Examples
+/// ```cadenza
+/// "hello {name}"                    // "hello " joined with the value of name
+/// "you have {count + 1} items"      // Evaluates the expression
+/// "literal \{braces\}"              // Escaped braces don't interpolate
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static INTERPOLATE_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    INTERPOLATE_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "__interpolate__",
+        signature: Type::function(vec![], Type::String),
+        eval_fn: eval_interpolate,
+        ir_fn: ir_interpolate,
+    })
+}
+
+fn eval_interpolate(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let mut result = String::new();
+    for expr in args {
+        match expr.eval(ctx)? {
+            Value::String(s) => result.push_str(&s),
+            value => {
+                let _ = write!(result, "{value}");
+            }
+        }
+    }
+    Ok(Value::String(result.into()))
+}
+
+fn ir_interpolate(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    // TODO: Lower to the host's string functions (`concat`, `from-int`, `from-float`)
+    Err(crate::diagnostic::Diagnostic::syntax(
+        "String interpolation not yet supported in IR",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_last(input: &str) -> Value {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse(input).ast();
+        let results = crate::eval(&root, &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        results
+            .last()
+            .cloned()
+            .expect("Expected at least one result")
+    }
+
+    #[test]
+    fn test_interpolate_special_form_eval() {
+        let value = eval_last(
+            "let name = \"Ada\"\nlet count = 2\n\"hello {name}, you have {count + 1} items\"",
+        );
+        assert_eq!(value, Value::String("hello Ada, you have 3 items".into()));
+    }
+
+    #[test]
+    fn test_interpolate_nested_and_escaped() {
+        let value = eval_last("let x = 1.5\n\"\\{x\\} = {\"<{x}>\"}\"");
+        assert_eq!(value, Value::String("{x} = <1.5>".into()));
+    }
+}
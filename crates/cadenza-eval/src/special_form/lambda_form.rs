@@ -0,0 +1,206 @@
+//! The `->` special form for anonymous functions.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
+    interner::InternedString,
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::{BuiltinSpecialForm, for_form},
+    value::{Type, UserFunction, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::sync::OnceLock;
+
+/// Returns the `->` special form for anonymous functions.
+///
+/// The `->` special form builds a closure from the parameters on its left and
+/// the body on its right. The parameters are written bare (`x y -> x + y`),
+/// after `fn` (`fn x y -> x + y`) or after `\` (`\x y -> x + y`); `fn -> body`
+/// takes no parameters. Parameters starting with `for` make a loop instead
+/// (see [`for_form`]).
+///
+/// # Evaluation
+/// - Takes exactly 2 arguments: the parameters and the body
+/// - Creates a UserFunction value capturing the environment
+/// - Returns the function without defining a name for it
+///
+/// # IR Generation
+/// - Not yet supported
+///
+/// # Examples
+/// ```cadenza
+/// let double = x -> x * 2
+/// map (fn x -> x + 1) xs
+/// 5 |> (x -> x * 2)
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static LAMBDA_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    LAMBDA_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "->",
+        signature: Type::function(vec![Type::Unknown, Type::Unknown], Type::Unknown),
+        eval_fn: eval_lambda,
+        ir_fn: ir_lambda,
+    })
+}
+
+/// Returns whether `expr` is an anonymous function expression.
+pub fn is_lambda(expr: &Expr) -> bool {
+    let Expr::Apply(apply) = expr else {
+        return false;
+    };
+    apply
+        .callee()
+        .and_then(|callee| extract_identifier(&callee))
+        .is_some_and(|id| &*id == "->")
+}
+
+fn eval_lambda(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let [params, body] = args else {
+        return Err(Diagnostic::arity(2, args.len()));
+    };
+    if let Some(header) = for_form::header(params) {
+        return for_form::eval_for(&header, body, ctx);
+    }
+
+    Ok(Value::UserFunction(UserFunction {
+        name: "lambda".into(),
+        params: lambda_params(params)?,
+        patterns: vec![],
+        body: body.clone(),
+        captured_env: ctx.env.clone(),
+    }))
+}
+
+/// Returns the parameter names on the left of `->`, dropping a leading `fn`
+/// or `\`.
+pub(crate) fn lambda_params(expr: &Expr) -> Result<Vec<InternedString>> {
+    let exprs = match expr {
+        Expr::Apply(apply) => {
+            let mut exprs = vec![
+                apply
+                    .callee()
+                    .ok_or_else(|| Diagnostic::syntax("missing lambda parameters"))?,
+            ];
+            exprs.extend(apply.all_arguments());
+            exprs
+        }
+        other => vec![other.clone()],
+    };
+
+    let introducer = exprs
+        .first()
+        .and_then(extract_identifier)
+        .is_some_and(|id| matches!(&*id, "fn" | "\\"));
+
+    let mut params = Vec::with_capacity(exprs.len());
+    for expr in &exprs[introducer as usize..] {
+        match expr {
+            Expr::Ident(ident) => params.push(ident.syntax().text().interned()),
+            other => {
+                return Err(Diagnostic::syntax("lambda parameters must be identifiers")
+                    .with_span(other.span()));
+            }
+        }
+    }
+    Ok(params)
+}
+
+fn ir_lambda(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "Anonymous functions not yet supported in IR",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> Vec<Value> {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse(input).ast();
+        let results = crate::eval(&root, &mut env, &mut compiler);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        results
+    }
+
+    #[test]
+    fn test_lambda_special_form_eval() {
+        let results = eval_all(
+            "
+let double = x -> x * 2
+let add = fn x y -> x + y
+let sub = \\x y -> x - y
+let answer = fn -> 42
+double 4
+add 1 2
+sub 5 3
+answer
+",
+        );
+        assert_eq!(
+            results[4..],
+            [
+                Value::Integer(8),
+                Value::Integer(3),
+                Value::Integer(2),
+                Value::Integer(42)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lambda_captures_environment() {
+        let results = eval_all(
+            "
+fn adder n = x -> x + n
+let add5 = adder 5
+add5 10
+",
+        );
+        assert_eq!(results[2], Value::Integer(15));
+    }
+
+    #[test]
+    fn test_lambda_higher_order() {
+        let results = eval_all(
+            "
+let k = 10
+map (fn x -> x * 2) [1, 2, 3]
+filter (x -> x > k) [5, 15, 20]
+fold (acc x -> acc + x) 0 [1, 2, 3]
+",
+        );
+        let shown: Vec<String> = results[1..].iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, vec!["[2, 4, 6]", "[15, 20]", "6"]);
+    }
+
+    #[test]
+    fn test_lambda_pipeline() {
+        let results = eval_all(
+            "
+fn apply_to x f = f x
+5 |> (x -> x * 2) |> (fn x -> x + 1)
+3 |> apply_to (x -> x * x)
+",
+        );
+        assert_eq!(results[1..], [Value::Integer(11), Value::Integer(9)]);
+    }
+
+    #[test]
+    fn test_lambda_params_must_be_identifiers() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse("let f = 1 -> 2\n").ast();
+        crate::eval(&root, &mut env, &mut compiler);
+        assert!(compiler.has_errors());
+    }
+}
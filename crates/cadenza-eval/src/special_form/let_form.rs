@@ -3,13 +3,18 @@
 use crate::{
     Eval,
     context::EvalContext,
-    diagnostic::{Diagnostic, Result},
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, DiagnosticLevel, Result},
+    eval::extract_identifier,
     interner::InternedString,
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
-    special_form::BuiltinSpecialForm,
-    value::{Type, Value},
+    pattern,
+    special_form::{
+        BuiltinSpecialForm, attr_form,
+        tuple_form::{as_tuple_pattern, ir_bind_tuple_pattern},
+    },
+    value::{Attribute, Type, Value},
 };
-use cadenza_syntax::ast::Expr;
+use cadenza_syntax::{ast::Expr, span::Span};
 use std::sync::OnceLock;
 
 /// Returns the `let` special form for variable declarations.
@@ -17,20 +22,39 @@ use std::sync::OnceLock;
 /// The `let` special form binds a name to a value in the current scope.
 ///
 /// # Evaluation
-/// - Takes 2 arguments: identifier and value expression
+/// - Takes 2 arguments: identifier (or pattern) and value expression
 /// - Evaluates the value expression
-/// - Binds the identifier to the evaluated value in the environment
+/// - Binds the identifier to the evaluated value in the environment, or
+///   destructures it with the record, tuple or list pattern (see
+///   [`crate::pattern`]), reporting an error if its shape doesn't match
 /// - Returns the evaluated value
+/// - Applies the pending attributes to the variable: `@export` exports it
+///   from the compiled module
+/// - If evaluation fails, binds the identifier (or every variable of the
+///   pattern) to the poisoned [`Value::Error`] so later uses don't report the
+///   failure again
+/// - `let rec name = value` declares the name before evaluating the value, so
+///   a closure in the value can call itself by that name. Using the name while
+///   the value is evaluated, as in `let rec x = x + 1`, is an error
 ///
 /// # IR Generation
 /// - Generates IR for the value expression
 /// - Binds the identifier to the resulting ValueId
+/// - Binding a variable that holds a linear value moves it to the new name
+/// - A tuple pattern binds each variable to an element of the tuple
 /// - Returns the ValueId
+/// - Record and list patterns are not yet supported
 ///
 /// # Examples
 /// ```cadenza
 /// let x = 42
 /// let y = x + 1
+/// let { x, y } = point
+/// let { x: px, y: py } = point
+/// let (q, r) = divmod a b
+/// let [first, second] = pair
+/// let { pos = (x, y) } = entity
+/// let rec fact = \n -> if n <= 1 then 1 else n * (fact (n - 1))
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static LET_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
@@ -42,7 +66,29 @@ pub fn get() -> &'static BuiltinSpecialForm {
     })
 }
 
+/// Splits the `rec` off the arguments of `let rec name = value`, returning
+/// whether there was one.
+///
+/// `with_value` says whether `args` end with the value, or are the `rec name`
+/// on the left of `=`. `let rec = value` binds a variable named `rec`.
+pub(crate) fn strip_rec(args: &[Expr], with_value: bool) -> (bool, &[Expr]) {
+    match args {
+        [keyword, rest @ ..]
+            if rest.len() == 1 + with_value as usize
+                && extract_identifier(keyword).is_some_and(|keyword| &*keyword == "rec") =>
+        {
+            (true, rest)
+        }
+        _ => (false, args),
+    }
+}
+
 fn eval_let(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let (recursive, args) = strip_rec(args, true);
+    if recursive {
+        return eval_let_rec(args, ctx);
+    }
+
     // If called with 0 arguments, return Nil
     if args.is_empty() {
         return Ok(Value::Nil);
@@ -60,13 +106,28 @@ fn eval_let(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
         ));
     }
 
-    // First argument is the identifier
+    // Take the attributes before evaluating the value, whose own definitions
+    // must not consume them
+    let attributes = ctx.compiler.take_attributes();
+
+    // First argument is the identifier or a pattern
+    if pattern::is_pattern(&args[0]) {
+        if !attributes.is_empty() {
+            return Err(Diagnostic::syntax(
+                "attributes can only be applied to a let binding one name",
+            )
+            .with_span(args[0].span()));
+        }
+        return pattern::bind(&args[0], &args[1], ctx);
+    }
+
     let ident = match &args[0] {
         Expr::Ident(i) => i,
-        _ => {
-            return Err(Diagnostic::syntax(
-                "let requires an identifier as the variable name",
-            ));
+        other => {
+            return Err(
+                Diagnostic::syntax("let requires an identifier as the variable name")
+                    .with_span(other.span()),
+            );
         }
     };
 
@@ -76,22 +137,90 @@ fn eval_let(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
 
     // Second argument is the value expression
     let value_expr = &args[1];
-    let value = value_expr.eval(ctx)?;
+    let value = match value_expr.eval(ctx) {
+        Ok(value) => value,
+        Err(error) => {
+            // Poison the variable so its uses don't repeat the error
+            ctx.env.define(name, Value::Error);
+            return Err(error);
+        }
+    };
 
     // Define the variable in the environment with the evaluated value
     ctx.env.define(name, value.clone());
+    apply_attributes(name, ident.span(), attributes, ctx)?;
 
     // Return the value
     Ok(value)
 }
 
+/// Evaluates `let rec name = value`, given `[name, value]`.
+///
+/// The name is declared uninitialized while the value is evaluated (see
+/// [`crate::Env::declare_uninitialized`]). A closure made then captures it
+/// that way, and is named after the binding, so each call to it binds the
+/// name to the closure.
+fn eval_let_rec(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let attributes = ctx.compiler.take_attributes();
+    let [Expr::Ident(ident), value_expr] = args else {
+        return Err(Diagnostic::syntax(
+            "let rec requires an identifier and a value (e.g., let rec f = \\n -> f n)",
+        )
+        .with_span(args[0].span()));
+    };
+    let name = ident.syntax().text().interned();
+
+    ctx.env.declare_uninitialized(name);
+    let value = match value_expr.eval(ctx) {
+        Ok(Value::UserFunction(mut function)) if function.captured_env.is_uninitialized(name) => {
+            function.name = name;
+            Value::UserFunction(function)
+        }
+        Ok(value) => value,
+        Err(error) => {
+            ctx.env.define(name, Value::Error);
+            return Err(error);
+        }
+    };
+
+    ctx.env.define(name, value.clone());
+    apply_attributes(name, ident.span(), attributes, ctx)?;
+    Ok(value)
+}
+
+/// Applies `attributes` to the variable `name` that was just defined, whose
+/// name is at `span`.
+fn apply_attributes(
+    name: InternedString,
+    span: Span,
+    attributes: Vec<Attribute>,
+    ctx: &mut EvalContext<'_>,
+) -> Result<()> {
+    for attr in attributes {
+        match &*attr.name {
+            "export" => attr_form::export(name, span, &attr.args, ctx)?,
+            other => {
+                let warning =
+                    Diagnostic::syntax(format!("unknown attribute @{other} on variable {name}"))
+                        .with_span(span)
+                        .set_level(DiagnosticLevel::Warning);
+                ctx.compiler.record_diagnostic(*warning);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn ir_let(
     args: &[Expr],
     block: &mut BlockBuilder,
     ctx: &mut IrGenContext,
-    _source: SourceLocation,
+    source: SourceLocation,
     gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
 ) -> Result<ValueId> {
+    // A recursive binding only differs for closures, which IR doesn't support
+    let (_, args) = strip_rec(args, true);
+
     // Validate argument count
     if args.len() != 2 {
         return Err(Diagnostic::syntax(
@@ -99,6 +228,13 @@ fn ir_let(
         ));
     }
 
+    if let Some(pattern) = as_tuple_pattern(&args[0]) {
+        let value_id = gen_expr(&args[1], block, ctx)?;
+        ctx.consume(&args[1]);
+        ir_bind_tuple_pattern(&pattern, value_id, block, ctx, source)?;
+        return Ok(value_id);
+    }
+
     // Extract the variable name from the identifier
     let var_name = match &args[0] {
         Expr::Ident(ident) => ident.syntax().text().interned(),
@@ -112,6 +248,9 @@ fn ir_let(
     // Generate IR for the value expression using the provided gen_expr callback
     let value_id = gen_expr(&args[1], block, ctx)?;
 
+    // Binding another variable's linear value moves it to the new name
+    ctx.consume(&args[1]);
+
     // Get the type of the generated value from the context
     let value_type = ctx
         .get_value_type(value_id)
@@ -3,9 +3,12 @@
 use crate::{
     Eval,
     context::EvalContext,
-    diagnostic::Result,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
+    interner::InternedString,
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
-    special_form::BuiltinSpecialForm,
+    list,
+    special_form::{BuiltinSpecialForm, for_form, record_form::as_spread},
     value::{Type, Value},
 };
 use cadenza_syntax::ast::Expr;
@@ -16,19 +19,29 @@ use std::sync::OnceLock;
 /// The `__list__` special form evaluates its arguments and constructs a list value.
 /// It is automatically used by the parser when encountering list literal syntax `[...]`.
 ///
+/// A list with a single element of the form `element for x in xs if condition`
+/// is a comprehension instead (see [`Comprehension`]).
+///
 /// # Evaluation
 /// - Evaluates each argument expression
 /// - Returns a list containing all evaluated values
+/// - A spread `...xs` adds the elements of the list or range `xs` in its
+///   place. Spreading anything else is a type error
+/// - A comprehension keeps the elements of `xs` that pass every condition
+///   and maps the element expression over them, as `map` over `filter` would
 ///
 /// # IR Generation
-/// - Generates IR for each element
-/// - Creates a list construction instruction
+/// Lists aren't supported in IR yet, so neither literals nor comprehensions
+/// compile. Comprehensions are only desugared during evaluation and in
+/// [`crate::expand`].
 ///
 /// # Examples
 /// ```cadenza
 /// [1, 2, 3]         // Creates Value::List([Integer(1), Integer(2), Integer(3)])
 /// []                // Creates Value::List([])
 /// [x, y + 1, f z]   // Evaluates each element expression
+/// [0, ...xs, 4]     // The elements of xs between 0 and 4
+/// [x * x for x in xs if x > 2]
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static LIST_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
@@ -40,10 +53,195 @@ pub fn get() -> &'static BuiltinSpecialForm {
     })
 }
 
+/// A list comprehension: `[element for var in list if condition ...]`.
+///
+/// A comprehension is sugar for `map` and `filter`:
+///
+/// ```cadenza
+/// [x * x for x in xs if x > 2]
+/// map (fn x -> x * x) (filter (fn x -> x > 2) xs)
+/// ```
+///
+/// The list and each condition may be an application without parentheses
+/// (`for i in range 0 n if even i`): a condition runs up to the next `if`.
+/// The element must be a single expression.
+pub(crate) struct Comprehension {
+    /// The expression each kept element is mapped to.
+    pub element: Expr,
+    /// The name each element of the list is bound to.
+    pub var: InternedString,
+    /// The list, as one expression or an application of the first to the
+    /// rest.
+    pub list: Vec<Expr>,
+    /// The conditions an element must pass, in order, each as one expression
+    /// or an application of the first to the rest.
+    pub conditions: Vec<Vec<Expr>>,
+}
+
+/// Returns the comprehension the arguments of `__list__` are, or `None` if
+/// they are a plain list.
+///
+/// # Errors
+/// Returns an error if the list has a `for` in it but isn't a well-formed
+/// comprehension.
+pub(crate) fn comprehension(args: &[Expr]) -> Option<Result<Comprehension>> {
+    let [Expr::Apply(apply)] = args else {
+        return None;
+    };
+
+    // The element is the receiver of the application to `for`, which is the
+    // innermost one as the chain nests to the left
+    let mut element = None;
+    let mut level = apply.clone();
+    loop {
+        let receiver = level.receiver().and_then(|receiver| receiver.value())?;
+        let arguments: Vec<Expr> = level.arguments().filter_map(|arg| arg.value()).collect();
+        if let [arg] = &arguments[..]
+            && is_keyword(arg, "for")
+        {
+            element = Some((receiver.clone(), level.all_arguments().len()));
+        }
+        match receiver {
+            Expr::Apply(inner) => level = inner,
+            _ => break,
+        }
+    }
+    let (element, skip) = element?;
+    let clauses = &apply.all_arguments()[skip..];
+    Some(clauses_of(element, clauses))
+}
+
+/// Splits the clauses after `for` into the parts of a comprehension.
+fn clauses_of(element: Expr, clauses: &[Expr]) -> Result<Comprehension> {
+    let [Expr::Ident(var), keyword, rest @ ..] = clauses else {
+        return Err(
+            Diagnostic::syntax("a comprehension expects `[element for x in xs]`")
+                .with_span(element.span()),
+        );
+    };
+    if !is_keyword(keyword, "in") {
+        return Err(
+            Diagnostic::syntax("a comprehension expects `in` after the variable")
+                .with_span(keyword.span()),
+        );
+    }
+    if let Some(extra) = rest.iter().find(|expr| is_keyword(expr, "for")) {
+        return Err(
+            Diagnostic::syntax("a comprehension takes a single `for`").with_span(extra.span())
+        );
+    }
+
+    let end = rest
+        .iter()
+        .position(|expr| is_keyword(expr, "if"))
+        .unwrap_or(rest.len());
+    let (list, mut rest) = rest.split_at(end);
+    if list.is_empty() {
+        return Err(
+            Diagnostic::syntax("a comprehension is missing a list after `in`")
+                .with_span(keyword.span()),
+        );
+    }
+
+    // Each `if` starts a condition that runs up to the next one
+    let mut conditions = Vec::new();
+    while let [keyword, after @ ..] = rest {
+        let end = after
+            .iter()
+            .position(|expr| is_keyword(expr, "if"))
+            .unwrap_or(after.len());
+        let (condition, next) = after.split_at(end);
+        if condition.is_empty() {
+            return Err(
+                Diagnostic::syntax("a comprehension is missing a condition after `if`")
+                    .with_span(keyword.span()),
+            );
+        }
+        conditions.push(condition.to_vec());
+        rest = next;
+    }
+
+    Ok(Comprehension {
+        element,
+        var: var.syntax().text().interned(),
+        list: list.to_vec(),
+        conditions,
+    })
+}
+
+/// Returns whether `expr` is the identifier `keyword`.
+fn is_keyword(expr: &Expr, keyword: &str) -> bool {
+    matches!(expr, Expr::Ident(_)) && extract_identifier(expr).is_some_and(|id| &*id == keyword)
+}
+
+/// Evaluates a comprehension, binding the variable to each element of the
+/// list in turn.
+///
+/// This gives the same result as `map` over `filter`, but a condition may be
+/// an application, which can't be the body of a lambda on its own.
+fn eval_comprehension(comprehension: Comprehension, ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let Comprehension {
+        element,
+        var,
+        list,
+        conditions,
+    } = comprehension;
+    let value = for_form::eval_list(&list, ctx)?;
+    let Some(items) = list::elements(&value) else {
+        return Err(
+            Diagnostic::type_error(Type::list(Type::Unknown), value.type_of())
+                .with_span(list[0].span()),
+        );
+    };
+
+    let mut mapped = Vec::new();
+    for item in items {
+        ctx.env.push_scope();
+        ctx.env.define(var, item);
+        let result =
+            keep(&conditions, ctx).and_then(|keep| keep.then(|| element.eval(ctx)).transpose());
+        ctx.env.pop_scope();
+        mapped.extend(result?);
+    }
+    Ok(Value::List(mapped))
+}
+
+/// Returns whether the bound element passes every condition.
+fn keep(conditions: &[Vec<Expr>], ctx: &mut EvalContext<'_>) -> Result<bool> {
+    for condition in conditions {
+        match for_form::eval_list(condition, ctx)? {
+            Value::Bool(true) => {}
+            Value::Bool(false) => return Ok(false),
+            other => {
+                let span = condition[0]
+                    .span()
+                    .merge(condition[condition.len() - 1].span());
+                return Err(Diagnostic::type_error(Type::Bool, other.type_of()).with_span(span));
+            }
+        }
+    }
+    Ok(true)
+}
+
 fn eval_list(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    if let Some(comprehension) = comprehension(args) {
+        return eval_comprehension(comprehension?, ctx);
+    }
+
     // Evaluate each argument expression
     let mut elements = Vec::with_capacity(args.len());
     for expr in args {
+        if let Some(operand) = as_spread(expr) {
+            let value = operand.eval(ctx)?;
+            let Some(spread) = list::elements(&value) else {
+                return Err(
+                    Diagnostic::type_error(Type::list(Type::Unknown), value.type_of())
+                        .with_span(operand.span()),
+                );
+            };
+            elements.extend(spread);
+            continue;
+        }
         let value = expr.eval(ctx)?;
         elements.push(value);
     }
@@ -53,12 +251,18 @@ fn eval_list(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
 }
 
 fn ir_list(
-    _args: &[Expr],
+    args: &[Expr],
     _block: &mut BlockBuilder,
     _ctx: &mut IrGenContext,
     _source: SourceLocation,
     _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
 ) -> Result<ValueId> {
+    if comprehension(args).is_some() {
+        return Err(Diagnostic::syntax(
+            "List comprehensions not yet supported in IR",
+        ));
+    }
+
     // TODO: Add list construction instruction to IR
     // For now, return an error as lists aren't fully supported in IR yet
     Err(crate::diagnostic::Diagnostic::syntax(
@@ -110,4 +314,69 @@ mod tests {
         let value = &results[0];
         assert_eq!(*value, Value::List(vec![]));
     }
+
+    #[test]
+    fn test_list_spread() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = "let xs = [2, 3]\nlet r = 4..6\n[1, ...xs, ...r]\n[...1]";
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+
+        assert_eq!(
+            results[2],
+            Value::List((1..6).map(Value::Integer).collect())
+        );
+        let diagnostics = compiler.diagnostics();
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert!(matches!(
+            &diagnostics[0].kind,
+            crate::diagnostic::DiagnosticKind::TypeError {
+                actual: crate::value::Type::Integer,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_comprehension_conditions_are_applications() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input =
+            "fn between lo hi x = x > lo && x < hi\n[x for x in 0..10 if between 2 7 x if x != 4]";
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(
+            results[1],
+            Value::List([3, 5, 6].map(Value::Integer).into())
+        );
+    }
+
+    #[test]
+    fn test_comprehension_errors() {
+        for (input, message) in [
+            (
+                "[x for x numbers]",
+                "a comprehension expects `in` after the variable",
+            ),
+            ("[x for x in 1..3 if x]", "type error"),
+            (
+                "[x for x in xs for y in ys]",
+                "a comprehension takes a single `for`",
+            ),
+        ] {
+            let mut env = Env::with_standard_builtins();
+            let mut compiler = Compiler::new();
+            crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+            let diagnostics = compiler.diagnostics();
+            assert_eq!(diagnostics.len(), 1, "{input}: {diagnostics:?}");
+            assert!(diagnostics[0].to_string().contains(message), "{input}");
+        }
+    }
 }
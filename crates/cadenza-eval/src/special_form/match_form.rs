@@ -1,30 +1,64 @@
 //! The `match` special form for pattern matching.
 
 use crate::{
-    Eval,
+    Eval, InferType,
     context::EvalContext,
-    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
-    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, DiagnosticLevel, Result},
+    eval::{self, Tail},
+    interner::InternedString,
+    ir::{
+        BinOp, BlockBuilder, IrConst, IrGenContext, IrGenState, SavedVar, SourceLocation, ValueId,
+    },
+    pattern::{Pattern, PatternKind},
     special_form::BuiltinSpecialForm,
+    unit::{DerivedDimension, Dimension, Unit, UnitRegistry},
     value::{Type, Value},
 };
-use cadenza_syntax::ast::Expr;
-use std::sync::OnceLock;
+use cadenza_syntax::{ast::Expr, span::Span};
+use std::{collections::HashMap, sync::OnceLock};
 
 /// Returns the `match` special form for pattern matching.
 ///
-/// The `match` special form implements pattern matching on boolean values.
+/// The `match` special form picks the first arm whose pattern matches a
+/// value. Patterns are literals, variables, `_`, and record, tuple and list
+/// patterns nested in any combination (see [`crate::pattern`]). A unit
+/// pattern, `meter x`, matches a quantity of the dimension the unit measures
+/// and binds its magnitude in that unit.
+///
+/// An arm can have a guard, `pattern when condition => result`, which must
+/// also hold for the arm to match. The guard sees the pattern's variables.
+/// `=>` binds tighter than application, so a guard that calls a function
+/// needs parentheses: `n when (is_prime n) => n`.
 ///
 /// # Evaluation
 /// - Takes at least 2 arguments: match expression and pattern arms
-/// - Evaluates the match expression (must be boolean)
+/// - Evaluates the match expression
 /// - Checks each pattern arm in order
 /// - Pattern arms have syntax: `pattern => result`
-/// - Patterns must be `true` or `false`
-/// - Returns the result of the first matching arm
+/// - Evaluates the result of the first matching arm with the pattern's
+///   variables bound, in a scope of its own
+/// - An arm with a guard matches only if the guard, evaluated after the
+///   pattern matches and with its variables bound, is `true`; otherwise the
+///   next arm is tried
+/// - Reports an error if no arm matches
+/// - Warns if the arms match quantities by unit but leave a declared measure's
+///   dimension uncovered, unless an arm matches any value
 ///
 /// # IR Generation
-/// - Not yet implemented (returns error)
+/// - Compiles the arms to a decision tree: each node branches on one literal
+///   test of the value or of a tuple element, so no test is made twice on a
+///   path, and joins its two subtrees with a phi
+/// - An arm's result is generated at each leaf of the tree it's reached at
+/// - A guard is a branch at the leaf of its arm, to the arm's result if it
+///   holds and to the tree of the arms after it if it doesn't. A guarded arm
+///   doesn't cover the values it matches, so later arms must
+/// - Requires the arms to cover every value, with a final `_` or variable arm
+///   unless the value is a boolean matched as both `true` and `false`
+/// - A unit pattern is decided at compile time from the dimension tag of the
+///   matched value: arms for other dimensions are dropped, and the magnitude
+///   is converted from the base units compiled code holds quantities in. It
+///   must be an arm's whole pattern and bind the magnitude to a name or `_`
+/// - Record and list patterns are not yet supported
 ///
 /// # Examples
 ///
@@ -33,11 +67,32 @@ use std::sync::OnceLock;
 /// match x > 0
 ///     true => "positive"
 ///     false => "negative or zero"
+///
+/// match point
+///     (0, 0) => "origin"
+///     (0, y) => "on the y axis"
+///     (x, _) => "elsewhere"
+///
+/// match shape
+///     { kind = "circle", radius } => 3.14 * radius * radius
+///     [first, second] => first + second
+///     _ => 0
+///
+/// match distance
+///     (inch n) => n
+///     (second t) => t
+///
+/// match reading
+///     n when n > 100 => "high"
+///     (x, y) when x == y => "level"
+///     _ => "normal"
 /// ```
 ///
 /// Single-line syntax (more compact):
 /// ```cadenza
 /// match x > 0 true => "positive" false => "negative"
+/// match n 0 => "zero" (-1) => "minus one" _ => "other"
+/// match n (n when n > 10 => "big") (_ => "small")
 /// ```
 ///
 /// Note: The `=>` operator has higher binding power than function application,
@@ -52,31 +107,21 @@ pub fn get() -> &'static BuiltinSpecialForm {
     })
 }
 
-fn eval_match(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
-    // Validate argument count: need match expression and at least one arm
-    if args.len() < 2 {
-        return Err(Diagnostic::syntax(
-            "match expects at least 2 arguments: match_expr and pattern arms",
-        ));
-    }
-
-    // First argument is the expression to match on
-    let match_expr = &args[0];
-    let match_value = match_expr.eval(ctx)?;
-
-    // Check that the match value is a boolean
-    let match_bool = match match_value {
-        Value::Bool(b) => b,
-        _ => {
-            return Err(Diagnostic::type_error(Type::Bool, match_value.type_of())
-                .with_span(match_expr.span()));
-        }
-    };
+/// An arm of a `match`.
+pub(crate) struct Arm {
+    pub pattern: Pattern,
+    /// The condition that must also hold for the arm to match.
+    pub guard: Option<Expr>,
+    pub result: Expr,
+}
 
+/// Returns the arms of a `match` from its arguments after the value. Unit
+/// patterns refer to `units`.
+pub(crate) fn match_arms(args: &[Expr], units: &UnitRegistry) -> Result<Vec<Arm>> {
     // Collect all pattern arms - they can be passed as individual args or in a block
     let mut arms = Vec::new();
 
-    for arg in &args[1..] {
+    for arg in args {
         // Check if this is a __block__ containing multiple arms
         if let Expr::Apply(apply) = arg
             && let Some(Expr::Synthetic(syn)) = apply.callee()
@@ -90,41 +135,127 @@ fn eval_match(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
         arms.push(arg.clone());
     }
 
-    // Process each arm
+    let mut parsed = Vec::with_capacity(arms.len());
+    let mut rest = arms.as_slice();
+    while let [arm, tail @ ..] = rest {
+        // `=>` binds tighter than application, so unless the arm is in
+        // parentheses, `pattern when guard => result` is three arguments
+        if let [when, arrow, tail @ ..] = tail
+            && is_when(when)
+            && let Some((guard, result)) = arrow_operands(arrow)
+        {
+            parsed.push(Arm {
+                pattern: Pattern::parse_with_units(arm, units)?,
+                guard: Some(guard),
+                result,
+            });
+            rest = tail;
+            continue;
+        }
+        parsed.push(match_arm(arm, units)?);
+        rest = tail;
+    }
+    Ok(parsed)
+}
+
+/// Returns the arm `pattern => result`, or `pattern when guard => result`.
+fn match_arm(arm: &Expr, units: &UnitRegistry) -> Result<Arm> {
+    if let Some((pattern, result)) = arrow_operands(arm) {
+        return Ok(Arm {
+            pattern: Pattern::parse_with_units(&pattern, units)?,
+            guard: None,
+            result,
+        });
+    }
+    // `pattern when guard => result` is `((pattern when) (guard => result))`
+    if let Expr::Apply(apply) = arm
+        && let Some(Expr::Apply(inner)) = apply.receiver().and_then(|r| r.value())
+        && let [arrow] = arguments(apply).as_slice()
+        && let [when] = arguments(&inner).as_slice()
+        && is_when(when)
+        && let Some(pattern) = inner.receiver().and_then(|r| r.value())
+        && let Some((guard, result)) = arrow_operands(arrow)
+    {
+        return Ok(Arm {
+            pattern: Pattern::parse_with_units(&pattern, units)?,
+            guard: Some(guard),
+            result,
+        });
+    }
+    Err(Diagnostic::syntax("match arms must be written `pattern => result`").with_span(arm.span()))
+}
+
+/// Returns the operands of `lhs => rhs`.
+fn arrow_operands(expr: &Expr) -> Option<(Expr, Expr)> {
+    if let Expr::Apply(apply) = expr
+        && let Some(Expr::Op(op)) = apply.callee()
+        && op.syntax().text() == "=>"
+        && let [lhs, rhs] = apply.all_arguments().as_slice()
+    {
+        return Some((lhs.clone(), rhs.clone()));
+    }
+    None
+}
+
+/// Returns the arguments of `apply` itself, not of the applications nested in
+/// its receiver.
+fn arguments(apply: &cadenza_syntax::ast::Apply) -> Vec<Expr> {
+    apply.arguments().filter_map(|arg| arg.value()).collect()
+}
+
+fn is_when(expr: &Expr) -> bool {
+    matches!(expr, Expr::Ident(ident) if ident.syntax().text() == "when")
+}
+
+fn eval_match(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    eval_tail(args, ctx)?.finish(ctx)
+}
+
+/// Evaluates a `match`, leaving a call the matching arm ends with unmade.
+pub(crate) fn eval_tail(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Tail> {
+    // Validate argument count: need match expression and at least one arm
+    if args.len() < 2 {
+        return Err(Diagnostic::syntax(
+            "match expects at least 2 arguments: match_expr and pattern arms",
+        ));
+    }
+
+    // First argument is the expression to match on
+    let match_expr = &args[0];
+    let arms = match_arms(&args[1..], ctx.compiler.units())?;
+    check_measures(&arms, match_expr.span(), ctx);
+    let match_value = match_expr.eval(ctx)?;
+
     for arm in &arms {
-        // Each arm should be an arrow expression: pattern => result
-        if let Expr::Apply(apply) = arm {
-            // Check if the callee is the => operator
-            if let Some(Expr::Op(op)) = apply.callee()
-                && op.syntax().text() == "=>"
-            {
-                let arm_args = apply.all_arguments();
-                if arm_args.len() == 2 {
-                    let pattern = &arm_args[0];
-                    let result_expr = &arm_args[1];
-
-                    // Check if pattern is a boolean literal
-                    if let Expr::Ident(ident) = pattern {
-                        let pattern_text = ident.syntax().text();
-                        let matches = match pattern_text.as_str() {
-                            "true" => match_bool,
-                            "false" => !match_bool,
-                            _ => {
-                                return Err(Diagnostic::syntax(format!(
-                                    "match pattern must be 'true' or 'false', got '{}'",
-                                    pattern_text.as_str()
-                                ))
-                                .with_span(pattern.span()));
-                            }
-                        };
-
-                        if matches {
-                            return result_expr.eval(ctx);
-                        }
-                    }
+        let Ok(bindings) = arm.pattern.destructure(&match_value) else {
+            continue;
+        };
+
+        // The pattern's variables are only bound in the arm
+        ctx.env.push_scope();
+        for (name, value) in bindings {
+            ctx.env.define(name, value);
+        }
+        if let Some(guard) = &arm.guard {
+            match guard.eval(ctx) {
+                Ok(Value::Bool(true)) => {}
+                Ok(Value::Bool(false)) => {
+                    ctx.env.pop_scope();
+                    continue;
+                }
+                other => {
+                    ctx.env.pop_scope();
+                    return Err(match other {
+                        Ok(value) => Diagnostic::type_error(Type::Bool, value.type_of())
+                            .with_span(guard.span()),
+                        Err(error) => error,
+                    });
                 }
             }
         }
+        let result = eval::eval_tail(&arm.result, ctx);
+        ctx.env.pop_scope();
+        return result;
     }
 
     // No pattern matched
@@ -134,6 +265,49 @@ fn eval_match(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
     )
 }
 
+/// Warns if `arms` match quantities by unit without covering the dimension
+/// of every measure declared, and none matches any value.
+fn check_measures(arms: &[Arm], span: Span, ctx: &mut EvalContext<'_>) {
+    let mut covered = Vec::new();
+    for Arm { pattern, guard, .. } in arms {
+        match &pattern.kind {
+            PatternKind::Wildcard | PatternKind::Bind(_) if guard.is_none() => return,
+            PatternKind::Quantity(unit, _) => covered.push(unit.dimension),
+            _ => {}
+        }
+    }
+    if covered.is_empty() {
+        return;
+    }
+
+    let mut missing: Vec<Dimension> = Vec::new();
+    for unit in ctx.compiler.units().all_units() {
+        if !covered.contains(&unit.dimension) && !missing.contains(&unit.dimension) {
+            missing.push(unit.dimension);
+        }
+    }
+    // A match in a function is checked at each call, but reported once
+    let reported = ctx
+        .compiler
+        .diagnostics()
+        .iter()
+        .any(|diagnostic| diagnostic.span == Some(span) && diagnostic.is_warning());
+    if missing.is_empty() || reported {
+        return;
+    }
+    let missing: Vec<&str> = missing
+        .iter()
+        .map(|dimension| &*dimension.base_unit)
+        .collect();
+    let warning = Diagnostic::syntax(format!(
+        "match doesn't cover every measure: add an arm for {} or a `_` arm",
+        missing.join(", ")
+    ))
+    .with_span(span)
+    .set_level(DiagnosticLevel::Warning);
+    ctx.compiler.record_diagnostic(*warning);
+}
+
 fn ir_match(
     _args: &[Expr],
     _block: &mut BlockBuilder,
@@ -146,19 +320,51 @@ fn ir_match(
     ))
 }
 
+/// Generates IR for a sub-expression of a `match` (see
+/// [`ir_match_with_state`]).
+pub type GenExpr<'a> = dyn FnMut(&Expr, &mut IrGenState, &mut IrGenContext) -> Result<ValueId> + 'a;
+
+/// The position of a part of the matched value: the indices of the tuple
+/// elements leading to it, outermost first.
+type Path = Vec<usize>;
+
+/// An arm of a `match` flattened for the decision tree.
+#[derive(Clone)]
+struct Row {
+    /// The literal each part of the value must equal for the arm to match.
+    tests: Vec<(Path, Value)>,
+    /// The variables the arm binds to parts of the value.
+    bindings: Vec<(InternedString, Path)>,
+    /// The variable the arm binds to the magnitude of the value, a quantity,
+    /// in a unit.
+    magnitude: Option<(InternedString, Unit)>,
+    /// The condition that must also hold for the arm to match.
+    guard: Option<Expr>,
+    /// The result of the arm.
+    result: Expr,
+}
+
+/// The state shared by the nodes of a decision tree.
+struct Tree<'a, 'b> {
+    /// The value of each part of the matched value the arms refer to.
+    parts: HashMap<Path, ValueId>,
+    /// The span of the matched expression, where a missing arm is reported.
+    span: Span,
+    source: SourceLocation,
+    gen_expr: &'a mut GenExpr<'b>,
+}
+
 /// IR generation for match with multi-block support.
 ///
-/// Generates control flow with branches for boolean pattern matching.
+/// Compiles the arms to a decision tree of branches (see [`get`]). Unit
+/// patterns refer to `units`.
 pub fn ir_match_with_state(
     args: &[Expr],
-    state: &mut crate::ir::IrGenState,
+    units: &UnitRegistry,
+    state: &mut IrGenState,
     ctx: &mut IrGenContext,
     source: SourceLocation,
-    gen_expr: &mut dyn FnMut(
-        &Expr,
-        &mut crate::ir::IrGenState,
-        &mut IrGenContext,
-    ) -> Result<ValueId>,
+    gen_expr: &mut GenExpr<'_>,
 ) -> Result<ValueId> {
     // Validate argument count: need match expression and at least one arm
     if args.len() < 2 {
@@ -167,73 +373,221 @@ pub fn ir_match_with_state(
         ));
     }
 
-    // First argument is the expression to match on (must be boolean)
     let match_expr = &args[0];
+    let arms = match_arms(&args[1..], units)?;
+    let value = gen_expr(match_expr, state, ctx)?;
+
+    let mut rows = Vec::new();
+    for Arm {
+        pattern,
+        guard,
+        result,
+    } in arms
+    {
+        let mut row = Row {
+            tests: vec![],
+            bindings: vec![],
+            magnitude: None,
+            guard,
+            result,
+        };
+        if let PatternKind::Quantity(unit, magnitude) = &pattern.kind {
+            // The dimension of a quantity is known at compile time, so an arm
+            // for another dimension can never match
+            let Some(dimension) = ctx.dimension(value) else {
+                return Err(Diagnostic::syntax(
+                    "a unit pattern in compiled code needs a quantity of known dimension",
+                )
+                .with_span(match_expr.span()));
+            };
+            if *dimension != DerivedDimension::from_dimension(unit.dimension) {
+                continue;
+            }
+            match &magnitude.kind {
+                PatternKind::Bind(name) => row.magnitude = Some((*name, unit.clone())),
+                PatternKind::Wildcard => {}
+                _ => {
+                    return Err(Diagnostic::syntax(
+                        "a unit pattern in compiled code must bind the magnitude to a name",
+                    )
+                    .with_span(magnitude.span));
+                }
+            }
+        } else {
+            flatten(&pattern, vec![], &mut row)?;
+        }
+        rows.push(row);
+    }
 
-    // Collect all pattern arms - they can be passed as individual args or in a block
-    let mut arms = Vec::new();
+    // Read every part of the value the arms test or bind up front, in the
+    // block that dominates the whole tree
+    let mut paths: Vec<Path> = rows
+        .iter()
+        .flat_map(|row| {
+            let tested = row.tests.iter().map(|(path, _)| path);
+            let bound = row.bindings.iter().map(|(_, path)| path);
+            tested.chain(bound)
+        })
+        .flat_map(|path| (1..=path.len()).map(|len| path[..len].to_vec()))
+        .collect();
+    paths.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+    paths.dedup();
+
+    let mut parts = HashMap::from([(vec![], value)]);
+    for path in paths {
+        let (&index, parent) = path.split_last().expect("paths are not empty");
+        let parent = parts[parent];
+        let ty = match ctx.get_value_type(parent) {
+            Some(Type::Tuple(types)) => types.get(index).cloned().unwrap_or(Type::Unknown),
+            _ => Type::Unknown,
+        };
+        let field = InternedString::new(&index.to_string());
+        let part = state
+            .current_block()
+            .field(parent, field, ty.clone(), source);
+        ctx.set_value_type(part, ty);
+        parts.insert(path, part);
+    }
 
-    for arg in &args[1..] {
-        // Check if this is a __block__ containing multiple arms
-        if let Expr::Apply(apply) = arg
-            && let Some(Expr::Synthetic(syn)) = apply.callee()
-            && syn.identifier() == "__block__"
-        {
-            // It's a block - collect all arguments as arms
-            arms.extend(apply.all_arguments());
-            continue;
+    let mut tree = Tree {
+        parts,
+        span: match_expr.span(),
+        source,
+        gen_expr,
+    };
+    gen_tree(rows, &mut tree, state, ctx)
+}
+
+/// Adds the tests and bindings of `pattern`, matched against the part of the
+/// value at `path`, to `row`.
+fn flatten(pattern: &Pattern, path: Path, row: &mut Row) -> Result<()> {
+    match &pattern.kind {
+        PatternKind::Wildcard => {}
+        PatternKind::Bind(name) => row.bindings.push((*name, path)),
+        PatternKind::Literal(value) => row.tests.push((path, value.clone())),
+        PatternKind::Tuple(elements) => {
+            for (index, element) in elements.iter().enumerate() {
+                let mut path = path.clone();
+                path.push(index);
+                flatten(element, path, row)?;
+            }
+        }
+        PatternKind::Record(_) | PatternKind::List(_) | PatternKind::Variant(..) => {
+            return Err(Diagnostic::syntax(
+                "record, list and variant patterns in match are not yet supported in IR",
+            )
+            .with_span(pattern.span));
+        }
+        PatternKind::Quantity(..) => {
+            return Err(Diagnostic::syntax(
+                "unit patterns inside other patterns are not yet supported in IR",
+            )
+            .with_span(pattern.span));
         }
-        // Otherwise, it's a single arm
-        arms.push(arg.clone());
     }
+    Ok(())
+}
 
-    // Parse the arms to find the then and else branches
-    // Each arm should be: pattern -> result
-    let mut then_expr = None;
-    let mut else_expr = None;
+/// Generates the decision tree for the arms `rows`, in order, that may still
+/// match given the tests made on the way to this node.
+fn gen_tree(
+    rows: Vec<Row>,
+    tree: &mut Tree<'_, '_>,
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+) -> Result<ValueId> {
+    let Some(first) = rows.first() else {
+        return Err(Diagnostic::syntax(
+            "match in compiled code must cover every value: add a `_` arm",
+        )
+        .with_span(tree.span));
+    };
 
-    for arm in &arms {
-        if let Expr::Apply(apply) = arm
-            && let Some(Expr::Op(op)) = apply.callee()
-            && op.syntax().text() == "=>"
-        {
-            let arm_args = apply.all_arguments();
-            if arm_args.len() == 2 {
-                let pattern = &arm_args[0];
-                let result_expr = &arm_args[1];
-
-                if let Expr::Ident(ident) = pattern {
-                    let pattern_text = ident.syntax().text();
-                    match pattern_text.as_str() {
-                        "true" => then_expr = Some(result_expr.clone()),
-                        "false" => else_expr = Some(result_expr.clone()),
-                        _ => {
-                            return Err(Diagnostic::syntax(format!(
-                                "match pattern must be 'true' or 'false', got '{}'",
-                                pattern_text.as_str()
-                            ))
-                            .with_span(pattern.span()));
-                        }
-                    }
-                }
+    // The first arm that may still match matches if it tests nothing more
+    // and its guard holds
+    let Some((path, literal)) = first.tests.first().cloned() else {
+        if first.guard.is_some() {
+            return gen_guard(rows, tree, state, ctx);
+        }
+        return gen_leaf(first.clone(), tree, state, ctx);
+    };
+
+    // Otherwise test its first literal. An arm testing the same part for
+    // the same literal goes on to its next test if it's equal, and an arm
+    // testing it for another literal can only match if it isn't
+    let tested = |row: &Row| row.tests.iter().position(|(p, _)| *p == path);
+    let equal: Vec<Row> = rows
+        .iter()
+        .filter_map(|row| match tested(row) {
+            Some(i) if row.tests[i].1 != literal => None,
+            Some(i) => {
+                let mut row = row.clone();
+                row.tests.remove(i);
+                Some(row)
             }
+            None => Some(row.clone()),
+        })
+        .collect();
+    let not_equal: Vec<Row> = rows
+        .into_iter()
+        .filter_map(|mut row| match tested(&row) {
+            Some(i) if row.tests[i].1 == literal => None,
+            // A boolean that isn't one literal is the other
+            Some(i) if matches!(literal, Value::Bool(_)) => {
+                row.tests.remove(i);
+                Some(row)
+            }
+            _ => Some(row),
+        })
+        .collect();
+
+    let part = tree.parts[&path];
+    let source = tree.source;
+    match literal {
+        // A boolean is its own test
+        Value::Bool(true) => gen_branch(part, equal, not_equal, tree, state, ctx),
+        Value::Bool(false) => gen_branch(part, not_equal, equal, tree, state, ctx),
+        literal => {
+            let (value, ty) = match literal {
+                Value::Integer(n) => (IrConst::Integer(n), Type::Integer),
+                Value::Float(n) => (IrConst::Float(n), Type::Float),
+                Value::String(s) => (IrConst::String(InternedString::new(&s)), Type::String),
+                other => {
+                    return Err(Diagnostic::syntax(format!(
+                        "can't compile a match on {}",
+                        other.type_of()
+                    ))
+                    .with_span(tree.span));
+                }
+            };
+            // Comparisons are typed by their operands
+            let block = state.current_block();
+            let literal = block.const_val(value, ty.clone(), source);
+            let cond = block.binop(BinOp::Eq, part, literal, ty, source);
+            ctx.set_value_type(cond, Type::Bool);
+            gen_branch(cond, equal, not_equal, tree, state, ctx)
         }
     }
+}
 
-    let then_expr =
-        then_expr.ok_or_else(|| Diagnostic::syntax("match expression missing 'true' pattern"))?;
-    let else_expr =
-        else_expr.ok_or_else(|| Diagnostic::syntax("match expression missing 'false' pattern"))?;
-
-    // Generate the condition in the entry block
-    let cond = gen_expr(match_expr, state, ctx)?;
+/// Generates a node of the decision tree: a branch on `cond` to the trees
+/// for `then_rows` and `else_rows`, joined by a phi.
+fn gen_branch(
+    cond: ValueId,
+    then_rows: Vec<Row>,
+    else_rows: Vec<Row>,
+    tree: &mut Tree<'_, '_>,
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+) -> Result<ValueId> {
+    let source = tree.source;
 
     // Allocate block IDs for then, else, and merge blocks
     let then_block_id = state.alloc_block_id();
     let else_block_id = state.alloc_block_id();
     let merge_block_id = state.alloc_block_id();
 
-    // Complete the entry block with a branch instruction
+    // Complete the current block with a branch instruction
     let current = state
         .current_block
         .take()
@@ -241,32 +595,43 @@ pub fn ir_match_with_state(
     let (entry_block, next_val) = current.branch(cond, then_block_id, else_block_id, source);
     state.complete_current_block(entry_block, next_val);
 
-    // Now create the then block (gets fresh value IDs after entry block)
+    // Both branches start from the variables moved before the branch, and
+    // each moves its result into the phi
+    let moved = ctx.moved_vars();
+
     let then_block = state.create_block_with_id(then_block_id);
     state.current_block = Some(then_block);
-    let then_value = gen_expr(&then_expr, state, ctx)?;
+    let then_value = gen_tree(then_rows, tree, state, ctx)?;
+    // The subtree may have ended in a merge block of its own
     let then_block = state
         .current_block
         .take()
         .expect("Current block missing after generating then branch");
+    let then_exit = then_block.id();
+    let then_moved = ctx.moved_vars();
+    ctx.set_moved_vars(moved);
     let (then_block_complete, then_next_val) = then_block.jump(merge_block_id, source);
     state.complete_current_block(then_block_complete, then_next_val);
 
-    // Create the else block (gets fresh value IDs after then block)
     let else_block = state.create_block_with_id(else_block_id);
     state.current_block = Some(else_block);
-    let else_value = gen_expr(&else_expr, state, ctx)?;
+    let else_value = gen_tree(else_rows, tree, state, ctx)?;
     let else_block = state
         .current_block
         .take()
         .expect("Current block missing after generating else branch");
+    let else_exit = else_block.id();
+
+    // A variable moved on either path is moved after the branch
+    let mut moved = ctx.moved_vars();
+    moved.extend(then_moved);
+    ctx.set_moved_vars(moved);
     let (else_block_complete, else_next_val) = else_block.jump(merge_block_id, source);
     state.complete_current_block(else_block_complete, else_next_val);
 
     // Create the merge block with phi node
-    let merge_block = state.create_block_with_id(merge_block_id);
-    let mut merge = merge_block;
-    let incoming = vec![(then_value, then_block_id), (else_value, else_block_id)];
+    let mut merge = state.create_block_with_id(merge_block_id);
+    let incoming = vec![(then_value, then_exit), (else_value, else_exit)];
 
     // Infer the type from the branches (prefer non-Unknown types)
     let result_ty = ctx
@@ -284,10 +649,78 @@ pub fn ir_match_with_state(
     Ok(result)
 }
 
+/// Generates the guard of the first of `rows`, which tests nothing more: a
+/// branch to its result if the guard holds and to the tree of the other
+/// rows if it doesn't.
+fn gen_guard(
+    mut rows: Vec<Row>,
+    tree: &mut Tree<'_, '_>,
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+) -> Result<ValueId> {
+    let mut first = rows.remove(0);
+    let guard = first.guard.take().expect("the row has a guard");
+    let saved = bind_row(&first, tree, state, ctx);
+    let cond = (tree.gen_expr)(&guard, state, ctx);
+    ctx.restore_vars(saved);
+    gen_branch(cond?, vec![first], rows, tree, state, ctx)
+}
+
+/// Generates a leaf of the decision tree: the result of the arm `row`, with
+/// its variables bound to the parts of the value.
+fn gen_leaf(
+    row: Row,
+    tree: &mut Tree<'_, '_>,
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+) -> Result<ValueId> {
+    let saved = bind_row(&row, tree, state, ctx);
+    let result = (tree.gen_expr)(&row.result, state, ctx);
+    if result.is_ok() {
+        ctx.consume(&row.result);
+    }
+    ctx.restore_vars(saved);
+    result
+}
+
+/// Binds the variables of the arm `row` to the parts of the value, and
+/// returns the bindings they shadow.
+fn bind_row(
+    row: &Row,
+    tree: &mut Tree<'_, '_>,
+    state: &mut IrGenState,
+    ctx: &mut IrGenContext,
+) -> Vec<SavedVar> {
+    let mut names: Vec<InternedString> = row.bindings.iter().map(|(name, _)| *name).collect();
+    names.extend(row.magnitude.as_ref().map(|(name, _)| *name));
+    let saved = ctx.save_vars(&names);
+    for (name, path) in &row.bindings {
+        let part = tree.parts[path];
+        let ty = ctx.get_value_type(part).cloned().unwrap_or(Type::Unknown);
+        ctx.bind_var(*name, part, &InferType::Concrete(ty));
+    }
+    if let Some((name, unit)) = &row.magnitude {
+        // The quantity is held in base units. Dividing always makes a new
+        // value, so the magnitude doesn't take on the quantity's dimension
+        let source = tree.source;
+        let block = state.current_block();
+        let mut magnitude = tree.parts[&vec![]];
+        if unit.offset != 0.0 {
+            let offset = block.const_val(IrConst::Float(unit.offset), Type::Float, source);
+            magnitude = block.binop(BinOp::Sub, magnitude, offset, Type::Float, source);
+        }
+        let scale = block.const_val(IrConst::Float(unit.scale), Type::Float, source);
+        magnitude = block.binop(BinOp::Div, magnitude, scale, Type::Float, source);
+        ctx.set_value_type(magnitude, Type::Float);
+        ctx.bind_var(*name, magnitude, &InferType::Concrete(Type::Float));
+    }
+    saved
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Compiler, Env};
+    use crate::{Compiler, DiagnosticKind, Env};
     use cadenza_syntax::parse::parse;
 
     #[test]
@@ -340,4 +773,136 @@ mod tests {
         assert_eq!(results2.len(), 1);
         assert_eq!(results2[0], Value::String("positive".into()));
     }
+
+    fn eval_all(input: &str) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn test_match_literals_and_wildcards() {
+        let (results, compiler) = eval_all(
+            r#"
+fn describe n = match n
+    0 => "zero"
+    (-1) => "minus one"
+    "one" => "a string"
+    _ => "other"
+describe 0
+describe (0 - 1)
+describe "one"
+describe 7
+"#,
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        let shown: Vec<String> = results[1..].iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, ["zero", "minus one", "a string", "other"]);
+    }
+
+    #[test]
+    fn test_match_nested_patterns_bind_in_their_arm() {
+        let (results, compiler) = eval_all(
+            r#"
+let x = "outer"
+fn area shape = match shape
+    { kind = "square", size } => size * size
+    { kind = "rect", dims = (w, h) } => w * h
+    [a, b] => a + b
+    _ => 0
+area { kind = "square", size = 3 }
+area { kind = "rect", dims = (2, 5) }
+area [4, 6]
+area [1, 2, 3]
+match (1, 2) (x, y) => x + y
+x
+"#,
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        let shown: Vec<String> = results[2..].iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, ["9", "10", "10", "0", "3", "outer"]);
+    }
+
+    #[test]
+    fn test_match_units_binds_the_magnitude_in_the_unit() {
+        let (results, compiler) = eval_all(
+            r#"
+measure millimeter
+measure inch = millimeter 25.4
+measure second
+fn describe q = match q
+    (inch n) => n
+    (second t) => t * 1000.0
+describe 254millimeter
+describe 2second
+"#,
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results[4..], [Value::Float(10.0), Value::Float(2000.0)]);
+    }
+
+    #[test]
+    fn test_match_units_warns_about_uncovered_measures() {
+        let (results, compiler) = eval_all(
+            r#"
+measure meter
+measure second
+measure gram
+fn length q = match q ((meter x) => x)
+length 3meter
+length 4meter
+"#,
+        );
+        let messages: Vec<String> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(messages.len(), 1, "{messages:?}");
+        assert!(
+            messages[0].contains("add an arm for second, gram or a `_` arm"),
+            "{messages:?}"
+        );
+        assert_eq!(results[5], Value::Float(4.0));
+    }
+
+    #[test]
+    fn test_match_guards_fall_through_to_later_arms() {
+        let (results, compiler) = eval_all(
+            r#"
+let n = "outer"
+fn size x = match x
+    n when n > 10 => "big"
+    n when n < 0 => "negative"
+    _ => "small"
+size 42
+size (0 - 1)
+size 5
+match (1, 1) ((a, b) when a == b => "same") (_ => "different")
+n
+"#,
+        );
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        let shown: Vec<String> = results[2..].iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, ["big", "negative", "small", "same", "outer"]);
+    }
+
+    #[test]
+    fn test_match_guard_must_be_a_boolean() {
+        let (_, compiler) = eval_all("match 3 (n when n => 1) (_ => 2)\n");
+        assert!(matches!(
+            compiler.diagnostics()[0].kind(),
+            DiagnosticKind::TypeError {
+                expected: Type::Bool,
+                actual: Type::Integer,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_match_without_matching_arm_is_an_error() {
+        let (_, compiler) = eval_all("match 3 0 => 1 1 => 2\n");
+        assert!(compiler.has_errors());
+    }
 }
@@ -55,7 +55,7 @@ fn eval_measure(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
                 let text = ident.syntax().text();
                 let name: InternedString = text.to_string().as_str().into();
                 let unit = Unit::base(name);
-                ctx.compiler.units_mut().register(unit);
+                ctx.compiler.register_unit(unit);
                 return Ok(Value::Nil);
             }
             _ => {
@@ -118,6 +118,7 @@ fn eval_measure(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
                 let scale = match scale_value {
                     Value::Integer(n) => n as f64,
                     Value::Float(f) => f,
+                    Value::Decimal(d) => d.to_f64(),
                     _ => {
                         return Err(Diagnostic::syntax(
                             "measure conversion scale must be a number",
@@ -133,7 +134,7 @@ fn eval_measure(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
                 // Create derived unit: 1 new_unit = scale base_units
                 let derived_unit = Unit::derived(name, base_unit.dimension, scale, 0.0);
 
-                ctx.compiler.units_mut().register(derived_unit);
+                ctx.compiler.register_unit(derived_unit);
                 Ok(Value::Nil)
             }
             _ => Err(Diagnostic::syntax(
@@ -6,7 +6,7 @@ use crate::{
     diagnostic::{Diagnostic, Result},
     eval::{apply_value, eval_ident_no_auto_apply, extract_identifier},
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
-    special_form::BuiltinSpecialForm,
+    special_form::{BuiltinSpecialForm, lambda_form},
     value::{Type, Value},
 };
 use cadenza_syntax::ast::Expr;
@@ -22,6 +22,7 @@ use std::sync::OnceLock;
 /// - The RHS can be:
 ///   1. A function identifier (e.g., `|> f` means `f lhs_value`)
 ///   2. A function application (e.g., `|> f x y` means `f lhs_value x y`)
+///   3. An anonymous function (e.g., `|> (x -> x + 1)`)
 /// - Returns the result of the function application
 ///
 /// # IR Generation
@@ -32,6 +33,7 @@ use std::sync::OnceLock;
 /// 5 |> add 3           // Equivalent to: add 5 3
 /// 10 |> sub 2 |> mul 3 // Equivalent to: mul (sub 10 2) 3
 /// x |> f |> g          // Equivalent to: g (f x)
+/// 5 |> (x -> x * 2)    // Equivalent to: (x -> x * 2) 5
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static PIPELINE_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
@@ -63,6 +65,11 @@ fn eval_pipeline(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
             // Apply the function to the LHS value
             apply_value(func, vec![lhs_value], ctx)
         }
+        // Case 3: RHS is an anonymous function - apply it to the LHS value
+        rhs if lambda_form::is_lambda(rhs) => {
+            let func = rhs.eval(ctx)?;
+            apply_value(func, vec![lhs_value], ctx)
+        }
         // Case 2: RHS is an application - inject LHS as first argument
         Expr::Apply(apply) => {
             // Get the callee
@@ -4,12 +4,14 @@ use crate::{
     Eval,
     context::EvalContext,
     diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
     interner::InternedString,
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
     special_form::BuiltinSpecialForm,
+    suggest,
     value::{Type, Value},
 };
-use cadenza_syntax::ast::Expr;
+use cadenza_syntax::{ast::Expr, span::Span};
 use std::sync::OnceLock;
 
 /// Returns the `__record__` special form for record literals.
@@ -19,9 +21,19 @@ use std::sync::OnceLock;
 /// # Evaluation
 /// - Takes variable number of arguments (field assignments or identifiers)
 /// - Each argument can be:
-///   1. An assignment expression: `[=, field_name, value_expr]`
+///   1. An assignment expression: `[=, field_name, value_expr]` or `[:, field_name, value_expr]`
 ///   2. A shorthand identifier: just the field name (expands to `field = field`)
-/// - Returns a Record value with evaluated fields
+/// - Returns a Record value with evaluated fields, in the order written
+/// - A field given twice is an error
+/// - A spread `...r` adds the fields of the record `r`. A field after it, or
+///   in a later spread, overrides one it gave, in the earlier field's place,
+///   if the two have the same type. Spreading anything but a record is a
+///   type error
+/// - An update `{ r | x = 1 }` is a copy of the record `r` with the fields
+///   after the `|` replaced. Each must be one of `r`'s fields and keep its
+///   type, so the result has `r`'s type, including its struct name
+///
+/// The same field syntax is used by record patterns (see [`crate::pattern`]).
 ///
 /// # IR Generation
 /// - Not yet implemented (returns error)
@@ -29,8 +41,11 @@ use std::sync::OnceLock;
 /// # Examples
 /// ```cadenza
 /// { a = 1, b = 2 }  // Full syntax
+/// { a: 1, b: 2 }    // Full syntax, matching record patterns
 /// { x, y }          // Shorthand syntax (uses x and y from environment)
 /// {}                // Empty record
+/// { ...point, z = 0 } // The fields of point, then z
+/// { point | x = 30 }  // A copy of point with x replaced
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static RECORD_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
@@ -42,67 +57,309 @@ pub fn get() -> &'static BuiltinSpecialForm {
     })
 }
 
-fn eval_record(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
-    // Each argument can be either:
-    // 1. An assignment expression: [=, field_name, value_expr]
-    // 2. A shorthand identifier: just the field name (expands to field = field)
-    let mut fields = Vec::with_capacity(args.len());
+/// A field in a record literal or record pattern.
+pub(crate) struct RecordField {
+    /// The field name.
+    pub name: InternedString,
+    /// The span of the field name.
+    pub span: Span,
+    /// The value (in a literal) or the pattern it is bound to (in a pattern);
+    /// `None` for the shorthand `{ x }`.
+    pub value: Option<Expr>,
+}
 
-    for arg in args {
-        match arg {
-            // Shorthand syntax: { x, y } where x and y are identifiers
-            Expr::Ident(ident) => {
-                let text = ident.syntax().text();
-                let field_name = InternedString::new(&text.to_string());
+/// An entry in a record literal: a field, a spread of another record's
+/// fields, or the record an update copies.
+pub(crate) enum RecordEntry {
+    Field(RecordField),
+    /// The record after `...`.
+    Spread(Expr),
+    /// The record before the `|` of `{ r | x = 1 }`, which is always the
+    /// first entry.
+    Base(Expr),
+}
+
+/// Returns the operand of the spread `...expr`, or `None` if `expr` isn't a
+/// spread.
+pub(crate) fn as_spread(expr: &Expr) -> Option<Expr> {
+    let Expr::Apply(apply) = expr else {
+        return None;
+    };
+    let callee = extract_identifier(&apply.callee()?)?;
+    if &*callee != "..." {
+        return None;
+    }
+    match <[Expr; 1]>::try_from(apply.all_arguments()) {
+        Ok([operand]) => Some(operand),
+        Err(_) => None,
+    }
+}
+
+/// The parts of the first argument of a record update: `{ r | x = e }`
+/// parses as `(r | x) = e`, and the shorthand `{ r | x }` as `r | x`.
+struct Update {
+    /// The record the update copies.
+    base: Expr,
+    /// The first field's name.
+    name: Expr,
+    /// The first field's value; `None` for the shorthand.
+    value: Option<Expr>,
+}
+
+/// Returns the parts of `expr` if it is the first argument of an update.
+fn as_update(expr: &Expr) -> Option<Update> {
+    let Expr::Apply(apply) = expr else {
+        return None;
+    };
+    let callee = extract_identifier(&apply.callee()?)?;
+    let [first, second] = <[Expr; 2]>::try_from(apply.all_arguments()).ok()?;
+    match &*callee {
+        "|" => Some(Update {
+            base: first,
+            name: second,
+            value: None,
+        }),
+        "=" | ":" => {
+            let update = as_update(&first)?;
+            update.value.is_none().then_some(Update {
+                value: Some(second),
+                ..update
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Splits the arguments of a `__record__` form into fields.
+///
+/// `{ x }`, `{ x = e }` and `{ x: e }` all lower to the same [`RecordField`],
+/// so record literals and record patterns accept exactly the same syntax.
+/// A spread or update is an error here; only [`record_entries`] accepts
+/// them.
+pub(crate) fn record_fields(args: &[Expr]) -> Result<Vec<RecordField>> {
+    // An update's base comes first, so the error is for the first argument
+    record_entries(args)?
+        .into_iter()
+        .zip(args)
+        .map(|(entry, arg)| match entry {
+            RecordEntry::Field(field) => Ok(field),
+            RecordEntry::Spread(_) => Err(Diagnostic::syntax(
+                "a spread is only allowed in a record literal",
+            )
+            .with_span(arg.span())),
+            RecordEntry::Base(_) => Err(Diagnostic::syntax(
+                "a record update is only allowed in a record literal",
+            )
+            .with_span(arg.span())),
+        })
+        .collect()
+}
+
+/// Returns the field `name` names, with `value`.
+fn record_field(name: &Expr, value: Option<Expr>) -> Result<RecordField> {
+    let Expr::Ident(ident) = name else {
+        return Err(
+            Diagnostic::syntax("record field name must be an identifier").with_span(name.span()),
+        );
+    };
+    Ok(RecordField {
+        name: ident.syntax().text().interned(),
+        span: ident.span(),
+        value,
+    })
+}
 
-                // Look up the variable in the environment
-                let value = ctx.env.get(field_name).cloned().ok_or_else(|| {
-                    Diagnostic::undefined_variable(field_name).with_span(ident.span())
-                })?;
+/// Splits the arguments of a `__record__` form into fields, spreads and the
+/// base of an update.
+pub(crate) fn record_entries(args: &[Expr]) -> Result<Vec<RecordEntry>> {
+    let mut entries = Vec::with_capacity(args.len() + 1);
 
-                fields.push((field_name, value));
+    for (i, arg) in args.iter().enumerate() {
+        if i == 0
+            && let Some(update) = as_update(arg)
+        {
+            entries.push(RecordEntry::Base(update.base));
+            entries.push(RecordEntry::Field(record_field(
+                &update.name,
+                update.value,
+            )?));
+            continue;
+        }
+        if let Some(operand) = as_spread(arg) {
+            // An update keeps its base's type, which a spread could change
+            if matches!(entries.first(), Some(RecordEntry::Base(_))) {
+                return Err(
+                    Diagnostic::syntax("a record update can't spread another record")
+                        .with_span(arg.span()),
+                );
             }
-            // Full syntax: { a = 1, b = 2 }
+            entries.push(RecordEntry::Spread(operand));
+            continue;
+        }
+        match arg {
+            // Shorthand syntax: { x, y } where x and y are identifiers
+            Expr::Ident(ident) => entries.push(RecordEntry::Field(RecordField {
+                name: ident.syntax().text().interned(),
+                span: ident.span(),
+                value: None,
+            })),
+            // Full syntax: { a = 1, b = 2 } or { a: 1, b: 2 }
             Expr::Apply(apply) => {
+                let is_field = apply
+                    .callee()
+                    .and_then(|callee| extract_identifier(&callee))
+                    .is_some_and(|op| matches!(&*op, "=" | ":"));
+                if !is_field {
+                    return Err(Diagnostic::syntax(
+                        "record field must be an identifier or assignment expression",
+                    )
+                    .with_span(arg.span()));
+                }
+
                 // Get all arguments once to avoid duplicate calls
-                let all_args = apply.all_arguments();
+                let mut all_args = apply.all_arguments();
                 if all_args.len() != 2 {
                     return Err(Diagnostic::syntax(
                         "record field assignment must have exactly 2 arguments",
                     ));
                 }
-
-                // Extract the field name (should be an identifier)
-                let field_name = match &all_args[0] {
-                    Expr::Ident(ident) => {
-                        let text = ident.syntax().text();
-                        InternedString::new(&text.to_string())
-                    }
-                    _ => {
-                        return Err(Diagnostic::syntax(
-                            "record field name must be an identifier",
-                        ));
-                    }
-                };
-
-                // Evaluate the field value (second arg)
-                let value = all_args[1].eval(ctx)?;
-
-                fields.push((field_name, value));
+                let value = all_args.pop();
+                entries.push(RecordEntry::Field(record_field(&all_args[0], value)?));
             }
             _ => {
                 return Err(Diagnostic::syntax(
                     "record field must be an identifier or assignment expression",
-                ));
+                )
+                .with_span(arg.span()));
             }
         }
     }
 
-    // Return the record value (structural type, so type_name is None)
-    Ok(Value::Record {
-        type_name: None,
-        fields,
-    })
+    // A field written twice would leave the record's order and contents up
+    // to which one wins, so it's rejected. A spread's fields are overridden
+    // instead, since that's what spreading a record is for
+    let fields: Vec<&RecordField> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            RecordEntry::Field(field) => Some(field),
+            RecordEntry::Spread(_) | RecordEntry::Base(_) => None,
+        })
+        .collect();
+    for (i, field) in fields.iter().enumerate() {
+        if fields[..i].iter().any(|earlier| earlier.name == field.name) {
+            return Err(Diagnostic::syntax(format!(
+                "field '{}' is given more than once",
+                &*field.name
+            ))
+            .with_span(field.span));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Returns the fields of `pattern` if it is a record pattern (`{ ... }`).
+pub(crate) fn as_record_pattern(pattern: &Expr) -> Option<Vec<Expr>> {
+    let Expr::Apply(apply) = pattern else {
+        return None;
+    };
+    let callee = extract_identifier(&apply.callee()?)?;
+    (&*callee == "__record__").then(|| apply.all_arguments())
+}
+
+fn eval_record(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let entries = record_entries(args)?;
+    let mut fields = Vec::with_capacity(entries.len());
+    // The struct name an update keeps, and whether the record is one
+    let mut type_name = None;
+    let mut update = false;
+
+    for entry in entries {
+        let field = match entry {
+            RecordEntry::Field(field) => field,
+            RecordEntry::Base(base) => {
+                match base.eval(ctx)? {
+                    Value::Record {
+                        type_name: name,
+                        fields: base_fields,
+                    } => {
+                        type_name = name;
+                        fields = base_fields;
+                    }
+                    value => {
+                        return Err(
+                            Diagnostic::type_error(Type::Record(vec![]), value.type_of())
+                                .with_span(base.span()),
+                        );
+                    }
+                }
+                update = true;
+                continue;
+            }
+            RecordEntry::Spread(operand) => {
+                let spread = match operand.eval(ctx)? {
+                    Value::Record { fields, .. } => fields,
+                    value => {
+                        return Err(
+                            Diagnostic::type_error(Type::Record(vec![]), value.type_of())
+                                .with_span(operand.span()),
+                        );
+                    }
+                };
+                for (name, value) in spread {
+                    set_field(&mut fields, name, value, operand.span())?;
+                }
+                continue;
+            }
+        };
+        let value = match &field.value {
+            Some(value) => value.eval(ctx)?,
+            // Shorthand: look up the variable in the environment
+            None => match ctx.env.get(field.name) {
+                Some(Value::Error) => {
+                    return Err(Diagnostic::poisoned(field.name).with_span(field.span));
+                }
+                Some(value) => value.clone(),
+                None => {
+                    return Err(suggest::undefined_variable(field.name, ctx).with_span(field.span));
+                }
+            },
+        };
+        // An update replaces fields; adding one would change the type
+        if update && !fields.iter().any(|(name, _)| *name == field.name) {
+            let type_description = match type_name {
+                Some(name) => format!("struct {}", &*name),
+                None => "record".to_string(),
+            };
+            return Err(
+                Diagnostic::field_not_found(field.name, type_description).with_span(field.span)
+            );
+        }
+        set_field(&mut fields, field.name, value, field.span)?;
+    }
+
+    // Literals are structural; an update keeps its base's struct name
+    Ok(Value::Record { type_name, fields })
+}
+
+/// Adds the field `name` to `fields`, overriding the value an earlier spread,
+/// or the record an update copies, gave it, which must have the same type.
+fn set_field(
+    fields: &mut Vec<(InternedString, Value)>,
+    name: InternedString,
+    value: Value,
+    span: Span,
+) -> Result<()> {
+    let Some((_, earlier)) = fields.iter_mut().find(|(field, _)| *field == name) else {
+        fields.push((name, value));
+        return Ok(());
+    };
+    let (expected, actual) = (earlier.type_of(), value.type_of());
+    if expected != actual {
+        return Err(Diagnostic::type_error(expected, actual).with_span(span));
+    }
+    *earlier = value;
+    Ok(())
 }
 
 fn ir_record(
@@ -120,7 +377,7 @@ fn ir_record(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Compiler, Env};
+    use crate::{Compiler, Env, diagnostic::DiagnosticKind};
     use cadenza_syntax::parse::parse;
 
     #[test]
@@ -203,4 +460,67 @@ let y = 20
             _ => panic!("Expected Record value"),
         }
     }
+
+    #[test]
+    fn test_record_spread() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = r#"
+let a = { x = 1, y = 2 }
+let b = { y = 20, z = 30 }
+{ ...a, ...b, x = 10 }
+{ ...a, y = "two" }
+{ ...[1] }
+"#;
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+
+        assert_eq!(
+            results[2],
+            Value::Record {
+                type_name: None,
+                fields: vec![
+                    ("x".into(), Value::Integer(10)),
+                    ("y".into(), Value::Integer(20)),
+                    ("z".into(), Value::Integer(30)),
+                ],
+            }
+        );
+        let diagnostics = compiler.diagnostics();
+        assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+        assert!(matches!(
+            &diagnostics[0].kind,
+            DiagnosticKind::TypeError {
+                expected: Type::Integer,
+                actual: Type::String,
+            }
+        ));
+        assert!(matches!(
+            &diagnostics[1].kind,
+            DiagnosticKind::TypeError {
+                expected: Type::Record(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_record_duplicate_field() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = "{ a = 1, b = 2, a = 3 }";
+        let parsed = parse(input);
+        let root = parsed.ast();
+
+        crate::eval(&root, &mut env, &mut compiler);
+
+        let diagnostics = compiler.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .contains("field 'a' is given more than once")
+        );
+    }
 }
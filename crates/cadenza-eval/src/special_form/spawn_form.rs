@@ -0,0 +1,237 @@
+//! The `spawn` special form for starting tasks.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{Diagnostic, Result},
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use std::sync::OnceLock;
+
+/// Returns the `spawn` special form for starting tasks.
+///
+/// The `spawn` special form queues an expression to be evaluated as a task on
+/// the compiler's executor. See [`crate::task`] for scheduling semantics.
+///
+/// # Evaluation
+/// - Takes 1 argument: the expression to run
+/// - Captures the current environment, like a zero-argument closure
+/// - Does not evaluate the expression; it gets a turn when other code waits,
+///   for it or on a channel, or when top-level evaluation finishes
+/// - Returns a task handle
+///
+/// # IR Generation
+/// - Not supported
+///
+/// # Examples
+/// ```cadenza
+/// let t = spawn (expensive 42)
+/// await t
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static SPAWN_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    SPAWN_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "spawn",
+        signature: Type::function(vec![Type::Unknown], Type::task(Type::Unknown)),
+        eval_fn: eval_spawn,
+        ir_fn: ir_spawn,
+    })
+}
+
+fn eval_spawn(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Diagnostic::arity(1, args.len()));
+    }
+
+    let id = ctx
+        .compiler
+        .executor_mut()
+        .spawn(args[0].clone(), ctx.env.clone());
+    Ok(Value::Task(id))
+}
+
+fn ir_spawn(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "spawn is not supported in IR generation",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, DiagnosticKind, Env, Value};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_src(src: &str) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(src).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn spawn_is_lazy_until_awaited() {
+        let (results, compiler) = eval_src("let t = spawn (1 + 2)\nawait t\n");
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results[1], Value::Integer(3));
+    }
+
+    #[test]
+    fn channels_stream_between_tasks() {
+        let src = r#"
+let ch = channel 1
+let producer = spawn (send ch 10)
+let consumer = spawn ((recv ch) * 2)
+await consumer
+"#;
+        let (results, compiler) = eval_src(src);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results[3], Value::Integer(20));
+    }
+
+    const PRODUCER: &str = "fn produce ch =\n    send ch 1\n    send ch 2\n    send ch 3\n";
+
+    #[test]
+    fn send_suspends_while_the_channel_is_full() {
+        let src = format!(
+            "{PRODUCER}let ch = channel 1\nlet producer = spawn (produce ch)\nrecv ch\nrecv ch\nrecv ch\n"
+        );
+        let (results, compiler) = eval_src(&src);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(
+            results[3..],
+            [Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+        );
+    }
+
+    #[test]
+    fn recv_suspends_while_the_channel_is_empty() {
+        let src = format!(
+            "{PRODUCER}let ch = channel 1\nlet consumer = spawn [recv ch, recv ch, recv ch]\nlet producer = spawn (produce ch)\nawait consumer\n"
+        );
+        let (results, compiler) = eval_src(&src);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(
+            results[4],
+            Value::List(vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3)
+            ])
+        );
+    }
+
+    #[test]
+    fn tasks_suspend_deep_in_recursion() {
+        // Deep enough to move the task to another stack segment first
+        let src = format!(
+            "{PRODUCER}fn deep ch n = if n == 0 then (produce ch) else [(deep ch (n - 1))]\nlet ch = channel 1\nlet t = spawn (deep ch 3000)\nrecv ch\nrecv ch\nrecv ch\n"
+        );
+        let (results, compiler) = eval_src(&src);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(
+            results[4..],
+            [Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+        );
+    }
+
+    #[test]
+    fn recv_without_sender_is_a_deadlock() {
+        let (_, compiler) = eval_src("let ch = channel 1\nrecv ch\n");
+        let kinds: Vec<_> = compiler.diagnostics().iter().map(|d| d.kind()).collect();
+        assert!(
+            matches!(kinds.as_slice(), [DiagnosticKind::Deadlock(_)]),
+            "{kinds:?}"
+        );
+    }
+
+    #[test]
+    fn tasks_waiting_on_each_other_are_a_deadlock() {
+        let src = "let ch = channel 1\nlet a = spawn (recv ch)\nlet b = spawn (await a)\nawait b\n";
+        let (_, compiler) = eval_src(src);
+        let kinds: Vec<_> = compiler.diagnostics().iter().map(|d| d.kind()).collect();
+        // `await b` fails, then `a` and `b` are both woken with one error
+        assert!(
+            matches!(
+                kinds.as_slice(),
+                [DiagnosticKind::Deadlock(_), DiagnosticKind::Deadlock(_)]
+            ),
+            "{kinds:?}"
+        );
+    }
+
+    #[test]
+    fn stuck_tasks_report_one_deadlock() {
+        let src = "let ch = channel 1
+fn spawn_all n = if n == 0 then 0 else [spawn (send ch n), spawn_all (n - 1)]
+spawn_all 500
+";
+        let (_, compiler) = eval_src(src);
+        let kinds: Vec<_> = compiler.diagnostics().iter().map(|d| d.kind()).collect();
+        assert!(
+            matches!(kinds.as_slice(), [DiagnosticKind::Deadlock(_)]),
+            "{kinds:?}"
+        );
+    }
+
+    #[test]
+    fn waiting_unawaited_tasks_are_a_deadlock() {
+        let src = format!("{PRODUCER}let ch = channel 1\nspawn (produce ch)\n");
+        let (_, compiler) = eval_src(&src);
+        let kinds: Vec<_> = compiler.diagnostics().iter().map(|d| d.kind()).collect();
+        assert!(
+            matches!(kinds.as_slice(), [DiagnosticKind::Deadlock(_)]),
+            "{kinds:?}"
+        );
+    }
+
+    #[test]
+    fn exit_cancels_waiting_tasks() {
+        let src = format!("{PRODUCER}let ch = channel 1\nspawn (produce ch)\nrecv ch\nexit 0\n");
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        compiler.set_capabilities(crate::system::Capabilities::all());
+        crate::eval(&parse(&src).ast(), &mut env, &mut compiler);
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(compiler.exit_status(), Some(0));
+        let producer = compiler.executor().result(crate::task::TaskId(0));
+        assert!(matches!(producer, Some(Err(_))), "{producer:?}");
+    }
+
+    #[test]
+    fn unawaited_task_errors_are_reported() {
+        let (_, compiler) = eval_src("spawn (undefined_thing)\n");
+        assert_eq!(compiler.num_diagnostics(), 1);
+    }
+}
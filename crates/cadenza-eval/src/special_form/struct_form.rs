@@ -3,7 +3,8 @@
 use crate::{
     Eval,
     context::EvalContext,
-    diagnostic::{Diagnostic, Result},
+    derive,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, DiagnosticLevel, Result},
     interner::InternedString,
     ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
     special_form::BuiltinSpecialForm,
@@ -22,6 +23,7 @@ use std::sync::OnceLock;
 /// - Field definitions are a record where each field maps to its type
 /// - Creates a struct type definition
 /// - Registers a constructor function with the struct name in the environment
+/// - Derives the traits named by a preceding `@derive` (see [`crate::derive`])
 /// - Returns the struct type as a Type value
 ///
 /// # IR Generation
@@ -72,6 +74,10 @@ fn eval_struct(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
         ));
     }
 
+    // Take the attributes before evaluating the fields, whose own definitions
+    // must not consume them
+    let attributes = ctx.compiler.take_attributes();
+
     // First argument is the struct name (identifier)
     let struct_name = match &args[0] {
         Expr::Ident(i) => {
@@ -129,12 +135,33 @@ fn eval_struct(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
     // The constructor is a Value::StructConstructor that can be called to create instances
     let constructor = Value::StructConstructor {
         name: struct_name,
-        field_types,
+        field_types: field_types.clone(),
     };
 
     // Register the constructor in the environment with the struct's name
     ctx.env.define(struct_name, constructor);
 
+    let span = args[0].span();
+    for attr in attributes {
+        match &*attr.name {
+            "derive" => {
+                for arg in &attr.args {
+                    if let Value::Symbol(trait_name) = arg {
+                        derive::derive(*trait_name, struct_name, &field_types, span, ctx)?;
+                    }
+                }
+            }
+            other => {
+                let warning = Diagnostic::syntax(format!(
+                    "unknown attribute @{other} on struct {struct_name}"
+                ))
+                .with_span(span)
+                .set_level(DiagnosticLevel::Warning);
+                ctx.compiler.record_diagnostic(*warning);
+            }
+        }
+    }
+
     // Return the struct type as a Type value
     Ok(Value::Type(struct_type))
 }
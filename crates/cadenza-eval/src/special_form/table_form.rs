@@ -0,0 +1,272 @@
+//! The `__table__` special form for tables of delimited data.
+
+use crate::{
+    Eval,
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    eval::extract_identifier,
+    interner::InternedString,
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    special_form::{BuiltinSpecialForm, tuple_form::as_tuple_pattern},
+    unit::Unit,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::{Expr, LiteralValue};
+use std::sync::OnceLock;
+
+/// Returns the `__table__` special form for tables of delimited data.
+///
+/// Front-ends for data files, such as `cadenza-csv`, parse a file to a
+/// `__table__` application, so the file evaluates to a list of records that
+/// a pipeline can work on directly.
+///
+/// # Evaluation
+/// - Takes a header tuple followed by a tuple per row
+/// - The header names the fields, as identifiers or strings. An empty header
+///   names them `column1`, `column2`, ...
+/// - Each row becomes a record with a field per column, in the header's order
+/// - A number with a unit suffix, such as `12.5mm`, is a quantity. A unit
+///   that isn't defined is defined as a base unit
+/// - Each column has one type: integers in a column of floats are converted
+///   to floats, and any other mix is a type error. Nil, for a blank cell,
+///   fits every column
+/// - Returns the list of records
+///
+/// # IR Generation
+/// - Not yet implemented (returns error)
+///
+/// # Examples
+/// ```cadenza
+/// __table__ (name, height) ("ada", 1.62meter) ("grace", 1.55meter)
+/// // [{ name = "ada", height = 1.62meter }, { name = "grace", height = 1.55meter }]
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static TABLE_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    TABLE_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "__table__",
+        signature: Type::function(vec![], Type::list(Type::Record(vec![]))),
+        eval_fn: eval_table,
+        ir_fn: ir_table,
+    })
+}
+
+fn eval_table(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let Some((header, rows)) = args.split_first() else {
+        return Err(Diagnostic::syntax("__table__ requires a header"));
+    };
+    let mut names = header_names(header, ctx)?;
+
+    let mut columns: Vec<Option<Type>> = Vec::new();
+    let mut records = Vec::with_capacity(rows.len());
+    for row in rows {
+        let Some(cells) = as_tuple_pattern(row) else {
+            return Err(Diagnostic::syntax("table row must be a tuple").with_span(row.span()));
+        };
+        if names.is_empty() {
+            names = (1..=cells.len())
+                .map(|column| format!("column{column}").as_str().into())
+                .collect();
+        }
+        if cells.len() != names.len() {
+            return Err(Diagnostic::syntax(format!(
+                "table row has {} cells, but the table has {} columns",
+                cells.len(),
+                names.len()
+            ))
+            .with_span(row.span()));
+        }
+        columns.resize(names.len(), None);
+
+        let mut fields = Vec::with_capacity(cells.len());
+        for ((cell, name), column) in cells.iter().zip(&names).zip(&mut columns) {
+            define_unit_suffix(cell, ctx);
+            let value = cell.eval(ctx)?;
+            let ty = value.type_of();
+            match column {
+                _ if ty == Type::Nil => {}
+                None => *column = Some(ty),
+                Some(expected) if *expected == ty => {}
+                Some(Type::Float) if ty == Type::Integer => {}
+                Some(expected @ Type::Integer) if ty == Type::Float => *expected = Type::Float,
+                Some(expected) => {
+                    return Err(Diagnostic::type_error(expected.clone(), ty).with_span(cell.span()));
+                }
+            }
+            fields.push((*name, value));
+        }
+        records.push(fields);
+    }
+
+    // A column is only known to hold floats once every row is read
+    let records = records
+        .into_iter()
+        .map(|mut fields| {
+            for ((_, value), column) in fields.iter_mut().zip(&columns) {
+                if let (Value::Integer(n), Some(Type::Float)) = (&*value, column) {
+                    *value = Value::Float(*n as f64);
+                }
+            }
+            Value::Record {
+                type_name: None,
+                fields,
+            }
+        })
+        .collect();
+    Ok(Value::List(records))
+}
+
+/// Returns the field names the header tuple `header` gives.
+fn header_names(header: &Expr, ctx: &mut EvalContext<'_>) -> Result<Vec<InternedString>> {
+    let Some(columns) = as_tuple_pattern(header) else {
+        return Err(Diagnostic::syntax("table header must be a tuple").with_span(header.span()));
+    };
+
+    let mut names: Vec<InternedString> = Vec::with_capacity(columns.len());
+    for column in &columns {
+        let name = match column {
+            Expr::Ident(ident) => ident.syntax().text().interned(),
+            _ => match column.eval(ctx)? {
+                Value::String(name) => InternedString::new(&name),
+                value => {
+                    return Err(Diagnostic::type_error(Type::String, value.type_of())
+                        .with_span(column.span()));
+                }
+            },
+        };
+        if names.contains(&name) {
+            return Err(
+                Diagnostic::syntax(format!("column '{}' is given more than once", &*name))
+                    .with_span(column.span()),
+            );
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Defines the unit of a number with a unit suffix, such as the `mm` of
+/// `12.5mm`, as a base unit if nothing is bound to it, since a data file has
+/// no way to declare its units.
+fn define_unit_suffix(cell: &Expr, ctx: &mut EvalContext<'_>) {
+    let Expr::Apply(apply) = cell else {
+        return;
+    };
+    let Some(unit) = apply
+        .callee()
+        .and_then(|callee| extract_identifier(&callee))
+    else {
+        return;
+    };
+    let is_number = |arg: &Expr| {
+        let Expr::Literal(literal) = arg else {
+            return false;
+        };
+        matches!(
+            literal.value(),
+            Some(LiteralValue::Integer(_) | LiteralValue::Float(_))
+        )
+    };
+    if let [number] = &apply.all_arguments()[..]
+        && is_number(number)
+        && ctx.env.get(unit).is_none()
+        && ctx.compiler.units().get(unit).is_none()
+    {
+        ctx.compiler.register_unit(Unit::base(unit));
+    }
+}
+
+fn ir_table(
+    _args: &[Expr],
+    _block: &mut BlockBuilder,
+    _ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    Err(Diagnostic::syntax(
+        "__table__ special form IR generation not yet implemented",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Env, diagnostic::DiagnosticKind};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_last(input: &str) -> (Value, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let root = parse(input).ast();
+        let mut results = crate::eval(&root, &mut env, &mut compiler);
+        (results.pop().unwrap(), compiler)
+    }
+
+    fn record(fields: &[(&str, Value)]) -> Value {
+        Value::Record {
+            type_name: None,
+            fields: fields
+                .iter()
+                .map(|(name, value)| ((*name).into(), value.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_table_makes_records() {
+        let (value, compiler) =
+            eval_last(r#"__table__ (name, "mass") ("a", 2) ("b", 2.5) ("c", ())"#);
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(
+            value,
+            Value::List(vec![
+                record(&[
+                    ("name", Value::String("a".into())),
+                    ("mass", Value::Float(2.0))
+                ]),
+                record(&[
+                    ("name", Value::String("b".into())),
+                    ("mass", Value::Float(2.5))
+                ]),
+                record(&[("name", Value::String("c".into())), ("mass", Value::Nil)]),
+            ])
+        );
+
+        let (value, _) = eval_last("__table__ () (1, 2)");
+        assert_eq!(
+            value,
+            Value::List(vec![record(&[
+                ("column1", Value::Integer(1)),
+                ("column2", Value::Integer(2)),
+            ])])
+        );
+    }
+
+    #[test]
+    fn test_table_defines_unit_suffixes() {
+        let (value, compiler) = eval_last("__table__ (length,) (12.5mm,)");
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert!(compiler.units().get("mm".into()).is_some());
+        let Value::List(records) = value else {
+            panic!("expected a list, got {value:?}");
+        };
+        assert!(
+            matches!(&records[0], Value::Record { fields, .. } if matches!(fields[0].1, Value::Quantity { value: 12.5, .. }))
+        );
+    }
+
+    #[test]
+    fn test_table_columns_have_one_type() {
+        let (_, compiler) = eval_last(r#"__table__ (a,) (1,) ("x",)"#);
+        let kinds: Vec<_> = compiler.diagnostics().iter().map(|d| &d.kind).collect();
+        assert!(
+            matches!(
+                kinds[..],
+                [DiagnosticKind::TypeError {
+                    expected: Type::Integer,
+                    actual: Type::String
+                }]
+            ),
+            "{kinds:?}"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! The `?` and `|?` special forms for passing on failures.
+
+use crate::{
+    Eval,
+    context::EvalContext,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
+    env::Env,
+    eval::extract_identifier,
+    interner::InternedString,
+    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    result,
+    special_form::BuiltinSpecialForm,
+    value::{Type, Value},
+};
+use cadenza_syntax::ast::{Expr, LiteralValue};
+use std::sync::OnceLock;
+
+/// Returns the try special form, bound to both `?` and `|?`.
+///
+/// `x?` unwraps an `Ok` or a `Some`, and returns an `Err` or a `None` from
+/// the enclosing function. `|?` does the same with the lowest precedence, so
+/// it ends a pipeline.
+///
+/// # Evaluation
+/// - Takes 1 argument: a `Result` or an `Option`
+/// - Returns the field of an `Ok` or a `Some`
+/// - Otherwise, the enclosing function returns the `Err` or `None`; at the
+///   top level it is an error
+///
+/// # IR Generation
+/// - A `Result` or an `Option` is its success value in compiled code, and
+///   `Err` and `None` make the function fail (see
+///   [`IrFunction::fallible`](crate::ir::IrFunction::fallible)), so `x?`
+///   generates `x` and makes the enclosing function fallible. A call to a
+///   fallible function already passes its failure on.
+///
+/// # Examples
+/// ```cadenza
+/// fn half x = if x < 0 then Err "negative" else Ok (x / 2)
+///
+/// fn quarter x = half (half x)?
+///
+/// quarter 8        // Ok 2
+/// quarter (0 - 8) // Err "negative"
+/// 8 |> half |?     // 4
+/// ```
+pub fn get() -> &'static BuiltinSpecialForm {
+    static TRY_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
+    TRY_FORM.get_or_init(|| BuiltinSpecialForm {
+        name: "?",
+        signature: Type::function(vec![Type::Unknown], Type::Unknown),
+        eval_fn: eval_try,
+        ir_fn: ir_try,
+    })
+}
+
+fn eval_try(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    let [arg] = args else {
+        return Err(Diagnostic::arity(1, args.len()));
+    };
+    let value = arg.eval(ctx)?;
+    result::unwrap(value).map_err(|diagnostic| diagnostic.or_span(arg.span()))
+}
+
+fn ir_try(
+    args: &[Expr],
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    _source: SourceLocation,
+    gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+) -> Result<ValueId> {
+    let [arg] = args else {
+        return Err(Diagnostic::arity(1, args.len()));
+    };
+    let value = gen_expr(arg, block, ctx)?;
+    ctx.set_fallible();
+    Ok(value)
+}
+
+/// Returns the message compiled code fails with where `part` is written, if
+/// it is an `Err` or a `None`.
+///
+/// `part` is one expression, or an application of the first expression to
+/// the rest. Compiled code can only fail with `Err` of a string literal.
+pub(crate) fn failure(part: &[Expr], env: &Env) -> Option<Result<InternedString>> {
+    let (callee, args) = match part {
+        [Expr::Apply(apply)] => (apply.callee()?, apply.all_arguments()),
+        [callee, args @ ..] => (callee.clone(), args.to_vec()),
+        [] => return None,
+    };
+    let variant = match env.get(extract_identifier(&callee)?)? {
+        Value::VariantConstructor { ty, variant } | Value::Variant { ty, variant, .. }
+            if result::is_builtin(ty) =>
+        {
+            *variant
+        }
+        _ => return None,
+    };
+    let message = match (&*variant, &args[..]) {
+        ("None", []) => Some(variant),
+        ("Err", [Expr::Literal(literal)]) => match literal.value() {
+            Some(LiteralValue::String(s)) => Some(s.syntax().text().interned()),
+            _ => None,
+        },
+        ("Err", _) => None,
+        _ => return None,
+    };
+    Some(message.ok_or_else(|| {
+        Diagnostic::syntax("compiled code can only fail with `Err` of a string literal")
+            .with_span(callee.span())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, diagnostic::DiagnosticKind};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_all(input: &str) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    #[test]
+    fn test_try_returns_err_from_function() {
+        let (results, compiler) = eval_all(
+            "
+fn first x = (if x > 0 then Ok x else Err \"not positive\")? + 1
+first 1
+first 0
+",
+        );
+
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results[1], Value::Integer(2));
+        assert_eq!(
+            results[2],
+            result::err(Value::String("not positive".into()))
+        );
+    }
+
+    #[test]
+    fn test_try_at_top_level_is_an_error() {
+        let (_, compiler) = eval_all("None?");
+
+        assert!(matches!(
+            compiler.diagnostics()[0].kind,
+            DiagnosticKind::Propagated(_)
+        ));
+    }
+
+    #[test]
+    fn test_try_requires_result_or_option() {
+        let (_, compiler) = eval_all("1?");
+
+        assert!(matches!(
+            compiler.diagnostics()[0].kind,
+            DiagnosticKind::TypeError { .. }
+        ));
+    }
+}
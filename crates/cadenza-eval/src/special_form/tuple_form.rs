@@ -1,10 +1,12 @@
 //! The `__tuple__` special form for tuple literals.
 
 use crate::{
-    Eval,
+    Eval, InferType,
     context::EvalContext,
     diagnostic::{Diagnostic, Result},
-    ir::{BlockBuilder, IrGenContext, SourceLocation, ValueId},
+    eval::extract_identifier,
+    interner::InternedString,
+    ir::{BlockBuilder, IrConst, IrGenContext, SourceLocation, ValueId},
     special_form::BuiltinSpecialForm,
     value::{Type, Value},
 };
@@ -14,33 +16,44 @@ use std::sync::OnceLock;
 /// Returns the `__tuple__` special form for tuple literals.
 ///
 /// The `__tuple__` special form creates a tuple value from positional arguments.
+/// The empty tuple `()` is the unit value, nil, which is what expressions
+/// without a meaningful result return.
 ///
 /// # Evaluation
 /// - Takes variable number of arguments (tuple elements)
 /// - Each argument is evaluated and added to the tuple in order
-/// - Returns a Tuple value with evaluated elements
+/// - Returns a Tuple value with evaluated elements, or nil for `()`
 ///
 /// # IR Generation
-/// - Not yet implemented (returns error)
+/// - Generates IR for each element, then a tuple of their values, typed with
+///   their types
+/// - Generates a nil constant for `()`
+/// - A tuple is never boxed in WASM: it takes one local per element, and a
+///   function returning one returns each element. Nil takes none, so a
+///   function returning it has no results.
 ///
 /// # Examples
 /// ```cadenza
 /// (1, 2, 3)     // Tuple with three elements
 /// (x, y)        // Tuple with two elements
-/// ()            // Empty tuple
+/// ()            // Unit (nil)
 /// (42,)         // Single-element tuple
 /// ```
 pub fn get() -> &'static BuiltinSpecialForm {
     static TUPLE_FORM: OnceLock<BuiltinSpecialForm> = OnceLock::new();
     TUPLE_FORM.get_or_init(|| BuiltinSpecialForm {
         name: "__tuple__",
-        signature: Type::function(vec![], Type::Tuple(vec![])),
+        signature: Type::function(vec![], Type::Nil),
         eval_fn: eval_tuple,
         ir_fn: ir_tuple,
     })
 }
 
 fn eval_tuple(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
+    if args.is_empty() {
+        return Ok(Value::Nil);
+    }
+
     // Each argument is evaluated and added to the tuple
     let mut elements = Vec::with_capacity(args.len());
 
@@ -56,15 +69,77 @@ fn eval_tuple(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
 }
 
 fn ir_tuple(
-    _args: &[Expr],
-    _block: &mut BlockBuilder,
-    _ctx: &mut IrGenContext,
-    _source: SourceLocation,
-    _gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
+    args: &[Expr],
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+    gen_expr: &mut dyn FnMut(&Expr, &mut BlockBuilder, &mut IrGenContext) -> Result<ValueId>,
 ) -> Result<ValueId> {
-    Err(Diagnostic::syntax(
-        "tuple special form IR generation not yet implemented",
-    ))
+    if args.is_empty() {
+        let result = block.const_val(IrConst::Nil, Type::Nil, source);
+        ctx.set_value_type(result, Type::Nil);
+        return Ok(result);
+    }
+
+    let mut elements = Vec::with_capacity(args.len());
+    let mut types = Vec::with_capacity(args.len());
+    for arg in args {
+        let value = gen_expr(arg, block, ctx)?;
+        // Putting a variable's linear value in a tuple moves it
+        ctx.consume(arg);
+        elements.push(value);
+        types.push(ctx.get_value_type(value).cloned().unwrap_or(Type::Unknown));
+    }
+
+    let ty = Type::Tuple(types);
+    let result = block.tuple(elements, ty.clone(), source);
+    ctx.set_value_type(result, ty);
+    Ok(result)
+}
+
+/// Returns the elements of `pattern` if it is a tuple pattern (`(a, b)`).
+pub(crate) fn as_tuple_pattern(pattern: &Expr) -> Option<Vec<Expr>> {
+    let Expr::Apply(apply) = pattern else {
+        return None;
+    };
+    let callee = extract_identifier(&apply.callee()?)?;
+    (&*callee == "__tuple__").then(|| apply.all_arguments())
+}
+
+/// Generates IR binding the variables of the tuple pattern with elements
+/// `pattern` to the elements of the tuple `value`, read as its fields `0`,
+/// `1`, ...
+pub(crate) fn ir_bind_tuple_pattern(
+    pattern: &[Expr],
+    value: ValueId,
+    block: &mut BlockBuilder,
+    ctx: &mut IrGenContext,
+    source: SourceLocation,
+) -> Result<()> {
+    let types = match ctx.get_value_type(value) {
+        Some(Type::Tuple(types)) if types.len() == pattern.len() => types.clone(),
+        Some(Type::Tuple(types)) => return Err(Diagnostic::arity(pattern.len(), types.len())),
+        Some(Type::Unknown) | None => vec![Type::Unknown; pattern.len()],
+        Some(ty) => return Err(Diagnostic::type_error(Type::Tuple(vec![]), ty.clone())),
+    };
+    for (index, (element, ty)) in pattern.iter().zip(types).enumerate() {
+        let field = InternedString::new(&index.to_string());
+        let element_value = block.field(value, field, ty.clone(), source);
+        ctx.set_value_type(element_value, ty.clone());
+        match element {
+            Expr::Ident(ident) => {
+                let name = ident.syntax().text().interned();
+                ctx.bind_var(name, element_value, &InferType::Concrete(ty));
+            }
+            nested => {
+                let nested = as_tuple_pattern(nested).ok_or_else(|| {
+                    Diagnostic::syntax("tuple pattern elements must be identifiers or tuples")
+                })?;
+                ir_bind_tuple_pattern(&nested, element_value, block, ctx, source)?;
+            }
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -84,17 +159,8 @@ mod tests {
 
         let results = crate::eval(&root, &mut env, &mut compiler);
 
-        assert_eq!(results.len(), 1);
-        match &results[0] {
-            Value::Tuple {
-                type_name,
-                elements,
-            } => {
-                assert!(type_name.is_none());
-                assert_eq!(elements.len(), 0);
-            }
-            _ => panic!("Expected tuple, got {:?}", results[0]),
-        }
+        // The empty tuple is the unit value
+        assert_eq!(results, vec![Value::Nil]);
     }
 
     #[test]
@@ -201,4 +267,27 @@ mod tests {
             _ => panic!("Expected tuple, got {:?}", results[0]),
         }
     }
+
+    #[test]
+    fn test_destructure_tuple() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = "let ((a, b), c) = ((1, 2), 3)\na + b + c";
+        let results = crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+
+        assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+        assert_eq!(results.last(), Some(&Value::Integer(6)));
+    }
+
+    #[test]
+    fn test_destructure_arity_mismatch() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+
+        let input = "let (a, b) = (1, 2, 3)";
+        crate::eval(&parse(input).ast(), &mut env, &mut compiler);
+
+        assert!(compiler.has_errors());
+    }
 }
@@ -17,7 +17,8 @@ use std::sync::OnceLock;
 ///
 /// # Evaluation
 /// - Takes 1 argument: an expression
-/// - Infers the type of the expression using the type inferencer
+/// - Infers the type of the expression without evaluating it
+/// - Reports an error if a name it uses isn't defined yet
 /// - Returns the type as a Value::Type
 ///
 /// # IR Generation
@@ -44,24 +45,9 @@ fn eval_typeof(args: &[Expr], ctx: &mut EvalContext<'_>) -> Result<Value> {
         return Err(Diagnostic::syntax("typeof expects 1 argument: expression"));
     }
 
-    let expr = &args[0];
-
-    // Build type environment from current runtime environment and compiler
-    let type_env = crate::typeinfer::TypeEnv::from_context(ctx.env, ctx.compiler);
-
-    // Infer the type of the expression
-    let inferred_type = ctx
-        .compiler
-        .type_inferencer_mut()
-        .infer_expr(expr, &type_env)
-        .map_err(|e| {
-            Diagnostic::syntax(format!("Type inference failed for expression: {}", e))
-                .with_span(expr.span())
-        })?;
-
-    // Convert to concrete type, or use Unknown if it has type variables
-    let concrete_type = inferred_type.to_concrete().unwrap_or(Type::Unknown);
-    Ok(Value::Type(concrete_type))
+    // Reflection checks the expression's names are defined and never
+    // evaluates it (see `crate::reflect`)
+    ctx.reflect_type(&args[0]).map(Value::Type)
 }
 
 fn ir_typeof(
@@ -0,0 +1,523 @@
+//! The stable embedding API.
+//!
+//! Enabled by the `stable` feature, this module is the part of the crate
+//! that follows semantic versioning: its items only change incompatibly in a
+//! major release. Every other module is unstable and may change in any
+//! release, even when it's public.
+//!
+//! An [`Engine`] evaluates source text in a persistent environment, with
+//! the [`CompileOptions`] and host [`Capability`]s the embedder chooses:
+//!
+//! ```
+//! use cadenza_eval::stable::{Engine, Value};
+//!
+//! let mut engine = Engine::new();
+//! engine.eval("fn double x = x * 2");
+//! let outcome = engine.eval("double 21");
+//! assert!(!outcome.has_errors());
+//! assert_eq!(outcome.value(), Some(&Value::Integer(42)));
+//! ```
+//!
+//! A host that uses Cadenza files for configuration or scripting can load
+//! them with [`Engine::reload`], and call it again when a file changes: only
+//! the definitions that changed are evaluated again, so the rest of the state
+//! is kept, and [`Engine::on_reload`] reports the [`Changes`].
+//!
+//! A host with its own notation can extend the syntax with
+//! [`Engine::add_operator`], which binds an operator's symbol to the
+//! function that handles it:
+//!
+//! ```
+//! use cadenza_eval::stable::{Associativity, Engine, Operator, Precedence, Value};
+//!
+//! let mut engine = Engine::new();
+//! engine.eval("fn tolerance x dx = [x - dx, x + dx]");
+//! let handler = engine.get("tolerance").cloned().unwrap();
+//! let additive = Precedence::of("+").unwrap();
+//! engine
+//!     .add_operator(Operator::infix("±", additive, Associativity::Left), handler)
+//!     .unwrap();
+//! let outcome = engine.eval("10 ± 2");
+//! assert_eq!(
+//!     outcome.value(),
+//!     Some(&Value::List(vec![Value::Integer(8), Value::Integer(12)]))
+//! );
+//! ```
+//!
+//! [`Value`], [`Type`], [`DiagnosticLevel`] and [`Capability`] are
+//! non-exhaustive, so new variants are not breaking changes; match them with
+//! a wildcard arm.
+//!
+//! The items of this module are listed in `public-api.txt` at the root of
+//! the crate, which `cargo xtask public-api` regenerates and
+//! `cargo xtask public-api --check` compares in CI, so any change to the
+//! stable API shows up in review.
+
+use crate::{Compiler, Env, InternedString, eval, map::Map, symbol_index};
+pub use crate::{
+    diagnostic::{Diagnostic, DiagnosticLevel},
+    options::CompileOptions,
+    system::{Capabilities, Capability},
+    value::{Type, Value},
+};
+pub use cadenza_syntax::operators::{Associativity, Fixity, Operator, OperatorError, Precedence};
+use cadenza_syntax::{ast::Expr, operators::Operators, parse::parse_with};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Evaluates Cadenza source text for an embedder.
+///
+/// Each call to [`Engine::eval`] sees the definitions of the calls before
+/// it, like inputs to a REPL, and gets the full fuel budget of the options.
+/// A new engine grants no capabilities, so scripts can't reach the host
+/// until [`Engine::grant`] allows it.
+pub struct Engine {
+    env: Env,
+    compiler: Compiler,
+    /// The operators source is parsed with.
+    operators: Operators,
+    /// The source of each definition of each file loaded by
+    /// [`Engine::reload`], with the attributes before it.
+    files: HashMap<PathBuf, Map<String>>,
+    /// Called after each reload that changed a definition.
+    on_reload: Option<Box<OnReload>>,
+}
+
+/// A callback for [`Engine::on_reload`].
+type OnReload = dyn FnMut(&Path, &Changes);
+
+impl Engine {
+    /// Creates an engine with the default options.
+    pub fn new() -> Self {
+        Self::with_options(CompileOptions::default())
+    }
+
+    /// Creates an engine with `options`.
+    pub fn with_options(options: CompileOptions) -> Self {
+        Self {
+            env: Env::with_options(&options),
+            compiler: Compiler::with_options(options),
+            operators: Operators::new(),
+            files: HashMap::new(),
+            on_reload: None,
+        }
+    }
+
+    /// Returns the options the engine evaluates with.
+    pub fn options(&self) -> &CompileOptions {
+        self.compiler.options()
+    }
+
+    /// Grants scripts `capability`.
+    pub fn grant(&mut self, capability: Capability) {
+        let mut capabilities = self.compiler.capabilities();
+        capabilities.allow(capability);
+        self.compiler.set_capabilities(capabilities);
+    }
+
+    /// Returns the capabilities granted to scripts.
+    pub fn capabilities(&self) -> Capabilities {
+        self.compiler.capabilities()
+    }
+
+    /// Binds `name` to `value` for the code evaluated after it.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.env.define(InternedString::new(name), value);
+    }
+
+    /// Adds `operator` to the syntax of the code evaluated after it, and
+    /// binds its symbol to `handler`, the function an operator expression
+    /// applies: with an infix `±`, `a ± b` is `handler a b`.
+    ///
+    /// The symbol must be an identifier, such as `±` or `mod`; symbols that
+    /// aren't ASCII must be separated from their operands by whitespace.
+    pub fn add_operator(
+        &mut self,
+        operator: Operator,
+        handler: Value,
+    ) -> Result<(), OperatorError> {
+        let symbol = operator.symbol.clone();
+        self.operators.add(operator)?;
+        self.define(&symbol, handler);
+        Ok(())
+    }
+
+    /// Removes the operator `symbol` from the syntax of the code evaluated
+    /// after it, and returns whether there was one. A removed built-in
+    /// operator, such as `**`, is a parse error where it's used; an added
+    /// operator's symbol stays bound to its handler.
+    pub fn remove_operator(&mut self, symbol: &str) -> bool {
+        self.operators.remove(symbol)
+    }
+
+    /// Returns the value of `name`, as defined by evaluated code or
+    /// [`Engine::define`].
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let name = InternedString::new(name);
+        self.compiler.get_var(name).or_else(|| self.env.get(name))
+    }
+
+    /// Evaluates `source` and returns the value of each top-level
+    /// expression with the diagnostics it reported.
+    pub fn eval(&mut self, source: &str) -> Outcome {
+        let parsed = parse_with(source, &self.operators);
+        if !parsed.errors.is_empty() {
+            return Outcome::parse_errors(parsed.errors);
+        }
+        self.compiler.refuel();
+        let values = crate::eval(&parsed.ast(), &mut self.env, &mut self.compiler);
+        Outcome {
+            values,
+            diagnostics: self.compiler.take_diagnostics(),
+            exit_status: self.compiler.exit_status(),
+            changes: Changes::default(),
+        }
+    }
+
+    /// Loads the file at `path`, or reloads it after it changed.
+    ///
+    /// The first load evaluates the whole file, like [`Engine::eval`]. A
+    /// reload compares each top-level definition (a `fn`, `let`, `struct`,
+    /// `enum` or `measure`, with the attributes before it) to the one last
+    /// loaded, and only evaluates the definitions that were added or
+    /// changed; definitions that were removed are undefined, and the other
+    /// top-level expressions aren't evaluated again. Variables whose
+    /// definitions didn't change keep their values.
+    ///
+    /// The reload is atomic: if the file doesn't parse, or evaluating it
+    /// reports an error, no function or variable changes and the outcome
+    /// lists no changes. Otherwise the callback set with
+    /// [`Engine::on_reload`] is told what changed.
+    pub fn reload(&mut self, path: impl AsRef<Path>) -> io::Result<Outcome> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        let parsed = parse_with(&source, &self.operators);
+        if !parsed.errors.is_empty() {
+            return Ok(Outcome::parse_errors(parsed.errors));
+        }
+        let root = parsed.ast();
+        let items: Vec<_> = root.items().collect();
+        let definitions = definitions(&items, &source);
+
+        // The items to evaluate, or none for the whole file, and the names
+        // they define or undefine
+        let mut changes = Changes::default();
+        let mut touched = Vec::new();
+        let selected = match self.files.get(path) {
+            None => {
+                touched.extend(definitions.keys().copied());
+                changes.added = touched.iter().map(|name| name.to_string()).collect();
+                None
+            }
+            Some(previous) => {
+                for (name, text) in &definitions {
+                    let list = match previous.get(name) {
+                        None => &mut changes.added,
+                        Some(old) if old != text => &mut changes.changed,
+                        Some(_) => continue,
+                    };
+                    list.push(name.to_string());
+                    touched.push(*name);
+                }
+                for name in previous.keys() {
+                    if !definitions.contains_key(name) {
+                        changes.removed.push(name.to_string());
+                        touched.push(*name);
+                    }
+                }
+                let selected = (0..items.len())
+                    .filter(|index| {
+                        definition_of(&items, *index).is_some_and(|name| touched.contains(&name))
+                    })
+                    .map(|index| items[index].clone());
+                Some(selected.collect::<Vec<_>>())
+            }
+        };
+
+        // Keep what the reload replaces, to put it back if the reload fails
+        let env = self.env.clone();
+        let saved: Vec<_> = touched
+            .iter()
+            .map(|name| (*name, self.compiler.get_var(*name).cloned()))
+            .collect();
+        for name in &changes.removed {
+            let name = InternedString::new(name);
+            self.compiler.remove_var(name);
+            self.env.remove(name);
+        }
+
+        self.compiler.refuel();
+        let values = match selected {
+            Some(items) => eval::eval_items(items, &mut self.env, &mut self.compiler),
+            None => crate::eval(&root, &mut self.env, &mut self.compiler),
+        };
+        let mut outcome = Outcome {
+            values,
+            diagnostics: self.compiler.take_diagnostics(),
+            exit_status: self.compiler.exit_status(),
+            changes: Changes::default(),
+        };
+        if outcome.has_errors() {
+            self.env = env;
+            for (name, value) in saved {
+                match value {
+                    Some(value) => self.compiler.define_var(name, value),
+                    None => {
+                        self.compiler.remove_var(name);
+                    }
+                }
+            }
+            return Ok(outcome);
+        }
+
+        self.files.insert(path.to_path_buf(), definitions);
+        if !changes.is_empty()
+            && let Some(on_reload) = &mut self.on_reload
+        {
+            on_reload(path, &changes);
+        }
+        outcome.changes = changes;
+        Ok(outcome)
+    }
+
+    /// Calls `callback` with the path and the [`Changes`] after each
+    /// [`Engine::reload`] that changed a definition.
+    pub fn on_reload(&mut self, callback: impl FnMut(&Path, &Changes) + 'static) {
+        self.on_reload = Some(Box::new(callback));
+    }
+}
+
+/// Returns the source of each top-level definition among `items`, with the
+/// attributes before it, by name.
+fn definitions(items: &[Expr], source: &str) -> Map<String> {
+    let mut definitions = Map::default();
+    let mut attributes = String::new();
+    for item in items {
+        let text = &source[item.span().start..item.span().end];
+        if eval::is_attribute(item) {
+            attributes.push_str(text);
+            attributes.push('\n');
+            continue;
+        }
+        if let Some(symbol) = symbol_index::definition(item) {
+            definitions.insert(
+                InternedString::new(&symbol.name),
+                format!("{attributes}{text}"),
+            );
+        }
+        attributes.clear();
+    }
+    definitions
+}
+
+/// Returns the name of the definition `items[index]` is, or is an attribute
+/// of.
+fn definition_of(items: &[Expr], index: usize) -> Option<InternedString> {
+    items[index..]
+        .iter()
+        .find(|item| !eval::is_attribute(item))
+        .and_then(symbol_index::definition)
+        .map(|symbol| InternedString::new(&symbol.name))
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The result of [`Engine::eval`] or [`Engine::reload`].
+#[derive(Debug)]
+pub struct Outcome {
+    values: Vec<Value>,
+    diagnostics: Vec<Diagnostic>,
+    exit_status: Option<i32>,
+    changes: Changes,
+}
+
+impl Outcome {
+    /// Returns the outcome of source that doesn't parse.
+    fn parse_errors(errors: Vec<cadenza_syntax::parse::ParseError>) -> Self {
+        Self {
+            values: Vec::new(),
+            diagnostics: errors
+                .into_iter()
+                .map(|error| *Box::<Diagnostic>::from(error))
+                .collect(),
+            exit_status: None,
+            changes: Changes::default(),
+        }
+    }
+
+    /// The value of each top-level expression, in order. An expression that
+    /// failed has [`Value::Error`].
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// The value of the last top-level expression, if there is one.
+    pub fn value(&self) -> Option<&Value> {
+        self.values.last()
+    }
+
+    /// The errors, warnings and hints reported while evaluating.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns true if any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    /// The status the script asked to exit with through `exit` or `abort`,
+    /// which stops evaluation (and needs [`Capability::Process`]).
+    pub fn exit_status(&self) -> Option<i32> {
+        self.exit_status
+    }
+
+    /// The definitions [`Engine::reload`] changed, which are none for
+    /// [`Engine::eval`] or a reload that failed.
+    pub fn changes(&self) -> &Changes {
+        &self.changes
+    }
+}
+
+/// The definitions an [`Engine::reload`] added, changed and removed, by
+/// name, in source order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    added: Vec<String>,
+    changed: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl Changes {
+    /// The definitions that weren't in the file before.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// The definitions whose source changed.
+    pub fn changed(&self) -> &[String] {
+        &self.changed
+    }
+
+    /// The definitions that are no longer in the file.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Returns true if no definition changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_definitions_between_calls() {
+        let mut engine = Engine::new();
+        engine.define("offset", Value::Integer(1));
+        assert!(!engine.eval("let base = 40 + offset").has_errors());
+        let outcome = engine.eval("base + offset");
+        assert_eq!(outcome.value(), Some(&Value::Integer(42)));
+        assert!(engine.get("base").is_some());
+    }
+
+    #[test]
+    fn reports_parse_and_evaluation_errors() {
+        let mut engine = Engine::new();
+        assert!(engine.eval("(1 +").has_errors());
+        let outcome = engine.eval("missing\n1");
+        assert!(outcome.has_errors());
+        assert_eq!(outcome.values(), &[Value::Error, Value::Integer(1)]);
+    }
+
+    #[test]
+    fn grants_no_capabilities_by_default() {
+        let mut engine = Engine::new();
+        assert!(!engine.capabilities().allows(Capability::Filesystem));
+        engine.grant(Capability::Filesystem);
+        assert!(engine.capabilities().allows(Capability::Filesystem));
+        assert!(!engine.capabilities().allows(Capability::Network));
+    }
+
+    #[test]
+    fn adds_and_removes_operators() {
+        let mut engine = Engine::new();
+        engine.eval("fn angle degrees = degrees * 2");
+        let handler = engine.get("angle").cloned().unwrap();
+        let multiplicative = Precedence::of("*").unwrap();
+        engine
+            .add_operator(Operator::prefix("∠", multiplicative), handler.clone())
+            .unwrap();
+        assert_eq!(engine.eval("∠ 45 + 1").value(), Some(&Value::Integer(91)));
+        assert!(
+            engine
+                .add_operator(Operator::prefix("+", multiplicative), handler)
+                .is_err()
+        );
+
+        assert!(engine.remove_operator("**"));
+        assert!(engine.eval("2 ** 3").has_errors());
+        assert!(engine.remove_operator("∠"));
+        assert_eq!(engine.eval("∠ 1").value(), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn reloads_changed_definitions() {
+        let path = std::env::temp_dir().join(format!("cadenza-reload-{}.cdz", std::process::id()));
+        let reloaded = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut engine = Engine::new();
+        let log = reloaded.clone();
+        engine.on_reload(move |_, changes| log.borrow_mut().push(changes.clone()));
+
+        std::fs::write(&path, "let calls = 0\nfn scale x = x * 2\nfn old = 1\n").unwrap();
+        let outcome = engine.reload(&path).unwrap();
+        assert_eq!(outcome.changes().added(), ["calls", "scale", "old"]);
+        engine.eval("calls = 5");
+
+        // Only the changed function is evaluated again, so `calls` keeps
+        // its value
+        std::fs::write(&path, "let calls = 0\nfn scale x = x * 3\n").unwrap();
+        let outcome = engine.reload(&path).unwrap();
+        assert!(!outcome.has_errors(), "{:?}", outcome.diagnostics());
+        assert_eq!(outcome.changes().changed(), ["scale"]);
+        assert_eq!(outcome.changes().removed(), ["old"]);
+        assert_eq!(
+            engine.eval("scale calls").value(),
+            Some(&Value::Integer(15))
+        );
+        assert!(engine.eval("old").has_errors());
+
+        // A reload that fails changes nothing
+        std::fs::write(
+            &path,
+            "let calls = 0\nfn scale x = x * 4\nlet broken = missing\n",
+        )
+        .unwrap();
+        let outcome = engine.reload(&path).unwrap();
+        assert!(outcome.has_errors());
+        assert!(outcome.changes().is_empty());
+        assert_eq!(engine.eval("scale 1").value(), Some(&Value::Integer(3)));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(reloaded.borrow().len(), 2);
+    }
+
+    #[test]
+    fn reports_the_exit_status() {
+        let mut engine = Engine::new();
+        engine.grant(Capability::Process);
+        let outcome = engine.eval("exit 3\n1");
+        assert_eq!(outcome.exit_status(), Some(3));
+        assert_eq!(outcome.values().len(), 1);
+    }
+}
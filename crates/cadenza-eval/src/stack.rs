@@ -0,0 +1,148 @@
+//! Stack segments for deeply nested calls.
+//!
+//! The tree-walk evaluator recurses on the Rust stack, and each nested
+//! Cadenza call takes several kilobytes of it, so a thread's stack only fits
+//! a few hundred calls. Calls in tail position don't nest (see
+//! [`apply_value`](crate::apply_value)), but other recursion does, so before
+//! each call [`grow`] checks how much of the current segment is left. When it
+//! runs low, evaluation continues on a fresh segment allocated on the heap,
+//! on the same thread, and switches back when the call returns. Recursion
+//! depth is then bounded by memory and the
+//! [`recursion_limit`](crate::options::CompileOptions::recursion_limit)
+//! rather than by the stack of the thread that started evaluating.
+//!
+//! Segments come from [`corosensei`], which maps each with a guard page below
+//! it and switches to it with [`corosensei::on_stack`]. Its limits apply:
+//!
+//! - Only the targets `corosensei` supports natively have segments: x86,
+//!   x86-64, AArch64, ARM, RISC-V, LoongArch and 64-bit PowerPC. WebAssembly
+//!   can't switch stacks, so there the depth is bounded by the stack the host
+//!   gives the module.
+//! - Memory is reserved a segment at a time and committed as it is touched.
+//!   A thread keeps a few segments it has finished with, so recursion that
+//!   keeps crossing a segment boundary doesn't map one per call.
+//! - A frame bigger than the red zone left in each segment would run into
+//!   the guard page and abort the process; evaluator frames are far smaller.
+//!
+//! Stacks are assumed to grow downwards, as they do on every platform the
+//! evaluator runs on natively.
+
+use crate::{context::EvalContext, diagnostic::Result, value::Value};
+
+/// How much stack the evaluator may use on a thread it didn't start, before
+/// moving to a segment of its own.
+///
+/// The thread's real size is unknown, but even test threads have 2 MiB.
+#[cfg(not(target_family = "wasm"))]
+const INITIAL_BUDGET: usize = 256 * 1024;
+
+/// The size of each new segment.
+#[cfg(not(target_family = "wasm"))]
+const SEGMENT_SIZE: usize = 16 * 1024 * 1024;
+
+/// How much of a segment is left unused, for the work done between checks.
+#[cfg(not(target_family = "wasm"))]
+const RED_ZONE: usize = 1024 * 1024;
+
+/// How many finished segments a thread keeps for reuse.
+#[cfg(not(target_family = "wasm"))]
+const SPARE_SEGMENTS: usize = 4;
+
+#[cfg(not(target_family = "wasm"))]
+thread_local! {
+    /// The address below which the current thread moves to a new segment,
+    /// set the first time it evaluates a call.
+    static LIMIT: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+
+    /// Segments the thread has finished with.
+    static SPARE: std::cell::RefCell<Vec<corosensei::stack::DefaultStack>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Runs `f`, on a new stack segment if the current one is running low.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn grow(
+    ctx: &mut EvalContext<'_>,
+    f: impl FnOnce(&mut EvalContext<'_>) -> Result<Value>,
+) -> Result<Value> {
+    let here = stack_pointer();
+    let limit = LIMIT.get().unwrap_or_else(|| {
+        let limit = here.saturating_sub(INITIAL_BUDGET);
+        LIMIT.set(Some(limit));
+        limit
+    });
+    if here > limit {
+        return f(ctx);
+    }
+
+    let mut segment = take_segment()?;
+    let result = corosensei::on_stack(&mut segment, || {
+        let outer = enter_segment();
+        let result = f(ctx);
+        LIMIT.set(outer);
+        result
+    });
+    reuse_segment(segment);
+    result
+}
+
+/// Returns a segment to run on, reusing one the thread has finished with if
+/// it can.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn take_segment() -> Result<corosensei::stack::DefaultStack> {
+    match SPARE.with_borrow_mut(Vec::pop) {
+        Some(segment) => Ok(segment),
+        None => corosensei::stack::DefaultStack::new(SEGMENT_SIZE).map_err(|err| {
+            crate::diagnostic::Diagnostic::internal(format!(
+                "failed to allocate a stack segment: {err}"
+            ))
+        }),
+    }
+}
+
+/// Keeps a segment nothing runs on anymore for reuse, if the thread doesn't
+/// have enough already.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn reuse_segment(segment: corosensei::stack::DefaultStack) {
+    SPARE.with_borrow_mut(|spare| {
+        if spare.len() < SPARE_SEGMENTS {
+            spare.push(segment);
+        }
+    });
+}
+
+/// Marks the current stack as a fresh segment of [`SEGMENT_SIZE`], returning
+/// the limit of the one it was entered from.
+///
+/// Called first thing on every segment, including the ones
+/// [tasks](crate::task) run on.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn enter_segment() -> Option<usize> {
+    LIMIT.replace(Some(stack_pointer() - (SEGMENT_SIZE - RED_ZONE)))
+}
+
+/// Replaces the current stack's limit, returning the previous one.
+///
+/// Used when switching between the stacks of tasks, which doesn't go through
+/// [`grow`].
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn swap_limit(limit: Option<usize>) -> Option<usize> {
+    LIMIT.replace(limit)
+}
+
+/// Runs `f` on the current stack, the only one there is.
+#[cfg(target_family = "wasm")]
+pub(crate) fn grow(
+    ctx: &mut EvalContext<'_>,
+    f: impl FnOnce(&mut EvalContext<'_>) -> Result<Value>,
+) -> Result<Value> {
+    f(ctx)
+}
+
+/// Returns an address near the top of the current stack.
+#[cfg(not(target_family = "wasm"))]
+#[inline(never)]
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
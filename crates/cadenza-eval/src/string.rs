@@ -0,0 +1,215 @@
+//! String primitives.
+//!
+//! Like the [math functions](crate::math), `concat`, `to_string`,
+//! `contains` and `to_upper` match functions of the host interface (see
+//! [`ir::host`](crate::ir::host)), so compiled modules call the host for
+//! them. `split` and `parse_int` are only available to the evaluator, since
+//! the host has no lists and its functions can't fail. The prelude's
+//! `length` counts the characters of a string.
+//!
+//! ```cadenza
+//! concat "total: " (to_string 42)
+//! split "a,b,c" ","                 # ["a", "b", "c"]
+//! contains "cadenza" "den"          # true
+//! to_upper "loud"                   # "LOUD"
+//! parse_int "-17"                   # -17
+//! ```
+
+use crate::{
+    diagnostic::{Diagnostic, Result},
+    value::{BuiltinFn, Type, Value},
+};
+
+/// Creates the `concat` builtin: `concat a b` joins two strings.
+pub fn builtin_concat() -> BuiltinFn {
+    BuiltinFn {
+        name: "concat",
+        signature: Type::function(vec![Type::String, Type::String], Type::String),
+        func: |args, _ctx| {
+            if args.len() != 2 {
+                return Err(Diagnostic::arity(2, args.len()));
+            }
+            match (&args[0], &args[1]) {
+                (Value::String(a), Value::String(b)) => Ok(Value::String(format!("{a}{b}").into())),
+                (Value::String(_), other) | (other, _) => {
+                    Err(Diagnostic::type_error(Type::String, other.type_of()))
+                }
+            }
+        },
+    }
+}
+
+/// Creates the `to_string` builtin: formats an integer or a float.
+pub fn builtin_to_string() -> BuiltinFn {
+    BuiltinFn {
+        name: "to_string",
+        signature: Type::function(vec![Type::Unknown], Type::String),
+        func: |args, _ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            match &args[0] {
+                Value::Integer(n) => Ok(Value::String(n.to_string().into())),
+                Value::BigInt(n) => Ok(Value::String(n.to_string().into())),
+                Value::Float(x) => Ok(Value::String(x.to_string().into())),
+                Value::Decimal(d) => Ok(Value::String(d.to_string().into())),
+                other => Err(Diagnostic::type_error(Type::Float, other.type_of())),
+            }
+        },
+    }
+}
+
+/// Creates the `split` builtin: `split s sep` returns the parts of `s`
+/// between occurrences of `sep`, or its characters if `sep` is empty.
+pub fn builtin_split() -> BuiltinFn {
+    BuiltinFn {
+        name: "split",
+        signature: Type::function(vec![Type::String, Type::String], Type::list(Type::String)),
+        func: |args, _ctx| {
+            let [s, sep] = args else {
+                return Err(Diagnostic::arity(2, args.len()));
+            };
+            let (s, sep) = (text(s)?, text(sep)?);
+            let parts = if sep.is_empty() {
+                s.chars()
+                    .map(|c| Value::String(c.to_string().into()))
+                    .collect()
+            } else {
+                s.split(sep)
+                    .map(|part| Value::String(part.to_string().into()))
+                    .collect()
+            };
+            Ok(Value::List(parts))
+        },
+    }
+}
+
+/// Creates the `contains` builtin: `contains s needle` is true if `needle`
+/// occurs in `s`.
+pub fn builtin_contains() -> BuiltinFn {
+    BuiltinFn {
+        name: "contains",
+        signature: Type::function(vec![Type::String, Type::String], Type::Bool),
+        func: |args, _ctx| {
+            let [s, needle] = args else {
+                return Err(Diagnostic::arity(2, args.len()));
+            };
+            Ok(Value::Bool(text(s)?.contains(text(needle)?)))
+        },
+    }
+}
+
+/// Creates the `to_upper` builtin: converts a string to uppercase.
+pub fn builtin_to_upper() -> BuiltinFn {
+    BuiltinFn {
+        name: "to_upper",
+        signature: Type::function(vec![Type::String], Type::String),
+        func: |args, _ctx| {
+            let [s] = args else {
+                return Err(Diagnostic::arity(1, args.len()));
+            };
+            Ok(Value::String(text(s)?.to_uppercase().into()))
+        },
+    }
+}
+
+/// Creates the `parse_int` builtin: parses a decimal integer, with an
+/// optional sign and surrounding whitespace.
+pub fn builtin_parse_int() -> BuiltinFn {
+    BuiltinFn {
+        name: "parse_int",
+        signature: Type::function(vec![Type::String], Type::Integer),
+        func: |args, _ctx| {
+            let [s] = args else {
+                return Err(Diagnostic::arity(1, args.len()));
+            };
+            let s = text(s)?;
+            s.trim()
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| Diagnostic::syntax(format!("cannot parse {s:?} as an integer")))
+        },
+    }
+}
+
+/// Creates the `__escape__` builtin, which front ends for other syntaxes
+/// emit for the escapes their strings spell differently from Cadenza's:
+/// `__escape__ "\\u00e9"` is `"é"`, as `"\u{e9}"` would be.
+///
+/// It decodes `\b`, `\f`, `\uXXXX` (a UTF-16 surrogate pair as two of them)
+/// and `\UXXXXXXXX`.
+pub fn builtin_escape() -> BuiltinFn {
+    BuiltinFn {
+        name: "__escape__",
+        signature: Type::function(vec![Type::String], Type::String),
+        func: |args, _ctx| {
+            let [s] = args else {
+                return Err(Diagnostic::arity(1, args.len()));
+            };
+            let s = text(s)?;
+            decode_escapes(s)
+                .map(|s| Value::String(s.into()))
+                .ok_or_else(|| Diagnostic::syntax(format!("invalid escape sequence {s:?}")))
+        },
+    }
+}
+
+/// Decodes `text`, which must be nothing but the escapes
+/// [`__escape__`](builtin_escape) takes.
+fn decode_escapes(mut text: &str) -> Option<String> {
+    let mut decoded = String::new();
+    while !text.is_empty() {
+        let (c, rest) = decode_escape(text)?;
+        decoded.push(c);
+        text = rest;
+    }
+    Some(decoded)
+}
+
+/// Decodes the escape `text` starts with, and returns its character and the
+/// text after it.
+fn decode_escape(text: &str) -> Option<(char, &str)> {
+    let mut chars = text.strip_prefix('\\')?.chars();
+    let escape = chars.next()?;
+    let rest = chars.as_str();
+    match escape {
+        'b' => Some(('\u{8}', rest)),
+        'f' => Some(('\u{c}', rest)),
+        'u' => {
+            let (code, rest) = hex(rest, 4)?;
+            if !(0xD800..0xDC00).contains(&code) {
+                return Some((char::from_u32(code)?, rest));
+            }
+            // A high surrogate, which the low one must follow
+            let (low, rest) = hex(rest.strip_prefix("\\u")?, 4)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return None;
+            }
+            let code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+            Some((char::from_u32(code)?, rest))
+        }
+        'U' => {
+            let (code, rest) = hex(rest, 8)?;
+            Some((char::from_u32(code)?, rest))
+        }
+        _ => None,
+    }
+}
+
+/// Reads the `len` hex digits `text` starts with, and returns their value and
+/// the text after them.
+fn hex(text: &str, len: usize) -> Option<(u32, &str)> {
+    let digits = text.get(..len)?;
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((u32::from_str_radix(digits, 16).ok()?, &text[len..]))
+}
+
+/// Returns the string `value` holds.
+fn text(value: &Value) -> Result<&str> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(Diagnostic::type_error(Type::String, other.type_of())),
+    }
+}
@@ -0,0 +1,122 @@
+//! "Did you mean" suggestions for names that don't resolve.
+//!
+//! An undefined variable is compared with every name it could have meant:
+//! the bindings in scope, the prelude, the compiler's definitions and the
+//! registered units. The closest one within a third of the name's length in
+//! edits (at least one edit) is suggested. A name differing only in case wins
+//! outright, and ties go to the alphabetically first name so the suggestion
+//! doesn't depend on hash order.
+
+use crate::{
+    context::EvalContext,
+    diagnostic::{Diagnostic, DiagnosticKind},
+    interner::InternedString,
+};
+
+/// Returns an undefined variable error for `name`, suggesting the closest
+/// name defined in `ctx`.
+pub(crate) fn undefined_variable(name: InternedString, ctx: &EvalContext<'_>) -> Box<Diagnostic> {
+    let candidates = ctx
+        .env
+        .iter()
+        .map(|(name, _)| name)
+        .chain(ctx.compiler.defs().keys().copied())
+        .chain(ctx.compiler.units().all_units().map(|unit| unit.name));
+    let kind = DiagnosticKind::UndefinedVariable {
+        name,
+        suggestion: closest(&name, candidates),
+    };
+    Box::new(Diagnostic::new(kind, None))
+}
+
+/// Returns the candidate closest to `name`, if any is close enough.
+fn closest(
+    name: &str,
+    candidates: impl IntoIterator<Item = InternedString>,
+) -> Option<InternedString> {
+    let limit = name.chars().count().max(3) / 3;
+    candidates
+        .into_iter()
+        .filter(|candidate| &**candidate != name && is_identifier(candidate))
+        .filter_map(|candidate| {
+            let distance = if candidate.eq_ignore_ascii_case(name) {
+                0
+            } else {
+                edit_distance(name, &candidate)
+            };
+            (distance <= limit).then_some((distance, candidate))
+        })
+        .min_by(|(a, x), (b, y)| a.cmp(b).then_with(|| (**x).cmp(&**y)))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns whether `name` is a name a user writes, rather than an operator
+/// or a form the parser emits, like `__list__`.
+fn is_identifier(name: &str) -> bool {
+    !name.starts_with("__")
+        && name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Returns the edit distance between `a` and `b`: the fewest insertions,
+/// deletions and substitutions of characters, and swaps of adjacent
+/// characters, turning one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // `d[i][j]` is the distance between the first `i` characters of `a` and
+    // the first `j` of `b`
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        d[i][0] = i;
+        for j in 1..=b.len() {
+            let substitute = d[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitute.min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = distance;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Env};
+    use cadenza_syntax::parse::parse;
+
+    #[test]
+    fn closest_name_within_a_third_of_its_length() {
+        let names = ["defined_var", "length", "Width", "__list__", "+"].map(InternedString::from);
+        let suggest = |name| closest(name, names).map(|name| name.to_string());
+        assert_eq!(suggest("defind_var").as_deref(), Some("defined_var"));
+        assert_eq!(suggest("lenght").as_deref(), Some("length"));
+        assert_eq!(suggest("width").as_deref(), Some("Width"));
+        assert_eq!(suggest("list"), None);
+        assert_eq!(suggest("height"), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("aera", "area"), 1);
+    }
+
+    #[test]
+    fn undefined_variables_suggest_names_in_scope() {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        let source = "let total_length = 10\nfn area w h = w * h\ntotal_lenght\naera 2 3\n";
+        crate::eval(&parse(source).ast(), &mut env, &mut compiler);
+        let help: Vec<_> = compiler
+            .diagnostics()
+            .iter()
+            .map(|d| d.kind().help())
+            .collect();
+        assert_eq!(
+            help,
+            [
+                Some("did you mean `total_length`?".to_string()),
+                Some("did you mean `area`?".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Workspace symbol search.
+//!
+//! Editors search every top-level definition in a workspace by name
+//! (`workspace/symbol` in the LSP, completion in the REPL). Scanning every
+//! file per keystroke doesn't scale to thousands of definitions, so each file
+//! gets a [`SymbolIndex`]:
+//!
+//! - **Prefix index**: the symbols sorted by lowercased name, so the symbols
+//!   starting with a query are a binary search away.
+//! - **Trigram index**: for each three-byte window of a lowercased name, the
+//!   symbols containing it. A query of three or more bytes only checks the
+//!   symbols that have all of its trigrams.
+//!
+//! Indexes are built by the [`file_symbols`] Salsa query, so an edit rebuilds
+//! only the edited file's index, and not even that if the edit doesn't touch
+//! its definitions' names. [`SymbolSearch`] keeps a database of open files for
+//! callers that just want to add files and search.
+//!
+//! ```
+//! use cadenza_eval::symbol_index::{SymbolKind, SymbolSearch};
+//!
+//! let mut search = SymbolSearch::new();
+//! search.set_file("geometry.cdz", "fn square x = x * x\nlet unit_square = square 1\n");
+//!
+//! let matches = search.search("squ", 10);
+//! assert_eq!(matches[0].symbol.name, "square");
+//! assert_eq!(matches[0].symbol.kind, SymbolKind::Function);
+//! assert_eq!(matches[1].symbol.name, "unit_square");
+//! ```
+
+use crate::{
+    db::{CadenzaDb, CadenzaDbImpl, QueryStats, SourceFile, parse_file},
+    eval::extract_identifier,
+    interner::InternedString,
+    map::Map,
+    special_form::let_form,
+};
+use cadenza_syntax::{
+    ast::{Expr, Root},
+    span::Span,
+};
+use salsa::Setter;
+use std::collections::BTreeMap;
+
+/// The kind of a top-level definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    /// `fn name params... = body`
+    Function,
+    /// `let name = value`
+    Variable,
+    /// `struct Name { fields }`
+    Struct,
+    /// `enum Name = Variant fields | ...`
+    Enum,
+    /// `measure unit`
+    Measure,
+}
+
+/// A top-level definition.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    /// The defined name.
+    pub name: String,
+    /// What kind of definition it is.
+    pub kind: SymbolKind,
+    /// The span of the name in the definition.
+    pub span: Span,
+}
+
+/// The searchable definitions of one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolIndex {
+    /// The symbols, sorted by lowercased name.
+    symbols: Vec<Symbol>,
+    /// The lowercased name of each symbol.
+    keys: Vec<String>,
+    /// The symbols whose lowercased name contains each trigram.
+    trigrams: BTreeMap<[u8; 3], Vec<u32>>,
+}
+
+impl SymbolIndex {
+    /// Indexes `symbols`.
+    pub fn new(mut symbols: Vec<Symbol>) -> Self {
+        symbols.sort_by_cached_key(|symbol| (symbol.name.to_lowercase(), symbol.span));
+        let keys: Vec<String> = symbols
+            .iter()
+            .map(|symbol| symbol.name.to_lowercase())
+            .collect();
+
+        let mut trigrams: BTreeMap<[u8; 3], Vec<u32>> = BTreeMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            for trigram in trigrams_of(key) {
+                let postings = trigrams.entry(trigram).or_default();
+                // Postings are ascending, so a repeated trigram is the last one
+                if postings.last() != Some(&(i as u32)) {
+                    postings.push(i as u32);
+                }
+            }
+        }
+
+        Self {
+            symbols,
+            keys,
+            trigrams,
+        }
+    }
+
+    /// Returns the symbols, sorted by lowercased name.
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Returns the symbols whose name starts with `query`, ignoring case.
+    ///
+    /// `query` must already be lowercase.
+    fn prefix_matches<'a, 'q>(&'a self, query: &'q str) -> impl Iterator<Item = &'a Symbol> + 'q
+    where
+        'a: 'q,
+    {
+        let start = self.keys.partition_point(|key| key.as_str() < query);
+        self.keys[start..]
+            .iter()
+            .zip(&self.symbols[start..])
+            .take_while(move |(key, _)| key.starts_with(query))
+            .map(|(_, symbol)| symbol)
+    }
+
+    /// Returns the symbols whose name contains `query` other than at the
+    /// start, ignoring case.
+    ///
+    /// `query` must already be lowercase. Queries shorter than a trigram
+    /// match nothing here; they only match as prefixes.
+    fn substring_matches<'a, 'q>(&'a self, query: &'q str) -> impl Iterator<Item = &'a Symbol> + 'q
+    where
+        'a: 'q,
+    {
+        let mut postings: Vec<&[u32]> = Vec::new();
+        let mut missing = query.len() < 3;
+        for trigram in trigrams_of(query) {
+            match self.trigrams.get(&trigram) {
+                Some(list) => postings.push(list),
+                None => missing = true,
+            }
+        }
+        postings.sort_by_key(|list| list.len());
+
+        let candidates = if missing {
+            &[][..]
+        } else {
+            postings.first().copied().unwrap_or_default()
+        };
+        candidates
+            .iter()
+            .filter(move |i| {
+                postings[1..]
+                    .iter()
+                    .all(|list| list.binary_search(i).is_ok())
+            })
+            .map(|&i| i as usize)
+            .filter(move |&i| !self.keys[i].starts_with(query) && self.keys[i].contains(query))
+            .map(|i| &self.symbols[i])
+    }
+}
+
+/// Returns the three-byte windows of `key`.
+fn trigrams_of(key: &str) -> impl Iterator<Item = [u8; 3]> + '_ {
+    key.as_bytes()
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+}
+
+/// A set of source files to search.
+#[salsa::input]
+pub struct Workspace {
+    /// The files, in the order they were added.
+    #[returns(ref)]
+    pub files: Vec<SourceFile>,
+}
+
+/// Returns the index of the top-level definitions in `source`.
+///
+/// Salsa memoizes the index per file, so it is only rebuilt when the file
+/// changes.
+#[salsa::tracked(returns(ref))]
+pub fn file_symbols(db: &dyn CadenzaDb, source: SourceFile) -> SymbolIndex {
+    let cst = parse_file(db, source).cst(db).clone();
+    let symbols = Root::cast(cst)
+        .map(|root| root.items().filter_map(|item| definition(&item)).collect())
+        .unwrap_or_default();
+    SymbolIndex::new(symbols)
+}
+
+/// Returns the symbol defined by a top-level item, if it is a definition.
+pub(crate) fn definition(item: &Expr) -> Option<Symbol> {
+    let Expr::Apply(apply) = item else {
+        return None;
+    };
+    // `fn`, `let`, `enum` and derived `measure` definitions are `=`
+    // applications
+    let head = match extract_identifier(&apply.callee()?) {
+        Some(callee) if &*callee == "=" => match apply.all_arguments().into_iter().next()? {
+            Expr::Apply(lhs) => lhs,
+            _ => return None,
+        },
+        _ => apply.clone(),
+    };
+
+    let kind = match &*extract_identifier(&head.callee()?)? {
+        "fn" => SymbolKind::Function,
+        "let" => SymbolKind::Variable,
+        "struct" => SymbolKind::Struct,
+        "enum" => SymbolKind::Enum,
+        "measure" => SymbolKind::Measure,
+        _ => return None,
+    };
+    let args = head.all_arguments();
+    let args = match kind {
+        SymbolKind::Variable => let_form::strip_rec(&args, false).1,
+        _ => &args,
+    };
+    let name = match args.first()? {
+        Expr::Ident(ident) => ident,
+        _ => return None,
+    };
+    Some(Symbol {
+        name: name.syntax().text().to_string(),
+        kind,
+        span: name.span(),
+    })
+}
+
+/// A symbol found by a search.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SymbolMatch<'a> {
+    /// The file defining the symbol.
+    pub file: SourceFile,
+    /// The symbol.
+    pub symbol: &'a Symbol,
+}
+
+/// Returns up to `limit` symbols in `workspace` whose name contains `query`,
+/// ignoring case.
+///
+/// Symbols whose name starts with the query come first, then the other
+/// matches; each group is in file order, then name order. An empty query
+/// matches every symbol.
+pub fn search<'db>(
+    db: &'db dyn CadenzaDb,
+    workspace: Workspace,
+    query: &str,
+    limit: usize,
+) -> Vec<SymbolMatch<'db>> {
+    let query = query.to_lowercase();
+    let query = query.as_str();
+    let indexes: Vec<(SourceFile, &SymbolIndex)> = workspace
+        .files(db)
+        .iter()
+        .map(|&file| (file, file_symbols(db, file)))
+        .collect();
+
+    let prefixes = indexes.iter().flat_map(move |(file, index)| {
+        index.prefix_matches(query).map(|symbol| SymbolMatch {
+            file: *file,
+            symbol,
+        })
+    });
+    let substrings = indexes.iter().flat_map(move |(file, index)| {
+        index.substring_matches(query).map(|symbol| SymbolMatch {
+            file: *file,
+            symbol,
+        })
+    });
+    prefixes.chain(substrings).take(limit).collect()
+}
+
+/// A symbol found by [`SymbolSearch::search`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundSymbol {
+    /// The path of the file defining the symbol.
+    pub path: String,
+    /// The symbol.
+    pub symbol: Symbol,
+}
+
+/// A workspace of files kept up to date for symbol search.
+#[derive(Clone)]
+pub struct SymbolSearch {
+    db: CadenzaDbImpl,
+    workspace: Workspace,
+    files: Map<SourceFile>,
+}
+
+impl Default for SymbolSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolSearch {
+    /// Creates an empty workspace.
+    pub fn new() -> Self {
+        let db = CadenzaDbImpl::default();
+        let workspace = Workspace::new(&db, Vec::new());
+        Self {
+            db,
+            workspace,
+            files: Map::default(),
+        }
+    }
+
+    /// Adds the file at `path`, or replaces its text.
+    pub fn set_file(&mut self, path: &str, text: &str) {
+        match self.files.get(&InternedString::from(path)) {
+            Some(&file) if file.text(&self.db) == text => {}
+            Some(&file) => {
+                file.set_text(&mut self.db).to(text.to_string());
+            }
+            None => {
+                let file = SourceFile::new(&self.db, path.to_string(), text.to_string());
+                self.files.insert(path.into(), file);
+                let mut files = self.workspace.files(&self.db).clone();
+                files.push(file);
+                self.workspace.set_files(&mut self.db).to(files);
+            }
+        }
+    }
+
+    /// Removes the file at `path`.
+    pub fn remove_file(&mut self, path: &str) {
+        if let Some(file) = self.files.shift_remove(&InternedString::from(path)) {
+            let mut files = self.workspace.files(&self.db).clone();
+            files.retain(|other| *other != file);
+            self.workspace.set_files(&mut self.db).to(files);
+        }
+    }
+
+    /// Returns the paths of the files, in the order they were added.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.workspace
+            .files(&self.db)
+            .iter()
+            .map(|file| file.path(&self.db).as_str())
+    }
+
+    /// Returns the text of the file at `path`.
+    pub fn text(&self, path: &str) -> Option<&str> {
+        let file = self.files.get(&InternedString::from(path))?;
+        Some(file.text(&self.db))
+    }
+
+    /// Returns up to `limit` symbols whose name contains `query` (see
+    /// [`search`]).
+    pub fn search(&self, query: &str, limit: usize) -> Vec<FoundSymbol> {
+        search(&self.db, self.workspace, query, limit)
+            .into_iter()
+            .map(|found| FoundSymbol {
+                path: found.file.path(&self.db).clone(),
+                symbol: found.symbol.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns how the workspace's queries have been answered (see
+    /// [`CadenzaDbImpl::query_stats`]).
+    pub fn query_stats(&self) -> QueryStats {
+        self.db.query_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(search: &SymbolSearch, query: &str) -> Vec<String> {
+        search
+            .search(query, usize::MAX)
+            .into_iter()
+            .map(|found| found.symbol.name)
+            .collect()
+    }
+
+    #[test]
+    fn indexes_top_level_definitions() {
+        let mut search = SymbolSearch::new();
+        search.set_file(
+            "main.cdz",
+            "\
+fn area w h = w * h
+let total = area 2 3
+struct Point { x = Integer, y = Integer }
+measure meter
+measure inch = meter 0.0254
+area 1 1
+",
+        );
+
+        let symbols: Vec<(String, SymbolKind, Span)> = search
+            .search("", usize::MAX)
+            .into_iter()
+            .map(|found| (found.symbol.name, found.symbol.kind, found.symbol.span))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("area".into(), SymbolKind::Function, Span::new(3, 7)),
+                ("inch".into(), SymbolKind::Measure, Span::new(105, 109)),
+                ("meter".into(), SymbolKind::Measure, Span::new(91, 96)),
+                ("Point".into(), SymbolKind::Struct, Span::new(48, 53)),
+                ("total".into(), SymbolKind::Variable, Span::new(24, 29)),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefix_matches_come_before_substring_matches() {
+        let mut search = SymbolSearch::new();
+        search.set_file("a.cdz", "fn parse_int s = s\nfn try_parse s = s\n");
+        search.set_file("b.cdz", "fn Parser x = x\nfn reparse x = x\nfn pa x = x\n");
+
+        assert_eq!(
+            names(&search, "parse"),
+            vec!["parse_int", "Parser", "try_parse", "reparse"]
+        );
+        // Too short for a trigram, so only prefixes match
+        assert_eq!(names(&search, "pa"), vec!["parse_int", "pa", "Parser"]);
+        assert_eq!(names(&search, "arse_"), vec!["parse_int"]);
+        assert!(names(&search, "zzz").is_empty());
+        assert_eq!(search.search("parse", 2).len(), 2);
+    }
+
+    #[test]
+    fn edits_update_the_index() {
+        let mut search = SymbolSearch::new();
+        search.set_file("main.cdz", "fn old_name x = x\n");
+        search.set_file("lib.cdz", "fn helper x = x\n");
+        assert_eq!(names(&search, "name"), vec!["old_name"]);
+
+        search.set_file("main.cdz", "fn new_name x = x\n");
+        assert_eq!(names(&search, "name"), vec!["new_name"]);
+
+        search.remove_file("main.cdz");
+        assert!(names(&search, "name").is_empty());
+        assert_eq!(names(&search, "help"), vec!["helper"]);
+        assert_eq!(search.search("help", 1)[0].path, "lib.cdz");
+        assert_eq!(search.paths().collect::<Vec<_>>(), vec!["lib.cdz"]);
+    }
+
+    #[test]
+    fn unchanged_files_keep_their_index() {
+        let mut search = SymbolSearch::new();
+        search.set_file("main.cdz", "fn area w = w * w\n");
+        search.set_file("lib.cdz", "fn helper x = x\n");
+        let lib = search.files[&InternedString::from("lib.cdz")];
+        let before: *const SymbolIndex = file_symbols(&search.db, lib);
+
+        search.set_file("main.cdz", "fn area w = w * w * 1\n");
+        let after: *const SymbolIndex = file_symbols(&search.db, lib);
+        assert_eq!(before, after);
+    }
+}
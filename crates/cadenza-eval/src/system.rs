@@ -0,0 +1,561 @@
+//! Capability-gated access to the host system.
+//!
+//! Scripts can read and write files, run commands, read environment
+//! variables, stream standard input and output, and make HTTP requests (see
+//! the `http` module, behind the `http` feature), but only
+//! when the embedder grants the matching [`Capability`]. A fresh
+//! [`Compiler`](crate::Compiler) grants nothing, so evaluation is sandboxed by
+//! default (as in the web playground and the LSP).
+//!
+//! Failures are returned as [`Result`](crate::result) values rather than
+//! raised as diagnostics, so scripts can decide how to handle them:
+//!
+//! - `Ok value` on success
+//! - `Err message` on failure, including when the capability is denied
+//!
+//! ```cadenza
+//! let gcode = read_file "part.gcode"
+//! let sliced = exec "slicer" ["--input", "part.stl"]
+//! let home = env_var "HOME"?
+//! let path = (env ())?.PATH
+//! ```
+//!
+//! With the `stdio` capability, `read_line ()`, `read_all ()`, `write text` and
+//! `write_line text` make filter-style scripts possible:
+//!
+//! ```cadenza
+//! write_line (to_upper (read_all ())?) |?
+//! ```
+//!
+//! `exit code` and `abort message` stop the program instead, so scripts can
+//! report success or failure to a shell. Evaluation stops at the top level and
+//! the compiler keeps the exit status (see
+//! [`Compiler::exit_status`](crate::Compiler::exit_status)); in generated
+//! modules they call the host's `process` interface and trap if it returns.
+
+use crate::{
+    diagnostic::{Diagnostic, Result},
+    result,
+    value::{BuiltinFn, Type, Value},
+};
+use std::{
+    fmt,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+/// A kind of host access a script may be granted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Reading and writing files.
+    Filesystem,
+    /// Running commands and reading environment variables.
+    Process,
+    /// Making network requests.
+    Network,
+    /// Reading standard input and writing standard output.
+    Stdio,
+}
+
+impl Capability {
+    /// All capabilities.
+    pub const ALL: [Capability; 4] = [
+        Capability::Filesystem,
+        Capability::Process,
+        Capability::Network,
+        Capability::Stdio,
+    ];
+
+    /// Returns the name used on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Capability::Filesystem => "fs",
+            Capability::Process => "process",
+            Capability::Network => "net",
+            Capability::Stdio => "stdio",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Capability {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "fs" | "filesystem" => Ok(Capability::Filesystem),
+            "process" => Ok(Capability::Process),
+            "net" | "network" => Ok(Capability::Network),
+            "stdio" => Ok(Capability::Stdio),
+            _ => Err(format!(
+                "unknown capability '{s}', expected one of: {}",
+                Capability::ALL.map(Capability::as_str).join(", ")
+            )),
+        }
+    }
+}
+
+/// The set of capabilities granted to a program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    bits: u8,
+}
+
+impl Capabilities {
+    /// Grants nothing.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Grants every capability.
+    pub fn all() -> Self {
+        Capability::ALL.into_iter().collect()
+    }
+
+    /// Grants `capability`.
+    pub fn allow(&mut self, capability: Capability) {
+        self.bits |= capability.bit();
+    }
+
+    /// Returns true if `capability` is granted.
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.bits & capability.bit() != 0
+    }
+}
+
+impl FromIterator<Capability> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        let mut capabilities = Self::none();
+        for capability in iter {
+            capabilities.allow(capability);
+        }
+        capabilities
+    }
+}
+
+pub use crate::result::ok;
+
+/// Wraps a failed result with an error message.
+pub fn err(message: impl Into<String>) -> Value {
+    result::err(Value::String(message.into().into()))
+}
+
+pub(crate) fn denied(capability: Capability, name: &str) -> Value {
+    err(format!(
+        "{name} requires the '{capability}' capability, which has not been granted"
+    ))
+}
+
+pub(crate) fn expect_string(value: &Value) -> Result<&str> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(Diagnostic::type_error(Type::String, other.type_of())),
+    }
+}
+
+/// Creates the `read_file` builtin: `read_file path` returns the file's contents.
+pub fn builtin_read_file() -> BuiltinFn {
+    BuiltinFn {
+        name: "read_file",
+        signature: Type::function(vec![Type::String], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let path = expect_string(&args[0])?;
+            if !ctx.compiler.capabilities().allows(Capability::Filesystem) {
+                return Ok(denied(Capability::Filesystem, "read_file"));
+            }
+            Ok(match std::fs::read_to_string(path) {
+                Ok(contents) => ok(Value::String(contents.into())),
+                Err(e) => err(format!("failed to read {path}: {e}")),
+            })
+        },
+    }
+}
+
+/// Creates the `write_file` builtin: `write_file path contents` replaces the file's contents.
+pub fn builtin_write_file() -> BuiltinFn {
+    BuiltinFn {
+        name: "write_file",
+        signature: Type::function(vec![Type::String, Type::String], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 2 {
+                return Err(Diagnostic::arity(2, args.len()));
+            }
+            let path = expect_string(&args[0])?;
+            let contents = expect_string(&args[1])?;
+            if !ctx.compiler.capabilities().allows(Capability::Filesystem) {
+                return Ok(denied(Capability::Filesystem, "write_file"));
+            }
+            Ok(match std::fs::write(path, contents) {
+                Ok(()) => ok(Value::Nil),
+                Err(e) => err(format!("failed to write {path}: {e}")),
+            })
+        },
+    }
+}
+
+/// Creates the `exec` builtin: `exec cmd args` runs a command to completion.
+///
+/// The result is a record with the exit `status` and captured `stdout` and
+/// `stderr`. A non-zero exit status is still `Ok`; only failing to run the
+/// command is an `Err`.
+pub fn builtin_exec() -> BuiltinFn {
+    BuiltinFn {
+        name: "exec",
+        signature: Type::function(vec![Type::String, Type::list(Type::String)], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 2 {
+                return Err(Diagnostic::arity(2, args.len()));
+            }
+            let cmd = expect_string(&args[0])?;
+            let cmd_args = match &args[1] {
+                Value::List(items) => items
+                    .iter()
+                    .map(expect_string)
+                    .collect::<Result<Vec<_>>>()?,
+                other => {
+                    return Err(Diagnostic::type_error(
+                        Type::list(Type::String),
+                        other.type_of(),
+                    ));
+                }
+            };
+            if !ctx.compiler.capabilities().allows(Capability::Process) {
+                return Ok(denied(Capability::Process, "exec"));
+            }
+            let output = match std::process::Command::new(cmd).args(cmd_args).output() {
+                Ok(output) => output,
+                Err(e) => return Ok(err(format!("failed to run {cmd}: {e}"))),
+            };
+            let status = output
+                .status
+                .code()
+                .map_or(Value::Nil, |code| Value::Integer(code as i64));
+            Ok(ok(Value::Record {
+                type_name: None,
+                fields: vec![
+                    ("status".into(), status),
+                    (
+                        "stdout".into(),
+                        Value::String(String::from_utf8_lossy(&output.stdout).into_owned().into()),
+                    ),
+                    (
+                        "stderr".into(),
+                        Value::String(String::from_utf8_lossy(&output.stderr).into_owned().into()),
+                    ),
+                ],
+            }))
+        },
+    }
+}
+
+/// Creates the `env_var` builtin: `env_var name` reads an environment variable.
+pub fn builtin_env_var() -> BuiltinFn {
+    BuiltinFn {
+        name: "env_var",
+        signature: Type::function(vec![Type::String], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let name = expect_string(&args[0])?;
+            if !ctx.compiler.capabilities().allows(Capability::Process) {
+                return Ok(denied(Capability::Process, "env_var"));
+            }
+            Ok(match std::env::var(name) {
+                Ok(value) => ok(Value::String(value.into())),
+                Err(e) => err(format!("{name}: {e}")),
+            })
+        },
+    }
+}
+
+/// Creates the `env` builtin: `env ()` returns every environment variable
+/// as a record, sorted by name.
+pub fn builtin_env() -> BuiltinFn {
+    BuiltinFn {
+        name: "env",
+        signature: Type::function(vec![Type::Nil], Type::Unknown),
+        func: |args, ctx| {
+            expect_unit(args)?;
+            if !ctx.compiler.capabilities().allows(Capability::Process) {
+                return Ok(denied(Capability::Process, "env"));
+            }
+            let mut vars: Vec<_> = std::env::vars().collect();
+            vars.sort();
+            Ok(ok(Value::Record {
+                type_name: None,
+                fields: vars
+                    .into_iter()
+                    .map(|(name, value)| (name.as_str().into(), Value::String(value.into())))
+                    .collect(),
+            }))
+        },
+    }
+}
+
+/// Checks that a builtin taking no input was applied to `()`.
+fn expect_unit(args: &[Value]) -> Result<()> {
+    match args {
+        [Value::Nil] => Ok(()),
+        [other] => Err(Diagnostic::type_error(Type::Nil, other.type_of())),
+        _ => Err(Diagnostic::arity(1, args.len())),
+    }
+}
+
+/// Creates the `read_line` builtin: `read_line ()` reads the next line of
+/// standard input, without its line ending.
+///
+/// The result is `Ok (Some line)`, or `Ok None` at the end of the input.
+pub fn builtin_read_line() -> BuiltinFn {
+    BuiltinFn {
+        name: "read_line",
+        signature: Type::function(vec![Type::Nil], Type::Unknown),
+        func: |args, ctx| {
+            expect_unit(args)?;
+            if !ctx.compiler.capabilities().allows(Capability::Stdio) {
+                return Ok(denied(Capability::Stdio, "read_line"));
+            }
+            let mut line = String::new();
+            Ok(match std::io::stdin().read_line(&mut line) {
+                Ok(0) => ok(result::none()),
+                Ok(_) => {
+                    let len = line.trim_end_matches(['\n', '\r']).len();
+                    line.truncate(len);
+                    ok(result::some(Value::String(line.into())))
+                }
+                Err(e) => err(format!("failed to read standard input: {e}")),
+            })
+        },
+    }
+}
+
+/// Creates the `read_all` builtin: `read_all ()` reads the rest of standard
+/// input.
+pub fn builtin_read_all() -> BuiltinFn {
+    BuiltinFn {
+        name: "read_all",
+        signature: Type::function(vec![Type::Nil], Type::Unknown),
+        func: |args, ctx| {
+            expect_unit(args)?;
+            if !ctx.compiler.capabilities().allows(Capability::Stdio) {
+                return Ok(denied(Capability::Stdio, "read_all"));
+            }
+            let mut contents = String::new();
+            Ok(match std::io::stdin().read_to_string(&mut contents) {
+                Ok(_) => ok(Value::String(contents.into())),
+                Err(e) => err(format!("failed to read standard input: {e}")),
+            })
+        },
+    }
+}
+
+/// Creates the `write` builtin: `write text` writes to standard output.
+pub fn builtin_write() -> BuiltinFn {
+    BuiltinFn {
+        name: "write",
+        signature: Type::function(vec![Type::String], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let text = expect_string(&args[0])?;
+            if !ctx.compiler.capabilities().allows(Capability::Stdio) {
+                return Ok(denied(Capability::Stdio, "write"));
+            }
+            Ok(write_stdout(text))
+        },
+    }
+}
+
+/// Creates the `write_line` builtin: `write_line text` writes to standard
+/// output, followed by a newline.
+pub fn builtin_write_line() -> BuiltinFn {
+    BuiltinFn {
+        name: "write_line",
+        signature: Type::function(vec![Type::String], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let text = expect_string(&args[0])?;
+            if !ctx.compiler.capabilities().allows(Capability::Stdio) {
+                return Ok(denied(Capability::Stdio, "write_line"));
+            }
+            Ok(write_stdout(&format!("{text}\n")))
+        },
+    }
+}
+
+/// Writes `text` to standard output and flushes it, so a prompt without a
+/// newline appears before the next read.
+fn write_stdout(text: &str) -> Value {
+    let mut stdout = std::io::stdout().lock();
+    match stdout
+        .write_all(text.as_bytes())
+        .and_then(|()| stdout.flush())
+    {
+        Ok(()) => ok(Value::Nil),
+        Err(e) => err(format!("failed to write standard output: {e}")),
+    }
+}
+
+/// Creates the `exit` builtin: `exit code` stops the program with exit status
+/// `code`.
+pub fn builtin_exit() -> BuiltinFn {
+    BuiltinFn {
+        name: "exit",
+        signature: Type::function(vec![Type::Integer], Type::Nil),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let code = match &args[0] {
+                Value::Integer(code) => *code,
+                other => return Err(Diagnostic::type_error(Type::Integer, other.type_of())),
+            };
+            if !ctx.compiler.capabilities().allows(Capability::Process) {
+                return Ok(denied(Capability::Process, "exit"));
+            }
+            let code = i32::try_from(code)
+                .map_err(|_| Diagnostic::syntax(format!("exit status {code} is out of range")))?;
+            Err(Diagnostic::exit(code))
+        },
+    }
+}
+
+/// Creates the `abort` builtin: `abort message` stops the program with an
+/// error.
+pub fn builtin_abort() -> BuiltinFn {
+    BuiltinFn {
+        name: "abort",
+        signature: Type::function(vec![Type::String], Type::Nil),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let message = expect_string(&args[0])?;
+            if !ctx.compiler.capabilities().allows(Capability::Process) {
+                return Ok(denied(Capability::Process, "abort"));
+            }
+            Err(Diagnostic::aborted(message))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Env};
+    use cadenza_syntax::parse::parse;
+
+    fn eval_with(src: &str, capabilities: Capabilities) -> (Vec<Value>, Compiler) {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        compiler.set_capabilities(capabilities);
+        let results = crate::eval(&parse(src).ast(), &mut env, &mut compiler);
+        (results, compiler)
+    }
+
+    fn is_err(value: &Value) -> bool {
+        matches!(value, Value::Variant { variant, .. } if &**variant == "Err")
+    }
+
+    #[test]
+    fn capabilities_parse_from_names() {
+        assert_eq!("fs".parse(), Ok(Capability::Filesystem));
+        assert_eq!("process".parse(), Ok(Capability::Process));
+        assert_eq!("net".parse(), Ok(Capability::Network));
+        assert_eq!("stdio".parse(), Ok(Capability::Stdio));
+        assert!("gpu".parse::<Capability>().is_err());
+
+        let granted: Capabilities = [Capability::Process].into_iter().collect();
+        assert!(granted.allows(Capability::Process));
+        assert!(!granted.allows(Capability::Filesystem));
+    }
+
+    #[test]
+    fn denied_by_default() {
+        let (results, compiler) = eval_with(
+            "read_file \"Cargo.toml\"\nenv_var \"PATH\"\nenv ()\nexec \"true\" []\nexit 1\nread_line ()\nwrite_line \"hi\"\n",
+            Capabilities::none(),
+        );
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert!(results.iter().all(is_err), "{results:?}");
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let path = std::env::temp_dir().join(format!("cadenza-system-{}.txt", std::process::id()));
+        let path = path.display().to_string().replace('\\', "/");
+        let src = format!("write_file \"{path}\" \"hello\"\nread_file \"{path}\"\n");
+        let (results, compiler) = eval_with(&src, Capabilities::all());
+        let _ = std::fs::remove_file(&path);
+
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results[0], ok(Value::Nil));
+        assert_eq!(results[1], ok(Value::String("hello".into())));
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        let (results, _) = eval_with(
+            "read_file \"/definitely/not/a/real/file\"\n",
+            Capabilities::all(),
+        );
+        assert!(is_err(&results[0]), "{results:?}");
+    }
+
+    #[test]
+    fn exit_and_abort_stop_evaluation() {
+        let granted: Capabilities = [Capability::Process].into_iter().collect();
+        let (results, compiler) = eval_with("1\nfn stop = exit 3\nstop\n4\n", granted);
+        assert_eq!(compiler.exit_status(), Some(3));
+        assert!(
+            compiler.diagnostics().is_empty(),
+            "{:?}",
+            compiler.diagnostics()
+        );
+        assert_eq!(results.len(), 3, "{results:?}");
+
+        let (results, compiler) = eval_with("abort \"broken\"\n4\n", granted);
+        assert_eq!(compiler.exit_status(), Some(Compiler::ABORT_STATUS));
+        assert_eq!(results.len(), 1, "{results:?}");
+        assert!(
+            compiler.diagnostics()[0]
+                .to_string()
+                .contains("aborted: broken"),
+            "{:?}",
+            compiler.diagnostics()
+        );
+    }
+
+    #[test]
+    fn argument_types_are_checked() {
+        let (_, compiler) = eval_with("exec \"true\" \"not a list\"\n", Capabilities::all());
+        assert!(compiler.has_errors());
+
+        let (_, compiler) = eval_with("read_all 1\n", Capabilities::all());
+        assert!(compiler.has_errors());
+    }
+}
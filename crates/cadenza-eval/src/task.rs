@@ -0,0 +1,774 @@
+//! Structured concurrency for the evaluator.
+//!
+//! Tasks and channels run on a single-threaded, cooperative executor owned by
+//! the [`Compiler`](crate::Compiler), so programs behave deterministically:
+//!
+//! - `spawn expr` captures `expr` and the current environment, queues it, and
+//!   returns a task handle without evaluating anything.
+//! - `await task` waits until `task` has finished, then returns its result (or
+//!   re-raises its error).
+//! - `channel n` creates a channel buffering at most `n` values. `send` waits
+//!   while the channel is full and `recv` while it is empty, so producer and
+//!   consumer tasks stream values through bounded buffers.
+//!
+//! Natively, every task runs on a stack of its own (a [`corosensei`]
+//! coroutine, on a [stack segment](crate::stack)). A task that has to wait
+//! parks itself on what it waits for: a channel to have room, a channel to
+//! have a value, or a task to finish. Parked tasks don't get turns. A send
+//! wakes one task waiting to receive, a receive one waiting to send, and
+//! closing a channel or finishing a task wakes every task waiting on it, by
+//! queueing them to check again. Top-level code waits by giving the queued
+//! tasks turns, in order, until what it waits for is ready. Once no task is
+//! queued, nothing can end the wait, and it fails with a deadlock error
+//! (`E0026`).
+//!
+//! WebAssembly can't switch stacks, so there a task runs to completion once
+//! started, and a task that has to wait runs the queued tasks inside itself.
+//! Waits a suspended task could get past natively, such as a producer
+//! filling a channel before its consumer starts, deadlock there.
+//!
+//! Tasks that are never awaited are joined when top-level evaluation
+//! finishes, so no work outlives the program that spawned it. Tasks still
+//! parked then are all woken with a deadlock error, which is reported once.
+//!
+//! ```cadenza
+//! fn produce ch =
+//!     send ch 1
+//!     send ch 2
+//!     close ch
+//!
+//! let ch = channel 1
+//! let producer = spawn (produce ch)
+//! recv ch
+//! recv ch
+//! ```
+
+use crate::{
+    compiler::Compiler,
+    context::{Eval, EvalContext},
+    diagnostic::{Diagnostic, Result},
+    env::Env,
+    value::{BuiltinFn, Type, Value},
+};
+use cadenza_syntax::ast::Expr;
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+
+/// A handle to a spawned task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaskId(pub u32);
+
+/// A handle to a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelId(pub u32);
+
+/// What a parked task waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Event {
+    /// Room in a channel's buffer.
+    Sendable(ChannelId),
+    /// A value in a channel's buffer, or the channel closing.
+    Receivable(ChannelId),
+    /// A task finishing.
+    Finished(TaskId),
+}
+
+/// The lifecycle of a task.
+#[derive(Clone)]
+pub enum TaskState {
+    /// Spawned but not yet started.
+    Pending {
+        /// The expression to evaluate.
+        expr: Expr,
+        /// The environment captured at spawn time.
+        env: Env,
+    },
+    /// Started and not finished: either being evaluated or suspended while it
+    /// waits.
+    Running,
+    /// Finished with a value.
+    Done(Value),
+    /// Finished with an error.
+    Failed(Box<Diagnostic>),
+}
+
+/// A bounded FIFO channel.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    capacity: usize,
+    buffer: VecDeque<Value>,
+    closed: bool,
+}
+
+impl Channel {
+    /// Returns the maximum number of buffered values.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of buffered values.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns true if no values are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns true if the channel has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn is_full(&self) -> bool {
+        self.buffer.len() >= self.capacity
+    }
+}
+
+/// How a suspended task is told to continue.
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wake {
+    /// Check again whether the wait is over, as what it waits for changed.
+    Retry,
+    /// Give up: no other task can end the wait.
+    Deadlock,
+}
+
+/// The coroutine a task runs on. The compiler is handed over on every switch
+/// between it and the stack that resumed it.
+#[cfg(not(target_family = "wasm"))]
+type Coroutine = corosensei::Coroutine<
+    (std::ptr::NonNull<Compiler>, Wake),
+    std::ptr::NonNull<Compiler>,
+    Result<Value>,
+>;
+
+#[cfg(not(target_family = "wasm"))]
+type Yielder =
+    corosensei::Yielder<(std::ptr::NonNull<Compiler>, Wake), std::ptr::NonNull<Compiler>>;
+
+/// A started task's coroutine and what it needs to switch back to it.
+#[cfg(not(target_family = "wasm"))]
+struct Fiber {
+    /// The coroutine, taken out while the task runs.
+    coroutine: Option<Coroutine>,
+    /// What the task suspends itself with, set when it starts.
+    yielder: Option<std::ptr::NonNull<Yielder>>,
+    /// The task's stack limit while it is suspended (see [`crate::stack`]).
+    limit: Option<usize>,
+}
+
+/// The cooperative executor holding all tasks and channels.
+#[derive(Default)]
+pub struct Executor {
+    tasks: Vec<TaskState>,
+    /// Tasks waiting for a turn, to start or to resume, in order.
+    queue: VecDeque<TaskId>,
+    /// Parked tasks, by what they wait for, in the order they parked.
+    parked: FxHashMap<Event, VecDeque<TaskId>>,
+    channels: Vec<Channel>,
+    /// The task being evaluated, if any.
+    current: Option<TaskId>,
+    #[cfg(not(target_family = "wasm"))]
+    fibers: FxHashMap<TaskId, Fiber>,
+}
+
+impl Executor {
+    /// Creates an empty executor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `expr` for evaluation in `env` and returns its handle.
+    pub fn spawn(&mut self, expr: Expr, env: Env) -> TaskId {
+        let id = TaskId(self.tasks.len() as u32);
+        self.tasks.push(TaskState::Pending { expr, env });
+        self.queue.push_back(id);
+        id
+    }
+
+    /// Returns the state of a task.
+    pub fn task(&self, id: TaskId) -> Option<&TaskState> {
+        self.tasks.get(id.0 as usize)
+    }
+
+    /// Returns the number of tasks that have not started yet.
+    pub fn num_pending(&self) -> usize {
+        self.tasks
+            .iter()
+            .filter(|task| matches!(task, TaskState::Pending { .. }))
+            .count()
+    }
+
+    /// Returns the task being evaluated, if any.
+    pub fn current(&self) -> Option<TaskId> {
+        self.current
+    }
+
+    /// Returns the result of a finished task, or `None` if it has not finished.
+    pub fn result(&self, id: TaskId) -> Option<Result<Value>> {
+        match self.task(id)? {
+            TaskState::Done(value) => Some(Ok(value.clone())),
+            TaskState::Failed(err) => Some(Err(err.clone())),
+            TaskState::Pending { .. } | TaskState::Running => None,
+        }
+    }
+
+    /// Dequeues the next pending task, marking it as running.
+    ///
+    /// Returns the task's expression and environment so the caller can evaluate
+    /// it without holding a borrow of the executor.
+    pub fn start_next(&mut self) -> Option<(TaskId, Expr, Env)> {
+        let id = self.queue.pop_front()?;
+        let state = std::mem::replace(&mut self.tasks[id.0 as usize], TaskState::Running);
+        match state {
+            TaskState::Pending { expr, env } => Some((id, expr, env)),
+            other => {
+                // Already started through another path; restore and move on
+                self.tasks[id.0 as usize] = other;
+                self.start_next()
+            }
+        }
+    }
+
+    /// Records the outcome of a task started with [`Executor::start_next`].
+    pub fn finish(&mut self, id: TaskId, result: Result<Value>) {
+        self.tasks[id.0 as usize] = match result {
+            Ok(value) => TaskState::Done(value),
+            Err(err) => TaskState::Failed(err),
+        };
+        self.wake_all(Event::Finished(id));
+    }
+
+    /// Parks task `id` until `event` wakes it.
+    #[cfg(not(target_family = "wasm"))]
+    fn park(&mut self, id: TaskId, event: Event) {
+        self.parked.entry(event).or_default().push_back(id);
+    }
+
+    /// Queues the task parked longest on `event`, if any.
+    fn wake_one(&mut self, event: Event) {
+        if let Some(waiting) = self.parked.get_mut(&event)
+            && let Some(id) = waiting.pop_front()
+        {
+            if waiting.is_empty() {
+                self.parked.remove(&event);
+            }
+            self.queue.push_back(id);
+        }
+    }
+
+    /// Queues every task parked on `event`.
+    fn wake_all(&mut self, event: Event) {
+        if let Some(waiting) = self.parked.remove(&event) {
+            self.queue.extend(waiting);
+        }
+    }
+
+    /// Unparks every parked task, in the order they parked, without
+    /// queueing them.
+    #[cfg(not(target_family = "wasm"))]
+    fn take_parked(&mut self) -> Vec<TaskId> {
+        let mut parked: Vec<_> = self.parked.drain().flat_map(|(_, ids)| ids).collect();
+        // Tasks are numbered in spawn order, which is what the report follows
+        parked.sort_by_key(|id| id.0);
+        parked
+    }
+
+    /// Drops every unfinished task without running it any further.
+    ///
+    /// Used when the program exits with tasks still waiting.
+    pub fn cancel(&mut self) {
+        self.queue.clear();
+        self.parked.clear();
+        #[cfg(not(target_family = "wasm"))]
+        drop(std::mem::take(&mut self.fibers));
+        for task in &mut self.tasks {
+            if matches!(task, TaskState::Pending { .. } | TaskState::Running) {
+                *task = TaskState::Failed(Diagnostic::syntax("task cancelled"));
+            }
+        }
+    }
+
+    /// Creates a channel buffering at most `capacity` values.
+    pub fn channel(&mut self, capacity: usize) -> ChannelId {
+        let id = ChannelId(self.channels.len() as u32);
+        self.channels.push(Channel {
+            capacity,
+            buffer: VecDeque::new(),
+            closed: false,
+        });
+        id
+    }
+
+    /// Returns a channel by handle.
+    pub fn get_channel(&self, id: ChannelId) -> Option<&Channel> {
+        self.channels.get(id.0 as usize)
+    }
+
+    /// Attempts to buffer `value`. Returns the value back if the channel is full.
+    pub fn try_send(
+        &mut self,
+        id: ChannelId,
+        value: Value,
+    ) -> Result<std::result::Result<(), Value>> {
+        let channel = self.channel_mut(id)?;
+        if channel.closed {
+            return Err(Diagnostic::syntax("send on closed channel"));
+        }
+        if channel.is_full() {
+            return Ok(Err(value));
+        }
+        channel.buffer.push_back(value);
+        self.wake_one(Event::Receivable(id));
+        Ok(Ok(()))
+    }
+
+    /// Attempts to take the next value. Returns `None` if the channel is empty.
+    pub fn try_recv(&mut self, id: ChannelId) -> Result<Option<Value>> {
+        let value = self.channel_mut(id)?.buffer.pop_front();
+        if value.is_some() {
+            self.wake_one(Event::Sendable(id));
+        }
+        Ok(value)
+    }
+
+    /// Closes a channel; receivers get `nil` once it drains.
+    pub fn close(&mut self, id: ChannelId) -> Result<()> {
+        self.channel_mut(id)?.closed = true;
+        self.wake_all(Event::Receivable(id));
+        self.wake_all(Event::Sendable(id));
+        Ok(())
+    }
+
+    fn channel_mut(&mut self, id: ChannelId) -> Result<&mut Channel> {
+        self.channels
+            .get_mut(id.0 as usize)
+            .ok_or_else(|| Diagnostic::internal(format!("unknown channel {}", id.0)))
+    }
+
+    /// Returns true if task `id` is waiting, on the stack being evaluated, for
+    /// the evaluation to finish, so waiting for it would never end.
+    fn is_waiting_on_this_stack(&self, id: TaskId) -> bool {
+        if cfg!(target_family = "wasm") {
+            // Every running task is one that called into the current one
+            matches!(self.task(id), Some(TaskState::Running))
+        } else {
+            self.current == Some(id)
+        }
+    }
+}
+
+/// Gives the next queued task a turn: starts it, or resumes it if it is
+/// waiting.
+///
+/// Returns false if no tasks were queued.
+#[cfg(not(target_family = "wasm"))]
+pub fn run_next(compiler: &mut Compiler) -> bool {
+    let Some(id) = compiler.executor_mut().queue.pop_front() else {
+        return false;
+    };
+    resume(id, Wake::Retry, compiler);
+    true
+}
+
+/// Starts and runs the next queued task to completion.
+///
+/// Returns false if no tasks were queued.
+#[cfg(target_family = "wasm")]
+pub fn run_next(compiler: &mut Compiler) -> bool {
+    let Some((id, expr, mut env)) = compiler.executor_mut().start_next() else {
+        return false;
+    };
+    let caller = compiler.executor_mut().current.replace(id);
+    let result = {
+        let mut ctx = EvalContext::new(&mut env, compiler);
+        expr.eval(&mut ctx)
+    };
+    let executor = compiler.executor_mut();
+    executor.current = caller;
+    executor.finish(id, result);
+    true
+}
+
+/// Runs task `id` until it finishes or suspends itself, starting it first if
+/// it hasn't started. A suspended task has parked itself (see [`wait`]).
+#[cfg(not(target_family = "wasm"))]
+fn resume(id: TaskId, wake: Wake, compiler: &mut Compiler) {
+    let executor = compiler.executor_mut();
+    let state = std::mem::replace(&mut executor.tasks[id.0 as usize], TaskState::Running);
+    let mut coroutine = match state {
+        TaskState::Pending { expr, env } => {
+            let segment = match crate::stack::take_segment() {
+                Ok(segment) => segment,
+                Err(err) => return executor.finish(id, Err(err)),
+            };
+            executor.fibers.insert(
+                id,
+                Fiber {
+                    coroutine: None,
+                    yielder: None,
+                    limit: None,
+                },
+            );
+            Coroutine::with_stack(segment, move |yielder, (compiler, _)| {
+                start(id, expr, env, yielder, compiler)
+            })
+        }
+        TaskState::Running => executor
+            .fibers
+            .get_mut(&id)
+            .and_then(|fiber| fiber.coroutine.take())
+            .expect("a started task that isn't running has a coroutine"),
+        finished => {
+            executor.tasks[id.0 as usize] = finished;
+            return;
+        }
+    };
+
+    let limit = executor.fibers.get(&id).and_then(|fiber| fiber.limit);
+    let caller = executor.current.replace(id);
+    let outer = crate::stack::swap_limit(limit);
+    let result = coroutine.resume((std::ptr::NonNull::from(&mut *compiler), wake));
+    let limit = crate::stack::swap_limit(outer);
+
+    let executor = compiler.executor_mut();
+    executor.current = caller;
+    match result {
+        corosensei::CoroutineResult::Yield(_) => {
+            let fiber = executor
+                .fibers
+                .get_mut(&id)
+                .expect("a suspended task has a fiber");
+            fiber.coroutine = Some(coroutine);
+            fiber.limit = limit;
+        }
+        corosensei::CoroutineResult::Return(result) => {
+            executor.fibers.remove(&id);
+            executor.finish(id, result);
+            crate::stack::reuse_segment(coroutine.into_stack());
+        }
+    }
+}
+
+/// Evaluates a task's expression, on the task's own stack.
+#[cfg(not(target_family = "wasm"))]
+fn start(
+    id: TaskId,
+    expr: Expr,
+    mut env: Env,
+    yielder: &Yielder,
+    compiler: std::ptr::NonNull<Compiler>,
+) -> Result<Value> {
+    crate::stack::enter_segment();
+    // SAFETY: the stack that resumed this task handed over the compiler and
+    // doesn't use it again until the task suspends itself or finishes, which
+    // hands it back (see `suspend`)
+    let compiler = unsafe { &mut *compiler.as_ptr() };
+    if let Some(fiber) = compiler.executor_mut().fibers.get_mut(&id) {
+        fiber.yielder = Some(std::ptr::NonNull::from(yielder));
+    }
+    let mut ctx = EvalContext::new(&mut env, compiler);
+    expr.eval(&mut ctx)
+}
+
+/// Suspends the current task `id` until the executor gives it another turn.
+/// The task must have parked itself, or nothing will.
+#[cfg(not(target_family = "wasm"))]
+fn suspend(id: TaskId, compiler: &mut Compiler) -> Wake {
+    let yielder = compiler
+        .executor()
+        .fibers
+        .get(&id)
+        .and_then(|fiber| fiber.yielder)
+        .expect("a running task has a yielder");
+    // SAFETY: the yielder lives at the bottom of this task's stack, which is
+    // the one running this. The compiler is handed to the stack that resumed
+    // the task, and back when it resumes the task again.
+    let (_, wake) = unsafe { yielder.as_ref() }.suspend(std::ptr::NonNull::from(&mut *compiler));
+    wake
+}
+
+/// Waits until `attempt` returns a value, giving other tasks turns meanwhile.
+///
+/// A task parks on `event` and tries `attempt` again every time it happens;
+/// top-level code (and any code on WebAssembly) tries it again after every
+/// turn. `what` describes the wait for the deadlock error if it can never
+/// end.
+#[cfg_attr(target_family = "wasm", allow(unused_variables))]
+fn wait<T>(
+    compiler: &mut Compiler,
+    event: Event,
+    what: &str,
+    mut attempt: impl FnMut(&mut Executor) -> Result<Option<T>>,
+) -> Result<T> {
+    loop {
+        if let Some(done) = attempt(compiler.executor_mut())? {
+            return Ok(done);
+        }
+
+        // A task parks itself and lets the executor run the others
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(id) = compiler.executor().current {
+            compiler.executor_mut().park(id, event);
+            match suspend(id, compiler) {
+                Wake::Retry => continue,
+                Wake::Deadlock => return Err(Diagnostic::deadlock(what)),
+            }
+        }
+
+        // Only a queued task can change what this waits for
+        if !run_next(compiler) {
+            return Err(Diagnostic::deadlock(what));
+        }
+    }
+}
+
+/// Runs every queued task, returning the errors of tasks that failed.
+///
+/// Used to join outstanding tasks when top-level evaluation finishes. When
+/// the tasks left are all parked, nothing can wake them: they are all woken
+/// with a deadlock error, and the first is reported for all of them.
+pub fn run_until_idle(compiler: &mut Compiler) -> Vec<Box<Diagnostic>> {
+    let mut errors = Vec::new();
+    loop {
+        while let Some(id) = compiler.executor().queue.front().copied() {
+            run_next(compiler);
+            if let Some(Err(err)) = compiler.executor().result(id) {
+                errors.push(err);
+            }
+        }
+
+        // Tasks only park when they can suspend
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let parked = compiler.executor_mut().take_parked();
+            if parked.is_empty() {
+                break;
+            }
+            let mut deadlock = None;
+            for id in parked {
+                resume(id, Wake::Deadlock, compiler);
+                if let Some(Err(err)) = compiler.executor().result(id) {
+                    deadlock.get_or_insert(err);
+                }
+            }
+            errors.extend(deadlock);
+        }
+        #[cfg(target_family = "wasm")]
+        break;
+    }
+    errors
+}
+
+/// Waits until task `id` has finished and returns its result.
+pub fn await_task(id: TaskId, compiler: &mut Compiler) -> Result<Value> {
+    wait(
+        compiler,
+        Event::Finished(id),
+        "await on a task that can never finish",
+        |executor| {
+            if executor.task(id).is_none() {
+                return Err(Diagnostic::internal(format!("unknown task {}", id.0)));
+            }
+            if executor.is_waiting_on_this_stack(id) {
+                return Err(Diagnostic::deadlock("task awaits itself"));
+            }
+            Ok(executor.result(id))
+        },
+    )?
+}
+
+/// Sends `value` on a channel, waiting while the channel is full.
+pub fn send(id: ChannelId, value: Value, compiler: &mut Compiler) -> Result<()> {
+    let mut value = Some(value);
+    wait(
+        compiler,
+        Event::Sendable(id),
+        "send on full channel with no task left to receive",
+        |executor| {
+            let Some(pending) = value.take() else {
+                return Ok(Some(()));
+            };
+            match executor.try_send(id, pending)? {
+                Ok(()) => Ok(Some(())),
+                Err(rejected) => {
+                    value = Some(rejected);
+                    Ok(None)
+                }
+            }
+        },
+    )
+}
+
+/// Receives a value from a channel, waiting while it is empty.
+///
+/// Returns `nil` once a closed channel has drained.
+pub fn recv(id: ChannelId, compiler: &mut Compiler) -> Result<Value> {
+    wait(
+        compiler,
+        Event::Receivable(id),
+        "recv on empty channel with no task left to send",
+        |executor| {
+            if let Some(value) = executor.try_recv(id)? {
+                return Ok(Some(value));
+            }
+            if executor.get_channel(id).is_some_and(Channel::is_closed) {
+                return Ok(Some(Value::Nil));
+            }
+            Ok(None)
+        },
+    )
+}
+
+fn expect_task(value: &Value) -> Result<TaskId> {
+    match value {
+        Value::Task(id) => Ok(*id),
+        other => Err(Diagnostic::type_error(
+            Type::task(Type::Unknown),
+            other.type_of(),
+        )),
+    }
+}
+
+fn expect_channel(value: &Value) -> Result<ChannelId> {
+    match value {
+        Value::Channel(id) => Ok(*id),
+        other => Err(Diagnostic::type_error(
+            Type::channel(Type::Unknown),
+            other.type_of(),
+        )),
+    }
+}
+
+/// Creates the `await` builtin, which joins a task and returns its result.
+pub fn builtin_await() -> BuiltinFn {
+    BuiltinFn {
+        name: "await",
+        signature: Type::function(vec![Type::task(Type::Unknown)], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let id = expect_task(&args[0])?;
+            await_task(id, ctx.compiler)
+        },
+    }
+}
+
+/// Creates the `channel` builtin, which creates a bounded channel.
+pub fn builtin_channel() -> BuiltinFn {
+    BuiltinFn {
+        name: "channel",
+        signature: Type::function(vec![Type::Integer], Type::channel(Type::Unknown)),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let capacity = match &args[0] {
+                Value::Integer(n) if *n > 0 => *n as usize,
+                Value::Integer(n) => {
+                    return Err(Diagnostic::syntax(format!(
+                        "channel capacity must be positive, got {n}"
+                    )));
+                }
+                other => return Err(Diagnostic::type_error(Type::Integer, other.type_of())),
+            };
+            Ok(Value::Channel(
+                ctx.compiler.executor_mut().channel(capacity),
+            ))
+        },
+    }
+}
+
+/// Creates the `send` builtin, which buffers a value on a channel.
+pub fn builtin_send() -> BuiltinFn {
+    BuiltinFn {
+        name: "send",
+        signature: Type::function(vec![Type::channel(Type::Unknown), Type::Unknown], Type::Nil),
+        func: |args, ctx| {
+            if args.len() != 2 {
+                return Err(Diagnostic::arity(2, args.len()));
+            }
+            let id = expect_channel(&args[0])?;
+            send(id, args[1].clone(), ctx.compiler)?;
+            Ok(Value::Nil)
+        },
+    }
+}
+
+/// Creates the `recv` builtin, which takes the next value from a channel.
+pub fn builtin_recv() -> BuiltinFn {
+    BuiltinFn {
+        name: "recv",
+        signature: Type::function(vec![Type::channel(Type::Unknown)], Type::Unknown),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let id = expect_channel(&args[0])?;
+            recv(id, ctx.compiler)
+        },
+    }
+}
+
+/// Creates the `close` builtin, which closes a channel.
+pub fn builtin_close() -> BuiltinFn {
+    BuiltinFn {
+        name: "close",
+        signature: Type::function(vec![Type::channel(Type::Unknown)], Type::Nil),
+        func: |args, ctx| {
+            if args.len() != 1 {
+                return Err(Diagnostic::arity(1, args.len()));
+            }
+            let id = expect_channel(&args[0])?;
+            ctx.compiler.executor_mut().close(id)?;
+            Ok(Value::Nil)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadenza_syntax::parse::parse;
+
+    #[test]
+    fn tasks_start_in_spawn_order() {
+        let root = parse("1\n2\n").ast();
+        let mut items = root.items();
+        let mut executor = Executor::new();
+        let a = executor.spawn(items.next().unwrap(), Env::new());
+        let b = executor.spawn(items.next().unwrap(), Env::new());
+
+        assert_eq!(executor.num_pending(), 2);
+        let (first, _, _) = executor.start_next().unwrap();
+        assert_eq!(first, a);
+        assert!(executor.result(a).is_none());
+        executor.finish(a, Ok(Value::Integer(1)));
+        assert_eq!(executor.result(a).unwrap().unwrap(), Value::Integer(1));
+
+        let (second, _, _) = executor.start_next().unwrap();
+        assert_eq!(second, b);
+        assert!(executor.start_next().is_none());
+    }
+
+    #[test]
+    fn channels_are_bounded() {
+        let mut executor = Executor::new();
+        let ch = executor.channel(1);
+
+        assert!(executor.try_send(ch, Value::Integer(1)).unwrap().is_ok());
+        assert_eq!(
+            executor.try_send(ch, Value::Integer(2)).unwrap(),
+            Err(Value::Integer(2))
+        );
+        assert_eq!(executor.try_recv(ch).unwrap(), Some(Value::Integer(1)));
+        assert_eq!(executor.try_recv(ch).unwrap(), None);
+
+        executor.close(ch).unwrap();
+        assert!(executor.try_send(ch, Value::Integer(3)).is_err());
+    }
+}
@@ -3,7 +3,9 @@
 //! This module provides helper functions for testing evaluation, including
 //! evaluating source strings and collecting results and diagnostics.
 
-use crate::{compiler::Compiler, diagnostic::Diagnostic, env::Env, value::Value};
+use crate::{
+    compiler::Compiler, diagnostic::Diagnostic, env::Env, module_graph::ModuleGraph, value::Value,
+};
 use cadenza_syntax::parse::parse;
 
 /// The result of evaluating a source string, including both values and diagnostics.
@@ -115,3 +117,140 @@ pub fn wat(src: &str) -> String {
         "No IR generated".to_string()
     }
 }
+
+/// Builds a module graph from `(name, source)` pairs.
+fn module_graph(modules: &[(&str, &str)]) -> ModuleGraph {
+    let mut graph = ModuleGraph::new();
+    for (name, src) in modules {
+        graph.add(*name, *src);
+    }
+    graph
+}
+
+/// Evaluate a set of modules as one compilation unit and return all values and diagnostics.
+///
+/// Values are listed in evaluation order (dependencies first).
+pub fn eval_modules(modules: &[(&str, &str)]) -> EvalResult {
+    let mut env = Env::with_standard_builtins();
+    let mut compiler = Compiler::new();
+
+    let values = module_graph(modules)
+        .eval(&mut env, &mut compiler)
+        .into_iter()
+        .flat_map(|(_, values)| values)
+        .collect();
+    let diagnostics = compiler.take_diagnostics();
+
+    EvalResult {
+        values,
+        diagnostics,
+    }
+}
+
+/// Evaluate a set of modules with IR generation enabled and return the linked IR module as a string.
+pub fn ir_modules(modules: &[(&str, &str)]) -> String {
+    let mut env = Env::with_standard_builtins();
+    let mut compiler = Compiler::with_ir();
+
+    let _values = module_graph(modules).eval(&mut env, &mut compiler);
+
+    if let Some(ir_module) = compiler.build_ir_module() {
+        ir_module.to_string()
+    } else {
+        "No IR generated".to_string()
+    }
+}
+
+/// Evaluate a set of modules with IR generation enabled and return the WAT for the linked module.
+pub fn wat_modules(modules: &[(&str, &str)]) -> String {
+    let mut env = Env::with_standard_builtins();
+    let mut compiler = Compiler::with_ir();
+
+    let _values = module_graph(modules).eval(&mut env, &mut compiler);
+
+    if let Some(ir_module) = compiler.build_ir_module() {
+        match crate::ir::generate_wat(&ir_module) {
+            Ok(wat) => wat,
+            Err(e) => format!("WAT generation error: {}", e),
+        }
+    } else {
+        "No IR generated".to_string()
+    }
+}
+
+/// Evaluate a source string with IR generation enabled and render every
+/// diagnostic it reports, from parsing, evaluation and type checking.
+///
+/// Each diagnostic is rendered with its level, code, message and location,
+/// followed by its source line with the span underlined, so changes to the
+/// wording or span of an error show up in its snapshot.
+pub fn diagnostics(src: &str) -> String {
+    let parsed = parse(src);
+    let diagnostics: Vec<Diagnostic> = if parsed.errors.is_empty() {
+        let root = parsed.ast();
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::with_ir();
+        let _values = crate::eval(&root, &mut env, &mut compiler);
+        compiler.take_diagnostics()
+    } else {
+        parsed
+            .errors
+            .into_iter()
+            .map(|err| *Box::<Diagnostic>::from(err))
+            .collect()
+    };
+
+    if diagnostics.is_empty() {
+        return "No diagnostics".to_string();
+    }
+    diagnostics
+        .iter()
+        .map(|diagnostic| render(src, diagnostic))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders `diagnostic`, reported in `src`, for [`diagnostics`].
+fn render(src: &str, diagnostic: &Diagnostic) -> String {
+    let level = if diagnostic.is_error() {
+        "error"
+    } else if diagnostic.is_warning() {
+        "warning"
+    } else {
+        "hint"
+    };
+    let mut out = format!(
+        "{level}[{}]: {}\n",
+        diagnostic.kind().code(),
+        diagnostic.kind()
+    );
+
+    if let Some(span) = diagnostic.span {
+        let start = span.start.min(src.len());
+        let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+        let line = src[..start].matches('\n').count() + 1;
+        let column = start - line_start;
+        // A span running past the end of its first line is underlined to there
+        let width = span.end.min(line_end).saturating_sub(start).max(1);
+        let gutter = " ".repeat(line.to_string().len());
+
+        out.push_str(&format!("{gutter}--> {line}:{}\n", column + 1));
+        out.push_str(&format!("{gutter} |\n"));
+        out.push_str(&format!("{line} | {}\n", &src[line_start..line_end]));
+        out.push_str(&format!(
+            "{gutter} | {}{}\n",
+            " ".repeat(column),
+            "^".repeat(width)
+        ));
+    }
+    if let Some(help) = diagnostic.kind().help() {
+        out.push_str(&format!("  = help: {help}\n"));
+    }
+
+    for frame in &diagnostic.stack_trace {
+        let name = frame.name.as_deref().unwrap_or("<anonymous>");
+        out.push_str(&format!("  = in {name}\n"));
+    }
+    out
+}
@@ -308,7 +308,7 @@ fn test_comparison_type_mismatch_errors() {
     // Test == operator should error on type mismatch
     let result = eval_all("1 == \"hello\"");
     assert_eq!(result.values.len(), 1);
-    assert_eq!(result.values[0], Value::Nil); // Error returns Nil
+    assert_eq!(result.values[0], Value::Error); // Error returns Error
     assert_eq!(result.diagnostics.len(), 1);
     assert!(matches!(
         result.diagnostics[0].kind,
@@ -318,7 +318,7 @@ fn test_comparison_type_mismatch_errors() {
     // Test != operator should error on type mismatch
     let result = eval_all("1 != \"world\"");
     assert_eq!(result.values.len(), 1);
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert_eq!(result.diagnostics.len(), 1);
     assert!(matches!(
         result.diagnostics[0].kind,
@@ -328,7 +328,7 @@ fn test_comparison_type_mismatch_errors() {
     // Test < operator should error on non-numeric type
     let result = eval_all("\"foo\" < 5");
     assert_eq!(result.values.len(), 1);
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert_eq!(result.diagnostics.len(), 1);
     assert!(matches!(
         result.diagnostics[0].kind,
@@ -338,7 +338,7 @@ fn test_comparison_type_mismatch_errors() {
     // Test <= operator should error on non-numeric type
     let result = eval_all("\"bar\" <= 10");
     assert_eq!(result.values.len(), 1);
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert_eq!(result.diagnostics.len(), 1);
     assert!(matches!(
         result.diagnostics[0].kind,
@@ -348,7 +348,7 @@ fn test_comparison_type_mismatch_errors() {
     // Test > operator should error on type mismatch
     let result = eval_all("5 > \"hello\"");
     assert_eq!(result.values.len(), 1);
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert_eq!(result.diagnostics.len(), 1);
     assert!(matches!(
         result.diagnostics[0].kind,
@@ -358,7 +358,7 @@ fn test_comparison_type_mismatch_errors() {
     // Test >= operator should error on type mismatch
     let result = eval_all("10 >= \"world\"");
     assert_eq!(result.values.len(), 1);
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert_eq!(result.diagnostics.len(), 1);
     assert!(matches!(
         result.diagnostics[0].kind,
@@ -368,7 +368,7 @@ fn test_comparison_type_mismatch_errors() {
     // Test that mixing integers and floats now also errors (strongly typed)
     let result = eval_all("1 < 2.5");
     assert_eq!(result.values.len(), 1);
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert_eq!(result.diagnostics.len(), 1);
     assert!(matches!(
         result.diagnostics[0].kind,
@@ -376,11 +376,11 @@ fn test_comparison_type_mismatch_errors() {
     ));
 
     let result = eval_all("1 == 1.0");
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert!(!result.diagnostics.is_empty());
 
     let result = eval_all("1.0 != 1");
-    assert_eq!(result.values[0], Value::Nil);
+    assert_eq!(result.values[0], Value::Error);
     assert!(!result.diagnostics.is_empty());
 }
 
@@ -443,6 +443,36 @@ fn test_type_inference_with_environment() {
     assert_eq!(inferred.unwrap(), InferType::Concrete(Type::Integer));
 }
 
+#[test]
+fn test_type_inference_tuples() {
+    use crate::typeinfer::{InferType, TypeEnv};
+
+    let mut compiler = Compiler::new();
+    let env = TypeEnv::new();
+
+    let parsed = parse("(1, (\"two\", 3.0))");
+    let root = parsed.ast();
+    let items: Vec<_> = root.items().collect();
+    let inferred = compiler.type_inferencer_mut().infer_expr(&items[0], &env);
+    assert_eq!(
+        inferred.unwrap(),
+        InferType::Tuple(vec![
+            InferType::Concrete(Type::Integer),
+            InferType::Tuple(vec![
+                InferType::Concrete(Type::String),
+                InferType::Concrete(Type::Float)
+            ]),
+        ])
+    );
+
+    // Projections take the element's type, `.1.0` indexing twice
+    let parsed = parse("(1, (\"two\", 3.0)).1.0");
+    let root = parsed.ast();
+    let items: Vec<_> = root.items().collect();
+    let inferred = compiler.type_inferencer_mut().infer_expr(&items[0], &env);
+    assert_eq!(inferred.unwrap(), InferType::Concrete(Type::String));
+}
+
 #[test]
 fn test_type_inference_function_application() {
     use crate::typeinfer::InferType;
@@ -650,3 +680,117 @@ fn test_type_inference_error_node() {
         }
     }
 }
+
+#[test]
+fn test_type_inference_unit() {
+    use crate::typeinfer::InferType;
+
+    let mut compiler = Compiler::new();
+    let mut env = crate::typeinfer::TypeEnv::from_env(&Env::with_standard_builtins());
+
+    // print has type String -> Nil, as the host import does
+    let print: InternedString = "print".into();
+    env.insert(
+        print,
+        InferType::Fn(
+            vec![InferType::Concrete(Type::String)],
+            Box::new(InferType::Concrete(Type::Nil)),
+        ),
+    );
+
+    // Discarding a result, the unit literal and blocks ending with a binding
+    // are all nil, while a block's earlier bindings stay in scope
+    for (source, ty) in [
+        ("let _ = print \"hi\"", Type::Nil),
+        ("()", Type::Nil),
+        ("let y =\n    let _ = print \"a\"\n", Type::Nil),
+        ("let y =\n    let _ = print \"a\"\n    ()\n", Type::Nil),
+        (
+            "let y =\n    let n = 1\n    let _ = print \"a\"\n    n\n",
+            Type::Integer,
+        ),
+    ] {
+        let parsed = parse(source);
+        let root = parsed.ast();
+        let items: Vec<_> = root.items().collect();
+        let inferred = compiler.type_inferencer_mut().infer_expr(&items[0], &env);
+        assert_eq!(inferred.unwrap(), InferType::Concrete(ty), "{source}");
+    }
+}
+
+#[test]
+fn test_type_inference_enum_variants() {
+    use crate::typeinfer::InferType;
+
+    let mut env = Env::with_standard_builtins();
+    let mut compiler = Compiler::new();
+    let definition = parse("enum Shape = Circle r | Rect Integer Integer | Empty");
+    let results = crate::eval(&definition.ast(), &mut env, &mut compiler);
+    assert!(!compiler.has_errors(), "{:?}", compiler.diagnostics());
+    let Value::Type(shape) = &results[0] else {
+        panic!("Expected the enum type, got {:?}", results[0]);
+    };
+    let type_env = crate::typeinfer::TypeEnv::from_env(&env);
+
+    // Constructing any variant gives the enum, whatever its untyped fields
+    for source in ["Circle 2", "Circle \"big\"", "Rect 1 2", "Empty"] {
+        let parsed = parse(source);
+        let root = parsed.ast();
+        let items: Vec<_> = root.items().collect();
+        let inferred = compiler
+            .type_inferencer_mut()
+            .infer_expr(&items[0], &type_env);
+        assert_eq!(
+            inferred.unwrap(),
+            InferType::Concrete(shape.clone()),
+            "{source}"
+        );
+    }
+
+    // Typed fields are checked
+    let parsed = parse("Rect 1 \"wide\"");
+    let items: Vec<_> = parsed.ast().items().collect();
+    assert!(
+        compiler
+            .type_inferencer_mut()
+            .infer_expr(&items[0], &type_env)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_type_inference_record_update() {
+    use crate::typeinfer::{InferType, TypeEnv, TypeVar};
+
+    let mut compiler = Compiler::new();
+    let mut env = TypeEnv::new();
+    let point = InferType::Record(vec![
+        ("x".into(), InferType::Concrete(Type::Integer)),
+        ("label".into(), InferType::Concrete(Type::String)),
+    ]);
+    env.insert("point".into(), point.clone());
+    // A record whose fields aren't known yet, like a function parameter
+    let row = InferType::Var(TypeVar::new(100));
+    env.insert("r".into(), row.clone());
+
+    let mut infer = |source: &str| {
+        let parsed = parse(source);
+        let items: Vec<_> = parsed.ast().items().collect();
+        compiler.type_inferencer_mut().infer_expr(&items[0], &env)
+    };
+
+    // Literals take their fields' types; an update takes its base's
+    assert_eq!(
+        infer("{ x = 1, ok = \"yes\" }").unwrap(),
+        InferType::Record(vec![
+            ("x".into(), InferType::Concrete(Type::Integer)),
+            ("ok".into(), InferType::Concrete(Type::String)),
+        ])
+    );
+    assert_eq!(infer("{ point | x = 2 }").unwrap(), point);
+    assert_eq!(infer("{ r | x = 2 }").unwrap(), row);
+
+    // Updated fields must exist and keep their types
+    assert!(infer("{ point | y = 2 }").is_err());
+    assert!(infer("{ point | x = \"two\" }").is_err());
+}
@@ -0,0 +1,662 @@
+//! Termination checking for `@total` functions.
+//!
+//! `@total` before a function asks the compiler to check that every call to
+//! it returns. This matters most for functions that macros run at compile
+//! time, where a call that never returns hangs the build rather than the
+//! program.
+//!
+//! The check is syntactic and conservative: it proves termination for the
+//! common shapes of recursion and warns (`W0004`) about the rest. Each call
+//! the function makes to itself needs an argument that gets smaller:
+//!
+//! - a part of the parameter in its place, bound by a destructuring pattern
+//!   (a parameter pattern, or a `match` on the parameter), or `tail` of it
+//! - the parameter minus a positive integer literal, where a condition on
+//!   the way to the call bounds the parameter from below, or divided by a
+//!   literal of at least 2 where it is at least 1
+//! - the parameter plus a positive integer literal, where a condition bounds
+//!   it from above
+//!
+//! A bound is an integer literal or a parameter the call passes on
+//! unchanged. The conditions are those of `if` and of `match` on a boolean,
+//! joined with `&&` and `||`:
+//!
+//! ```cadenza
+//! @total
+//! fn fact n = if n <= 1 then 1 else n * (fact (n - 1))
+//!
+//! @total
+//! fn count_up i end = match i < end
+//!     true => (count_up (i + 1) end)
+//!     false => i
+//!
+//! @total
+//! fn countdown n = if n == 0 then 0 else countdown (n - 1)
+//! # warning: countdown may not terminate: `n - 1` gets smaller, but
+//! # nothing bounds n from below there
+//! ```
+//!
+//! The check doesn't follow calls through other functions, so a function
+//! that calls itself through another one, or uses itself as a value, is
+//! reported too.
+
+use crate::{
+    compiler::Compiler,
+    diagnostic::{BoxedDiagnosticExt, Diagnostic},
+    eval::{extract_identifier, literal_value},
+    interner::InternedString,
+    pattern::{Pattern, PatternKind},
+    special_form::{if_form, match_form},
+    value::{UserFunction, Value},
+};
+use cadenza_syntax::{ast::Expr, span::Span};
+use std::collections::{HashMap, HashSet};
+
+/// Returns a warning for each way `function` may fail to terminate.
+///
+/// Other functions are looked up in `compiler`, to find calls back to
+/// `function` through them.
+pub fn check(function: &UserFunction, compiler: &Compiler) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        function,
+        warnings: Vec::new(),
+    };
+    let mut scope = Scope {
+        params: function.params.iter().copied().map(Some).collect(),
+        ..Scope::default()
+    };
+    for (param, pattern) in &function.patterns {
+        let index = function.params.iter().position(|p| p == param);
+        if let (Some(index), Ok(pattern)) = (index, Pattern::parse(pattern)) {
+            scope.destructure(index, &pattern);
+        }
+    }
+    checker.walk(&function.body, &mut scope, compiler);
+    checker.check_mutual_recursion(compiler);
+    checker.warnings
+}
+
+/// A bound on a parameter: an integer literal, or another parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Literal(i64),
+    Param(usize),
+}
+
+/// What a condition tells about a parameter where it holds.
+#[derive(Debug, Clone, Copy)]
+struct Fact {
+    param: usize,
+    /// Whether the parameter is above (`true`) or below the bound.
+    above: bool,
+    /// Whether the parameter can't equal the bound.
+    strict: bool,
+    bound: Bound,
+}
+
+/// The names in scope at a point in the function body.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    /// The parameters, or `None` where a binding shadows one.
+    params: Vec<Option<InternedString>>,
+    /// Variables bound to a part of a parameter, by parameter index.
+    smaller: HashMap<InternedString, usize>,
+    facts: Vec<Fact>,
+}
+
+impl Scope {
+    /// Returns the index of the parameter `expr` names.
+    fn param(&self, expr: &Expr) -> Option<usize> {
+        let Expr::Ident(_) = expr else {
+            return None;
+        };
+        let name = extract_identifier(expr)?;
+        self.params.iter().position(|param| *param == Some(name))
+    }
+
+    /// Returns the index of the parameter `expr` is the parameter or a part
+    /// of.
+    fn part_of(&self, expr: &Expr) -> Option<(usize, bool)> {
+        if let Some(index) = self.param(expr) {
+            return Some((index, false));
+        }
+        let Expr::Ident(_) = expr else {
+            return None;
+        };
+        let name = extract_identifier(expr)?;
+        self.smaller.get(&name).map(|index| (*index, true))
+    }
+
+    /// Binds the variables of `pattern`, taking apart parameter `index`.
+    ///
+    /// A destructuring pattern binds parts of the parameter; a plain
+    /// variable is no smaller than it.
+    fn destructure(&mut self, index: usize, pattern: &Pattern) {
+        match pattern.kind {
+            PatternKind::Record(_)
+            | PatternKind::Tuple(_)
+            | PatternKind::List(_)
+            | PatternKind::Variant(..) => {
+                for name in pattern.variables() {
+                    self.shadow(name);
+                    self.smaller.insert(name, index);
+                }
+            }
+            _ => self.bind(pattern),
+        }
+    }
+
+    /// Binds the variables of `pattern` to values unrelated to the
+    /// parameters.
+    fn bind(&mut self, pattern: &Pattern) {
+        for name in pattern.variables() {
+            self.shadow(name);
+        }
+    }
+
+    /// Forgets what was known about `name`, which a binding now shadows.
+    fn shadow(&mut self, name: InternedString) {
+        self.smaller.remove(&name);
+        if let Some(index) = self.params.iter().position(|param| *param == Some(name)) {
+            self.params[index] = None;
+            self.facts
+                .retain(|fact| fact.param != index && fact.bound != Bound::Param(index));
+        }
+    }
+
+    /// Records what `condition` tells about the parameters where it is
+    /// `holds`.
+    fn assume(&mut self, condition: &Expr, holds: bool) {
+        let Expr::Apply(apply) = condition else {
+            return;
+        };
+        let Some(op) = apply
+            .callee()
+            .and_then(|callee| extract_identifier(&callee))
+        else {
+            return;
+        };
+        let Ok([lhs, rhs]) = <[Expr; 2]>::try_from(apply.all_arguments()) else {
+            return;
+        };
+        // `a && b` holding, or `a || b` failing, tells both
+        match (&*op, holds) {
+            ("&&", true) | ("||", false) => {
+                self.assume(&lhs, holds);
+                self.assume(&rhs, holds);
+                return;
+            }
+            _ => {}
+        }
+        // `lhs > rhs` where it holds, or where `lhs <= rhs` fails
+        let (above, strict) = match (&*op, holds) {
+            (">", true) | ("<=", false) => (true, true),
+            (">=", true) | ("<", false) => (true, false),
+            ("<", true) | (">=", false) => (false, true),
+            ("<=", true) | (">", false) => (false, false),
+            _ => return,
+        };
+        let mut facts = Vec::new();
+        if let (Some(param), Some(bound)) = (self.param(&lhs), self.bound(&rhs)) {
+            facts.push(Fact {
+                param,
+                above,
+                strict,
+                bound,
+            });
+        }
+        if let (Some(param), Some(bound)) = (self.param(&rhs), self.bound(&lhs)) {
+            facts.push(Fact {
+                param,
+                above: !above,
+                strict,
+                bound,
+            });
+        }
+        self.facts.extend(facts);
+    }
+
+    /// Returns `expr` as a bound: an integer literal or a parameter.
+    fn bound(&self, expr: &Expr) -> Option<Bound> {
+        match expr {
+            Expr::Literal(literal) => match literal_value(literal) {
+                Ok(Value::Integer(n)) => Some(Bound::Literal(n)),
+                _ => None,
+            },
+            _ => self.param(expr).map(Bound::Param),
+        }
+    }
+}
+
+struct Checker<'a> {
+    function: &'a UserFunction,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn warn(&mut self, reason: String, span: Span) {
+        let warning = Diagnostic::may_not_terminate(self.function.name, reason).with_span(span);
+        self.warnings.push(*warning);
+    }
+
+    /// Returns whether `expr` names the function being checked.
+    fn is_self(&self, expr: &Expr) -> bool {
+        matches!(expr, Expr::Ident(_))
+            && extract_identifier(expr).is_some_and(|name| name == self.function.name)
+    }
+
+    /// Checks the calls to the function in `expr`, where `scope` holds.
+    fn walk(&mut self, expr: &Expr, scope: &mut Scope, compiler: &Compiler) {
+        let Expr::Apply(apply) = expr else {
+            if self.is_self(expr) {
+                if self.function.params.is_empty() {
+                    self.check_call(expr, &[], scope);
+                } else {
+                    let reason = "it refers to itself other than in a call, which the check \
+                                  can't follow";
+                    self.warn(reason.into(), expr.span());
+                }
+            }
+            return;
+        };
+        let Some(callee) = apply.callee() else {
+            return;
+        };
+        let args = apply.all_arguments();
+        if self.is_self(&callee) {
+            self.check_call(expr, &args, scope);
+            for arg in &args {
+                self.walk(arg, &mut scope.clone(), compiler);
+            }
+            return;
+        }
+        match extract_identifier(&callee).as_deref() {
+            Some("if") => self.walk_if(&args, scope, compiler),
+            Some("match") => self.walk_match(&args, scope, compiler),
+            Some("__block__") => {
+                // Bindings in a block shadow names for the rest of it
+                let mut block = scope.clone();
+                for arg in &args {
+                    self.walk(arg, &mut block, compiler);
+                }
+            }
+            Some("=") => self.walk_binding(&args, scope, compiler),
+            Some("->") => {
+                // A lambda's parameters shadow names in its body
+                if let [params, body] = args.as_slice() {
+                    let mut inner = scope.clone();
+                    for name in identifiers(params) {
+                        inner.shadow(name);
+                    }
+                    self.walk(body, &mut inner, compiler);
+                }
+            }
+            _ => {
+                self.walk(&callee, &mut scope.clone(), compiler);
+                for arg in &args {
+                    self.walk(arg, &mut scope.clone(), compiler);
+                }
+            }
+        }
+    }
+
+    /// Walks the parts of a run of expressions, such as `then f x`, which
+    /// applies the first to the rest.
+    fn walk_part(&mut self, part: &[Expr], scope: &Scope, compiler: &Compiler) {
+        match part {
+            [callee, args @ ..] if self.is_self(callee) && !args.is_empty() => {
+                let span = Span::new(callee.span().start, part[part.len() - 1].span().end);
+                // The arguments were written in parentheses
+                let text: Vec<_> = part
+                    .iter()
+                    .map(|expr| match expr {
+                        Expr::Apply(_) => format!("({})", expr.syntax().text()),
+                        _ => expr.syntax().text().to_string(),
+                    })
+                    .collect();
+                self.check_call_at(span, &text.join(" "), args, scope);
+                for arg in args {
+                    self.walk(arg, &mut scope.clone(), compiler);
+                }
+            }
+            _ => {
+                for expr in part {
+                    self.walk(expr, &mut scope.clone(), compiler);
+                }
+            }
+        }
+    }
+
+    fn walk_if(&mut self, args: &[Expr], scope: &Scope, compiler: &Compiler) {
+        let Ok(parts) = if_form::split(args) else {
+            return self.walk_part(args, scope, compiler);
+        };
+        self.walk_part(parts.condition, scope, compiler);
+        let condition = match parts.condition {
+            [condition] => Some(condition),
+            _ => None,
+        };
+        let mut consequent = scope.clone();
+        let mut alternative = scope.clone();
+        if let Some(condition) = condition {
+            consequent.assume(condition, true);
+            alternative.assume(condition, false);
+        }
+        self.walk_part(parts.consequent, &consequent, compiler);
+        if let Some(part) = parts.alternative {
+            self.walk_part(part, &alternative, compiler);
+        }
+    }
+
+    fn walk_match(&mut self, args: &[Expr], scope: &Scope, compiler: &Compiler) {
+        let Some((scrutinee, arms)) = args.split_first() else {
+            return;
+        };
+        self.walk(scrutinee, &mut scope.clone(), compiler);
+        let Ok(arms) = match_form::match_arms(arms, compiler.units()) else {
+            return self.walk_part(arms, scope, compiler);
+        };
+        for match_form::Arm {
+            pattern,
+            guard,
+            result,
+        } in arms
+        {
+            let mut arm = scope.clone();
+            match (&pattern.kind, scope.part_of(scrutinee)) {
+                (PatternKind::Literal(Value::Bool(holds)), _) => arm.assume(scrutinee, *holds),
+                (_, Some((index, _))) => arm.destructure(index, &pattern),
+                _ => arm.bind(&pattern),
+            }
+            // The result is only reached when the guard holds
+            if let Some(guard) = &guard {
+                self.walk(guard, &mut arm.clone(), compiler);
+                arm.assume(guard, true);
+            }
+            self.walk(&result, &mut arm, compiler);
+        }
+    }
+
+    /// Walks `name = value` or `let name = value`, after which `name`
+    /// shadows what it named before.
+    fn walk_binding(&mut self, args: &[Expr], scope: &mut Scope, compiler: &Compiler) {
+        let [target, value] = args else {
+            for arg in args {
+                self.walk(arg, &mut scope.clone(), compiler);
+            }
+            return;
+        };
+        let (form, names) = match target {
+            Expr::Apply(apply) => (
+                apply
+                    .callee()
+                    .and_then(|callee| extract_identifier(&callee)),
+                apply.all_arguments(),
+            ),
+            _ => (None, vec![target.clone()]),
+        };
+        let name = match (form.as_deref(), names.as_slice()) {
+            (None | Some("let"), [name @ Expr::Ident(_)]) => extract_identifier(name),
+            // A nested function's parameters shadow names in its body
+            (Some("fn"), [name, params @ ..]) => {
+                let mut inner = scope.clone();
+                for param in params.iter().flat_map(identifiers) {
+                    inner.shadow(param);
+                }
+                self.walk(value, &mut inner, compiler);
+                extract_identifier(name)
+            }
+            _ => None,
+        };
+        if form.as_deref() != Some("fn") {
+            self.walk(value, &mut scope.clone(), compiler);
+        }
+        let Some(name) = name else {
+            return;
+        };
+        let part = self.part(value, scope);
+        scope.shadow(name);
+        if let Some(index) = part {
+            scope.smaller.insert(name, index);
+        }
+    }
+
+    /// Returns the index of the parameter `expr` is a part of: a variable
+    /// bound to a part, or `tail` of the parameter or a part.
+    fn part(&self, expr: &Expr, scope: &Scope) -> Option<usize> {
+        if let Some((index, true)) = scope.part_of(expr) {
+            return Some(index);
+        }
+        let Expr::Apply(apply) = expr else {
+            return None;
+        };
+        let callee = extract_identifier(&apply.callee()?)?;
+        match (&*callee, apply.all_arguments().as_slice()) {
+            ("tail", [list]) => scope.part_of(list).map(|(index, _)| index),
+            _ => None,
+        }
+    }
+
+    fn check_call(&mut self, call: &Expr, args: &[Expr], scope: &Scope) {
+        let text = call.syntax().text().to_string();
+        self.check_call_at(call.span(), text.trim(), args, scope);
+    }
+
+    /// Warns unless an argument of the call `text` at `span` gets smaller.
+    fn check_call_at(&mut self, span: Span, text: &str, args: &[Expr], scope: &Scope) {
+        let mut hint = None;
+        for (index, arg) in args.iter().enumerate() {
+            match self.decreases(index, arg, args, scope) {
+                Ok(()) => return,
+                Err(reason) => hint = hint.or(reason),
+            }
+        }
+        let reason = hint
+            .unwrap_or_else(|| format!("no argument of the recursive call `{text}` gets smaller"));
+        self.warn(reason, span);
+    }
+
+    /// Returns `Ok` if `arg`, passed for parameter `index`, is smaller than
+    /// the parameter, or else why the argument might not be, if it looks
+    /// like it was meant to be.
+    fn decreases(
+        &self,
+        index: usize,
+        arg: &Expr,
+        args: &[Expr],
+        scope: &Scope,
+    ) -> Result<(), Option<String>> {
+        if self.part(arg, scope) == Some(index) {
+            return Ok(());
+        }
+        let Some(param) = scope.params.get(index).copied().flatten() else {
+            return Err(None);
+        };
+        let Expr::Apply(apply) = arg else {
+            return Err(None);
+        };
+        let Some(op) = apply
+            .callee()
+            .and_then(|callee| extract_identifier(&callee))
+        else {
+            return Err(None);
+        };
+        let step = |expr: &Expr| match scope.bound(expr) {
+            Some(Bound::Literal(n)) => Some(n),
+            _ => None,
+        };
+        let operands = apply.all_arguments();
+        // Whether the argument goes down, and whether it only does from 1 up
+        let (above, positive) = match (&*op, operands.as_slice()) {
+            ("-", [lhs, rhs]) if scope.param(lhs) == Some(index) && step(rhs) > Some(0) => {
+                (true, false)
+            }
+            ("/", [lhs, rhs]) if scope.param(lhs) == Some(index) && step(rhs) >= Some(2) => {
+                (true, true)
+            }
+            ("+", [lhs, rhs])
+                if (scope.param(lhs) == Some(index) && step(rhs) > Some(0))
+                    || (scope.param(rhs) == Some(index) && step(lhs) > Some(0)) =>
+            {
+                (false, false)
+            }
+            _ => return Err(None),
+        };
+
+        // A parameter bound is only fixed if the call passes it on
+        let unchanged = |bound: usize| {
+            bound != index
+                && args
+                    .get(bound)
+                    .is_some_and(|arg| scope.param(arg) == Some(bound))
+        };
+        let bounded = scope.facts.iter().any(|fact| {
+            fact.param == index
+                && fact.above == above
+                && match fact.bound {
+                    Bound::Param(bound) => !positive && unchanged(bound),
+                    Bound::Literal(_) if !positive => true,
+                    Bound::Literal(n) if fact.strict => n >= 0,
+                    Bound::Literal(n) => n >= 1,
+                }
+        });
+        if bounded {
+            return Ok(());
+        }
+        let direction = match (above, positive) {
+            (true, false) => "from below",
+            (true, true) => "to at least 1",
+            (false, _) => "from above",
+        };
+        Err(Some(format!(
+            "`{}` gets {}, but nothing bounds {param} {direction} there",
+            arg.syntax().text().to_string().trim(),
+            if above { "smaller" } else { "larger" },
+        )))
+    }
+
+    /// Warns if the function calls itself through other functions, which
+    /// the check doesn't follow.
+    fn check_mutual_recursion(&mut self, compiler: &Compiler) {
+        let name = self.function.name;
+        for (callee, span) in references(&self.function.body) {
+            if callee == name {
+                continue;
+            }
+            let mut seen = HashSet::from([name, callee]);
+            let mut pending = vec![callee];
+            while let Some(next) = pending.pop() {
+                let Some(Value::UserFunction(function)) = compiler.get_var(next) else {
+                    continue;
+                };
+                for (reference, _) in references(&function.body) {
+                    if reference == name {
+                        let reason = format!(
+                            "it calls itself through {callee}, which the check can't follow"
+                        );
+                        return self.warn(reason, span);
+                    }
+                    if seen.insert(reference) {
+                        pending.push(reference);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns every identifier mentioned in `expr`, with its span.
+fn references(expr: &Expr) -> Vec<(InternedString, Span)> {
+    let mut found = Vec::new();
+    collect_references(expr, &mut found);
+    found
+}
+
+fn collect_references(expr: &Expr, found: &mut Vec<(InternedString, Span)>) {
+    match expr {
+        Expr::Ident(ident) => found.push((ident.syntax().text().interned(), expr.span())),
+        Expr::Apply(apply) => {
+            if let Some(callee) = apply.callee() {
+                collect_references(&callee, found);
+            }
+            for arg in apply.all_arguments() {
+                collect_references(&arg, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the identifiers in `expr`, such as a lambda's parameters.
+fn identifiers(expr: &Expr) -> Vec<InternedString> {
+    references(expr).into_iter().map(|(name, _)| name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, DiagnosticKind, Env};
+    use cadenza_syntax::parse::parse;
+
+    /// Returns the reason of each termination warning for `src`.
+    fn reasons(src: &str) -> Vec<String> {
+        let mut env = Env::with_standard_builtins();
+        let mut compiler = Compiler::new();
+        crate::eval(&parse(src).ast(), &mut env, &mut compiler);
+        compiler
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| match diagnostic.kind() {
+                DiagnosticKind::MayNotTerminate { reason, .. } => reason.clone(),
+                other => panic!("unexpected diagnostic {other}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn accepts_recursion_toward_a_bound() {
+        let src = "\
+@total
+fn down n = if n > 0 && n < 100 then down (n - 2) else 0
+@total
+fn up i end = if end <= i then i else up (i + 1) end
+@total
+fn halve n = if 1 > n then 0 else halve (n / 2)
+@total
+fn first (a, b) = match a
+    (x, y) => (first x)
+    _ => b
+";
+        assert_eq!(reasons(src), Vec::<String>::new());
+    }
+
+    #[test]
+    fn bounds_must_hold_at_the_call() {
+        let src = "\
+@total
+fn chase i end = if i < end then chase (i + 1) (end + 1) else i
+@total
+fn wrong n = if n > 0 then 0 else wrong (n - 1)
+";
+        assert_eq!(
+            reasons(src),
+            [
+                "`i + 1` gets larger, but nothing bounds i from above there",
+                "`n - 1` gets smaller, but nothing bounds n from below there",
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowed_parameters_are_not_the_parameter() {
+        let src = "\
+@total
+fn again n =
+    let n = n + 1
+    if n > 0 then again (n - 1) else 0
+";
+        assert_eq!(
+            reasons(src),
+            ["no argument of the recursive call `again (n - 1)` gets smaller"]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Expression-level tracing of evaluation.
+//!
+//! With a [`Tracer`] installed ([`Compiler::set_tracer`]), the evaluator
+//! logs each expression it evaluates, once its value is known, as one line:
+//!
+//! ```text
+//! 2:12 [depth 2] x * 2 => 6
+//! ```
+//!
+//! That is the line and column the expression starts at, the number of
+//! scopes in the environment while it ran, its source text, and its value (or
+//! `error: message` if it failed). Inner expressions finish first, so their
+//! lines come before the expressions containing them. A call in tail position
+//! is made after the expression ends, so it's traced by the expressions of
+//! the body it calls rather than by a line of its own.
+//!
+//! A [`TraceFilter`] limits the lines to expressions whose text contains a
+//! pattern, or that run at most some number of scopes deep.
+//!
+//! ```
+//! use cadenza_eval::{Compiler, Env, trace::{TraceFilter, Tracer}};
+//! use std::sync::{Arc, Mutex};
+//!
+//! #[derive(Clone, Default)]
+//! struct Shared(Arc<Mutex<Vec<u8>>>);
+//!
+//! impl std::io::Write for Shared {
+//!     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+//!         self.0.lock().unwrap().write(buf)
+//!     }
+//!     fn flush(&mut self) -> std::io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let out = Shared::default();
+//! let filter = TraceFilter { pattern: None, max_depth: Some(2) };
+//! let mut compiler = Compiler::new();
+//! compiler.set_tracer(Some(Tracer::new(filter, out.clone())));
+//!
+//! let root = cadenza_syntax::parse::parse("fn double x =\n    x * 2\ndouble 3\n").ast();
+//! cadenza_eval::eval(&root, &mut Env::with_standard_builtins(), &mut compiler);
+//!
+//! let log = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+//! assert!(log.contains("2:5 [depth 2] x * 2 => 6\n"));
+//! assert!(log.ends_with("3:1 [depth 1] double 3 => 6\n"));
+//! ```
+//!
+//! [`Compiler::set_tracer`]: crate::Compiler::set_tracer
+
+use crate::{Value, diagnostic::Diagnostic};
+use cadenza_syntax::{SyntaxNode, ast::Expr};
+use std::{fmt, io::Write};
+
+/// The longest expression text or value written on a trace line, in
+/// characters; longer ones are cut short with `...`.
+const MAX_TEXT: usize = 60;
+
+/// Which evaluated expressions a [`Tracer`] logs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    /// Only log expressions whose source text contains this.
+    pub pattern: Option<String>,
+    /// Only log expressions evaluated with at most this many scopes in the
+    /// environment.
+    pub max_depth: Option<usize>,
+}
+
+impl TraceFilter {
+    /// Returns whether an expression with source `text`, evaluated `depth`
+    /// scopes deep, is logged.
+    pub fn matches(&self, text: &str, depth: usize) -> bool {
+        self.pattern
+            .as_deref()
+            .is_none_or(|pattern| text.contains(pattern))
+            && self.max_depth.is_none_or(|max| depth <= max)
+    }
+}
+
+/// Writes a line for each evaluated expression that passes its filter.
+pub struct Tracer {
+    filter: TraceFilter,
+    out: Box<dyn Write + Send>,
+    /// The root of the last traced expression and the offsets its lines
+    /// start at, since consecutive expressions usually share a file.
+    lines: Option<(SyntaxNode, Vec<usize>)>,
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("filter", &self.filter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tracer {
+    /// Creates a tracer writing the lines `filter` lets through to `out`.
+    pub fn new(filter: TraceFilter, out: impl Write + Send + 'static) -> Self {
+        Self {
+            filter,
+            out: Box::new(out),
+            lines: None,
+        }
+    }
+
+    /// Creates a tracer writing to standard error.
+    pub fn stderr(filter: TraceFilter) -> Self {
+        Self::new(filter, std::io::stderr())
+    }
+
+    /// Returns the filter.
+    pub fn filter(&self) -> &TraceFilter {
+        &self.filter
+    }
+
+    /// Logs the evaluation of `expr`, `depth` scopes deep, to `result`.
+    ///
+    /// Failing to write the line doesn't fail the evaluation, so the line is
+    /// dropped.
+    pub(crate) fn record(
+        &mut self,
+        expr: &Expr,
+        result: &Result<Value, Box<Diagnostic>>,
+        depth: usize,
+    ) {
+        let text = expr.syntax().text().to_string();
+        if !self.filter.matches(&text, depth) {
+            return;
+        }
+        let (line, column) = self.position(expr);
+        let outcome = match result {
+            Ok(value) => shorten(&value.to_string()),
+            Err(diagnostic) => format!("error: {}", shorten(&diagnostic.to_string())),
+        };
+        let _ = writeln!(
+            self.out,
+            "{line}:{column} [depth {depth}] {} => {outcome}",
+            shorten(&text)
+        );
+    }
+
+    /// Returns the 1-based line and column `expr` starts at in its file.
+    fn position(&mut self, expr: &Expr) -> (usize, usize) {
+        let mut root = expr.syntax();
+        while let Some(parent) = root.parent() {
+            root = parent;
+        }
+        let starts = match &mut self.lines {
+            Some((cached, starts)) if cached == root => starts,
+            lines => {
+                let text = root.text().to_string();
+                let starts = std::iter::once(0)
+                    .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+                    .collect();
+                &mut lines.insert((root.clone(), starts)).1
+            }
+        };
+        let offset = expr.span().start;
+        let line = starts.partition_point(|start| *start <= offset);
+        (line, offset - starts[line - 1] + 1)
+    }
+}
+
+/// Returns the first line of `text`, cut to [`MAX_TEXT`] characters.
+fn shorten(text: &str) -> String {
+    let first = text.lines().next().unwrap_or_default();
+    match first.char_indices().nth(MAX_TEXT) {
+        Some((end, _)) => format!("{}...", &first[..end]),
+        None if first.len() < text.trim_end().len() => format!("{first}..."),
+        None => first.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_pattern_and_depth() {
+        let filter = TraceFilter {
+            pattern: Some("double".to_string()),
+            max_depth: Some(2),
+        };
+        assert!(filter.matches("double 3", 1));
+        assert!(!filter.matches("double 3", 3));
+        assert!(!filter.matches("x * 2", 1));
+        assert!(TraceFilter::default().matches("anything", 100));
+    }
+
+    #[test]
+    fn shortens_long_and_multiline_text() {
+        assert_eq!(shorten("x * 2"), "x * 2");
+        assert_eq!(shorten("fn f x =\n    x"), "fn f x =...");
+        assert_eq!(shorten(&"a".repeat(70)), format!("{}...", "a".repeat(60)));
+    }
+}
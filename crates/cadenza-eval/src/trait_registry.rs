@@ -1,11 +1,28 @@
 //! Trait registry for storing and looking up trait definitions and implementations.
+//!
+//! # Coherence
+//!
+//! For each trait, the registry keeps at most one implementation that applies
+//! to any given type. `Type::Unknown` in an implementation's type is a
+//! wildcard, so `List(Unknown)` is a blanket implementation for every list.
+//! Two implementations *overlap* if some type matches both; overlapping
+//! implementations are only allowed when one is strictly more specific than
+//! the other, as `List(Integer)` is more specific than `List(Unknown)`.
+//!
+//! Lookups resolve to the most specific matching implementation, so the
+//! result doesn't depend on the order implementations were registered in.
+//!
+//! Implementations that record their module are also checked for orphans:
+//! implementing a trait from another module for a type from another module is
+//! allowed, but warned about, since two modules could do the same.
 
 use crate::{
-    diagnostic::{Diagnostic, Result},
+    diagnostic::{BoxedDiagnosticExt, Diagnostic, Result},
     interner::InternedString,
     map::Map,
     value::{MethodSignature, Type, Value},
 };
+use cadenza_syntax::span::Span;
 
 /// A trait definition.
 ///
@@ -19,6 +36,8 @@ pub struct TraitDef {
     pub methods: Vec<MethodSignature>,
     /// Type parameters for generic traits (currently unused, reserved for future).
     pub type_params: Vec<InternedString>,
+    /// The module that defines the trait, if known.
+    pub module: Option<InternedString>,
 }
 
 impl TraitDef {
@@ -28,6 +47,7 @@ impl TraitDef {
             name,
             methods,
             type_params: Vec::new(),
+            module: None,
         }
     }
 
@@ -41,8 +61,15 @@ impl TraitDef {
             name,
             methods,
             type_params,
+            module: None,
         }
     }
+
+    /// Sets the module that defines the trait.
+    pub fn with_module(mut self, module: InternedString) -> Self {
+        self.module = Some(module);
+        self
+    }
 }
 
 /// A trait implementation.
@@ -53,10 +80,15 @@ pub struct TraitImpl {
     /// The name of the trait being implemented.
     pub trait_name: InternedString,
     /// The type for which the trait is being implemented.
+    /// `Type::Unknown` anywhere in the type matches any type.
     pub for_type: Type,
     /// The method implementations.
     /// Maps method name to the implementation (a function value).
     pub methods: Map<Value>,
+    /// The span of the implementation's definition, if known.
+    pub span: Option<Span>,
+    /// The module containing the implementation, if known.
+    pub module: Option<InternedString>,
 }
 
 impl TraitImpl {
@@ -66,8 +98,33 @@ impl TraitImpl {
             trait_name,
             for_type,
             methods,
+            span: None,
+            module: None,
         }
     }
+
+    /// Sets the span of the implementation's definition.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Sets the module containing the implementation.
+    pub fn with_module(mut self, module: InternedString) -> Self {
+        self.module = Some(module);
+        self
+    }
+
+    /// Points `diagnostic` at this implementation's definition.
+    fn locate(&self, mut diagnostic: Box<Diagnostic>) -> Box<Diagnostic> {
+        if let Some(span) = self.span {
+            diagnostic = diagnostic.with_span(span);
+        }
+        if let Some(module) = self.module {
+            diagnostic = diagnostic.with_file(module);
+        }
+        diagnostic
+    }
 }
 
 /// A registry for trait definitions and implementations.
@@ -81,6 +138,8 @@ pub struct TraitRegistry {
     /// Key: (type, trait name) -> implementation
     /// Using a Vec instead of a Map because the key is composite
     implementations: Vec<TraitImpl>,
+    /// The module defining each nominal type, for the orphan check.
+    type_modules: Map<InternedString>,
 }
 
 impl TraitRegistry {
@@ -89,6 +148,7 @@ impl TraitRegistry {
         Self {
             traits: Map::default(),
             implementations: Vec::new(),
+            type_modules: Map::default(),
         }
     }
 
@@ -112,39 +172,49 @@ impl TraitRegistry {
         self.traits.get(&name)
     }
 
+    /// Records that the nominal type `name` is defined in `module`.
+    pub fn define_type(&mut self, name: InternedString, module: InternedString) {
+        self.type_modules.insert(name, module);
+    }
+
     /// Registers a new trait implementation.
     ///
-    /// Multiple implementations for the same (type, trait) pair are not allowed (coherence).
+    /// Overlapping implementations for the same trait are not allowed unless
+    /// one is strictly more specific than the other (coherence).
     ///
     /// # Errors
-    /// Returns an error if:
+    /// Returns an error, at the implementation's span, if:
     /// - The trait does not exist
-    /// - An implementation for this (type, trait) pair already exists
+    /// - The implementation overlaps an existing implementation of the trait
     /// - The implementation methods do not match the trait's method signatures
     pub fn implement_trait(&mut self, trait_impl: TraitImpl) -> Result<()> {
         // Check that the trait exists
         let trait_def = self.get_trait(trait_impl.trait_name).ok_or_else(|| {
-            Diagnostic::syntax(format!("Trait {} not found", &*trait_impl.trait_name))
+            trait_impl.locate(Diagnostic::syntax(format!(
+                "Trait {} not found",
+                &*trait_impl.trait_name
+            )))
         })?;
 
-        // Check for duplicate implementation
-        if self
-            .find_implementation(&trait_impl.for_type, trait_impl.trait_name)
-            .is_some()
-        {
-            return Err(Diagnostic::syntax(format!(
-                "Trait {} already implemented for type {}",
-                &*trait_impl.trait_name, trait_impl.for_type
-            )));
+        // Check for overlapping implementations
+        for existing in self.get_implementations_for_trait(trait_impl.trait_name) {
+            let (new, old) = (&trait_impl.for_type, &existing.for_type);
+            if overlaps(new, old) && is_instance(new, old) == is_instance(old, new) {
+                return Err(trait_impl.locate(Diagnostic::overlapping_impl(
+                    trait_impl.trait_name,
+                    new.clone(),
+                    old.clone(),
+                )));
+            }
         }
 
         // Validate that all methods are implemented
         for method_sig in &trait_def.methods {
             if !trait_impl.methods.contains_key(&method_sig.name) {
-                return Err(Diagnostic::syntax(format!(
+                return Err(trait_impl.locate(Diagnostic::syntax(format!(
                     "Implementation missing method {}",
                     &*method_sig.name
-                )));
+                ))));
             }
         }
 
@@ -154,7 +224,31 @@ impl TraitRegistry {
         Ok(())
     }
 
-    /// Finds a trait implementation for a specific type and trait.
+    /// Returns an orphan warning if `trait_impl` is in a module that defines
+    /// neither the trait nor the implementing type.
+    ///
+    /// Implementations without a module are never orphans.
+    pub fn orphan_warning(&self, trait_impl: &TraitImpl) -> Option<Box<Diagnostic>> {
+        let module = trait_impl.module?;
+        let trait_is_local = self
+            .get_trait(trait_impl.trait_name)
+            .is_some_and(|trait_def| trait_def.module == Some(module));
+        let type_is_local = match &trait_impl.for_type {
+            Type::Struct { name, .. } => self.type_modules.get(name) == Some(&module),
+            _ => false,
+        };
+        if trait_is_local || type_is_local {
+            return None;
+        }
+        Some(trait_impl.locate(Diagnostic::orphan_impl(
+            trait_impl.trait_name,
+            trait_impl.for_type.clone(),
+        )))
+    }
+
+    /// Finds the trait implementation that applies to a specific type.
+    ///
+    /// If several implementations match, the most specific one is returned.
     pub fn find_implementation(
         &self,
         for_type: &Type,
@@ -162,7 +256,16 @@ impl TraitRegistry {
     ) -> Option<&TraitImpl> {
         self.implementations
             .iter()
-            .find(|impl_| impl_.trait_name == trait_name && &impl_.for_type == for_type)
+            .filter(|impl_| {
+                impl_.trait_name == trait_name && is_instance(for_type, &impl_.for_type)
+            })
+            .reduce(|best, impl_| {
+                if is_instance(&impl_.for_type, &best.for_type) {
+                    impl_
+                } else {
+                    best
+                }
+            })
     }
 
     /// Gets all trait implementations for a specific type.
@@ -198,6 +301,70 @@ impl Default for TraitRegistry {
     }
 }
 
+/// Returns true if every type matching `ty` also matches `pattern`, treating
+/// `Type::Unknown` in `pattern` as a wildcard.
+fn is_instance(ty: &Type, pattern: &Type) -> bool {
+    structurally(ty, pattern, &|ty, pattern| {
+        matches!(pattern, Type::Unknown) || (!matches!(ty, Type::Unknown) && ty == pattern)
+    })
+}
+
+/// Returns true if some type matches both `a` and `b`.
+fn overlaps(a: &Type, b: &Type) -> bool {
+    structurally(a, b, &|a, b| {
+        matches!(a, Type::Unknown) || matches!(b, Type::Unknown) || a == b
+    })
+}
+
+/// Compares `a` and `b` constructor by constructor, using `leaf` wherever
+/// either side isn't a compound type with the same shape.
+fn structurally(a: &Type, b: &Type, leaf: &dyn Fn(&Type, &Type) -> bool) -> bool {
+    let all = |a: &[Type], b: &[Type]| {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| structurally(a, b, leaf))
+    };
+    let fields = |a: &[(InternedString, Type)], b: &[(InternedString, Type)]| {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|((n1, a), (n2, b))| n1 == n2 && structurally(a, b, leaf))
+    };
+    match (a, b) {
+        (Type::List(a), Type::List(b))
+        | (Type::Task(a), Type::Task(b))
+        | (Type::Channel(a), Type::Channel(b)) => structurally(a, b, leaf),
+        (Type::Fn(a), Type::Fn(b)) | (Type::Tuple(a), Type::Tuple(b)) => all(a, b),
+        (Type::Record(a), Type::Record(b)) => fields(a, b),
+        (
+            Type::Struct {
+                name: a,
+                fields: a_fields,
+            },
+            Type::Struct {
+                name: b,
+                fields: b_fields,
+            },
+        ) => a == b && fields(a_fields, b_fields),
+        (
+            Type::Enum {
+                name: a,
+                variants: a_variants,
+            },
+            Type::Enum {
+                name: b,
+                variants: b_variants,
+            },
+        ) => {
+            a == b
+                && a_variants.len() == b_variants.len()
+                && a_variants
+                    .iter()
+                    .zip(b_variants)
+                    .all(|((n1, a), (n2, b))| n1 == n2 && all(a, b))
+        }
+        _ => leaf(a, b),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -289,4 +456,105 @@ mod tests {
         let trait_impl2 = TraitImpl::new(trait_name, Type::Integer, methods);
         assert!(registry.implement_trait(trait_impl2).is_err());
     }
+
+    fn show_registry() -> (TraitRegistry, InternedString) {
+        let mut registry = TraitRegistry::new();
+        let trait_name = InternedString::new("Show");
+        registry
+            .define_trait(TraitDef::new(trait_name, vec![]))
+            .unwrap();
+        (registry, trait_name)
+    }
+
+    #[test]
+    fn test_overlapping_implementation_reported_at_span() {
+        let (mut registry, show) = show_registry();
+        let blanket = Type::Tuple(vec![Type::Unknown, Type::Integer]);
+        let other = Type::Tuple(vec![Type::Integer, Type::Unknown]);
+        registry
+            .implement_trait(TraitImpl::new(show, blanket, Map::default()))
+            .unwrap();
+
+        let overlapping = TraitImpl::new(show, other, Map::default())
+            .with_span(Span::new(10, 20))
+            .with_module("main".into());
+        let error = registry.implement_trait(overlapping).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            crate::DiagnosticKind::OverlappingImpl { .. }
+        ));
+        assert_eq!(error.span, Some(Span::new(10, 20)));
+        assert_eq!(error.file, Some("main".into()));
+    }
+
+    #[test]
+    fn test_most_specific_implementation_wins() {
+        let list = |ty| Type::list(ty);
+        let impl_for = |show, ty, tag: i64| {
+            let mut methods = Map::default();
+            methods.insert(InternedString::new("tag"), Value::Integer(tag));
+            TraitImpl::new(show, ty, methods)
+        };
+
+        // Registration order doesn't change the result
+        for specific_first in [false, true] {
+            let (mut registry, show) = show_registry();
+            let mut impls = vec![
+                impl_for(show, list(Type::Unknown), 1),
+                impl_for(show, list(Type::Integer), 2),
+            ];
+            if specific_first {
+                impls.reverse();
+            }
+            for trait_impl in impls {
+                registry.implement_trait(trait_impl).unwrap();
+            }
+
+            let tag = |ty: &Type| {
+                registry.find_implementation(ty, show).unwrap().methods[&InternedString::new("tag")]
+                    .clone()
+            };
+            assert_eq!(tag(&list(Type::Integer)), Value::Integer(2));
+            assert_eq!(tag(&list(Type::String)), Value::Integer(1));
+            assert!(registry.find_implementation(&Type::Integer, show).is_none());
+        }
+    }
+
+    #[test]
+    fn test_orphan_implementation_warning() {
+        let mut registry = TraitRegistry::new();
+        let show = InternedString::new("Show");
+        let point = Type::Struct {
+            name: "Point".into(),
+            fields: vec![],
+        };
+        registry
+            .define_trait(TraitDef::new(show, vec![]).with_module("fmt".into()))
+            .unwrap();
+        registry.define_type("Point".into(), "geometry".into());
+
+        let in_module = |ty: Type, module: &str| {
+            TraitImpl::new(show, ty, Map::default())
+                .with_span(Span::new(0, 4))
+                .with_module(module.into())
+        };
+
+        // Local trait or local type
+        assert!(
+            registry
+                .orphan_warning(&in_module(Type::Integer, "fmt"))
+                .is_none()
+        );
+        assert!(
+            registry
+                .orphan_warning(&in_module(point.clone(), "geometry"))
+                .is_none()
+        );
+
+        // Neither is local
+        let warning = registry.orphan_warning(&in_module(point, "main")).unwrap();
+        assert!(warning.is_warning());
+        assert_eq!(warning.file, Some("main".into()));
+        assert_eq!(warning.span, Some(Span::new(0, 4)));
+    }
 }
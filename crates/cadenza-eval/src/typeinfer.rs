@@ -374,6 +374,9 @@ impl InferType {
             Type::Symbol => InferType::Concrete(Type::Symbol),
             Type::Integer => InferType::Concrete(Type::Integer),
             Type::Float => InferType::Concrete(Type::Float),
+            Type::Decimal => InferType::Concrete(Type::Decimal),
+            Type::Vector => InferType::Concrete(Type::Vector),
+            Type::Matrix => InferType::Concrete(Type::Matrix),
             Type::String => InferType::Concrete(Type::String),
             Type::Type => InferType::Concrete(Type::Type),
             Type::Unknown => InferType::Concrete(Type::Unknown),
@@ -411,20 +414,18 @@ impl InferType {
             Type::Tuple(elems) => {
                 InferType::Tuple(elems.iter().map(InferType::from_concrete).collect())
             }
-            Type::Enum(variants) => {
-                // Enums are represented as unions of records
-                InferType::Union(
-                    variants
-                        .iter()
-                        .map(|(name, ty)| {
-                            InferType::Record(vec![(*name, InferType::from_concrete(ty))])
-                        })
-                        .collect(),
-                )
+            Type::Enum { .. } => {
+                // Enums are nominal: a value of one only unifies with values
+                // of the same enum, whichever variant they are
+                InferType::Concrete(ty.clone())
             }
             Type::Union(types) => {
                 InferType::Union(types.iter().map(InferType::from_concrete).collect())
             }
+            Type::Task(_) | Type::Channel(_) => {
+                // TODO: Track task result and channel element types during inference
+                InferType::Concrete(ty.clone())
+            }
             Type::Trait { .. } | Type::Constrained { .. } => {
                 // TODO: Add proper support for trait and constrained types in type inference
                 // For now, treat them as unknown types
@@ -753,8 +754,33 @@ impl TypeEnv {
     ///
     /// This converts the runtime value's type to an InferType for use in type checking.
     pub fn add_value(&mut self, name: InternedString, value: &crate::value::Value) {
-        let ty = value.type_of();
-        self.insert(name, InferType::from_concrete(&ty));
+        let ty = match value {
+            // A variant constructor accepts any value for its untyped fields
+            crate::value::Value::VariantConstructor { ty, variant } => {
+                let mut vars = Vec::new();
+                let params = ty
+                    .variant(*variant)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|field| match field {
+                        Type::Unknown => {
+                            let var = TypeVar::new(vars.len() as u32);
+                            vars.push(var);
+                            InferType::Var(var)
+                        }
+                        field => InferType::from_concrete(field),
+                    })
+                    .collect();
+                let ty = InferType::Fn(params, Box::new(InferType::Concrete(ty.clone())));
+                if vars.is_empty() {
+                    ty
+                } else {
+                    InferType::Forall(vars, Box::new(ty))
+                }
+            }
+            value => InferType::from_concrete(&value.type_of()),
+        };
+        self.insert(name, ty);
     }
 
     /// Inserts a binding into the environment.
@@ -762,6 +788,11 @@ impl TypeEnv {
         self.bindings.insert(name, ty);
     }
 
+    /// Removes a binding from the environment, returning its type.
+    pub fn remove(&mut self, name: InternedString) -> Option<InferType> {
+        self.bindings.remove(&name)
+    }
+
     /// Looks up a variable in the environment.
     pub fn get(&self, name: InternedString) -> Option<&InferType> {
         self.bindings.get(&name)
@@ -843,6 +874,26 @@ impl TypeInferencer {
                 Ok(Substitution::singleton(*v, t.clone()))
             }
 
+            // An overloaded type unifies as the first of its alternatives
+            // that does, so an operation on numbers of unknown type defaults
+            // to the first numeric type (see `crate::num::DEFAULTS`)
+            (InferType::Union(alternatives), t) | (t, InferType::Union(alternatives))
+                if !matches!(t, InferType::Union(_)) =>
+            {
+                alternatives
+                    .iter()
+                    .find_map(|alternative| self.unify(alternative, t, span).ok())
+                    .ok_or_else(|| {
+                        Box::new(Diagnostic::new(
+                            DiagnosticKind::InternalError(format!(
+                                "type mismatch: cannot unify {} with {}",
+                                t1, t2
+                            )),
+                            Some(span),
+                        ))
+                    })
+            }
+
             // Function types unify if arguments and return types unify
             (InferType::Fn(args1, ret1), InferType::Fn(args2, ret2)) => {
                 if args1.len() != args2.len() {
@@ -867,7 +918,8 @@ impl TypeInferencer {
             // List types unify if element types unify
             (InferType::List(elem1), InferType::List(elem2)) => self.unify(elem1, elem2, span),
 
-            // Record types unify if they have the same fields with unifiable types
+            // Record types unify if they have the same fields with unifiable
+            // types, in any order
             (InferType::Record(fields1), InferType::Record(fields2)) => {
                 if fields1.len() != fields2.len() {
                     return Err(Box::new(Diagnostic::new(
@@ -881,16 +933,16 @@ impl TypeInferencer {
                 }
 
                 let mut subst = Substitution::new();
-                for ((name1, ty1), (name2, ty2)) in fields1.iter().zip(fields2.iter()) {
-                    if name1 != name2 {
+                for (name1, ty1) in fields1 {
+                    let Some((_, ty2)) = fields2.iter().find(|(name2, _)| name2 == name1) else {
                         return Err(Box::new(Diagnostic::new(
                             DiagnosticKind::InternalError(format!(
-                                "record field name mismatch: {} vs {}",
-                                &**name1, &**name2
+                                "record field name mismatch: {} is missing",
+                                &**name1
                             )),
                             Some(span),
                         )));
-                    }
+                    };
                     let s = self.unify(&subst.apply(ty1), &subst.apply(ty2), span)?;
                     subst = s.compose(&subst);
                 }
@@ -1021,6 +1073,7 @@ impl TypeInferencer {
         let ty = match lit.value() {
             Some(LiteralValue::Integer(_)) => Type::Integer,
             Some(LiteralValue::Float(_)) => Type::Float,
+            Some(LiteralValue::Decimal(_)) => Type::Decimal,
             Some(LiteralValue::String(_)) | Some(LiteralValue::StringWithEscape(_)) => Type::String,
             None => Type::Unknown,
         };
@@ -1049,6 +1102,26 @@ impl TypeInferencer {
         apply: &cadenza_syntax::ast::Apply,
         env: &TypeEnv,
     ) -> Result<InferType> {
+        // Bindings and blocks take their types from their arguments rather
+        // than from the signatures of their special forms
+        if let Some(name) = apply
+            .callee()
+            .and_then(|callee| crate::eval::extract_identifier(&callee))
+        {
+            match &*name {
+                "=" => return self.infer_binding(apply, env),
+                "__block__" => return self.infer_block(apply, env),
+                "__tuple__" => return self.infer_tuple(apply, env),
+                "__record__" => return self.infer_record(apply, env),
+                "." => {
+                    if let Some(ty) = self.infer_tuple_element(apply, env)? {
+                        return Ok(ty);
+                    }
+                }
+                _ => {}
+            }
+        }
+
         // Infer type of the callee
         let callee_ty = if let Some(callee) = apply.callee() {
             self.infer_expr(&callee, env)?
@@ -1077,6 +1150,158 @@ impl TypeInferencer {
         Ok(subst.apply(&result_ty))
     }
 
+    /// Infers the type of a binding, `let x = value` or `x = value`, which
+    /// evaluates to the bound value.
+    fn infer_binding(
+        &mut self,
+        apply: &cadenza_syntax::ast::Apply,
+        env: &TypeEnv,
+    ) -> Result<InferType> {
+        match apply.all_arguments().get(1) {
+            Some(value) => self.infer_expr(value, env),
+            None => Ok(InferType::Var(self.fresh_var())),
+        }
+    }
+
+    /// Infers the type of a tuple literal from its elements, or nil for `()`.
+    fn infer_tuple(
+        &mut self,
+        apply: &cadenza_syntax::ast::Apply,
+        env: &TypeEnv,
+    ) -> Result<InferType> {
+        let elements = apply
+            .all_arguments()
+            .iter()
+            .map(|element| self.infer_expr(element, env))
+            .collect::<Result<Vec<_>>>()?;
+        if elements.is_empty() {
+            return Ok(InferType::Concrete(Type::Nil));
+        }
+        Ok(InferType::Tuple(elements))
+    }
+
+    /// Infers the type of a record literal from its fields and spreads.
+    ///
+    /// An update, `{ r | x = 1 }`, has the type of `r`: its fields must be
+    /// fields of `r` with the same types, and the rest are kept as they are.
+    /// When `r`'s type isn't known yet, the update's is the same type
+    /// variable, so fields the update doesn't mention still carry through.
+    fn infer_record(
+        &mut self,
+        apply: &cadenza_syntax::ast::Apply,
+        env: &TypeEnv,
+    ) -> Result<InferType> {
+        use crate::special_form::record_form::{RecordEntry, record_entries};
+
+        let mut base = None;
+        let mut fields: Vec<(InternedString, InferType)> = Vec::new();
+        let mut field_spans = Vec::new();
+        for entry in record_entries(&apply.all_arguments())? {
+            let added = match entry {
+                RecordEntry::Base(expr) => {
+                    base = Some(self.infer_expr(&expr, env)?);
+                    continue;
+                }
+                RecordEntry::Spread(expr) => match self.infer_expr(&expr, env)? {
+                    InferType::Record(spread) => spread,
+                    // The fields of an unknown record aren't known either
+                    _ => return Ok(InferType::Var(self.fresh_var())),
+                },
+                RecordEntry::Field(field) => {
+                    let ty = match &field.value {
+                        Some(value) => self.infer_expr(value, env)?,
+                        None => match env.get(field.name) {
+                            Some(ty) => self.instantiate(ty),
+                            None => InferType::Var(self.fresh_var()),
+                        },
+                    };
+                    field_spans.push((field.name, field.span));
+                    vec![(field.name, ty)]
+                }
+            };
+            for (name, ty) in added {
+                match fields.iter_mut().find(|(field, _)| *field == name) {
+                    Some((_, earlier)) => *earlier = ty,
+                    None => fields.push((name, ty)),
+                }
+            }
+        }
+
+        let Some(base) = base else {
+            return Ok(InferType::Record(fields));
+        };
+        let InferType::Record(base_fields) = &base else {
+            return Ok(base);
+        };
+        let mut subst = Substitution::new();
+        for (name, ty) in &fields {
+            let span = field_spans
+                .iter()
+                .find(|(field, _)| field == name)
+                .map_or(apply.span(), |(_, span)| *span);
+            let Some((_, base_ty)) = base_fields.iter().find(|(field, _)| field == name) else {
+                return Err(Box::new(
+                    Diagnostic::field_not_found(*name, "record").with_span(span),
+                ));
+            };
+            let s = self.unify(&subst.apply(base_ty), &subst.apply(ty), span)?;
+            subst = s.compose(&subst);
+        }
+        Ok(subst.apply(&base))
+    }
+
+    /// Infers the type of a tuple projection such as `pair.0`, or returns
+    /// `None` if the `.` accesses a record field.
+    ///
+    /// The element type is only known when the tuple's type is; otherwise
+    /// it's a fresh type variable.
+    fn infer_tuple_element(
+        &mut self,
+        apply: &cadenza_syntax::ast::Apply,
+        env: &TypeEnv,
+    ) -> Result<Option<InferType>> {
+        let [tuple, index] = &apply.all_arguments()[..] else {
+            return Ok(None);
+        };
+        let Some(indices) = crate::special_form::field_access_form::tuple_indices(index) else {
+            return Ok(None);
+        };
+        let mut ty = self.infer_expr(tuple, env)?;
+        for index in indices {
+            ty = match ty {
+                InferType::Tuple(mut elements) if index < elements.len() => {
+                    elements.swap_remove(index)
+                }
+                InferType::Concrete(Type::Tuple(mut elements)) if index < elements.len() => {
+                    InferType::Concrete(elements.swap_remove(index))
+                }
+                _ => InferType::Var(self.fresh_var()),
+            };
+        }
+        Ok(Some(ty))
+    }
+
+    /// Infers the type of a block: that of its last expression, with the
+    /// names bound before it in scope, or nil if it ends with a binding.
+    fn infer_block(
+        &mut self,
+        apply: &cadenza_syntax::ast::Apply,
+        env: &TypeEnv,
+    ) -> Result<InferType> {
+        let mut scope = env.clone();
+        let mut ty = InferType::Concrete(Type::Nil);
+        for expr in apply.all_arguments() {
+            ty = self.infer_expr(&expr, &scope)?;
+            if crate::special_form::block_form::is_binding(&expr) {
+                if let Some(name) = let_binding_name(&expr) {
+                    scope.insert(name, ty);
+                }
+                ty = InferType::Concrete(Type::Nil);
+            }
+        }
+        Ok(ty)
+    }
+
     fn infer_op(&mut self, op: &cadenza_syntax::ast::Op, env: &TypeEnv) -> Result<InferType> {
         // Operators are looked up as identifiers in the environment
         // At runtime, they evaluate to Symbol values, but for type inference
@@ -1111,6 +1336,28 @@ impl TypeInferencer {
     }
 }
 
+/// Returns the name `let name = value` binds, unless it's `_`.
+fn let_binding_name(expr: &cadenza_syntax::ast::Expr) -> Option<InternedString> {
+    use cadenza_syntax::ast::Expr;
+
+    let Expr::Apply(binding) = expr else {
+        return None;
+    };
+    let Some(Expr::Apply(target)) = binding.all_arguments().into_iter().next() else {
+        return None;
+    };
+    let keyword = target
+        .callee()
+        .and_then(|callee| crate::eval::extract_identifier(&callee));
+    if keyword.as_deref() != Some("let") {
+        return None;
+    }
+    let args = target.all_arguments();
+    let (_, args) = crate::special_form::let_form::strip_rec(&args, false);
+    let name = crate::eval::extract_identifier(args.first()?)?;
+    (&*name != "_").then_some(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1153,6 +1400,24 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_numeric_operators_default_to_integer() {
+        let mut inf = TypeInferencer::new();
+        let mut env = TypeEnv::from_env(&crate::env::Env::with_standard_builtins());
+        let unknown = inf.fresh_var();
+        env.insert("a".into(), InferType::Var(unknown));
+
+        let mut infer = |src: &str| {
+            let root = cadenza_syntax::parse::parse(src).ast();
+            let expr = root.items().next().unwrap();
+            inf.infer_expr(&expr, &env).unwrap()
+        };
+        assert_eq!(infer("1 + 2"), InferType::Concrete(Type::Integer));
+        assert_eq!(infer("1.5 * 2.0"), InferType::Concrete(Type::Float));
+        assert_eq!(infer("1.5 < 2.0"), InferType::Concrete(Type::Bool));
+        assert_eq!(infer("a - a"), InferType::Concrete(Type::Integer));
+    }
+
     #[test]
     fn test_occurs_check() {
         let inf = TypeInferencer::new();
@@ -242,7 +242,7 @@ impl fmt::Display for DerivedDimension {
 /// The global unit registry.
 ///
 /// This stores all defined units and provides lookup and conversion services.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct UnitRegistry {
     /// All defined units, indexed by name.
     units: Map<Unit>,
@@ -2,15 +2,16 @@
 //!
 //! Values can be symbols, lists, functions, macros, or built-in operations.
 
-use crate::{diagnostic::Result, interner::InternedString};
+use crate::{bigint::BigInt, decimal::Decimal, diagnostic::Result, interner::InternedString, list};
 use cadenza_syntax::{ast::Expr, span::Span};
-use std::fmt;
+use std::{borrow::Cow, fmt};
 
 /// A runtime type in the Cadenza evaluator.
 ///
 /// Types are first-class values that can be inspected and operated on at runtime.
 /// This allows for type-level programming and better error messages.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Type {
     /// The type of nil/unit values.
     Nil,
@@ -22,6 +23,8 @@ pub enum Type {
     Integer,
     /// The type of floating-point values.
     Float,
+    /// The type of exact decimal values.
+    Decimal,
     /// The type of string values.
     String,
     /// The type of list values with element type.
@@ -44,8 +47,14 @@ pub enum Type {
     },
     /// A tuple type with a list of element types.
     Tuple(Vec<Type>),
-    /// An enum type with variant names and their associated types.
-    Enum(Vec<(InternedString, Type)>),
+    /// A nominally-typed enum (sum type): a value of it is one of its
+    /// variants, which each have positional fields.
+    Enum {
+        /// The name of the enum type.
+        name: InternedString,
+        /// The variant names and the types of their fields.
+        variants: Vec<(InternedString, Vec<Type>)>,
+    },
     /// A union type representing one of several possible types.
     Union(Vec<Type>),
     /// A trait type definition.
@@ -56,6 +65,14 @@ pub enum Type {
         /// The method signatures in this trait.
         methods: Vec<MethodSignature>,
     },
+    /// The type of vectors of numbers.
+    Vector,
+    /// The type of matrices of numbers.
+    Matrix,
+    /// The type of a spawned task producing a value of the inner type.
+    Task(Box<Type>),
+    /// The type of a channel carrying values of the inner type.
+    Channel(Box<Type>),
     /// A type with trait constraints.
     /// Example: `[Numeric a] => a` is represented as Constrained with ty=a and traits=[Numeric].
     Constrained {
@@ -117,6 +134,28 @@ impl MethodSignature {
 }
 
 impl Type {
+    /// Returns whether `self` and `other` are the same type, with the fields
+    /// of structural records in any order.
+    ///
+    /// Records with the same fields are equal whatever order their fields
+    /// were written in, so comparing them checks their types this way.
+    pub fn same_as(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Record(a), Type::Record(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(name, ty)| {
+                        b.iter()
+                            .any(|(other_name, other)| other_name == name && ty.same_as(other))
+                    })
+            }
+            (Type::List(a), Type::List(b)) => a.same_as(b),
+            (Type::Tuple(a), Type::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same_as(b))
+            }
+            _ => self == other,
+        }
+    }
+
     /// Creates a function type from argument types and a return type.
     pub fn function(args: Vec<Type>, ret: Type) -> Self {
         let mut types = args;
@@ -124,6 +163,18 @@ impl Type {
         Type::Fn(types)
     }
 
+    /// Returns the field types of the variant `name`, if this is an enum
+    /// type with that variant.
+    pub fn variant(&self, name: InternedString) -> Option<&[Type]> {
+        let Type::Enum { variants, .. } = self else {
+            return None;
+        };
+        variants
+            .iter()
+            .find(|(variant, _)| *variant == name)
+            .map(|(_, fields)| fields.as_slice())
+    }
+
     /// Creates a union type from a list of types.
     ///
     /// # Panics
@@ -138,6 +189,47 @@ impl Type {
         Type::List(Box::new(element))
     }
 
+    /// Creates a task type with the given result type.
+    pub fn task(result: Type) -> Self {
+        Type::Task(Box::new(result))
+    }
+
+    /// Creates a channel type with the given element type.
+    pub fn channel(element: Type) -> Self {
+        Type::Channel(Box::new(element))
+    }
+
+    /// Returns true if values of this type are linear: owned by exactly one
+    /// binding and deleted when their owner goes out of scope.
+    ///
+    /// Strings, lists, closures and handles (tasks and channels) are linear.
+    /// Records, structs, tuples, enums and unions are linear if any component
+    /// is. Scalars and unknown types are copied.
+    pub fn is_linear(&self) -> bool {
+        match self {
+            Type::String | Type::List(_) | Type::Fn(_) | Type::Task(_) | Type::Channel(_) => true,
+            Type::Record(fields) | Type::Struct { fields, .. } => {
+                fields.iter().any(|(_, ty)| ty.is_linear())
+            }
+            Type::Enum { variants, .. } => variants
+                .iter()
+                .any(|(_, fields)| fields.iter().any(Type::is_linear)),
+            Type::Tuple(types) | Type::Union(types) => types.iter().any(Type::is_linear),
+            Type::Constrained { ty, .. } => ty.is_linear(),
+            Type::Nil
+            | Type::Bool
+            | Type::Symbol
+            | Type::Integer
+            | Type::Float
+            | Type::Decimal
+            | Type::Vector
+            | Type::Matrix
+            | Type::Type
+            | Type::Trait { .. }
+            | Type::Unknown => false,
+        }
+    }
+
     /// Returns the string representation of this type.
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -146,6 +238,7 @@ impl Type {
             Type::Symbol => "symbol",
             Type::Integer => "integer",
             Type::Float => "float",
+            Type::Decimal => "decimal",
             Type::String => "string",
             Type::List(_) => "list",
             Type::Type => "type",
@@ -153,9 +246,13 @@ impl Type {
             Type::Record(_) => "record",
             Type::Struct { .. } => "struct",
             Type::Tuple(_) => "tuple",
-            Type::Enum(_) => "enum",
+            Type::Enum { .. } => "enum",
             Type::Union(_) => "union",
             Type::Trait { .. } => "trait",
+            Type::Vector => "vector",
+            Type::Matrix => "matrix",
+            Type::Task(_) => "task",
+            Type::Channel(_) => "channel",
             Type::Constrained { .. } => "constrained",
             Type::Unknown => "unknown",
         }
@@ -170,6 +267,7 @@ impl fmt::Display for Type {
             Type::Symbol => write!(f, "symbol"),
             Type::Integer => write!(f, "integer"),
             Type::Float => write!(f, "float"),
+            Type::Decimal => write!(f, "decimal"),
             Type::String => write!(f, "string"),
             Type::List(elem) => write!(f, "list[{elem}]"),
             Type::Type => write!(f, "type"),
@@ -218,15 +316,18 @@ impl fmt::Display for Type {
                 }
                 write!(f, ")")
             }
-            Type::Enum(variants) => {
-                write!(f, "enum[")?;
-                for (i, (name, t)) in variants.iter().enumerate() {
+            Type::Enum { name, variants } => {
+                write!(f, "enum {} =", &**name)?;
+                for (i, (variant, fields)) in variants.iter().enumerate() {
                     if i > 0 {
-                        write!(f, " | ")?;
+                        write!(f, " |")?;
+                    }
+                    write!(f, " {}", &**variant)?;
+                    for t in fields {
+                        write!(f, " {t}")?;
                     }
-                    write!(f, "{}({})", &**name, t)?;
                 }
-                write!(f, "]")
+                Ok(())
             }
             Type::Union(types) => {
                 if types.is_empty() {
@@ -263,6 +364,10 @@ impl fmt::Display for Type {
                     Ok(())
                 }
             }
+            Type::Vector => write!(f, "vector"),
+            Type::Matrix => write!(f, "matrix"),
+            Type::Task(result) => write!(f, "task[{result}]"),
+            Type::Channel(elem) => write!(f, "channel[{elem}]"),
             Type::Constrained { ty, traits } => {
                 write!(f, "[")?;
                 for (i, trait_ref) in traits.iter().enumerate() {
@@ -324,7 +429,13 @@ impl SourceInfo {
 }
 
 /// A runtime value in the Cadenza evaluator.
+///
+/// Scalars (nil, booleans, symbols, integers, floats) are stored inline, and
+/// empty strings and lists don't allocate. String literals borrow their text
+/// from the interner, so evaluating one doesn't copy it; strings built at
+/// runtime are owned.
 #[derive(Clone)]
+#[non_exhaustive]
 pub enum Value {
     /// The nil/unit value, typically returned from side-effecting operations.
     Nil,
@@ -338,15 +449,49 @@ pub enum Value {
     /// An integer value (for now using i64, will be rational later).
     Integer(i64),
 
+    /// An integer too large for an `i64`, still of type `Integer`.
+    ///
+    /// Arithmetic promotes to this on overflow and demotes back whenever a
+    /// result fits (see [`crate::bigint`]).
+    BigInt(BigInt),
+
     /// A floating-point value.
     Float(f64),
 
-    /// A string value.
-    String(String),
+    /// An exact decimal, written with a `d` suffix as in `1.5d` (see
+    /// [`crate::decimal`]).
+    Decimal(Decimal),
+
+    /// A string value, borrowed from the interner for literals.
+    String(Cow<'static, str>),
 
     /// A list of values.
     List(Vec<Value>),
 
+    /// The integers from `start` up to but not including `end`, written
+    /// `start..end`, or `start..=last` to include `last`.
+    ///
+    /// A range is a list whose elements are computed as they're used, so
+    /// `1..1000000 |> take 5` never builds a million elements. It has the
+    /// type of a list and equals the list of the same elements; see
+    /// [`crate::list`] for the builtins that keep it lazy.
+    Range { start: i64, end: i64 },
+
+    /// A vector of floats and quantities, each element with its own unit
+    /// (see [`crate::linalg`]).
+    Vector(Vec<Value>),
+
+    /// A matrix of floats and quantities, each element with its own unit
+    /// (see [`crate::linalg`]).
+    Matrix {
+        /// The number of rows.
+        rows: usize,
+        /// The number of columns.
+        columns: usize,
+        /// The elements, row by row.
+        elements: Vec<Value>,
+    },
+
     /// A tuple value with positional elements.
     ///
     /// Tuples are similar to records but use positional (numeric) fields instead of named fields.
@@ -367,9 +512,16 @@ pub enum Value {
     /// When `type_name` is None, this is a structurally-typed record.
     /// When `type_name` is Some, this is a nominally-typed struct instance.
     ///
-    /// Structural records are equal if they have the same fields and values.
+    /// Fields keep the order they were written in: a record literal's fields
+    /// in the order of the literal, a struct instance's in the order of the
+    /// struct definition, and a decoded JSON object's in the order of the
+    /// document. Display, `to_json` and field iteration all follow it.
+    ///
+    /// Structural records are equal if they have the same fields and values,
+    /// in any order, so order is presentation only; [`stable_hash`] agrees.
     /// Nominal structs are equal only if they have the same type name, fields, and values.
-    /// Field order is preserved from construction.
+    ///
+    /// [`stable_hash`]: crate::hash::stable_hash
     Record {
         /// The type name for nominally-typed structs, None for structural records.
         type_name: Option<InternedString>,
@@ -389,6 +541,32 @@ pub enum Value {
         field_types: Vec<(InternedString, Type)>,
     },
 
+    /// A value of an enum type: one of its variants, with the values of the
+    /// variant's fields in order.
+    ///
+    /// Example: `Circle 2` where `Circle` is a variant of
+    /// `enum Shape = Circle r | Rect w h`.
+    Variant {
+        /// The enum type the variant belongs to.
+        ty: Type,
+        /// The name of the variant.
+        variant: InternedString,
+        /// The field values.
+        fields: Vec<Value>,
+    },
+
+    /// A constructor for a variant of an enum type.
+    ///
+    /// When an enum is defined, each variant with fields gets a constructor
+    /// named after it, which takes the values of the fields. A variant
+    /// without fields is bound to its value instead.
+    VariantConstructor {
+        /// The enum type the variant belongs to.
+        ty: Type,
+        /// The name of the variant.
+        variant: InternedString,
+    },
+
     /// A type value (types are first-class values).
     Type(Type),
 
@@ -430,6 +608,19 @@ pub enum Value {
 
     /// A user-defined function with parameter names and body expression.
     UserFunction(UserFunction),
+
+    /// A handle to a task spawned with `spawn`.
+    Task(crate::task::TaskId),
+
+    /// A handle to a bounded channel created with `channel`.
+    Channel(crate::task::ChannelId),
+
+    /// A poisoned value, bound in place of a value whose evaluation failed.
+    ///
+    /// Reading a poisoned variable raises a [`Poisoned`](crate::DiagnosticKind::Poisoned)
+    /// diagnostic, which unwinds like any other error but is never recorded,
+    /// so one failure doesn't cascade into an error at every use.
+    Error,
 }
 
 /// A built-in function type with type signature.
@@ -479,6 +670,10 @@ pub struct UserFunction {
     pub name: InternedString,
     /// The parameter names (in order).
     pub params: Vec<InternedString>,
+    /// The parameters written as destructuring patterns (see
+    /// [`crate::pattern`]), each with the parameter name its argument is
+    /// bound to before the pattern takes it apart.
+    pub patterns: Vec<(InternedString, Expr)>,
     /// The function body expression.
     pub body: Expr,
     /// The captured environment at function definition time.
@@ -487,6 +682,18 @@ pub struct UserFunction {
     pub captured_env: crate::env::Env,
 }
 
+/// An attribute written as `@name args...` on the line before a definition.
+///
+/// Attributes are collected by the compiler and consumed by the next
+/// definition (e.g. `@memoize` before a `fn`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    /// The attribute name.
+    pub name: InternedString,
+    /// The evaluated attribute arguments.
+    pub args: Vec<Value>,
+}
+
 impl Value {
     /// Returns true if this value is nil.
     pub fn is_nil(&self) -> bool {
@@ -523,11 +730,14 @@ impl Value {
             Value::Nil => Type::Nil,
             Value::Bool(_) => Type::Bool,
             Value::Symbol(_) => Type::Symbol,
-            Value::Integer(_) => Type::Integer,
+            Value::Integer(_) | Value::BigInt(_) => Type::Integer,
             Value::Float(_) => Type::Float,
+            Value::Decimal(_) => Type::Decimal,
             Value::String(_) => Type::String,
             // For lists, we use Unknown since we don't track element types at runtime yet
-            Value::List(_) => Type::list(Type::Unknown),
+            Value::List(_) | Value::Range { .. } => Type::list(Type::Unknown),
+            Value::Vector(_) => Type::Vector,
+            Value::Matrix { .. } => Type::Matrix,
             // For tuples, extract element types
             // Structural tuples (type_name = None) return Tuple type with element types
             // Nominal tuples (type_name = Some) might need a separate type (TBD)
@@ -563,6 +773,13 @@ impl Value {
                     fields: field_types.clone(),
                 },
             ),
+            Value::Variant { ty, .. } => ty.clone(),
+            // A variant constructor takes the variant's fields and returns
+            // the enum
+            Value::VariantConstructor { ty, variant } => Type::function(
+                ty.variant(*variant).unwrap_or_default().to_vec(),
+                ty.clone(),
+            ),
             Value::Type(_) => Type::Type,
             Value::Quantity { .. } => Type::Float, // Quantities are numeric
             Value::UnitConstructor(_) => Type::function(vec![Type::Float], Type::Float),
@@ -575,6 +792,9 @@ impl Value {
                 let param_types = vec![Type::Unknown; uf.params.len()];
                 Type::function(param_types, Type::Unknown)
             }
+            Value::Task(_) => Type::task(Type::Unknown),
+            Value::Channel(_) => Type::channel(Type::Unknown),
+            Value::Error => Type::Unknown,
         }
     }
 
@@ -691,9 +911,24 @@ impl fmt::Debug for Value {
             Value::Bool(b) => write!(f, "{b}"),
             Value::Symbol(id) => write!(f, "Symbol({id:?})"),
             Value::Integer(n) => write!(f, "{n}"),
+            Value::BigInt(n) => write!(f, "{n}"),
             Value::Float(n) => write!(f, "{n}"),
+            Value::Decimal(n) => write!(f, "{n:?}"),
             Value::String(s) => write!(f, "{s:?}"),
             Value::List(items) => f.debug_list().entries(items).finish(),
+            Value::Range { start, end } => write!(f, "{start}..{end}"),
+            Value::Vector(elements) => {
+                write!(f, "vector ")?;
+                f.debug_list().entries(elements).finish()
+            }
+            Value::Matrix {
+                columns, elements, ..
+            } => {
+                write!(f, "matrix ")?;
+                f.debug_list()
+                    .entries(elements.chunks((*columns).max(1)))
+                    .finish()
+            }
             Value::Tuple {
                 type_name,
                 elements,
@@ -765,6 +1000,25 @@ impl fmt::Debug for Value {
                     field_types.len()
                 )
             }
+            Value::Variant {
+                variant, fields, ..
+            } => {
+                write!(f, "{}", &**variant)?;
+                if !fields.is_empty() {
+                    write!(f, "(")?;
+                    for (i, value) in fields.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{:?}", value)?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Value::VariantConstructor { variant, .. } => {
+                write!(f, "VariantConstructor({})", &**variant)
+            }
             Value::Type(t) => write!(f, "Type({t})"),
             Value::Quantity {
                 value,
@@ -776,6 +1030,9 @@ impl fmt::Debug for Value {
             Value::BuiltinMacro(bm) => write!(f, "<builtin-macro {}>", bm.name),
             Value::SpecialForm(sf) => write!(f, "<special-form {}>", sf.name()),
             Value::UserFunction(uf) => write!(f, "<fn {}>", &*uf.name),
+            Value::Task(id) => write!(f, "<task {}>", id.0),
+            Value::Channel(id) => write!(f, "<channel {}>", id.0),
+            Value::Error => write!(f, "<error>"),
         }
     }
 }
@@ -787,7 +1044,9 @@ impl fmt::Display for Value {
             Value::Bool(b) => write!(f, "{b}"),
             Value::Symbol(id) => write!(f, "#{}", &**id),
             Value::Integer(n) => write!(f, "{n}"),
+            Value::BigInt(n) => write!(f, "{n}"),
             Value::Float(n) => write!(f, "{n}"),
+            Value::Decimal(n) => write!(f, "{n}"),
             Value::String(s) => write!(f, "{s}"),
             Value::List(items) => {
                 write!(f, "[")?;
@@ -799,6 +1058,17 @@ impl fmt::Display for Value {
                 }
                 write!(f, "]")
             }
+            Value::Range { start, end } => write!(f, "{start}..{end}"),
+            Value::Vector(elements) => write!(f, "vector {}", Value::List(elements.clone())),
+            Value::Matrix {
+                columns, elements, ..
+            } => {
+                let rows = elements
+                    .chunks((*columns).max(1))
+                    .map(|row| Value::List(row.to_vec()))
+                    .collect();
+                write!(f, "matrix {}", Value::List(rows))
+            }
             Value::Tuple {
                 type_name,
                 elements,
@@ -865,6 +1135,25 @@ impl fmt::Display for Value {
             Value::StructConstructor { name, .. } => {
                 write!(f, "<struct-constructor {}>", &**name)
             }
+            Value::Variant {
+                variant, fields, ..
+            } => {
+                write!(f, "{}", &**variant)?;
+                for value in fields {
+                    // A field that is itself a variant with fields needs
+                    // parentheses, as it does in source
+                    match value {
+                        Value::Variant { fields, .. } if !fields.is_empty() => {
+                            write!(f, " ({value})")?
+                        }
+                        _ => write!(f, " {value}")?,
+                    }
+                }
+                Ok(())
+            }
+            Value::VariantConstructor { variant, .. } => {
+                write!(f, "<variant-constructor {}>", &**variant)
+            }
             Value::Type(t) => write!(f, "{t}"),
             Value::Quantity {
                 value,
@@ -876,20 +1165,55 @@ impl fmt::Display for Value {
             Value::BuiltinMacro(bm) => write!(f, "<builtin-macro {}>", bm.name),
             Value::SpecialForm(sf) => write!(f, "<special-form {}>", sf.name()),
             Value::UserFunction(uf) => write!(f, "<fn {}>", &*uf.name),
+            Value::Task(id) => write!(f, "<task {}>", id.0),
+            Value::Channel(id) => write!(f, "<channel {}>", id.0),
+            Value::Error => write!(f, "<error>"),
         }
     }
 }
 
+/// Returns whether two records have the same fields with equal values,
+/// whatever order the fields are in.
+fn same_fields(a: &[(InternedString, Value)], b: &[(InternedString, Value)]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(name, value)| b.iter().any(|(other, v)| other == name && v == value))
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Value::Nil, Value::Nil) => true,
+            (Value::Error, Value::Error) => true,
             (Value::Bool(a), Value::Bool(b)) => a == b,
             (Value::Symbol(a), Value::Symbol(b)) => a == b,
             (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::BigInt(a), Value::BigInt(b)) => a == b,
             (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Decimal(a), Value::Decimal(b)) => a == b,
             (Value::String(a), Value::String(b)) => a == b,
             (Value::List(a), Value::List(b)) => a == b,
+            (Value::Vector(a), Value::Vector(b)) => a == b,
+            (
+                Value::Matrix {
+                    rows: r1,
+                    columns: c1,
+                    elements: e1,
+                },
+                Value::Matrix {
+                    rows: r2,
+                    columns: c2,
+                    elements: e2,
+                },
+            ) => (r1, c1) == (r2, c2) && e1 == e2,
+            // A range equals the list of its elements, and any empty range
+            // equals any other
+            (Value::Range { .. } | Value::List(_), Value::Range { .. } | Value::List(_)) => {
+                let (Some(a), Some(b)) = (list::elements(self), list::elements(other)) else {
+                    unreachable!("lists and ranges have elements");
+                };
+                a.eq(b)
+            }
             (
                 Value::Record {
                     type_name: n1,
@@ -903,11 +1227,21 @@ impl PartialEq for Value {
                 // Structural records (type_name = None) are equal if fields match
                 // Nominal structs (type_name = Some) must also have matching type names
                 match (n1, n2) {
-                    (None, None) => f1 == f2, // Structural equality
-                    (Some(name1), Some(name2)) => name1 == name2 && f1 == f2, // Nominal equality
-                    _ => false,               // Structural record vs nominal struct are never equal
+                    (None, None) => same_fields(f1, f2), // Structural equality
+                    (Some(name1), Some(name2)) => name1 == name2 && same_fields(f1, f2), // Nominal equality
+                    _ => false, // Structural record vs nominal struct are never equal
                 }
             }
+            (
+                Value::Tuple {
+                    type_name: n1,
+                    elements: e1,
+                },
+                Value::Tuple {
+                    type_name: n2,
+                    elements: e2,
+                },
+            ) => n1 == n2 && e1 == e2,
             (
                 Value::StructConstructor { name: n1, .. },
                 Value::StructConstructor { name: n2, .. },
@@ -915,7 +1249,32 @@ impl PartialEq for Value {
                 // Struct constructors are equal if they construct the same struct type
                 n1 == n2
             }
+            (
+                Value::Variant {
+                    ty: t1,
+                    variant: v1,
+                    fields: f1,
+                },
+                Value::Variant {
+                    ty: t2,
+                    variant: v2,
+                    fields: f2,
+                },
+            ) => t1 == t2 && v1 == v2 && f1 == f2,
+            (
+                Value::VariantConstructor {
+                    ty: t1,
+                    variant: v1,
+                },
+                Value::VariantConstructor {
+                    ty: t2,
+                    variant: v2,
+                },
+            ) => t1 == t2 && v1 == v2,
             (Value::Type(a), Value::Type(b)) => a == b,
+            // Tasks and channels are handles; they are equal if they refer to the same one
+            (Value::Task(a), Value::Task(b)) => a == b,
+            (Value::Channel(a), Value::Channel(b)) => a == b,
             (
                 Value::Quantity {
                     value: v1,
@@ -964,7 +1323,7 @@ mod tests {
         assert_eq!(Value::Bool(true).type_of(), Type::Bool);
         assert_eq!(Value::Integer(42).type_of(), Type::Integer);
         assert_eq!(Value::Float(2.5).type_of(), Type::Float);
-        assert_eq!(Value::String("hello".to_string()).type_of(), Type::String);
+        assert_eq!(Value::String("hello".into()).type_of(), Type::String);
         assert_eq!(Value::List(vec![]).type_of(), Type::list(Type::Unknown));
         assert_eq!(Value::Type(Type::Integer).type_of(), Type::Type);
     }
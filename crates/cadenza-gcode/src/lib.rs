@@ -28,7 +28,7 @@ pub mod testing;
 mod generated;
 
 pub use error::{Error, Result};
-pub use syntax::parse;
+pub use syntax::{GcodeFrontend, parse};
 
 #[cfg(test)]
 mod fuzz;
@@ -20,7 +20,7 @@
 //! // eval() doesn't care that this came from GCode - it's just an AST
 //! ```
 
-use cadenza_syntax::{parse::Parse, token::Kind};
+use cadenza_syntax::{frontend::Frontend, parse::Parse, token::Kind};
 use cadenza_tree::GreenNodeBuilder;
 
 /// Parse GCode source into a Cadenza-compatible AST.
@@ -28,6 +28,24 @@ pub fn parse(src: &str) -> Parse {
     Parser::new(src).parse()
 }
 
+/// The GCode front-end, for `.gcode`, `.gco` and `.nc` files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcodeFrontend;
+
+impl Frontend for GcodeFrontend {
+    fn name(&self) -> &str {
+        "gcode"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gcode", "gco", "nc"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        parse(source)
+    }
+}
+
 struct Parser<'src> {
     src: &'src str,
     pos: usize,
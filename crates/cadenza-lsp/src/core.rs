@@ -1,15 +1,32 @@
 //! Core LSP utilities shared between native and WASM implementations.
 
+use crate::{
+    settings::{LintLevel, Settings},
+    units,
+};
+use cadenza_eval::{
+    CompileOptions, Compiler, DiagnosticKind, Env, Value, dead_code::unused_functions,
+    module_graph::ModuleGraph,
+};
+use cadenza_syntax::frontend::{Cadenza, Frontend};
 use lsp_types::*;
+use std::sync::Arc;
 
 /// Convert cadenza parse errors to LSP diagnostics.
 pub fn parse_to_diagnostics(source: &str) -> Vec<Diagnostic> {
-    let parsed = cadenza_syntax::parse::parse(source);
+    parse_to_diagnostics_with(&Cadenza, source)
+}
+
+/// Like [`parse_to_diagnostics`], for source written in the syntax of
+/// `frontend`. The diagnostics are attributed to the syntax.
+pub fn parse_to_diagnostics_with(frontend: &dyn Frontend, source: &str) -> Vec<Diagnostic> {
+    let parsed = frontend.parse(source);
 
     parsed
         .errors
-        .iter()
+        .into_iter()
         .map(|error| {
+            let error = frontend.map_error(error);
             let start_pos = offset_to_position(source, error.span.start);
             let end_pos = offset_to_position(source, error.span.end);
 
@@ -18,8 +35,8 @@ pub fn parse_to_diagnostics(source: &str) -> Vec<Diagnostic> {
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
-                source: Some("cadenza".to_string()),
-                message: error.message.clone(),
+                source: Some(frontend.name().to_string()),
+                message: error.message,
                 related_information: None,
                 tags: None,
                 data: None,
@@ -28,6 +45,201 @@ pub fn parse_to_diagnostics(source: &str) -> Vec<Diagnostic> {
         .collect()
 }
 
+/// Convert cadenza parse errors, evaluation errors, and unused-function
+/// warnings to LSP diagnostics.
+///
+/// Evaluation continues past failing top-level expressions, so every
+/// independent error is reported in one pass. Unused functions are tagged
+/// [`DiagnosticTag::UNNECESSARY`] so editors render them grayed out. Nothing
+/// is evaluated unless the source parses. Evaluation is limited to the
+/// default fuel budget, so a non-terminating program can't stall the editor.
+pub fn check_to_diagnostics(source: &str) -> Vec<Diagnostic> {
+    check_module_to_diagnostics("main", source, &Settings::default(), |_| None)
+}
+
+/// Like [`check_to_diagnostics`], for the module `name` in a workspace.
+///
+/// `resolve` returns the source of the module with a given name, if the
+/// workspace has one. The modules `source` imports, directly or not, are
+/// evaluated first so their definitions resolve, but only the diagnostics for
+/// `source` itself are returned. Warnings are reported at the level
+/// `settings` gives their code.
+pub fn check_module_to_diagnostics(
+    name: &str,
+    source: &str,
+    settings: &Settings,
+    resolve: impl Fn(&str) -> Option<String>,
+) -> Vec<Diagnostic> {
+    check_module_to_diagnostics_with(Arc::new(Cadenza), name, source, settings, resolve)
+}
+
+/// Like [`check_module_to_diagnostics`], for a module written in the syntax
+/// of `frontend`. The modules it imports are Cadenza.
+pub fn check_module_to_diagnostics_with(
+    frontend: Arc<dyn Frontend>,
+    name: &str,
+    source: &str,
+    settings: &Settings,
+    resolve: impl Fn(&str) -> Option<String>,
+) -> Vec<Diagnostic> {
+    let diagnostics = parse_to_diagnostics_with(frontend.as_ref(), source);
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    let mut graph = ModuleGraph::new();
+    graph.add_with(name, source, frontend);
+    let mut next = 0;
+    while let Some(module) = graph.modules().get(next) {
+        next += 1;
+        let imports: Vec<_> = module.imports.iter().map(|(import, _)| *import).collect();
+        for import in imports {
+            if graph.get(import).is_none()
+                && let Some(source) = resolve(&import)
+            {
+                graph.add(import, source);
+            }
+        }
+    }
+
+    let options = CompileOptions {
+        fuel: Some(settings.fuel),
+        ..CompileOptions::default()
+    };
+    let mut env = Env::with_options(&options);
+    let mut compiler = Compiler::with_options(options);
+    graph.eval(&mut env, &mut compiler);
+    for warning in unused_functions(&graph) {
+        compiler.record_diagnostic(warning);
+    }
+
+    let name = name.into();
+    compiler
+        .diagnostics()
+        .iter()
+        .filter(|diagnostic| diagnostic.file.is_none_or(|file| file == name))
+        .filter_map(|diagnostic| {
+            let span = diagnostic.span?;
+            let start_pos = offset_to_position(source, span.start);
+            let end_pos = offset_to_position(source, span.end);
+            let code = diagnostic.kind().code();
+
+            let severity = match (diagnostic.is_warning(), settings.lint_level(code)) {
+                (true, LintLevel::Allow) => return None,
+                (true, LintLevel::Warn) => DiagnosticSeverity::WARNING,
+                _ => DiagnosticSeverity::ERROR,
+            };
+
+            let mut message = diagnostic.kind().to_string();
+            if let Some(help) = diagnostic.kind().help() {
+                message.push_str(&format!("\nhelp: {help}"));
+            }
+            // The quick fix renaming to the suggestion reads it back (see `suggestion`)
+            let data = match diagnostic.kind() {
+                DiagnosticKind::UndefinedVariable {
+                    suggestion: Some(suggestion),
+                    ..
+                } => Some(serde_json::json!({ "suggestion": &**suggestion })),
+                _ => None,
+            };
+
+            Some(Diagnostic {
+                range: Range::new(start_pos, end_pos),
+                severity: Some(severity),
+                code: Some(NumberOrString::String(code.to_string())),
+                code_description: None,
+                source: Some("cadenza".to_string()),
+                message,
+                related_information: None,
+                tags: diagnostic
+                    .is_unnecessary()
+                    .then(|| vec![DiagnosticTag::UNNECESSARY]),
+                data,
+            })
+        })
+        .collect()
+}
+
+/// Returns the defined name an undefined variable diagnostic from
+/// [`check_module_to_diagnostics`] suggests instead, if it has one.
+pub fn suggestion(diagnostic: &Diagnostic) -> Option<&str> {
+    diagnostic.data.as_ref()?.get("suggestion")?.as_str()
+}
+
+/// Returns the byte range of the identifier around `offset` in `source`.
+pub fn word_at(source: &str, offset: usize) -> Option<std::ops::Range<usize>> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = source[..offset]
+        .rfind(|c: char| !is_word(c))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let end = source[offset..]
+        .find(|c: char| !is_word(c))
+        .map(|i| offset + i)
+        .unwrap_or(source.len());
+    (start < end).then_some(start..end)
+}
+
+/// Returns the hover text, as Markdown, for the identifier around `offset`.
+///
+/// Quantity literals show their unit conversions (see
+/// [`units::quantity_hover`]). With [`Settings::eval_on_hover`], the document is evaluated within the fuel
+/// budget, and a name bound at the top level shows its value.
+pub fn hover_markdown(source: &str, offset: usize, settings: &Settings) -> Option<String> {
+    if let Some(markdown) = units::quantity_hover(source, offset) {
+        return Some(markdown);
+    }
+    let word = &source[word_at(source, offset)?];
+    if settings.eval_on_hover
+        && let Some(value) = top_level_value(source, word, settings)
+    {
+        return Some(format!("```cadenza\n{word} = {value}\n```"));
+    }
+    Some(format!("Symbol: `{word}`\n\nType information coming soon!"))
+}
+
+/// Returns the completions at `offset`: unit suffixes after a numeric
+/// literal (see [`units::unit_completions`]), and keywords otherwise.
+pub fn completions(source: &str, offset: usize) -> Vec<CompletionItem> {
+    if let Some(items) = units::unit_completions(source, offset) {
+        return items;
+    }
+    vec![
+        CompletionItem {
+            label: "let".to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            detail: Some("Variable binding".to_string()),
+            ..Default::default()
+        },
+        CompletionItem {
+            label: "fn".to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            detail: Some("Function definition".to_string()),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Evaluates `source` and returns the value it binds to `name`, unless that
+/// is a builtin.
+fn top_level_value(source: &str, name: &str, settings: &Settings) -> Option<Value> {
+    let parsed = cadenza_syntax::parse::parse(source);
+    if !parsed.errors.is_empty() {
+        return None;
+    }
+    let options = CompileOptions {
+        fuel: Some(settings.fuel),
+        ..CompileOptions::default()
+    };
+    let mut env = Env::with_options(&options);
+    let mut compiler = Compiler::with_options(options);
+    cadenza_eval::eval(&parsed.ast(), &mut env, &mut compiler);
+    match env.get(name.into())? {
+        Value::BuiltinFn(_) | Value::BuiltinMacro(_) | Value::SpecialForm(_) => None,
+        value => Some(value.clone()),
+    }
+}
+
 /// Convert a byte offset to an LSP Position.
 pub fn offset_to_position(text: &str, offset: usize) -> Position {
     let mut line = 0;
@@ -103,4 +315,97 @@ mod tests {
         );
         assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
     }
+
+    #[test]
+    fn test_check_to_diagnostics() {
+        let source = "fn area w = w * w\nfn _unused x = x\n";
+        let diagnostics = check_to_diagnostics(source);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostics[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 3), Position::new(1, 10))
+        );
+    }
+
+    #[test]
+    fn test_check_module_resolves_imports() {
+        let resolve = |name: &str| match name {
+            "geometry" => {
+                Some("import units\nfn square x = x * x\nfn _broken x = missing\n".into())
+            }
+            "units" => Some("fn scale x = x * 2\n".into()),
+            _ => None,
+        };
+        let source = "import geometry\nlet a = square (scale 3)\n";
+        let diagnostics =
+            check_module_to_diagnostics("main", source, &Settings::default(), resolve);
+        // The unused function in `geometry` isn't reported for `main`
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+        let diagnostics =
+            check_module_to_diagnostics("main", "import missing\n", &Settings::default(), resolve);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn test_lint_levels() {
+        let source = "fn _unused x = x\n";
+        let mut settings = Settings {
+            warnings: LintLevel::Deny,
+            ..Settings::default()
+        };
+        let diagnostics = check_module_to_diagnostics("main", source, &settings, |_| None);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("W0001".to_string()))
+        );
+
+        settings.lints.insert("W0001".to_string(), LintLevel::Allow);
+        assert!(check_module_to_diagnostics("main", source, &settings, |_| None).is_empty());
+    }
+
+    #[test]
+    fn test_hover_evaluates_when_enabled() {
+        let source = "let answer = 6 * 7\nanswer\n";
+        let offset = source.rfind("answer").unwrap() + 2;
+        let mut settings = Settings::default();
+        assert_eq!(
+            hover_markdown(source, offset, &settings).unwrap(),
+            "Symbol: `answer`\n\nType information coming soon!"
+        );
+
+        settings.eval_on_hover = true;
+        assert_eq!(
+            hover_markdown(source, offset, &settings).unwrap(),
+            "```cadenza\nanswer = 42\n```"
+        );
+        // Builtins aren't shown as values
+        let source = "typeof 1\n";
+        assert!(
+            hover_markdown(source, 1, &settings)
+                .unwrap()
+                .starts_with("Symbol")
+        );
+        assert_eq!(hover_markdown("  ", 1, &settings), None);
+    }
+
+    #[test]
+    fn test_check_reports_independent_errors() {
+        // `y` and `x + 1` depend on the failed `x`, so only the two root
+        // causes are reported
+        let source = "let x = missing\nlet y = x + 1\nx * y\n1 / 0\n";
+        let diagnostics = check_to_diagnostics(source);
+        assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+        );
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 8));
+        assert_eq!(diagnostics[1].range.start.line, 3);
+    }
 }
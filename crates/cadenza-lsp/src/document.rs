@@ -0,0 +1,214 @@
+//! Versioned documents and answers.
+//!
+//! Editors number each version of a document, and keep sending edits while
+//! the server works on a hover or code lens for an older one. So answers are
+//! [`Versioned`]: they record the version they were computed from, and the
+//! span they cover along with the text it held. Before returning an answer,
+//! the server [validates](Document::validate) it against the current
+//! document, so a result for text that has since changed is dropped instead
+//! of pointing at the wrong place.
+//!
+//! Hovers are also cached by span ([`Document::hover`]): hovering anywhere in
+//! the same word reuses the answer until the document changes.
+//!
+//! ```
+//! use cadenza_lsp::{Settings, document::Document};
+//!
+//! let mut document = Document::new(1, "let width = 3\n".to_string());
+//! let answer = document.hover(5, &Settings::default()).unwrap();
+//! assert_eq!(answer.span, 4..9);
+//!
+//! // The word moved, so the answer computed for version 1 is stale
+//! document.update(2, "let  width = 3\n".to_string());
+//! assert!(document.validate(answer).is_none());
+//! ```
+
+use crate::{Settings, core, units};
+use std::{collections::HashMap, ops::Range};
+
+/// An answer computed from one version of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Versioned<T> {
+    /// The document version the answer was computed from.
+    pub version: i32,
+    /// The byte range the answer is about.
+    pub span: Range<usize>,
+    /// The text of `span` in that version.
+    pub text: String,
+    pub value: T,
+}
+
+impl<T> Versioned<T> {
+    /// Creates an answer about `span` of `source`, at `version`.
+    pub fn new(version: i32, source: &str, span: Range<usize>, value: T) -> Self {
+        Self {
+            version,
+            text: source[span.clone()].to_string(),
+            span,
+            value,
+        }
+    }
+}
+
+/// An open document: its latest version and text, and the hovers computed
+/// for it.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    version: i32,
+    text: String,
+    /// Hovers for the current version, keyed by the span they cover.
+    hovers: HashMap<Range<usize>, Versioned<String>>,
+}
+
+impl Document {
+    /// Creates a document at `version`.
+    pub fn new(version: i32, text: String) -> Self {
+        Self {
+            version,
+            text,
+            hovers: HashMap::new(),
+        }
+    }
+
+    /// Returns the current version.
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Returns the current text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text with `version`, dropping the cached hovers.
+    ///
+    /// Versions older than the current one arrived out of order and are
+    /// ignored. Returns whether the document changed.
+    pub fn update(&mut self, version: i32, text: String) -> bool {
+        if version < self.version {
+            return false;
+        }
+        self.version = version;
+        if text != self.text {
+            self.text = text;
+            self.hovers.clear();
+        }
+        true
+    }
+
+    /// Returns `answer` if it still applies to the current version.
+    ///
+    /// An answer from the current version always applies. One from another
+    /// version applies only if its span still holds the same text, as a whole
+    /// word, since anything else means the span moved or was edited. The
+    /// returned answer is marked with the current version, but its value is
+    /// still the one computed from its own version.
+    pub fn validate<T>(&self, answer: Versioned<T>) -> Option<Versioned<T>> {
+        if answer.version == self.version {
+            return Some(answer);
+        }
+        let span = answer.span.clone();
+        let unchanged = self.text.get(span.clone()) == Some(answer.text.as_str())
+            && !continues_word(&self.text, span.start, span.end);
+        unchanged.then(|| Versioned {
+            version: self.version,
+            ..answer
+        })
+    }
+
+    /// Returns the hover for `offset`, from the cache if the same span was
+    /// hovered in this version.
+    pub fn hover(&mut self, offset: usize, settings: &Settings) -> Option<Versioned<String>> {
+        if let Some(cached) = self.cached_hover(offset) {
+            return Some(cached);
+        }
+        let answer = hover(&self.text, self.version, offset, settings)?;
+        self.cache_hover(answer.clone());
+        Some(answer)
+    }
+
+    /// Caches a hover computed elsewhere, if it is for the current version.
+    pub fn cache_hover(&mut self, answer: Versioned<String>) {
+        if answer.version == self.version {
+            self.hovers.insert(answer.span.clone(), answer);
+        }
+    }
+
+    /// Returns a hover cached for `offset` in the current version.
+    pub fn cached_hover(&self, offset: usize) -> Option<Versioned<String>> {
+        self.hovers
+            .values()
+            .find(|hover| hover.span.contains(&offset))
+            .cloned()
+    }
+}
+
+/// Returns the hover for `offset` in version `version` of `source`, covering
+/// the quantity literal or word around it (see [`core::hover_markdown`]).
+pub fn hover(
+    source: &str,
+    version: i32,
+    offset: usize,
+    settings: &Settings,
+) -> Option<Versioned<String>> {
+    let span = match units::quantity_at(source, offset) {
+        Some((number, unit)) => number.start..unit.end,
+        None => core::word_at(source, offset)?,
+    };
+    let markdown = core::hover_markdown(source, offset, settings)?;
+    Some(Versioned::new(version, source, span, markdown))
+}
+
+/// Returns true if an identifier character touches `start..end` on either
+/// side, so the span is now part of a longer word.
+fn continues_word(text: &str, start: usize, end: usize) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    text[..start].chars().next_back().is_some_and(is_word)
+        || text[end..].chars().next().is_some_and(is_word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_answers_whose_span_is_unchanged() {
+        let mut document = Document::new(1, "let width = 3\nwidth\n".to_string());
+        let answer = document.hover(1, &Settings::default()).unwrap();
+        assert_eq!(answer.span, 0..3);
+
+        // An edit after the span leaves it in place
+        document.update(2, "let width = 4\nwidth\n".to_string());
+        let answer = document.validate(answer).unwrap();
+        assert_eq!(answer.version, 2);
+
+        // Typing into the word makes it a different word
+        document.update(3, "lets width = 4\nwidth\n".to_string());
+        assert!(document.validate(answer).is_none());
+    }
+
+    #[test]
+    fn caches_hovers_by_span() {
+        let mut document = Document::new(1, "let width = 3\n".to_string());
+        let first = document.hover(4, &Settings::default()).unwrap();
+        assert_eq!(document.cached_hover(8), Some(first.clone()));
+
+        // Out-of-order versions are ignored
+        assert!(!document.update(0, "let w = 3\n".to_string()));
+        assert_eq!(document.text(), "let width = 3\n");
+
+        document.update(2, "let height = 3\n".to_string());
+        assert_eq!(document.cached_hover(8), None);
+        // A stale answer isn't cached for the new version
+        document.cache_hover(first);
+        assert_eq!(document.cached_hover(8), None);
+    }
+
+    #[test]
+    fn hovers_cover_quantity_literals() {
+        let source = "measure millimeter\nlet x = 25.4millimeter\n";
+        let offset = source.rfind("millimeter").unwrap() + 2;
+        let answer = hover(source, 1, offset, &Settings::default()).unwrap();
+        assert_eq!(answer.text, "25.4millimeter");
+    }
+}
@@ -0,0 +1,224 @@
+//! Edits to a module's `import` lines.
+//!
+//! These back the "add missing import" and "organize imports" code actions.
+//! Both only touch top-level `import` forms whose arguments are all module
+//! names and which sit on lines of their own; anything else is left alone
+//! rather than risk dropping code or comments.
+
+use crate::core::offset_to_position;
+use cadenza_eval::{InternedString, extract_identifier};
+use cadenza_syntax::{ast::Expr, parse::parse};
+use lsp_types::{Range, TextEdit};
+use std::ops::Range as ByteRange;
+
+/// A top-level `import` form.
+struct ImportLine {
+    /// The lines the form occupies, including the trailing newline.
+    lines: ByteRange<usize>,
+    /// The imported module names.
+    modules: Vec<InternedString>,
+}
+
+/// Returns the top-level imports of `source`, or `None` if any of them can't
+/// be rewritten safely.
+fn import_lines(source: &str) -> Option<Vec<ImportLine>> {
+    let parsed = parse(source);
+    let mut imports = Vec::new();
+    for item in parsed.ast().items() {
+        let Expr::Apply(apply) = &item else {
+            continue;
+        };
+        let is_import = apply
+            .callee()
+            .and_then(|callee| extract_identifier(&callee))
+            .is_some_and(|id| &*id == "import");
+        if !is_import {
+            continue;
+        }
+
+        let arguments = apply.all_arguments();
+        let modules = arguments
+            .iter()
+            .map(extract_identifier)
+            .collect::<Option<Vec<_>>>()?;
+        // The form ends at its last module name; the item's span also covers
+        // any trailing comment and whitespace
+        let span = item.span();
+        let text_end = arguments.last().map_or(span.end, |arg| arg.span().end);
+        let text = &source[span.start..text_end];
+        let start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let end = source[text_end..]
+            .find('\n')
+            .map_or(source.len(), |i| text_end + i + 1);
+        // Something else shares the line, like a trailing comment
+        if source[start..end].trim() != text {
+            return None;
+        }
+        imports.push(ImportLine {
+            lines: start..end,
+            modules,
+        });
+    }
+    Some(imports)
+}
+
+/// Returns an edit replacing `range` of `source` with `new_text`.
+fn text_edit(source: &str, range: ByteRange<usize>, new_text: String) -> TextEdit {
+    TextEdit {
+        range: Range::new(
+            offset_to_position(source, range.start),
+            offset_to_position(source, range.end),
+        ),
+        new_text,
+    }
+}
+
+/// Returns the edits that sort and dedupe the imports of `source`, one module
+/// per line, gathered where the first import is.
+///
+/// Returns no edits if the imports are already organized or can't be
+/// rewritten safely.
+pub fn organize_imports(source: &str) -> Vec<TextEdit> {
+    let Some(imports) = import_lines(source) else {
+        return Vec::new();
+    };
+    let Some(first) = imports.first() else {
+        return Vec::new();
+    };
+
+    let mut modules: Vec<&str> = imports
+        .iter()
+        .flat_map(|import| import.modules.iter().map(|module| &**module))
+        .collect();
+    modules.sort_unstable();
+    modules.dedup();
+    let block: String = modules
+        .iter()
+        .map(|module| format!("import {module}\n"))
+        .collect();
+
+    // Already organized when the imports are exactly the block, in one run
+    let run = first.lines.start..imports.last().unwrap().lines.end;
+    let contiguous = imports
+        .windows(2)
+        .all(|pair| pair[0].lines.end == pair[1].lines.start);
+    let current = &source[run];
+    if contiguous && (current == block || current == block.trim_end_matches('\n')) {
+        return Vec::new();
+    }
+
+    // A file ending in its only import, without a newline, keeps that shape
+    let block = if first.lines.end == source.len() && !source.ends_with('\n') {
+        block.trim_end_matches('\n').to_string()
+    } else {
+        block
+    };
+
+    let mut edits = vec![text_edit(source, first.lines.clone(), block)];
+    for import in &imports[1..] {
+        edits.push(text_edit(source, import.lines.clone(), String::new()));
+    }
+    edits
+}
+
+/// Returns the edit that adds `import module` to `source`, keeping sorted
+/// imports sorted, or `None` if `module` is already imported.
+pub fn add_import(source: &str, module: &str) -> Option<TextEdit> {
+    let imports = import_lines(source).unwrap_or_default();
+    if imports
+        .iter()
+        .any(|import| import.modules.iter().any(|name| &**name == module))
+    {
+        return None;
+    }
+
+    let line = format!("import {module}\n");
+    let edit = match imports.iter().find(|import| &*import.modules[0] > module) {
+        Some(next) => text_edit(source, next.lines.start..next.lines.start, line),
+        None => match imports.last() {
+            Some(last) if !source[..last.lines.end].ends_with('\n') => {
+                text_edit(source, last.lines.end..last.lines.end, format!("\n{line}"))
+            }
+            Some(last) => text_edit(source, last.lines.end..last.lines.end, line),
+            // Separate the first import from the code below it
+            None if source.trim().is_empty() => text_edit(source, 0..0, line),
+            None => text_edit(source, 0..0, format!("{line}\n")),
+        },
+    };
+    Some(edit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::position_to_offset;
+
+    /// Applies non-overlapping `edits` to `source`.
+    fn apply(source: &str, edits: &[TextEdit]) -> String {
+        let mut edits: Vec<_> = edits
+            .iter()
+            .map(|edit| {
+                (
+                    position_to_offset(source, edit.range.start),
+                    position_to_offset(source, edit.range.end),
+                    &edit.new_text,
+                )
+            })
+            .collect();
+        edits.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+        let mut result = source.to_string();
+        for (start, end, text) in edits {
+            result.replace_range(start..end, text);
+        }
+        result
+    }
+
+    #[test]
+    fn organizes_imports() {
+        let source = "import units parsing\nlet a = 1\nimport geometry\nimport units\n";
+        assert_eq!(
+            apply(source, &organize_imports(source)),
+            "import geometry\nimport parsing\nimport units\nlet a = 1\n"
+        );
+
+        // Nothing to do when already organized
+        let organized = "import geometry\nimport units\n\nlet a = 1\n";
+        assert!(organize_imports(organized).is_empty());
+        assert!(organize_imports("import geometry").is_empty());
+        assert!(organize_imports("let a = 1\n").is_empty());
+        assert_eq!(
+            apply("import b a b", &organize_imports("import b a b")),
+            "import a\nimport b"
+        );
+
+        // Comments on import lines are kept by not organizing
+        assert!(organize_imports("import units # lengths\nimport geometry\n").is_empty());
+    }
+
+    #[test]
+    fn adds_missing_imports_in_order() {
+        let add = |source: &str, module| apply(source, &[add_import(source, module).unwrap()]);
+
+        let source = "import geometry\nimport units\n\nlet a = 1\n";
+        assert_eq!(
+            add(source, "parsing"),
+            "import geometry\nimport parsing\nimport units\n\nlet a = 1\n"
+        );
+        assert_eq!(
+            add(source, "algebra"),
+            "import algebra\nimport geometry\nimport units\n\nlet a = 1\n"
+        );
+        assert_eq!(
+            add(source, "vectors"),
+            "import geometry\nimport units\nimport vectors\n\nlet a = 1\n"
+        );
+        assert_eq!(add("let a = 1\n", "units"), "import units\n\nlet a = 1\n");
+        assert_eq!(
+            add("import units", "vectors"),
+            "import units\nimport vectors\n"
+        );
+        assert_eq!(add("", "units"), "import units\n");
+
+        assert!(add_import(source, "units").is_none());
+    }
+}
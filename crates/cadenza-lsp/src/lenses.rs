@@ -0,0 +1,77 @@
+//! Code lenses.
+//!
+//! Each top-level function gets a lens over its name counting the references
+//! to it in the document. The count is syntactic, like
+//! [`cadenza_eval::dead_code`]: every identifier with the function's name
+//! counts, even if a local binding shadows it.
+
+use crate::document::Versioned;
+use cadenza_eval::{
+    CadenzaDbImpl, SourceFile,
+    symbol_index::{SymbolKind, file_symbols},
+};
+use cadenza_syntax::{parse::parse, token::Kind};
+
+/// Returns a lens titled with the reference count (`2 references`) for each
+/// top-level function in version `version` of `source`.
+pub fn code_lenses(source: &str, version: i32) -> Vec<Versioned<String>> {
+    let db = CadenzaDbImpl::default();
+    let file = SourceFile::new(&db, String::new(), source.to_string());
+    let index = file_symbols(&db, file);
+    let mut functions: Vec<_> = index
+        .symbols()
+        .iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Function)
+        .collect();
+    functions.sort_by_key(|symbol| symbol.span.start);
+    if functions.is_empty() {
+        return Vec::new();
+    }
+
+    let identifiers: Vec<String> = parse(source)
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| {
+            let token = element.as_token()?;
+            (token.kind() == Kind::Identifier).then(|| token.text().to_string())
+        })
+        .collect();
+
+    functions
+        .into_iter()
+        .map(|symbol| {
+            // The definition's own name isn't a reference
+            let references = identifiers
+                .iter()
+                .filter(|identifier| **identifier == symbol.name)
+                .count()
+                - 1;
+            let title = match references {
+                1 => "1 reference".to_string(),
+                n => format!("{n} references"),
+            };
+            Versioned::new(version, source, symbol.span.start..symbol.span.end, title)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_references() {
+        let source = "fn square x = x * x\nfn cube x = x * square x\nsquare 2\n";
+        let lenses: Vec<(&str, String)> = code_lenses(source, 3)
+            .into_iter()
+            .map(|lens| (&source[lens.span], lens.value))
+            .collect();
+        assert_eq!(
+            lenses,
+            [
+                ("square", "2 references".to_string()),
+                ("cube", "0 references".to_string()),
+            ]
+        );
+    }
+}
@@ -5,8 +5,20 @@
 //! - WASM LSP server (via wasm-bindgen in cadenza-web)
 
 pub mod core;
+pub mod document;
+pub mod imports;
+pub mod lenses;
+pub mod settings;
+pub mod units;
 
-pub use core::{offset_to_position, parse_to_diagnostics, position_to_offset};
+pub use core::{
+    check_module_to_diagnostics, check_module_to_diagnostics_with, check_to_diagnostics,
+    completions, hover_markdown, offset_to_position, parse_to_diagnostics,
+    parse_to_diagnostics_with, position_to_offset, suggestion,
+};
+
+pub use document::{Document, Versioned};
+pub use settings::Settings;
 
 // Re-export lsp_types for consumers
 pub use lsp_types;
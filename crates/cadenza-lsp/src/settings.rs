@@ -0,0 +1,116 @@
+//! Editor settings.
+//!
+//! Both language servers read the same [`Settings`]: the native server from
+//! `initializationOptions` and `workspace/didChangeConfiguration`, and the web
+//! playground through `lsp_configure`. Settings may be given directly or
+//! under a `cadenza` section, and every field is optional:
+//!
+//! ```json
+//! {
+//!   "cadenza": {
+//!     "warnings": "warn",
+//!     "lints": { "W0001": "allow" },
+//!     "evalOnHover": true,
+//!     "fuel": 1000000
+//!   }
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The default evaluation fuel budget, so a non-terminating program can't
+/// stall the editor.
+pub const DEFAULT_FUEL: u64 = 1_000_000;
+
+/// How a warning is reported in the editor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// The warning is hidden.
+    Allow,
+    /// The warning is shown as a warning.
+    #[default]
+    Warn,
+    /// The warning is shown as an error.
+    Deny,
+}
+
+/// Language server settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// How warnings are reported.
+    pub warnings: LintLevel,
+    /// Per-warning overrides of [`Settings::warnings`], keyed by diagnostic
+    /// code (e.g. `W0001` for unused functions).
+    pub lints: BTreeMap<String, LintLevel>,
+    /// Whether hovering a top-level binding evaluates the document and shows
+    /// its value.
+    pub eval_on_hover: bool,
+    /// The evaluation step budget for checking and hover evaluation.
+    pub fuel: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            warnings: LintLevel::Warn,
+            lints: BTreeMap::new(),
+            eval_on_hover: false,
+            fuel: DEFAULT_FUEL,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads settings from a client's JSON, either at the top level or under a
+    /// `cadenza` section.
+    ///
+    /// # Errors
+    ///
+    /// Returns the deserialization error if the JSON isn't valid settings.
+    pub fn from_json(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        let value = match value {
+            serde_json::Value::Object(mut object) if object.contains_key("cadenza") => {
+                object.remove("cadenza").unwrap_or_default()
+            }
+            serde_json::Value::Null => return Ok(Self::default()),
+            value => value,
+        };
+        serde_json::from_value(value)
+    }
+
+    /// Returns how the warning with diagnostic `code` is reported.
+    pub fn lint_level(&self, code: &str) -> LintLevel {
+        self.lints.get(code).copied().unwrap_or(self.warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_settings_with_or_without_a_section() {
+        let settings = Settings::from_json(json!({
+            "cadenza": { "lints": { "W0001": "deny" }, "evalOnHover": true }
+        }))
+        .unwrap();
+        assert!(settings.eval_on_hover);
+        assert_eq!(settings.fuel, DEFAULT_FUEL);
+        assert_eq!(settings.lint_level("W0001"), LintLevel::Deny);
+        assert_eq!(settings.lint_level("W0002"), LintLevel::Warn);
+
+        let settings = Settings::from_json(json!({ "warnings": "allow", "fuel": 10 })).unwrap();
+        assert_eq!(settings.lint_level("W0002"), LintLevel::Allow);
+        assert_eq!(settings.fuel, 10);
+
+        assert_eq!(
+            Settings::from_json(serde_json::Value::Null).unwrap(),
+            Settings::default()
+        );
+        assert!(Settings::from_json(json!({ "warnings": "loud" })).is_err());
+    }
+}
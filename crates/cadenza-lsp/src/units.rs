@@ -0,0 +1,263 @@
+//! Hover and completion for unit measures.
+//!
+//! Units come from the document's top-level `measure` definitions. Only those
+//! are evaluated, not the rest of the document, so this is cheap and doesn't
+//! depend on [`Settings::eval_on_hover`](crate::Settings::eval_on_hover).
+
+use crate::core::offset_to_position;
+use cadenza_eval::{Compiler, Env, Unit, UnitRegistry, extract_identifier};
+use cadenza_syntax::{ast::Expr, parse::parse};
+use lsp_types::{CompletionItem, CompletionItemKind, CompletionTextEdit, TextEdit};
+use std::ops::Range;
+
+/// Returns whether `expr` is a `measure` definition, with or without a
+/// conversion (`measure inch = millimeter 25.4`).
+fn is_measure(expr: &Expr) -> bool {
+    let Expr::Apply(apply) = expr else {
+        return false;
+    };
+    match apply
+        .callee()
+        .and_then(|callee| extract_identifier(&callee))
+        .as_deref()
+    {
+        Some("measure") => true,
+        Some("=") => apply.all_arguments().first().is_some_and(is_measure),
+        _ => false,
+    }
+}
+
+/// Evaluates the `measure` definitions in `source`, returning the compiler
+/// holding their units.
+fn measures(source: &str) -> Compiler {
+    let definitions: Vec<&str> = parse(source)
+        .ast()
+        .items()
+        .filter(is_measure)
+        .map(|item| {
+            let span = item.span();
+            &source[span.start..span.end]
+        })
+        .collect();
+
+    let mut env = Env::with_standard_builtins();
+    let mut compiler = Compiler::new();
+    cadenza_eval::eval(
+        &parse(&definitions.join("\n")).ast(),
+        &mut env,
+        &mut compiler,
+    );
+    compiler
+}
+
+/// Returns the units in the same dimension as `unit`, other than `unit`,
+/// sorted by name.
+fn siblings<'a>(units: &'a UnitRegistry, unit: &Unit) -> Vec<&'a Unit> {
+    let mut siblings: Vec<&Unit> = units
+        .all_units()
+        .filter(|other| other.dimension == unit.dimension && other.name != unit.name)
+        .collect();
+    siblings.sort_by(|a, b| (*a.name).cmp(&*b.name));
+    siblings
+}
+
+/// Formats `value` with at most six decimals, so conversions don't show
+/// floating point noise.
+fn format_number(value: f64) -> String {
+    let formatted = format!("{value:.6}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Finds the quantity literal (`25.4millimeter`) around `offset`, returning
+/// the ranges of its number and its unit.
+pub(crate) fn quantity_at(source: &str, offset: usize) -> Option<(Range<usize>, Range<usize>)> {
+    let is_part = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+    let start = source[..offset]
+        .rfind(|c: char| !is_part(c))
+        .map_or(0, |i| i + 1);
+    let end = source[offset..]
+        .find(|c: char| !is_part(c))
+        .map_or(source.len(), |i| offset + i);
+    let literal = &source[start..end];
+
+    let number_len = literal
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+        .unwrap_or(literal.len());
+    let unit = &literal[number_len..];
+    let starts_with_digit = literal.starts_with(|c: char| c.is_ascii_digit());
+    if !starts_with_digit || !unit.starts_with(|c: char| c.is_alphabetic()) {
+        return None;
+    }
+    Some((start..start + number_len, start + number_len..end))
+}
+
+/// Returns the hover text, as Markdown, for the quantity literal around
+/// `offset`: its dimension, its value in the base unit, and its value in each
+/// other unit of the dimension.
+///
+/// Returns `None` if there is no quantity literal there, or its unit isn't
+/// defined by a `measure` in `source`.
+pub fn quantity_hover(source: &str, offset: usize) -> Option<String> {
+    let (number, unit) = quantity_at(source, offset)?;
+    let value: f64 = source[number.clone()].replace('_', "").parse().ok()?;
+    let compiler = measures(source);
+    let units = compiler.units();
+    let unit = units.get(source[unit].into())?;
+    let base = units.get(unit.dimension.base_unit)?;
+
+    let mut markdown = format!(
+        "```cadenza\n{}{}\n```\n\nDimension: `{}`",
+        &source[number], &*unit.name, &*unit.dimension.base_unit,
+    );
+    if unit.name != base.name {
+        let base_value = unit.convert_to(value, base)?;
+        markdown.push_str(&format!(
+            "\n\nBase value: `{}{}`",
+            format_number(base_value),
+            &*base.name
+        ));
+    }
+
+    let conversions: Vec<String> = siblings(units, unit)
+        .into_iter()
+        .filter_map(|sibling| {
+            let converted = unit.convert_to(value, sibling)?;
+            Some(format!(
+                "- `{}{}`",
+                format_number(converted),
+                &*sibling.name
+            ))
+        })
+        .collect();
+    if !conversions.is_empty() {
+        markdown.push_str("\n\nConversions:\n");
+        markdown.push_str(&conversions.join("\n"));
+    }
+    Some(markdown)
+}
+
+/// Returns the unit suffixes to complete after the numeric literal ending at
+/// `offset`, or `None` if there isn't one.
+///
+/// The literal may already have the start of a suffix (`25.4mi`), which the
+/// completions replace.
+pub fn unit_completions(source: &str, offset: usize) -> Option<Vec<CompletionItem>> {
+    let before = &source[..offset];
+    let word_start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .map_or(0, |i| i + 1);
+    let word = &before[word_start..];
+    let number_len = word
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+        .unwrap_or(word.len());
+    if !word.starts_with(|c: char| c.is_ascii_digit())
+        || word[number_len..].contains(|c: char| !(c.is_alphanumeric() || c == '_'))
+    {
+        return None;
+    }
+
+    let suffix = lsp_types::Range::new(
+        offset_to_position(source, word_start + number_len),
+        offset_to_position(source, offset),
+    );
+
+    let compiler = measures(source);
+    let mut units: Vec<&Unit> = compiler.units().all_units().collect();
+    units.sort_by(|a, b| (*a.name).cmp(&*b.name));
+    let items = units
+        .into_iter()
+        .map(|unit| {
+            let detail = if unit.name == unit.dimension.base_unit {
+                "base unit".to_string()
+            } else {
+                format!("unit of {}", &*unit.dimension.base_unit)
+            };
+            CompletionItem {
+                label: unit.name.to_string(),
+                kind: Some(CompletionItemKind::UNIT),
+                detail: Some(detail),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range: suffix,
+                    new_text: unit.name.to_string(),
+                })),
+                ..Default::default()
+            }
+        })
+        .collect();
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+measure millimeter
+measure inch = millimeter 25.4
+measure meter = millimeter 1000
+measure second
+let x = 25.4millimeter
+let y = 2inch
+let z = 25.4
+";
+
+    #[test]
+    fn hovers_quantities_with_conversions() {
+        let offset = SOURCE.find("25.4millimeter").unwrap() + 6;
+        assert_eq!(
+            quantity_hover(SOURCE, offset).unwrap(),
+            "```cadenza\n25.4millimeter\n```\n\n\
+             Dimension: `millimeter`\n\n\
+             Conversions:\n\
+             - `1inch`\n\
+             - `0.0254meter`"
+        );
+
+        let offset = SOURCE.find("2inch").unwrap();
+        assert_eq!(
+            quantity_hover(SOURCE, offset).unwrap(),
+            "```cadenza\n2inch\n```\n\n\
+             Dimension: `millimeter`\n\n\
+             Base value: `50.8millimeter`\n\n\
+             Conversions:\n\
+             - `0.0508meter`\n\
+             - `50.8millimeter`"
+        );
+
+        // Plain numbers and unknown units aren't quantities
+        assert_eq!(quantity_hover(SOURCE, SOURCE.rfind("25.4").unwrap()), None);
+        assert_eq!(quantity_hover("let a = 3furlong\n", 10), None);
+    }
+
+    #[test]
+    fn completes_units_after_numbers() {
+        let source = format!("{SOURCE}let w = 3me");
+        let labels = |source: &str, offset| {
+            unit_completions(source, offset)
+                .map(|items| items.into_iter().map(|item| item.label).collect::<Vec<_>>())
+        };
+        let all = Some(vec![
+            "inch".to_string(),
+            "meter".to_string(),
+            "millimeter".to_string(),
+            "second".to_string(),
+        ]);
+        assert_eq!(labels(&source, source.len()), all);
+        assert_eq!(labels(&source, source.len() - 2), all);
+
+        let item = &unit_completions(&source, source.len()).unwrap()[0];
+        assert_eq!(item.detail.as_deref(), Some("unit of millimeter"));
+        // The typed part of the suffix is replaced
+        let Some(CompletionTextEdit::Edit(edit)) = &item.text_edit else {
+            panic!("expected a text edit");
+        };
+        assert_eq!(edit.range.end.character - edit.range.start.character, 2);
+
+        // Not after identifiers or spaces
+        assert_eq!(labels("let x1", 6), None);
+        assert_eq!(labels("let x = 3 ", 10), None);
+    }
+}
@@ -12,7 +12,7 @@ pub mod testing;
 mod generated;
 
 pub use error::{Error, Result};
-pub use syntax::parse;
+pub use syntax::{MarkdownFrontend, parse};
 
 #[cfg(test)]
 mod fuzz;
@@ -9,7 +9,7 @@
 //! - **Parser**: Builds GreenNode CST using cadenza-syntax token kinds
 //! - **AST**: Markdown elements become Apply nodes that call macros with content
 
-use cadenza_syntax::{parse::Parse, token::Kind};
+use cadenza_syntax::{frontend::Frontend, parse::Parse, token::Kind};
 use cadenza_tree::GreenNodeBuilder;
 
 /// Parse Markdown source into a Cadenza-compatible AST.
@@ -17,6 +17,24 @@ pub fn parse(src: &str) -> Parse {
     Parser::new(src).parse()
 }
 
+/// The Markdown front-end, for `.md` and `.markdown` files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownFrontend;
+
+impl Frontend for MarkdownFrontend {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["md", "markdown"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        parse(source)
+    }
+}
+
 struct Parser<'src> {
     src: &'src str,
     pos: usize,
@@ -20,7 +20,7 @@
 //! // eval() doesn't care that this came from SQL - it's just an AST
 //! ```
 
-use cadenza_syntax::{parse::Parse, token::Kind};
+use cadenza_syntax::{frontend::Frontend, parse::Parse, token::Kind};
 use cadenza_tree::GreenNodeBuilder;
 
 /// Parse SQL source into a Cadenza-compatible AST.
@@ -28,6 +28,24 @@ pub fn parse(src: &str) -> Parse {
     Parser::new(src).parse()
 }
 
+/// The SQL front-end, for `.sql` files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlFrontend;
+
+impl Frontend for SqlFrontend {
+    fn name(&self) -> &str {
+        "sql"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sql"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        parse(source)
+    }
+}
+
 struct Parser<'src> {
     src: &'src str,
     pos: usize,
@@ -375,9 +375,7 @@ enum PrefixBindingPower {
 enum InfixBindingPower {
     /// Pipe operators: |>
     Pipe,
-    /// Range operators: .., ..=
-    Range,
-    /// Assignment operators: =, +=, -=, *=, /=, %=, &=, |=, ^=, <<=, >>=, ->, <-
+    /// Assignment operators: =, +=, -=, *=, /=, %=, &=, |=, ^=, <<=, >>=, ->, <-, :
     Assignment,
     /// Juxtaposition (function application)
     Juxtaposition,
@@ -391,6 +389,11 @@ enum InfixBindingPower {
     Equality,
     /// Comparison: <, <=, >, >=
     Comparison,
+    /// Range operators: .., ..=
+    ///
+    /// Tighter than juxtaposition, so `f 1..3` passes a range and
+    /// `for x in 1..n -> body` loops over one
+    Range,
     /// Bitwise OR: |
     BitwiseOr,
     /// Bitwise XOR: ^
@@ -505,6 +508,7 @@ impl Punctuation {
             p("CaretEqual", "^=").infix(InfixBindingPower::Assignment),
             p("LessLessEqual", "<<=").infix(InfixBindingPower::Assignment),
             p("GreaterGreaterEqual", ">>=").infix(InfixBindingPower::Assignment),
+            p("Colon", ":").infix(InfixBindingPower::Assignment),
             p("PipePipe", "||").infix(InfixBindingPower::LogicalOr),
             p("AmpersandAmpersand", "&&").infix(InfixBindingPower::LogicalAnd),
             p("EqualEqual", "==").infix(InfixBindingPower::Equality),
@@ -527,11 +531,10 @@ impl Punctuation {
             p("Dot", ".").infix(InfixBindingPower::FieldAccess),
             p("ColonColon", "::").infix(InfixBindingPower::PathAccess),
             // Non-operator punctuation
-            p("Backslash", "\\"),
+            p("Backslash", "\\").prefix(PrefixBindingPower::Unary),
             p("Backtick", "`"),
             p("SingleQuote", "'"),
             p("Comma", ","),
-            p("Colon", ":"),
             p("Semicolon", ";"),
             p("LParen", "("),
             p("RParen", ")"),
@@ -542,6 +545,8 @@ impl Punctuation {
             p("RBracket", "]"),
             p("StringStart", "\""),
             p("StringEnd", "\"").dup(),
+            p("InterpolationStart", "{").dup(),
+            p("InterpolationEnd", "}").dup(),
             p("CommentStart", "#").trivia(),
             p("DocCommentStart", "##"),
             p("Space", " ").ws(),
@@ -634,7 +639,7 @@ impl Literal {
             Literal { name }
         }
 
-        [l("Integer"), l("Float"), l("Identifier")]
+        [l("Integer"), l("Float"), l("Decimal"), l("Identifier")]
     };
 }
 
@@ -680,6 +685,7 @@ impl Synthetic {
             s("SyntheticTuple", "__tuple__"),
             s("SyntheticBlock", "__block__"),
             s("SyntheticIndex", "__index__"),
+            s("SyntheticInterpolation", "__interpolate__"),
             // Markdown elements
             s("SyntheticMarkdownH1", "h1"),
             s("SyntheticMarkdownH2", "h2"),
@@ -694,6 +700,15 @@ impl Synthetic {
             s("SyntheticMarkdownEmphasis", "em"),
             s("SyntheticMarkdownStrong", "strong"),
             s("SyntheticMarkdownCodeInline", "code_inline"),
+            // Tables of delimited data, such as CSV
+            s("SyntheticTable", "__table__"),
+            // Record fields implied by the structure of a document, such as
+            // the tables of a TOML file
+            s("SyntheticField", "="),
+            // Escapes a document's strings spell differently from Cadenza's,
+            // such as JSON's `\u00e9`, kept as written and decoded when
+            // evaluated
+            s("SyntheticEscape", "__escape__"),
         ]
     };
 }
@@ -234,6 +234,7 @@ impl Literal {
 pub enum LiteralValue {
     Integer(IntegerValue),
     Float(FloatValue),
+    Decimal(DecimalValue),
     String(StringValue),
     StringWithEscape(StringValueWithEscape),
 }
@@ -243,6 +244,7 @@ impl fmt::Debug for LiteralValue {
         match self {
             Self::Integer(value) => write!(f, "{value:?}"),
             Self::Float(value) => write!(f, "{value:?}"),
+            Self::Decimal(value) => write!(f, "{value:?}"),
             Self::String(value) => write!(f, "{value:?}"),
             Self::StringWithEscape(value) => write!(f, "{value:?}"),
         }
@@ -254,6 +256,7 @@ impl LiteralValue {
         match node.kind() {
             Kind::Integer => Some(Self::Integer(IntegerValue::cast(node)?)),
             Kind::Float => Some(Self::Float(FloatValue::cast(node)?)),
+            Kind::Decimal => Some(Self::Decimal(DecimalValue::cast(node)?)),
             Kind::StringContent => Some(Self::String(StringValue::cast(node)?)),
             Kind::StringContentWithEscape => {
                 Some(Self::StringWithEscape(StringValueWithEscape::cast(node)?))
@@ -291,6 +294,22 @@ impl FloatValue {
     }
 }
 
+ast_node!(DecimalValue, Decimal);
+
+impl fmt::Debug for DecimalValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.text())
+    }
+}
+
+impl DecimalValue {
+    /// The digits of the literal, without the `d` suffix.
+    pub fn digits(&self) -> String {
+        let text = self.syntax().text().to_string();
+        text.strip_suffix('d').unwrap_or(&text).to_string()
+    }
+}
+
 ast_node!(StringValue, StringContent);
 
 impl fmt::Debug for StringValue {
@@ -301,6 +320,15 @@ impl fmt::Debug for StringValue {
 
 ast_node!(StringValueWithEscape, StringContentWithEscape);
 
+/// A malformed escape sequence in a string literal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EscapeError {
+    /// The escape sequence, from its backslash to where it went wrong.
+    pub span: Span,
+    /// What is wrong with it.
+    pub message: String,
+}
+
 impl StringValueWithEscape {
     /// Unescape the string literal, processing escape sequences.
     ///
@@ -311,34 +339,104 @@ impl StringValueWithEscape {
     /// - `\\` -> backslash
     /// - `\"` -> double quote
     /// - `\0` -> null character
-    pub fn unescaped(&self) -> Result<String, Span> {
+    /// - `\{` and `\}` -> braces, which would otherwise start an interpolation
+    /// - `\x41` -> the ASCII character with that code, two hex digits up to `7F`
+    /// - `\u{1F600}` -> the Unicode scalar value with that code point, one to
+    ///   six hex digits
+    pub fn unescaped(&self) -> Result<String, EscapeError> {
         let s = self.syntax().text();
         let mut result = String::with_capacity(s.len());
         let base_offset = self.span().start;
         let mut chars = s.char_indices().peekable();
 
         while let Some((idx, ch)) = chars.next() {
-            if ch == '\\' {
-                match chars.next() {
-                    Some((_next_idx, 'n')) => result.push('\n'),
-                    Some((_next_idx, 'r')) => result.push('\r'),
-                    Some((_next_idx, 't')) => result.push('\t'),
-                    Some((_next_idx, '\\')) => result.push('\\'),
-                    Some((_next_idx, '"')) => result.push('"'),
-                    Some((_next_idx, '0')) => result.push('\0'),
-                    Some((next_idx, c)) => {
-                        // Invalid escape sequence - point to backslash through the char
-                        let end = next_idx + c.len_utf8();
-                        return Err(Span::new(base_offset + idx, base_offset + end));
+            if ch != '\\' {
+                result.push(ch);
+                continue;
+            }
+            // Errors point from the backslash through the last character read
+            let error = |end: usize, message: String| EscapeError {
+                span: Span::new(base_offset + idx, base_offset + end),
+                message,
+            };
+            match chars.next() {
+                Some((_next_idx, 'n')) => result.push('\n'),
+                Some((_next_idx, 'r')) => result.push('\r'),
+                Some((_next_idx, 't')) => result.push('\t'),
+                Some((_next_idx, '\\')) => result.push('\\'),
+                Some((_next_idx, '"')) => result.push('"'),
+                Some((_next_idx, '0')) => result.push('\0'),
+                Some((_next_idx, c @ ('{' | '}'))) => result.push(c),
+                Some((next_idx, 'x')) => {
+                    let mut end = next_idx + 1;
+                    let mut code = 0;
+                    for _ in 0..2 {
+                        let Some((digit_idx, digit)) =
+                            chars.next_if(|(_, c)| c.is_ascii_hexdigit())
+                        else {
+                            return Err(error(end, "`\\x` takes two hex digits".into()));
+                        };
+                        end = digit_idx + 1;
+                        code = code * 16 + digit.to_digit(16).expect("hex digit");
                     }
-                    None => {
-                        // Invalid escape sequence - point to backslash through the char
-                        let end = idx + 1;
-                        return Err(Span::new(base_offset + idx, base_offset + end));
+                    if code > 0x7F {
+                        return Err(error(
+                            end,
+                            "`\\x` escapes are ASCII, up to `\\x7F`; use `\\u{...}` above".into(),
+                        ));
                     }
+                    result.push(char::from_u32(code).expect("ASCII is a char"));
+                }
+                Some((next_idx, 'u')) => {
+                    if chars.next_if(|(_, c)| *c == '{').is_none() {
+                        return Err(error(
+                            next_idx + 1,
+                            "`\\u` takes a code point in braces, like `\\u{1F600}`".into(),
+                        ));
+                    }
+                    let mut digits = String::new();
+                    let mut end = next_idx + 2;
+                    let closed = loop {
+                        match chars.next() {
+                            Some((brace_idx, '}')) => {
+                                end = brace_idx + 1;
+                                break true;
+                            }
+                            Some((digit_idx, c)) => {
+                                end = digit_idx + c.len_utf8();
+                                digits.push(c);
+                            }
+                            None => break false,
+                        }
+                    };
+                    if !closed {
+                        return Err(error(end, "`\\u{` is missing its closing `}`".into()));
+                    }
+                    if digits.is_empty()
+                        || digits.len() > 6
+                        || !digits.chars().all(|c| c.is_ascii_hexdigit())
+                    {
+                        return Err(error(end, "`\\u{...}` takes one to six hex digits".into()));
+                    }
+                    let code = u32::from_str_radix(&digits, 16).expect("hex digits");
+                    let Some(c) = char::from_u32(code) else {
+                        return Err(error(
+                            end,
+                            format!("`\\u{{{digits}}}` is not a Unicode scalar value"),
+                        ));
+                    };
+                    result.push(c);
+                }
+                Some((next_idx, c)) => {
+                    let end = next_idx + c.len_utf8();
+                    return Err(error(end, format!("unknown escape sequence `\\{c}`")));
+                }
+                None => {
+                    return Err(error(
+                        idx + 1,
+                        "`\\` at the end of a string escapes nothing".into(),
+                    ));
                 }
-            } else {
-                result.push(ch);
             }
         }
 
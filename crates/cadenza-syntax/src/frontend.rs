@@ -0,0 +1,157 @@
+//! Front-ends: the syntaxes source files can be written in.
+//!
+//! Every syntax parses to the same Cadenza AST, so the evaluator doesn't care
+//! which one a file was written in. A [`Frontend`] parses one syntax and
+//! names the file extensions it's used for, and [`Frontends`] picks the
+//! front-end for a file by its extension, so tools such as the CLI and the
+//! language server handle `.cdz`, `.md`, `.sql` and `.gcode` files alike.
+//! Third parties add their own syntaxes by registering a front-end.
+
+use crate::parse::{Parse, ParseError};
+use std::{fmt, path::Path, sync::Arc};
+
+/// A syntax that parses to the Cadenza AST.
+pub trait Frontend: fmt::Debug + Send + Sync {
+    /// Returns the name of the syntax, such as `cadenza` or `sql`, which
+    /// diagnostics are attributed to.
+    fn name(&self) -> &str;
+
+    /// Returns the extensions, without the dot, of the files written in the
+    /// syntax.
+    fn extensions(&self) -> &[&str];
+
+    /// Parses `source`.
+    fn parse(&self, source: &str) -> Parse;
+
+    /// Maps a parse error to the one reported to the user, such as to
+    /// reword it in the syntax's own terms. Errors are kept as they are by
+    /// default.
+    fn map_error(&self, error: ParseError) -> ParseError {
+        error
+    }
+}
+
+/// The Cadenza syntax.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cadenza;
+
+impl Frontend for Cadenza {
+    fn name(&self) -> &str {
+        "cadenza"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cdz"]
+    }
+
+    fn parse(&self, source: &str) -> Parse {
+        crate::parse::parse(source)
+    }
+}
+
+/// A registry of front-ends, looked up by name or file extension.
+#[derive(Debug, Clone)]
+pub struct Frontends {
+    frontends: Vec<Arc<dyn Frontend>>,
+}
+
+impl Default for Frontends {
+    fn default() -> Self {
+        Self {
+            frontends: vec![Arc::new(Cadenza)],
+        }
+    }
+}
+
+impl Frontends {
+    /// Returns a registry holding the [`Cadenza`] front-end.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `frontend`. It takes over the name and extensions of any
+    /// front-end registered before it.
+    pub fn register(&mut self, frontend: impl Frontend + 'static) {
+        self.frontends.push(Arc::new(frontend));
+    }
+
+    /// Returns the front-ends, in the order they were registered.
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Frontend>> {
+        self.frontends.iter()
+    }
+
+    /// Returns the front-end named `name`.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Frontend>> {
+        self.frontends
+            .iter()
+            .rev()
+            .find(|frontend| frontend.name() == name)
+    }
+
+    /// Returns the front-end for files with the extension `extension`.
+    pub fn for_extension(&self, extension: &str) -> Option<&Arc<dyn Frontend>> {
+        self.frontends
+            .iter()
+            .rev()
+            .find(|frontend| frontend.extensions().contains(&extension))
+    }
+
+    /// Returns the front-end for the file at `path` by its extension. Files
+    /// with no extension, or one no front-end claims, are Cadenza.
+    pub fn for_path(&self, path: &Path) -> Arc<dyn Frontend> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| self.for_extension(extension))
+            .cloned()
+            .unwrap_or_else(|| Arc::new(Cadenza))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses each line as a call of `echo`.
+    #[derive(Debug)]
+    struct Echo;
+
+    impl Frontend for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["echo", "cdz"]
+        }
+
+        fn parse(&self, source: &str) -> Parse {
+            let lines: Vec<_> = source
+                .lines()
+                .map(|line| format!("echo {line:?}"))
+                .collect();
+            crate::parse::parse(&lines.join("\n"))
+        }
+    }
+
+    #[test]
+    fn picks_front_ends_by_extension() {
+        let mut frontends = Frontends::new();
+        assert_eq!(frontends.for_path(Path::new("main.cdz")).name(), "cadenza");
+        assert_eq!(
+            frontends.for_path(Path::new("notes.echo")).name(),
+            "cadenza"
+        );
+        assert_eq!(frontends.for_path(Path::new("script")).name(), "cadenza");
+
+        // A later registration takes over the extensions it claims
+        frontends.register(Echo);
+        assert_eq!(frontends.for_path(Path::new("notes.echo")).name(), "echo");
+        assert_eq!(frontends.for_path(Path::new("main.cdz")).name(), "echo");
+        assert_eq!(frontends.get("cadenza").unwrap().name(), "cadenza");
+        assert!(frontends.get("sql").is_none());
+
+        let parsed = frontends.get("echo").unwrap().parse("hello");
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(format!("{:?}", parsed.ast()), r#"[[echo, "hello"]]"#);
+    }
+}
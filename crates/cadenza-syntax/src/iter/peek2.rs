@@ -49,9 +49,8 @@ impl<I: Iterator> Peek2<I> {
         })
     }
 
-    #[cfg(test)]
     pub fn peek2(&mut self) -> (Option<&I::Item>, Option<&I::Item>) {
-        while self.len < 2 {
+        while self.len < 2 && !self.ended {
             self.push();
         }
         (self.buf[0].as_ref(), self.buf[1].as_ref())
@@ -8,6 +8,8 @@ enum Mode {
     #[default]
     Normal,
     StringContent,
+    /// After string content, at either the closing quote or the `{` that
+    /// starts an interpolation
     StringEnd,
     Comment,
     DocComment,
@@ -32,6 +34,10 @@ fn is_ident_continue(c: char) -> bool {
 pub struct Lexer<'a> {
     chars: Peek2<Chars<'a>>,
     mode: Mode,
+    /// The braces opened inside each interpolation that is still open,
+    /// innermost last. The `}` that closes an interpolation resumes its
+    /// string.
+    interpolations: Vec<usize>,
 }
 
 impl<'a> Lexer<'a> {
@@ -39,6 +45,7 @@ impl<'a> Lexer<'a> {
         Self {
             chars: Peek2::new(Chars::new(src)),
             mode: Mode::default(),
+            interpolations: Vec::new(),
         }
     }
 
@@ -56,8 +63,8 @@ impl<'a> Lexer<'a> {
 
     fn next_token(&mut self) -> Option<Token> {
         if matches!(self.mode, Mode::StringContent) {
-            // check if the string is empty
-            if let Some(a) = self.chars.peek().filter(|v| *v == '"') {
+            // check if the string (or the segment before an interpolation) is empty
+            if let Some(a) = self.chars.peek().filter(|v| *v == '"' || *v == '{') {
                 self.mode = Mode::StringEnd;
                 let mut span = a.span;
                 span.end = span.start;
@@ -71,18 +78,33 @@ impl<'a> Lexer<'a> {
             Mode::Normal => {}
             Mode::StringContent => {
                 self.mode = Mode::StringEnd;
-                let mut escape = false;
-                let mut has_escape = false;
+                // the first character may itself start an escape
+                let mut escape = a.value == '\\';
+                let mut has_escape = escape;
+                // after `\u`, and inside the braces of its code point
+                let mut unicode = false;
+                let mut code_point = false;
                 let end = self.read_while(a, |v| {
+                    // the braces of `\u{...}` don't start an interpolation, but
+                    // an unclosed one stops at the quote
+                    if code_point {
+                        code_point = v.value != '}';
+                        return v.value != '"';
+                    }
+                    if core::mem::take(&mut unicode) && v.value == '{' {
+                        code_point = true;
+                        return true;
+                    }
                     // if we're escaping then always return
                     if core::mem::take(&mut escape) {
+                        unicode = v.value == 'u';
                         return true;
                     }
                     if v.value == '\\' {
                         escape = true;
                         has_escape = true;
                     }
-                    v.value != '"'
+                    v.value != '"' && v.value != '{'
                 });
 
                 let kind = if has_escape {
@@ -95,6 +117,10 @@ impl<'a> Lexer<'a> {
             }
             Mode::StringEnd => {
                 self.mode = Mode::Normal;
+                if a.value == '{' {
+                    self.interpolations.push(0);
+                    return Some(Kind::InterpolationStart.spanned(a));
+                }
                 debug_assert!(a.value == '"');
                 return Some(Kind::StringEnd.spanned(a));
             }
@@ -206,6 +232,9 @@ impl<'a> Lexer<'a> {
             },
             '$' => {
                 if let Some(b) = self.chars.next_if_eq('{') {
+                    if let Some(depth) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
                     Kind::LDollarBrace.spanned((a, b))
                 } else {
                     Kind::Dollar.spanned(a)
@@ -215,8 +244,24 @@ impl<'a> Lexer<'a> {
             '\\' => Kind::Backslash.spanned(a),
             '(' => Kind::LParen.spanned(a),
             ')' => Kind::RParen.spanned(a),
-            '{' => Kind::LBrace.spanned(a),
-            '}' => Kind::RBrace.spanned(a),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                Kind::LBrace.spanned(a)
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.mode = Mode::StringContent;
+                    Kind::InterpolationEnd.spanned(a)
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    Kind::RBrace.spanned(a)
+                }
+                None => Kind::RBrace.spanned(a),
+            },
             '[' => Kind::LBracket.spanned(a),
             ']' => Kind::RBracket.spanned(a),
             ';' => Kind::Semicolon.spanned(a),
@@ -281,20 +326,52 @@ impl<'a> Lexer<'a> {
             },
             c if c.is_ascii_digit() => {
                 let mut token = Kind::Integer;
-                let mut seen_dot = false;
+                let is_digit = |v: &Char| v.value.is_ascii_digit() || v.value == '_';
 
-                // TODO support E-notation
-                let end = self.read_while(a, |v| {
-                    if v.value == '.' {
-                        if seen_dot {
-                            return false;
-                        }
-                        seen_dot = true;
-                        token = Kind::Float;
-                        return true;
+                // `0x`, `0o` and `0b` start hex, octal and binary integers when a
+                // digit of the radix follows. Octal and binary go on to read any
+                // decimal digit, so one out of range is reported by the parser
+                // rather than starting another literal
+                if c == '0'
+                    && let (Some(prefix), Some(digit)) = self.chars.peek2()
+                    && let Some(radix) = match prefix.value {
+                        'x' => Some(16),
+                        'o' => Some(8),
+                        'b' => Some(2),
+                        _ => None,
                     }
-                    v.value.is_ascii_digit() || v.value == '_'
-                });
+                    && digit.value.is_digit(radix)
+                {
+                    let prefix = self.chars.next().expect("peeked the prefix");
+                    let end = self.read_while(prefix, |v| {
+                        v.value == '_' || v.value.is_digit(radix.max(10))
+                    });
+                    return Some(token.spanned((a, end)));
+                }
+
+                // TODO support E-notation
+                let mut end = self.read_while(a, is_digit);
+
+                // A `.` followed by another `.` starts a range, as in `1..10`,
+                // rather than a fraction
+                let fraction = match self.chars.peek2() {
+                    (Some(dot), after) => dot.value == '.' && after.is_none_or(|v| v.value != '.'),
+                    _ => false,
+                };
+                if fraction && let Some(dot) = self.chars.next() {
+                    token = Kind::Float;
+                    end = self.read_while(dot, is_digit);
+                }
+
+                // A `d` suffix makes an exact decimal, as in `1.5d`. Anything
+                // longer, like `1day`, is left to be read as a unit
+                if let (Some(suffix), after) = self.chars.peek2()
+                    && suffix.value == 'd'
+                    && after.is_none_or(|v| !is_ident_continue(v.value))
+                {
+                    token = Kind::Decimal;
+                    end = self.chars.next().expect("peeked the suffix");
+                }
 
                 token.spanned((a, end))
             }
@@ -1,9 +1,11 @@
 pub mod ast;
 pub mod contents;
+pub mod frontend;
 mod generated;
 pub mod hash;
 mod iter;
 pub mod lexer;
+pub mod operators;
 pub mod parse;
 pub mod source_file;
 pub mod span;
@@ -0,0 +1,289 @@
+//! The operator table the parser reads binding powers from.
+//!
+//! By default the parser knows the built-in operators of [`Kind`]. A host
+//! with its own notation, such as a CAD app writing `10 ± 0.1` or `∠ 45`,
+//! can add operators to an [`Operators`] table, or remove built-in ones, and
+//! parse with it using [`crate::parse::parse_with`]. An added operator is an
+//! identifier, so it parses to an application of the function bound to its
+//! symbol: `a ± b` is `[±, a, b]`.
+//!
+//! Symbols that aren't ASCII lex as identifiers, so they must be separated
+//! from their operands by whitespace: `a±b` is a single identifier.
+
+use crate::{lexer::Lexer, token::Kind};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// How tightly an operator binds its operands.
+///
+/// Precedences are taken from the built-in infix operators, so an added
+/// operator binds like the one it's given the precedence of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Precedence(u8);
+
+impl Precedence {
+    /// Returns the precedence of the built-in infix operator `symbol`, such
+    /// as `+` or `*`.
+    pub fn of(symbol: &str) -> Option<Self> {
+        let (l_bp, r_bp) = builtin(symbol)?.infix_binding_power()?;
+        Some(Self(l_bp.min(r_bp)))
+    }
+}
+
+/// Which side an infix operator groups a chain of it from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ** b ** c` is `a ** (b ** c)`.
+    Right,
+}
+
+/// Where an operator goes relative to its operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fixity {
+    /// Before its operand, like `!x`.
+    Prefix,
+    /// Between its operands, like `a + b`.
+    Infix(Associativity),
+    /// After its operand, like `x?`.
+    Postfix,
+}
+
+/// An operator to add to an [`Operators`] table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Operator {
+    pub symbol: String,
+    pub fixity: Fixity,
+    pub precedence: Precedence,
+}
+
+impl Operator {
+    /// Returns the prefix operator `symbol`.
+    pub fn prefix(symbol: impl Into<String>, precedence: Precedence) -> Self {
+        Self {
+            symbol: symbol.into(),
+            fixity: Fixity::Prefix,
+            precedence,
+        }
+    }
+
+    /// Returns the infix operator `symbol`.
+    pub fn infix(
+        symbol: impl Into<String>,
+        precedence: Precedence,
+        associativity: Associativity,
+    ) -> Self {
+        Self {
+            symbol: symbol.into(),
+            fixity: Fixity::Infix(associativity),
+            precedence,
+        }
+    }
+
+    /// Returns the postfix operator `symbol`.
+    pub fn postfix(symbol: impl Into<String>, precedence: Precedence) -> Self {
+        Self {
+            symbol: symbol.into(),
+            fixity: Fixity::Postfix,
+            precedence,
+        }
+    }
+}
+
+/// An error adding an operator to an [`Operators`] table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperatorError {
+    /// The symbol doesn't lex as a single identifier, so it can't be told
+    /// apart from the tokens around it. Built-in operators can only be
+    /// removed.
+    InvalidSymbol { symbol: String },
+}
+
+impl fmt::Display for OperatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperatorError::InvalidSymbol { symbol } => {
+                write!(f, "`{symbol}` can't be used as an operator symbol")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OperatorError {}
+
+/// The operators the parser recognizes: the built-in operators, less the
+/// removed ones, and the added ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Operators {
+    prefix: HashMap<String, u8>,
+    infix: HashMap<String, (u8, u8)>,
+    postfix: HashMap<String, u8>,
+    removed: HashSet<Kind>,
+}
+
+impl Operators {
+    /// Returns the table of the built-in operators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `operator`, replacing an operator with the same symbol and
+    /// fixity.
+    pub fn add(&mut self, operator: Operator) -> Result<(), OperatorError> {
+        let Operator {
+            symbol,
+            fixity,
+            precedence: Precedence(bp),
+        } = operator;
+        if single_token(&symbol) != Some(Kind::Identifier) {
+            return Err(OperatorError::InvalidSymbol { symbol });
+        }
+        // The binding powers follow the built-in operators', so `!` and `?`
+        // bind like the prefix and postfix operators of their precedence
+        match fixity {
+            Fixity::Prefix => {
+                self.prefix.insert(symbol, bp);
+            }
+            Fixity::Infix(Associativity::Left) => {
+                self.infix.insert(symbol, (bp, bp + 1));
+            }
+            Fixity::Infix(Associativity::Right) => {
+                self.infix.insert(symbol, (bp + 1, bp));
+            }
+            Fixity::Postfix => {
+                self.postfix.insert(symbol, bp + 1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the operator `symbol`, in every fixity, and returns whether
+    /// there was one.
+    ///
+    /// A removed built-in operator is a parse error where it's used.
+    pub fn remove(&mut self, symbol: &str) -> bool {
+        let added = [
+            self.prefix.remove(symbol).is_some(),
+            self.infix.remove(symbol).is_some(),
+            self.postfix.remove(symbol).is_some(),
+        ];
+        if added.contains(&true) {
+            return true;
+        }
+        match builtin(symbol) {
+            Some(kind) => self.removed.insert(kind),
+            None => false,
+        }
+    }
+
+    /// Returns whether the built-in operator `kind` was removed.
+    pub(crate) fn is_removed(&self, kind: Kind) -> bool {
+        self.removed.contains(&kind)
+    }
+
+    /// Returns the binding power of the token `kind` with the source `text`
+    /// as a prefix operator.
+    pub(crate) fn prefix_binding_power(&self, kind: Kind, text: &str) -> Option<u8> {
+        match kind {
+            Kind::Identifier => self.prefix.get(text).copied(),
+            _ if self.is_removed(kind) => None,
+            _ => kind.prefix_binding_power(),
+        }
+    }
+
+    /// Returns the binding powers of the token `kind` with the source `text`
+    /// as an infix operator.
+    pub(crate) fn infix_binding_power(&self, kind: Kind, text: &str) -> Option<(u8, u8)> {
+        match kind {
+            Kind::Identifier => self.infix.get(text).copied(),
+            _ if self.is_removed(kind) => None,
+            _ => kind.infix_binding_power(),
+        }
+    }
+
+    /// Returns the binding power of the token `kind` with the source `text`
+    /// as a postfix operator.
+    pub(crate) fn postfix_binding_power(&self, kind: Kind, text: &str) -> Option<u8> {
+        match kind {
+            Kind::Identifier => self.postfix.get(text).copied(),
+            _ if self.is_removed(kind) => None,
+            _ => kind.postfix_binding_power(),
+        }
+    }
+}
+
+/// Returns the kind of the only token `symbol` lexes to.
+fn single_token(symbol: &str) -> Option<Kind> {
+    let mut tokens = Lexer::new(symbol);
+    let token = tokens.next()?;
+    let whole = token.span.start == 0 && token.span.end == symbol.len();
+    (whole && tokens.next().is_none()).then_some(token.kind)
+}
+
+/// Returns the kind of the built-in operator `symbol`.
+fn builtin(symbol: &str) -> Option<Kind> {
+    single_token(symbol).filter(|kind| kind.is_prefix() || kind.is_infix() || kind.is_postfix())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_with;
+
+    fn ast(src: &str, operators: &Operators) -> String {
+        let parsed = parse_with(src, operators);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        format!("{:?}", parsed.ast())
+    }
+
+    #[test]
+    fn added_operators_bind_like_their_precedence() {
+        let mut operators = Operators::new();
+        let additive = Precedence::of("+").unwrap();
+        let power = Precedence::of("**").unwrap();
+        operators
+            .add(Operator::infix("±", additive, Associativity::Left))
+            .unwrap();
+        operators
+            .add(Operator::infix("↑", power, Associativity::Right))
+            .unwrap();
+        operators.add(Operator::prefix("∠", additive)).unwrap();
+        operators.add(Operator::postfix("°", additive)).unwrap();
+
+        assert_eq!(ast("a ± b * c", &operators), "[[±, a, [*, b, c]]]");
+        assert_eq!(ast("a ± b ± c", &operators), "[[±, [±, a, b], c]]");
+        assert_eq!(ast("a ↑ b ↑ c", &operators), "[[↑, a, [↑, b, c]]]");
+        assert_eq!(ast("∠ 45 °", &operators), "[[∠, [°, 45]]]");
+        // Without the table they're identifiers applied to each other
+        assert_eq!(ast("a ± b", &Operators::new()), "[[[a, ±], b]]");
+    }
+
+    #[test]
+    fn removed_operators_are_parse_errors() {
+        let mut operators = Operators::new();
+        assert!(operators.remove("**"));
+        assert!(!operators.remove("**="));
+        assert!(!operators.remove("nope"));
+        assert_eq!(ast("a * b", &operators), "[[*, a, b]]");
+        assert_eq!(parse_with("a ** b", &operators).errors.len(), 1);
+    }
+
+    #[test]
+    fn only_identifiers_are_operator_symbols() {
+        let mut operators = Operators::new();
+        let additive = Precedence::of("+").unwrap();
+        for symbol in ["+", "a b", "", "1"] {
+            assert_eq!(
+                operators.add(Operator::prefix(symbol, additive)),
+                Err(OperatorError::InvalidSymbol {
+                    symbol: symbol.to_string()
+                })
+            );
+        }
+        assert_eq!(Precedence::of("±"), None);
+        assert!(Precedence::of("*") > Precedence::of("+"));
+    }
+}
@@ -2,10 +2,11 @@ use crate::{
     SyntaxNode,
     iter::Peek2,
     lexer::Lexer,
+    operators::Operators,
     span::Span,
     token::{Kind, Token},
 };
-use cadenza_tree::{GreenNode, GreenNodeBuilder};
+use cadenza_tree::{Checkpoint, GreenNode, GreenNodeBuilder};
 
 #[derive(Debug)]
 pub struct ParseError {
@@ -29,11 +30,18 @@ impl Parse {
 }
 
 pub fn parse(src: &str) -> Parse {
-    Parser::new(src).parse()
+    parse_with(src, &Operators::new())
+}
+
+/// Parses `src` with the operators of `operators` rather than the built-in
+/// ones.
+pub fn parse_with(src: &str, operators: &Operators) -> Parse {
+    Parser::new(src, operators).parse()
 }
 
 struct Parser<'src> {
     src: &'src str,
+    operators: &'src Operators,
     tokens: Peek2<Lexer<'src>>,
     builder: GreenNodeBuilder,
     errors: Vec<ParseError>,
@@ -41,9 +49,10 @@ struct Parser<'src> {
 }
 
 impl<'src> Parser<'src> {
-    fn new(src: &'src str) -> Self {
+    fn new(src: &'src str, operators: &'src Operators) -> Self {
         Self {
             src,
+            operators,
             tokens: Peek2::new(Lexer::new(src)),
             builder: GreenNodeBuilder::new(),
             errors: Vec::new(),
@@ -91,7 +100,7 @@ impl<'src> Parser<'src> {
         let content_checkpoint = self.builder.checkpoint();
 
         // Check for prefix operators
-        if let Some(prefix_bp) = self.current().prefix_binding_power() {
+        if let Some(prefix_bp) = self.prefix_binding_power() {
             // Create a unary Apply node: operator(operand)
             self.builder
                 .start_node_at(apply_checkpoint, Kind::Apply.into());
@@ -109,6 +118,26 @@ impl<'src> Parser<'src> {
             self.builder.finish_node();
 
             self.builder.finish_node();
+        } else if self.current_is_keyword("do") {
+            // `do` followed by an indented block is a block expression,
+            // usable anywhere an expression is: `do(__block__ ...)`
+            let do_marker = self.whitespace.marker();
+            self.bump();
+            self.skip_trivia();
+            if do_marker.line != self.whitespace.line
+                && self.whitespace.len > do_marker.len
+                && do_marker.should_continue(self)
+            {
+                self.builder
+                    .start_node_at(apply_checkpoint, Kind::Apply.into());
+                self.builder
+                    .start_node_at(content_checkpoint, Kind::ApplyReceiver.into());
+                self.builder.finish_node();
+                self.builder.start_node(Kind::ApplyArgument.into());
+                self.parse_block(do_marker);
+                self.builder.finish_node();
+                self.builder.finish_node();
+            }
         } else {
             self.parse_primary();
         }
@@ -180,10 +209,8 @@ impl<'src> Parser<'src> {
                 return;
             }
 
-            let op = self.current();
-
             // Check if this is a postfix operator first
-            if let Some(l_bp) = op.postfix_binding_power() {
+            if let Some(l_bp) = self.postfix_binding_power() {
                 // Stop if binding power is too low
                 if l_bp < min_bp {
                     marker.finish(self);
@@ -206,7 +233,7 @@ impl<'src> Parser<'src> {
 
                 self.builder.finish_node();
                 // Continue the outer loop to check for more operators
-            } else if let Some((l_bp, r_bp)) = op.infix_binding_power() {
+            } else if let Some((l_bp, r_bp)) = self.infix_binding_power() {
                 // Check if this is an explicit infix operator
                 // Stop if binding power is too low
                 if l_bp < min_bp {
@@ -242,31 +269,7 @@ impl<'src> Parser<'src> {
                 self.builder.start_node(Kind::ApplyArgument.into());
 
                 if entering_block {
-                    // Parse as a block: collect all expressions at this indentation level
-                    self.builder.start_node(Kind::Apply.into());
-
-                    // Create synthetic block receiver
-                    self.builder.start_node(Kind::ApplyReceiver.into());
-                    self.builder.start_node(Kind::SyntheticBlock.into());
-                    self.builder.finish_node(); // SyntheticBlock
-                    self.builder.finish_node(); // ApplyReceiver
-
-                    // Parse expressions at this indentation level
-                    // The marker's should_continue() will ensure we stop if we dedent below the parent,
-                    // and the indentation check ensures we stay at the block level
-                    while child_marker.should_continue(self)
-                        && self.whitespace.len == block_indent_level
-                    {
-                        self.builder.start_node(Kind::ApplyArgument.into());
-                        let expr_marker = self.whitespace.marker();
-                        // Start with binding power 0 for each expression in the block
-                        self.parse_expression_bp(0, expr_marker);
-                        self.builder.finish_node(); // ApplyArgument
-
-                        self.skip_trivia();
-                    }
-
-                    self.builder.finish_node(); // Apply (__block__)
+                    self.parse_block(child_marker);
                 } else {
                     // Parse single expression (no block)
                     self.parse_expression_bp(r_bp, child_marker);
@@ -306,12 +309,40 @@ impl<'src> Parser<'src> {
         }
     }
 
+    /// Parses the expressions at the current indentation level, which is
+    /// deeper than `parent`'s, as the arguments of a `__block__` application.
+    fn parse_block(&mut self, parent: WhitespaceMarker) {
+        let block_indent_level = self.whitespace.len;
+        self.builder.start_node(Kind::Apply.into());
+
+        // Create synthetic block receiver
+        self.builder.start_node(Kind::ApplyReceiver.into());
+        self.builder.start_node(Kind::SyntheticBlock.into());
+        self.builder.finish_node(); // SyntheticBlock
+        self.builder.finish_node(); // ApplyReceiver
+
+        // Parse expressions at this indentation level
+        // The marker's should_continue() will ensure we stop if we dedent below the parent,
+        // and the indentation check ensures we stay at the block level
+        while parent.should_continue(self) && self.whitespace.len == block_indent_level {
+            self.builder.start_node(Kind::ApplyArgument.into());
+            let expr_marker = self.whitespace.marker();
+            // Start with binding power 0 for each expression in the block
+            self.parse_expression_bp(0, expr_marker);
+            self.builder.finish_node(); // ApplyArgument
+
+            self.skip_trivia();
+        }
+
+        self.builder.finish_node(); // Apply (__block__)
+    }
+
     fn parse_primary(&mut self) {
         match self.current() {
             Kind::Identifier => {
                 self.bump();
             }
-            Kind::Integer | Kind::Float => {
+            Kind::Integer | Kind::Float | Kind::Decimal => {
                 self.parse_literal();
             }
             Kind::StringStart => {
@@ -341,6 +372,12 @@ impl<'src> Parser<'src> {
                 self.error("unexpected end of file");
                 self.builder.finish_node();
             }
+            kind if self.operators.is_removed(kind) => {
+                self.builder.start_node(Kind::Error.into());
+                self.error(&format!("{} is not an operator", kind.display_name()));
+                self.bump();
+                self.builder.finish_node();
+            }
             _ => {
                 // Other tokens (including operators) are allowed as primaries
                 // This allows operators to be used as values in this keyword-less language
@@ -662,7 +699,7 @@ impl<'src> Parser<'src> {
             self.builder.start_node(number_kind.into());
             self.builder
                 .token(number_kind.into(), self.text(number_span));
-            self.builder.finish_node(); // Close Integer/Float node
+            self.builder.finish_node(); // Close Integer/Float/Decimal node
             self.builder.finish_node(); // Close Literal
             self.builder.finish_node(); // Close ApplyArgument
 
@@ -680,26 +717,98 @@ impl<'src> Parser<'src> {
         self.builder.start_node(number_kind.into());
         self.builder
             .token(number_kind.into(), self.text(number_span));
-        self.builder.finish_node(); // Close Integer/Float node
+        self.builder.finish_node(); // Close Integer/Float/Decimal node
         self.builder.finish_node(); // Close Literal
     }
 
+    /// Parse a string literal.
+    ///
+    /// A string with interpolations, like `"a {b} c"`, is represented as
+    /// Apply(SyntheticInterpolation, ["a ", b, " c"]), leaving out empty segments.
     fn parse_string(&mut self) {
-        self.builder.start_node(Kind::Literal.into());
+        let checkpoint = self.builder.checkpoint();
         self.bump(); // StringStart
 
-        // Consume StringContent if present (may not be present for unterminated strings at EOF)
-        if [Kind::StringContent, Kind::StringContentWithEscape].contains(&self.current()) {
-            self.bump();
+        let segment = self.builder.checkpoint();
+        let has_segment = self.parse_string_segment();
+
+        if self.current() != Kind::InterpolationStart {
+            self.parse_string_end();
+            self.builder.start_node_at(checkpoint, Kind::Literal.into());
+            self.builder.finish_node();
+            return;
+        }
+
+        self.builder.start_node_at(checkpoint, Kind::Apply.into());
+        if has_segment {
+            self.finish_string_segment(segment);
         }
 
+        // Create a synthetic receiver node - the AST layer will provide the identifier
+        self.builder.start_node(Kind::ApplyReceiver.into());
+        self.builder.start_node(Kind::SyntheticInterpolation.into());
+        self.builder.finish_node();
+        self.builder.finish_node();
+
+        while self.current() == Kind::InterpolationStart {
+            let interpolation_marker = InterpolationMarker::new(self);
+            interpolation_marker.start(self);
+
+            if self.current() == Kind::InterpolationEnd {
+                self.builder.start_node(Kind::Error.into());
+                self.error("expected expression in interpolation");
+                self.builder.finish_node();
+            } else {
+                self.builder.start_node(Kind::ApplyArgument.into());
+                self.parse_expression_bp(0, interpolation_marker.saved_whitespace);
+                self.builder.finish_node();
+                self.skip_trivia();
+            }
+
+            interpolation_marker.finish(self);
+
+            let segment = self.builder.checkpoint();
+            if self.parse_string_segment() {
+                self.finish_string_segment(segment);
+            }
+        }
+
+        self.parse_string_end();
+        self.builder.finish_node();
+    }
+
+    /// Consumes the content of a string segment, if present, and returns
+    /// whether it is non-empty.
+    fn parse_string_segment(&mut self) -> bool {
+        // The content may not be present for unterminated strings at EOF
+        let Some(token) = self
+            .tokens
+            .peek()
+            .filter(|t| [Kind::StringContent, Kind::StringContentWithEscape].contains(&t.kind))
+        else {
+            return false;
+        };
+        let non_empty = !token.span.is_empty();
+        self.bump();
+        non_empty
+    }
+
+    /// Wraps the segment consumed since `checkpoint` in an argument of the
+    /// interpolation.
+    fn finish_string_segment(&mut self, checkpoint: Checkpoint) {
+        self.builder
+            .start_node_at(checkpoint, Kind::ApplyArgument.into());
+        self.builder.start_node_at(checkpoint, Kind::Literal.into());
+        self.builder.finish_node();
+        self.builder.finish_node();
+    }
+
+    fn parse_string_end(&mut self) {
         if self.current() == Kind::StringEnd {
             self.bump();
         } else {
             self.error("expected closing quote");
         }
-
-        self.builder.finish_node();
     }
 
     fn current(&mut self) -> Kind {
@@ -709,6 +818,48 @@ impl<'src> Parser<'src> {
             .unwrap_or(Kind::Eof)
     }
 
+    /// Returns true if the current token is the identifier `keyword`.
+    fn current_is_keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.peek() {
+            Some(token) if token.kind == Kind::Identifier => {
+                &self.src[token.span.start..token.span.end] == keyword
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the current token with its source text.
+    fn current_token(&mut self) -> (Kind, &'src str) {
+        match self.tokens.peek() {
+            Some(token) => (token.kind, &self.src[token.span.start..token.span.end]),
+            None => (Kind::Eof, ""),
+        }
+    }
+
+    /// Returns the binding power of the current token as a prefix operator.
+    fn prefix_binding_power(&mut self) -> Option<u8> {
+        let (kind, text) = self.current_token();
+        self.operators.prefix_binding_power(kind, text)
+    }
+
+    /// Returns the binding powers of the current token as an infix operator.
+    fn infix_binding_power(&mut self) -> Option<(u8, u8)> {
+        let (kind, text) = self.current_token();
+        self.operators.infix_binding_power(kind, text)
+    }
+
+    /// Returns the binding power of the current token as a postfix operator.
+    fn postfix_binding_power(&mut self) -> Option<u8> {
+        let (kind, text) = self.current_token();
+        self.operators.postfix_binding_power(kind, text)
+    }
+
+    /// Returns true if the current token is an infix or postfix operator,
+    /// which may start a continuation line.
+    fn is_infix_or_postfix(&mut self) -> bool {
+        self.infix_binding_power().is_some() || self.postfix_binding_power().is_some()
+    }
+
     fn bump(&mut self) {
         let Some(token) = self.tokens.next() else {
             return;
@@ -805,6 +956,8 @@ type ParenMarker = DelimiterMarker<{ Kind::RParen as u16 }>;
 type BracketMarker = DelimiterMarker<{ Kind::RBracket as u16 }>;
 /// Type alias for brace marker
 type BraceMarker = DelimiterMarker<{ Kind::RBrace as u16 }>;
+/// Type alias for string interpolation marker
+type InterpolationMarker = DelimiterMarker<{ Kind::InterpolationEnd as u16 }>;
 
 impl<const CLOSE: u16> Marker for DelimiterMarker<CLOSE> {
     fn start(&self, parser: &mut Parser) {
@@ -985,7 +1138,7 @@ impl WhitespaceMarker {
 
         // For comma-first style and operators: allow same indentation if current is comma/infix/postfix
         // This enables: [ 1\n, 2\n, 3] and similar patterns
-        if current == Kind::Comma || current.is_infix() || current.is_postfix() {
+        if current == Kind::Comma || parser.is_infix_or_postfix() {
             return parser.whitespace.len >= self.len;
         }
 
@@ -1007,7 +1160,7 @@ impl Marker for WhitespaceMarker {
         // decide whether to continue parsing
         if matches!(
             current,
-            Kind::RBrace | Kind::RBracket | Kind::RParen | Kind::Comma
+            Kind::RBrace | Kind::RBracket | Kind::RParen | Kind::Comma | Kind::InterpolationEnd
         ) {
             return false;
         }
@@ -1018,7 +1171,7 @@ impl Marker for WhitespaceMarker {
 
         // Infix and postfix operators are allowed to start continuation lines
         // at same indentation level
-        if current.is_infix() || current.is_postfix() {
+        if parser.is_infix_or_postfix() {
             return parser.whitespace.len >= self.len;
         }
 
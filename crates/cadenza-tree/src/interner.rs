@@ -257,9 +257,7 @@ impl IntegerData {
             return index;
         }
         let index = self.values.len() as u32;
-        // Parse the integer, removing underscores
-        let clean = s.replace('_', "");
-        let value = clean.parse();
+        let value = parse_integer(s);
         self.values.push(value);
         self.map.insert(s.to_string(), index);
         index
@@ -270,6 +268,19 @@ impl IntegerData {
     }
 }
 
+/// Parses an integer literal: decimal, or hex, octal or binary after a `0x`,
+/// `0o` or `0b` prefix, with `_` separating digits anywhere.
+fn parse_integer(s: &str) -> Result<i128, ParseIntError> {
+    let clean = s.replace('_', "");
+    let (digits, radix) = match clean.get(..2) {
+        Some("0x") => (&clean[2..], 16),
+        Some("0o") => (&clean[2..], 8),
+        Some("0b") => (&clean[2..], 2),
+        _ => (&clean[..], 10),
+    };
+    i128::from_str_radix(digits, radix)
+}
+
 static INTEGER_STORAGE: OnceLock<std::sync::Mutex<IntegerData>> = OnceLock::new();
 
 fn integer_storage() -> &'static std::sync::Mutex<IntegerData> {
@@ -447,6 +458,15 @@ mod tests {
             assert_eq!(*i, Ok(1_000_000));
         }
 
+        #[test]
+        fn handles_radix_prefixes() {
+            let parsed = ["0xFF", "0o755", "0b1010", "0xdead_beef"]
+                .map(|s| *InternedInteger::from(s).as_ref().unwrap());
+            assert_eq!(parsed, [0xFF, 0o755, 0b1010, 0xdead_beef]);
+            let i: InternedInteger = "0b102".into();
+            assert!(i.is_err());
+        }
+
         #[test]
         fn returns_err_for_invalid() {
             let i: InternedInteger = "not_a_number".into();
@@ -217,7 +217,7 @@ function App() {
                     <option value="">Select an example...</option>
                   )}
                   {EXAMPLES.map((ex) => (
-                    <option key={ex.id} value={ex.id}>
+                    <option key={ex.id} value={ex.id} title={ex.description}>
                       {ex.name}
                     </option>
                   ))}
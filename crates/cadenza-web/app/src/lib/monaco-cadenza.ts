@@ -130,6 +130,7 @@ export function setupDiagnostics(
       endColumn: diag.end_character + 1,
       message: diag.message,
       source: 'cadenza',
+      tags: diag.unnecessary ? [monaco.MarkerTag.Unnecessary] : undefined,
     }));
     
     monaco.editor.setModelMarkers(model, 'cadenza', markers);
@@ -227,6 +228,8 @@ function completionKindToMonaco(kind: string): monaco.languages.CompletionItemKi
       return monaco.languages.CompletionItemKind.Variable;
     case 'class':
       return monaco.languages.CompletionItemKind.Class;
+    case 'unit':
+      return monaco.languages.CompletionItemKind.Unit;
     default:
       return monaco.languages.CompletionItemKind.Text;
   }
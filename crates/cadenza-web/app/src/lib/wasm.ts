@@ -1,7 +1,8 @@
 // WASM bindings for cadenza-web
 // This module loads the actual WASM module built by wasm-pack
 
-import type { LexResult, ParseResult, AstResult, EvalResult, CadenzaWasm, LspDiagnostic, LspHoverInfo, LspCompletionItem, Syntax, SyntaxInfo } from '../types/cadenza';
+import type { LexResult, ParseResult, AstResult, EvalResult, CadenzaWasm, LspDiagnostic, LspHoverInfo, LspCompletionItem, LspSettings, Syntax, SyntaxInfo, ExampleInfo } from '../types/cadenza';
+import { EXAMPLES } from '../generated/examples';
 
 // The WASM module will be loaded from the pkg directory
 let wasmModule: typeof import('../../pkg/cadenza_web') | null = null;
@@ -46,6 +47,12 @@ function createWasmBindings(module: typeof import('../../pkg/cadenza_web')): Cad
     get_syntaxes: (): SyntaxInfo[] => {
       return module.get_syntaxes() as SyntaxInfo[];
     },
+    get_examples: (): ExampleInfo[] => {
+      return module.get_examples() as ExampleInfo[];
+    },
+    lsp_configure: (settings: LspSettings): boolean => {
+      return module.lsp_configure(settings) as boolean;
+    },
     lsp_diagnostics: (source: string): LspDiagnostic[] => {
       return module.lsp_diagnostics(source) as LspDiagnostic[];
     },
@@ -197,6 +204,13 @@ export const mockWasm: CadenzaWasm = {
     { id: 'sql', name: 'SQL' },
     { id: 'gcode', name: 'GCode' },
   ],
+  // Mock: the same catalog the build script generates for the native module
+  get_examples: (): ExampleInfo[] =>
+    EXAMPLES.map(({ id, title, description, tags, source }) => ({ id, title, description, tags, source })),
+  lsp_configure: (_settings: LspSettings): boolean => {
+    // Mock: accept any settings
+    return true;
+  },
   lsp_diagnostics: (_source: string): LspDiagnostic[] => {
     // Mock: return empty diagnostics
     return [];
@@ -7,6 +7,14 @@ export interface SyntaxInfo {
   name: string;
 }
 
+export interface ExampleInfo {
+  id: string;
+  title: string;
+  description: string;
+  tags: string[];
+  source: string;
+}
+
 export interface Token {
   kind: string;
   start: number;
@@ -85,6 +93,7 @@ export interface LspDiagnostic {
   end_character: number;
   message: string;
   severity: 'error' | 'warning' | 'info' | 'hint';
+  unnecessary: boolean;
 }
 
 export interface LspHoverInfo {
@@ -98,6 +107,16 @@ export interface LspCompletionItem {
   detail: string | null;
 }
 
+export type LintLevel = 'allow' | 'warn' | 'deny';
+
+// Same settings as the native language server; every field is optional
+export interface LspSettings {
+  warnings?: LintLevel;
+  lints?: Record<string, LintLevel>;
+  evalOnHover?: boolean;
+  fuel?: number;
+}
+
 // WASM module interface (will be loaded dynamically)
 export interface CadenzaWasm {
   lex: (source: string) => LexResult;
@@ -106,6 +125,8 @@ export interface CadenzaWasm {
   eval_source: (source: string, syntax: Syntax) => EvalResult;
   get_token_kinds: () => string[];
   get_syntaxes: () => SyntaxInfo[];
+  get_examples: () => ExampleInfo[];
+  lsp_configure: (settings: LspSettings) => boolean;
   lsp_diagnostics: (source: string) => LspDiagnostic[];
   lsp_hover: (source: string, line: number, character: number) => LspHoverInfo;
   lsp_completions: (source: string, line: number, character: number) => LspCompletionItem[];
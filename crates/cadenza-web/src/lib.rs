@@ -9,13 +9,16 @@
 //! - [`parse`]: Parses source into a concrete syntax tree (CST)
 //! - [`ast`]: Converts to abstract syntax tree (AST)
 //! - [`eval`]: Evaluates the source code
-//! - LSP functions for language server protocol support
+//! - [`get_examples`]: The bundled example programs, for the playground
+//! - LSP functions for language server protocol support, configured with
+//!   [`lsp_configure`]
 
 use cadenza_eval::{Compiler, Env, Value};
-use cadenza_lsp::{core as lsp_core, lsp_types};
+use cadenza_lsp::{Settings, core as lsp_core, lsp_types};
 use cadenza_syntax::{lexer::Lexer, token::Kind};
 use cadenza_tree::SyntaxElement;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
 
 /// Available syntax types for parsing source code.
@@ -61,6 +64,21 @@ pub struct SyntaxInfo {
     pub name: String,
 }
 
+/// A bundled example program for the UI.
+#[derive(Serialize)]
+pub struct ExampleInfo {
+    /// The example ID, like `measures`.
+    pub id: &'static str,
+    /// The title from the example's header.
+    pub title: &'static str,
+    /// The description from the example's header.
+    pub description: &'static str,
+    /// The topics the example covers.
+    pub tags: &'static [&'static str],
+    /// The example's source code.
+    pub source: &'static str,
+}
+
 /// Token information returned from lexing.
 #[derive(Serialize)]
 pub struct Token {
@@ -222,6 +240,26 @@ pub fn get_syntaxes() -> JsValue {
     serde_wasm_bindgen::to_value(&syntaxes).expect("Failed to serialize syntaxes")
 }
 
+/// Get the bundled example programs.
+///
+/// Returns an array of example objects with `id`, `title`, `description`,
+/// `tags`, and `source` fields, in catalog order.
+#[wasm_bindgen]
+pub fn get_examples() -> JsValue {
+    let examples: Vec<ExampleInfo> = cadenza_eval::examples::all()
+        .iter()
+        .map(|example| ExampleInfo {
+            id: example.id,
+            title: example.title,
+            description: example.description,
+            tags: example.tags,
+            source: example.source,
+        })
+        .collect();
+
+    serde_wasm_bindgen::to_value(&examples).expect("Failed to serialize examples")
+}
+
 /// Parse source code with the specified syntax.
 fn parse_with_syntax(source: &str, syntax: Syntax) -> cadenza_syntax::parse::Parse {
     match syntax {
@@ -485,14 +523,8 @@ pub fn eval_source(source: &str, syntax: JsValue) -> JsValue {
 
     // Add evaluation diagnostics
     for diag in compiler.take_diagnostics() {
-        let level = match diag.level {
-            cadenza_eval::DiagnosticLevel::Error => "error",
-            cadenza_eval::DiagnosticLevel::Warning => "warning",
-            cadenza_eval::DiagnosticLevel::Hint => "hint",
-        };
-
         diagnostics.push(EvalDiagnostic {
-            level: level.to_string(),
+            level: diag.level.as_str().to_string(),
             message: diag.to_string(),
             start: diag.span.map(|s| s.start),
             end: diag.span.map(|s| s.end),
@@ -533,6 +565,32 @@ pub struct LspDiagnostic {
     pub message: String,
     /// Severity: "error", "warning", "info", or "hint".
     pub severity: String,
+    /// Whether the range is unused code, which editors render grayed out.
+    pub unnecessary: bool,
+}
+
+thread_local! {
+    /// The settings given to [`lsp_configure`].
+    static LSP_SETTINGS: RefCell<Settings> = RefCell::new(Settings::default());
+}
+
+/// Configure the LSP functions.
+///
+/// Takes the same settings object as the native language server (see
+/// [`cadenza_lsp::settings`]). Returns whether the settings were valid;
+/// invalid settings are ignored.
+#[wasm_bindgen]
+pub fn lsp_configure(settings: JsValue) -> bool {
+    let settings = serde_wasm_bindgen::from_value(settings)
+        .ok()
+        .and_then(|value| Settings::from_json(value).ok());
+    match settings {
+        Some(settings) => {
+            LSP_SETTINGS.set(settings);
+            true
+        }
+        None => false,
+    }
 }
 
 /// Get diagnostics for the given source code.
@@ -540,7 +598,9 @@ pub struct LspDiagnostic {
 /// Returns an array of diagnostic objects with position information and messages.
 #[wasm_bindgen]
 pub fn lsp_diagnostics(source: &str) -> JsValue {
-    let diagnostics = lsp_core::parse_to_diagnostics(source);
+    let diagnostics = LSP_SETTINGS.with_borrow(|settings| {
+        lsp_core::check_module_to_diagnostics("main", source, settings, |_| None)
+    });
 
     let lsp_diagnostics: Vec<LspDiagnostic> = diagnostics
         .into_iter()
@@ -561,6 +621,9 @@ pub fn lsp_diagnostics(source: &str) -> JsValue {
                 .to_string(),
                 None => "error".to_string(),
             },
+            unnecessary: d
+                .tags
+                .is_some_and(|tags| tags.contains(&lsp_types::DiagnosticTag::UNNECESSARY)),
         })
         .collect();
 
@@ -584,35 +647,17 @@ pub fn lsp_hover(source: &str, line: u32, character: u32) -> JsValue {
     let position = lsp_types::Position::new(line, character);
     let offset = lsp_core::position_to_offset(source, position);
 
-    // Find the word boundaries around the offset
-    let start = source[..offset]
-        .rfind(|c: char| !c.is_alphanumeric() && c != '_')
-        .map(|i| i + 1)
-        .unwrap_or(0);
-
-    let end = source[offset..]
-        .find(|c: char| !c.is_alphanumeric() && c != '_')
-        .map(|i| offset + i)
-        .unwrap_or(source.len());
-
-    let hover_info = if start < end {
-        let word = &source[start..end];
-        if !word.is_empty() {
-            LspHoverInfo {
-                content: format!("Symbol: `{}`\n\nType information coming soon!", word),
-                found: true,
-            }
-        } else {
-            LspHoverInfo {
-                content: String::new(),
-                found: false,
-            }
-        }
-    } else {
-        LspHoverInfo {
+    let hover_info = match LSP_SETTINGS
+        .with_borrow(|settings| lsp_core::hover_markdown(source, offset, settings))
+    {
+        Some(content) => LspHoverInfo {
+            content,
+            found: true,
+        },
+        None => LspHoverInfo {
             content: String::new(),
             found: false,
-        }
+        },
     };
 
     serde_wasm_bindgen::to_value(&hover_info).expect("Failed to serialize hover info")
@@ -634,22 +679,21 @@ pub struct LspCompletionItem {
 /// Returns an array of completion items.
 #[wasm_bindgen]
 pub fn lsp_completions(source: &str, line: u32, character: u32) -> JsValue {
-    // For now, return basic keyword completions
-    let _position = lsp_types::Position::new(line, character);
-    let _offset = lsp_core::position_to_offset(source, _position);
-
-    let items = vec![
-        LspCompletionItem {
-            label: "let".to_string(),
-            kind: "keyword".to_string(),
-            detail: Some("Variable binding".to_string()),
-        },
-        LspCompletionItem {
-            label: "fn".to_string(),
-            kind: "keyword".to_string(),
-            detail: Some("Function definition".to_string()),
-        },
-    ];
+    let position = lsp_types::Position::new(line, character);
+    let offset = lsp_core::position_to_offset(source, position);
+
+    let items: Vec<LspCompletionItem> = lsp_core::completions(source, offset)
+        .into_iter()
+        .map(|item| LspCompletionItem {
+            label: item.label,
+            kind: match item.kind {
+                Some(lsp_types::CompletionItemKind::UNIT) => "unit",
+                _ => "keyword",
+            }
+            .to_string(),
+            detail: item.detail,
+        })
+        .collect();
 
     serde_wasm_bindgen::to_value(&items).expect("Failed to serialize completion items")
 }
@@ -143,6 +143,19 @@ impl From<f64> for Number {
     }
 }
 
+/// Parses decimal digits such as `12.375` into a fraction in lowest terms.
+fn rational(digits: &str) -> Option<(i128, i128)> {
+    let digits = digits.replace('_', "");
+    let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+    let numerator: i128 = format!("{whole}{fraction}").parse().ok()?;
+    let denominator = 10i128.checked_pow(fraction.len().try_into().ok()?)?;
+    let (mut a, mut b) = (numerator.abs(), denominator);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    Some((numerator / a, denominator / a))
+}
+
 pub type Scope = Arc<[InternedString]>;
 
 /// The core value types in Cadenza.
@@ -287,13 +300,19 @@ impl Value {
                         Value::Error(format!("Invalid float literal: {float:?}").into())
                     }
                 }
+                Some(LiteralValue::Decimal(decimal)) => match rational(&decimal.digits()) {
+                    Some((numerator, denominator)) => {
+                        Value::Number(Number::Rational(numerator, denominator))
+                    }
+                    None => Value::Error(format!("Invalid decimal literal: {decimal:?}").into()),
+                },
                 Some(LiteralValue::String(string)) => {
                     Value::String(string.syntax().text().to_string())
                 }
                 Some(LiteralValue::StringWithEscape(string)) => match string.unescaped() {
                     Ok(unescaped) => Value::String(unescaped),
-                    Err(span) => {
-                        Value::Error(crate::Error::from("Invalid escape sequence").with_span(span))
+                    Err(error) => {
+                        Value::Error(crate::Error::from(error.message).with_span(error.span))
                     }
                 },
                 None => Value::Error("Missing literal value".into()),
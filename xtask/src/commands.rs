@@ -5,10 +5,12 @@ use xshell::Shell;
 pub mod build;
 pub mod ci;
 pub mod common;
+pub mod corpus;
 pub mod explorer;
 pub mod fmt;
 pub mod hooks;
 pub mod precommit;
+pub mod public_api;
 pub mod semantics;
 pub mod test;
 
@@ -18,12 +20,16 @@ pub enum Command {
     Build(build::Build),
     /// Run CI checks (fmt, clippy, udeps, test). Runs all if no subcommand specified.
     Ci(ci::Ci),
+    /// Add, rename, and prune test-data cases and their snapshots
+    Corpus(corpus::Corpus),
     /// Apply rustfmt to all files
     Fmt(fmt::Fmt),
     /// Manage git hooks
     Hooks(hooks::Hooks),
     /// Run precommit checks (checks rustfmt and runs clippy)
     Precommit(precommit::Precommit),
+    /// Write or check the listing of cadenza-eval's stable API
+    PublicApi(public_api::PublicApi),
     /// Run the Compiler Explorer development server or build for production
     Explorer(explorer::Explorer),
     /// Extract tests from semantics documentation or generate reports
@@ -37,9 +43,11 @@ impl Command {
         match self {
             Command::Build(cmd) => cmd.run(sh),
             Command::Ci(cmd) => cmd.run(sh),
+            Command::Corpus(cmd) => cmd.run(sh),
             Command::Fmt(cmd) => cmd.run(sh),
             Command::Hooks(cmd) => cmd.run(sh),
             Command::Precommit(cmd) => cmd.run(sh),
+            Command::PublicApi(cmd) => cmd.run(sh),
             Command::Explorer(cmd) => cmd.run(sh),
             Command::Semantics(cmd) => cmd.run(sh),
             Command::Test(cmd) => cmd.run(sh),
@@ -2,7 +2,7 @@ use anyhow::Result;
 use clap::{Args, Subcommand};
 use xshell::{Shell, cmd};
 
-use super::common;
+use super::{common, public_api::PublicApi};
 
 #[derive(Args)]
 pub struct Ci {
@@ -20,6 +20,8 @@ pub enum CiCommand {
     Udeps,
     /// Run cargo test
     Test(TestArgs),
+    /// Check the stable API listing and test the `stable` feature
+    PublicApi,
 }
 
 #[derive(Args, Default)]
@@ -39,6 +41,7 @@ impl Ci {
                 CiCommand::Clippy.run(sh)?;
                 CiCommand::Udeps.run(sh)?;
                 CiCommand::Test(TestArgs::default()).run(sh)?;
+                CiCommand::PublicApi.run(sh)?;
                 Ok(())
             }
         }
@@ -67,6 +70,12 @@ impl CiCommand {
                 cmd!(sh, "cargo +nightly udeps --workspace --all-targets").run()?;
                 Ok(())
             }
+            CiCommand::PublicApi => {
+                PublicApi { check: true }.run(sh)?;
+                eprintln!("Testing the stable API...");
+                cmd!(sh, "cargo test -p cadenza-eval --features stable stable").run()?;
+                Ok(())
+            }
             CiCommand::Test(test_args) => {
                 eprintln!("Running cargo test...");
                 let args = &test_args.args;
@@ -0,0 +1,461 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use std::{
+    collections::BTreeSet,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use xshell::{Shell, cmd};
+
+#[derive(Args)]
+pub struct Corpus {
+    #[command(subcommand)]
+    command: CorpusCommand,
+}
+
+#[derive(Subcommand)]
+pub enum CorpusCommand {
+    /// Add a test-data case and write its initial snapshots
+    Add {
+        /// The corpus to add to (syntax, eval, markdown, sql, gcode, cli, csv,
+        /// config)
+        corpus: String,
+        /// The case, relative to `test-data` and without the extension
+        /// (e.g. `arith-pow` or `invalid-parse/unclosed-paren`). In a corpus
+        /// with several extensions, add one to pick it (e.g. `weather.tsv`)
+        case: String,
+        /// Read the source from this file instead of stdin
+        #[arg(long, value_name = "FILE")]
+        from: Option<PathBuf>,
+    },
+    /// Rename a case and its snapshots in every corpus that has it
+    Rename {
+        /// The current case name
+        old: String,
+        /// The new case name
+        new: String,
+        /// Only rename the case in this corpus
+        #[arg(long)]
+        corpus: Option<String>,
+    },
+    /// Delete snapshots whose case no longer exists
+    Prune {
+        /// List the orphaned snapshots without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+impl Corpus {
+    pub fn run(&self, sh: &Shell) -> Result<()> {
+        let root = sh.current_dir();
+        match &self.command {
+            CorpusCommand::Add { corpus, case, from } => {
+                let corpus = TestData::named(corpus)?;
+                let source = match from {
+                    Some(path) => sh.read_file(path)?,
+                    None => {
+                        let mut source = String::new();
+                        std::io::stdin().read_to_string(&mut source)?;
+                        source
+                    }
+                };
+                let path = corpus.add(&root, case, &source)?;
+                println!("Wrote {}", path.display());
+
+                // Building regenerates the test module; accepting writes the
+                // new case's snapshots for review
+                let (case, _) = corpus.split_extension(case);
+                let package = corpus.package;
+                let filter = format!("generated::test_data::{}::", module_path(case, "::"));
+                cmd!(sh, "cargo test -p {package} -- {filter}")
+                    .env("INSTA_UPDATE", "always")
+                    .run()?;
+                println!("Review the new snapshots before committing them");
+                Ok(())
+            }
+            CorpusCommand::Rename { old, new, corpus } => {
+                let corpora = match corpus {
+                    Some(name) => vec![TestData::named(name)?],
+                    None => TestData::ALL
+                        .iter()
+                        .filter(|corpus| corpus.source(&root, old).is_some())
+                        .collect(),
+                };
+                if corpora.is_empty() {
+                    anyhow::bail!("no corpus has a case named `{old}`");
+                }
+                for corpus in corpora {
+                    let snapshots = corpus.rename(&root, old, new)?;
+                    println!(
+                        "{}: renamed `{old}` to `{new}` and {snapshots} snapshots",
+                        corpus.name
+                    );
+                }
+                Ok(())
+            }
+            CorpusCommand::Prune { dry_run } => {
+                let mut pruned = 0;
+                for corpus in TestData::ALL {
+                    for snapshot in corpus.orphans(&root)? {
+                        println!("{}", snapshot.display());
+                        if !dry_run {
+                            sh.remove_path(&snapshot)?;
+                        }
+                        pruned += 1;
+                    }
+                }
+                let verb = if *dry_run { "Found" } else { "Removed" };
+                println!("{verb} {pruned} orphaned snapshots");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A crate's `test-data` directory, whose cases its build script turns into
+/// snapshot tests under `generated::test_data`.
+struct TestData {
+    /// The name used on the command line
+    name: &'static str,
+    /// The package, which is also the crate's directory under `crates/`
+    package: &'static str,
+    /// The test binary's name, which insta prefixes snapshot names with
+    binary: &'static str,
+    /// The extensions of case files; new cases get the first unless they
+    /// name another
+    extensions: &'static [&'static str],
+    /// Subdirectories holding more cases; any other subdirectory is a single
+    /// case made of several files
+    groups: &'static [&'static str],
+}
+
+impl TestData {
+    const ALL: &[TestData] = &[
+        TestData {
+            name: "syntax",
+            package: "cadenza-syntax",
+            binary: "cadenza_syntax",
+            extensions: &["cdz"],
+            groups: &["invalid-parse"],
+        },
+        TestData {
+            name: "eval",
+            package: "cadenza-eval",
+            binary: "cadenza_eval",
+            extensions: &["cdz"],
+            groups: &[],
+        },
+        TestData {
+            name: "markdown",
+            package: "cadenza-markdown",
+            binary: "cadenza_markdown",
+            extensions: &["md"],
+            groups: &[],
+        },
+        TestData {
+            name: "sql",
+            package: "cadenza-sql",
+            binary: "cadenza_sql",
+            extensions: &["sql"],
+            groups: &[],
+        },
+        TestData {
+            name: "gcode",
+            package: "cadenza-gcode",
+            binary: "cadenza_gcode",
+            extensions: &["gcode"],
+            groups: &[],
+        },
+        TestData {
+            name: "cli",
+            package: "cadenza-cli",
+            binary: "cadenza",
+            extensions: &["repl"],
+            groups: &[],
+        },
+        TestData {
+            name: "csv",
+            package: "cadenza-csv",
+            binary: "cadenza_csv",
+            extensions: &["csv", "tsv"],
+            groups: &[],
+        },
+        TestData {
+            name: "config",
+            package: "cadenza-config",
+            binary: "cadenza_config",
+            extensions: &["json", "toml"],
+            groups: &[],
+        },
+    ];
+
+    fn named(name: &str) -> Result<&'static TestData> {
+        Self::ALL
+            .iter()
+            .find(|corpus| corpus.name == name)
+            .with_context(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|corpus| corpus.name).collect();
+                format!(
+                    "unknown corpus `{name}`, expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+
+    fn dir(&self, root: &Path) -> PathBuf {
+        root.join("crates").join(self.package).join("test-data")
+    }
+
+    fn snapshots(&self, root: &Path) -> PathBuf {
+        root.join("crates")
+            .join(self.package)
+            .join("src/generated/snapshots")
+    }
+
+    /// Splits one of the corpus's extensions off `case`, or returns the
+    /// first extension if it doesn't end with one.
+    fn split_extension<'a>(&self, case: &'a str) -> (&'a str, &'static str) {
+        self.extensions
+            .iter()
+            .find_map(|&extension| {
+                let stem = case.strip_suffix(extension)?.strip_suffix('.')?;
+                Some((stem, extension))
+            })
+            .unwrap_or((case, self.extensions[0]))
+    }
+
+    /// Returns the file or directory holding `case`, if it exists.
+    fn source(&self, root: &Path, case: &str) -> Option<PathBuf> {
+        let file = self
+            .extensions
+            .iter()
+            .map(|extension| self.dir(root).join(format!("{case}.{extension}")))
+            .find(|file| file.is_file());
+        let dir = self.dir(root).join(case);
+        if file.is_some() {
+            file
+        } else if dir.is_dir() && !self.groups.contains(&case) {
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
+    /// Returns every case, relative to `test-data` and without the extension.
+    fn cases(&self, root: &Path) -> Result<Vec<String>> {
+        let mut cases = Vec::new();
+        let dir = self.dir(root);
+        for group in std::iter::once("").chain(self.groups.iter().copied()) {
+            let Ok(entries) = std::fs::read_dir(dir.join(group)) else {
+                continue;
+            };
+            for entry in entries {
+                let path = entry?.path();
+                let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+                let is_case = if path.is_dir() {
+                    group.is_empty() && !self.groups.contains(&stem)
+                } else {
+                    path.extension()
+                        .and_then(|ext| ext.to_str())
+                        .is_some_and(|ext| self.extensions.contains(&ext))
+                };
+                if is_case {
+                    cases.push(match group {
+                        "" => stem.to_string(),
+                        group => format!("{group}/{stem}"),
+                    });
+                }
+            }
+        }
+        cases.sort();
+        Ok(cases)
+    }
+
+    /// Writes `source` as a new case, returning its path.
+    fn add(&self, root: &Path, case: &str, source: &str) -> Result<PathBuf> {
+        let (case, extension) = self.split_extension(case);
+        if let Some(existing) = self.source(root, case) {
+            anyhow::bail!("{} already exists", existing.display());
+        }
+        let path = self.dir(root).join(format!("{case}.{extension}"));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, source)?;
+        Ok(path)
+    }
+
+    /// Renames the case `old` to `new` along with its snapshots, returning
+    /// how many snapshots were renamed.
+    fn rename(&self, root: &Path, old: &str, new: &str) -> Result<usize> {
+        let source = self
+            .source(root, old)
+            .with_context(|| format!("{} has no case named `{old}`", self.name))?;
+        if let Some(existing) = self.source(root, new) {
+            anyhow::bail!("{} already exists", existing.display());
+        }
+        let renamed = match source.extension() {
+            Some(extension) if source.is_file() => self
+                .dir(root)
+                .join(format!("{new}.{}", extension.to_string_lossy())),
+            _ => self.dir(root).join(new),
+        };
+        if let Some(parent) = renamed.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&source, &renamed)?;
+
+        let old_prefix = snapshot_prefix(self.binary, old);
+        let new_prefix = snapshot_prefix(self.binary, new);
+        let mut count = 0;
+        for snapshot in self.snapshot_files(root)? {
+            let name = snapshot.file_name().unwrap().to_string_lossy();
+            if let Some(rest) = name.strip_prefix(&old_prefix) {
+                std::fs::rename(
+                    &snapshot,
+                    snapshot.with_file_name(format!("{new_prefix}{rest}")),
+                )?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the snapshots whose case doesn't exist.
+    fn orphans(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        let modules: BTreeSet<String> = self
+            .cases(root)?
+            .iter()
+            .map(|case| module_path(case, "__"))
+            .collect();
+        let prefix = format!("{}__generated__test_data__", self.binary);
+        let mut orphans = Vec::new();
+        for snapshot in self.snapshot_files(root)? {
+            let name = snapshot.file_name().unwrap().to_string_lossy();
+            // The module path is everything up to the snapshot's own name
+            let Some((module, _)) = name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.rsplit_once("__"))
+            else {
+                continue;
+            };
+            if !modules.contains(module) {
+                orphans.push(snapshot);
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// Returns the accepted and pending snapshot files, sorted.
+    fn snapshot_files(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        let Ok(entries) = std::fs::read_dir(self.snapshots(root)) else {
+            return Ok(Vec::new());
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let name = path.file_name().unwrap().to_string_lossy();
+            if name.ends_with(".snap") || name.ends_with(".snap.new") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// Returns the module path the build scripts generate for `case`: each path
+/// component as an identifier, joined by `separator`.
+fn module_path(case: &str, separator: &str) -> String {
+    case.split('/')
+        .map(|part| part.replace('-', "_"))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Returns the start of the names of `case`'s snapshot files: the module path,
+/// then the snapshot name, which the build scripts start with the case's
+/// components joined by `_` (e.g. `invalid_parse_unclosed_paren_ast`).
+fn snapshot_prefix(binary: &str, case: &str) -> String {
+    format!(
+        "{binary}__generated__test_data__{}__{}",
+        module_path(case, "__"),
+        module_path(case, "_")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_prefix() {
+        assert_eq!(
+            snapshot_prefix("cadenza_eval", "arith-add"),
+            "cadenza_eval__generated__test_data__arith_add__arith_add"
+        );
+        assert_eq!(
+            snapshot_prefix("cadenza_syntax", "invalid-parse/leading-comma"),
+            "cadenza_syntax__generated__test_data__invalid_parse__leading_comma__invalid_parse_leading_comma"
+        );
+    }
+
+    #[test]
+    fn test_rename_and_prune() {
+        let root = std::env::temp_dir().join(format!("xtask-corpus-{}", std::process::id()));
+        let corpus = TestData::named("syntax").unwrap();
+        let snapshots = corpus.snapshots(&root);
+        std::fs::create_dir_all(&snapshots).unwrap();
+        let snapshot = |case: &str, suffix: &str| {
+            snapshots.join(format!(
+                "{}{suffix}.snap",
+                snapshot_prefix("cadenza_syntax", case)
+            ))
+        };
+
+        corpus.add(&root, "ap-nested", "f (g x)\n").unwrap();
+        corpus.add(&root, "invalid-parse/comma", ",\n").unwrap();
+        corpus.add(&root, "ap", "f x\n").unwrap();
+        assert!(corpus.add(&root, "ap", "f x\n").is_err());
+        for case in ["ap-nested", "invalid-parse/comma", "ap", "gone"] {
+            std::fs::write(snapshot(case, "_ast"), "").unwrap();
+        }
+        assert_eq!(
+            corpus.cases(&root).unwrap(),
+            ["ap", "ap-nested", "invalid-parse/comma"]
+        );
+
+        // Renaming `ap` leaves `ap-nested`, whose name it prefixes, alone
+        assert_eq!(corpus.rename(&root, "ap", "apply").unwrap(), 1);
+        assert!(corpus.source(&root, "apply").is_some());
+        assert!(snapshot("apply", "_ast").exists());
+        assert!(snapshot("ap-nested", "_ast").exists());
+        assert!(corpus.rename(&root, "apply", "ap-nested").is_err());
+
+        assert_eq!(corpus.orphans(&root).unwrap(), [snapshot("gone", "_ast")]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_multiple_extensions() {
+        let root = std::env::temp_dir().join(format!("xtask-corpus-csv-{}", std::process::id()));
+        let corpus = TestData::named("csv").unwrap();
+
+        let csv = corpus.add(&root, "parts", "a,b\n").unwrap();
+        let tsv = corpus.add(&root, "weather.tsv", "a\tb\n").unwrap();
+        assert_eq!(csv.file_name().unwrap(), "parts.csv");
+        assert_eq!(tsv.file_name().unwrap(), "weather.tsv");
+        assert!(corpus.add(&root, "weather", "a,b\n").is_err());
+        assert_eq!(corpus.cases(&root).unwrap(), ["parts", "weather"]);
+
+        // Renaming keeps the case's extension
+        corpus.rename(&root, "weather", "forecast").unwrap();
+        assert!(corpus.dir(&root).join("forecast.tsv").is_file());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{Result, bail};
+use clap::Args;
+use xshell::Shell;
+
+/// The module holding the stable embedding API.
+const SOURCE: &str = "crates/cadenza-eval/src/stable.rs";
+
+/// The listing of the stable API, checked in next to the crate's manifest.
+const LISTING: &str = "crates/cadenza-eval/public-api.txt";
+
+/// The path items of the stable module are listed under.
+const MODULE: &str = "cadenza_eval::stable";
+
+#[derive(Args)]
+pub struct PublicApi {
+    /// Fail if the checked-in listing is out of date instead of writing it
+    #[arg(long)]
+    pub check: bool,
+}
+
+impl PublicApi {
+    pub fn run(&self, sh: &Shell) -> Result<()> {
+        let listing = listing(&sh.read_file(SOURCE)?);
+        if self.check {
+            let current = sh.read_file(LISTING).unwrap_or_default();
+            if current != listing {
+                bail!(
+                    "{LISTING} is out of date with {SOURCE}; \
+                     run `cargo xtask public-api` and review the changes"
+                );
+            }
+            println!("{LISTING} is up to date");
+        } else {
+            sh.write_file(LISTING, &listing)?;
+            println!("Wrote {LISTING}");
+        }
+        Ok(())
+    }
+}
+
+/// Lists the public items declared and re-exported by `source`, one
+/// signature per line, in source order.
+///
+/// Only the top level of the module is read: re-exports, public types and
+/// functions, and the public functions of their `impl` blocks, with trait
+/// implementations listed by name. Tests are skipped.
+fn listing(source: &str) -> String {
+    let mut lines = Vec::new();
+    let mut items = Items::new(source);
+    while let Some(item) = items.next_item() {
+        if item == "#[cfg(test)]" {
+            if items.next_item().is_some_and(|item| item.ends_with('{')) {
+                items.skip_block();
+            }
+        } else if let Some(paths) = item.strip_prefix("pub use ") {
+            for name in reexports(paths.trim_end_matches(';')) {
+                lines.push(format!("pub use {MODULE}::{name}"));
+            }
+        } else if let Some(header) = item.strip_prefix("impl") {
+            let header = header.trim_end_matches('{').trim();
+            let mut functions = Vec::new();
+            while let Some(inner) = items.next_item() {
+                if inner == "}" {
+                    break;
+                }
+                if inner.starts_with("pub fn ") {
+                    functions.push(signature(&inner));
+                }
+                if inner.ends_with('{') {
+                    items.skip_block();
+                }
+            }
+            match header.split_once(" for ") {
+                Some((the_trait, ty)) => {
+                    lines.push(format!("impl {the_trait} for {MODULE}::{ty}"));
+                }
+                None => lines.extend(
+                    functions
+                        .into_iter()
+                        .map(|function| qualify(&function, &format!("{MODULE}::{header}"))),
+                ),
+            }
+        } else if item.starts_with("pub ") {
+            lines.push(qualify(&signature(&item), MODULE));
+            if item.ends_with('{') {
+                items.skip_block();
+            }
+        } else if item.ends_with('{') {
+            items.skip_block();
+        }
+    }
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+/// Returns the declaration `item` without its body.
+fn signature(item: &str) -> String {
+    item.trim_end_matches('{')
+        .trim_end_matches(';')
+        .trim()
+        .to_string()
+}
+
+/// Qualifies the name declared by `signature`, as in
+/// `pub fn new() -> Self` under `module::Engine`.
+fn qualify(signature: &str, path: &str) -> String {
+    for keyword in [
+        "pub fn ",
+        "pub struct ",
+        "pub enum ",
+        "pub trait ",
+        "pub type ",
+    ] {
+        if let Some(rest) = signature.strip_prefix(keyword) {
+            return format!("{keyword}{path}::{rest}");
+        }
+    }
+    signature.to_string()
+}
+
+/// Returns the names a `use` tree such as `crate::{a::B, c::{D, E}}` brings
+/// into scope.
+fn reexports(tree: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut current = String::new();
+    let mut flush = |current: &mut String| {
+        let name = current.rsplit("::").next().unwrap_or_default();
+        let name = name.rsplit(" as ").next().unwrap_or(name).trim();
+        if !name.is_empty() {
+            names.push(name.to_string());
+        }
+        current.clear();
+    };
+    for c in tree.chars() {
+        match c {
+            '{' => current.clear(),
+            '}' | ',' => flush(&mut current),
+            c if c.is_whitespace() && current.trim().is_empty() => {}
+            _ => current.push(c),
+        }
+    }
+    flush(&mut current);
+    names
+}
+
+/// The top-level items of a Rust source file, read a declaration at a time.
+struct Items<'a> {
+    lines: std::str::Lines<'a>,
+}
+
+impl<'a> Items<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            lines: source.lines(),
+        }
+    }
+
+    /// Returns the next declaration, joined onto one line up to its `{` or
+    /// `;`, skipping blank lines, comments and attributes other than
+    /// `#[cfg(test)]`.
+    fn next_item(&mut self) -> Option<String> {
+        let mut item = String::new();
+        for line in self.lines.by_ref() {
+            let line = line.trim();
+            if item.is_empty()
+                && (line.is_empty()
+                    || line.starts_with("//")
+                    || (line.starts_with("#[") && line != "#[cfg(test)]"))
+            {
+                continue;
+            }
+            if !item.is_empty() && !item.ends_with(['(', '{', ':']) {
+                item.push(' ');
+            }
+            item.push_str(line);
+            let done = if item.starts_with("use ") || item.starts_with("pub use ") {
+                line.ends_with(';')
+            } else {
+                item == "#[cfg(test)]"
+                    || line.ends_with('{')
+                    || line.ends_with(';')
+                    || line.ends_with('}')
+            };
+            if done {
+                return Some(item.replace(", )", ")").replace(",)", ")"));
+            }
+        }
+        (!item.is_empty()).then_some(item)
+    }
+
+    /// Skips the lines up to the `}` closing the block just opened.
+    fn skip_block(&mut self) {
+        let mut depth = 1;
+        for line in self.lines.by_ref() {
+            let line = line.trim();
+            if line.starts_with("//") {
+                continue;
+            }
+            depth += line.matches('{').count();
+            depth -= line.matches('}').count();
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+}